@@ -0,0 +1,22 @@
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_COMMIT");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+
+    let git_commit = std::env::var("GIT_COMMIT").ok().or_else(|| {
+        let output = Command::new("git").args(["rev-parse", "HEAD"]).output().ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    });
+    println!("cargo:rustc-env=TELEPORT_GIT_COMMIT={}", git_commit.as_deref().unwrap_or("unknown"));
+
+    // Honour SOURCE_DATE_EPOCH so reproducible builds keep reproducing the same measurement.
+    let build_timestamp = std::env::var("SOURCE_DATE_EPOCH").unwrap_or_else(|_| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs().to_string())
+            .unwrap_or_default()
+    });
+    println!("cargo:rustc-env=TELEPORT_BUILD_TIMESTAMP={}", build_timestamp);
+}
@@ -0,0 +1,88 @@
+use std::str::FromStr;
+
+use alloy::primitives::Address;
+
+use crate::db::{AccessList, TeleportDB};
+
+/// Whether mints are open to everyone not denied, or only to those allowed, from
+/// `MINT_ACCESS_MODE` (`denylist` or `allowlist`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessMode {
+    Denylist,
+    Allowlist,
+}
+
+pub fn get_access_mode() -> AccessMode {
+    match std::env::var("MINT_ACCESS_MODE").as_deref() {
+        Ok("allowlist") => AccessMode::Allowlist,
+        _ => AccessMode::Denylist,
+    }
+}
+
+/// The key an address or x_id is listed under: lowercased addresses and numeric x_ids. `None`
+/// for anything else.
+pub fn normalize_subject(subject: &str) -> Option<String> {
+    if Address::from_str(subject).is_ok() {
+        Some(subject.to_lowercase())
+    } else if !subject.is_empty() && subject.bytes().all(|b| b.is_ascii_digit()) {
+        Some(subject.to_string())
+    } else {
+        None
+    }
+}
+
+#[derive(Debug)]
+pub struct MintDenied(pub String);
+
+impl std::fmt::Display for MintDenied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Minting is not allowed for {}", self.0)
+    }
+}
+
+impl std::error::Error for MintDenied {}
+
+/// Checks a mint of `x_id`'s token to `recipient` against the access lists. A denied recipient
+/// or creator always blocks the mint; in allowlist mode the recipient or the creator must also
+/// be allowed.
+pub fn check_mint<A: TeleportDB>(db: &A, recipient: &str, x_id: &str) -> eyre::Result<()> {
+    let subjects = [recipient.to_lowercase(), x_id.to_string()];
+    let mut allowed = false;
+    for subject in &subjects {
+        match db.get_access_list_entry(subject.clone())?.map(|entry| entry.list) {
+            Some(AccessList::Deny) => return Err(MintDenied(subject.clone()).into()),
+            Some(AccessList::Allow) => allowed = true,
+            None => {}
+        }
+    }
+    if get_access_mode() == AccessMode::Allowlist && !allowed {
+        return Err(MintDenied(recipient.to_string()).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{in_memory::InMemoryDB, AccessListEntry};
+
+    #[test]
+    fn denied_creators_cannot_mint() -> eyre::Result<()> {
+        let mut db = InMemoryDB::new();
+        let recipient = "0x36e7Fda8CC503D5Ec7729A42eb86EF02Af315Bf9";
+        check_mint(&db, recipient, "1")?;
+        db.set_access_list_entry(AccessListEntry {
+            subject: "1".to_string(),
+            list: AccessList::Deny,
+            reason: "abuse".to_string(),
+            added_by: "admin".to_string(),
+            added_at: 0,
+        })?;
+        let denied = check_mint(&db, recipient, "1").unwrap_err();
+        assert!(denied.is::<MintDenied>());
+        check_mint(&db, recipient, "2")?;
+        assert_eq!(normalize_subject(recipient), Some(recipient.to_lowercase()));
+        assert_eq!(normalize_subject("@teleport"), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,231 @@
+use std::{str::FromStr, sync::Arc};
+
+use alloy::{
+    hex::ToHexExt,
+    primitives::{keccak256, Address, B256, U256},
+    sol,
+    sol_types::SolValue,
+};
+use tokio::time::{sleep, Duration};
+
+use super::{
+    chain::{chain_var, ChainClient},
+    nft::{simulate, Reverted},
+};
+use crate::db::{lock::TrackedMutex, ContentAnchor, TeleportDB, TxRecord, TxStatus};
+
+const ANCHOR_INTERVAL: Duration = Duration::from_secs(30);
+const BATCH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+sol! {
+    /// Records fulfilled redemptions so contracts can check a token's tweet went out with the
+    /// content it was redeemed for. Only the enclave's minter wallet may record.
+    #[sol(rpc)]
+    contract FulfillmentRegistry {
+        function anchor(uint256 tokenId, uint256 tweetId, bytes32 contentHash) external;
+        /// A merkle root over `abi.encode(tokenId, tweetId, contentHash)` leaves, with pairs
+        /// hashed sorted as OpenZeppelin's `MerkleProof` expects.
+        function anchorRoot(bytes32 root, uint256 count) external;
+    }
+}
+
+/// The registry posted redemptions are anchored in, from `ANCHOR_REGISTRY_ADDRESS`. With
+/// `ANCHOR_BATCHED` set they are sent hourly as one merkle root, otherwise one transaction each.
+#[derive(Debug, Clone)]
+pub struct AnchorConfig {
+    pub registry: Address,
+    pub batched: bool,
+}
+
+impl AnchorConfig {
+    /// `None` when the chain anchors nothing.
+    pub fn from_env(chain_id: u64) -> eyre::Result<Option<Self>> {
+        let Ok(registry) = chain_var("ANCHOR_REGISTRY_ADDRESS", chain_id) else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            registry: Address::from_str(&registry)?,
+            batched: chain_var("ANCHOR_BATCHED", chain_id).is_ok_and(|batched| batched == "true"),
+        }))
+    }
+}
+
+/// Whether redemptions posted on `chain_id` should be queued for anchoring.
+pub fn is_enabled(chain_id: u64) -> bool {
+    chain_var("ANCHOR_REGISTRY_ADDRESS", chain_id).is_ok()
+}
+
+fn leaf(token_id: U256, tweet_id: U256, content_hash: B256) -> B256 {
+    keccak256((token_id, tweet_id, content_hash).abi_encode())
+}
+
+fn hash_pair(a: B256, b: B256) -> B256 {
+    if a < b {
+        keccak256([a, b].concat())
+    } else {
+        keccak256([b, a].concat())
+    }
+}
+
+/// The merkle root over `leaves` and each leaf's proof. A node without a sibling is carried up
+/// unhashed.
+fn merkle_tree(leaves: &[B256]) -> (B256, Vec<Vec<B256>>) {
+    let mut proofs = vec![Vec::new(); leaves.len()];
+    // Which node on the current level each leaf's path runs through.
+    let mut positions: Vec<usize> = (0..leaves.len()).collect();
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        for (proof, position) in proofs.iter_mut().zip(positions.iter_mut()) {
+            if let Some(sibling) = level.get(*position ^ 1) {
+                proof.push(*sibling);
+            }
+            *position /= 2;
+        }
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] => hash_pair(*a, *b),
+                [a] => *a,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    (level.first().copied().unwrap_or_default(), proofs)
+}
+
+fn parse_anchor(token_id: &str, anchor: &ContentAnchor) -> eyre::Result<(U256, U256, B256)> {
+    Ok((
+        U256::from_str(token_id)?,
+        U256::from_str(&anchor.tweet_id)?,
+        B256::from_str(&anchor.content_hash)?,
+    ))
+}
+
+fn track_anchor_tx<A: TeleportDB>(db: &mut A, chain_id: u64, tx_hash: String) -> eyre::Result<()> {
+    let tx = TxRecord {
+        chain_id,
+        kind: "anchor".to_string(),
+        status: TxStatus::Pending,
+        submitted_at: chrono::Utc::now().timestamp(),
+        block_number: None,
+        gas_used: None,
+        replaced_by: None,
+        requested_by: None,
+    };
+    db.add_tx(tx_hash, tx)
+}
+
+/// Sends each queued anchor on its own. One that would revert is dropped, since retrying it
+/// cannot succeed.
+async fn send_anchors<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain: &ChainClient,
+    registry: Address,
+) -> eyre::Result<()> {
+    let chain_id = chain.config.chain_id;
+    let pending = db.lock().await.get_pending_anchors(chain_id)?;
+    for (token_id, mut anchor) in pending {
+        let (id, tweet_id, content_hash) = parse_anchor(&token_id, &anchor)?;
+        let request = FulfillmentRegistry::new(registry, chain.provider().clone())
+            .anchor(id, tweet_id, content_hash)
+            .into_transaction_request();
+        if let Err(e) = simulate(chain, &request).await {
+            if e.is::<Reverted>() {
+                log::error!("Dropping anchor of NFT {}: {:?}", token_id, e);
+                db.lock().await.remove_anchor(chain_id, token_id)?;
+                continue;
+            }
+            return Err(e);
+        }
+        let request = chain.config.gas.apply(chain.provider(), request).await?;
+        let tx_hash = chain.nonces.send(chain.submit_provider(), request).await?;
+        let tx_hash = tx_hash.encode_hex_with_prefix();
+        log::info!("Anchored NFT {} with tx hash: {}", token_id, tx_hash);
+
+        anchor.tx_hash = Some(tx_hash.clone());
+        let mut db = db.lock().await;
+        db.set_anchor(chain_id, token_id, anchor)?;
+        track_anchor_tx(&mut *db, chain_id, tx_hash)?;
+    }
+    Ok(())
+}
+
+/// Sends every queued anchor as one merkle root, storing each leaf's proof so it can be served
+/// to whoever needs to verify it on-chain.
+async fn send_anchor_batch<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain: &ChainClient,
+    registry: Address,
+) -> eyre::Result<()> {
+    let chain_id = chain.config.chain_id;
+    let pending = db.lock().await.get_pending_anchors(chain_id)?;
+    if pending.is_empty() {
+        return Ok(());
+    }
+    let leaves = pending
+        .iter()
+        .map(|(token_id, anchor)| {
+            let (id, tweet_id, content_hash) = parse_anchor(token_id, anchor)?;
+            Ok(leaf(id, tweet_id, content_hash))
+        })
+        .collect::<eyre::Result<Vec<_>>>()?;
+    let (root, proofs) = merkle_tree(&leaves);
+
+    let request = FulfillmentRegistry::new(registry, chain.provider().clone())
+        .anchorRoot(root, U256::from(leaves.len()))
+        .into_transaction_request();
+    simulate(chain, &request).await?;
+    let request = chain.config.gas.apply(chain.provider(), request).await?;
+    let tx_hash = chain.nonces.send(chain.submit_provider(), request).await?;
+    let tx_hash = tx_hash.encode_hex_with_prefix();
+    log::info!("Anchored {} NFTs under root {} with tx hash: {}", leaves.len(), root, tx_hash);
+
+    let root = root.encode_hex_with_prefix();
+    let mut db = db.lock().await;
+    for ((token_id, mut anchor), proof) in pending.into_iter().zip(proofs) {
+        anchor.tx_hash = Some(tx_hash.clone());
+        anchor.root = Some(root.clone());
+        anchor.proof = proof.iter().map(|node| node.encode_hex_with_prefix()).collect();
+        db.set_anchor(chain_id, token_id, anchor)?;
+    }
+    track_anchor_tx(&mut *db, chain_id, tx_hash)
+}
+
+/// Anchors posted redemptions in the fulfillment registry from the minter wallet, giving
+/// contracts a view of fulfillment that does not rest on trusting this service's API.
+pub async fn run_anchorer<A: TeleportDB>(
+    db: Arc<TrackedMutex<A>>,
+    chain: ChainClient,
+    config: AnchorConfig,
+) {
+    let interval = if config.batched { BATCH_INTERVAL } else { ANCHOR_INTERVAL };
+    loop {
+        sleep(interval).await;
+        let result = if config.batched {
+            send_anchor_batch(&db, &chain, config.registry).await
+        } else {
+            send_anchors(&db, &chain, config.registry).await
+        };
+        if let Err(e) = result {
+            log::error!("Failed to send anchors on chain {}: {:?}", chain.config.chain_id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merkle_proofs_rebuild_the_root() {
+        let leaves: Vec<B256> = (0u64..5)
+            .map(|i| leaf(U256::from(i), U256::from(i + 100), keccak256(i.to_be_bytes())))
+            .collect();
+        let (root, proofs) = merkle_tree(&leaves);
+        for (leaf, proof) in leaves.iter().zip(&proofs) {
+            let rebuilt = proof.iter().fold(*leaf, |node, sibling| hash_pair(node, *sibling));
+            assert_eq!(rebuilt, root);
+        }
+        assert_eq!(merkle_tree(&leaves[..1]).0, leaves[0]);
+    }
+}
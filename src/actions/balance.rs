@@ -0,0 +1,179 @@
+use std::str::FromStr;
+
+use alloy::{
+    primitives::{Address, U256},
+    providers::Provider,
+};
+use serde::Serialize;
+use tokio::time::{sleep, Duration};
+
+use super::chain::{chain_var, ChainClient};
+use crate::{
+    kill_switch::{self, SideEffect},
+    metrics,
+};
+
+const BALANCE_POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// 0.05 ETH.
+const DEFAULT_MIN_BALANCE_WEI: u64 = 50_000_000_000_000_000;
+const WEI_PER_GWEI: u64 = 1_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceStatus {
+    Low,
+    Recovered,
+}
+
+/// Posted to `FUNDER_BALANCE_ALERT_URL` when the funder wallet drops below its minimum and again
+/// once it is topped back up.
+#[derive(Debug, Serialize)]
+pub struct BalanceAlert {
+    pub chain_id: u64,
+    pub address: Address,
+    pub status: BalanceStatus,
+    /// Decimal wei.
+    pub balance: String,
+    pub min_balance: String,
+}
+
+/// Tracks the balance of the wallet that pays for gas, the minter wallet unless `FUNDER_ADDRESS`
+/// names another, as the `funder_balance_gwei` gauge. Below `FUNDER_MIN_BALANCE_WEI` it alerts
+/// and, with `FUNDER_AUTO_PAUSE`, switches off top-ups until the balance recovers, so they fail
+/// fast instead of one by one. All read `<name>_<chain_id>` first.
+pub struct BalanceMonitor {
+    chain_id: u64,
+    address: Address,
+    min_balance: U256,
+    hook_url: Option<String>,
+    auto_pause: bool,
+    low: bool,
+    /// Whether top-ups are off because of this monitor rather than an operator.
+    paused: bool,
+}
+
+impl BalanceMonitor {
+    pub fn from_env(chain_id: u64, minter: Address) -> eyre::Result<Self> {
+        let address = match chain_var("FUNDER_ADDRESS", chain_id) {
+            Ok(address) => Address::from_str(&address)?,
+            Err(_) => minter,
+        };
+        let min_balance = match chain_var("FUNDER_MIN_BALANCE_WEI", chain_id) {
+            Ok(wei) => U256::from_str(&wei)?,
+            Err(_) => U256::from(DEFAULT_MIN_BALANCE_WEI),
+        };
+        Ok(Self {
+            chain_id,
+            address,
+            min_balance,
+            hook_url: chain_var("FUNDER_BALANCE_ALERT_URL", chain_id).ok(),
+            auto_pause: chain_var("FUNDER_AUTO_PAUSE", chain_id).is_ok_and(|pause| pause == "true"),
+            low: false,
+            paused: false,
+        })
+    }
+
+    /// Records the funder's current balance, alerting when it crosses the minimum in either
+    /// direction.
+    pub fn observe(&mut self, balance: U256) {
+        let chain_id = self.chain_id.to_string();
+        let gwei = i64::try_from(balance / U256::from(WEI_PER_GWEI)).unwrap_or(i64::MAX);
+        metrics::set_gauge("funder_balance_gwei", &[("chain_id", &chain_id)], gwei);
+        let low = balance < self.min_balance;
+        if low == self.low {
+            return;
+        }
+        self.low = low;
+        let status = if low {
+            log::error!(
+                "Funder {} on chain {} is down to {} wei, below {}",
+                self.address,
+                chain_id,
+                balance,
+                self.min_balance
+            );
+            BalanceStatus::Low
+        } else {
+            log::info!("Funder {} on chain {} is back to {} wei", self.address, chain_id, balance);
+            BalanceStatus::Recovered
+        };
+        if low && self.auto_pause && kill_switch::is_enabled(SideEffect::TopUps) {
+            log::warn!("Pausing top-ups until the funder on chain {} is refilled", chain_id);
+            kill_switch::set_enabled(SideEffect::TopUps, false);
+            self.paused = true;
+        } else if !low && self.paused {
+            log::info!("Resuming top-ups on chain {}", chain_id);
+            kill_switch::set_enabled(SideEffect::TopUps, true);
+            self.paused = false;
+        }
+        metrics::increment(
+            "funder_balance_alerts_total",
+            &[("chain_id", &chain_id), ("status", if low { "low" } else { "recovered" })],
+        );
+        let Some(hook_url) = self.hook_url.clone() else {
+            return;
+        };
+        let alert = BalanceAlert {
+            chain_id: self.chain_id,
+            address: self.address,
+            status,
+            balance: balance.to_string(),
+            min_balance: self.min_balance.to_string(),
+        };
+        tokio::spawn(async move {
+            let sent = async {
+                reqwest::Client::new()
+                    .post(&hook_url)
+                    .json(&alert)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                eyre::Ok(())
+            };
+            if let Err(e) = sent.await {
+                log::error!("Failed to send funder balance alert: {:?}", e);
+            }
+        });
+    }
+}
+
+/// Polls the funder wallet's balance on `chain` for the [`BalanceMonitor`].
+pub async fn run_balance_monitor(chain: ChainClient, mut monitor: BalanceMonitor) {
+    loop {
+        match chain.provider().get_balance(monitor.address).await {
+            Ok(balance) => monitor.observe(balance),
+            Err(e) => log::error!(
+                "Failed to read funder balance on chain {}: {:?}",
+                chain.config.chain_id,
+                e
+            ),
+        }
+        sleep(BALANCE_POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pauses_top_ups_while_low() {
+        let mut monitor = BalanceMonitor {
+            chain_id: 1,
+            address: Address::ZERO,
+            min_balance: U256::from(100),
+            hook_url: None,
+            auto_pause: true,
+            low: false,
+            paused: false,
+        };
+        monitor.observe(U256::from(150));
+        assert!(!monitor.low);
+        monitor.observe(U256::from(99));
+        assert!(monitor.low);
+        assert!(!kill_switch::is_enabled(SideEffect::TopUps));
+        monitor.observe(U256::from(100));
+        assert!(!monitor.low);
+        assert!(kill_switch::is_enabled(SideEffect::TopUps));
+    }
+}
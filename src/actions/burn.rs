@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use alloy::{hex::ToHexExt, primitives::U256, sol};
+use tokio::time::{sleep, Duration};
+
+use super::{
+    chain::ChainClient,
+    nft::{simulate, Reverted},
+};
+use crate::db::{lock::TrackedMutex, TeleportDB, TxRecord, TxStatus};
+
+const BURN_INTERVAL: Duration = Duration::from_secs(30);
+
+sol! {
+    /// Burning is owner-only, so only contracts that let the minter wallet burn redeemed tokens
+    /// support burn-on-redeem.
+    #[sol(rpc)]
+    contract BurnableNFT {
+        function burn(uint256 tokenId) external;
+    }
+}
+
+pub async fn burn_nft(chain: &ChainClient, token_id: U256) -> eyre::Result<String> {
+    let request = BurnableNFT::new(chain.config.nft_address, chain.provider().clone())
+        .burn(token_id)
+        .into_transaction_request();
+    simulate(chain, &request).await?;
+    let request = chain.config.gas.apply(chain.provider(), request).await?;
+    let tx_hash = chain.nonces.send(chain.submit_provider(), request).await?;
+
+    log::info!("Burned NFT {} with tx hash: {}", token_id, tx_hash);
+    Ok(tx_hash.encode_hex_with_prefix())
+}
+
+/// Sends the queued burns on `chain` that have not been sent yet. A burn that would revert is
+/// dropped, since retrying it cannot succeed.
+async fn send_pending_burns<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain: &ChainClient,
+) -> eyre::Result<()> {
+    let chain_id = chain.config.chain_id;
+    let pending = db.lock().await.get_pending_burns(chain_id)?;
+    for (token_id, _) in pending.into_iter().filter(|(_, burn)| burn.tx_hash.is_none()) {
+        let tx_hash = match burn_nft(chain, token_id.parse()?).await {
+            Ok(tx_hash) => tx_hash,
+            Err(e) if e.is::<Reverted>() => {
+                log::error!("Dropping burn of NFT {}: {:?}", token_id, e);
+                db.lock().await.remove_burn(chain_id, token_id)?;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let tx = TxRecord {
+            chain_id,
+            kind: "burn".to_string(),
+            status: TxStatus::Pending,
+            submitted_at: chrono::Utc::now().timestamp(),
+            block_number: None,
+            gas_used: None,
+            replaced_by: None,
+            requested_by: None,
+        };
+        let mut db = db.lock().await;
+        db.set_burn_tx(chain_id, token_id, tx_hash.clone())?;
+        db.add_tx(tx_hash, tx)?;
+    }
+    Ok(())
+}
+
+/// Burns redeemed tokens whose creators asked for it, and revoked redemptions, from the minter
+/// wallet. The indexer finishes the job when it sees the resulting transfer to the zero address.
+pub async fn run_burner<A: TeleportDB>(db: Arc<TrackedMutex<A>>, chain: ChainClient) {
+    loop {
+        sleep(BURN_INTERVAL).await;
+        if let Err(e) = send_pending_burns(&db, &chain).await {
+            log::error!("Failed to send burns on chain {}: {:?}", chain.config.chain_id, e);
+        }
+    }
+}
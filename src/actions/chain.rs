@@ -0,0 +1,176 @@
+use std::str::FromStr;
+
+use alloy::primitives::Address;
+
+use super::{
+    anchor::AnchorConfig,
+    gas::GasStrategy,
+    nft::get_nft_address,
+    payments::MintFeeConfig,
+    rpc::RpcPool,
+    smart_account::SmartAccountConfig,
+    top_up::TopUpPolicy,
+    wallet::{NonceManager, WalletProvider},
+};
+
+/// Base mainnet, the chain served when `CHAIN_IDS` is not set.
+pub const DEFAULT_CHAIN_ID: u64 = 8453;
+
+/// Which kind of contract the NFT contract is, from `TOKEN_STANDARD` (`erc721` or `erc1155`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenStandard {
+    /// One owner per token id.
+    Erc721,
+    /// Editions, where each token id is minted in several copies.
+    Erc1155,
+}
+
+impl FromStr for TokenStandard {
+    type Err = eyre::Report;
+
+    fn from_str(standard: &str) -> eyre::Result<Self> {
+        match standard {
+            "erc721" => Ok(Self::Erc721),
+            "erc1155" => Ok(Self::Erc1155),
+            _ => eyre::bail!("Unknown token standard {}", standard),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChainConfig {
+    pub chain_id: u64,
+    /// `RPC_URL` followed by the `RPC_FALLBACK_URLS`, in priority order.
+    pub rpc_urls: Vec<String>,
+    /// `WS_RPC_URL` followed by the `WS_RPC_FALLBACK_URLS`, in priority order.
+    pub ws_rpc_urls: Vec<String>,
+    /// `PRIVATE_TX_RPC_URL`, a private relay such as Flashbots Protect that mints and redeems are
+    /// submitted through instead of the public mempool, so tweet content in redeem calldata cannot
+    /// be frontrun or censored before it is mined.
+    pub private_tx_rpc_url: Option<String>,
+    /// The contract new tokens are minted and redeemed on.
+    pub nft_address: Address,
+    /// Every contract the indexer follows, the current `nft_address` included.
+    pub indexed_addresses: Vec<Address>,
+    pub token_standard: TokenStandard,
+    /// `EDITION_SIZE`, how many copies of each token an ERC-1155 contract mints.
+    pub edition_size: u64,
+    /// `TRUSTED_FORWARDER_ADDRESS`, an ERC-2771 forwarder the NFT contract trusts, through which
+    /// the minter wallet relays redeems signed by holders so their wallets need no ETH.
+    pub trusted_forwarder: Option<Address>,
+    /// ERC-4337 smart accounts, whose redeems a paymaster sponsors.
+    pub smart_accounts: Option<SmartAccountConfig>,
+    pub gas: GasStrategy,
+    /// `WATCH_DEPOSITS`, whether to record and notify ETH and token transfers to user addresses.
+    pub watch_deposits: bool,
+    /// ERC-20 mint fees, when mints must be paid for.
+    pub mint_fee: Option<MintFeeConfig>,
+    /// The fulfillment registry posted redemptions are anchored in, if any.
+    pub anchor: Option<AnchorConfig>,
+    /// Gas top-ups for users linking their X account, if any.
+    pub top_up: Option<TopUpPolicy>,
+}
+
+/// Reads `<name>_<chain_id>`, falling back to the unsuffixed `<name>`.
+pub fn chain_var(name: &str, chain_id: u64) -> eyre::Result<String> {
+    std::env::var(format!("{}_{}", name, chain_id))
+        .or_else(|_| std::env::var(name))
+        .map_err(|_| eyre::eyre!("{} not set for chain {}", name, chain_id))
+}
+
+/// Reads `INDEXED_NFT_ADDRESSES` (comma separated), so retired deployments stay indexed next to
+/// the current one.
+fn get_indexed_addresses(chain_id: u64, nft_address: Address) -> eyre::Result<Vec<Address>> {
+    let mut addresses = vec![nft_address];
+    if let Ok(indexed) = chain_var("INDEXED_NFT_ADDRESSES", chain_id) {
+        for address in indexed.split(',').map(str::trim).filter(|address| !address.is_empty()) {
+            let address = Address::from_str(address)?;
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+    }
+    Ok(addresses)
+}
+
+/// The keyed primary `<name>` followed by the comma separated `<fallbacks_name>`, which are used
+/// as given since they usually belong to other providers.
+fn get_endpoints(
+    name: &str,
+    fallbacks_name: &str,
+    chain_id: u64,
+    rpc_key: &str,
+) -> eyre::Result<Vec<String>> {
+    let mut urls = vec![chain_var(name, chain_id)? + rpc_key];
+    if let Ok(fallbacks) = chain_var(fallbacks_name, chain_id) {
+        urls.extend(
+            fallbacks.split(',').map(str::trim).filter(|url| !url.is_empty()).map(String::from),
+        );
+    }
+    Ok(urls)
+}
+
+impl ChainConfig {
+    pub fn from_env(chain_id: u64, rpc_key: &str) -> eyre::Result<Self> {
+        let nft_address = get_nft_address(chain_id)?;
+        Ok(Self {
+            chain_id,
+            rpc_urls: get_endpoints("RPC_URL", "RPC_FALLBACK_URLS", chain_id, rpc_key)?,
+            ws_rpc_urls: get_endpoints("WS_RPC_URL", "WS_RPC_FALLBACK_URLS", chain_id, rpc_key)?,
+            private_tx_rpc_url: chain_var("PRIVATE_TX_RPC_URL", chain_id).ok(),
+            nft_address,
+            indexed_addresses: get_indexed_addresses(chain_id, nft_address)?,
+            token_standard: match chain_var("TOKEN_STANDARD", chain_id) {
+                Ok(standard) => standard.parse()?,
+                Err(_) => TokenStandard::Erc721,
+            },
+            edition_size: match chain_var("EDITION_SIZE", chain_id) {
+                Ok(size) => size.parse()?,
+                Err(_) => 1,
+            },
+            trusted_forwarder: chain_var("TRUSTED_FORWARDER_ADDRESS", chain_id)
+                .ok()
+                .map(|address| Address::from_str(&address))
+                .transpose()?,
+            smart_accounts: SmartAccountConfig::from_env(chain_id)?,
+            gas: GasStrategy::from_env(chain_id)?,
+            watch_deposits: chain_var("WATCH_DEPOSITS", chain_id)
+                .is_ok_and(|watch| watch == "true"),
+            mint_fee: MintFeeConfig::from_env(chain_id)?,
+            anchor: AnchorConfig::from_env(chain_id)?,
+            top_up: TopUpPolicy::from_env(chain_id)?,
+        })
+    }
+}
+
+/// Loads every chain listed in `CHAIN_IDS` (comma separated, default chain first).
+pub fn load_chains(rpc_key: &str) -> eyre::Result<Vec<ChainConfig>> {
+    let chain_ids = std::env::var("CHAIN_IDS").unwrap_or_else(|_| DEFAULT_CHAIN_ID.to_string());
+    chain_ids
+        .split(',')
+        .map(|chain_id| ChainConfig::from_env(chain_id.trim().parse()?, rpc_key))
+        .collect()
+}
+
+/// A configured chain together with the minter wallet's providers and nonces on it.
+#[derive(Clone)]
+pub struct ChainClient {
+    pub config: ChainConfig,
+    pub rpc: RpcPool,
+    /// The private relay at `PRIVATE_TX_RPC_URL`, if configured.
+    pub private_provider: Option<WalletProvider>,
+    pub nonces: NonceManager,
+}
+
+impl ChainClient {
+    /// The healthy RPC endpoint reads and simulations go to.
+    pub fn provider(&self) -> &WalletProvider {
+        self.rpc.provider()
+    }
+
+    /// Sends the minter wallet's transactions: the private relay when one is configured,
+    /// otherwise the current RPC endpoint.
+    pub fn submit_provider(&self) -> &WalletProvider {
+        self.private_provider.as_ref().unwrap_or_else(|| self.provider())
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+
+use alloy::rpc::types::Log;
+
+/// Holds logs back until they are `depth` blocks deep so that a shallow reorg can drop them before
+/// anything is written to the databases.
+pub struct ConfirmationBuffer {
+    depth: u64,
+    pending: BTreeMap<(u64, u64), Log>,
+}
+
+fn log_key(log: &Log) -> Option<(u64, u64)> {
+    Some((log.block_number?, log.log_index?))
+}
+
+impl ConfirmationBuffer {
+    pub fn new(depth: u64) -> Self {
+        Self { depth, pending: BTreeMap::new() }
+    }
+
+    /// Buffers a log. Removed logs cancel their pending counterpart; if that counterpart was
+    /// already released, the removed log is handed back so the caller can roll it back.
+    pub fn push(&mut self, log: Log) -> Option<Log> {
+        let key = log_key(&log)?;
+        if log.removed {
+            return match self.pending.remove(&key) {
+                Some(_) => None,
+                None => Some(log),
+            };
+        }
+        self.pending.insert(key, log);
+        None
+    }
+
+    /// Releases, in chain order, every buffered log that is at least `depth` blocks below `head`.
+    pub fn drain_confirmed(&mut self, head: u64) -> Vec<Log> {
+        let Some(confirmed_block) = head.checked_sub(self.depth) else {
+            return Vec::new();
+        };
+        let still_pending = self.pending.split_off(&(confirmed_block + 1, 0));
+        std::mem::replace(&mut self.pending, still_pending).into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_at(block_number: u64, log_index: u64, removed: bool) -> Log {
+        Log {
+            block_number: Some(block_number),
+            log_index: Some(log_index),
+            removed,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn releases_logs_once_deep_enough() {
+        let mut buffer = ConfirmationBuffer::new(2);
+        buffer.push(log_at(10, 1, false));
+        buffer.push(log_at(10, 0, false));
+        buffer.push(log_at(11, 0, false));
+        assert!(buffer.drain_confirmed(11).is_empty());
+        let released = buffer.drain_confirmed(12);
+        assert_eq!(released.len(), 2);
+        assert_eq!(released[0].log_index, Some(0));
+        assert_eq!(buffer.drain_confirmed(13).len(), 1);
+    }
+
+    #[test]
+    fn removed_logs_cancel_or_roll_back() {
+        let mut buffer = ConfirmationBuffer::new(2);
+        buffer.push(log_at(10, 0, false));
+        assert!(buffer.push(log_at(10, 0, true)).is_none());
+        assert!(buffer.drain_confirmed(20).is_empty());
+        assert!(buffer.push(log_at(9, 0, true)).is_some());
+    }
+}
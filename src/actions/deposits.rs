@@ -0,0 +1,214 @@
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+
+use alloy::{
+    primitives::{b256, utils::format_ether, Address, B256, U256},
+    providers::Provider,
+    rpc::types::{Filter, Log},
+};
+use chrono_tz::Tz;
+use eyre::OptionExt;
+use tokio::time::{sleep, Duration};
+
+use super::chain::ChainClient;
+use crate::{
+    db::{lock::TrackedMutex, LedgerEntry, TeleportDB},
+    events::Notification,
+    metrics,
+    notify::Notifier,
+    timezone::parse_timezone,
+};
+
+const DEPOSIT_POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// Blocks scanned per pass, so catching up after downtime does not stall on one huge pass.
+const MAX_BLOCKS_PER_PASS: u64 = 50;
+/// Recipients per `eth_getLogs` filter, within what RPC providers accept.
+const MAX_RECIPIENTS_PER_FILTER: usize = 500;
+/// `Transfer(address,address,uint256)`, shared by ERC-20 and ERC-721.
+const TRANSFER_TOPIC: B256 =
+    b256!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef");
+
+/// A deposit that was seen, and the user address it went to.
+struct Deposit {
+    to: Address,
+    entry: LedgerEntry,
+}
+
+/// ETH sent directly to a user address in `block_number`. Transfers made from inside a contract
+/// call do not appear as transactions and are missed.
+async fn eth_deposits(
+    chain: &ChainClient,
+    users: &HashMap<Address, String>,
+    block_number: u64,
+    now: i64,
+) -> eyre::Result<Vec<Deposit>> {
+    let block = chain
+        .provider()
+        .get_block_by_number(block_number.into(), true)
+        .await?
+        .ok_or_eyre(format!("Block {} not found", block_number))?;
+    Ok(block
+        .transactions
+        .txns()
+        .filter_map(|tx| {
+            let to = tx.to.filter(|to| users.contains_key(to) && tx.value > U256::ZERO)?;
+            let entry = LedgerEntry {
+                chain_id: chain.config.chain_id,
+                asset: None,
+                from: tx.from.to_string(),
+                amount: tx.value.to_string(),
+                token_id: None,
+                tx_hash: tx.hash.to_string(),
+                log_index: None,
+                block_number,
+                recorded_at: now,
+            };
+            Some(Deposit { to, entry })
+        })
+        .collect())
+}
+
+/// An ERC-20 or ERC-721 transfer log, which differ in whether the third word is indexed.
+fn token_deposit(chain_id: u64, log: &Log, now: i64) -> Option<Deposit> {
+    let (from, to, amount, token_id) = match log.topics() {
+        [_, from, to] => (from, to, U256::from_be_slice(&log.data().data), None),
+        [_, from, to, token_id] => (from, to, U256::from(1), Some(U256::from_be_bytes(token_id.0))),
+        _ => return None,
+    };
+    let entry = LedgerEntry {
+        chain_id,
+        asset: Some(log.address().to_string()),
+        from: Address::from_word(*from).to_string(),
+        amount: amount.to_string(),
+        token_id: token_id.map(|token_id| token_id.to_string()),
+        tx_hash: log.transaction_hash?.to_string(),
+        log_index: log.log_index,
+        block_number: log.block_number?,
+        recorded_at: now,
+    };
+    Some(Deposit { to: Address::from_word(*to), entry })
+}
+
+async fn token_deposits(
+    chain: &ChainClient,
+    users: &HashMap<Address, String>,
+    from_block: u64,
+    to_block: u64,
+    now: i64,
+) -> eyre::Result<Vec<Deposit>> {
+    let recipients: Vec<B256> = users.keys().map(|user| user.into_word()).collect();
+    let mut deposits = Vec::new();
+    for recipients in recipients.chunks(MAX_RECIPIENTS_PER_FILTER) {
+        let filter = Filter::new()
+            .from_block(from_block)
+            .to_block(to_block)
+            .event_signature(TRANSFER_TOPIC)
+            .topic2(recipients.to_vec());
+        let logs = chain.provider().get_logs(&filter).await?;
+        deposits
+            .extend(logs.iter().filter_map(|log| token_deposit(chain.config.chain_id, log, now)));
+    }
+    Ok(deposits)
+}
+
+fn deposit_message(entry: &LedgerEntry, to: &str) -> String {
+    let received = match (&entry.asset, &entry.token_id) {
+        (None, _) => {
+            format!("{} ETH", format_ether(U256::from_str(&entry.amount).unwrap_or_default()))
+        }
+        (Some(asset), Some(token_id)) => format!("token #{} of {}", token_id, asset),
+        (Some(asset), None) => format!("{} base units of token {}", entry.amount, asset),
+    };
+    format!("{} arrived at {} from {}", received, to, entry.from)
+}
+
+/// Records `deposit` in its recipient's ledger and, the first time it is seen, tells them.
+async fn record_deposit<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    notifier: &Notifier,
+    address: String,
+    deposit: Deposit,
+) -> eyre::Result<()> {
+    let mut db = db.lock().await;
+    if !db.add_ledger_entry(address.clone(), deposit.entry.clone())? {
+        return Ok(());
+    }
+    let kind = match (&deposit.entry.asset, &deposit.entry.token_id) {
+        (None, _) => "eth",
+        (Some(_), Some(_)) => "erc721",
+        (Some(_), None) => "erc20",
+    };
+    metrics::increment(
+        "deposits_total",
+        &[("chain_id", &deposit.entry.chain_id.to_string()), ("kind", kind)],
+    );
+    let Some(x_id) = db.get_user_by_address(address.clone())?.x_id else {
+        return Ok(());
+    };
+    let timezone = db
+        .get_timezone(x_id.clone())
+        .ok()
+        .and_then(|timezone| parse_timezone(&timezone).ok())
+        .unwrap_or(Tz::UTC);
+    drop(db);
+
+    let message = deposit_message(&deposit.entry, &address);
+    let deliver_after = notifier.deliver_after(timezone, chrono::Utc::now());
+    notifier.notify(Notification { x_id, message, deliver_after }).await
+}
+
+/// Scans the blocks after the deposit cursor for ETH and token transfers to user addresses.
+async fn scan_deposits<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain: &ChainClient,
+    notifier: &Notifier,
+) -> eyre::Result<()> {
+    let chain_id = chain.config.chain_id;
+    let latest = chain.provider().get_block_number().await?;
+    let db_lock = db.lock().await;
+    // A first run starts at the head rather than scanning the chain's history.
+    let from_block = db_lock.get_deposit_cursor(chain_id)?.map_or(latest, |cursor| cursor + 1);
+    let users: HashMap<Address, String> = db_lock
+        .get_user_addresses()?
+        .into_iter()
+        .filter_map(|address| Some((Address::from_str(&address).ok()?, address)))
+        .collect();
+    drop(db_lock);
+    if from_block > latest {
+        return Ok(());
+    }
+    if users.is_empty() {
+        return db.lock().await.set_deposit_cursor(chain_id, latest);
+    }
+    let to_block = latest.min(from_block + MAX_BLOCKS_PER_PASS - 1);
+
+    let now = chrono::Utc::now().timestamp();
+    let mut deposits = token_deposits(chain, &users, from_block, to_block, now).await?;
+    for block_number in from_block..=to_block {
+        deposits.extend(eth_deposits(chain, &users, block_number, now).await?);
+    }
+    for deposit in deposits {
+        let Some(address) = users.get(&deposit.to).cloned() else {
+            continue;
+        };
+        let tx_hash = deposit.entry.tx_hash.clone();
+        if let Err(e) = record_deposit(db, notifier, address, deposit).await {
+            log::error!("Failed to record deposit in {}: {:?}", tx_hash, e);
+        }
+    }
+    db.lock().await.set_deposit_cursor(chain_id, to_block)
+}
+
+/// Follows `chain` for funds arriving at registered user addresses, so users hear about them and
+/// support can trace unexpected deposits from each address's ledger.
+pub async fn run_deposit_watcher<A: TeleportDB>(
+    db: Arc<TrackedMutex<A>>,
+    chain: ChainClient,
+    notifier: Notifier,
+) {
+    loop {
+        sleep(DEPOSIT_POLL_INTERVAL).await;
+        if let Err(e) = scan_deposits(&db, &chain, &notifier).await {
+            log::error!("Failed to scan deposits on chain {}: {:?}", chain.config.chain_id, e);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    future::Future,
+    sync::Arc,
+};
+
+use tokio::sync::{oneshot, Semaphore};
+
+use crate::db::BlockCursor;
+
+/// Runs event handlers concurrently, at most `limit` at a time, while handlers that share an
+/// ordering key (a token id) still run one after another in the order they were dispatched.
+#[derive(Clone)]
+pub struct EventDispatcher {
+    limit: u32,
+    permits: Arc<Semaphore>,
+    /// Completion of the most recently dispatched handler for each key.
+    tails: Arc<std::sync::Mutex<BTreeMap<String, oneshot::Receiver<()>>>>,
+}
+
+impl EventDispatcher {
+    pub fn new(limit: u32) -> Self {
+        let limit = limit.max(1);
+        Self { limit, permits: Arc::new(Semaphore::new(limit as usize)), tails: Default::default() }
+    }
+
+    /// Spawns `handler` once a slot is free and every earlier handler for `key` has finished.
+    /// Waits for a slot, so a full pool pushes back on the log stream.
+    pub async fn dispatch<F>(&self, key: String, handler: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let permit =
+            self.permits.clone().acquire_owned().await.expect("Dispatcher semaphore closed");
+        let (done, tail) = oneshot::channel();
+        let previous = self.tails.lock().unwrap().insert(key, tail);
+        tokio::spawn(async move {
+            if let Some(previous) = previous {
+                // An error only means the previous handler panicked, which still ends its turn.
+                let _ = previous.await;
+            }
+            handler.await;
+            drop(permit);
+            let _ = done.send(());
+        });
+    }
+
+    /// Waits until every handler dispatched so far has finished.
+    pub async fn wait_idle(&self) {
+        let _all =
+            self.permits.acquire_many(self.limit).await.expect("Dispatcher semaphore closed");
+    }
+}
+
+/// Where the dispatched logs are, so the persisted cursor only moves past a log once every log
+/// dispatched before it has finished too, however out of order the handlers complete.
+#[derive(Clone, Default)]
+pub struct CursorTracker {
+    cursors: Arc<std::sync::Mutex<TrackedCursors>>,
+}
+
+#[derive(Default)]
+struct TrackedCursors {
+    in_flight: BTreeSet<BlockCursor>,
+    /// Finished logs after the earliest one still in flight.
+    finished: BTreeSet<BlockCursor>,
+}
+
+impl CursorTracker {
+    /// Records a log as dispatched. Logs are started in chain order.
+    pub fn start(&self, cursor: BlockCursor) {
+        self.cursors.lock().unwrap().in_flight.insert(cursor);
+    }
+
+    /// Records a log as finished, returning the cursor it lets the persisted one move to: the
+    /// last finished log before the earliest one still in flight, if there is a new one.
+    pub fn finish(&self, cursor: BlockCursor) -> Option<BlockCursor> {
+        let mut cursors = self.cursors.lock().unwrap();
+        cursors.in_flight.remove(&cursor);
+        cursors.finished.insert(cursor);
+        let settled = match cursors.in_flight.first().copied() {
+            Some(earliest) => {
+                let unsettled = cursors.finished.split_off(&earliest);
+                std::mem::replace(&mut cursors.finished, unsettled)
+            }
+            None => std::mem::take(&mut cursors.finished),
+        };
+        settled.last().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use tokio::time::{sleep, Duration};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn keeps_per_key_order_across_concurrent_handlers() {
+        let dispatcher = EventDispatcher::new(4);
+        let order = Arc::new(Mutex::new(Vec::new()));
+        for (key, name, delay) in [("1", "1a", 50), ("2", "2a", 0), ("1", "1b", 0)] {
+            let order = order.clone();
+            dispatcher
+                .dispatch(key.to_string(), async move {
+                    sleep(Duration::from_millis(delay)).await;
+                    order.lock().unwrap().push(name);
+                })
+                .await;
+        }
+        dispatcher.wait_idle().await;
+        assert_eq!(*order.lock().unwrap(), vec!["2a", "1a", "1b"]);
+    }
+
+    #[test]
+    fn cursor_waits_for_earlier_logs() {
+        let tracker = CursorTracker::default();
+        let cursor = |block_number| BlockCursor { block_number, log_index: 0 };
+        for block_number in 1..=3 {
+            tracker.start(cursor(block_number));
+        }
+        assert_eq!(tracker.finish(cursor(3)), None);
+        assert_eq!(tracker.finish(cursor(2)), None);
+        assert_eq!(tracker.finish(cursor(1)), Some(cursor(3)));
+        tracker.start(cursor(4));
+        tracker.start(cursor(5));
+        assert_eq!(tracker.finish(cursor(4)), Some(cursor(4)));
+    }
+}
@@ -0,0 +1,119 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use alloy::{
+    primitives::{address, keccak256, Address, B256},
+    providers::{Provider, ProviderBuilder},
+    sol,
+    transports::Transport,
+};
+
+use super::chain::ChainClient;
+
+/// The ENS registry, at the same address on mainnet and its testnets.
+const ENS_REGISTRY: Address = address!("00000000000C2E074eC69A0dFb2997BA6C7d2e1e");
+/// Mainnet, where names are resolved unless `ENS_CHAIN_ID` says otherwise.
+const DEFAULT_ENS_CHAIN_ID: u64 = 1;
+
+sol! {
+    #[sol(rpc)]
+    contract EnsRegistry {
+        function resolver(bytes32 node) external view returns (address);
+    }
+
+    #[sol(rpc)]
+    contract EnsResolver {
+        function addr(bytes32 node) external view returns (address);
+    }
+}
+
+/// A name that does not resolve to an address, as opposed to a failure to reach ENS.
+#[derive(Debug)]
+pub struct UnresolvedName(pub String);
+
+impl std::fmt::Display for UnresolvedName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ENS name {} does not resolve to an address", self.0)
+    }
+}
+
+impl std::error::Error for UnresolvedName {}
+
+/// The ENS node of `name`. Names are lowercased; full UTS-46 normalization is left to clients.
+pub fn namehash(name: &str) -> B256 {
+    name.to_lowercase().rsplit('.').fold(B256::ZERO, |node, label| {
+        keccak256([node.as_slice(), keccak256(label.as_bytes()).as_slice()].concat())
+    })
+}
+
+fn is_ens_name(name: &str) -> bool {
+    name.contains('.') && name.split('.').all(|label| !label.is_empty())
+}
+
+async fn resolve_with<T: Transport + Clone, P: Provider<T>>(
+    provider: &P,
+    name: &str,
+) -> eyre::Result<Address> {
+    let node = namehash(name);
+    let resolver = EnsRegistry::new(ENS_REGISTRY, provider).resolver(node).call().await?._0;
+    if resolver == Address::ZERO {
+        return Err(UnresolvedName(name.to_string()).into());
+    }
+    let address = EnsResolver::new(resolver, provider).addr(node).call().await?._0;
+    if address == Address::ZERO {
+        return Err(UnresolvedName(name.to_string()).into());
+    }
+    Ok(address)
+}
+
+/// Resolves `name` on the `ENS_CHAIN_ID` chain, through its client when it is served and
+/// otherwise through `ENS_RPC_URL`.
+async fn resolve_name(chains: &BTreeMap<u64, ChainClient>, name: &str) -> eyre::Result<Address> {
+    let chain_id = std::env::var("ENS_CHAIN_ID")
+        .ok()
+        .and_then(|chain_id| chain_id.parse().ok())
+        .unwrap_or(DEFAULT_ENS_CHAIN_ID);
+    match chains.get(&chain_id) {
+        Some(chain) => resolve_with(chain.provider(), name).await,
+        None => {
+            let rpc_url = std::env::var("ENS_RPC_URL")
+                .map_err(|_| eyre::eyre!("ENS_RPC_URL not set to resolve {}", name))?;
+            resolve_with(&ProviderBuilder::new().on_http(rpc_url.parse()?), name).await
+        }
+    }
+}
+
+/// Reads `address_or_name` as an address, or resolves it as an ENS name. Returns the address and
+/// the name it was resolved from, if any.
+pub async fn resolve_address(
+    chains: &BTreeMap<u64, ChainClient>,
+    address_or_name: &str,
+) -> eyre::Result<(Address, Option<String>)> {
+    if let Ok(address) = Address::from_str(address_or_name) {
+        return Ok((address, None));
+    }
+    if !is_ens_name(address_or_name) {
+        return Err(UnresolvedName(address_or_name.to_string()).into());
+    }
+    let address = resolve_name(chains, address_or_name).await?;
+    Ok((address, Some(address_or_name.to_lowercase())))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::b256;
+
+    use super::*;
+
+    #[test]
+    fn namehash_matches_eip_137() {
+        assert_eq!(
+            namehash("eth"),
+            b256!("93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae")
+        );
+        assert_eq!(
+            namehash("foo.eth"),
+            b256!("de9b09fd7c5f901e23a3f19fecc54828e9c848539801e86591bd9801b019f84f")
+        );
+        assert_eq!(namehash("Foo.ETH"), namehash("foo.eth"));
+    }
+}
@@ -0,0 +1,97 @@
+use alloy::{primitives::U256, rpc::types::Log, sol, sol_types::SolEventInterface};
+
+use super::nft::NFT::{self, NFTEvents};
+
+sol! {
+    /// The edition contract, where one token id is minted in several copies. It emits the same
+    /// `NewTokenData` and `RedeemTweet` events as the ERC-721 contract, and redeeming burns one
+    /// copy from the holder.
+    #[sol(rpc)]
+    contract NFT1155 {
+        event TransferSingle(address indexed operator, address indexed from, address indexed to, uint256 id, uint256 value);
+        event TransferBatch(address indexed operator, address indexed from, address indexed to, uint256[] ids, uint256[] values);
+
+        function mintTo(address recipient, uint256 x_id, string policy, uint256 copies) external;
+        function redeem(address holder, uint256 tokenId, string content, uint8 tokenType) external;
+        function balanceOf(address account, uint256 id) external view returns (uint256);
+    }
+}
+
+/// The contract event name of an edition contract log and, when it concerns a single token, that
+/// token's id.
+pub fn describe_log(log: &Log) -> Option<(&'static str, Option<U256>)> {
+    match NFT1155::NFT1155Events::decode_raw_log(log.topics(), &log.data().data, true) {
+        Ok(NFT1155::NFT1155Events::TransferSingle(transfer)) => {
+            Some(("TransferSingle", Some(transfer.id)))
+        }
+        Ok(NFT1155::NFT1155Events::TransferBatch(transfer)) => {
+            let token_id = match transfer.ids.as_slice() {
+                [id] => Some(*id),
+                _ => None,
+            };
+            Some(("TransferBatch", token_id))
+        }
+        Err(_) => None,
+    }
+}
+
+/// Decodes an edition contract log into the ERC-721 events the handlers understand. Each token
+/// id a transfer moves becomes one `Transfer`, whatever the number of copies.
+pub fn decode_log(log: &Log) -> Vec<NFTEvents> {
+    let transfer =
+        |from, to, token_id| NFTEvents::Transfer(NFT::Transfer { from, to, tokenId: token_id });
+    match NFT1155::NFT1155Events::decode_raw_log(log.topics(), &log.data().data, true) {
+        Ok(NFT1155::NFT1155Events::TransferSingle(single)) => {
+            vec![transfer(single.from, single.to, single.id)]
+        }
+        Ok(NFT1155::NFT1155Events::TransferBatch(batch)) => {
+            batch.ids.iter().map(|id| transfer(batch.from, batch.to, *id)).collect()
+        }
+        Err(_) => match NFTEvents::decode_raw_log(log.topics(), &log.data().data, true) {
+            Ok(
+                event @ (NFTEvents::NewTokenData(_) |
+                NFTEvents::RedeemTweet(_) |
+                NFTEvents::OwnershipTransferred(_)),
+            ) => vec![event],
+            _ => vec![],
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{Address, LogData},
+        sol_types::SolEvent,
+    };
+
+    use super::*;
+
+    #[test]
+    fn batch_transfers_become_one_transfer_per_token() {
+        let batch = NFT1155::TransferBatch {
+            operator: Address::with_last_byte(1),
+            from: Address::with_last_byte(2),
+            to: Address::with_last_byte(3),
+            ids: vec![U256::from(7), U256::from(8)],
+            values: vec![U256::from(2), U256::from(1)],
+        };
+        let data: LogData = batch.encode_log_data();
+        let log = Log {
+            inner: alloy::primitives::Log { address: Address::ZERO, data },
+            ..Default::default()
+        };
+        let token_ids: Vec<U256> = decode_log(&log)
+            .into_iter()
+            .map(|event| match event {
+                NFTEvents::Transfer(transfer) => {
+                    assert_eq!(transfer.to, Address::with_last_byte(3));
+                    transfer.tokenId
+                }
+                _ => panic!("Expected a transfer"),
+            })
+            .collect();
+        assert_eq!(token_ids, vec![U256::from(7), U256::from(8)]);
+        assert_eq!(describe_log(&log).map(|(_, token_id)| token_id), Some(None));
+    }
+}
@@ -0,0 +1,178 @@
+use alloy::{
+    hex::ToHexExt,
+    primitives::{aliases::U48, Address, Bytes, U256},
+    sol,
+};
+use eyre::OptionExt;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    chain::{ChainClient, TokenStandard},
+    nft::{decode_redeem_calldata, redeem_calldata, simulate, Reverted},
+};
+
+/// How long a holder has to sign and submit a prepared forward request.
+const FORWARD_REQUEST_TTL_SECS: u64 = 10 * 60;
+/// Gas the forwarder passes on to the redeem call. Unused gas is not charged to the relayer.
+const FORWARDED_REDEEM_GAS: u64 = 500_000;
+
+sol! {
+    /// OpenZeppelin's `ERC2771Forwarder`. It checks the EIP-712 signature of a request and calls
+    /// the target with the signer appended to the calldata, which the target reads back as
+    /// `_msgSender()` when it trusts the forwarder.
+    #[sol(rpc)]
+    contract ERC2771Forwarder {
+        struct ForwardRequestData {
+            address from;
+            address to;
+            uint256 value;
+            uint256 gas;
+            uint48 deadline;
+            bytes data;
+            bytes signature;
+        }
+
+        function execute(ForwardRequestData calldata request) external payable;
+        function verify(ForwardRequestData calldata request) external view returns (bool);
+        function nonces(address owner) external view returns (uint256);
+        function eip712Domain() external view returns (bytes1 fields, string name, string version, uint256 chainId, address verifyingContract, bytes32 salt, uint256[] extensions);
+    }
+}
+
+/// The EIP-712 domain a forward request is signed under.
+#[derive(Serialize)]
+pub struct ForwardDomain {
+    pub name: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub verifying_contract: Address,
+}
+
+/// The `ForwardRequest` message a holder signs, with the fields the forwarder's typehash lists.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ForwardRequest {
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+    pub gas: U256,
+    pub nonce: U256,
+    pub deadline: u64,
+    pub data: Bytes,
+}
+
+fn get_forwarder(chain: &ChainClient) -> eyre::Result<Address> {
+    chain
+        .config
+        .trusted_forwarder
+        .ok_or_eyre(format!("No trusted forwarder on chain {}", chain.config.chain_id))
+}
+
+/// The token a forward request redeems, or `None` if it does anything other than redeem one of
+/// `nft_address`'s tokens, since the minter wallet pays for whatever it relays.
+pub fn redeemed_token(
+    standard: TokenStandard,
+    nft_address: Address,
+    request: &ForwardRequest,
+) -> Option<U256> {
+    if request.to != nft_address || request.value != U256::ZERO {
+        return None;
+    }
+    decode_redeem_calldata(standard, request.from, &request.data)
+}
+
+/// The domain and unsigned request for `holder` to redeem `token_id` through the forwarder.
+pub async fn prepare_redeem(
+    chain: &ChainClient,
+    token_id: U256,
+    holder: Address,
+    content: String,
+) -> eyre::Result<(ForwardDomain, ForwardRequest)> {
+    let forwarder = ERC2771Forwarder::new(get_forwarder(chain)?, chain.provider().clone());
+    let domain = forwarder.eip712Domain().call().await?;
+    let nonce = forwarder.nonces(holder).call().await?._0;
+    let deadline = chrono::Utc::now().timestamp() as u64 + FORWARD_REQUEST_TTL_SECS;
+    let request = ForwardRequest {
+        from: holder,
+        to: chain.config.nft_address,
+        value: U256::ZERO,
+        gas: U256::from(FORWARDED_REDEEM_GAS),
+        nonce,
+        deadline,
+        data: redeem_calldata(chain.config.token_standard, holder, token_id, content),
+    };
+    let domain = ForwardDomain {
+        name: domain.name,
+        version: domain.version,
+        chain_id: domain.chainId.to(),
+        verifying_contract: domain.verifyingContract,
+    };
+    Ok((domain, request))
+}
+
+/// Submits a holder-signed forward request from the minter wallet. The forwarder checks the
+/// nonce itself, so the request's `nonce` only has to match what the holder signed.
+pub async fn relay_redeem(
+    chain: &ChainClient,
+    request: ForwardRequest,
+    signature: Bytes,
+) -> eyre::Result<String> {
+    let forwarder = ERC2771Forwarder::new(get_forwarder(chain)?, chain.provider().clone());
+    let request = ERC2771Forwarder::ForwardRequestData {
+        from: request.from,
+        to: request.to,
+        value: request.value,
+        gas: request.gas,
+        deadline: U48::try_from(request.deadline)?,
+        data: request.data,
+        signature,
+    };
+    if !forwarder.verify(request.clone()).call().await?._0 {
+        let reason = "Forward request is expired or not signed by its sender".to_string();
+        return Err(Reverted(reason).into());
+    }
+    let request = forwarder.execute(request).into_transaction_request();
+    simulate(chain, &request).await?;
+    let request = chain.config.gas.apply(chain.provider(), request).await?;
+    let tx_hash = chain.nonces.send(chain.submit_provider(), request).await?;
+
+    log::info!("Relayed forwarded redeem with tx hash: {}", tx_hash);
+    Ok(tx_hash.encode_hex_with_prefix())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_redeems_of_the_nft_contract_are_relayed() {
+        let nft_address = Address::with_last_byte(1);
+        let holder = Address::with_last_byte(2);
+        let token_id = U256::from(7);
+        let request = ForwardRequest {
+            from: holder,
+            to: nft_address,
+            value: U256::ZERO,
+            gas: U256::from(FORWARDED_REDEEM_GAS),
+            nonce: U256::ZERO,
+            deadline: 0,
+            data: redeem_calldata(TokenStandard::Erc721, holder, token_id, "gm".into()),
+        };
+        assert_eq!(redeemed_token(TokenStandard::Erc721, nft_address, &request), Some(token_id));
+
+        let elsewhere = ForwardRequest { to: Address::with_last_byte(3), ..request.clone() };
+        assert_eq!(redeemed_token(TokenStandard::Erc721, nft_address, &elsewhere), None);
+        let paying = ForwardRequest { value: U256::from(1), ..request.clone() };
+        assert_eq!(redeemed_token(TokenStandard::Erc721, nft_address, &paying), None);
+
+        let edition = ForwardRequest {
+            data: redeem_calldata(
+                TokenStandard::Erc1155,
+                Address::with_last_byte(4),
+                token_id,
+                "gm".into(),
+            ),
+            ..request
+        };
+        assert_eq!(redeemed_token(TokenStandard::Erc1155, nft_address, &edition), None);
+    }
+}
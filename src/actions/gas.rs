@@ -0,0 +1,193 @@
+use alloy::{
+    network::TransactionBuilder,
+    primitives::U256,
+    providers::Provider,
+    rpc::types::{BlockNumberOrTag, TransactionRequest},
+};
+
+use super::{chain::chain_var, wallet::WalletProvider};
+
+const DEFAULT_FEE_HISTORY_BLOCKS: u64 = 20;
+const DEFAULT_BASE_FEE_PERCENTILE: u64 = 50;
+/// Headroom over the expected base fee, matching the provider's default of twice the base fee.
+const DEFAULT_BASE_FEE_MULTIPLIER_PERCENT: u128 = 200;
+const DEFAULT_PRIORITY_FEE_PERCENTILE: u64 = 50;
+
+fn parse_chain_var<T: std::str::FromStr>(name: &str, chain_id: u64) -> eyre::Result<Option<T>>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    chain_var(name, chain_id).ok().map(|value| value.parse()).transpose().map_err(Into::into)
+}
+
+/// How EIP-1559 fees are chosen for the minter wallet's transactions. Every field can be set per
+/// chain with a `_<chain_id>` suffix; amounts are in wei.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasStrategy {
+    /// `GAS_FEE_HISTORY_BLOCKS`, how many recent blocks the percentiles are taken over.
+    pub fee_history_blocks: u64,
+    /// `GAS_BASE_FEE_PERCENTILE` of the recent base fees used as the expected base fee.
+    pub base_fee_percentile: u64,
+    /// `GAS_BASE_FEE_MULTIPLIER_PERCENT` applied to the expected base fee in the max fee.
+    pub base_fee_multiplier_percent: u128,
+    /// `GAS_PRIORITY_FEE_PERCENTILE` of recent priority fees paid.
+    pub priority_fee_percentile: u64,
+    /// `GAS_MAX_PRIORITY_FEE_PER_GAS`, a cap on the priority fee.
+    pub max_priority_fee_per_gas: Option<u128>,
+    /// `GAS_MAX_FEE_PER_GAS`, a cap on the total fee per gas, replacements included.
+    pub max_fee_per_gas: Option<u128>,
+}
+
+impl Default for GasStrategy {
+    fn default() -> Self {
+        Self {
+            fee_history_blocks: DEFAULT_FEE_HISTORY_BLOCKS,
+            base_fee_percentile: DEFAULT_BASE_FEE_PERCENTILE,
+            base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
+            priority_fee_percentile: DEFAULT_PRIORITY_FEE_PERCENTILE,
+            max_priority_fee_per_gas: None,
+            max_fee_per_gas: None,
+        }
+    }
+}
+
+fn percentile(values: &[u128], percentile: u64) -> u128 {
+    let mut values = values.to_vec();
+    values.sort_unstable();
+    let index = (values.len().saturating_sub(1) * percentile.min(100) as usize) / 100;
+    values.get(index).copied().unwrap_or_default()
+}
+
+impl GasStrategy {
+    pub fn from_env(chain_id: u64) -> eyre::Result<Self> {
+        let default = Self::default();
+        Ok(Self {
+            fee_history_blocks: parse_chain_var("GAS_FEE_HISTORY_BLOCKS", chain_id)?
+                .unwrap_or(default.fee_history_blocks),
+            base_fee_percentile: parse_chain_var("GAS_BASE_FEE_PERCENTILE", chain_id)?
+                .unwrap_or(default.base_fee_percentile),
+            base_fee_multiplier_percent: parse_chain_var(
+                "GAS_BASE_FEE_MULTIPLIER_PERCENT",
+                chain_id,
+            )?
+            .unwrap_or(default.base_fee_multiplier_percent),
+            priority_fee_percentile: parse_chain_var("GAS_PRIORITY_FEE_PERCENTILE", chain_id)?
+                .unwrap_or(default.priority_fee_percentile),
+            max_priority_fee_per_gas: parse_chain_var("GAS_MAX_PRIORITY_FEE_PER_GAS", chain_id)?,
+            max_fee_per_gas: parse_chain_var("GAS_MAX_FEE_PER_GAS", chain_id)?,
+        })
+    }
+
+    /// Clamps `(max_fee_per_gas, max_priority_fee_per_gas)` to the configured caps.
+    pub fn cap(&self, (max_fee, priority_fee): (u128, u128)) -> (u128, u128) {
+        let max_fee = self.max_fee_per_gas.map_or(max_fee, |cap| max_fee.min(cap));
+        let priority_fee =
+            self.max_priority_fee_per_gas.map_or(priority_fee, |cap| priority_fee.min(cap));
+        (max_fee, priority_fee.min(max_fee))
+    }
+
+    fn fees_from_history(&self, base_fees: &[u128], priority_fees: &[u128]) -> (u128, u128) {
+        let base_fee = percentile(base_fees, self.base_fee_percentile);
+        let priority_fee = percentile(priority_fees, 50);
+        let max_fee = base_fee * self.base_fee_multiplier_percent / 100 + priority_fee;
+        self.cap((max_fee, priority_fee))
+    }
+
+    /// Current `(max_fee_per_gas, max_priority_fee_per_gas)` under this strategy.
+    pub async fn fees(&self, provider: &WalletProvider) -> eyre::Result<(u128, u128)> {
+        let history = provider
+            .get_fee_history(
+                self.fee_history_blocks,
+                BlockNumberOrTag::Latest,
+                &[self.priority_fee_percentile as f64],
+            )
+            .await?;
+        let priority_fees: Vec<u128> = history
+            .reward
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|rewards| rewards.first().copied())
+            .collect();
+        Ok(self.fees_from_history(&history.base_fee_per_gas, &priority_fees))
+    }
+
+    pub async fn apply(
+        &self,
+        provider: &WalletProvider,
+        request: TransactionRequest,
+    ) -> eyre::Result<TransactionRequest> {
+        Ok(with_fees(request, self.fees(provider).await?))
+    }
+}
+
+/// What a transaction would cost at current fees. Fees are in wei per gas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasEstimate {
+    pub gas: u128,
+    /// What gas is going for now, within the strategy's cap.
+    pub fee_per_gas: u128,
+    /// The most the strategy would offer per gas.
+    pub max_fee_per_gas: u128,
+}
+
+impl GasEstimate {
+    /// Expected cost in wei.
+    pub fn cost(&self) -> U256 {
+        U256::from(self.gas) * U256::from(self.fee_per_gas)
+    }
+
+    /// Cost in wei if fees rise to the strategy's max.
+    pub fn max_cost(&self) -> U256 {
+        U256::from(self.gas) * U256::from(self.max_fee_per_gas)
+    }
+}
+
+impl GasStrategy {
+    /// Estimates the gas `request` needs and prices it at the current gas price, bounded by the
+    /// fees this strategy would offer.
+    pub async fn estimate(
+        &self,
+        provider: &WalletProvider,
+        request: &TransactionRequest,
+    ) -> eyre::Result<GasEstimate> {
+        let gas = provider.estimate_gas(request).await? as u128;
+        let (max_fee_per_gas, _) = self.fees(provider).await?;
+        let fee_per_gas = provider.get_gas_price().await?.min(max_fee_per_gas);
+        Ok(GasEstimate { gas, fee_per_gas, max_fee_per_gas })
+    }
+}
+
+pub fn with_fees(
+    request: TransactionRequest,
+    (max_fee, priority_fee): (u128, u128),
+) -> TransactionRequest {
+    request.with_max_fee_per_gas(max_fee).with_max_priority_fee_per_gas(priority_fee)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fees_follow_percentiles_and_caps() {
+        let strategy = GasStrategy { base_fee_percentile: 100, ..Default::default() };
+        let base_fees = [10, 30, 20];
+        let priority_fees = [1, 3, 2];
+        assert_eq!(strategy.fees_from_history(&base_fees, &priority_fees), (62, 2));
+
+        let capped = GasStrategy {
+            max_fee_per_gas: Some(40),
+            max_priority_fee_per_gas: Some(1),
+            ..strategy
+        };
+        assert_eq!(capped.fees_from_history(&base_fees, &priority_fees), (40, 1));
+        assert_eq!(capped.cap((5, 3)), (5, 1));
+    }
+
+    #[test]
+    fn estimate_costs_scale_with_gas() {
+        let estimate = GasEstimate { gas: 21_000, fee_per_gas: 10, max_fee_per_gas: 25 };
+        assert_eq!(estimate.cost(), U256::from(210_000));
+        assert_eq!(estimate.max_cost(), U256::from(525_000));
+    }
+}
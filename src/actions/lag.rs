@@ -0,0 +1,117 @@
+use serde::Serialize;
+
+use super::chain::chain_var;
+use crate::metrics;
+
+const DEFAULT_LAG_ALERT_BLOCKS: u64 = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LagStatus {
+    Lagging,
+    Recovered,
+}
+
+/// Posted to `INDEXER_LAG_ALERT_URL` when the indexer falls behind and again once it catches up.
+#[derive(Debug, Serialize)]
+pub struct LagAlert {
+    pub chain_id: u64,
+    pub status: LagStatus,
+    pub head: u64,
+    pub indexed_block: u64,
+    pub lag: u64,
+}
+
+/// Tracks how far a chain's indexer is behind the chain head, as the `indexer_lag_blocks` gauge,
+/// and alerts when it is more than `INDEXER_LAG_ALERT_BLOCKS` behind on top of the confirmation
+/// depth it holds back on purpose. Both read `<name>_<chain_id>` first.
+pub struct LagMonitor {
+    chain_id: u64,
+    confirmation_depth: u64,
+    threshold: u64,
+    hook_url: Option<String>,
+    lagging: bool,
+}
+
+impl LagMonitor {
+    pub fn from_env(chain_id: u64, confirmation_depth: u64) -> Self {
+        let threshold = chain_var("INDEXER_LAG_ALERT_BLOCKS", chain_id)
+            .ok()
+            .and_then(|blocks| blocks.parse().ok())
+            .unwrap_or(DEFAULT_LAG_ALERT_BLOCKS);
+        Self {
+            chain_id,
+            confirmation_depth,
+            threshold,
+            hook_url: chain_var("INDEXER_LAG_ALERT_URL", chain_id).ok(),
+            lagging: false,
+        }
+    }
+
+    /// Records that the indexer has handled everything up to `indexed_block` while the chain is
+    /// at `head`, alerting when that crosses the threshold in either direction.
+    pub fn observe(&mut self, head: u64, indexed_block: u64) {
+        let lag = head.saturating_sub(indexed_block);
+        let chain_id = self.chain_id.to_string();
+        metrics::set_gauge("indexer_lag_blocks", &[("chain_id", &chain_id)], lag as i64);
+        let lagging = lag.saturating_sub(self.confirmation_depth) > self.threshold;
+        if lagging == self.lagging {
+            return;
+        }
+        self.lagging = lagging;
+        let status = if lagging {
+            log::error!("Indexer on chain {} is {} blocks behind head {}", chain_id, lag, head);
+            LagStatus::Lagging
+        } else {
+            log::info!("Indexer on chain {} caught up to within {} blocks", chain_id, lag);
+            LagStatus::Recovered
+        };
+        metrics::increment(
+            "indexer_lag_alerts_total",
+            &[("chain_id", &chain_id), ("status", if lagging { "lagging" } else { "recovered" })],
+        );
+        let Some(hook_url) = self.hook_url.clone() else {
+            return;
+        };
+        let alert = LagAlert { chain_id: self.chain_id, status, head, indexed_block, lag };
+        // Sent in the background so a slow hook never holds up indexing.
+        tokio::spawn(async move {
+            let sent = async {
+                reqwest::Client::new()
+                    .post(&hook_url)
+                    .json(&alert)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                eyre::Ok(())
+            };
+            if let Err(e) = sent.await {
+                log::error!("Failed to send indexer lag alert: {:?}", e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alerts_once_per_crossing() {
+        let mut monitor = LagMonitor {
+            chain_id: 1,
+            confirmation_depth: 5,
+            threshold: 10,
+            hook_url: None,
+            lagging: false,
+        };
+        monitor.observe(100, 85);
+        assert!(!monitor.lagging);
+        monitor.observe(100, 84);
+        assert!(monitor.lagging);
+        monitor.observe(120, 84);
+        assert!(monitor.lagging);
+        monitor.observe(120, 110);
+        assert!(!monitor.lagging);
+    }
+}
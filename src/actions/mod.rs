@@ -1,2 +1,25 @@
+pub mod anchor;
+pub mod balance;
+pub mod burn;
+pub mod chain;
+pub mod confirmations;
+pub mod deposits;
+pub mod dispatch;
+pub mod ens;
+pub mod erc1155;
+pub mod forwarder;
+pub mod gas;
+pub mod lag;
 pub mod nft;
+pub mod pause;
+pub mod payments;
+pub mod preflight;
+pub mod promotion;
+pub mod rebuild;
+pub mod relay;
+pub mod royalty;
+pub mod rpc;
+pub mod smart_account;
+pub mod top_up;
+pub mod tx_monitor;
 pub mod wallet;
@@ -2,25 +2,63 @@ use std::{str::FromStr, sync::Arc};
 
 use alloy::{
     hex::ToHexExt,
-    primitives::{Address, FixedBytes, Uint},
+    network::TransactionBuilder,
+    primitives::{keccak256, Address, Bytes, FixedBytes, TxHash, Uint, U256},
     providers::{Provider, ProviderBuilder, WsConnect},
-    rpc::types::{BlockNumberOrTag, Filter},
+    rpc::types::{BlockNumberOrTag, Filter, Log, TransactionRequest},
     sol,
-    sol_types::SolEventInterface,
+    sol_types::{SolCall, SolEventInterface, SolInterface},
+    transports::{RpcError, Transport, TransportErrorKind},
 };
-use eyre::OptionExt;
+use chrono_tz::Tz;
+use eyre::{OptionExt, WrapErr};
 use futures_util::stream::StreamExt;
 use serde::Deserialize;
-use tokio::sync::Mutex;
+use tokio::time::{sleep, Duration, Instant};
 use NFT::NFTEvents;
 
 use self::NFT::{NewTokenData, RedeemTweet, Transfer};
 
-use super::wallet::WalletProvider;
+use super::{
+    anchor,
+    chain::{chain_var, ChainClient, ChainConfig, TokenStandard},
+    confirmations::ConfirmationBuffer,
+    dispatch::{CursorTracker, EventDispatcher},
+    erc1155::{self, NFT1155},
+    gas::{with_fees, GasEstimate},
+    lag::LagMonitor,
+    pause,
+    promotion::get_mint_confirmations,
+};
 use crate::{
-    db::{client_db::ClientDB, TeleportDB},
+    access_list,
+    db::{
+        lock::TrackedMutex,
+        marketplace::MarketplaceIndex,
+        storage::{Redemption, Storage, Stores},
+        BlockCursor, ContentLicense, FailedEvent, MentionRule, MintConfirmation, ModerationRecord,
+        ModerationTier, ReputationSignal, TeleportDB,
+    },
+    event_bus::EventBus,
+    events::{Activity, ContractLog, Notification, TokenActivity},
+    kill_switch::{ensure_enabled, SideEffect, SideEffectDisabled},
+    license::{self, LICENSE_CHECK_VERSION},
+    mentions::{self, MentionCheck, MentionsNeedReview, ViolationAction, MENTION_CHECK_VERSION},
+    metadata::TokenMetadata,
+    metadata_refresh::MetadataRefreshers,
+    metrics,
+    notify::Notifier,
     oai,
-    twitter::{builder::TwitterBuilder, tweet::Tweet},
+    quorum::{Quorum, QuorumNeedsReview, QUORUM_REVIEW_VERSION},
+    reports::{self, HeldForReview},
+    reputation::{self, Standing},
+    timezone::{format_local, parse_timezone},
+    twitter::{
+        builder::{TwitterBuilder, TwitterClient},
+        error::TwitterError,
+        tweet::Tweet,
+    },
+    webhooks,
 };
 
 sol!(
@@ -30,224 +68,1819 @@ sol!(
 );
 
 #[derive(Deserialize)]
-struct TweetContent {
-    text: String,
-    media_url: Option<String>,
+pub struct PollContent {
+    pub options: Vec<String>,
+    pub duration_minutes: u32,
+}
+
+/// What a redemption posts: plain text, or JSON with optional media and poll.
+#[derive(Deserialize)]
+pub struct TweetContent {
+    pub text: String,
+    pub media_url: Option<String>,
+    pub poll: Option<PollContent>,
+    /// Who owns the content and how it may be reused, checked against the policy's license rule.
+    #[serde(default)]
+    pub license: Option<ContentLicense>,
+}
+
+impl TweetContent {
+    pub fn parse(content: &str) -> Self {
+        // Plain text content predates the JSON form and is still accepted.
+        serde_json::from_str(content).unwrap_or_else(|_| Self {
+            text: content.to_string(),
+            media_url: None,
+            poll: None,
+            license: None,
+        })
+    }
 }
 
-pub fn get_nft_address() -> eyre::Result<Address> {
-    let nft_address = std::env::var("NFT_ADDRESS")?;
+/// The hash a token's policy is recorded under at mint time.
+pub fn policy_hash(policy: &str) -> String {
+    keccak256(policy).encode_hex_with_prefix()
+}
+
+/// The hash redemption content is pre-approved under; exact bytes, so any edit needs moderating.
+pub fn content_hash(content: &str) -> String {
+    keccak256(content).encode_hex_with_prefix()
+}
+
+/// A redeem event whose policy is not the one its token was minted with. The event is
+/// dead-lettered unposted, since retrying it cannot change the policy.
+#[derive(Debug)]
+pub struct PolicyMismatch(pub String);
+
+impl std::fmt::Display for PolicyMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Redeem event for NFT {} does not carry its minted policy", self.0)
+    }
+}
+
+impl std::error::Error for PolicyMismatch {}
+
+/// A redemption whose tweet was posted but neither recorded nor deleted. Retrying would post it
+/// a second time, so the event is dead-lettered for an admin to reconcile.
+#[derive(Debug)]
+pub struct RedemptionNeedsReview {
+    pub token_id: String,
+    pub tweet_id: String,
+}
+
+impl std::fmt::Display for RedemptionNeedsReview {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "NFT {} was tweeted as {} but the tweet was not recorded",
+            self.token_id, self.tweet_id
+        )
+    }
+}
+
+impl std::error::Error for RedemptionNeedsReview {}
+
+pub fn get_nft_address(chain_id: u64) -> eyre::Result<Address> {
+    let nft_address = chain_var("NFT_ADDRESS", chain_id)?;
     Ok(Address::from_str(&nft_address)?)
 }
 
-pub async fn subscribe_to_nft_events<A: TeleportDB>(
-    db: Arc<Mutex<A>>,
+/// Everything an event handler needs, cloned into each handled log.
+pub struct EventContext<A: TeleportDB> {
+    pub chain_id: u64,
+    pub db: Arc<TrackedMutex<A>>,
+    pub marketplace: Arc<dyn MarketplaceIndex>,
+    pub twitter_builder: TwitterBuilder,
+    pub notifier: Notifier,
+    pub dispatcher: EventDispatcher,
+    pub cursors: CursorTracker,
+    pub event_bus: EventBus,
+    pub token_standard: TokenStandard,
+    pub refreshers: MetadataRefreshers,
+    /// Index events without tweeting, for a read-only instance.
+    pub read_only: bool,
+}
+
+impl<A: TeleportDB> EventContext<A> {
+    pub fn storage(&self) -> Stores<A> {
+        Stores::new(self.db.clone(), self.marketplace.clone())
+    }
+}
+
+impl<A: TeleportDB> Clone for EventContext<A> {
+    fn clone(&self) -> Self {
+        Self {
+            chain_id: self.chain_id,
+            db: self.db.clone(),
+            marketplace: self.marketplace.clone(),
+            twitter_builder: self.twitter_builder.clone(),
+            notifier: self.notifier.clone(),
+            dispatcher: self.dispatcher.clone(),
+            cursors: self.cursors.clone(),
+            event_bus: self.event_bus.clone(),
+            token_standard: self.token_standard,
+            refreshers: self.refreshers.clone(),
+            read_only: self.read_only,
+        }
+    }
+}
+
+const RECONNECT_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(60);
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_INDEXER_CONCURRENCY: u32 = 8;
+
+/// How many event handlers may run at once, from `INDEXER_CONCURRENCY`.
+pub fn get_indexer_concurrency() -> eyre::Result<u32> {
+    match std::env::var("INDEXER_CONCURRENCY") {
+        Ok(concurrency) => match concurrency.parse() {
+            Ok(concurrency) => Ok(concurrency),
+            Err(_) => eyre::bail!("Invalid INDEXER_CONCURRENCY {}", concurrency),
+        },
+        Err(_) => Ok(DEFAULT_INDEXER_CONCURRENCY),
+    }
+}
+
+/// How the indexer learns about new logs, from `INDEXER_MODE` (`ws` or `poll`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexerMode {
+    /// `eth_subscribe` over the WebSocket RPC.
+    WebSocket,
+    /// `eth_getLogs` over the HTTP RPC every `interval`.
+    Poll { interval: Duration },
+}
+
+impl IndexerMode {
+    pub fn from_env() -> eyre::Result<Self> {
+        match std::env::var("INDEXER_MODE").as_deref() {
+            Err(_) | Ok("ws") => Ok(Self::WebSocket),
+            Ok("poll") => {
+                let interval = match std::env::var("POLL_INTERVAL_SECS") {
+                    Ok(secs) => match secs.parse() {
+                        Ok(secs) => Duration::from_secs(secs),
+                        Err(_) => eyre::bail!("Invalid POLL_INTERVAL_SECS {}", secs),
+                    },
+                    Err(_) => DEFAULT_POLL_INTERVAL,
+                };
+                Ok(Self::Poll { interval })
+            }
+            Ok(mode) => eyre::bail!("Unknown INDEXER_MODE {}", mode),
+        }
+    }
+}
+
+pub struct IndexerConfig {
+    pub mode: IndexerMode,
+    /// From [`get_confirmation_depth`].
+    pub confirmation_depth: u64,
+    /// From [`get_indexer_concurrency`].
+    pub concurrency: u32,
+    pub chain: ChainConfig,
+    pub marketplace: Arc<dyn MarketplaceIndex>,
+    pub event_bus: EventBus,
+    pub refreshers: MetadataRefreshers,
+    pub read_only: bool,
+}
+
+/// Keeps the NFT indexer alive, reconnecting with exponential backoff whenever the WebSocket
+/// drops or polling fails. Each restart backfills from the persisted block cursor, so no events
+/// are skipped across reconnects.
+pub async fn run_nft_indexer<A: TeleportDB>(
+    db: Arc<TrackedMutex<A>>,
     twitter_builder: TwitterBuilder,
-    ws_rpc_url: String,
-    database_url: String,
+    notifier: Notifier,
+    config: IndexerConfig,
+) {
+    let ctx = EventContext {
+        chain_id: config.chain.chain_id,
+        db,
+        marketplace: config.marketplace.clone(),
+        twitter_builder,
+        notifier,
+        dispatcher: EventDispatcher::new(config.concurrency),
+        cursors: CursorTracker::default(),
+        event_bus: config.event_bus.clone(),
+        token_standard: config.chain.token_standard,
+        refreshers: config.refreshers.clone(),
+        read_only: config.read_only,
+    };
+    tokio::spawn(retry_failed_events(ctx.clone()));
+    let mut lag = LagMonitor::from_env(ctx.chain_id, config.confirmation_depth);
+    let mut backoff = RECONNECT_INITIAL_BACKOFF;
+    let endpoints = match config.mode {
+        IndexerMode::WebSocket => &config.chain.ws_rpc_urls,
+        IndexerMode::Poll { .. } => &config.chain.rpc_urls,
+    };
+    let mut endpoint = 0;
+    let depth = config.confirmation_depth;
+    loop {
+        let connected_at = Instant::now();
+        let url = &endpoints[endpoint];
+        let result = match config.mode {
+            IndexerMode::WebSocket => {
+                subscribe_to_nft_events(&ctx, &config.chain, url, depth, &mut lag).await
+            }
+            IndexerMode::Poll { interval } => {
+                poll_nft_events(&ctx, &config.chain, url, interval, depth, &mut lag).await
+            }
+        };
+        match result {
+            Ok(()) => log::warn!("NFT event stream ended on chain {}", ctx.chain_id),
+            Err(e) => {
+                log::error!("NFT event indexing failed on chain {}: {:?}", ctx.chain_id, e)
+            }
+        }
+        // A connection that stayed up for a while starts the backoff over, back on the primary
+        // endpoint; one that failed quickly moves on to the next fallback.
+        if connected_at.elapsed() > RECONNECT_MAX_BACKOFF {
+            backoff = RECONNECT_INITIAL_BACKOFF;
+            endpoint = 0;
+        } else {
+            endpoint = (endpoint + 1) % endpoints.len();
+            if endpoint != 0 {
+                log::warn!("Failing over to RPC endpoint #{} on chain {}", endpoint, ctx.chain_id);
+            }
+        }
+        log::info!("Reconnecting to NFT events in {:?}", backoff);
+        sleep(backoff).await;
+        backoff = (backoff * 2).min(RECONNECT_MAX_BACKOFF);
+    }
+}
+
+/// Polling fallback for RPC providers without a stable WebSocket endpoint. Only confirmed blocks
+/// are fetched, so reorgs shallower than the confirmation depth never reach the handlers.
+async fn poll_nft_events<A: TeleportDB>(
+    ctx: &EventContext<A>,
+    chain: &ChainConfig,
+    rpc_url: &str,
+    interval: Duration,
+    confirmation_depth: u64,
+    lag: &mut LagMonitor,
+) -> eyre::Result<()> {
+    let provider = ProviderBuilder::new().on_http(rpc_url.parse()?);
+
+    log::info!(
+        "Polling events for contracts at: {:?} on chain {}",
+        chain.indexed_addresses,
+        chain.chain_id
+    );
+
+    loop {
+        let head = provider.get_block_number().await?;
+        let confirmed_head = head.saturating_sub(confirmation_depth);
+        let last_processed = ctx.db.lock().await.get_last_processed_block(ctx.chain_id);
+        if let Ok(cursor) = &last_processed {
+            lag.observe(head, cursor.block_number);
+        }
+        match last_processed {
+            Ok(cursor) if cursor < BlockCursor::end_of_block(confirmed_head) => {
+                backfill_nft_events(
+                    &provider,
+                    ctx,
+                    &chain.indexed_addresses,
+                    cursor,
+                    confirmed_head,
+                )
+                .await?;
+            }
+            Ok(_) => {}
+            Err(_) => {
+                let cursor = BlockCursor::end_of_block(confirmed_head);
+                ctx.db.lock().await.set_last_processed_block(
+                    ctx.chain_id,
+                    cursor.block_number,
+                    cursor.log_index,
+                )?;
+            }
+        }
+        sleep(interval).await;
+    }
+}
+
+async fn subscribe_to_nft_events<A: TeleportDB>(
+    ctx: &EventContext<A>,
+    chain: &ChainConfig,
+    ws_rpc_url: &str,
+    confirmation_depth: u64,
+    lag: &mut LagMonitor,
 ) -> eyre::Result<()> {
     let ws = WsConnect::new(ws_rpc_url);
     let provider = ProviderBuilder::new().on_ws(ws).await?;
-    let nft_address = get_nft_address()?;
-
-    let filter = Filter::new().address(nft_address).from_block(BlockNumberOrTag::Latest);
+    let addresses = chain.indexed_addresses.clone();
 
-    log::info!("Subscribed to events for contract at: {}", nft_address.to_string());
+    let filter = Filter::new().address(addresses.clone()).from_block(BlockNumberOrTag::Latest);
 
+    // Subscribe before backfilling so nothing emitted during the backfill is missed; logs the
+    // backfill already covered are skipped below.
     let sub = provider.subscribe_logs(&filter).await?;
     let mut stream = sub.into_stream();
 
-    let client_db = ClientDB::new(database_url);
+    log::info!(
+        "Subscribed to events for contracts at: {:?} on chain {}",
+        addresses,
+        chain.chain_id
+    );
+
+    let mut confirmations = ConfirmationBuffer::new(confirmation_depth);
+
+    let head = provider.get_block_number().await?;
+    let confirmed_head = head.saturating_sub(confirmation_depth);
+    let last_processed = ctx.db.lock().await.get_last_processed_block(ctx.chain_id);
+    let cursor = match last_processed {
+        Ok(cursor) => cursor,
+        Err(_) => {
+            let cursor = BlockCursor::end_of_block(confirmed_head);
+            let mut db = ctx.db.lock().await;
+            db.set_last_processed_block(ctx.chain_id, cursor.block_number, cursor.log_index)?;
+            cursor
+        }
+    };
+    if cursor < BlockCursor::end_of_block(confirmed_head) {
+        backfill_nft_events(&provider, ctx, &addresses, cursor, confirmed_head).await?;
+    }
 
-    while let Some(log) = stream.next().await {
-        if let Ok(event) = NFTEvents::decode_raw_log(log.topics(), &log.data().data, true) {
-            let db = db.clone();
-            let twitter_builder = twitter_builder.clone();
-            let client_db = client_db.clone();
+    // Logs above the confirmed head predate the subscription but are not deep enough to handle
+    // yet, so they start out in the confirmation buffer.
+    let unconfirmed_from = (confirmed_head + 1).max(cursor.block_number);
+    if unconfirmed_from <= head {
+        let filter =
+            Filter::new().address(addresses.clone()).from_block(unconfirmed_from).to_block(head);
+        for log in provider.get_logs(&filter).await? {
+            if log_cursor(&log).is_some_and(|log_cursor| log_cursor > cursor) {
+                confirmations.push(log);
+            }
+        }
+    }
+
+    let mut latest_block = head;
+    // Everything up to here has been handed to the handlers; the cursor alone would look stuck
+    // on a quiet chain, since it only moves with logs.
+    let mut indexed_block = confirmed_head;
+    let mut head_poll = tokio::time::interval(HEAD_POLL_INTERVAL);
+    loop {
+        let log = tokio::select! {
+            log = stream.next() => match log {
+                Some(log) => log,
+                None => break,
+            },
+            _ = head_poll.tick() => {
+                match provider.get_block_number().await {
+                    Ok(block_number) => {
+                        latest_block = latest_block.max(block_number);
+                        lag.observe(block_number, indexed_block);
+                    }
+                    Err(e) => log::error!("Failed to fetch block number: {:?}", e),
+                }
+                release_confirmed(ctx, &mut confirmations, latest_block).await;
+                indexed_block = latest_block.saturating_sub(confirmation_depth);
+                continue;
+            }
+        };
+        let Some(block_number) = log.block_number else {
+            continue;
+        };
+        if block_number <= head && !log.removed {
+            continue;
+        }
+        latest_block = latest_block.max(block_number);
+        if let Some(removed) = confirmations.push(log) {
+            let ctx = ctx.clone();
             tokio::spawn(async move {
-                if let Err(e) =
-                    handle_event(db, client_db, twitter_builder, log.transaction_hash, event).await
-                {
-                    log::error!("Error handling event: {:?}", e);
+                if let Err(e) = rollback_log(&ctx, removed).await {
+                    log::error!("Error rolling back removed log: {:?}", e);
                 }
             });
         }
+        release_confirmed(ctx, &mut confirmations, latest_block).await;
+        indexed_block = latest_block.saturating_sub(confirmation_depth);
     }
 
     Ok(())
 }
 
-async fn handle_event<A: TeleportDB>(
-    db: Arc<Mutex<A>>,
-    client_db: ClientDB,
-    twitter_builder: TwitterBuilder,
-    tx_hash: Option<FixedBytes<32>>,
+const HEAD_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Number of blocks a log must be buried under before it is handled, from `CONFIRMATION_DEPTH`.
+pub fn get_confirmation_depth() -> eyre::Result<u64> {
+    match std::env::var("CONFIRMATION_DEPTH") {
+        Ok(depth) => match depth.parse() {
+            Ok(depth) => Ok(depth),
+            Err(_) => eyre::bail!("Invalid CONFIRMATION_DEPTH {}", depth),
+        },
+        Err(_) => Ok(0),
+    }
+}
+
+async fn release_confirmed<A: TeleportDB>(
+    ctx: &EventContext<A>,
+    confirmations: &mut ConfirmationBuffer,
+    head: u64,
+) {
+    for log in confirmations.drain_confirmed(head) {
+        dispatch_log(ctx, log).await;
+    }
+}
+
+fn event_token_id(event: &NFTEvents) -> Option<U256> {
+    match event {
+        NFTEvents::RedeemTweet(redeem) => Some(redeem.tokenId),
+        NFTEvents::NewTokenData(new_token_data) => Some(new_token_data.tokenId),
+        NFTEvents::Transfer(transfer) => Some(transfer.tokenId),
+        _ => None,
+    }
+}
+
+/// The contract event name of a log and, when it concerns a single token, that token's id.
+fn describe_log(standard: TokenStandard, log: &Log) -> Option<(&'static str, Option<U256>)> {
+    if standard == TokenStandard::Erc1155 {
+        if let Some(description) = erc1155::describe_log(log) {
+            return Some(description);
+        }
+    }
+    let event = NFTEvents::decode_raw_log(log.topics(), &log.data().data, true).ok()?;
+    Some((event_name(&event), event_token_id(&event)))
+}
+
+/// Decodes a log into the events the handlers act on. An edition contract's transfers come out
+/// as one ERC-721 style `Transfer` per token id.
+pub(super) fn decode_log(standard: TokenStandard, log: &Log) -> Vec<NFTEvents> {
+    match standard {
+        TokenStandard::Erc721 => {
+            NFTEvents::decode_raw_log(log.topics(), &log.data().data, true).into_iter().collect()
+        }
+        TokenStandard::Erc1155 => erc1155::decode_log(log),
+    }
+}
+
+/// Events for the same token are handled in order; everything else may run in parallel.
+fn ordering_key(standard: TokenStandard, log: &Log) -> String {
+    let token_id = describe_log(standard, log).and_then(|(_, token_id)| token_id);
+    match token_id {
+        Some(token_id) => format!("token:{}", token_id),
+        None => format!("tx:{:?}", log.transaction_hash),
+    }
+}
+
+async fn dispatch_log<A: TeleportDB>(ctx: &EventContext<A>, log: Log) {
+    if let Some(cursor) = log_cursor(&log) {
+        ctx.cursors.start(cursor);
+    }
+    let handler_ctx = ctx.clone();
+    ctx.dispatcher
+        .dispatch(ordering_key(ctx.token_standard, &log), async move {
+            handle_log(&handler_ctx, log).await;
+        })
+        .await;
+}
+
+/// Undoes the database effects of a log that a reorg removed after it had already been handled.
+async fn rollback_log<A: TeleportDB>(ctx: &EventContext<A>, log: Log) -> eyre::Result<()> {
+    for event in decode_log(ctx.token_standard, &log) {
+        rollback_event(ctx, &log, event).await?;
+    }
+    Ok(())
+}
+
+async fn rollback_event<A: TeleportDB>(
+    ctx: &EventContext<A>,
+    log: &Log,
     event: NFTEvents,
 ) -> eyre::Result<()> {
     match event {
-        NFTEvents::RedeemTweet(redeem) => {
-            if let Err(e) = handle_redeem_tweet(db, client_db, twitter_builder, redeem).await {
-                log::error!("Error handling RedeemTweet event: {:?}", e);
-            }
-        }
         NFTEvents::NewTokenData(new_token_data) => {
-            if let Err(e) = handle_new_token_data(db, client_db, tx_hash, new_token_data).await {
-                log::error!("Error handling NewTokenData event: {:?}", e);
+            let tx_hash = log
+                .transaction_hash
+                .ok_or_eyre("Transaction hash is missing")?
+                .encode_hex_with_prefix();
+            if ctx.db.lock().await.remove_mint_confirmation(tx_hash.clone())? {
+                // Still pending; the mint is promoted if the transaction lands again.
+                log::warn!(
+                    "Reorg removed unconfirmed mint of NFT {} in tx {}",
+                    new_token_data.tokenId,
+                    tx_hash
+                );
+            } else {
+                log::error!(
+                    "Reorg removed an already handled NewTokenData event in tx {}, manual review \
+                     required",
+                    tx_hash
+                );
             }
         }
         NFTEvents::Transfer(transfer) => {
-            if let Err(e) = handle_transfer(client_db, transfer).await {
-                log::error!("Error handling Transfer event: {:?}", e);
+            let token_id = transfer.tokenId.to_string();
+            // Back to the previous holder, which delists a rolled back mint.
+            ctx.storage().transfer_token(ctx.chain_id, token_id.clone(), transfer.from).await?;
+            log::warn!("Rolled back reorged transfer of NFT {} on {}", token_id, log.address());
+        }
+        event => {
+            log::error!(
+                "Reorg removed an already handled {} event in tx {:?}, manual review required",
+                event_name(&event),
+                log.transaction_hash
+            );
+        }
+    }
+    Ok(())
+}
+
+fn event_name(event: &NFTEvents) -> &'static str {
+    match event {
+        NFTEvents::RedeemTweet(_) => "RedeemTweet",
+        NFTEvents::RedeemLike(_) => "RedeemLike",
+        NFTEvents::NewTokenData(_) => "NewTokenData",
+        NFTEvents::Transfer(_) => "Transfer",
+        NFTEvents::Approval(_) => "Approval",
+        NFTEvents::ApprovalForAll(_) => "ApprovalForAll",
+        NFTEvents::OwnershipTransferred(_) => "OwnershipTransferred",
+    }
+}
+
+pub(super) const BACKFILL_CHUNK_SIZE: u64 = 2000;
+
+/// Replays the contract's logs after `cursor` up to and including `to_block` through the live
+/// handler path, marking each chunk fully processed once it is done.
+async fn backfill_nft_events<A: TeleportDB, P: Provider<T>, T: Transport + Clone>(
+    provider: &P,
+    ctx: &EventContext<A>,
+    addresses: &[Address],
+    cursor: BlockCursor,
+    to_block: u64,
+) -> eyre::Result<()> {
+    log::info!("Backfilling NFT events from {:?} to block {}", cursor, to_block);
+    let mut chunk_start = cursor.block_number;
+    while chunk_start <= to_block {
+        let chunk_end = (chunk_start + BACKFILL_CHUNK_SIZE - 1).min(to_block);
+        let filter =
+            Filter::new().address(addresses.to_vec()).from_block(chunk_start).to_block(chunk_end);
+        let logs = provider.get_logs(&filter).await?;
+        for log in logs {
+            if log_cursor(&log).is_some_and(|log_cursor| log_cursor > cursor) {
+                dispatch_log(ctx, log).await;
             }
         }
-        _ => {}
+        // The chunk only counts as processed once all of its handlers are done.
+        ctx.dispatcher.wait_idle().await;
+        let end = BlockCursor::end_of_block(chunk_end);
+        let mut db = ctx.db.lock().await;
+        db.set_last_processed_block(ctx.chain_id, end.block_number, end.log_index)?;
+        chunk_start = chunk_end + 1;
+    }
+    log::info!("Backfill complete at block {}", to_block);
+    Ok(())
+}
+
+fn log_cursor(log: &Log) -> Option<BlockCursor> {
+    Some(BlockCursor { block_number: log.block_number?, log_index: log.log_index? })
+}
+
+fn contract_log(chain_id: u64, standard: TokenStandard, log: &Log) -> Option<ContractLog> {
+    let (name, token_id) = describe_log(standard, log)?;
+    Some(ContractLog {
+        chain_id,
+        contract: log.address().to_string(),
+        name: name.to_string(),
+        token_id: token_id.map(|token_id| token_id.to_string()),
+        tx_hash: log.transaction_hash?.encode_hex_with_prefix(),
+        block_number: log.block_number?,
+        log_index: log.log_index?,
+        log: serde_json::to_value(log).ok()?,
+    })
+}
+
+/// Handles a single contract log and then advances the persisted block cursor past it, once every
+/// log dispatched before it is handled too. Logs are marked processed before their handler runs,
+/// so a redelivered log (reconnect, backfill overlap) can never post a tweet or insert a
+/// `RedeemedIndex` row twice.
+async fn handle_log<A: TeleportDB>(ctx: &EventContext<A>, log: Log) {
+    let cursor = log_cursor(&log);
+    // Published before the cursor moves past the log, so a crash re-publishes it on backfill. One
+    // the bus never acknowledged is queued, so the cursor moving on does not lose it.
+    if let Err(e) = publish_log(ctx, &log).await {
+        log::error!("Failed to publish log {:?}, queueing for retry: {:?}", cursor, e);
+        if let Err(e) = queue_failed_publish(ctx, &log, 0, &e).await {
+            log::error!("Failed to queue log {:?} for publishing: {:?}", cursor, e);
+        }
+    }
+    let first_delivery = match (log.transaction_hash, log.log_index) {
+        (Some(tx_hash), Some(log_index)) => {
+            let tx_hash = tx_hash.encode_hex_with_prefix();
+            ctx.db.lock().await.mark_event_processed(ctx.chain_id, tx_hash, log_index)
+        }
+        _ => Ok(true),
     };
+    match first_delivery {
+        Ok(true) => {
+            if let Err(e) = handle_decoded_log(ctx, &log).await {
+                log::error!("Error handling log {:?}, queueing for retry: {:?}", cursor, e);
+                if let Err(e) = queue_failed_log(ctx, &log, 0, &e).await {
+                    log::error!("Failed to queue log {:?} for retry: {:?}", cursor, e);
+                }
+            }
+        }
+        Ok(false) => log::info!("Skipping already processed log {:?}", cursor),
+        Err(e) => log::error!("Failed to record processed log {:?}: {:?}", cursor, e),
+    }
+    // Handlers finish out of order, so the cursor only moves up to the logs before the earliest
+    // one still running.
+    if let Some(cursor) = cursor.and_then(|cursor| ctx.cursors.finish(cursor)) {
+        let mut db = ctx.db.lock().await;
+        if let Err(e) =
+            db.set_last_processed_block(ctx.chain_id, cursor.block_number, cursor.log_index)
+        {
+            log::error!("Failed to update last processed block: {:?}", e);
+        }
+    }
+}
+
+async fn handle_decoded_log<A: TeleportDB>(ctx: &EventContext<A>, log: &Log) -> eyre::Result<()> {
+    if let Some(paused) = pause::decode_pause(log) {
+        return pause::record_pause(&ctx.db, ctx.chain_id, log.address(), paused).await;
+    }
+    for event in decode_log(ctx.token_standard, log) {
+        count_event(ctx.chain_id, event_name(&event), "decoded");
+        // Mints and redemptions change what marketplaces should show for the token.
+        let refreshed_token = match &event {
+            NFTEvents::NewTokenData(_) | NFTEvents::RedeemTweet(_) => event_token_id(&event),
+            _ => None,
+        };
+        handle_event(
+            ctx.chain_id,
+            ctx.token_standard,
+            log.address(),
+            ctx.db.clone(),
+            ctx.marketplace.clone(),
+            ctx.twitter_builder.clone(),
+            ctx.notifier.clone(),
+            log.transaction_hash,
+            event,
+            ctx.read_only,
+        )
+        .await?;
+        // The full instance indexing the same events refreshes them already.
+        if let Some(token_id) = refreshed_token.filter(|_| !ctx.read_only) {
+            let refreshers = ctx.refreshers.clone();
+            let (chain_id, contract) = (ctx.chain_id, log.address());
+            tokio::spawn(async move { refreshers.refresh(chain_id, contract, token_id).await });
+        }
+    }
     Ok(())
 }
 
+const RETRY_POLL_INTERVAL: Duration = Duration::from_secs(10);
+const RETRY_INITIAL_BACKOFF: Duration = Duration::from_secs(30);
+const RETRY_MAX_BACKOFF: Duration = Duration::from_secs(3600);
+const MAX_RETRY_ATTEMPTS: u32 = 8;
+
+/// Delay before the next retry of an event that has failed `attempts` times.
+fn retry_delay(attempts: u32) -> Duration {
+    RETRY_INITIAL_BACKOFF
+        .saturating_mul(1 << attempts.saturating_sub(1).min(16))
+        .min(RETRY_MAX_BACKOFF)
+}
+
+async fn publish_log<A: TeleportDB>(ctx: &EventContext<A>, log: &Log) -> eyre::Result<()> {
+    match contract_log(ctx.chain_id, ctx.token_standard, log) {
+        Some(contract_log) => ctx.event_bus.publish(contract_log).await,
+        None => Ok(()),
+    }
+}
+
+/// Prefixes the retry queue id of a log the event bus did not acknowledge, which keeps it apart
+/// from a failure of the same log's handlers, so retrying it only publishes it again.
+const FAILED_PUBLISH_PREFIX: &str = "publish:";
+
+pub(super) fn failed_event_id(log: &Log) -> String {
+    format!(
+        "{}:{}",
+        log.transaction_hash.unwrap_or_default().encode_hex_with_prefix(),
+        log.log_index.unwrap_or_default()
+    )
+}
+
+/// Puts a log into the persistent retry queue after its `previous_attempts + 1`th failure,
+/// dead-lettering it once it has used up `MAX_RETRY_ATTEMPTS`.
+async fn queue_failed_log<A: TeleportDB>(
+    ctx: &EventContext<A>,
+    log: &Log,
+    previous_attempts: u32,
+    error: &eyre::Report,
+) -> eyre::Result<()> {
+    if error.chain().any(|e| e.is::<SideEffectDisabled>()) {
+        // Waiting on a kill switch is not a failure, so it never uses up attempts.
+        let event = FailedEvent {
+            chain_id: ctx.chain_id,
+            log: serde_json::to_string(log)?,
+            attempts: previous_attempts,
+            next_attempt_at: Some(
+                chrono::Utc::now().timestamp() + RETRY_INITIAL_BACKOFF.as_secs() as i64,
+            ),
+            last_error: format!("{:?}", error),
+        };
+        return ctx.db.lock().await.upsert_failed_event(failed_event_id(log), event);
+    }
+    let attempts = previous_attempts + 1;
+    let next_attempt_at = if error.chain().any(|e| e.is::<HeldForReview>()) {
+        // Held redemptions wait for an admin to release the creator and replay them.
+        log::warn!("Dead-lettering log {} held for review", failed_event_id(log));
+        None
+    } else if error.chain().any(|e| e.is::<MentionsNeedReview>()) {
+        // Likewise, until an admin approves its mentions.
+        log::warn!("Dead-lettering log {} held for its mentions", failed_event_id(log));
+        None
+    } else if error.chain().any(|e| e.is::<QuorumNeedsReview>()) {
+        // Or until an admin settles what its moderators disagreed on.
+        log::warn!("Dead-lettering log {} its moderators split on", failed_event_id(log));
+        None
+    } else if error.chain().any(|e| e.is::<PolicyMismatch>()) {
+        log::error!("Dead-lettering log {} with a mismatched policy", failed_event_id(log));
+        None
+    } else if error.downcast_ref::<RedemptionNeedsReview>().is_some() {
+        log::error!("Dead-lettering log {} with an unrecorded tweet", failed_event_id(log));
+        None
+    } else if attempts >= MAX_RETRY_ATTEMPTS {
+        log::error!("Dead-lettering log {} after {} attempts", failed_event_id(log), attempts);
+        None
+    } else {
+        Some(chrono::Utc::now().timestamp() + retry_delay(attempts).as_secs() as i64)
+    };
+    let event = FailedEvent {
+        chain_id: ctx.chain_id,
+        log: serde_json::to_string(log)?,
+        attempts,
+        next_attempt_at,
+        last_error: format!("{:?}", error),
+    };
+    ctx.db.lock().await.upsert_failed_event(failed_event_id(log), event)
+}
+
+/// Puts a log the event bus did not acknowledge into the retry queue, dead-lettering it once it
+/// has used up `MAX_RETRY_ATTEMPTS`. The stream drops copies by message id, so publishing again
+/// is safe even if the ack was only lost.
+async fn queue_failed_publish<A: TeleportDB>(
+    ctx: &EventContext<A>,
+    log: &Log,
+    previous_attempts: u32,
+    error: &eyre::Report,
+) -> eyre::Result<()> {
+    let event_id = format!("{}{}", FAILED_PUBLISH_PREFIX, failed_event_id(log));
+    let attempts = previous_attempts + 1;
+    let next_attempt_at = if attempts >= MAX_RETRY_ATTEMPTS {
+        log::error!("Dead-lettering publish of {} after {} attempts", event_id, attempts);
+        None
+    } else {
+        Some(chrono::Utc::now().timestamp() + retry_delay(attempts).as_secs() as i64)
+    };
+    let event = FailedEvent {
+        chain_id: ctx.chain_id,
+        log: serde_json::to_string(log)?,
+        attempts,
+        next_attempt_at,
+        last_error: format!("{:?}", error),
+    };
+    ctx.db.lock().await.upsert_failed_event(event_id, event)
+}
+
+/// Re-runs queued failed logs once their backoff has elapsed. Retries go straight to the
+/// handlers, since the logs are already marked processed. Queued publishes are only published
+/// again.
+async fn retry_failed_events<A: TeleportDB>(ctx: EventContext<A>) {
+    let mut interval = tokio::time::interval(RETRY_POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        let now = chrono::Utc::now().timestamp();
+        let due = ctx.db.lock().await.get_due_failed_events(ctx.chain_id, now);
+        let due = match due {
+            Ok(due) => due,
+            Err(e) => {
+                log::error!("Failed to read retry queue: {:?}", e);
+                continue;
+            }
+        };
+        for (event_id, event) in due {
+            let result = match serde_json::from_str::<Log>(&event.log) {
+                Ok(log) if event_id.starts_with(FAILED_PUBLISH_PREFIX) => {
+                    match publish_log(&ctx, &log).await {
+                        Ok(()) => ctx.db.lock().await.remove_failed_event(event_id.clone()),
+                        Err(e) => queue_failed_publish(&ctx, &log, event.attempts, &e).await,
+                    }
+                }
+                Ok(log) => match handle_decoded_log(&ctx, &log).await {
+                    Ok(()) => ctx.db.lock().await.remove_failed_event(event_id.clone()),
+                    Err(e) => {
+                        log::warn!("Retry {} of log {} failed: {:?}", event.attempts, event_id, e);
+                        queue_failed_log(&ctx, &log, event.attempts, &e).await
+                    }
+                },
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = result {
+                log::error!("Failed to process retry of log {}: {:?}", event_id, e);
+            }
+        }
+    }
+}
+
+/// Counts an event in `indexer_events_total` by type and `outcome`: decoded from a log, then
+/// handled or failed, or skipped as unsafe by moderation.
+fn count_event(chain_id: u64, event: &str, outcome: &str) {
+    metrics::increment(
+        "indexer_events_total",
+        &[("chain_id", &chain_id.to_string()), ("event", event), ("outcome", outcome)],
+    );
+}
+
+/// Runs an event's handler, recording its outcome and latency by event type. Retries count
+/// again, so failures show how often attempts fail rather than how many events were lost.
+async fn handle_event<A: TeleportDB>(
+    chain_id: u64,
+    standard: TokenStandard,
+    contract: Address,
+    db: Arc<TrackedMutex<A>>,
+    marketplace: Arc<dyn MarketplaceIndex>,
+    twitter_builder: TwitterBuilder,
+    notifier: Notifier,
+    tx_hash: Option<FixedBytes<32>>,
+    event: NFTEvents,
+    read_only: bool,
+) -> eyre::Result<()> {
+    let name = event_name(&event);
+    let started = Instant::now();
+    let result = dispatch_event(
+        chain_id,
+        standard,
+        contract,
+        db,
+        marketplace,
+        twitter_builder,
+        notifier,
+        tx_hash,
+        event,
+        read_only,
+    )
+    .await;
+    metrics::observe(
+        "indexer_event_duration_seconds",
+        &[("chain_id", &chain_id.to_string()), ("event", name)],
+        started.elapsed().as_secs_f64(),
+    );
+    count_event(chain_id, name, if result.is_ok() { "handled" } else { "failed" });
+    result
+}
+
+async fn dispatch_event<A: TeleportDB>(
+    chain_id: u64,
+    standard: TokenStandard,
+    contract: Address,
+    db: Arc<TrackedMutex<A>>,
+    marketplace: Arc<dyn MarketplaceIndex>,
+    twitter_builder: TwitterBuilder,
+    notifier: Notifier,
+    tx_hash: Option<FixedBytes<32>>,
+    event: NFTEvents,
+    read_only: bool,
+) -> eyre::Result<()> {
+    match event {
+        NFTEvents::RedeemTweet(redeem) => handle_redeem_tweet(
+            chain_id,
+            standard,
+            contract,
+            db,
+            marketplace,
+            twitter_builder,
+            redeem,
+            read_only,
+        )
+        .await
+        .wrap_err_with(|| format!("Error handling RedeemTweet event from {}", contract)),
+        NFTEvents::NewTokenData(new_token_data) => {
+            handle_new_token_data(chain_id, contract, db, marketplace, tx_hash, new_token_data)
+                .await
+                .wrap_err_with(|| format!("Error handling NewTokenData event from {}", contract))
+        }
+        NFTEvents::Transfer(transfer) => handle_transfer(
+            chain_id,
+            standard,
+            db,
+            marketplace,
+            twitter_builder,
+            notifier,
+            transfer,
+            read_only,
+        )
+        .await
+        .wrap_err_with(|| format!("Error handling Transfer event from {}", contract)),
+        NFTEvents::OwnershipTransferred(transfer) => {
+            pause::record_owner(&db, chain_id, contract, transfer.newOwner).await
+        }
+        _ => Ok(()),
+    }
+}
+
 async fn handle_redeem_tweet<A: TeleportDB>(
-    db: Arc<Mutex<A>>,
-    client_db: ClientDB,
+    chain_id: u64,
+    standard: TokenStandard,
+    contract: Address,
+    db: Arc<TrackedMutex<A>>,
+    marketplace: Arc<dyn MarketplaceIndex>,
     twitter_builder: TwitterBuilder,
     redeem: RedeemTweet,
+    read_only: bool,
 ) -> eyre::Result<()> {
-    let safe = oai::is_tweet_safe(&redeem.content, &redeem.policy).await;
-    if safe {
+    let creator = redeem.x_id.to_string();
+    let token_id = redeem.tokenId.to_string();
+    let db_lock = db.lock().await;
+    match db_lock.get_policy_hash(chain_id, token_id.clone())? {
+        Some(minted) if minted != policy_hash(&redeem.policy) => {
+            metrics::increment("policy_mismatches_total", &[("chain_id", &chain_id.to_string())]);
+            return Err(PolicyMismatch(token_id).into());
+        }
+        Some(_) => {}
+        None => log::warn!("NFT {} has no recorded policy, trusting its redeem event", token_id),
+    }
+    // Tokens are judged by the policy as it stood when they were minted, not as it stands now.
+    let snapshot = db_lock.get_policy_snapshot(chain_id, token_id.clone())?;
+    let standing = reputation::standing(&*db_lock, creator.clone())?;
+    if reports::is_held(db_lock.get_creator_strikes(creator.clone())?) ||
+        standing == Standing::Restricted
+    {
+        return Err(HeldForReview(creator).into());
+    }
+    // Creators under watch always get a full moderation, never a precheck verdict alone.
+    let threshold = match &snapshot {
+        Some(snapshot) => snapshot.precheck_threshold,
+        None => db_lock.get_policy_precheck_threshold(redeem.policy.clone()).ok(),
+    }
+    .or_else(oai::get_default_precheck_threshold)
+    .filter(|_| standing == Standing::Good);
+    let tier = match &snapshot {
+        Some(snapshot) => snapshot.moderation_tier,
+        None => db_lock.get_policy_moderation_tier(redeem.policy.clone())?,
+    };
+    // Likewise, content they pre-approved is only taken on trust from creators in good standing,
+    // and never under a policy that wants every redemption checked by a quorum.
+    let approved = db_lock
+        .get_approved_content(
+            creator.clone(),
+            policy_hash(&redeem.policy),
+            content_hash(&redeem.content),
+        )?
+        .filter(|_| standing == Standing::Good && tier == ModerationTier::Standard);
+    let mention_rule = match &snapshot {
+        Some(snapshot) => snapshot.mention_rule.clone(),
+        None => db_lock.get_policy_mention_rule(redeem.policy.clone())?,
+    };
+    let license_rule = match &snapshot {
+        Some(snapshot) => snapshot.license_rule.clone(),
+        None => db_lock.get_policy_license_rule(redeem.policy.clone())?,
+    };
+    drop(db_lock);
+    let pinned = snapshot.as_ref().map(|snapshot| snapshot.prompt_version.as_str());
+    // Checked before moderating, so a queued redemption is moderated once it can also be posted.
+    if !read_only {
+        ensure_enabled(SideEffect::Tweets)?;
+    }
+    let mentions_allowed = match &mention_rule {
+        Some(rule) => {
+            check_mentions(&db, &twitter_builder, chain_id, &token_id, &redeem, rule, read_only)
+                .await?
+        }
+        None => true,
+    };
+    let tweet_content = TweetContent::parse(&redeem.content);
+    let license_violation =
+        license::check(license_rule.as_ref(), tweet_content.license.as_ref()).err();
+    if let Some(violation) = &license_violation {
+        log::warn!("NFT {}'s content license is not accepted: {}", token_id, violation);
+    }
+    // A retry reuses the verdict an earlier attempt recorded, so the content is moderated, and a
+    // rejection counted against its creator, only once.
+    let recorded = db.lock().await.get_moderation_record(chain_id, token_id.clone()).ok();
+    let first_verdict = recorded.is_none();
+    let moderation = match (recorded, approved) {
+        (Some(record), _) => {
+            oai::Moderation { safe: record.safe, prompt_version: record.prompt_version }
+        }
+        _ if license_violation.is_some() => {
+            metrics::increment("license_rejections_total", &[("chain_id", &chain_id.to_string())]);
+            oai::Moderation { safe: false, prompt_version: LICENSE_CHECK_VERSION.to_string() }
+        }
+        _ if !mentions_allowed => {
+            metrics::increment("mention_rejections_total", &[("chain_id", &chain_id.to_string())]);
+            oai::Moderation { safe: false, prompt_version: MENTION_CHECK_VERSION.to_string() }
+        }
+        (None, Some(approved)) => {
+            metrics::increment("moderation_bypasses_total", &[("chain_id", &chain_id.to_string())]);
+            oai::Moderation { safe: true, prompt_version: approved.prompt_version }
+        }
+        (None, None) if tier == ModerationTier::HighAssurance => {
+            moderate_with_quorum(&db, chain_id, &token_id, &redeem).await?
+        }
+        (None, None) => {
+            if oai::calls_openai() {
+                ensure_enabled(SideEffect::OpenAi)?;
+            }
+            oai::moderate_tweet_with_precheck(&redeem.content, &redeem.policy, threshold, pinned)
+                .await
+        }
+    };
+    if first_verdict {
+        db.lock().await.add_moderation_record(
+            chain_id,
+            token_id.clone(),
+            ModerationRecord {
+                prompt_version: moderation.prompt_version.clone(),
+                safe: moderation.safe,
+            },
+        )?;
+        // Counted once the verdict is recorded, and never failing the redemption, which a retry
+        // would count again.
+        if !moderation.safe {
+            let rejection =
+                reputation::record(&db, creator.clone(), ReputationSignal::Rejection).await;
+            if let Err(e) = rejection {
+                log::error!("Failed to count the rejection of NFT {}: {:?}", token_id, e);
+            }
+        }
+    }
+    if !moderation.safe {
+        count_event(chain_id, "RedeemTweet", "skipped_unsafe");
+    }
+    if moderation.safe {
         let db_lock = db.lock().await;
         let user = db_lock.get_user_by_x_id(redeem.x_id.to_string()).ok();
+        // A retried redemption whose tweet already went out must not post it a second time.
+        let recorded_tweet = db_lock.get_tweet(chain_id, token_id.clone()).ok();
         drop(db_lock);
-        let mut tweet_content = TweetContent { text: redeem.content.clone(), media_url: None };
 
-        if let Some(user) = user {
-            let client = twitter_builder
-                .with_auth(user.access_tokens.ok_or_eyre("User has no access tokens")?.into());
+        let posted = match user {
+            Some(_) if read_only => {
+                log::info!("Read-only, not tweeting NFT {}", token_id);
+                None
+            }
+            Some(_) if recorded_tweet.is_some() => {
+                log::info!("NFT {} was already tweeted, not posting again", token_id);
+                None
+            }
+            Some(user) => {
+                let client = twitter_builder
+                    .with_auth(user.access_tokens.ok_or_eyre("User has no access tokens")?.into());
 
-            // to be backwards compatible for now
-            if let Ok(parsed_tweet_content) = serde_json::from_str::<TweetContent>(&redeem.content)
-            {
-                tweet_content.text = parsed_tweet_content.text;
-                if let Some(media_url) = parsed_tweet_content.media_url {
+                // Features the API tier lacks are left out instead of failing the redemption.
+                let capabilities = twitter_builder.capabilities;
+                let mut tweet = match &tweet_content.media_url {
+                    Some(media_url) if !capabilities.media_upload => {
+                        log::warn!(
+                            "X API tier cannot upload media, linking it in NFT {}",
+                            token_id
+                        );
+                        Tweet::new(format!("{} {}", tweet_content.text, media_url))
+                    }
+                    _ => Tweet::new(tweet_content.text.clone()),
+                };
+                if let Some(media_url) =
+                    tweet_content.media_url.as_ref().filter(|_| capabilities.media_upload)
+                {
                     let media_bytes = reqwest::get(media_url).await?.bytes().await?.to_vec();
                     let media_id = client.upload_media(media_bytes, None).await?;
-                    tweet_content.media_url = Some(media_id);
+                    tweet.set_media_ids(vec![media_id]);
+                }
+                if let Some(poll) = tweet_content.poll {
+                    if capabilities.polls {
+                        tweet.set_poll(poll.options, poll.duration_minutes);
+                    } else {
+                        log::warn!("X API tier cannot post polls, dropping NFT {}'s", token_id);
+                    }
                 }
-            }
 
-            let mut tweet = Tweet::new(tweet_content.text.clone());
-            if let Some(media_id) = tweet_content.media_url {
-                tweet.set_media_ids(vec![media_id]);
+                let text = tweet.text().to_string();
+                let tweet_id = match client.raw_tweet(tweet).await {
+                    Ok(tweet_id) => tweet_id,
+                    Err(e) => recover_duplicate(&client, &creator, &token_id, &text, e).await?,
+                };
+                Some((tweet_id, client))
             }
+            None => None,
+        };
+        let posted_tweet = posted.as_ref().map(|(tweet_id, _)| tweet_id.clone());
 
-            let tweet_id = client.raw_tweet(tweet).await?;
-
+        // Other copies of an edition may still be held, so its token stays listed.
+        let delist = standard == TokenStandard::Erc721;
+        let redemption = Redemption {
+            token_id: token_id.clone(),
+            tweet_id: posted_tweet.clone().or(recorded_tweet),
+            text: tweet_content.text,
+            policy: redeem.policy,
+            anchor: posted_tweet
+                .filter(|_| anchor::is_enabled(chain_id))
+                .map(|_| content_hash(&redeem.content)),
+            delist,
+        };
+        let storage = Stores::new(db.clone(), marketplace);
+        if let Err(e) = storage.record_redemption(chain_id, redemption).await {
+            return Err(match &posted {
+                Some((tweet_id, client)) => {
+                    compensate_redemption(&db, client, chain_id, &token_id, tweet_id, e).await
+                }
+                None => e,
+            });
+        }
+        if let Some(content_license) = tweet_content.license {
+            record_license(&db, chain_id, &token_id, content_license).await?;
+        }
+        if delist {
             let mut db = db.lock().await;
-            db.add_tweet(redeem.tokenId.to_string(), tweet_id)?;
+            if db.get_burn_on_redeem(creator.clone())? {
+                db.queue_burn(chain_id, token_id.clone(), false)?;
+            }
             drop(db);
+            log::info!(
+                "NFT {} from {} deleted from the marketplace index.",
+                redeem.tokenId,
+                contract
+            );
         }
-
-        let token_id = redeem.tokenId.to_string();
-        let token_owner = client_db.get_token_owner(token_id.clone()).await?;
-        client_db
-            .add_redeemed_tweet(
-                token_owner.clone(),
-                token_id.clone(),
-                tweet_content.text,
-                redeem.policy,
+        // Delivered last, once nothing is left that could fail and have a retry deliver them
+        // again.
+        let activity = TokenActivity {
+            x_id: creator.clone(),
+            chain_id,
+            token_id: token_id.clone(),
+            activity: Activity::Redeemed,
+            to: None,
+            tweet_id: None,
+        };
+        webhooks::deliver(&db, activity.clone()).await;
+        if let Some((tweet_id, _)) = posted {
+            let tweet_id = Some(tweet_id);
+            webhooks::deliver(
+                &db,
+                TokenActivity { activity: Activity::TweetPosted, tweet_id, ..activity },
             )
-            .await?;
-        client_db.increment_user_redeemed(token_owner.user_id).await?;
-        client_db.delete_token(token_id).await?;
-        log::info!("NFT {} deleted on postgresdb.", redeem.tokenId.to_string());
+            .await;
+        }
     }
     Ok(())
 }
 
+/// Stores the license a redemption declared and adds it to the token's metadata, which is pinned
+/// again with it.
+async fn record_license<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain_id: u64,
+    token_id: &str,
+    content_license: ContentLicense,
+) -> eyre::Result<()> {
+    let mut db = db.lock().await;
+    db.set_redemption_license(chain_id, token_id.to_string(), content_license.clone())?;
+    let Some(record) = db.get_token_metadata(chain_id, token_id.to_string())? else {
+        log::warn!("NFT {} has no metadata to add its content license to", token_id);
+        return Ok(());
+    };
+    let mut metadata: TokenMetadata = serde_json::from_str(&record.metadata)?;
+    metadata.set_license(&content_license);
+    db.set_token_metadata(chain_id, token_id.to_string(), serde_json::to_string(&metadata)?)
+}
+
+/// Handles a redemption whose tweet went out but could not be recorded. Once the tweet id is
+/// saved a retry finds it and only redoes the marketplace index, so `error` is retried as usual.
+/// Otherwise a retry would post the tweet again, so the tweet is deleted first, and if even that
+/// fails the event waits for an admin as [`RedemptionNeedsReview`].
+async fn compensate_redemption<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    client: &TwitterClient<'_>,
+    chain_id: u64,
+    token_id: &str,
+    tweet_id: &str,
+    error: eyre::Report,
+) -> eyre::Report {
+    if db.lock().await.get_tweet(chain_id, token_id.to_string()).is_ok() {
+        return error;
+    }
+    let chain = chain_id.to_string();
+    match client.delete_tweet(tweet_id.to_string()).await {
+        Ok(()) => {
+            log::warn!("Deleted tweet {} of NFT {} that could not be recorded", tweet_id, token_id);
+            metrics::increment(
+                "redemption_compensations_total",
+                &[("chain_id", &chain), ("action", "deleted")],
+            );
+            error
+        }
+        Err(e) => {
+            log::error!("Failed to delete unrecorded tweet {}: {:?}", tweet_id, e);
+            metrics::increment(
+                "redemption_compensations_total",
+                &[("chain_id", &chain), ("action", "review")],
+            );
+            error.wrap_err(RedemptionNeedsReview {
+                token_id: token_id.to_string(),
+                tweet_id: tweet_id.to_string(),
+            })
+        }
+    }
+}
+
+const DEFAULT_DUPLICATE_GRACE_SECS: i64 = 24 * 60 * 60;
+
+/// How far back the creator's timeline is searched for a tweet X rejected as a duplicate, from
+/// `TWEET_DUPLICATE_GRACE_SECS` (default a day, 0 to dead-letter duplicates instead).
+pub fn get_duplicate_grace() -> chrono::Duration {
+    let secs = std::env::var("TWEET_DUPLICATE_GRACE_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(DEFAULT_DUPLICATE_GRACE_SECS);
+    chrono::Duration::seconds(secs)
+}
+
+/// When X refuses a redemption's tweet as a duplicate, an earlier attempt most likely posted it
+/// before failing. The identical tweet found on the creator's recent timeline is taken as the
+/// fulfillment; anything else hands back `error`.
+async fn recover_duplicate(
+    client: &TwitterClient<'_>,
+    creator: &str,
+    token_id: &str,
+    text: &str,
+    error: eyre::Report,
+) -> eyre::Result<String> {
+    let grace = get_duplicate_grace();
+    let duplicate = error.downcast_ref::<TwitterError>().is_some_and(TwitterError::is_duplicate);
+    if !duplicate || grace <= chrono::Duration::zero() {
+        return Err(error);
+    }
+    let since = chrono::Utc::now() - grace;
+    match client.find_recent_tweet(creator, text, since).await? {
+        Some(tweet_id) => {
+            log::info!("NFT {} was already tweeted as {}, recording it", token_id, tweet_id);
+            metrics::increment("tweet_duplicates_total", &[("outcome", "recovered")]);
+            Ok(tweet_id)
+        }
+        None => {
+            log::warn!("X rejected NFT {} as a duplicate but no match was found", token_id);
+            metrics::increment("tweet_duplicates_total", &[("outcome", "unmatched")]);
+            Err(error)
+        }
+    }
+}
+
+/// Moderates a high-assurance redemption with both moderators of the quorum, skipping the
+/// precheck. A split is held for review unless an admin already approved the redemption.
+async fn moderate_with_quorum<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain_id: u64,
+    token_id: &str,
+    redeem: &RedeemTweet,
+) -> eyre::Result<oai::Moderation> {
+    if db.lock().await.is_quorum_dispute_approved(chain_id, token_id.to_string())? {
+        return Ok(oai::Moderation {
+            safe: true,
+            prompt_version: QUORUM_REVIEW_VERSION.to_string(),
+        });
+    }
+    let quorum = Quorum::from_env()?;
+    if quorum.calls_openai() {
+        ensure_enabled(SideEffect::OpenAi)?;
+    }
+    match quorum.moderate(&redeem.content, &redeem.policy).await? {
+        Some(moderation) => Ok(moderation),
+        None => Err(QuorumNeedsReview(token_id.to_string()).into()),
+    }
+}
+
+/// Checks a redemption's @mentions against its policy's rule, returning false to reject it.
+/// Handles the policy does not list hold it for review unless `MENTION_VIOLATION_ACTION` rejects
+/// them. Pinned accounts are only verified when there are tokens to ask the X API with.
+async fn check_mentions<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    twitter_builder: &TwitterBuilder,
+    chain_id: u64,
+    token_id: &str,
+    redeem: &RedeemTweet,
+    rule: &MentionRule,
+    read_only: bool,
+) -> eyre::Result<bool> {
+    let db_lock = db.lock().await;
+    if db_lock.are_mentions_approved(chain_id, token_id.to_string())? {
+        return Ok(true);
+    }
+    let access_tokens = db_lock
+        .get_user_by_x_id(redeem.x_id.to_string())
+        .ok()
+        .and_then(|user| user.access_tokens)
+        .filter(|_| !read_only);
+    drop(db_lock);
+    let mut check = mentions::check_handles(&redeem.content, rule);
+    if let (MentionCheck::Allowed, Some(access_tokens)) = (&check, access_tokens) {
+        let client = twitter_builder.with_auth(access_tokens.into());
+        check = mentions::verify_accounts(&client, &redeem.content, rule).await?;
+    }
+    match check {
+        MentionCheck::Allowed => Ok(true),
+        MentionCheck::Impersonation(handle) => {
+            log::warn!("Rejecting NFT {}: @{} impersonates an allowed handle", token_id, handle);
+            Ok(false)
+        }
+        MentionCheck::Unlisted(handle) => match mentions::get_violation_action() {
+            ViolationAction::Reject => {
+                log::warn!("Rejecting NFT {}: its policy does not allow @{}", token_id, handle);
+                Ok(false)
+            }
+            ViolationAction::Review => Err(MentionsNeedReview(token_id.to_string()).into()),
+        },
+    }
+}
+
+/// Generates the ERC-721 metadata `tokenURI` resolves to for a newly minted token.
+async fn store_token_metadata<A: TeleportDB>(
+    chain_id: u64,
+    db: &Arc<TrackedMutex<A>>,
+    marketplace: &Arc<dyn MarketplaceIndex>,
+    new_token_data: &NewTokenData,
+) -> eyre::Result<()> {
+    let token_id = new_token_data.tokenId.to_string();
+    let handle = marketplace
+        .find_token_owner(chain_id, token_id.clone())
+        .await?
+        .map(|owner| owner.twitter_user_name);
+    let metadata = TokenMetadata::new(
+        &token_id,
+        &new_token_data.x_id.to_string(),
+        handle.as_deref(),
+        &new_token_data.policy,
+    );
+    db.lock().await.set_token_metadata(chain_id, token_id, serde_json::to_string(&metadata)?)
+}
+
+/// Promotes a mint as soon as its `NewTokenData` is seen, or, when `MINT_CONFIRMATIONS` is set,
+/// leaves it confirming for the mint promoter.
 async fn handle_new_token_data<A: TeleportDB>(
-    db: Arc<Mutex<A>>,
-    client_db: ClientDB,
+    chain_id: u64,
+    contract: Address,
+    db: Arc<TrackedMutex<A>>,
+    marketplace: Arc<dyn MarketplaceIndex>,
     transaction_hash: Option<FixedBytes<32>>,
     new_token_data: NewTokenData,
 ) -> eyre::Result<()> {
+    let tx_hash =
+        transaction_hash.ok_or_eyre("Transaction hash is missing")?.encode_hex_with_prefix();
+    if get_mint_confirmations() == 0 {
+        return promote_mint(chain_id, contract, db, marketplace, tx_hash, new_token_data).await;
+    }
     let mut db = db.lock().await;
-    let nft_id = db.promote_pending_nft(
-        transaction_hash.ok_or_eyre("Transaction hash is missing")?.encode_hex_with_prefix(),
-        new_token_data.tokenId.to_string(),
-    )?;
-    drop(db);
+    let token_id = new_token_data.tokenId.to_string();
+    let pending_nft =
+        db.get_pending_nft(tx_hash.clone())?.ok_or_else(|| eyre::eyre!("Pending NFT not found"))?;
+    let confirmation =
+        MintConfirmation { nft_id: pending_nft.nft_id, chain_id, token_id, confirmations: 0 };
+    db.set_mint_confirmation(tx_hash, confirmation)
+}
 
+/// Turns the pending NFT minted by `tx_hash` into a token.
+pub(super) async fn promote_mint<A: TeleportDB>(
+    chain_id: u64,
+    contract: Address,
+    db: Arc<TrackedMutex<A>>,
+    marketplace: Arc<dyn MarketplaceIndex>,
+    tx_hash: String,
+    new_token_data: NewTokenData,
+) -> eyre::Result<()> {
     let token_id = new_token_data.tokenId.to_string();
-    client_db.set_token_id(token_id.clone(), nft_id).await?;
+    let storage = Stores::new(db.clone(), marketplace.clone());
+    storage.record_mint(chain_id, tx_hash, token_id.clone()).await?;
+    // The token is already promoted, so a retry could not get this far; log instead of failing.
+    if let Err(e) = store_token_metadata(chain_id, &db, &marketplace, &new_token_data).await {
+        log::error!("Failed to store metadata of NFT {}: {:?}", token_id, e);
+    }
+    deliver_to_creator(&db, chain_id, &token_id, Activity::Minted, None).await;
     log::info!(
-        "NFT minted with id {} to address {}",
+        "NFT minted with id {} on {} to address {}",
         new_token_data.tokenId.to_string(),
+        contract,
         new_token_data.to.to_string()
     );
     Ok(())
 }
 
-async fn handle_transfer(client_db: ClientDB, transfer: Transfer) -> eyre::Result<()> {
+async fn handle_transfer<A: TeleportDB>(
+    chain_id: u64,
+    standard: TokenStandard,
+    db: Arc<TrackedMutex<A>>,
+    marketplace: Arc<dyn MarketplaceIndex>,
+    twitter_builder: TwitterBuilder,
+    notifier: Notifier,
+    transfer: Transfer,
+    read_only: bool,
+) -> eyre::Result<()> {
     let from = transfer.from.to_string();
     let to = transfer.to.to_string();
     let token_id = transfer.tokenId.to_string();
 
+    let storage = Stores::new(db.clone(), marketplace.clone());
     if from == "0x0000000000000000000000000000000000000000" {
         // Do nothing
     } else if to == "0x0000000000000000000000000000000000000000" {
-        client_db.delete_token(token_id.clone()).await?;
+        if standard == TokenStandard::Erc721 {
+            storage.transfer_token(chain_id, token_id.clone(), transfer.to).await?;
+            handle_burn(chain_id, db, &storage, twitter_builder, &token_id, read_only).await?;
+        }
     } else {
-        client_db.update_token_owner(token_id.clone(), to.clone()).await?;
+        storage.transfer_token(chain_id, token_id.clone(), transfer.to).await?;
+        let notified =
+            notify_creator_of_transfer(chain_id, db.clone(), notifier.clone(), &token_id, &to);
+        if let Err(e) = notified.await {
+            log::error!("Failed to notify creator of transfer of NFT {}: {:?}", token_id, e);
+        }
+        deliver_to_creator(&db, chain_id, &token_id, Activity::Transferred, Some(to.clone())).await;
+        if !read_only {
+            let dm = dm_new_holder(chain_id, db, twitter_builder, &notifier, &token_id, &to);
+            if let Err(e) = dm.await {
+                log::error!("Failed to DM new holder of NFT {}: {:?}", token_id, e);
+            }
+        }
     }
 
     log::info!("NFT {} transferred from {} to {}.", token_id, from, to);
     Ok(())
 }
 
-pub async fn mint_nft(
-    provider: WalletProvider,
+/// Archives the redemption of a token the minter wallet burned, and deletes its tweet if the burn
+/// revoked it. Burns nobody queued are left alone, and a queued burn is only forgotten once
+/// handled, so a failed handler is retried.
+async fn handle_burn<A: TeleportDB>(
+    chain_id: u64,
+    db: Arc<TrackedMutex<A>>,
+    storage: &dyn Storage,
+    twitter_builder: TwitterBuilder,
+    token_id: &str,
+    read_only: bool,
+) -> eyre::Result<()> {
+    let Some(burn) = db.lock().await.get_burn(chain_id, token_id.to_string())? else {
+        return Ok(());
+    };
+    storage.archive_redemption(chain_id, token_id.to_string(), burn.revocation).await?;
+
+    let tweet_id = db.lock().await.get_tweet(chain_id, token_id.to_string()).ok();
+    if let Some(tweet_id) = tweet_id.filter(|_| burn.revocation && !read_only) {
+        ensure_enabled(SideEffect::Tweets)?;
+        let db_lock = db.lock().await;
+        let nft = db_lock.get_nft_by_token_id(chain_id, token_id.to_string())?;
+        let creator = db_lock.get_user_by_address(nft.address)?;
+        drop(db_lock);
+        let client = twitter_builder
+            .with_auth(creator.access_tokens.ok_or_eyre("Creator has no access tokens")?.into());
+        client.delete_tweet(tweet_id.clone()).await?;
+        log::info!("Deleted tweet {} of revoked NFT {}", tweet_id, token_id);
+        // The tweet is gone, so a retry would fail to delete it again; counting it is best effort.
+        if let Some(x_id) = creator.x_id {
+            let deleted = reputation::record(&db, x_id, ReputationSignal::DeletedTweet).await;
+            if let Err(e) = deleted {
+                log::error!("Failed to count the deleted tweet of NFT {}: {:?}", token_id, e);
+            }
+        }
+    }
+    db.lock().await.remove_burn(chain_id, token_id.to_string())
+}
+
+/// Delivers activity on a promoted token to its creator's webhook, if they registered one.
+async fn deliver_to_creator<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain_id: u64,
+    token_id: &str,
+    activity: Activity,
+    to: Option<String>,
+) {
+    let db_lock = db.lock().await;
+    let creator = db_lock
+        .get_nft_by_token_id(chain_id, token_id.to_string())
+        .and_then(|nft| db_lock.get_user_by_address(nft.address))
+        .ok()
+        .and_then(|creator| creator.x_id);
+    drop(db_lock);
+    let Some(x_id) = creator else {
+        return;
+    };
+    let token_id = token_id.to_string();
+    webhooks::deliver(db, TokenActivity { x_id, chain_id, token_id, activity, to, tweet_id: None })
+        .await;
+}
+
+/// Notifies the creator of a token when it moves to an address belonging to a known user.
+async fn notify_creator_of_transfer<A: TeleportDB>(
+    chain_id: u64,
+    db: Arc<TrackedMutex<A>>,
+    notifier: Notifier,
+    token_id: &str,
+    to: &str,
+) -> eyre::Result<()> {
+    let db = db.lock().await;
+    let Ok(fan) = db.get_user_by_address(to.to_string()) else {
+        return Ok(());
+    };
+    let nft = db.get_nft_by_token_id(chain_id, token_id.to_string())?;
+    let creator = db.get_user_by_address(nft.address)?;
+    let creator_x_id = creator.x_id.ok_or_eyre("Creator has no x_id")?;
+    let timezone = db
+        .get_timezone(creator_x_id.clone())
+        .ok()
+        .and_then(|timezone| parse_timezone(&timezone).ok())
+        .unwrap_or(Tz::UTC);
+    drop(db);
+
+    let now = chrono::Utc::now();
+    let fan_x_id = fan.x_id.unwrap_or_default();
+    let message = notifier.transfer_message(token_id, to, &fan_x_id, &format_local(timezone, now));
+    let deliver_after = notifier.deliver_after(timezone, now);
+    notifier.notify(Notification { x_id: creator_x_id, message, deliver_after }).await
+}
+
+/// DMs the new holder of a token how to redeem it, when they are a known user who has not opted
+/// out and holder DMs are configured. Tokens returning to their creator are not announced.
+async fn dm_new_holder<A: TeleportDB>(
+    chain_id: u64,
+    db: Arc<TrackedMutex<A>>,
+    twitter_builder: TwitterBuilder,
+    notifier: &Notifier,
+    token_id: &str,
+    to: &str,
+) -> eyre::Result<()> {
+    let Some(holder_dms) = notifier.holder_dms() else {
+        return Ok(());
+    };
+    let db = db.lock().await;
+    let Ok(holder) = db.get_user_by_address(to.to_string()) else {
+        return Ok(());
+    };
+    let Some(holder_x_id) = holder.x_id.filter(|_| !holder.dm_opt_out) else {
+        return Ok(());
+    };
+    let nft = db.get_nft_by_token_id(chain_id, token_id.to_string())?;
+    let creator = db.get_user_by_address(nft.address)?;
+    let creator_x_id = creator.x_id.ok_or_eyre("Creator has no x_id")?;
+    drop(db);
+    if creator_x_id == holder_x_id {
+        return Ok(());
+    }
+
+    ensure_enabled(SideEffect::Tweets)?;
+    let client = twitter_builder.with_auth(holder_dms.sender());
+    let creator_handle = client.get_user_by_id(&creator_x_id).await?.username;
+    client.send_dm(&holder_x_id, &holder_dms.message(&creator_handle, token_id)).await?;
+    metrics::increment("holder_dms_total", &[("chain_id", &chain_id.to_string())]);
+    Ok(())
+}
+
+/// A transaction whose `eth_call` simulation reverted, carrying the decoded reason.
+#[derive(Debug)]
+pub struct Reverted(pub String);
+
+impl std::fmt::Display for Reverted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Transaction would revert: {}", self.0)
+    }
+}
+
+impl std::error::Error for Reverted {}
+
+/// Decodes revert data as one of the NFT contract's custom errors or a plain `Error(string)` /
+/// `Panic(uint256)`, falling back to the raw bytes.
+fn decode_revert_reason(data: &[u8]) -> String {
+    if let Ok(error) = NFT::NFTErrors::abi_decode(data, true) {
+        return format!("{:?}", error);
+    }
+    alloy::sol_types::decode_revert_reason(data)
+        .unwrap_or_else(|| alloy::hex::encode_prefixed(data))
+}
+
+/// Runs `request` through `eth_call` from the minter wallet so a revert is reported to the caller
+/// instead of being broadcast.
+pub(crate) async fn simulate(
+    chain: &ChainClient,
+    request: &TransactionRequest,
+) -> eyre::Result<()> {
+    let request = request.clone().with_from(chain.nonces.address());
+    match chain.provider().call(&request).await {
+        Ok(_) => Ok(()),
+        Err(RpcError::ErrorResp(payload)) => {
+            if let Some(data) = payload.as_revert_data() {
+                Err(Reverted(decode_revert_reason(&data)).into())
+            } else if payload.message.contains("revert") {
+                Err(Reverted(payload.message.to_string()).into())
+            } else {
+                Err(RpcError::<TransportErrorKind>::ErrorResp(payload).into())
+            }
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn mint_request(
+    chain: &ChainClient,
+    recipient: Address,
+    x_id: U256,
+    policy: String,
+) -> TransactionRequest {
+    let provider = chain.provider().clone();
+    match chain.config.token_standard {
+        TokenStandard::Erc721 => NFT::new(chain.config.nft_address, provider)
+            .mintTo(recipient, x_id, policy)
+            .into_transaction_request(),
+        TokenStandard::Erc1155 => NFT1155::new(chain.config.nft_address, provider)
+            .mintTo(recipient, x_id, policy, U256::from(chain.config.edition_size))
+            .into_transaction_request(),
+    }
+}
+
+/// Mints `x_id`'s token to `recipient`, unless the access lists forbid it.
+pub async fn mint_nft<A: TeleportDB>(
+    chain: &ChainClient,
+    db: &Arc<TrackedMutex<A>>,
     recipient: Address,
     x_id: String,
     policy: String,
 ) -> eyre::Result<String> {
-    let nft_address = get_nft_address()?;
-    let nft = NFT::new(nft_address, provider);
-    let mint = nft.mintTo(recipient, Uint::from_str(&x_id)?, policy);
-    let tx = mint.send().await?;
-
-    let tx_hash = tx.tx_hash();
+    access_list::check_mint(&*db.lock().await, &recipient.to_string(), &x_id)?;
+    let x_id = Uint::from_str(&x_id)?;
+    let request = mint_request(chain, recipient, x_id, policy);
+    simulate(chain, &request).await?;
+    let request = chain.config.gas.apply(chain.provider(), request).await?;
+    let tx_hash = chain.nonces.send(chain.submit_provider(), request).await?;
 
     log::info!("Minted NFT with tx hash: {}", tx_hash);
 
     Ok(tx_hash.encode_hex_with_prefix())
 }
 
+/// Mints one token of `x_id` to each recipient. The contract only lets its owner mint and has no
+/// batch function, so rather than aggregating calls the mints are pipelined: signed with
+/// consecutive nonces and sent without waiting for each other. Each recipient gets its own
+/// result, and any revert found in simulation aborts the whole batch before anything is sent.
+pub async fn batch_mint_nft<A: TeleportDB>(
+    chain: &ChainClient,
+    db: &Arc<TrackedMutex<A>>,
+    recipients: Vec<Address>,
+    x_id: String,
+    policy: String,
+) -> eyre::Result<Vec<eyre::Result<String>>> {
+    let db_lock = db.lock().await;
+    for recipient in &recipients {
+        access_list::check_mint(&*db_lock, &recipient.to_string(), &x_id)?;
+    }
+    drop(db_lock);
+    let x_id = Uint::from_str(&x_id)?;
+    let fees = chain.config.gas.fees(chain.provider()).await?;
+    let mut requests = Vec::with_capacity(recipients.len());
+    for recipient in recipients {
+        let request = mint_request(chain, recipient, x_id, policy.clone());
+        simulate(chain, &request).await?;
+        requests.push(with_fees(request, fees));
+    }
+    let results = chain.nonces.send_batch(chain.submit_provider(), requests).await;
+
+    log::info!("Batch minted {} NFTs", results.iter().filter(|result| result.is_ok()).count());
+    Ok(results
+        .into_iter()
+        .map(|result| result.map(|tx_hash| tx_hash.encode_hex_with_prefix()))
+        .collect())
+}
+
+/// Calldata for `holder` to redeem `token_id` themselves.
+pub fn redeem_calldata(
+    standard: TokenStandard,
+    holder: Address,
+    token_id: U256,
+    content: String,
+) -> Bytes {
+    match standard {
+        TokenStandard::Erc721 => {
+            NFT::redeemCall { tokenId: token_id, content, tokenType: 0 }.abi_encode()
+        }
+        TokenStandard::Erc1155 => {
+            NFT1155::redeemCall { holder, tokenId: token_id, content, tokenType: 0 }.abi_encode()
+        }
+    }
+    .into()
+}
+
+/// The token `data` redeems, if it is a redeem `holder` may send.
+pub fn decode_redeem_calldata(
+    standard: TokenStandard,
+    holder: Address,
+    data: &[u8],
+) -> Option<U256> {
+    match standard {
+        TokenStandard::Erc721 => {
+            NFT::redeemCall::abi_decode(data, true).ok().map(|call| call.tokenId)
+        }
+        TokenStandard::Erc1155 => NFT1155::redeemCall::abi_decode(data, true)
+            .ok()
+            .filter(|call| call.holder == holder)
+            .map(|call| call.tokenId),
+    }
+}
+
+/// Redeems `token_id`. On an edition contract this burns one copy from `holder`.
 pub async fn redeem_nft(
-    provider: WalletProvider,
+    chain: &ChainClient,
     token_id: String,
+    holder: Address,
     content: String,
 ) -> eyre::Result<String> {
-    let nft_address = get_nft_address()?;
-    let nft = NFT::new(nft_address, provider);
     let token_id = Uint::from_str(&token_id)?;
-    let redeem = nft.redeem(token_id, content, 0u8);
-    let tx = redeem.send().await?;
-
-    let tx_hash = tx.tx_hash();
+    let request = redeem_request(chain, token_id, holder, content);
+    simulate(chain, &request).await?;
+    let request = chain.config.gas.apply(chain.provider(), request).await?;
+    let tx_hash = chain.nonces.send(chain.submit_provider(), request).await?;
 
     log::info!("Redeemed NFT with tx hash: {}", tx_hash);
     Ok(tx_hash.encode_hex_with_prefix())
 }
 
-// pub async fn send_eth(
-//     provider: WalletProvider,
-//     recipient: Address,
-//     amount: &str,
-// ) -> eyre::Result<()> {
-//     let tx = TransactionRequest::default()
-//         .with_to(recipient)
-//         .with_value(parse_units(amount, "ether").unwrap().into());
-//     let _ = provider.send_transaction(tx).await?;
-//     Ok(())
-// }
+fn redeem_request(
+    chain: &ChainClient,
+    token_id: U256,
+    holder: Address,
+    content: String,
+) -> TransactionRequest {
+    let provider = chain.provider().clone();
+    match chain.config.token_standard {
+        TokenStandard::Erc721 => NFT::new(chain.config.nft_address, provider)
+            .redeem(token_id, content, 0u8)
+            .into_transaction_request(),
+        TokenStandard::Erc1155 => NFT1155::new(chain.config.nft_address, provider)
+            .redeem(holder, token_id, content, 0u8)
+            .into_transaction_request(),
+    }
+}
+
+/// What the minter wallet would pay to mint `x_id`'s token to `recipient` now. A mint that
+/// would revert fails with [`Reverted`].
+pub async fn estimate_mint(
+    chain: &ChainClient,
+    recipient: Address,
+    x_id: String,
+    policy: String,
+) -> eyre::Result<GasEstimate> {
+    let request = mint_request(chain, recipient, Uint::from_str(&x_id)?, policy);
+    estimate(chain, request).await
+}
+
+/// What the minter wallet would pay to redeem `token_id` for `holder` with `content` now.
+pub async fn estimate_redeem(
+    chain: &ChainClient,
+    token_id: String,
+    holder: Address,
+    content: String,
+) -> eyre::Result<GasEstimate> {
+    let request = redeem_request(chain, Uint::from_str(&token_id)?, holder, content);
+    estimate(chain, request).await
+}
+
+async fn estimate(chain: &ChainClient, request: TransactionRequest) -> eyre::Result<GasEstimate> {
+    simulate(chain, &request).await?;
+    let request = request.with_from(chain.nonces.address());
+    chain.config.gas.estimate(chain.provider(), &request).await
+}
+
+/// Whether `address` owns `token_id`, or at least one copy of it on an edition contract.
+pub async fn is_nft_holder(
+    chain: &ChainClient,
+    token_id: String,
+    address: Address,
+) -> eyre::Result<bool> {
+    let provider = chain.provider().clone();
+    let token_id = Uint::from_str(&token_id)?;
+    match chain.config.token_standard {
+        TokenStandard::Erc721 => {
+            let nft = NFT::new(chain.config.nft_address, provider);
+            Ok(nft.ownerOf(token_id).call().await?._0 == address)
+        }
+        TokenStandard::Erc1155 => {
+            let nft = NFT1155::new(chain.config.nft_address, provider);
+            Ok(nft.balanceOf(address, token_id).call().await?._0 > U256::ZERO)
+        }
+    }
+}
+
+/// The holder of `token_id` on an ERC-721 contract. Edition tokens have no single holder.
+pub async fn get_nft_owner(chain: &ChainClient, token_id: U256) -> eyre::Result<Option<Address>> {
+    match chain.config.token_standard {
+        TokenStandard::Erc721 => {
+            let nft = NFT::new(chain.config.nft_address, chain.provider().clone());
+            Ok(Some(nft.ownerOf(token_id).call().await?._0))
+        }
+        TokenStandard::Erc1155 => Ok(None),
+    }
+}
+
+/// Sends `amount` wei from the minter wallet to `recipient`.
+pub async fn send_eth(
+    chain: &ChainClient,
+    recipient: Address,
+    amount: U256,
+) -> eyre::Result<TxHash> {
+    let request = TransactionRequest::default().with_to(recipient).with_value(amount);
+    let request = chain.config.gas.apply(chain.provider(), request).await?;
+    chain.nonces.send(chain.submit_provider(), request).await
+}
 
 #[cfg(test)]
 mod tests {
@@ -258,6 +1891,30 @@ mod tests {
     };
 
     use super::*;
+    use crate::{
+        actions::{chain::DEFAULT_CHAIN_ID, rpc::RpcPool, wallet::NonceManager},
+        db::in_memory::InMemoryDB,
+    };
+
+    #[test]
+    fn retry_delay_backs_off_to_a_cap() {
+        assert_eq!(retry_delay(1), RETRY_INITIAL_BACKOFF);
+        assert_eq!(retry_delay(2), RETRY_INITIAL_BACKOFF * 2);
+        assert_eq!(retry_delay(3), RETRY_INITIAL_BACKOFF * 4);
+        assert_eq!(retry_delay(MAX_RETRY_ATTEMPTS * 4), RETRY_MAX_BACKOFF);
+    }
+
+    #[test]
+    fn decodes_revert_reasons() {
+        use alloy::sol_types::{Revert, SolError};
+
+        let custom = NFT::OwnableUnauthorizedAccount { account: Address::ZERO }.abi_encode();
+        assert!(decode_revert_reason(&custom).starts_with("OwnableUnauthorizedAccount"));
+        assert!(decode_revert_reason(&Revert::from("Not allowed").abi_encode())
+            .ends_with("Not allowed"));
+        assert_eq!(decode_revert_reason(&[0xde, 0xad]), "0xdead");
+    }
+
     #[tokio::test]
     async fn test_mint_nft() {
         env_logger::init();
@@ -273,11 +1930,22 @@ mod tests {
             .unwrap()
             .build()
             .unwrap();
+        let nonces = NonceManager::new(signer.address());
         let wallet = EthereumWallet::from(signer);
         let provider = ProviderBuilder::new()
             .with_recommended_fillers()
             .wallet(wallet)
             .on_http(rpc_url.parse().unwrap());
-        mint_nft(provider, recipient_address, 1.to_string(), "policy".to_string()).await.unwrap();
+        let config = ChainConfig::from_env(DEFAULT_CHAIN_ID, "").unwrap();
+        let chain = ChainClient {
+            config,
+            rpc: RpcPool::new(vec![provider]),
+            private_provider: None,
+            nonces,
+        };
+        let db = Arc::new(TrackedMutex::new(InMemoryDB::new()));
+        mint_nft(&chain, &db, recipient_address, 1.to_string(), "policy".to_string())
+            .await
+            .unwrap();
     }
 }
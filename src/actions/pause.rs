@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use alloy::{primitives::Address, rpc::types::Log, sol, sol_types::SolEventInterface};
+
+use super::{chain::ChainClient, nft::NFT};
+use crate::db::{lock::TrackedMutex, ContractStatus, TeleportDB};
+
+sol! {
+    /// OpenZeppelin's `Pausable`, for NFT contracts deployed with it.
+    #[sol(rpc)]
+    contract Pausable {
+        event Paused(address account);
+        event Unpaused(address account);
+        function paused() external view returns (bool);
+    }
+}
+
+/// A mint or redeem refused because the NFT contract would revert it: it is paused, or the
+/// minter wallet no longer owns it.
+#[derive(Debug)]
+pub struct ContractUnavailable(pub String);
+
+impl std::fmt::Display for ContractUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ContractUnavailable {}
+
+/// `Some(true)` for a `Paused` log and `Some(false)` for an `Unpaused` one.
+pub fn decode_pause(log: &Log) -> Option<bool> {
+    match Pausable::PausableEvents::decode_raw_log(log.topics(), &log.data().data, true).ok()? {
+        Pausable::PausableEvents::Paused(_) => Some(true),
+        Pausable::PausableEvents::Unpaused(_) => Some(false),
+    }
+}
+
+pub async fn record_pause<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain_id: u64,
+    contract: Address,
+    paused: bool,
+) -> eyre::Result<()> {
+    let mut db = db.lock().await;
+    let status = db.get_contract_status(chain_id, contract.to_string())?;
+    db.set_contract_status(chain_id, contract.to_string(), ContractStatus { paused, ..status })?;
+    if paused {
+        log::warn!(
+            "NFT contract {} on chain {} paused, refusing mints and redeems",
+            contract,
+            chain_id
+        );
+    } else {
+        log::info!("NFT contract {} on chain {} unpaused", contract, chain_id);
+    }
+    Ok(())
+}
+
+pub async fn record_owner<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain_id: u64,
+    contract: Address,
+    owner: Address,
+) -> eyre::Result<()> {
+    let mut db = db.lock().await;
+    let status = db.get_contract_status(chain_id, contract.to_string())?;
+    let status = ContractStatus { owner: Some(owner.to_string()), ..status };
+    db.set_contract_status(chain_id, contract.to_string(), status)?;
+    log::warn!("Ownership of NFT contract {} on chain {} moved to {}", contract, chain_id, owner);
+    Ok(())
+}
+
+/// Reads whether `chain`'s NFT contract is paused and who owns it, for events from before the
+/// indexer's cursor. Contracts without `Pausable` are never paused.
+pub async fn sync_contract_status<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain: &ChainClient,
+) -> eyre::Result<()> {
+    let contract = chain.config.nft_address;
+    let provider = chain.provider().clone();
+    let owner = NFT::new(contract, provider.clone()).owner().call().await?._0;
+    let paused = Pausable::new(contract, provider).paused().call().await.is_ok_and(|p| p._0);
+    let status = ContractStatus { paused, owner: Some(owner.to_string()) };
+    db.lock().await.set_contract_status(chain.config.chain_id, contract.to_string(), status)
+}
+
+/// Fails with `ContractUnavailable` when a transaction to `chain`'s NFT contract would revert
+/// for being paused, or, given the `minter`, for no longer being owned by it.
+pub async fn check_contract<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain: &ChainClient,
+    minter: Option<Address>,
+) -> eyre::Result<()> {
+    let contract = chain.config.nft_address;
+    let status =
+        db.lock().await.get_contract_status(chain.config.chain_id, contract.to_string())?;
+    if status.paused {
+        return Err(ContractUnavailable(format!(
+            "NFT contract on chain {} is paused; try again once it resumes",
+            chain.config.chain_id
+        ))
+        .into());
+    }
+    match (minter, status.owner) {
+        (Some(minter), Some(owner)) if owner != minter.to_string() => {
+            Err(ContractUnavailable(format!(
+                "NFT contract on chain {} is owned by {}, not the minter",
+                chain.config.chain_id, owner
+            ))
+            .into())
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{primitives::LogData, sol_types::SolEvent};
+
+    use super::*;
+
+    fn log(data: LogData) -> Log {
+        Log { inner: alloy::primitives::Log { address: Address::ZERO, data }, ..Default::default() }
+    }
+
+    #[test]
+    fn decodes_pause_and_unpause() {
+        let account = Address::with_last_byte(1);
+        assert_eq!(decode_pause(&log(Pausable::Paused { account }.encode_log_data())), Some(true));
+        assert_eq!(
+            decode_pause(&log(Pausable::Unpaused { account }.encode_log_data())),
+            Some(false)
+        );
+        let transfer = NFT::Transfer { from: account, to: account, tokenId: Default::default() };
+        assert_eq!(decode_pause(&log(transfer.encode_log_data())), None);
+    }
+}
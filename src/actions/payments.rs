@@ -0,0 +1,108 @@
+use std::str::FromStr;
+
+use alloy::{
+    primitives::{Address, B256, U256},
+    providers::Provider,
+    sol,
+};
+
+use super::chain::{chain_var, ChainClient};
+
+sol! {
+    event Transfer(address indexed from, address indexed to, uint256 value);
+}
+
+/// ERC-20 stablecoins mints can be paid in, from `MINT_FEE_TOKENS` (comma separated
+/// `<token address>:<fee in base units>`), and the `MINT_FEE_RECIPIENT` they are paid to.
+#[derive(Debug, Clone)]
+pub struct MintFeeConfig {
+    pub recipient: Address,
+    pub tokens: Vec<(Address, U256)>,
+}
+
+impl MintFeeConfig {
+    /// `None` when the chain charges no mint fee.
+    pub fn from_env(chain_id: u64) -> eyre::Result<Option<Self>> {
+        let Ok(tokens) = chain_var("MINT_FEE_TOKENS", chain_id) else {
+            return Ok(None);
+        };
+        let tokens = tokens
+            .split(',')
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .map(|token| {
+                let (address, fee) = token
+                    .split_once(':')
+                    .ok_or_else(|| eyre::eyre!("Expected <address>:<fee> in MINT_FEE_TOKENS"))?;
+                Ok((Address::from_str(address)?, U256::from_str(fee)?))
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+        let recipient = Address::from_str(&chain_var("MINT_FEE_RECIPIENT", chain_id)?)?;
+        Ok(Some(Self { recipient, tokens }))
+    }
+
+    fn fee(&self, token: Address) -> Option<U256> {
+        self.tokens.iter().find(|(accepted, _)| *accepted == token).map(|(_, fee)| *fee)
+    }
+}
+
+/// Why a payment does not pay for a mint, as opposed to a failure to read it from the chain.
+#[derive(Debug)]
+pub struct PaymentRejected(pub &'static str);
+
+impl std::fmt::Display for PaymentRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Payment rejected: {}", self.0)
+    }
+}
+
+impl std::error::Error for PaymentRejected {}
+
+/// A verified payment: the token paid in and the amount received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Payment {
+    pub token: Address,
+    pub amount: U256,
+}
+
+/// Checks that `payment_tx` transferred at least the fee for `count` mints in one accepted token
+/// from `payer` to the fee recipient. Transfers the service executes itself (permit and
+/// `transferFrom`) show up in the receipt the same way.
+pub async fn verify_payment(
+    chain: &ChainClient,
+    fees: &MintFeeConfig,
+    payment_tx: B256,
+    payer: Address,
+    count: u64,
+) -> eyre::Result<Payment> {
+    let receipt = chain
+        .provider()
+        .get_transaction_receipt(payment_tx)
+        .await?
+        .ok_or(PaymentRejected("transaction not found or not mined yet"))?;
+    if !receipt.status() {
+        return Err(PaymentRejected("transaction reverted").into());
+    }
+    let mut paid: Vec<Payment> = Vec::new();
+    for log in receipt.inner.logs() {
+        let Ok(transfer) = log.log_decode::<Transfer>() else {
+            continue;
+        };
+        let token = log.address();
+        let transfer = transfer.inner.data;
+        if transfer.from != payer || transfer.to != fees.recipient || fees.fee(token).is_none() {
+            continue;
+        }
+        match paid.iter_mut().find(|payment| payment.token == token) {
+            Some(payment) => payment.amount += transfer.value,
+            None => paid.push(Payment { token, amount: transfer.value }),
+        }
+    }
+    paid.into_iter()
+        .find(|payment| {
+            fees.fee(payment.token)
+                .and_then(|fee| fee.checked_mul(U256::from(count)))
+                .is_some_and(|due| payment.amount >= due)
+        })
+        .ok_or_else(|| PaymentRejected("no transfer of an accepted token covers the fee").into())
+}
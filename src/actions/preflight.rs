@@ -0,0 +1,68 @@
+use alloy::{primitives::Address, providers::Provider, sol};
+use eyre::WrapErr;
+
+use super::chain::ChainClient;
+
+sol! {
+    #[sol(rpc)]
+    contract Ownable {
+        function owner() external view returns (address);
+    }
+}
+
+/// Checks that `chain`'s RPC endpoints serve the configured chain and that `minter` owns its NFT
+/// contract, so a misconfiguration stops startup instead of reverting every mint. A read-only
+/// instance has no minter, and only its endpoints and contract are checked.
+pub async fn check_chain(chain: &ChainClient, minter: Option<Address>) -> eyre::Result<()> {
+    let chain_id = chain.config.chain_id;
+    let providers = chain
+        .rpc
+        .providers()
+        .iter()
+        .enumerate()
+        .map(|(i, provider)| (if i == 0 { "RPC_URL" } else { "RPC_FALLBACK_URLS" }, provider))
+        .chain(chain.private_provider.as_ref().map(|provider| ("PRIVATE_TX_RPC_URL", provider)));
+    for (name, provider) in providers {
+        let rpc_chain_id = provider
+            .get_chain_id()
+            .await
+            .wrap_err_with(|| format!("Failed to reach {} for chain {}", name, chain_id))?;
+        if rpc_chain_id != chain_id {
+            eyre::bail!(
+                "{} for chain {} serves chain {}; point {}_{} at a chain {} endpoint",
+                name,
+                chain_id,
+                rpc_chain_id,
+                name,
+                chain_id,
+                chain_id
+            );
+        }
+    }
+
+    let nft_address = chain.config.nft_address;
+    if chain.provider().get_code_at(nft_address).await?.is_empty() {
+        eyre::bail!(
+            "No contract at NFT_ADDRESS {} on chain {}; check NFT_ADDRESS_{}",
+            nft_address,
+            chain_id,
+            chain_id
+        );
+    }
+    let Some(minter) = minter else {
+        return Ok(());
+    };
+    let owner = Ownable::new(nft_address, chain.provider().clone()).owner().call().await?._0;
+    if owner != minter {
+        eyre::bail!(
+            "Minter wallet {} cannot mint on chain {}: NFT contract {} is owned by {}; transfer \
+             ownership to the minter or use the owner's NFT_MINTER_MNEMONIC",
+            minter,
+            chain_id,
+            nft_address,
+            owner
+        );
+    }
+    log::info!("Minter wallet {} owns NFT contract {} on chain {}", minter, nft_address, chain_id);
+    Ok(())
+}
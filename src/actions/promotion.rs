@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use alloy::{providers::Provider, sol_types::SolEventInterface};
+use tokio::time::{sleep, Duration};
+
+use super::{
+    chain::ChainClient,
+    nft::{promote_mint, NFT::NFTEvents},
+};
+use crate::{
+    db::{lock::TrackedMutex, marketplace::MarketplaceIndex, MintConfirmation, TeleportDB},
+    metrics,
+};
+
+const PROMOTION_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Blocks a mint transaction must be buried under, counting its own, before its pending NFT is
+/// promoted, from `MINT_CONFIRMATIONS`. Zero promotes on the first sighting of `NewTokenData`.
+pub fn get_mint_confirmations() -> u64 {
+    std::env::var("MINT_CONFIRMATIONS")
+        .ok()
+        .and_then(|confirmations| confirmations.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Re-reads each confirming mint's receipt and promotes the ones deep enough. The receipt, not
+/// the log the indexer saw, decides: a reorg that dropped the transaction leaves it confirming,
+/// and one that re-mined it under another token id leaves it to that block's own log.
+async fn promote_confirmed<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain: &ChainClient,
+    marketplace: &Arc<dyn MarketplaceIndex>,
+    required: u64,
+) -> eyre::Result<()> {
+    let chain_id = chain.config.chain_id;
+    let confirming = db.lock().await.get_mint_confirmations(chain_id)?;
+    if confirming.is_empty() {
+        return Ok(());
+    }
+    let head = chain.provider().get_block_number().await?;
+    for (tx_hash, confirmation) in confirming {
+        let receipt = chain.provider().get_transaction_receipt(tx_hash.parse()?).await?;
+        let mined = receipt.and_then(|receipt| {
+            let new_token_data = receipt
+                .inner
+                .logs()
+                .iter()
+                .filter(|log| log.address() == chain.config.nft_address)
+                .find_map(|log| {
+                    match NFTEvents::decode_raw_log(log.topics(), &log.data().data, true) {
+                        Ok(NFTEvents::NewTokenData(new_token_data))
+                            if new_token_data.tokenId.to_string() == confirmation.token_id =>
+                        {
+                            Some(new_token_data)
+                        }
+                        _ => None,
+                    }
+                })?;
+            Some((receipt.block_number?, new_token_data))
+        });
+        let Some((block_number, new_token_data)) = mined else {
+            if confirmation.confirmations > 0 {
+                let confirmation = MintConfirmation { confirmations: 0, ..confirmation };
+                db.lock().await.set_mint_confirmation(tx_hash, confirmation)?;
+            }
+            continue;
+        };
+        let confirmations = (head + 1).saturating_sub(block_number);
+        if confirmations < required {
+            let confirmation = MintConfirmation { confirmations, ..confirmation };
+            db.lock().await.set_mint_confirmation(tx_hash, confirmation)?;
+            continue;
+        }
+        let token_id = confirmation.token_id;
+        let contract = chain.config.nft_address;
+        if let Err(e) = promote_mint(
+            chain_id,
+            contract,
+            db.clone(),
+            marketplace.clone(),
+            tx_hash,
+            new_token_data,
+        )
+        .await
+        {
+            log::error!("Failed to promote NFT {} on chain {}: {:?}", token_id, chain_id, e);
+            continue;
+        }
+        metrics::increment("mints_promoted_total", &[("chain_id", &chain_id.to_string())]);
+    }
+    Ok(())
+}
+
+/// Promotes `chain`'s pending NFTs once their mint transactions have `MINT_CONFIRMATIONS`
+/// confirmations, so a reorg cannot undo a token users were already shown.
+pub async fn run_mint_promoter<A: TeleportDB>(
+    db: Arc<TrackedMutex<A>>,
+    chain: ChainClient,
+    marketplace: Arc<dyn MarketplaceIndex>,
+) {
+    let required = get_mint_confirmations();
+    loop {
+        sleep(PROMOTION_INTERVAL).await;
+        if let Err(e) = promote_confirmed(&db, &chain, &marketplace, required).await {
+            log::error!(
+                "Failed to promote confirmed mints on chain {}: {:?}",
+                chain.config.chain_id,
+                e
+            );
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+use alloy::{
+    hex::ToHexExt,
+    primitives::{Address, U256},
+    providers::Provider,
+    rpc::types::{Filter, Log},
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use eyre::OptionExt;
+use serde::Serialize;
+
+use super::{
+    chain::{ChainClient, TokenStandard},
+    nft::{self, BACKFILL_CHUNK_SIZE, NFT::NFTEvents},
+    pause,
+};
+use crate::{
+    db::{lock::TrackedMutex, marketplace::MarketplaceIndex, TeleportDB},
+    metrics,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RebuildStatus {
+    Idle,
+    /// Reading the contract's logs back from the chain.
+    Replaying,
+    /// Writing the folded token states to the marketplace index.
+    Applying,
+    /// Recomputing the daily creator stats of the replayed days.
+    RollingUp,
+    Done,
+    Failed,
+}
+
+/// Where the current or last rebuild is, for `GET /admin/rebuild`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RebuildProgress {
+    pub status: RebuildStatus,
+    pub chain_id: u64,
+    pub from_block: u64,
+    /// The indexer's cursor when the rebuild started. Later logs are the live indexer's.
+    pub to_block: u64,
+    pub replayed_block: u64,
+    pub logs_replayed: u64,
+    /// Logs in range the indexer never processed, or whose handler has not succeeded yet.
+    pub logs_skipped: u64,
+    pub tokens: u64,
+    pub tokens_applied: u64,
+    pub creator_days: u64,
+    pub errors: u64,
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+    pub error: Option<String>,
+}
+
+impl RebuildProgress {
+    fn idle() -> Self {
+        Self {
+            status: RebuildStatus::Idle,
+            chain_id: 0,
+            from_block: 0,
+            to_block: 0,
+            replayed_block: 0,
+            logs_replayed: 0,
+            logs_skipped: 0,
+            tokens: 0,
+            tokens_applied: 0,
+            creator_days: 0,
+            errors: 0,
+            started_at: None,
+            finished_at: None,
+            error: None,
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        matches!(
+            self.status,
+            RebuildStatus::Replaying | RebuildStatus::Applying | RebuildStatus::RollingUp
+        )
+    }
+}
+
+static PROGRESS: OnceLock<RwLock<RebuildProgress>> = OnceLock::new();
+
+fn progress_lock() -> &'static RwLock<RebuildProgress> {
+    PROGRESS.get_or_init(|| RwLock::new(RebuildProgress::idle()))
+}
+
+fn update(f: impl FnOnce(&mut RebuildProgress)) {
+    f(&mut progress_lock().write().unwrap());
+}
+
+pub fn progress() -> RebuildProgress {
+    progress_lock().read().unwrap().clone()
+}
+
+/// A token's state as the replayed events leave it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct TokenState {
+    minted: bool,
+    /// The last holder transferred to; the zero address once burned.
+    holder: Option<Address>,
+    redeemed: bool,
+}
+
+impl TokenState {
+    fn burned(&self) -> bool {
+        self.holder == Some(Address::ZERO)
+    }
+}
+
+/// What a replayed event changes about its token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenEvent {
+    Minted,
+    Transferred(Address),
+    Redeemed,
+}
+
+fn token_event(event: &NFTEvents) -> Option<(U256, TokenEvent)> {
+    match event {
+        NFTEvents::NewTokenData(new_token_data) => {
+            Some((new_token_data.tokenId, TokenEvent::Minted))
+        }
+        NFTEvents::Transfer(transfer) => {
+            Some((transfer.tokenId, TokenEvent::Transferred(transfer.to)))
+        }
+        NFTEvents::RedeemTweet(redeem) => Some((redeem.tokenId, TokenEvent::Redeemed)),
+        _ => None,
+    }
+}
+
+/// Applies an event to its token's state. Events must be folded in chain order.
+fn fold(tokens: &mut BTreeMap<U256, TokenState>, token_id: U256, event: TokenEvent) {
+    let state = tokens.entry(token_id).or_default();
+    match event {
+        TokenEvent::Minted => state.minted = true,
+        TokenEvent::Transferred(to) => state.holder = Some(to),
+        TokenEvent::Redeemed => state.redeemed = true,
+    }
+}
+
+/// Starts rebuilding `chain`'s marketplace listings, redemption tweet ids and creator stats from
+/// `from_block` up to `to_block`, the indexer's cursor for that chain, in the background. Only logs
+/// the indexer journaled as processed, and whose handlers succeeded, are replayed, so a rebuild
+/// never resurrects an event the live path rejected. Returns false if a rebuild is already running.
+pub fn start<A: TeleportDB>(
+    db: Arc<TrackedMutex<A>>,
+    chain: ChainClient,
+    marketplace: Arc<dyn MarketplaceIndex>,
+    from_block: u64,
+    to_block: u64,
+) -> bool {
+    {
+        let mut progress = progress_lock().write().unwrap();
+        if progress.is_running() {
+            return false;
+        }
+        *progress = RebuildProgress {
+            status: RebuildStatus::Replaying,
+            chain_id: chain.config.chain_id,
+            from_block,
+            to_block,
+            replayed_block: from_block,
+            started_at: Some(Utc::now().timestamp()),
+            ..RebuildProgress::idle()
+        };
+    }
+    tokio::spawn(async move {
+        let (status, error) =
+            match rebuild(&db, &chain, marketplace.as_ref(), from_block, to_block).await {
+                Ok(()) => (RebuildStatus::Done, None),
+                Err(e) => {
+                    log::error!("Rebuild of chain {} failed: {:?}", chain.config.chain_id, e);
+                    (RebuildStatus::Failed, Some(e.to_string()))
+                }
+            };
+        metrics::increment(
+            "rebuilds_total",
+            &[("outcome", if error.is_none() { "done" } else { "failed" })],
+        );
+        update(|progress| {
+            progress.status = status;
+            progress.error = error;
+            progress.finished_at = Some(Utc::now().timestamp());
+        });
+    });
+    true
+}
+
+async fn rebuild<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain: &ChainClient,
+    marketplace: &dyn MarketplaceIndex,
+    from_block: u64,
+    to_block: u64,
+) -> eyre::Result<()> {
+    log::info!(
+        "Rebuilding chain {} from block {} to {}",
+        chain.config.chain_id,
+        from_block,
+        to_block
+    );
+
+    let tokens = replay(db, chain, from_block, to_block).await?;
+    update(|progress| {
+        progress.status = RebuildStatus::Applying;
+        progress.tokens = tokens.len() as u64;
+    });
+    for (token_id, state) in tokens {
+        if let Err(e) = apply(db, chain, marketplace, token_id, &state).await {
+            log::warn!("Failed to rebuild NFT {}: {:?}", token_id, e);
+            update(|progress| progress.errors += 1);
+            continue;
+        }
+        update(|progress| progress.tokens_applied += 1);
+    }
+
+    update(|progress| progress.status = RebuildStatus::RollingUp);
+    let block = chain
+        .provider()
+        .get_block_by_number(from_block.into(), false)
+        .await?
+        .ok_or_eyre(format!("Block {} not found", from_block))?;
+    let first_day = DateTime::from_timestamp(block.header.timestamp as i64, 0)
+        .ok_or_eyre("Block timestamp out of range")?
+        .date_naive();
+    for day in days_since(first_day, Utc::now().date_naive()) {
+        marketplace.roll_up_creator_stats(day.to_string()).await?;
+        update(|progress| progress.creator_days += 1);
+    }
+    log::info!("Rebuild of chain {} complete", chain.config.chain_id);
+    Ok(())
+}
+
+/// The completed days from `first_day` up to `today`; today's is rolled up after midnight.
+fn days_since(first_day: NaiveDate, today: NaiveDate) -> Vec<NaiveDate> {
+    first_day.iter_days().take_while(|day| *day < today).collect()
+}
+
+async fn replay<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain: &ChainClient,
+    from_block: u64,
+    to_block: u64,
+) -> eyre::Result<BTreeMap<U256, TokenState>> {
+    let mut tokens = BTreeMap::new();
+    let mut chunk_start = from_block;
+    while chunk_start <= to_block {
+        let chunk_end = (chunk_start + BACKFILL_CHUNK_SIZE - 1).min(to_block);
+        let filter = Filter::new()
+            .address(chain.config.indexed_addresses.clone())
+            .from_block(chunk_start)
+            .to_block(chunk_end);
+        for log in chain.provider().get_logs(&filter).await? {
+            if !was_handled(db, chain.config.chain_id, &log).await? {
+                update(|progress| progress.logs_skipped += 1);
+                continue;
+            }
+            if let Some(paused) = pause::decode_pause(&log) {
+                pause::record_pause(db, chain.config.chain_id, log.address(), paused).await?;
+            }
+            for event in nft::decode_log(chain.config.token_standard, &log) {
+                if let Some((token_id, event)) = token_event(&event) {
+                    fold(&mut tokens, token_id, event);
+                }
+            }
+            update(|progress| progress.logs_replayed += 1);
+        }
+        update(|progress| progress.replayed_block = chunk_end);
+        chunk_start = chunk_end + 1;
+    }
+    Ok(tokens)
+}
+
+/// Whether the indexer processed `log` and its handler succeeded, rather than leaving it in the
+/// retry queue or dead letters.
+async fn was_handled<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain_id: u64,
+    log: &Log,
+) -> eyre::Result<bool> {
+    let (Some(tx_hash), Some(log_index)) = (log.transaction_hash, log.log_index) else {
+        return Ok(false);
+    };
+    let db = db.lock().await;
+    Ok(db.is_event_processed(chain_id, tx_hash.encode_hex_with_prefix(), log_index)? &&
+        db.get_failed_event(nft::failed_event_id(log)).is_err())
+}
+
+/// Writes a token's replayed state to the marketplace index, the same way the live handlers
+/// would have.
+async fn apply<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain: &ChainClient,
+    marketplace: &dyn MarketplaceIndex,
+    token_id: U256,
+    state: &TokenState,
+) -> eyre::Result<()> {
+    let chain_id = chain.config.chain_id;
+    let id = token_id.to_string();
+    if state.minted {
+        let nft_id = db.lock().await.get_nft_id_by_token_id(chain_id, id.clone())?;
+        marketplace.set_token_id(chain_id, id.clone(), nft_id).await?;
+    }
+    let mut redemption_recorded = false;
+    if state.redeemed {
+        let db = db.lock().await;
+        let safe = db.get_moderation_record(chain_id, id.clone()).is_ok_and(|record| record.safe);
+        let tweet_id = db.get_tweet(chain_id, id.clone()).ok();
+        drop(db);
+        if let Some(tweet_id) = tweet_id {
+            marketplace.set_redemption_tweet_id(chain_id, id.clone(), tweet_id).await?;
+        }
+        redemption_recorded = safe;
+    }
+    let standard = chain.config.token_standard;
+    if state.burned() || (standard == TokenStandard::Erc721 && redemption_recorded) {
+        return marketplace.delete_token(chain_id, id).await;
+    }
+    // An ERC-721 token is listed under its holder now, not as of the cursor, so a transfer the
+    // live indexer handles during the rebuild is not undone.
+    let holder = match standard {
+        TokenStandard::Erc721 => nft::get_nft_owner(chain, token_id).await?,
+        TokenStandard::Erc1155 => state.holder,
+    };
+    if let Some(holder) = holder {
+        marketplace.update_token_owner(chain_id, id, holder.to_string()).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fold_keeps_last_holder_and_redemption() {
+        let holder = Address::with_last_byte(1);
+        let buyer = Address::with_last_byte(2);
+        let mut tokens = BTreeMap::new();
+        let token = U256::from(1);
+        let burned = U256::from(2);
+        fold(&mut tokens, token, TokenEvent::Minted);
+        fold(&mut tokens, token, TokenEvent::Transferred(holder));
+        fold(&mut tokens, token, TokenEvent::Redeemed);
+        fold(&mut tokens, token, TokenEvent::Transferred(buyer));
+        fold(&mut tokens, burned, TokenEvent::Transferred(holder));
+        fold(&mut tokens, burned, TokenEvent::Transferred(Address::ZERO));
+
+        assert_eq!(
+            tokens[&token],
+            TokenState { minted: true, holder: Some(buyer), redeemed: true }
+        );
+        assert!(!tokens[&token].burned());
+        // Minted before the replayed range.
+        assert!(!tokens[&burned].minted);
+        assert!(tokens[&burned].burned());
+    }
+
+    #[test]
+    fn rolls_up_days_before_today() {
+        let first_day = NaiveDate::from_ymd_opt(2024, 2, 28).unwrap();
+        let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let days: Vec<String> =
+            days_since(first_day, today).iter().map(ToString::to_string).collect();
+        assert_eq!(days, vec!["2024-02-28", "2024-02-29"]);
+        assert!(days_since(today, today).is_empty());
+    }
+}
@@ -0,0 +1,222 @@
+use std::{str::FromStr, sync::Arc};
+
+use alloy::{
+    hex::ToHexExt,
+    network::TransactionBuilder,
+    primitives::{keccak256, Address, Bytes, U256},
+    providers::Provider,
+    rpc::types::{Filter, Log, TransactionRequest},
+    signers::{k256::ecdsa::SigningKey, local::LocalSigner, SignerSync},
+    sol,
+    sol_types::{eip712_domain, SolCall, SolEvent, SolStruct},
+};
+use eyre::OptionExt;
+use tokio::time::{sleep, Duration};
+
+use super::{
+    chain::ChainClient,
+    gas::GasStrategy,
+    nft::NFT::RedeemTweet,
+    wallet::{NonceManager, WalletProvider},
+};
+use crate::db::{lock::TrackedMutex, TeleportDB};
+
+const RELAY_POLL_INTERVAL: Duration = Duration::from_secs(30);
+const DEFAULT_RELAY_CONFIRMATIONS: u64 = 12;
+/// Source blocks scanned per pass.
+const MAX_BLOCKS_PER_PASS: u64 = 1_000;
+
+sol! {
+    /// A redemption on the chain its token lives on, as anchored in the L1 registry.
+    struct RelayedRedemption {
+        uint256 sourceChainId;
+        address source;
+        uint256 tokenId;
+        uint256 xId;
+        bytes32 sourceTxHash;
+        bytes32 contentHash;
+    }
+
+    #[sol(rpc)]
+    contract RedemptionRegistry {
+        function anchor(RelayedRedemption redemption, bytes signature) external;
+    }
+}
+
+/// The L1 registry redemptions are anchored in, from `RELAY_CHAIN_ID`, `RELAY_RPC_URL` and
+/// `RELAY_REGISTRY_ADDRESS`. `RELAY_SOURCE_CHAIN_IDS` (comma separated) picks the chains relayed
+/// from, every configured chain by default.
+#[derive(Debug, Clone)]
+pub struct RelayConfig {
+    pub chain_id: u64,
+    /// Only needed when the L1 is not one of the served chains.
+    pub rpc_url: Option<String>,
+    pub registry: Address,
+    pub source_chain_ids: Option<Vec<u64>>,
+    pub gas: GasStrategy,
+}
+
+impl RelayConfig {
+    /// `None` when the deployment does not relay.
+    pub fn from_env() -> eyre::Result<Option<Self>> {
+        let Ok(registry) = std::env::var("RELAY_REGISTRY_ADDRESS") else {
+            return Ok(None);
+        };
+        let chain_id = std::env::var("RELAY_CHAIN_ID")
+            .map_err(|_| eyre::eyre!("RELAY_CHAIN_ID not set"))?
+            .parse()?;
+        let source_chain_ids = std::env::var("RELAY_SOURCE_CHAIN_IDS")
+            .ok()
+            .map(|ids| ids.split(',').map(|id| id.trim().parse()).collect::<Result<Vec<_>, _>>())
+            .transpose()?;
+        Ok(Some(Self {
+            chain_id,
+            rpc_url: std::env::var("RELAY_RPC_URL").ok(),
+            registry: Address::from_str(&registry)?,
+            source_chain_ids,
+            gas: GasStrategy::from_env(chain_id)?,
+        }))
+    }
+
+    pub fn relays(&self, chain_id: u64) -> bool {
+        chain_id != self.chain_id &&
+            self.source_chain_ids.as_ref().map_or(true, |ids| ids.contains(&chain_id))
+    }
+}
+
+fn get_relay_confirmations() -> u64 {
+    std::env::var("RELAY_CONFIRMATIONS")
+        .ok()
+        .and_then(|confirmations| confirmations.parse().ok())
+        .unwrap_or(DEFAULT_RELAY_CONFIRMATIONS)
+}
+
+/// Where relayed messages are submitted: the minter wallet on the L1. When the L1 is also a
+/// configured chain, its `ChainClient`'s nonces must be shared so the two never collide.
+#[derive(Clone)]
+pub struct RelayTarget {
+    pub config: RelayConfig,
+    pub provider: WalletProvider,
+    pub nonces: NonceManager,
+    pub signer: LocalSigner<SigningKey>,
+}
+
+impl RelayTarget {
+    /// Signs `redemption` with the enclave's key, which the registry only accepts messages from,
+    /// so anchoring proves the enclave saw the redemption whoever submits it.
+    fn sign(&self, redemption: &RelayedRedemption) -> eyre::Result<Bytes> {
+        let domain = eip712_domain! {
+            name: "Teleport Redemption Registry",
+            version: "1",
+            chain_id: self.config.chain_id,
+            verifying_contract: self.config.registry,
+        };
+        let signature = self.signer.sign_hash_sync(&redemption.eip712_signing_hash(&domain))?;
+        Ok(signature.as_bytes().into())
+    }
+
+    async fn anchor(&self, redemption: RelayedRedemption) -> eyre::Result<String> {
+        let signature = self.sign(&redemption)?;
+        let call = RedemptionRegistry::anchorCall { redemption, signature };
+        let request = TransactionRequest::default()
+            .with_to(self.config.registry)
+            .with_input(call.abi_encode());
+        let request = self.config.gas.apply(&self.provider, request).await?;
+        let tx_hash = self.nonces.send(&self.provider, request).await?;
+        Ok(tx_hash.encode_hex_with_prefix())
+    }
+}
+
+fn relayed_redemption(chain_id: u64, log: &Log) -> eyre::Result<RelayedRedemption> {
+    let redeem = log.log_decode::<RedeemTweet>()?.inner.data;
+    Ok(RelayedRedemption {
+        sourceChainId: U256::from(chain_id),
+        source: log.address(),
+        tokenId: redeem.tokenId,
+        xId: redeem.x_id,
+        sourceTxHash: log.transaction_hash.ok_or_eyre("Transaction hash is missing")?,
+        contentHash: keccak256(redeem.content.as_bytes()),
+    })
+}
+
+fn relay_id(chain_id: u64, log: &Log) -> String {
+    format!(
+        "{}:{}:{}",
+        chain_id,
+        log.transaction_hash.unwrap_or_default().encode_hex_with_prefix(),
+        log.log_index.unwrap_or_default()
+    )
+}
+
+/// Anchors the confirmed redemptions on `source` since the relay cursor.
+async fn relay_redemptions<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    source: &ChainClient,
+    target: &RelayTarget,
+) -> eyre::Result<()> {
+    let chain_id = source.config.chain_id;
+    let head = source.provider().get_block_number().await?;
+    let confirmed = head.saturating_sub(get_relay_confirmations());
+    // A first run starts at the confirmed head rather than relaying the chain's history.
+    let from_block =
+        db.lock().await.get_relay_cursor(chain_id)?.map_or(confirmed, |cursor| cursor + 1);
+    if from_block > confirmed {
+        return Ok(());
+    }
+    let to_block = confirmed.min(from_block + MAX_BLOCKS_PER_PASS - 1);
+    let filter = Filter::new()
+        .address(source.config.indexed_addresses.clone())
+        .event_signature(RedeemTweet::SIGNATURE_HASH)
+        .from_block(from_block)
+        .to_block(to_block);
+    for log in source.provider().get_logs(&filter).await? {
+        let id = relay_id(chain_id, &log);
+        if db.lock().await.get_relayed(id.clone())?.is_some() {
+            continue;
+        }
+        let tx_hash = target.anchor(relayed_redemption(chain_id, &log)?).await?;
+        log::info!("Relayed redemption {} to chain {} in {}", id, target.config.chain_id, tx_hash);
+        db.lock().await.set_relayed(id, tx_hash)?;
+    }
+    db.lock().await.set_relay_cursor(chain_id, to_block)
+}
+
+/// Follows `source`'s redemptions and anchors each in the L1 registry once it is
+/// `RELAY_CONFIRMATIONS` blocks deep, so receipts outlive the L2 they happened on.
+pub async fn run_relayer<A: TeleportDB>(
+    db: Arc<TrackedMutex<A>>,
+    source: ChainClient,
+    target: RelayTarget,
+) {
+    loop {
+        sleep(RELAY_POLL_INTERVAL).await;
+        if let Err(e) = relay_redemptions(&db, &source, &target).await {
+            log::error!(
+                "Failed to relay redemptions from chain {}: {:?}",
+                source.config.chain_id,
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relays_sources_other_than_the_l1() {
+        let mut config = RelayConfig {
+            chain_id: 1,
+            rpc_url: None,
+            registry: Address::ZERO,
+            source_chain_ids: None,
+            gas: GasStrategy::default(),
+        };
+        assert!(config.relays(8453));
+        assert!(!config.relays(1));
+        config.source_chain_ids = Some(vec![42161]);
+        assert!(!config.relays(8453));
+        assert!(config.relays(42161));
+    }
+}
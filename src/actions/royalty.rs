@@ -0,0 +1,35 @@
+use alloy::{
+    primitives::{Address, FixedBytes, U256},
+    sol,
+};
+
+use super::chain::ChainClient;
+
+/// ERC-165 interface id of ERC-2981.
+const ERC2981_INTERFACE_ID: FixedBytes<4> = FixedBytes([0x2a, 0x55, 0x20, 0x5a]);
+/// Royalties are asked for on a sale price of this many units, so the amount returned is the
+/// royalty in basis points.
+const BASIS_POINTS: u64 = 10_000;
+
+sol! {
+    #[sol(rpc)]
+    contract ERC2981 {
+        function supportsInterface(bytes4 interfaceId) external view returns (bool);
+        function royaltyInfo(uint256 tokenId, uint256 salePrice) external view returns (address receiver, uint256 royaltyAmount);
+    }
+}
+
+/// Who is paid a royalty on sales of `token_id` and how much of the price, in basis points. A
+/// contract without ERC-2981 pays no royalty, which is reported as the zero address and 0.
+pub async fn get_royalty(chain: &ChainClient, token_id: U256) -> eyre::Result<(Address, u64)> {
+    let nft = ERC2981::new(chain.config.nft_address, chain.provider().clone());
+    if !nft.supportsInterface(ERC2981_INTERFACE_ID).call().await?._0 {
+        return Ok((Address::ZERO, 0));
+    }
+    let royalty = nft.royaltyInfo(token_id, U256::from(BASIS_POINTS)).call().await?;
+    let basis_points = u64::try_from(royalty.royaltyAmount)?;
+    if basis_points > BASIS_POINTS {
+        eyre::bail!("Royalty of {} basis points exceeds the sale price", basis_points);
+    }
+    Ok((royalty.receiver, basis_points))
+}
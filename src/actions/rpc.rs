@@ -0,0 +1,116 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use alloy::providers::Provider;
+use tokio::time::{sleep, timeout, Duration};
+
+use super::wallet::WalletProvider;
+use crate::metrics;
+
+const DEFAULT_HEALTH_CHECK_INTERVAL_SECS: u64 = 15;
+const DEFAULT_MAX_LAG_BLOCKS: u64 = 5;
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn get_health_check_interval() -> Duration {
+    let secs = std::env::var("RPC_HEALTH_CHECK_INTERVAL_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(DEFAULT_HEALTH_CHECK_INTERVAL_SECS);
+    Duration::from_secs(secs)
+}
+
+fn get_max_lag_blocks() -> u64 {
+    std::env::var("RPC_MAX_LAG_BLOCKS")
+        .ok()
+        .and_then(|blocks| blocks.parse().ok())
+        .unwrap_or(DEFAULT_MAX_LAG_BLOCKS)
+}
+
+/// The first endpoint, in priority order, that answered and is within `max_lag` blocks of the
+/// highest head any endpoint reported.
+fn choose_endpoint(heads: &[Option<u64>], max_lag: u64) -> Option<usize> {
+    let best = heads.iter().flatten().max()?;
+    heads.iter().position(|head| head.is_some_and(|head| head + max_lag >= *best))
+}
+
+/// A chain's HTTP RPC endpoints in priority order, all signing with the minter wallet, and the
+/// one currently in use. Endpoints are referred to by index, since their URLs carry API keys.
+#[derive(Clone)]
+pub struct RpcPool {
+    providers: Vec<WalletProvider>,
+    active: Arc<AtomicUsize>,
+}
+
+impl RpcPool {
+    pub fn new(providers: Vec<WalletProvider>) -> Self {
+        assert!(!providers.is_empty(), "RpcPool needs at least one endpoint");
+        Self { providers, active: Default::default() }
+    }
+
+    /// The endpoint requests should currently go to.
+    pub fn provider(&self) -> &WalletProvider {
+        &self.providers[self.active.load(Ordering::Relaxed)]
+    }
+
+    /// Every endpoint, the primary first.
+    pub fn providers(&self) -> &[WalletProvider] {
+        &self.providers
+    }
+
+    async fn probe(provider: &WalletProvider) -> Option<u64> {
+        timeout(PROBE_TIMEOUT, provider.get_block_number()).await.ok()?.ok()
+    }
+
+    /// Probes every endpoint and switches to the highest priority healthy one, falling back to
+    /// the primary endpoint once it has recovered.
+    pub async fn check_health(&self, chain_id: u64, max_lag: u64) {
+        let heads = futures::future::join_all(self.providers.iter().map(Self::probe)).await;
+        let chain_id = chain_id.to_string();
+        for (endpoint, head) in heads.iter().enumerate() {
+            metrics::set_gauge(
+                "rpc_endpoint_up",
+                &[("chain_id", &chain_id), ("endpoint", &endpoint.to_string())],
+                head.is_some() as i64,
+            );
+        }
+        let Some(chosen) = choose_endpoint(&heads, max_lag) else {
+            log::error!("No healthy RPC endpoint on chain {}", chain_id);
+            return;
+        };
+        let previous = self.active.swap(chosen, Ordering::Relaxed);
+        if previous != chosen {
+            log::warn!(
+                "Switched RPC endpoint on chain {} from #{} to #{}",
+                chain_id,
+                previous,
+                chosen
+            );
+            metrics::increment("rpc_failovers_total", &[("chain_id", &chain_id)]);
+        }
+    }
+}
+
+/// Keeps `pool` pointed at a healthy endpoint, probing every `RPC_HEALTH_CHECK_INTERVAL_SECS`.
+pub async fn run_rpc_health_checks(chain_id: u64, pool: RpcPool) {
+    let interval = get_health_check_interval();
+    let max_lag = get_max_lag_blocks();
+    loop {
+        pool.check_health(chain_id, max_lag).await;
+        sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefers_the_first_endpoint_that_keeps_up() {
+        assert_eq!(choose_endpoint(&[Some(100), Some(101)], 5), Some(0));
+        assert_eq!(choose_endpoint(&[None, Some(101), Some(101)], 5), Some(1));
+        assert_eq!(choose_endpoint(&[Some(90), Some(95), Some(101)], 5), Some(1));
+        assert_eq!(choose_endpoint(&[None, None], 5), None);
+    }
+}
@@ -0,0 +1,332 @@
+use std::str::FromStr;
+
+use alloy::{
+    primitives::{address, bytes, Address, Bytes, B256, U256},
+    providers::{Provider, ProviderBuilder},
+    sol,
+    sol_types::SolCall,
+};
+use eyre::OptionExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{
+    chain::{chain_var, ChainClient},
+    nft::{decode_redeem_calldata, redeem_calldata},
+};
+
+/// The canonical v0.7 EntryPoint, deployed at the same address on every chain.
+pub const ENTRY_POINT: Address = address!("0000000071727De22E5E9d8BAf0edAc6f37da032");
+/// Smart accounts are created with salt 0, one account per owner key.
+const ACCOUNT_SALT: U256 = U256::ZERO;
+/// A well-formed ECDSA signature that recovers to no owner, so gas can be estimated before the
+/// owner signs.
+const DUMMY_SIGNATURE: Bytes = bytes!("fffffffffffffffffffffffffffffff0000000000000000000000000000000007aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa1c");
+
+sol! {
+    #[sol(rpc)]
+    contract EntryPoint {
+        struct PackedUserOperation {
+            address sender;
+            uint256 nonce;
+            bytes initCode;
+            bytes callData;
+            bytes32 accountGasLimits;
+            uint256 preVerificationGas;
+            bytes32 gasFees;
+            bytes paymasterAndData;
+            bytes signature;
+        }
+
+        function getNonce(address sender, uint192 key) external view returns (uint256 nonce);
+        function getUserOpHash(PackedUserOperation calldata userOp) external view returns (bytes32);
+    }
+
+    /// eth-infinitism's `SimpleAccountFactory`, which deploys an account owned by one key.
+    #[sol(rpc)]
+    contract SimpleAccountFactory {
+        function createAccount(address owner, uint256 salt) external returns (address);
+        function getAddress(address owner, uint256 salt) external view returns (address);
+    }
+
+    contract SimpleAccount {
+        function execute(address dest, uint256 value, bytes calldata func) external;
+    }
+}
+
+/// A chain's ERC-4337 setup: the bundler user operations go to, the ERC-7677 paymaster that
+/// sponsors them and the factory user accounts are deployed from.
+#[derive(Debug, Clone)]
+pub struct SmartAccountConfig {
+    /// `BUNDLER_URL`.
+    pub bundler_url: String,
+    /// `PAYMASTER_URL`, the bundler itself by default.
+    pub paymaster_url: String,
+    /// `PAYMASTER_CONTEXT`, JSON passed to the paymaster, such as a sponsorship policy id.
+    pub paymaster_context: Value,
+    /// `ACCOUNT_FACTORY_ADDRESS`.
+    pub account_factory: Address,
+}
+
+impl SmartAccountConfig {
+    /// `None` when the chain has no `BUNDLER_URL`.
+    pub fn from_env(chain_id: u64) -> eyre::Result<Option<Self>> {
+        let Ok(bundler_url) = chain_var("BUNDLER_URL", chain_id) else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            paymaster_url: chain_var("PAYMASTER_URL", chain_id)
+                .unwrap_or_else(|_| bundler_url.clone()),
+            bundler_url,
+            paymaster_context: match chain_var("PAYMASTER_CONTEXT", chain_id) {
+                Ok(context) => serde_json::from_str(&context)?,
+                Err(_) => json!({}),
+            },
+            account_factory: Address::from_str(&chain_var("ACCOUNT_FACTORY_ADDRESS", chain_id)?)?,
+        }))
+    }
+}
+
+/// A v0.7 user operation in the unpacked form bundlers and paymasters take over JSON-RPC.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperation {
+    pub sender: Address,
+    pub nonce: U256,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub factory: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub factory_data: Option<Bytes>,
+    pub call_data: Bytes,
+    pub call_gas_limit: U256,
+    pub verification_gas_limit: U256,
+    pub pre_verification_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_verification_gas_limit: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_post_op_gas_limit: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_data: Option<Bytes>,
+    pub signature: Bytes,
+}
+
+/// Two 128 bit values packed into one word, the first in the high half.
+fn pack_u128s(high: U256, low: U256) -> B256 {
+    B256::from((high << 128) | low)
+}
+
+impl UserOperation {
+    /// The packed form the EntryPoint hashes and executes.
+    pub fn pack(&self) -> EntryPoint::PackedUserOperation {
+        let mut init_code = Vec::new();
+        if let Some(factory) = self.factory {
+            init_code.extend_from_slice(factory.as_slice());
+            init_code.extend_from_slice(self.factory_data.as_deref().unwrap_or_default());
+        }
+        let mut paymaster_and_data = Vec::new();
+        if let Some(paymaster) = self.paymaster {
+            let gas_limit =
+                |limit: Option<U256>| limit.unwrap_or_default().to_be_bytes::<32>()[16..].to_vec();
+            paymaster_and_data.extend_from_slice(paymaster.as_slice());
+            paymaster_and_data.extend(gas_limit(self.paymaster_verification_gas_limit));
+            paymaster_and_data.extend(gas_limit(self.paymaster_post_op_gas_limit));
+            paymaster_and_data
+                .extend_from_slice(self.paymaster_data.as_deref().unwrap_or_default());
+        }
+        EntryPoint::PackedUserOperation {
+            sender: self.sender,
+            nonce: self.nonce,
+            initCode: init_code.into(),
+            callData: self.call_data.clone(),
+            accountGasLimits: pack_u128s(self.verification_gas_limit, self.call_gas_limit),
+            preVerificationGas: self.pre_verification_gas,
+            gasFees: pack_u128s(self.max_priority_fee_per_gas, self.max_fee_per_gas),
+            paymasterAndData: paymaster_and_data.into(),
+            signature: self.signature.clone(),
+        }
+    }
+
+    fn apply_paymaster(&mut self, fields: PaymasterFields) {
+        self.paymaster = Some(fields.paymaster);
+        self.paymaster_data = Some(fields.paymaster_data);
+        if fields.paymaster_verification_gas_limit.is_some() {
+            self.paymaster_verification_gas_limit = fields.paymaster_verification_gas_limit;
+        }
+        if fields.paymaster_post_op_gas_limit.is_some() {
+            self.paymaster_post_op_gas_limit = fields.paymaster_post_op_gas_limit;
+        }
+    }
+}
+
+/// The paymaster fields of an ERC-7677 `pm_getPaymasterStubData` or `pm_getPaymasterData` reply.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PaymasterFields {
+    paymaster: Address,
+    paymaster_data: Bytes,
+    paymaster_verification_gas_limit: Option<U256>,
+    paymaster_post_op_gas_limit: Option<U256>,
+}
+
+/// An `eth_estimateUserOperationGas` reply.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GasEstimate {
+    pre_verification_gas: U256,
+    verification_gas_limit: U256,
+    call_gas_limit: U256,
+    paymaster_verification_gas_limit: Option<U256>,
+    paymaster_post_op_gas_limit: Option<U256>,
+}
+
+fn get_config(chain: &ChainClient) -> eyre::Result<&SmartAccountConfig> {
+    chain
+        .config
+        .smart_accounts
+        .as_ref()
+        .ok_or_eyre(format!("No bundler on chain {}", chain.config.chain_id))
+}
+
+async fn rpc<R: serde::de::DeserializeOwned>(
+    url: &str,
+    method: &'static str,
+    params: Value,
+) -> eyre::Result<R> {
+    let provider = ProviderBuilder::new().on_http(url.parse()?);
+    Ok(provider.raw_request(method.into(), params).await?)
+}
+
+/// The smart account `owner` gets from the chain's factory, and whether it is deployed yet.
+pub async fn get_account(chain: &ChainClient, owner: Address) -> eyre::Result<(Address, bool)> {
+    let config = get_config(chain)?;
+    let factory = SimpleAccountFactory::new(config.account_factory, chain.provider().clone());
+    let account = factory.getAddress(owner, ACCOUNT_SALT).call().await?._0;
+    let deployed = !chain.provider().get_code_at(account).await?.is_empty();
+    Ok((account, deployed))
+}
+
+/// A paymaster-sponsored user operation for `account` to redeem `token_id`, and the hash its
+/// owner signs. `owner` is needed to deploy the account alongside its first operation.
+pub async fn prepare_redeem(
+    chain: &ChainClient,
+    account: Address,
+    owner: Option<Address>,
+    token_id: U256,
+    content: String,
+) -> eyre::Result<(UserOperation, B256)> {
+    let config = get_config(chain)?;
+    let chain_id = format!("{:#x}", chain.config.chain_id);
+    let entry_point = EntryPoint::new(ENTRY_POINT, chain.provider().clone());
+    let (factory, factory_data) = if chain.provider().get_code_at(account).await?.is_empty() {
+        let owner = owner.ok_or_eyre("Account is not deployed and no owner was given")?;
+        let (predicted, _) = get_account(chain, owner).await?;
+        eyre::ensure!(predicted == account, "{} is not the smart account of {}", account, owner);
+        let create = SimpleAccountFactory::createAccountCall { owner, salt: ACCOUNT_SALT };
+        (Some(config.account_factory), Some(create.abi_encode().into()))
+    } else {
+        (None, None)
+    };
+    let redeem = redeem_calldata(chain.config.token_standard, account, token_id, content);
+    let execute = SimpleAccount::executeCall {
+        dest: chain.config.nft_address,
+        value: U256::ZERO,
+        func: redeem,
+    };
+    let (max_fee, priority_fee) = chain.config.gas.fees(chain.provider()).await?;
+    let mut user_op = UserOperation {
+        sender: account,
+        nonce: entry_point.getNonce(account, Default::default()).call().await?.nonce,
+        factory,
+        factory_data,
+        call_data: execute.abi_encode().into(),
+        max_fee_per_gas: U256::from(max_fee),
+        max_priority_fee_per_gas: U256::from(priority_fee),
+        signature: DUMMY_SIGNATURE,
+        ..Default::default()
+    };
+
+    let sponsor_params =
+        |user_op: &UserOperation| json!([user_op, ENTRY_POINT, chain_id, config.paymaster_context]);
+    let stub: PaymasterFields =
+        rpc(&config.paymaster_url, "pm_getPaymasterStubData", sponsor_params(&user_op)).await?;
+    user_op.apply_paymaster(stub);
+    let estimate: GasEstimate =
+        rpc(&config.bundler_url, "eth_estimateUserOperationGas", json!([user_op, ENTRY_POINT]))
+            .await?;
+    user_op.pre_verification_gas = estimate.pre_verification_gas;
+    user_op.verification_gas_limit = estimate.verification_gas_limit;
+    user_op.call_gas_limit = estimate.call_gas_limit;
+    user_op.paymaster_verification_gas_limit =
+        estimate.paymaster_verification_gas_limit.or(user_op.paymaster_verification_gas_limit);
+    user_op.paymaster_post_op_gas_limit =
+        estimate.paymaster_post_op_gas_limit.or(user_op.paymaster_post_op_gas_limit);
+    let sponsorship: PaymasterFields =
+        rpc(&config.paymaster_url, "pm_getPaymasterData", sponsor_params(&user_op)).await?;
+    user_op.apply_paymaster(sponsorship);
+
+    user_op.signature = Bytes::new();
+    let hash = entry_point.getUserOpHash(user_op.pack()).call().await?._0;
+    Ok((user_op, hash))
+}
+
+/// The token a user operation redeems, or `None` if it does anything other than redeem one of
+/// `nft_address`'s tokens from its own account, since the paymaster pays for whatever is sent.
+pub fn redeemed_token(chain: &ChainClient, user_op: &UserOperation) -> Option<U256> {
+    let execute = SimpleAccount::executeCall::abi_decode(&user_op.call_data, true).ok()?;
+    if execute.dest != chain.config.nft_address || execute.value != U256::ZERO {
+        return None;
+    }
+    decode_redeem_calldata(chain.config.token_standard, user_op.sender, &execute.func)
+}
+
+/// Hands a signed user operation to the bundler, returning its user operation hash.
+pub async fn send_user_operation(
+    chain: &ChainClient,
+    user_op: UserOperation,
+) -> eyre::Result<B256> {
+    let config = get_config(chain)?;
+    let hash =
+        rpc(&config.bundler_url, "eth_sendUserOperation", json!([user_op, ENTRY_POINT])).await?;
+    log::info!("Sent redeem user operation {} from {}", hash, user_op.sender);
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_gas_limits_and_paymaster_fields() {
+        let user_op = UserOperation {
+            factory: Some(Address::with_last_byte(1)),
+            factory_data: Some(Bytes::from(vec![0xab])),
+            call_gas_limit: U256::from(2),
+            verification_gas_limit: U256::from(3),
+            max_fee_per_gas: U256::from(4),
+            max_priority_fee_per_gas: U256::from(5),
+            paymaster: Some(Address::with_last_byte(6)),
+            paymaster_verification_gas_limit: Some(U256::from(7)),
+            paymaster_post_op_gas_limit: Some(U256::from(8)),
+            paymaster_data: Some(Bytes::from(vec![0xcd])),
+            ..Default::default()
+        };
+        let packed = user_op.pack();
+        assert_eq!(packed.initCode.len(), 21);
+        assert_eq!(packed.initCode[20], 0xab);
+        assert_eq!(
+            U256::from_be_bytes(packed.accountGasLimits.0),
+            (U256::from(3) << 128) | U256::from(2)
+        );
+        assert_eq!(U256::from_be_bytes(packed.gasFees.0), (U256::from(5) << 128) | U256::from(4));
+        let paymaster_and_data = packed.paymasterAndData;
+        assert_eq!(paymaster_and_data.len(), 20 + 16 + 16 + 1);
+        assert_eq!(paymaster_and_data[19], 6);
+        assert_eq!(paymaster_and_data[35], 7);
+        assert_eq!(paymaster_and_data[51], 8);
+        assert_eq!(paymaster_and_data[52], 0xcd);
+    }
+}
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use alloy::{
+    hex::ToHexExt,
+    primitives::{utils::parse_ether, Address, U256},
+    providers::Provider,
+};
+
+use super::{
+    chain::{chain_var, ChainClient},
+    nft::send_eth,
+};
+use crate::{
+    db::{lock::TrackedMutex, TeleportDB, TxRecord, TxStatus},
+    kill_switch::{ensure_enabled, SideEffect},
+    metrics,
+};
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+const DEFAULT_TOP_UP_MAX_PER_DAY: usize = 1;
+
+/// How the minter wallet tops up users' wallets with gas when they link their X account, from
+/// `TOP_UP_AMOUNT_ETH`. Only wallets holding less than `TOP_UP_BELOW_ETH` (default the amount)
+/// get one, at most `TOP_UP_MAX_PER_DAY` (default 1) times a day each. The `top_ups` kill switch
+/// turns them off.
+#[derive(Debug, Clone)]
+pub struct TopUpPolicy {
+    pub amount: U256,
+    pub below: U256,
+    pub max_per_day: usize,
+}
+
+impl TopUpPolicy {
+    /// `None` when the chain tops up no one.
+    pub fn from_env(chain_id: u64) -> eyre::Result<Option<Self>> {
+        let Ok(amount) = chain_var("TOP_UP_AMOUNT_ETH", chain_id) else {
+            return Ok(None);
+        };
+        let amount = parse_ether(&amount)?;
+        let below = match chain_var("TOP_UP_BELOW_ETH", chain_id) {
+            Ok(below) => parse_ether(&below)?,
+            Err(_) => amount,
+        };
+        let max_per_day = match chain_var("TOP_UP_MAX_PER_DAY", chain_id) {
+            Ok(max) => max.parse()?,
+            Err(_) => DEFAULT_TOP_UP_MAX_PER_DAY,
+        };
+        Ok(Some(Self { amount, below, max_per_day }))
+    }
+
+    /// Whether a wallet holding `balance`, topped up `sent_today` times in the last day, gets one.
+    pub fn allows(&self, balance: U256, sent_today: usize) -> bool {
+        balance < self.below && sent_today < self.max_per_day
+    }
+}
+
+/// Tops up `address` on `chain` if the chain's policy allows it, returning the transfer's hash.
+pub async fn top_up<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain: &ChainClient,
+    address: Address,
+) -> eyre::Result<Option<String>> {
+    let Some(policy) = &chain.config.top_up else {
+        return Ok(None);
+    };
+    ensure_enabled(SideEffect::TopUps)?;
+    let chain_id = chain.config.chain_id;
+    let now = chrono::Utc::now().timestamp();
+    let sent_today =
+        db.lock().await.count_top_ups_since(chain_id, address.to_string(), now - DAY_SECS)?;
+    let balance = chain.provider().get_balance(address).await?;
+    if !policy.allows(balance, sent_today) {
+        log::info!("Not topping up {} on chain {}: policy declined", address, chain_id);
+        return Ok(None);
+    }
+
+    let tx_hash = send_eth(chain, address, policy.amount).await?.encode_hex_with_prefix();
+    log::info!("Topped up {} on chain {} with tx hash: {}", address, chain_id, tx_hash);
+    metrics::increment("top_ups_total", &[("chain_id", &chain_id.to_string())]);
+    let tx = TxRecord {
+        chain_id,
+        kind: "top_up".to_string(),
+        status: TxStatus::Pending,
+        submitted_at: now,
+        block_number: None,
+        gas_used: None,
+        replaced_by: None,
+        requested_by: None,
+    };
+    let mut db = db.lock().await;
+    db.add_top_up(chain_id, address.to_string(), now)?;
+    db.add_tx(tx_hash.clone(), tx)?;
+    Ok(Some(tx_hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tops_up_low_wallets_within_the_daily_cap() {
+        let policy = TopUpPolicy {
+            amount: parse_ether("0.03").unwrap(),
+            below: parse_ether("0.01").unwrap(),
+            max_per_day: 2,
+        };
+        assert!(policy.allows(U256::ZERO, 0));
+        assert!(policy.allows(U256::ZERO, 1));
+        assert!(!policy.allows(U256::ZERO, 2));
+        assert!(!policy.allows(parse_ether("0.01").unwrap(), 0));
+    }
+}
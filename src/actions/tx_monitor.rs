@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use alloy::{hex::ToHexExt, providers::Provider};
+use tokio::time::{sleep, Duration};
+
+use super::chain::ChainClient;
+use crate::{
+    db::{lock::TrackedMutex, TeleportDB},
+    metrics,
+};
+
+const TX_MONITOR_INTERVAL: Duration = Duration::from_secs(15);
+const DEFAULT_STUCK_TX_TIMEOUT_SECS: u64 = 120;
+const DEFAULT_FEE_BUMP_PERCENT: u128 = 20;
+
+fn get_stuck_tx_timeout() -> Duration {
+    let secs = std::env::var("STUCK_TX_TIMEOUT_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(DEFAULT_STUCK_TX_TIMEOUT_SECS);
+    Duration::from_secs(secs)
+}
+
+pub(crate) fn get_fee_bump_percent() -> u128 {
+    std::env::var("TX_FEE_BUMP_PERCENT")
+        .ok()
+        .and_then(|percent| percent.parse().ok())
+        .unwrap_or(DEFAULT_FEE_BUMP_PERCENT)
+}
+
+/// Records receipts for the chain's tracked transactions that have been mined. Every hash of a
+/// replaced transaction is checked, since the original may land instead of its replacement.
+async fn poll_receipts<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain: &ChainClient,
+) -> eyre::Result<()> {
+    let pending = db.lock().await.get_pending_txs(chain.config.chain_id)?;
+    for tx_hash in pending {
+        let Some(receipt) = chain.provider().get_transaction_receipt(tx_hash.parse()?).await?
+        else {
+            continue;
+        };
+        db.lock().await.set_tx_mined(
+            tx_hash,
+            receipt.status(),
+            receipt.block_number,
+            receipt.gas_used as u64,
+        )?;
+    }
+    Ok(())
+}
+
+/// Watches the minter wallet's transactions on `chain`: records receipts as they are mined and
+/// resubmits any that have not been mined within `STUCK_TX_TIMEOUT_SECS` with higher fees, so
+/// one underpriced transaction does not hold up every later nonce.
+pub async fn run_tx_monitor<A: TeleportDB>(db: Arc<TrackedMutex<A>>, chain: ChainClient) {
+    let timeout = get_stuck_tx_timeout();
+    let bump_percent = get_fee_bump_percent();
+    let chain_id = chain.config.chain_id.to_string();
+    loop {
+        sleep(TX_MONITOR_INTERVAL).await;
+        if let Err(e) = poll_receipts(&db, &chain).await {
+            log::error!("Failed to poll transaction receipts: {:?}", e);
+        }
+        let replacements = match chain
+            .nonces
+            .replace_stuck(chain.submit_provider(), &chain.config.gas, timeout, bump_percent)
+            .await
+        {
+            Ok(replacements) => replacements,
+            Err(e) => {
+                log::error!("Failed to check for stuck transactions: {:?}", e);
+                continue;
+            }
+        };
+        for replacement in replacements {
+            log::warn!(
+                "Replaced stuck transaction {} (nonce {}) with {}",
+                replacement.old_tx_hash,
+                replacement.nonce,
+                replacement.new_tx_hash
+            );
+            metrics::increment("stuck_transactions_replaced_total", &[("chain_id", &chain_id)]);
+            let old_tx_hash = replacement.old_tx_hash.encode_hex_with_prefix();
+            let new_tx_hash = replacement.new_tx_hash.encode_hex_with_prefix();
+            let mut db = db.lock().await;
+            let result = db
+                .alias_pending_nft(old_tx_hash.clone(), new_tx_hash.clone())
+                .and_then(|_| db.replace_tx(old_tx_hash, new_tx_hash));
+            if let Err(e) = result {
+                log::error!("Failed to record replacement: {:?}", e);
+            }
+        }
+    }
+}
@@ -1,15 +1,29 @@
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
 use alloy::{
-    network::{Ethereum, EthereumWallet},
+    network::{Ethereum, EthereumWallet, TransactionBuilder},
+    primitives::{Address, TxHash, U256},
     providers::{
         fillers::{
             BlobGasFiller, ChainIdFiller, FillProvider, GasFiller, JoinFill, NonceFiller,
             WalletFiller,
         },
-        Identity, RootProvider,
+        Identity, Provider, RootProvider,
     },
+    rpc::types::TransactionRequest,
     transports::http::{Client, Http},
 };
 
+use super::gas::GasStrategy;
+use crate::kill_switch::{ensure_enabled, SideEffect, SideEffectDisabled};
+
+/// Gas for the zero-value transfer that cancels a transaction.
+const CANCEL_GAS_LIMIT: u128 = 21_000;
+
 pub type WalletProvider = FillProvider<
     JoinFill<
         JoinFill<
@@ -23,6 +37,268 @@ pub type WalletProvider = FillProvider<
     Ethereum,
 >;
 
+#[derive(Debug, Clone)]
+struct InFlightTx {
+    tx_hash: TxHash,
+    /// The request as built by the caller, before fees and gas were filled in.
+    request: TransactionRequest,
+    sent_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct NonceState {
+    next: Option<u64>,
+    in_flight: BTreeMap<u64, InFlightTx>,
+}
+
+impl NonceState {
+    /// Forgets in-flight transactions whose nonce has been mined.
+    fn prune(&mut self, confirmed: u64) {
+        self.in_flight = self.in_flight.split_off(&confirmed);
+    }
+
+    /// Picks the next nonce given the account's mined (`confirmed`) and mempool (`pending`)
+    /// transaction counts, forgetting in-flight transactions that have been mined.
+    fn next_nonce(&mut self, confirmed: u64, pending: u64) -> u64 {
+        self.prune(confirmed);
+        let next = self.next.map_or(pending, |next| next.max(pending));
+        self.next = Some(next);
+        next
+    }
+
+    fn record(&mut self, nonce: u64, tx_hash: TxHash, request: TransactionRequest, now: Instant) {
+        self.in_flight.insert(nonce, InFlightTx { tx_hash, request, sent_at: now });
+        self.next = Some(nonce + 1);
+    }
+
+    /// Forgets the local view so the next transaction resyncs from the node.
+    fn reset(&mut self) {
+        self.next = None;
+    }
+
+    /// In-flight transactions that were last (re)submitted more than `timeout` ago.
+    fn stuck(&self, now: Instant, timeout: Duration) -> Vec<(u64, InFlightTx)> {
+        self.in_flight
+            .iter()
+            .filter(|(_, tx)| now.duration_since(tx.sent_at) > timeout)
+            .map(|(nonce, tx)| (*nonce, tx.clone()))
+            .collect()
+    }
+}
+
+/// Fees for a replacement transaction: the previous fees raised by `bump_percent`, or the
+/// current estimate if that is higher. Nodes reject replacements that do not raise both fees
+/// (by 10% on geth).
+fn bump_fees(
+    previous: Option<(u128, u128)>,
+    estimate: (u128, u128),
+    bump_percent: u128,
+) -> (u128, u128) {
+    let (max_fee, priority_fee) = previous.map_or(estimate, |(max_fee, priority_fee)| {
+        (
+            (max_fee * (100 + bump_percent) / 100).max(estimate.0),
+            (priority_fee * (100 + bump_percent) / 100).max(estimate.1),
+        )
+    });
+    (max_fee.max(priority_fee), priority_fee)
+}
+
+/// A transaction that was resubmitted under the same nonce with higher fees.
+#[derive(Debug, Clone, Copy)]
+pub struct Replacement {
+    pub nonce: u64,
+    pub old_tx_hash: TxHash,
+    pub new_tx_hash: TxHash,
+}
+
+/// A transaction that cannot be cancelled because the minter wallet no longer has it in flight,
+/// usually because it was mined or replaced meanwhile.
+#[derive(Debug)]
+pub struct NotInFlight(pub TxHash);
+
+impl std::fmt::Display for NotInFlight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Transaction {} is not in flight", self.0)
+    }
+}
+
+impl std::error::Error for NotInFlight {}
+
+/// Hands out nonces for the shared minter wallet one transaction at a time, so concurrent
+/// `/mint` and `/redeem` requests never submit two transactions with the same nonce.
+#[derive(Debug, Clone)]
+pub struct NonceManager {
+    address: Address,
+    state: Arc<tokio::sync::Mutex<NonceState>>,
+}
+
+impl NonceManager {
+    pub fn new(address: Address) -> Self {
+        Self { address, state: Default::default() }
+    }
+
+    /// Sends `request` with the next nonce while holding the assignment lock until the
+    /// transaction has been handed to the node. A failed send resyncs the nonce from the node.
+    pub async fn send(
+        &self,
+        provider: &WalletProvider,
+        request: TransactionRequest,
+    ) -> eyre::Result<TxHash> {
+        self.send_batch(provider, vec![request]).await.pop().expect("One result per request")
+    }
+
+    /// Sends `requests` back to back with consecutive nonces, without waiting for any of them
+    /// to be mined. Stops at the first failed send, since later nonces would leave a gap; the
+    /// requests after it fail as unsent.
+    pub async fn send_batch(
+        &self,
+        provider: &WalletProvider,
+        requests: Vec<TransactionRequest>,
+    ) -> Vec<eyre::Result<TxHash>> {
+        if let Err(e) = ensure_enabled(SideEffect::Transactions) {
+            return requests.iter().map(|_| Err(SideEffectDisabled(e.0).into())).collect();
+        }
+        let mut state = self.state.lock().await;
+        let counts = async {
+            let confirmed = provider.get_transaction_count(self.address).await?;
+            let pending = provider.get_transaction_count(self.address).pending().await?;
+            eyre::Ok((confirmed, pending))
+        };
+        let mut next = match counts.await {
+            Ok((confirmed, pending)) => Some(state.next_nonce(confirmed, pending)),
+            Err(e) => {
+                log::error!("Failed to read minter nonce: {:?}", e);
+                None
+            }
+        };
+        let mut results = Vec::with_capacity(requests.len());
+        for request in requests {
+            let Some(nonce) = next else {
+                results.push(Err(eyre::eyre!("Transaction not sent")));
+                continue;
+            };
+            match provider.send_transaction(request.clone().with_nonce(nonce)).await {
+                Ok(pending_tx) => {
+                    let tx_hash = *pending_tx.tx_hash();
+                    state.record(nonce, tx_hash, request, Instant::now());
+                    results.push(Ok(tx_hash));
+                    next = Some(nonce + 1);
+                }
+                Err(e) => {
+                    state.reset();
+                    results.push(Err(e.into()));
+                    next = None;
+                }
+            }
+        }
+        results
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    pub async fn in_flight(&self) -> usize {
+        self.state.lock().await.in_flight.len()
+    }
+
+    /// Resubmits every in-flight transaction older than `timeout` under its nonce with fees
+    /// bumped by `bump_percent`, within the caps of `gas`. Holds the assignment lock so no new
+    /// nonce is handed out meanwhile.
+    pub async fn replace_stuck(
+        &self,
+        provider: &WalletProvider,
+        gas: &GasStrategy,
+        timeout: Duration,
+        bump_percent: u128,
+    ) -> eyre::Result<Vec<Replacement>> {
+        ensure_enabled(SideEffect::Transactions)?;
+        let mut state = self.state.lock().await;
+        let confirmed = provider.get_transaction_count(self.address).await?;
+        state.prune(confirmed);
+        let mut replacements = Vec::new();
+        for (nonce, tx) in state.stuck(Instant::now(), timeout) {
+            let estimate = gas.fees(provider).await?;
+            let sent = provider.get_transaction_by_hash(tx.tx_hash).await?;
+            let previous = sent
+                .as_ref()
+                .and_then(|sent| Some((sent.max_fee_per_gas?, sent.max_priority_fee_per_gas?)));
+            let (max_fee, priority_fee) = gas.cap(bump_fees(previous, estimate, bump_percent));
+            if previous.is_some_and(|(previous_max_fee, _)| max_fee <= previous_max_fee) {
+                log::warn!("Cannot replace transaction {}, fee cap reached", tx.tx_hash);
+                continue;
+            }
+            let mut request = tx
+                .request
+                .clone()
+                .with_nonce(nonce)
+                .with_max_fee_per_gas(max_fee)
+                .with_max_priority_fee_per_gas(priority_fee);
+            if let Some(sent) = &sent {
+                request = request.with_gas_limit(sent.gas);
+            }
+            match provider.send_transaction(request).await {
+                Ok(pending_tx) => {
+                    let new_tx_hash = *pending_tx.tx_hash();
+                    state.record(nonce, new_tx_hash, tx.request, Instant::now());
+                    replacements.push(Replacement { nonce, old_tx_hash: tx.tx_hash, new_tx_hash });
+                }
+                // Usually the original was mined in the meantime; the next pass prunes it.
+                Err(e) => log::warn!("Failed to replace transaction {}: {:?}", tx.tx_hash, e),
+            }
+        }
+        Ok(replacements)
+    }
+
+    /// Replaces the in-flight transaction `tx_hash` with a zero-value transfer to the minter
+    /// wallet under the same nonce, with fees bumped by `bump_percent` within the caps of `gas`.
+    /// Later stuck transaction passes bump the cancellation, not the original.
+    pub async fn cancel(
+        &self,
+        provider: &WalletProvider,
+        gas: &GasStrategy,
+        tx_hash: TxHash,
+        bump_percent: u128,
+    ) -> eyre::Result<Replacement> {
+        ensure_enabled(SideEffect::Transactions)?;
+        let mut state = self.state.lock().await;
+        let confirmed = provider.get_transaction_count(self.address).await?;
+        state.prune(confirmed);
+        let nonce = state
+            .in_flight
+            .iter()
+            .find(|(_, tx)| tx.tx_hash == tx_hash)
+            .map(|(nonce, _)| *nonce)
+            .ok_or(NotInFlight(tx_hash))?;
+        let estimate = gas.fees(provider).await?;
+        let previous = provider
+            .get_transaction_by_hash(tx_hash)
+            .await?
+            .and_then(|sent| Some((sent.max_fee_per_gas?, sent.max_priority_fee_per_gas?)));
+        let (max_fee, priority_fee) = gas.cap(bump_fees(previous, estimate, bump_percent));
+        if previous.is_some_and(|(previous_max_fee, _)| max_fee <= previous_max_fee) {
+            eyre::bail!("Cannot cancel transaction {}, fee cap reached", tx_hash);
+        }
+        let request = TransactionRequest::default()
+            .with_to(self.address)
+            .with_value(U256::ZERO)
+            .with_gas_limit(CANCEL_GAS_LIMIT);
+        let pending_tx = provider
+            .send_transaction(
+                request
+                    .clone()
+                    .with_nonce(nonce)
+                    .with_max_fee_per_gas(max_fee)
+                    .with_max_priority_fee_per_gas(priority_fee),
+            )
+            .await?;
+        let new_tx_hash = *pending_tx.tx_hash();
+        let cancellation = InFlightTx { tx_hash: new_tx_hash, request, sent_at: Instant::now() };
+        state.in_flight.insert(nonce, cancellation);
+        Ok(Replacement { nonce, old_tx_hash: tx_hash, new_tx_hash })
+    }
+}
+
 // pub fn gen_sk() -> eyre::Result<String> {
 //     let mut buf = [0u8; 32];
 //     getrandom::getrandom(&mut buf)?;
@@ -30,6 +306,44 @@ pub type WalletProvider = FillProvider<
 //     Ok(sk)
 // }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nonces_are_sequential_and_resync_after_reset() {
+        let mut state = NonceState::default();
+        let now = Instant::now();
+        assert_eq!(state.next_nonce(5, 5), 5);
+        state.record(5, TxHash::with_last_byte(5), TransactionRequest::default(), now);
+        // A lagging node must not hand out a nonce that is already in flight.
+        assert_eq!(state.next_nonce(5, 5), 6);
+        state.record(6, TxHash::with_last_byte(6), TransactionRequest::default(), now);
+        assert_eq!(state.next_nonce(6, 7), 7);
+        assert_eq!(state.in_flight.len(), 1);
+        state.reset();
+        assert_eq!(state.next_nonce(7, 7), 7);
+        assert!(state.in_flight.is_empty());
+    }
+
+    #[test]
+    fn stuck_transactions_are_replaced_with_higher_fees() {
+        let mut state = NonceState::default();
+        let sent_at = Instant::now();
+        state.record(1, TxHash::with_last_byte(1), TransactionRequest::default(), sent_at);
+        state.record(2, TxHash::with_last_byte(2), TransactionRequest::default(), sent_at);
+        let later = sent_at + Duration::from_secs(60);
+        state.record(2, TxHash::with_last_byte(3), TransactionRequest::default(), later);
+
+        let stuck = state.stuck(later + Duration::from_secs(1), Duration::from_secs(30));
+        assert_eq!(stuck.iter().map(|(nonce, _)| *nonce).collect::<Vec<_>>(), vec![1]);
+
+        assert_eq!(bump_fees(Some((100, 10)), (50, 5), 20), (120, 12));
+        assert_eq!(bump_fees(Some((100, 10)), (200, 30), 20), (200, 30));
+        assert_eq!(bump_fees(None, (200, 30), 20), (200, 30));
+    }
+}
+
 // #[cfg(test)]
 // mod tests {
 //     use std::str::FromStr;
@@ -0,0 +1,1367 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    str::FromStr,
+    sync::{Mutex, OnceLock},
+};
+
+use alloy::{
+    hex,
+    primitives::{Address, Signature},
+};
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{FromRequestParts, Query, State},
+    http::{header, request::Parts, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    access_list::normalize_subject,
+    actions::{
+        chain::TokenStandard,
+        rebuild::{self, RebuildProgress},
+    },
+    db::{
+        backup::BackupKey, lock::LockReport, AccessList, AccessListEntry, AdminAction,
+        AdminAuditEntry, CreatorSignals, FailedEvent, LedgerEntry, MintPayment, PendingApproval,
+        RedemptionRecord, ReputationSignal, ReputationSnapshot, TeleportDB, NFT,
+    },
+    endpoints::SharedState,
+    error_codes::{ApiError, ErrorCode},
+    kill_switch::{self, SideEffect},
+    mode::ServiceMode,
+    oai, reports,
+    reputation::{self, Standing},
+    sgx_attest::{sgx_attest, EnclaveMeasurement},
+    twitter::tier::{ApiTier, Capabilities},
+};
+
+pub const ADMIN_TOKEN_HEADER: &str = "x-admin-token";
+/// Longest an admin token may stay valid, so a leaked one cannot be replayed for long.
+const MAX_ADMIN_TOKEN_TTL_SECS: u64 = 60 * 60;
+
+/// What an admin API caller may do. Every admin is also a viewer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Read-only access to status and timelines, for support staff.
+    Viewer,
+    /// Viewer plus day-to-day operations such as replaying dead letters and flipping kill
+    /// switches.
+    Operator,
+    /// Viewer plus the audit log and the mint access lists.
+    Compliance,
+    /// Everything, including key rotation.
+    Admin,
+}
+
+impl FromStr for Role {
+    type Err = eyre::Report;
+
+    fn from_str(role: &str) -> eyre::Result<Self> {
+        match role {
+            "viewer" => Ok(Self::Viewer),
+            "operator" => Ok(Self::Operator),
+            "compliance" => Ok(Self::Compliance),
+            "admin" => Ok(Self::Admin),
+            _ => eyre::bail!("Unknown admin role {}", role),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    ReadStatus,
+    ReadTimeline,
+    ReadAuditLog,
+    ReplayDeadLetters,
+    PreviewModeration,
+    ReviewReports,
+    RevokeRedemptions,
+    RotateKeys,
+    ManageAccessLists,
+    ToggleKillSwitches,
+    /// Exporting the database, user keys included, or restoring it.
+    ManageBackups,
+    /// Rebuilding the marketplace index and creator stats from the event journal.
+    RebuildIndex,
+    /// Mapping an on-chain address back to the X account registered at it.
+    LookupUsers,
+}
+
+impl Role {
+    pub fn allows(self, permission: Permission) -> bool {
+        use Permission::*;
+        match self {
+            Self::Admin => true,
+            Self::Viewer => matches!(permission, ReadStatus | ReadTimeline),
+            Self::Operator => matches!(
+                permission,
+                ReadStatus |
+                    ReadTimeline |
+                    ReplayDeadLetters |
+                    PreviewModeration |
+                    ReviewReports |
+                    ToggleKillSwitches |
+                    LookupUsers
+            ),
+            Self::Compliance => {
+                matches!(
+                    permission,
+                    ReadStatus |
+                        ReadTimeline |
+                        ReadAuditLog |
+                        ReviewReports |
+                        RevokeRedemptions |
+                        ManageAccessLists |
+                        LookupUsers
+                )
+            }
+        }
+    }
+}
+
+fn parse_role_assignments(
+    admin_addresses: &str,
+    roles: &str,
+) -> eyre::Result<BTreeMap<Address, Role>> {
+    fn entries(list: &str) -> impl Iterator<Item = &str> {
+        list.split(',').map(str::trim).filter(|entry| !entry.is_empty())
+    }
+    let mut assignments = BTreeMap::new();
+    for address in entries(admin_addresses) {
+        assignments.insert(Address::from_str(address)?, Role::Admin);
+    }
+    for entry in entries(roles) {
+        let (address, role) = entry
+            .split_once(':')
+            .ok_or_else(|| eyre::eyre!("Expected <address>:<role>, got {}", entry))?;
+        assignments.insert(Address::from_str(address)?, Role::from_str(role)?);
+    }
+    Ok(assignments)
+}
+
+/// Reads the admin API signers and their roles. `ADMIN_ADDRESSES` (comma separated) are full
+/// admins and `ADMIN_ROLES` assigns other roles as `<address>:<role>` pairs. Both must come from
+/// a trusted file such as `teleport.env`, never from a passthrough variable.
+pub fn admin_roles() -> eyre::Result<BTreeMap<Address, Role>> {
+    parse_role_assignments(
+        &std::env::var("ADMIN_ADDRESSES").unwrap_or_default(),
+        &std::env::var("ADMIN_ROLES").unwrap_or_default(),
+    )
+}
+
+/// `<mr_enclave>.<mr_signer>.<expires_at>.<signature>`, where the signature is an EIP-191
+/// signature by an admin over [`AdminToken::message`]. An admin only mints one after checking the
+/// enclave's quote from `/admin/handshake`, so a token is useless to any other process.
+#[derive(Debug)]
+pub struct AdminToken {
+    mr_enclave: String,
+    mr_signer: String,
+    expires_at: u64,
+    signature: Signature,
+}
+
+impl FromStr for AdminToken {
+    type Err = eyre::Report;
+
+    fn from_str(token: &str) -> eyre::Result<Self> {
+        let parts: Vec<&str> = token.split('.').collect();
+        let [mr_enclave, mr_signer, expires_at, signature] = parts[..] else {
+            eyre::bail!("Malformed admin token");
+        };
+        Ok(Self {
+            mr_enclave: mr_enclave.to_lowercase(),
+            mr_signer: mr_signer.to_lowercase(),
+            expires_at: expires_at.parse()?,
+            signature: Signature::from_str(signature)?,
+        })
+    }
+}
+
+impl AdminToken {
+    pub fn message(mr_enclave: &str, mr_signer: &str, expires_at: u64) -> String {
+        format!("teleport-admin:{}:{}:{}", mr_enclave, mr_signer, expires_at)
+    }
+
+    /// Returns the admin that signed the token and their role if it is bound to `measurement`,
+    /// unexpired and expires within [`MAX_ADMIN_TOKEN_TTL_SECS`].
+    pub fn verify(
+        &self,
+        measurement: &EnclaveMeasurement,
+        admins: &BTreeMap<Address, Role>,
+        now: u64,
+    ) -> eyre::Result<(Address, Role)> {
+        if self.mr_enclave != hex::encode(measurement.mr_enclave) ||
+            self.mr_signer != hex::encode(measurement.mr_signer)
+        {
+            eyre::bail!("Admin token is bound to a different enclave");
+        }
+        if self.expires_at < now {
+            eyre::bail!("Admin token expired");
+        }
+        if self.expires_at - now > MAX_ADMIN_TOKEN_TTL_SECS {
+            eyre::bail!("Admin token outlives the {}s limit", MAX_ADMIN_TOKEN_TTL_SECS);
+        }
+        let message = Self::message(&self.mr_enclave, &self.mr_signer, self.expires_at);
+        let signer = self.signature.recover_address_from_msg(message)?;
+        let role = admins.get(&signer).ok_or_else(|| eyre::eyre!("{} is not an admin", signer))?;
+        Ok((signer, *role))
+    }
+}
+
+/// Extracts the admin behind a request's [`ADMIN_TOKEN_HEADER`], rejecting the request otherwise.
+/// Handlers still check the role with [`Admin::require`].
+pub struct Admin {
+    pub address: Address,
+    pub role: Role,
+}
+
+impl Admin {
+    pub fn require(&self, permission: Permission) -> Result<(), StatusCode> {
+        if self.role.allows(permission) {
+            Ok(())
+        } else {
+            log::warn!("{} ({:?}) lacks {:?}", self.address, self.role, permission);
+            Err(StatusCode::FORBIDDEN)
+        }
+    }
+
+    /// Records `action` against this admin in the audit log.
+    pub async fn audit<A: TeleportDB>(
+        &self,
+        shared_state: &SharedState<A>,
+        action: &str,
+        detail: String,
+    ) -> Result<(), StatusCode> {
+        let entry = AdminAuditEntry {
+            at: chrono::Utc::now().timestamp(),
+            admin: self.address.to_string(),
+            role: format!("{:?}", self.role).to_lowercase(),
+            action: action.to_string(),
+            detail,
+        };
+        shared_state.db.lock().await.add_admin_audit_entry(entry).map_err(|e| {
+            log::error!("Failed to write admin audit entry: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+    }
+}
+
+#[async_trait]
+impl<A: TeleportDB> FromRequestParts<SharedState<A>> for Admin {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        shared_state: &SharedState<A>,
+    ) -> Result<Self, Self::Rejection> {
+        let measurement = shared_state.measurement.ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+        let token = parts
+            .headers
+            .get(ADMIN_TOKEN_HEADER)
+            .and_then(|token| token.to_str().ok())
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+        let token = AdminToken::from_str(token).map_err(|_| StatusCode::UNAUTHORIZED)?;
+        let now = chrono::Utc::now().timestamp() as u64;
+        match token.verify(&measurement, &shared_state.admins, now) {
+            Ok((address, role)) => Ok(Self { address, role }),
+            Err(e) => {
+                log::warn!("Rejected admin token: {:?}", e);
+                Err(StatusCode::UNAUTHORIZED)
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct HandshakeQuery {
+    nonce: String,
+}
+
+#[derive(Serialize)]
+pub struct HandshakeResponse {
+    mr_enclave: String,
+    mr_signer: String,
+    quote: String,
+}
+
+/// Returns this enclave's measurement and a quote over the caller's nonce, which an admin checks
+/// before signing a token for that measurement.
+pub async fn handshake<A: TeleportDB>(
+    State(shared_state): State<SharedState<A>>,
+    Query(query): Query<HandshakeQuery>,
+) -> Result<Json<HandshakeResponse>, StatusCode> {
+    let measurement = shared_state.measurement.ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let nonce = hex::decode(&query.nonce).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let quote = sgx_attest(nonce).map_err(|e| {
+        log::error!("Failed to produce handshake quote: {:?}", e);
+        StatusCode::SERVICE_UNAVAILABLE
+    })?;
+    Ok(Json(HandshakeResponse {
+        mr_enclave: hex::encode(measurement.mr_enclave),
+        mr_signer: hex::encode(measurement.mr_signer),
+        quote: hex::encode(quote),
+    }))
+}
+
+#[derive(Serialize)]
+pub struct StatusResponse {
+    admin: Address,
+    role: Role,
+    chain_ids: Vec<u64>,
+    /// The last block the indexer finished, by chain.
+    last_processed_blocks: BTreeMap<u64, u64>,
+    mode: ServiceMode,
+    twitter_tier: ApiTier,
+    twitter_capabilities: Capabilities,
+    kill_switches: BTreeMap<SideEffect, bool>,
+}
+
+pub async fn status<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+) -> Result<Json<StatusResponse>, StatusCode> {
+    admin.require(Permission::ReadStatus)?;
+    let last_processed_blocks = {
+        let db = shared_state.db.lock().await;
+        shared_state
+            .chains
+            .keys()
+            .filter_map(|chain_id| {
+                let cursor = db.get_last_processed_block(*chain_id).ok()?;
+                Some((*chain_id, cursor.block_number))
+            })
+            .collect()
+    };
+    Ok(Json(StatusResponse {
+        admin: admin.address,
+        role: admin.role,
+        chain_ids: shared_state.chains.keys().copied().collect(),
+        last_processed_blocks,
+        mode: shared_state.mode,
+        twitter_tier: shared_state.twitter_builder.tier,
+        twitter_capabilities: shared_state.twitter_builder.capabilities,
+        kill_switches: kill_switches_state(),
+    }))
+}
+
+fn kill_switches_state() -> BTreeMap<SideEffect, bool> {
+    SideEffect::ALL.into_iter().map(|effect| (effect, kill_switch::is_enabled(effect))).collect()
+}
+
+/// Whether each side effect is enabled, i.e. its kill switch is off.
+pub async fn kill_switches<A: TeleportDB>(
+    admin: Admin,
+    State(_): State<SharedState<A>>,
+) -> Result<Json<BTreeMap<SideEffect, bool>>, StatusCode> {
+    admin.require(Permission::ReadStatus)?;
+    Ok(Json(kill_switches_state()))
+}
+
+#[derive(Deserialize)]
+pub struct KillSwitchRequest {
+    side_effect: SideEffect,
+    enabled: bool,
+}
+
+/// Switches a side effect off or back on. Work that needs it waits in its queue meanwhile. The
+/// setting outlives restarts and overrides `KILL_SWITCHES`.
+pub async fn set_kill_switch<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Json(request): Json<KillSwitchRequest>,
+) -> Result<StatusCode, StatusCode> {
+    admin.require(Permission::ToggleKillSwitches)?;
+    let details = format!("{} enabled={}", request.side_effect.name(), request.enabled);
+    admin.audit(&shared_state, "set_kill_switch", details).await?;
+    shared_state
+        .db
+        .lock()
+        .await
+        .set_kill_switch(request.side_effect.name().to_string(), request.enabled)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    kill_switch::set_enabled(request.side_effect, request.enabled);
+    if request.enabled {
+        log::info!("Kill switch released: {}", request.side_effect.name());
+    } else {
+        log::warn!("Kill switch on: {}", request.side_effect.name());
+    }
+    Ok(StatusCode::OK)
+}
+
+/// Who holds and waits on the DB lock right now, and how long each call site has waited for and
+/// held it since startup.
+pub async fn lock_report<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+) -> Result<Json<LockReport>, StatusCode> {
+    admin.require(Permission::ReadStatus)?;
+    Ok(Json(shared_state.db.report()))
+}
+
+const DEFAULT_AUDIT_LOG_LIMIT: usize = 100;
+
+#[derive(Deserialize)]
+pub struct AuditLogQuery {
+    limit: Option<usize>,
+}
+
+/// Most recent admin actions first.
+pub async fn audit_log<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<Json<Vec<AdminAuditEntry>>, StatusCode> {
+    admin.require(Permission::ReadAuditLog)?;
+    let limit = query.limit.unwrap_or(DEFAULT_AUDIT_LOG_LIMIT);
+    admin.audit(&shared_state, "read_audit_log", format!("limit={}", limit)).await?;
+    let entries = shared_state
+        .db
+        .lock()
+        .await
+        .get_admin_audit_log(limit)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(entries))
+}
+
+/// How long a proposed action waits for its second approver.
+const APPROVAL_TTL_SECS: i64 = 60 * 60;
+
+fn required_permission(action: &AdminAction) -> Permission {
+    match action {
+        AdminAction::ReplayDeadLetter { .. } => Permission::ReplayDeadLetters,
+        AdminAction::RevokeRedemption { .. } => Permission::RevokeRedemptions,
+        AdminAction::ExportBackup | AdminAction::RestoreBackup { .. } => Permission::ManageBackups,
+    }
+}
+
+/// Backups move through their own endpoints, so they are approved by the admin transferring them.
+fn approved_by_transfer(action: &AdminAction) -> bool {
+    matches!(action, AdminAction::ExportBackup | AdminAction::RestoreBackup { .. })
+}
+
+/// Approvals whose action is running. One is only removed once its action succeeded, so this
+/// keeps two admins approving it at once from running it twice in the meantime.
+fn approvals_in_flight() -> &'static Mutex<BTreeSet<String>> {
+    static APPROVALS_IN_FLIGHT: OnceLock<Mutex<BTreeSet<String>>> = OnceLock::new();
+    APPROVALS_IN_FLIGHT.get_or_init(Default::default)
+}
+
+/// Holds an approval in [`approvals_in_flight`] until dropped.
+struct ApprovalInFlight(String);
+
+impl ApprovalInFlight {
+    fn claim(approval_id: &str) -> Option<Self> {
+        approvals_in_flight()
+            .lock()
+            .unwrap()
+            .insert(approval_id.to_string())
+            .then(|| Self(approval_id.to_string()))
+    }
+}
+
+impl Drop for ApprovalInFlight {
+    fn drop(&mut self) {
+        approvals_in_flight().lock().unwrap().remove(&self.0);
+    }
+}
+
+/// Drops the proposals nobody approved in time.
+async fn prune_approvals<A: TeleportDB>(shared_state: &SharedState<A>, now: i64) {
+    match shared_state.db.lock().await.prune_pending_approvals(now) {
+        Ok(0) => {}
+        Ok(pruned) => log::info!("Pruned {} expired admin approvals", pruned),
+        Err(e) => log::error!("Failed to prune expired admin approvals: {:?}", e),
+    }
+}
+
+/// A proposal can only be approved, before it expires, by an admin other than its proposer.
+fn check_approver(approval: &PendingApproval, approver: Address, now: i64) -> eyre::Result<()> {
+    if approval.expires_at <= now {
+        eyre::bail!("Approval expired");
+    }
+    if approval.proposed_by == approver.to_string() {
+        eyre::bail!("Actions need a second admin to approve them");
+    }
+    Ok(())
+}
+
+/// Claims the proposal `approval_id` for `admin` to approve, so nobody else runs it meanwhile.
+async fn claim_approval<A: TeleportDB>(
+    shared_state: &SharedState<A>,
+    admin: &Admin,
+    approval_id: &str,
+    now: i64,
+) -> Result<(ApprovalInFlight, PendingApproval), StatusCode> {
+    let in_flight = ApprovalInFlight::claim(approval_id).ok_or(StatusCode::CONFLICT)?;
+    let approval = shared_state
+        .db
+        .lock()
+        .await
+        .get_pending_approvals()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .find(|(id, _)| id == approval_id)
+        .map(|(_, approval)| approval)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    admin.require(required_permission(&approval.action))?;
+    check_approver(&approval, admin.address, now).map_err(|e| {
+        log::warn!("Rejected approval of {} by {}: {:?}", approval_id, admin.address, e);
+        StatusCode::FORBIDDEN
+    })?;
+    Ok((in_flight, approval))
+}
+
+async fn execute<A: TeleportDB>(
+    shared_state: &SharedState<A>,
+    action: &AdminAction,
+    now: i64,
+) -> eyre::Result<()> {
+    match action {
+        AdminAction::ReplayDeadLetter { event_id } => {
+            let mut db = shared_state.db.lock().await;
+            let event = db.get_failed_event(event_id.clone())?;
+            if event.next_attempt_at.is_some() {
+                eyre::bail!("Event {} is not dead-lettered", event_id);
+            }
+            let event = FailedEvent { attempts: 0, next_attempt_at: Some(now), ..event };
+            db.upsert_failed_event(event_id.clone(), event)
+        }
+        AdminAction::RevokeRedemption { chain_id, token_id } => {
+            let chain = shared_state
+                .chain(Some(*chain_id))
+                .ok_or_else(|| eyre::eyre!("Unknown chain {}", chain_id))?;
+            // An edition token id is shared by every copy, so one redemption cannot be burned.
+            if chain.config.token_standard != TokenStandard::Erc721 {
+                eyre::bail!("Redemptions on chain {} cannot be revoked", chain_id);
+            }
+            let mut db = shared_state.db.lock().await;
+            db.get_tweet(*chain_id, token_id.clone())?;
+            db.queue_burn(*chain_id, token_id.clone(), true)
+        }
+        AdminAction::ExportBackup | AdminAction::RestoreBackup { .. } => {
+            eyre::bail!("Backups are approved by downloading or uploading them")
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ProposeResponse {
+    approval_id: String,
+    expires_at: i64,
+}
+
+/// Queues a dangerous action until a second admin approves it.
+pub async fn propose_action<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Json(action): Json<AdminAction>,
+) -> Result<Json<ProposeResponse>, StatusCode> {
+    admin.require(required_permission(&action))?;
+    let now = chrono::Utc::now().timestamp();
+    prune_approvals(&shared_state, now).await;
+    let approval_id = cuid::cuid2();
+    let expires_at = now + APPROVAL_TTL_SECS;
+    let detail = format!("{} {:?}", approval_id, action);
+    let approval = PendingApproval { action, proposed_by: admin.address.to_string(), expires_at };
+    shared_state
+        .db
+        .lock()
+        .await
+        .add_pending_approval(approval_id.clone(), approval)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    admin.audit(&shared_state, "propose_action", detail).await?;
+    Ok(Json(ProposeResponse { approval_id, expires_at }))
+}
+
+#[derive(Serialize)]
+pub struct PendingApprovalResponse {
+    approval_id: String,
+    #[serde(flatten)]
+    approval: PendingApproval,
+}
+
+pub async fn pending_approvals<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+) -> Result<Json<Vec<PendingApprovalResponse>>, StatusCode> {
+    admin.require(Permission::ReadStatus)?;
+    prune_approvals(&shared_state, chrono::Utc::now().timestamp()).await;
+    let approvals = shared_state
+        .db
+        .lock()
+        .await
+        .get_pending_approvals()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(
+        approvals
+            .into_iter()
+            .map(|(approval_id, approval)| PendingApprovalResponse { approval_id, approval })
+            .collect(),
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct ApproveQuery {
+    id: String,
+}
+
+/// Runs a proposed action once a second admin with the same permission approves it. The proposal
+/// stays open, for another try, if the action fails.
+pub async fn approve_action<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Query(query): Query<ApproveQuery>,
+) -> Result<StatusCode, StatusCode> {
+    let now = chrono::Utc::now().timestamp();
+    let (_in_flight, approval) = claim_approval(&shared_state, &admin, &query.id, now).await?;
+    if approved_by_transfer(&approval.action) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    admin
+        .audit(
+            &shared_state,
+            "approve_action",
+            format!("{} {:?} proposed by {}", query.id, approval.action, approval.proposed_by),
+        )
+        .await?;
+    execute(&shared_state, &approval.action, now).await.map_err(|e| {
+        log::error!("Failed to execute approved action {}: {:?}", query.id, e);
+        StatusCode::CONFLICT
+    })?;
+    if let Err(e) = shared_state.db.lock().await.remove_pending_approval(query.id.clone()) {
+        log::error!("Failed to remove approved action {}: {:?}", query.id, e);
+    }
+    Ok(StatusCode::OK)
+}
+
+const DEFAULT_PREVIEW_SAMPLE_SIZE: i64 = 50;
+const MAX_PREVIEW_SAMPLE_SIZE: i64 = 500;
+/// How many flipped redemptions are listed in a preview.
+const PREVIEW_EXAMPLES: usize = 20;
+
+/// Moderation settings to try out. Unset fields keep the current settings.
+#[derive(Deserialize)]
+pub struct ModerationPreviewRequest {
+    prompt_version: Option<String>,
+    precheck_threshold: Option<f32>,
+    sample_size: Option<i64>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct ModerationPreview {
+    evaluated: usize,
+    flipped_to_unsafe: usize,
+    flipped_to_safe: usize,
+    errors: usize,
+    /// `(chain_id, token_id)` of some redemptions whose verdict would change.
+    examples: Vec<(i64, i32)>,
+}
+
+impl ModerationPreview {
+    fn record(&mut self, redemption: (i64, i32), was_safe: bool, now_safe: eyre::Result<bool>) {
+        let now_safe = match now_safe {
+            Ok(now_safe) => now_safe,
+            Err(e) => {
+                log::warn!("Failed to re-moderate redemption {:?}: {:?}", redemption, e);
+                self.errors += 1;
+                return;
+            }
+        };
+        self.evaluated += 1;
+        match (was_safe, now_safe) {
+            (true, false) => self.flipped_to_unsafe += 1,
+            (false, true) => self.flipped_to_safe += 1,
+            _ => return,
+        }
+        if self.examples.len() < PREVIEW_EXAMPLES {
+            self.examples.push(redemption);
+        }
+    }
+}
+
+/// Re-moderates a random sample of past redemptions under the proposed settings without
+/// recording anything, and reports how many verdicts would change.
+pub async fn preview_moderation<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Json(request): Json<ModerationPreviewRequest>,
+) -> Result<Json<ModerationPreview>, ApiError> {
+    admin.require(Permission::PreviewModeration)?;
+    let prompt_version = request.prompt_version.unwrap_or_else(oai::stable_prompt_version);
+    oai::get_prompt_version(&prompt_version).map_err(|_| StatusCode::BAD_REQUEST)?;
+    if oai::calls_openai() && !kill_switch::is_enabled(SideEffect::OpenAi) {
+        return Err(ErrorCode::FeatureDisabled.into());
+    }
+    let sample_size = request
+        .sample_size
+        .unwrap_or(DEFAULT_PREVIEW_SAMPLE_SIZE)
+        .clamp(1, MAX_PREVIEW_SAMPLE_SIZE);
+    admin
+        .audit(
+            &shared_state,
+            "preview_moderation",
+            format!(
+                "prompt_version={} precheck_threshold={:?} sample_size={}",
+                prompt_version, request.precheck_threshold, sample_size
+            ),
+        )
+        .await?;
+
+    let sample = shared_state.marketplace.sample_redemptions(sample_size).await.map_err(|e| {
+        log::error!("Failed to sample redemptions: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let mut preview = ModerationPreview::default();
+    for redemption in sample {
+        let was_safe = shared_state
+            .db
+            .lock()
+            .await
+            .get_moderation_record(redemption.chain_id as u64, redemption.token_id.to_string())
+            .map_or(true, |record| record.safe);
+        let now_safe = oai::dry_run_moderation(
+            &redemption.content,
+            &redemption.safeguard,
+            &prompt_version,
+            request.precheck_threshold,
+        )
+        .await;
+        preview.record((redemption.chain_id, redemption.token_id), was_safe, now_safe);
+    }
+    Ok(Json(preview))
+}
+
+/// Latest reasons shown per reported tweet.
+const REPORT_SUMMARY_REASONS: usize = 5;
+
+#[derive(Serialize)]
+pub struct ReportSummary {
+    chain_id: u64,
+    token_id: String,
+    tweet_id: Option<String>,
+    creator: Option<String>,
+    strikes: u32,
+    held: bool,
+    reports: usize,
+    latest_reasons: Vec<String>,
+}
+
+/// Reported tweets, most reported first, with their creators' standing.
+pub async fn abuse_reports<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+) -> Result<Json<Vec<ReportSummary>>, StatusCode> {
+    admin.require(Permission::ReviewReports)?;
+    admin.audit(&shared_state, "read_abuse_reports", String::new()).await?;
+    let db = shared_state.db.lock().await;
+    let reported = db.get_abuse_reports().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut summaries = Vec::with_capacity(reported.len());
+    for ((chain_id, token_id), mut tweet_reports) in reported {
+        let creator = db
+            .get_nft_by_token_id(chain_id, token_id.clone())
+            .and_then(|nft| db.get_user_by_address(nft.address))
+            .ok()
+            .and_then(|user| user.x_id);
+        let strikes = match &creator {
+            Some(creator) => db.get_creator_strikes(creator.clone()).unwrap_or_default(),
+            None => 0,
+        };
+        tweet_reports.sort_by_key(|report| std::cmp::Reverse(report.at));
+        summaries.push(ReportSummary {
+            chain_id,
+            tweet_id: db.get_tweet(chain_id, token_id.clone()).ok(),
+            token_id,
+            creator,
+            strikes,
+            held: reports::is_held(strikes),
+            reports: tweet_reports.len(),
+            latest_reasons: tweet_reports
+                .into_iter()
+                .take(REPORT_SUMMARY_REASONS)
+                .map(|report| report.reason)
+                .collect(),
+        });
+    }
+    summaries.sort_by_key(|summary| std::cmp::Reverse(summary.reports));
+    Ok(Json(summaries))
+}
+
+#[derive(Deserialize)]
+pub struct ReleaseCreatorRequest {
+    x_id: String,
+}
+
+/// Clears a creator's strikes and reputation signals so their redemptions are posted again.
+/// Redemptions held while they were on hold stay dead-lettered until replayed.
+pub async fn release_creator<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Json(request): Json<ReleaseCreatorRequest>,
+) -> Result<StatusCode, StatusCode> {
+    admin.require(Permission::ReviewReports)?;
+    admin.audit(&shared_state, "release_creator", format!("x_id={}", request.x_id)).await?;
+    shared_state
+        .db
+        .lock()
+        .await
+        .clear_creator_strikes(request.x_id.clone())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    reputation::record(&shared_state.db, request.x_id, ReputationSignal::Released)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct ApproveMentionsRequest {
+    chain_id: u64,
+    token_id: String,
+}
+
+/// Lets a redemption held for mentioning handles its policy does not allow be posted. It stays
+/// dead-lettered until replayed.
+pub async fn approve_mentions<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Json(request): Json<ApproveMentionsRequest>,
+) -> Result<StatusCode, StatusCode> {
+    admin.require(Permission::ReviewReports)?;
+    let details = format!("chain_id={} token_id={}", request.chain_id, request.token_id);
+    admin.audit(&shared_state, "approve_mentions", details).await?;
+    shared_state
+        .db
+        .lock()
+        .await
+        .approve_mentions(request.chain_id, request.token_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct ApproveQuorumDisputeRequest {
+    chain_id: u64,
+    token_id: String,
+}
+
+/// Approves a redemption its moderation quorum disagreed on. The dead-lettered event still has to
+/// be replayed to post it.
+pub async fn approve_quorum_dispute<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Json(request): Json<ApproveQuorumDisputeRequest>,
+) -> Result<StatusCode, StatusCode> {
+    admin.require(Permission::ReviewReports)?;
+    let details = format!("chain_id={} token_id={}", request.chain_id, request.token_id);
+    admin.audit(&shared_state, "approve_quorum_dispute", details).await?;
+    shared_state
+        .db
+        .lock()
+        .await
+        .approve_quorum_dispute(request.chain_id, request.token_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(StatusCode::OK)
+}
+
+/// Largest backup [`restore_backup`] accepts.
+pub const MAX_BACKUP_BYTES: usize = 1 << 30;
+
+fn backup_key() -> Result<BackupKey, StatusCode> {
+    BackupKey::from_secret().map_err(|e| {
+        log::warn!("Backups unavailable: {:?}", e);
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+/// Streams the whole database out of the enclave, encrypted under `BACKUP_KEY`, for
+/// [`restore_backup`] on another host. Approves a proposed `export_backup` action, so it takes a
+/// second admin to the one who proposed it.
+pub async fn export_backup<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Query(query): Query<ApproveQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    admin.require(Permission::ManageBackups)?;
+    let key = backup_key()?;
+    let now = chrono::Utc::now().timestamp();
+    let (_in_flight, approval) = claim_approval(&shared_state, &admin, &query.id, now).await?;
+    if approval.action != AdminAction::ExportBackup {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    admin
+        .audit(
+            &shared_state,
+            "export_backup",
+            format!("{} proposed by {}", query.id, approval.proposed_by),
+        )
+        .await?;
+    let mut db = shared_state.db.lock().await;
+    // Removed before exporting, so the backup does not carry an approval to export it again.
+    db.remove_pending_approval(query.id.clone()).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let backup = key.export(&*db).map_err(|e| {
+        log::error!("Failed to export backup: {:?}", e);
+        if let Err(e) = db.add_pending_approval(query.id.clone(), approval) {
+            log::error!("Failed to reopen approval {}: {:?}", query.id, e);
+        }
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(([(header::CONTENT_TYPE, "application/octet-stream")], backup))
+}
+
+/// Restores a backup from [`export_backup`]. Approves a proposed `restore_backup` action for the
+/// backup's SHA-256 digest, so it takes a second admin to the one who proposed it. Only a fresh
+/// instance, with no users yet, can be restored, so a live database is never overwritten. Restart
+/// the instance afterwards, so the restored block cursors and kill switches take effect.
+pub async fn restore_backup<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Query(query): Query<ApproveQuery>,
+    backup: Bytes,
+) -> Result<StatusCode, StatusCode> {
+    admin.require(Permission::ManageBackups)?;
+    let key = backup_key()?;
+    let now = chrono::Utc::now().timestamp();
+    let (_in_flight, approval) = claim_approval(&shared_state, &admin, &query.id, now).await?;
+    let sha256 = hex::encode(openssl::sha::sha256(&backup));
+    if approval.action != (AdminAction::RestoreBackup { sha256 }) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let mut db = shared_state.db.lock().await;
+    if db.count_users().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)? > 0 {
+        return Err(StatusCode::CONFLICT);
+    }
+    // The restore replaces the pending approvals, this one included, with the backup's.
+    key.restore(&mut *db, &backup).map_err(|e| {
+        log::warn!("Rejected backup: {:?}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+    drop(db);
+    // Audited after the restore, which replaces the audit log with the backup's.
+    admin
+        .audit(
+            &shared_state,
+            "restore_backup",
+            format!("{} proposed by {}, {} bytes", query.id, approval.proposed_by, backup.len()),
+        )
+        .await?;
+    log::warn!("Database restored from a backup; restart to resume from it");
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct RebuildRequest {
+    chain_id: Option<u64>,
+    from_block: u64,
+}
+
+/// Starts rebuilding a chain's marketplace listings, redemption tweet ids and creator stats by
+/// replaying its processed events from `from_block`, for repairing them after a schema or
+/// handler bug. Follow it with `GET /admin/rebuild`.
+pub async fn start_rebuild<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Json(request): Json<RebuildRequest>,
+) -> Result<StatusCode, StatusCode> {
+    admin.require(Permission::RebuildIndex)?;
+    let chain = shared_state.chain(request.chain_id).ok_or(StatusCode::BAD_REQUEST)?.clone();
+    // Replays up to where this chain's indexer is, never another chain's cursor.
+    let to_block = shared_state
+        .db
+        .lock()
+        .await
+        .get_last_processed_block(chain.config.chain_id)
+        .map_err(|_| StatusCode::CONFLICT)?
+        .block_number;
+    if request.from_block > to_block {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let details = format!("chain_id={} from_block={}", chain.config.chain_id, request.from_block);
+    admin.audit(&shared_state, "start_rebuild", details).await?;
+    let started = rebuild::start(
+        shared_state.db.clone(),
+        chain,
+        shared_state.marketplace.clone(),
+        request.from_block,
+        to_block,
+    );
+    Ok(if started { StatusCode::ACCEPTED } else { StatusCode::CONFLICT })
+}
+
+/// The running or last rebuild.
+pub async fn rebuild_progress<A: TeleportDB>(
+    admin: Admin,
+    State(_): State<SharedState<A>>,
+) -> Result<Json<RebuildProgress>, StatusCode> {
+    admin.require(Permission::ReadStatus)?;
+    Ok(Json(rebuild::progress()))
+}
+
+#[derive(Deserialize)]
+pub struct ReputationQuery {
+    x_id: String,
+}
+
+#[derive(Serialize)]
+pub struct CreatorReputation {
+    score: u32,
+    standing: Standing,
+    signals: CreatorSignals,
+    history: Vec<ReputationSnapshot>,
+}
+
+/// A creator's current score and standing, the signals behind it and how it got there.
+pub async fn creator_reputation<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Query(query): Query<ReputationQuery>,
+) -> Result<Json<CreatorReputation>, StatusCode> {
+    admin.require(Permission::ReviewReports)?;
+    admin.audit(&shared_state, "read_reputation", format!("x_id={}", query.x_id)).await?;
+    let db = shared_state.db.lock().await;
+    let signals = db
+        .get_creator_signals(query.x_id.clone())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let history =
+        db.get_reputation_history(query.x_id).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let score = reputation::score(&signals);
+    Ok(Json(CreatorReputation { score, standing: Standing::of(score), signals, history }))
+}
+
+#[derive(Deserialize)]
+pub struct LedgerQuery {
+    address: String,
+}
+
+/// Deposits seen arriving at a user address, for tracing where unexpected funds came from.
+pub async fn deposit_ledger<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Query(query): Query<LedgerQuery>,
+) -> Result<Json<Vec<LedgerEntry>>, StatusCode> {
+    admin.require(Permission::ReadTimeline)?;
+    admin.audit(&shared_state, "read_ledger", format!("address={}", query.address)).await?;
+    let ledger = shared_state
+        .db
+        .lock()
+        .await
+        .get_ledger(query.address)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(ledger))
+}
+
+/// Every address and x_id on the mint allowlist or denylist.
+pub async fn access_list<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+) -> Result<Json<Vec<AccessListEntry>>, StatusCode> {
+    admin.require(Permission::ManageAccessLists)?;
+    let entries = shared_state
+        .db
+        .lock()
+        .await
+        .get_access_list()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(entries))
+}
+
+#[derive(Deserialize)]
+pub struct AccessListRequest {
+    /// An address or x_id.
+    subject: String,
+    list: AccessList,
+    #[serde(default)]
+    reason: String,
+}
+
+/// Allows or denies minting for an address or x_id, replacing any entry it already has.
+pub async fn set_access_list_entry<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Json(request): Json<AccessListRequest>,
+) -> Result<StatusCode, StatusCode> {
+    admin.require(Permission::ManageAccessLists)?;
+    let subject = normalize_subject(&request.subject).ok_or(StatusCode::BAD_REQUEST)?;
+    let details = format!("{} {:?} reason={}", subject, request.list, request.reason);
+    admin.audit(&shared_state, "set_access_list_entry", details).await?;
+    let entry = AccessListEntry {
+        subject,
+        list: request.list,
+        reason: request.reason,
+        added_by: admin.address.to_string(),
+        added_at: chrono::Utc::now().timestamp(),
+    };
+    shared_state
+        .db
+        .lock()
+        .await
+        .set_access_list_entry(entry)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct AccessListRemoval {
+    subject: String,
+}
+
+pub async fn remove_access_list_entry<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Json(request): Json<AccessListRemoval>,
+) -> Result<StatusCode, StatusCode> {
+    admin.require(Permission::ManageAccessLists)?;
+    let subject = normalize_subject(&request.subject).ok_or(StatusCode::BAD_REQUEST)?;
+    admin.audit(&shared_state, "remove_access_list_entry", subject.clone()).await?;
+    let removed = shared_state
+        .db
+        .lock()
+        .await
+        .remove_access_list_entry(subject)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(if removed { StatusCode::OK } else { StatusCode::NOT_FOUND })
+}
+
+/// Mint fees received, with the mints each payment paid for.
+pub async fn mint_earnings<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+) -> Result<Json<Vec<MintPayment>>, StatusCode> {
+    admin.require(Permission::ReadTimeline)?;
+    admin.audit(&shared_state, "read_earnings", String::new()).await?;
+    let payments = shared_state
+        .db
+        .lock()
+        .await
+        .get_mint_payments()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(payments))
+}
+
+const DEFAULT_PAGE_LIMIT: usize = 50;
+const MAX_PAGE_LIMIT: usize = 500;
+
+fn page_limit(limit: Option<usize>) -> usize {
+    limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT)
+}
+
+/// One page of a listing, with the cursor that fetches the next unless this is the last.
+#[derive(Serialize)]
+pub struct Page<T> {
+    items: Vec<T>,
+    next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    fn new(items: Vec<T>, limit: usize, cursor: impl FnOnce(&T) -> String) -> Self {
+        let next_cursor = if items.len() == limit { items.last().map(cursor) } else { None };
+        Self { items, next_cursor }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PageQuery {
+    cursor: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct UserSummary {
+    address: String,
+    x_id: Option<String>,
+    ens_name: Option<String>,
+}
+
+/// Registered users in address order, without their X tokens.
+pub async fn list_users<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Query(query): Query<PageQuery>,
+) -> Result<Json<Page<UserSummary>>, StatusCode> {
+    admin.require(Permission::ReadTimeline)?;
+    admin.audit(&shared_state, "list_users", String::new()).await?;
+    let limit = page_limit(query.limit);
+    let users = shared_state
+        .db
+        .lock()
+        .await
+        .list_users(query.cursor, limit)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let users = users
+        .into_iter()
+        .map(|(address, user)| UserSummary { address, x_id: user.x_id, ens_name: user.ens_name })
+        .collect();
+    Ok(Json(Page::new(users, limit, |user| user.address.clone())))
+}
+
+#[derive(Deserialize)]
+pub struct UserByAddressQuery {
+    address: String,
+}
+
+/// The user registered at an on-chain address, matched however the address is cased. It ties a
+/// wallet to an X account, so it is served at `/user_by_address` only to support roles holding
+/// [`Permission::LookupUsers`], and every lookup is audited.
+pub async fn user_by_address<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Query(query): Query<UserByAddressQuery>,
+) -> Result<Json<UserSummary>, StatusCode> {
+    admin.require(Permission::LookupUsers)?;
+    admin.audit(&shared_state, "user_by_address", query.address.clone()).await?;
+    let user = shared_state
+        .db
+        .lock()
+        .await
+        .get_user_by_address(query.address.clone())
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok(Json(UserSummary { address: query.address, x_id: user.x_id, ens_name: user.ens_name }))
+}
+
+#[derive(Serialize)]
+pub struct NftSummary {
+    nft_id: String,
+    #[serde(flatten)]
+    nft: NFT,
+}
+
+/// Minted NFTs on every chain, in id order.
+pub async fn list_nfts<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Query(query): Query<PageQuery>,
+) -> Result<Json<Page<NftSummary>>, StatusCode> {
+    admin.require(Permission::ReadTimeline)?;
+    admin.audit(&shared_state, "list_nfts", String::new()).await?;
+    let limit = page_limit(query.limit);
+    let nfts = shared_state
+        .db
+        .lock()
+        .await
+        .list_nfts(query.cursor, limit)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let nfts = nfts.into_iter().map(|(nft_id, nft)| NftSummary { nft_id, nft }).collect();
+    Ok(Json(Page::new(nfts, limit, |nft| nft.nft_id.clone())))
+}
+
+#[derive(Deserialize)]
+pub struct RedemptionPageQuery {
+    chain_id: Option<u64>,
+    cursor: Option<String>,
+    limit: Option<usize>,
+}
+
+/// A chain's moderated redemptions in token id order, rejected ones included.
+pub async fn list_redemptions<A: TeleportDB>(
+    admin: Admin,
+    State(shared_state): State<SharedState<A>>,
+    Query(query): Query<RedemptionPageQuery>,
+) -> Result<Json<Page<RedemptionRecord>>, StatusCode> {
+    admin.require(Permission::ReadTimeline)?;
+    let chain_id = query.chain_id.unwrap_or(shared_state.default_chain_id);
+    admin.audit(&shared_state, "list_redemptions", format!("chain_id={}", chain_id)).await?;
+    let limit = page_limit(query.limit);
+    let redemptions = shared_state
+        .db
+        .lock()
+        .await
+        .list_redemptions(chain_id, query.cursor, limit)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(Page::new(redemptions, limit, |redemption| redemption.token_id.clone())))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::signers::{local::PrivateKeySigner, SignerSync};
+
+    use super::*;
+
+    #[test]
+    fn admin_token_is_bound_to_measurement() -> eyre::Result<()> {
+        let admin = PrivateKeySigner::random();
+        let measurement = EnclaveMeasurement { mr_enclave: [1; 32], mr_signer: [2; 32] };
+        let (mr_enclave, mr_signer) =
+            (hex::encode(measurement.mr_enclave), hex::encode(measurement.mr_signer));
+        let sign = |expires_at: u64| -> eyre::Result<AdminToken> {
+            let message = AdminToken::message(&mr_enclave, &mr_signer, expires_at);
+            let signature = admin.sign_message_sync(message.as_bytes())?;
+            let signature = hex::encode(signature.as_bytes());
+            AdminToken::from_str(&format!(
+                "{}.{}.{}.{}",
+                mr_enclave, mr_signer, expires_at, signature
+            ))
+        };
+        let token = sign(100)?;
+
+        let admins = BTreeMap::from([(admin.address(), Role::Viewer)]);
+        assert_eq!(token.verify(&measurement, &admins, 50)?, (admin.address(), Role::Viewer));
+        assert!(token.verify(&measurement, &admins, 101).is_err());
+        assert!(token.verify(&measurement, &BTreeMap::new(), 50).is_err());
+        let look_alike = EnclaveMeasurement { mr_enclave: [3; 32], ..measurement };
+        assert!(token.verify(&look_alike, &admins, 50).is_err());
+        let long_lived = sign(50 + MAX_ADMIN_TOKEN_TTL_SECS + 1)?;
+        assert!(long_lived.verify(&measurement, &admins, 50).is_err());
+        assert!(long_lived.verify(&measurement, &admins, 51).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn roles_grant_permissions() -> eyre::Result<()> {
+        let viewer = Address::with_last_byte(1);
+        let admin = Address::with_last_byte(2);
+        let roles = parse_role_assignments(&admin.to_string(), &format!(" {}:viewer ", viewer))?;
+        assert_eq!(roles[&viewer], Role::Viewer);
+        assert_eq!(roles[&admin], Role::Admin);
+        assert!(parse_role_assignments("", &format!("{}:root", viewer)).is_err());
+
+        assert!(Role::Viewer.allows(Permission::ReadTimeline));
+        assert!(!Role::Viewer.allows(Permission::ReplayDeadLetters));
+        assert!(Role::Operator.allows(Permission::ReplayDeadLetters));
+        assert!(!Role::Operator.allows(Permission::RotateKeys));
+        assert!(Role::Operator.allows(Permission::ToggleKillSwitches));
+        assert!(!Role::Viewer.allows(Permission::ToggleKillSwitches));
+        assert!(Role::Compliance.allows(Permission::ReadAuditLog));
+        assert!(Role::Compliance.allows(Permission::ManageAccessLists));
+        assert!(Role::Admin.allows(Permission::RotateKeys));
+        assert!(!Role::Compliance.allows(Permission::ManageBackups));
+        assert!(!Role::Operator.allows(Permission::RebuildIndex));
+        assert!(Role::Operator.allows(Permission::LookupUsers));
+        assert!(!Role::Viewer.allows(Permission::LookupUsers));
+        Ok(())
+    }
+
+    #[test]
+    fn approvals_need_a_second_admin() {
+        let proposer = Address::with_last_byte(1);
+        let approval = PendingApproval {
+            action: AdminAction::ReplayDeadLetter { event_id: "0x1:0".to_string() },
+            proposed_by: proposer.to_string(),
+            expires_at: 100,
+        };
+        assert!(check_approver(&approval, proposer, 50).is_err());
+        assert!(check_approver(&approval, Address::with_last_byte(2), 50).is_ok());
+        assert!(check_approver(&approval, Address::with_last_byte(2), 100).is_err());
+    }
+
+    #[test]
+    fn moderation_preview_counts_flips() {
+        let mut preview = ModerationPreview::default();
+        preview.record((1, 1), true, Ok(true));
+        preview.record((1, 2), true, Ok(false));
+        preview.record((1, 3), false, Ok(true));
+        preview.record((1, 4), true, Err(eyre::eyre!("timeout")));
+        assert_eq!(
+            preview,
+            ModerationPreview {
+                evaluated: 3,
+                flipped_to_unsafe: 1,
+                flipped_to_safe: 1,
+                errors: 1,
+                examples: vec![(1, 2), (1, 3)],
+            }
+        );
+    }
+}
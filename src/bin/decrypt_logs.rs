@@ -0,0 +1,24 @@
+use std::{
+    fs::File,
+    io::{self, BufReader},
+};
+
+use openssl::pkey::PKey;
+use teleport::log_sink::decrypt_log;
+
+/// Usage: decrypt_logs <operator private key pem> <encrypted log file>
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 3 {
+        eprintln!("Usage: {} <private_key.pem> <encrypted.log>", args[0]);
+        std::process::exit(1);
+    }
+
+    let private_key_pem = std::fs::read(&args[1]).expect("Failed to read private key");
+    let private_key =
+        PKey::private_key_from_pem(&private_key_pem).expect("Failed to parse private key");
+    let file = File::open(&args[2]).expect("Failed to open encrypted log");
+
+    decrypt_log(BufReader::new(file), &mut io::stdout(), &private_key)
+        .expect("Failed to decrypt log");
+}
@@ -0,0 +1,250 @@
+use std::{collections::BTreeMap, future::Future, ops::Deref, sync::Arc};
+
+use alloy::primitives::{Address, U256};
+use axum::{extract::FromRef, http::StatusCode};
+
+use crate::{
+    actions::{
+        chain::{ChainClient, ChainConfig},
+        gas::GasEstimate,
+        nft::{estimate_mint, estimate_redeem, get_nft_owner, is_nft_holder},
+        royalty::get_royalty,
+        smart_account::get_account,
+    },
+    db::{
+        lock::{TrackedGuard, TrackedMutex},
+        marketplace::{CreatorDailyStats, MarketplaceIndex, TokenOwner},
+        RoyaltyInfo, TeleportDB,
+    },
+    endpoints::{resolve_request_address, SharedState},
+    sgx_attest::EnclaveMeasurement,
+    twitter::builder::TwitterBuilder,
+};
+
+/// The service database, for reading only: a locked [`ReadDb`] derefs to `&A`, never `&mut A`.
+pub struct ReadDb<A>(Arc<TrackedMutex<A>>);
+
+impl<A> Clone for ReadDb<A> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<A> ReadDb<A> {
+    #[track_caller]
+    pub fn lock(&self) -> impl Future<Output = ReadGuard<'_, A>> {
+        let guard = self.0.lock();
+        async move { ReadGuard(guard.await) }
+    }
+}
+
+impl<A: TeleportDB> ReadDb<A> {
+    /// Caches a royalty read from the chain, the one write a read handler makes. It only saves
+    /// later requests the RPC call, and holds nothing the chain doesn't.
+    pub async fn cache_royalty(
+        &self,
+        chain_id: u64,
+        token_id: String,
+        royalty: RoyaltyInfo,
+    ) -> eyre::Result<()> {
+        self.0.lock().await.set_royalty_info(chain_id, token_id, royalty)
+    }
+}
+
+pub struct ReadGuard<'a, A>(TrackedGuard<'a, A>);
+
+impl<A> Deref for ReadGuard<'_, A> {
+    type Target = A;
+
+    fn deref(&self) -> &A {
+        &self.0
+    }
+}
+
+/// The marketplace index, for reading only.
+#[derive(Clone)]
+pub struct ReadMarketplace(Arc<dyn MarketplaceIndex>);
+
+impl ReadMarketplace {
+    pub async fn get_creator_daily_stats(
+        &self,
+        creator_user_id: String,
+        from: String,
+        to: String,
+    ) -> eyre::Result<Vec<CreatorDailyStats>> {
+        self.0.get_creator_daily_stats(creator_user_id, from, to).await
+    }
+
+    pub async fn find_token_owner(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> eyre::Result<Option<TokenOwner>> {
+        self.0.find_token_owner(chain_id, token_id).await
+    }
+
+    pub async fn find_redemption_policy(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> eyre::Result<Option<String>> {
+        self.0.find_redemption_policy(chain_id, token_id).await
+    }
+}
+
+/// A chain as read handlers see it: its configuration and the calls they make against it. The
+/// client underneath holds the minter wallet, but nothing here signs or sends with it.
+#[derive(Clone, Copy)]
+pub struct ReadChain<'a>(&'a ChainClient);
+
+impl<'a> ReadChain<'a> {
+    pub fn new(chain: &'a ChainClient) -> Self {
+        Self(chain)
+    }
+
+    pub fn config(&self) -> &'a ChainConfig {
+        &self.0.config
+    }
+
+    /// Whether `address` holds `token_id` now.
+    pub async fn is_holder(&self, token_id: String, address: Address) -> eyre::Result<bool> {
+        is_nft_holder(self.0, token_id, address).await
+    }
+
+    pub async fn owner(&self, token_id: U256) -> eyre::Result<Option<Address>> {
+        get_nft_owner(self.0, token_id).await
+    }
+
+    pub async fn royalty(&self, token_id: U256) -> eyre::Result<(Address, u64)> {
+        get_royalty(self.0, token_id).await
+    }
+
+    pub async fn estimate_mint(
+        &self,
+        recipient: Address,
+        x_id: String,
+        policy: String,
+    ) -> eyre::Result<GasEstimate> {
+        estimate_mint(self.0, recipient, x_id, policy).await
+    }
+
+    pub async fn estimate_redeem(
+        &self,
+        token_id: String,
+        holder: Address,
+        content: String,
+    ) -> eyre::Result<GasEstimate> {
+        estimate_redeem(self.0, token_id, holder, content).await
+    }
+
+    /// The ERC-4337 smart account `owner` gets, and whether it is deployed yet.
+    pub async fn smart_account(&self, owner: Address) -> eyre::Result<(Address, bool)> {
+        get_account(self.0, owner).await
+    }
+}
+
+/// What read-only handlers get instead of [`SharedState`]: the stores, read-only, the public
+/// configuration and the chains as [`ReadChain`]s. It holds no minter key or X credentials, and
+/// the chain clients stay private, so nothing reachable from a read handler can sign, send or
+/// post, and the only write is [`ReadDb::cache_royalty`].
+#[derive(Clone)]
+pub struct ReadCtx<A: TeleportDB> {
+    pub db: ReadDb<A>,
+    pub marketplace: ReadMarketplace,
+    pub default_chain_id: u64,
+    pub chain_ids: Vec<u64>,
+    pub measurement: Option<EnclaveMeasurement>,
+    pub max_batch_mint: usize,
+    chains: BTreeMap<u64, ChainClient>,
+}
+
+impl<A: TeleportDB> ReadCtx<A> {
+    /// The requested chain, or the default chain when the request doesn't name one.
+    pub fn chain(&self, chain_id: Option<u64>) -> Option<ReadChain<'_>> {
+        self.chains.get(&chain_id.unwrap_or(self.default_chain_id)).map(ReadChain)
+    }
+
+    /// Reads an address a request names directly or by ENS name, and the name if it was one.
+    pub async fn resolve_address(
+        &self,
+        address_or_name: &str,
+    ) -> Result<(String, Option<String>), StatusCode> {
+        resolve_request_address(&self.chains, address_or_name).await
+    }
+}
+
+impl<A: TeleportDB> FromRef<SharedState<A>> for ReadCtx<A> {
+    fn from_ref(shared_state: &SharedState<A>) -> Self {
+        Self {
+            db: ReadDb(shared_state.db.clone()),
+            marketplace: ReadMarketplace(shared_state.marketplace.clone()),
+            default_chain_id: shared_state.default_chain_id,
+            chain_ids: shared_state.chains.keys().copied().collect(),
+            measurement: shared_state.measurement,
+            max_batch_mint: shared_state.max_batch_mint,
+            chains: shared_state.chains.clone(),
+        }
+    }
+}
+
+/// What redeem handlers get: the chains, whose minter wallet relays the holder's signed redeem,
+/// and the service database. Redemptions are authorized by the holder's signature alone, so
+/// neither the minter key itself nor any session or X credential is reachable from here.
+#[derive(Clone)]
+pub struct RedeemCtx<A: TeleportDB> {
+    pub db: Arc<TrackedMutex<A>>,
+    pub chains: BTreeMap<u64, ChainClient>,
+    pub default_chain_id: u64,
+}
+
+impl<A: TeleportDB> RedeemCtx<A> {
+    /// The requested chain, or the default chain when the request doesn't name one.
+    pub fn chain(&self, chain_id: Option<u64>) -> Option<&ChainClient> {
+        self.chains.get(&chain_id.unwrap_or(self.default_chain_id))
+    }
+}
+
+impl<A: TeleportDB> FromRef<SharedState<A>> for RedeemCtx<A> {
+    fn from_ref(shared_state: &SharedState<A>) -> Self {
+        Self {
+            db: shared_state.db.clone(),
+            chains: shared_state.chains.clone(),
+            default_chain_id: shared_state.default_chain_id,
+        }
+    }
+}
+
+/// What mint handlers get: the chains whose minter wallet sends the mint, the service database,
+/// and the X client that resolves a policy's allowed mentions with the creator's own tokens. Of
+/// the enclave signer only its address is here, for checking the contract lets it mint.
+#[derive(Clone)]
+pub struct MintCtx<A: TeleportDB> {
+    pub db: Arc<TrackedMutex<A>>,
+    pub chains: BTreeMap<u64, ChainClient>,
+    pub default_chain_id: u64,
+    pub minter: Address,
+    pub tee_url: String,
+    pub twitter_builder: TwitterBuilder,
+    pub max_batch_mint: usize,
+}
+
+impl<A: TeleportDB> MintCtx<A> {
+    /// The requested chain, or the default chain when the request doesn't name one.
+    pub fn chain(&self, chain_id: Option<u64>) -> Option<&ChainClient> {
+        self.chains.get(&chain_id.unwrap_or(self.default_chain_id))
+    }
+}
+
+impl<A: TeleportDB> FromRef<SharedState<A>> for MintCtx<A> {
+    fn from_ref(shared_state: &SharedState<A>) -> Self {
+        Self {
+            db: shared_state.db.clone(),
+            chains: shared_state.chains.clone(),
+            default_chain_id: shared_state.default_chain_id,
+            minter: shared_state.signer.address(),
+            tee_url: shared_state.tee_url.clone(),
+            twitter_builder: shared_state.twitter_builder.clone(),
+            max_batch_mint: shared_state.max_batch_mint,
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use alloy::hex;
+use eyre::OptionExt;
+
+use super::{
+    sealed::{decrypt, encrypt, NONCE_LEN},
+    snapshot, TeleportDB,
+};
+use crate::secrets;
+
+/// Starts every backup, so a sealed database file or raw snapshot is not mistaken for one.
+const BACKUP_MAGIC: &[u8] = b"teleport-backup-v1\n";
+
+/// The AES-256-GCM key backups are encrypted with, from the `BACKUP_KEY` secret (64 hex
+/// characters). The instance exporting a backup and the one restoring it must both be provisioned
+/// with it: unlike the storage key it is not bound to one enclave, or a backup could never leave
+/// its host.
+pub struct BackupKey([u8; 32]);
+
+impl BackupKey {
+    pub fn from_secret() -> eyre::Result<Self> {
+        let key = hex::decode(secrets::get_secret("BACKUP_KEY")?.trim())?;
+        Ok(Self(key.try_into().map_err(|_| eyre::eyre!("BACKUP_KEY must be 32 bytes"))?))
+    }
+
+    /// The whole of `db`, user keys included, as a checksummed snapshot encrypted under this key.
+    pub fn export<A: TeleportDB>(&self, db: &A) -> eyre::Result<Vec<u8>> {
+        let (nonce, sealed) = encrypt(&self.0, &snapshot::encode(&db.export_snapshot()?))?;
+        Ok([BACKUP_MAGIC, nonce.as_slice(), sealed.as_slice()].concat())
+    }
+
+    /// Replaces everything in `db` with a backup from [`BackupKey::export`], failing without
+    /// touching `db` if the backup was tampered with or encrypted under another key.
+    pub fn restore<A: TeleportDB>(&self, db: &mut A, backup: &[u8]) -> eyre::Result<()> {
+        let sealed = backup.strip_prefix(BACKUP_MAGIC).ok_or_eyre("Not a Teleport backup")?;
+        if sealed.len() < NONCE_LEN {
+            eyre::bail!("Backup too short");
+        }
+        let (nonce, sealed) = sealed.split_at(NONCE_LEN);
+        let snapshot = decrypt(&self.0, nonce, sealed).map_err(|_| {
+            eyre::eyre!("Failed to decrypt the backup: wrong BACKUP_KEY or a tampered backup")
+        })?;
+        db.import_snapshot(&snapshot::decode(&snapshot)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{in_memory::InMemoryDB, User};
+
+    #[test]
+    fn backup_restores_on_a_fresh_db() -> eyre::Result<()> {
+        let mut db = InMemoryDB::new();
+        let user = User { x_id: Some("1".to_string()), ..Default::default() };
+        db.add_user("0x1".to_string(), user.clone())?;
+        let key = BackupKey([7u8; 32]);
+        let backup = key.export(&db)?;
+
+        let mut restored = InMemoryDB::new();
+        assert!(BackupKey([8u8; 32]).restore(&mut restored, &backup).is_err());
+        let mut tampered = backup.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(key.restore(&mut restored, &tampered).is_err());
+        assert_eq!(restored.count_users()?, 0);
+
+        key.restore(&mut restored, &backup)?;
+        assert_eq!(restored.get_user_by_x_id("1".to_string())?, user);
+        Ok(())
+    }
+}
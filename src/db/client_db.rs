@@ -1,107 +1,647 @@
+use std::collections::BTreeMap;
+
+use deadpool_postgres::{Manager, ManagerConfig, Object, Pool, RecyclingMethod};
 use rustls::ClientConfig;
-use tokio_postgres::Client;
+use serde::Serialize;
 use tokio_postgres_rustls::MakeRustlsConnect;
 
+use super::{
+    marketplace::{get_database_url, CreatorDailyStats, RedemptionSample, TokenOwner},
+    storage::Redemption,
+};
+use crate::metrics;
+
+const DEFAULT_DATABASE_POOL_SIZE: usize = 16;
+
+/// Connections kept open to Postgres, from `DATABASE_POOL_SIZE`.
+fn get_pool_size() -> eyre::Result<usize> {
+    match std::env::var("DATABASE_POOL_SIZE") {
+        Ok(size) => match size.parse() {
+            Ok(size) => Ok(size),
+            Err(_) => eyre::bail!("Invalid DATABASE_POOL_SIZE {}", size),
+        },
+        Err(_) => Ok(DEFAULT_DATABASE_POOL_SIZE),
+    }
+}
+
+/// Which Postgres schema the enclave writes while migrating from the legacy `NftIndex` /
+/// `RedeemedIndex` tables to the internal `teleport` schema, from `DB_WRITE_MODE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WriteMode {
+    #[default]
+    Legacy,
+    /// Writes both schemas and reads the legacy one; failed internal writes are only reported.
+    Dual,
+    /// Writes and reads the internal schema only. `User` counters stay on the legacy table.
+    Internal,
+}
+
+impl WriteMode {
+    pub fn from_env() -> eyre::Result<Self> {
+        match std::env::var("DB_WRITE_MODE").as_deref() {
+            Err(_) | Ok("legacy") => Ok(Self::Legacy),
+            Ok("dual") => Ok(Self::Dual),
+            Ok("internal") => Ok(Self::Internal),
+            Ok(mode) => eyre::bail!("Unknown DB_WRITE_MODE {}", mode),
+        }
+    }
+
+    fn writes_legacy(self) -> bool {
+        self != Self::Internal
+    }
+
+    fn writes_internal(self) -> bool {
+        self != Self::Legacy
+    }
+}
+
+/// Recomputes one UTC day of `creator_daily_stats` from the raw event tables. Idempotent, so a
+/// day can be rolled up again after late writes.
+const CREATOR_DAILY_ROLLUP: &str = "
+    INSERT INTO teleport.creator_daily_stats (day, creator_user_id, mints, redemptions)
+    SELECT $1::text::date, creator_user_id, SUM(mints), SUM(redemptions)
+    FROM (
+        SELECT creator_user_id, COUNT(*) AS mints, 0 AS redemptions
+        FROM teleport.mints
+        WHERE minted_at >= $1::text::date AND minted_at < $1::text::date + 1
+        GROUP BY creator_user_id
+        UNION ALL
+        SELECT creator_user_id, 0 AS mints, COUNT(*) AS redemptions
+        FROM teleport.redemptions
+        WHERE redeemed_at >= $1::text::date AND redeemed_at < $1::text::date + 1
+        GROUP BY creator_user_id
+    ) AS events
+    GROUP BY creator_user_id
+    ON CONFLICT (day, creator_user_id)
+    DO UPDATE SET mints = EXCLUDED.mints, redemptions = EXCLUDED.redemptions
+";
+
+/// Replaces the content of redemptions older than the retention period with its SHA-256, in both
+/// schemas, and records each run that purged anything in `content_purges`.
+const CONTENT_PURGE: &str = "
+    WITH purged AS (
+        UPDATE teleport.redemptions
+        SET content_hash = encode(sha256(convert_to(content, 'UTF8')), 'hex'),
+            content = '',
+            content_purged_at = now()
+        WHERE chain_id = $1
+            AND content_purged_at IS NULL
+            AND redeemed_at < now() - make_interval(days => $2)
+        RETURNING id
+    ), legacy AS (
+        UPDATE \"RedeemedIndex\" SET \"content\" = ''
+        WHERE \"id\" IN (SELECT id FROM purged)
+        RETURNING \"id\"
+    )
+    INSERT INTO teleport.content_purges (chain_id, retention_days, redemptions)
+    SELECT $1, $2, COUNT(*) FROM purged HAVING COUNT(*) > 0
+    RETURNING redemptions
+";
+
+const ARCHIVE_LEGACY_REDEMPTION: &str = "
+    WITH archived AS (
+        DELETE FROM \"RedeemedIndex\" WHERE \"chainId\" = $1 AND \"tokenId\" = $2
+        RETURNING \"id\", \"chainId\", \"tokenId\", \"creatorUserId\", \"twitterUserName\",
+            \"safeguard\", \"content\", COALESCE(\"tweetId\", '') AS \"tweetId\"
+    )
+    INSERT INTO teleport.archived_redemptions
+        (id, chain_id, token_id, creator_user_id, twitter_user_name, safeguard, content, tweet_id,
+            revoked)
+    SELECT \"id\", \"chainId\", \"tokenId\", \"creatorUserId\", \"twitterUserName\",
+        \"safeguard\", \"content\", \"tweetId\", $3
+    FROM archived
+    ON CONFLICT (id) DO NOTHING
+";
+
+const ARCHIVE_INTERNAL_REDEMPTION: &str = "
+    WITH archived AS (
+        DELETE FROM teleport.redemptions WHERE chain_id = $1 AND token_id = $2
+        RETURNING id, chain_id, token_id, creator_user_id, twitter_user_name, safeguard, content,
+            tweet_id
+    )
+    INSERT INTO teleport.archived_redemptions
+        (id, chain_id, token_id, creator_user_id, twitter_user_name, safeguard, content, tweet_id,
+            revoked)
+    SELECT id, chain_id, token_id, creator_user_id, twitter_user_name, safeguard, content,
+        tweet_id, $3
+    FROM archived
+    ON CONFLICT (id) DO NOTHING
+";
+
+const INTERNAL_BACKFILL: &str = "
+    INSERT INTO teleport.tokens (chain_id, token_id, nft_id, user_id, twitter_user_name)
+    SELECT \"chainId\", \"tokenId\", \"id\", \"userId\", \"twitterUserName\"
+    FROM \"NftIndex\" WHERE \"tokenId\" IS NOT NULL
+    ON CONFLICT DO NOTHING;
+    INSERT INTO teleport.redemptions
+        (id, chain_id, token_id, creator_user_id, twitter_user_name, safeguard, content, tweet_id)
+    SELECT \"id\", \"chainId\", \"tokenId\", \"creatorUserId\", \"twitterUserName\",
+        \"safeguard\", \"content\", COALESCE(\"tweetId\", '')
+    FROM \"RedeemedIndex\"
+    ON CONFLICT DO NOTHING;
+";
+
+/// The Postgres marketplace index. Clones share one connection pool, which is created once at
+/// startup, so events and requests reuse connections instead of each opening its own.
 #[derive(Clone)]
 pub struct ClientDB {
-    database_url: String,
+    pool: Pool,
+    write_mode: WriteMode,
 }
 
-#[derive(Debug, Clone)]
-pub struct TokenOwner {
-    pub user_id: String,
-    pub twitter_user_name: String,
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenRow {
+    pub nft_id: String,
+    pub user_id: Option<String>,
 }
 
-impl ClientDB {
-    pub fn new(database_url: String) -> Self {
-        Self { database_url }
-    }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedemptionRow {
+    pub chain_id: i64,
+    pub token_id: i32,
+    pub tweet_id: String,
+}
 
-    pub async fn client(&self) -> eyre::Result<Client> {
+impl ClientDB {
+    pub fn new(database_url: &str) -> eyre::Result<Self> {
         let mut config = ClientConfig::new();
         config.root_store.add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
         let tls = MakeRustlsConnect::new(config);
-        let (client, connection) = tokio_postgres::connect(&self.database_url, tls).await?;
-        tokio::spawn(async move {
-            if let Err(e) = connection.await {
-                log::error!("connection error: {}", e);
+        let manager = Manager::from_config(
+            database_url.parse()?,
+            tls,
+            ManagerConfig { recycling_method: RecyclingMethod::Fast },
+        );
+        let pool = Pool::builder(manager).max_size(get_pool_size()?).build()?;
+        Ok(Self { pool, write_mode: WriteMode::Legacy })
+    }
+
+    /// The index at `DATABASE_URL` in the `DB_WRITE_MODE`, when this deployment has one.
+    pub fn from_env() -> eyre::Result<Option<Self>> {
+        get_database_url()
+            .map(|url| Ok(Self::new(&url)?.with_write_mode(WriteMode::from_env()?)))
+            .transpose()
+    }
+
+    pub fn with_write_mode(mut self, write_mode: WriteMode) -> Self {
+        self.write_mode = write_mode;
+        self
+    }
+
+    pub fn write_mode(&self) -> WriteMode {
+        self.write_mode
+    }
+
+    /// In dual mode the legacy tables stay authoritative, so a failed internal write is reported
+    /// for the verifier rather than failing the caller.
+    fn check_internal_write(
+        &self,
+        table: &str,
+        result: Result<u64, tokio_postgres::Error>,
+    ) -> eyre::Result<()> {
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if self.write_mode == WriteMode::Dual => {
+                log::error!("Dual write to {} failed: {:?}", table, e);
+                metrics::increment("dual_write_failures_total", &[("table", table)]);
+                Ok(())
             }
-        });
-        Ok(client)
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Copies legacy rows written before dual writes were turned on into the internal schema.
+    pub async fn backfill_internal_schema(&self) -> eyre::Result<()> {
+        self.client().await?.batch_execute(INTERNAL_BACKFILL).await?;
+        Ok(())
+    }
+
+    /// A connection from the pool, returned to it when dropped.
+    pub async fn client(&self) -> eyre::Result<Object> {
+        Ok(self.pool.get().await?)
     }
 
-    pub async fn get_token_owner(&self, token_id: String) -> eyre::Result<TokenOwner> {
+    pub async fn find_token_owner(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> eyre::Result<Option<TokenOwner>> {
         let token_id_int: i32 = token_id.parse()?;
-        let token_owner = self
-            .client()
+        let chain_id_int = chain_id as i64;
+        let query = if self.write_mode == WriteMode::Internal {
+            "SELECT user_id, twitter_user_name FROM teleport.tokens WHERE token_id = $1 AND chain_id = $2"
+        } else {
+            "SELECT \"userId\", \"twitterUserName\" FROM \"NftIndex\" WHERE \"tokenId\" = $1 AND \"chainId\" = $2"
+        };
+        let row = self.client().await?.query_opt(query, &[&token_id_int, &chain_id_int]).await?;
+        Ok(row.map(|row| TokenOwner { user_id: row.get(0), twitter_user_name: row.get(1) }))
+    }
+
+    /// Lists a redemption under the token's owner with the tweet that fulfilled it, counts it
+    /// against them and, for a delisted token, drops it from the listing, in one transaction so
+    /// a failure leaves none of it applied.
+    pub async fn record_redemption(
+        &self,
+        chain_id: u64,
+        redemption: Redemption,
+    ) -> eyre::Result<()> {
+        let token_id_int: i32 = redemption.token_id.parse()?;
+        let chain_id_int = chain_id as i64;
+        let id = cuid::cuid2();
+        let tweet_id = redemption.tweet_id.unwrap_or_default();
+        let (content, safeguard) = (redemption.text, redemption.policy);
+        let mut client = self.client().await?;
+        let mut transaction = client.transaction().await?;
+
+        let query = if self.write_mode == WriteMode::Internal {
+            "SELECT user_id, twitter_user_name FROM teleport.tokens WHERE token_id = $1 AND chain_id = $2"
+        } else {
+            "SELECT \"userId\", \"twitterUserName\" FROM \"NftIndex\" WHERE \"tokenId\" = $1 AND \"chainId\" = $2"
+        };
+        let row = transaction
+            .query_opt(query, &[&token_id_int, &chain_id_int])
             .await?
-            .query_one(
-                "SELECT \"userId\", \"twitterUserName\" FROM \"NftIndex\" WHERE \"tokenId\" = $1",
-                &[&token_id_int],
+            .ok_or_else(|| eyre::eyre!("Token is not in the marketplace index"))?;
+        let token_owner = TokenOwner { user_id: row.get(0), twitter_user_name: row.get(1) };
+
+        if self.write_mode.writes_legacy() {
+            transaction.execute(
+                "INSERT INTO \"RedeemedIndex\" (\"id\", \"creatorUserId\", \"tokenId\", \"tweetId\", \"twitterUserName\", \"safeguard\", \"content\", \"chainId\") VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                &[&id, &token_owner.user_id, &token_id_int, &tweet_id, &token_owner.twitter_user_name, &safeguard, &content, &chain_id_int],
+            )
+            .await?;
+            if redemption.delist {
+                transaction
+                    .execute(
+                        "DELETE FROM \"NftIndex\" WHERE \"tokenId\" = $1 AND \"chainId\" = $2",
+                        &[&token_id_int, &chain_id_int],
+                    )
+                    .await?;
+            }
+        }
+        if self.write_mode.writes_internal() {
+            // A failed statement aborts the whole transaction, so in dual mode the internal
+            // writes get a savepoint of their own to fall back to.
+            let internal = transaction.savepoint("internal_redemption").await?;
+            let mut result = internal.execute(
+                "INSERT INTO teleport.redemptions (id, chain_id, token_id, creator_user_id, twitter_user_name, safeguard, content, tweet_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                &[&id, &chain_id_int, &token_id_int, &token_owner.user_id, &token_owner.twitter_user_name, &safeguard, &content, &tweet_id],
+            )
+            .await;
+            if result.is_ok() && redemption.delist {
+                result = internal
+                    .execute(
+                        "DELETE FROM teleport.tokens WHERE token_id = $1 AND chain_id = $2",
+                        &[&token_id_int, &chain_id_int],
+                    )
+                    .await;
+            }
+            let result = match result {
+                Ok(rows) => internal.commit().await.map(|()| rows),
+                Err(e) => {
+                    internal.rollback().await?;
+                    Err(e)
+                }
+            };
+            self.check_internal_write("redemptions", result)?;
+        }
+        transaction
+            .execute(
+                "UPDATE \"User\" SET \"haveBeenRedeemed\" = \"haveBeenRedeemed\" + 1 WHERE \"id\" = $1",
+                &[&token_owner.user_id],
             )
             .await?;
-        Ok(TokenOwner { user_id: token_owner.get(0), twitter_user_name: token_owner.get(1) })
+        transaction.commit().await?;
+        Ok(())
     }
 
-    pub async fn add_redeemed_tweet(
+    pub async fn set_redemption_tweet_id(
         &self,
-        token_owner: TokenOwner,
+        chain_id: u64,
         token_id: String,
-        content: String,
-        safeguard: String,
+        tweet_id: String,
     ) -> eyre::Result<()> {
         let token_id_int: i32 = token_id.parse()?;
-        let id = cuid::cuid2();
+        let chain_id_int = chain_id as i64;
+        let client = self.client().await?;
+        if self.write_mode.writes_legacy() {
+            client
+                .execute(
+                    "UPDATE \"RedeemedIndex\" SET \"tweetId\" = $1 WHERE \"tokenId\" = $2 AND \"chainId\" = $3",
+                    &[&tweet_id, &token_id_int, &chain_id_int],
+                )
+                .await?;
+        }
+        if self.write_mode.writes_internal() {
+            let result = client
+                .execute(
+                    "UPDATE teleport.redemptions SET tweet_id = $1 WHERE token_id = $2 AND chain_id = $3",
+                    &[&tweet_id, &token_id_int, &chain_id_int],
+                )
+                .await;
+            self.check_internal_write("redemptions", result)?;
+        }
+        Ok(())
+    }
+
+    pub async fn set_token_id(
+        &self,
+        chain_id: u64,
+        token_id: String,
+        nft_id: String,
+    ) -> eyre::Result<()> {
+        let token_id_int: i32 = token_id.parse()?;
+        let chain_id_int = chain_id as i64;
+        let client = self.client().await?;
+        if self.write_mode.writes_legacy() {
+            client
+                .execute(
+                    "UPDATE \"NftIndex\" SET \"tokenId\" = $1, \"chainId\" = $2 WHERE \"id\" = $3",
+                    &[&token_id_int, &chain_id_int, &nft_id],
+                )
+                .await?;
+        }
+        if self.write_mode.writes_internal() {
+            // The frontend still creates token rows in the legacy table, so the owner is copied
+            // across from there while both schemas exist.
+            let result = client
+                .execute(
+                    "INSERT INTO teleport.tokens (chain_id, token_id, nft_id, user_id, twitter_user_name)
+                     SELECT $1, $2, $3, \"userId\", \"twitterUserName\" FROM \"NftIndex\" WHERE \"id\" = $3
+                     ON CONFLICT (chain_id, token_id) DO UPDATE SET nft_id = EXCLUDED.nft_id",
+                    &[&chain_id_int, &token_id_int, &nft_id],
+                )
+                .await;
+            self.check_internal_write("tokens", result)?;
+            let result = client
+                .execute(
+                    "INSERT INTO teleport.mints (chain_id, token_id, creator_user_id)
+                     SELECT $1, $2, \"userId\" FROM \"NftIndex\" WHERE \"id\" = $3
+                     ON CONFLICT DO NOTHING",
+                    &[&chain_id_int, &token_id_int, &nft_id],
+                )
+                .await;
+            self.check_internal_write("mints", result)?;
+        }
+        Ok(())
+    }
 
-        self.client().await?.execute(
-            "INSERT INTO \"RedeemedIndex\" (\"id\", \"creatorUserId\", \"tokenId\", \"tweetId\", \"twitterUserName\", \"safeguard\", \"content\") VALUES ($1, $2, $3, $4, $5, $6, $7)",
-            &[&id, &token_owner.user_id, &token_id_int, &"".to_string(), &token_owner.twitter_user_name, &safeguard, &content],
-        )
-        .await?;
+    pub async fn delete_token(&self, chain_id: u64, token_id: String) -> eyre::Result<()> {
+        let token_id_int: i32 = token_id.parse()?;
+        let chain_id_int = chain_id as i64;
+        let client = self.client().await?;
+        if self.write_mode.writes_legacy() {
+            client
+                .execute(
+                    "DELETE FROM \"NftIndex\" WHERE \"tokenId\" = $1 AND \"chainId\" = $2",
+                    &[&token_id_int, &chain_id_int],
+                )
+                .await?;
+        }
+        if self.write_mode.writes_internal() {
+            let result = client
+                .execute(
+                    "DELETE FROM teleport.tokens WHERE token_id = $1 AND chain_id = $2",
+                    &[&token_id_int, &chain_id_int],
+                )
+                .await;
+            self.check_internal_write("tokens", result)?;
+        }
         Ok(())
     }
 
-    pub async fn increment_user_redeemed(&self, user_id: String) -> eyre::Result<()> {
-        self.client().await?
-            .execute(
-                "UPDATE \"User\" SET \"haveBeenRedeemed\" = \"haveBeenRedeemed\" + 1 WHERE \"id\" = $1",
-                &[&user_id],
+    pub async fn update_token_owner(
+        &self,
+        chain_id: u64,
+        token_id: String,
+        user_id: String,
+    ) -> eyre::Result<()> {
+        let token_id_int: i32 = token_id.parse()?;
+        let chain_id_int = chain_id as i64;
+        let client = self.client().await?;
+        if self.write_mode.writes_legacy() {
+            client
+                .execute(
+                    "UPDATE \"NftIndex\" SET \"userId\" = $1 WHERE \"tokenId\" = $2 AND \"chainId\" = $3",
+                    &[&user_id, &token_id_int, &chain_id_int],
+                )
+                .await?;
+        }
+        if self.write_mode.writes_internal() {
+            let result = client
+                .execute(
+                    "UPDATE teleport.tokens SET user_id = $1 WHERE token_id = $2 AND chain_id = $3",
+                    &[&user_id, &token_id_int, &chain_id_int],
+                )
+                .await;
+            self.check_internal_write("tokens", result)?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_legacy_tokens(&self) -> eyre::Result<BTreeMap<(i64, i32), TokenRow>> {
+        let rows = self
+            .client()
+            .await?
+            .query(
+                "SELECT \"chainId\", \"tokenId\", \"id\", \"userId\" FROM \"NftIndex\" WHERE \"tokenId\" IS NOT NULL",
+                &[],
             )
             .await?;
-        Ok(())
+        Ok(rows
+            .iter()
+            .map(|row| {
+                ((row.get(0), row.get(1)), TokenRow { nft_id: row.get(2), user_id: row.get(3) })
+            })
+            .collect())
     }
 
-    pub async fn set_token_id(&self, token_id: String, nft_id: String) -> eyre::Result<()> {
-        let token_id_int: i32 = token_id.parse()?;
-        self.client()
+    pub async fn get_internal_tokens(&self) -> eyre::Result<BTreeMap<(i64, i32), TokenRow>> {
+        let rows = self
+            .client()
             .await?
-            .execute(
-                "UPDATE \"NftIndex\" SET \"tokenId\" = $1 WHERE \"id\" = $2",
-                &[&token_id_int, &nft_id],
+            .query("SELECT chain_id, token_id, nft_id, user_id FROM teleport.tokens", &[])
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| {
+                ((row.get(0), row.get(1)), TokenRow { nft_id: row.get(2), user_id: row.get(3) })
+            })
+            .collect())
+    }
+
+    pub async fn get_legacy_redemptions(&self) -> eyre::Result<BTreeMap<String, RedemptionRow>> {
+        let rows = self
+            .client()
+            .await?
+            .query(
+                "SELECT \"id\", \"chainId\", \"tokenId\", COALESCE(\"tweetId\", '') FROM \"RedeemedIndex\"",
+                &[],
             )
             .await?;
-        Ok(())
+        Ok(rows
+            .iter()
+            .map(|row| {
+                (
+                    row.get(0),
+                    RedemptionRow {
+                        chain_id: row.get(1),
+                        token_id: row.get(2),
+                        tweet_id: row.get(3),
+                    },
+                )
+            })
+            .collect())
     }
 
-    pub async fn delete_token(&self, token_id: String) -> eyre::Result<()> {
-        let token_id_int: i32 = token_id.parse()?;
-        self.client()
+    pub async fn get_internal_redemptions(&self) -> eyre::Result<BTreeMap<String, RedemptionRow>> {
+        let rows = self
+            .client()
             .await?
-            .execute("DELETE FROM \"NftIndex\" WHERE \"tokenId\" = $1", &[&token_id_int])
+            .query("SELECT id, chain_id, token_id, tweet_id FROM teleport.redemptions", &[])
             .await?;
-        Ok(())
+        Ok(rows
+            .iter()
+            .map(|row| {
+                (
+                    row.get(0),
+                    RedemptionRow {
+                        chain_id: row.get(1),
+                        token_id: row.get(2),
+                        tweet_id: row.get(3),
+                    },
+                )
+            })
+            .collect())
     }
 
-    pub async fn update_token_owner(&self, token_id: String, user_id: String) -> eyre::Result<()> {
-        let token_id_int: i32 = token_id.parse()?;
-        self.client()
+    /// Random sample of redemptions whose content has not been purged.
+    pub async fn sample_redemptions(&self, limit: i64) -> eyre::Result<Vec<RedemptionSample>> {
+        let rows = self
+            .client()
             .await?
-            .execute(
-                "UPDATE \"NftIndex\" SET \"userId\" = $1 WHERE \"tokenId\" = $2",
-                &[&user_id, &token_id_int],
+            .query(
+                "SELECT \"chainId\", \"tokenId\", \"content\", \"safeguard\" FROM \"RedeemedIndex\" WHERE \"content\" <> '' ORDER BY random() LIMIT $1",
+                &[&limit],
             )
             .await?;
+        Ok(rows
+            .iter()
+            .map(|row| RedemptionSample {
+                chain_id: row.get(0),
+                token_id: row.get(1),
+                content: row.get(2),
+                safeguard: row.get(3),
+            })
+            .collect())
+    }
+
+    /// A redemption's content and policy, unless it was never indexed or has been purged.
+    pub async fn get_redemption(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> eyre::Result<Option<RedemptionSample>> {
+        let token_id_int: i32 = token_id.parse()?;
+        let chain_id_int = chain_id as i64;
+        let query = if self.write_mode == WriteMode::Internal {
+            "SELECT chain_id, token_id, content, safeguard FROM teleport.redemptions WHERE chain_id = $1 AND token_id = $2 AND content <> '' LIMIT 1"
+        } else {
+            "SELECT \"chainId\", \"tokenId\", \"content\", \"safeguard\" FROM \"RedeemedIndex\" WHERE \"chainId\" = $1 AND \"tokenId\" = $2 AND \"content\" <> '' LIMIT 1"
+        };
+        let row = self.client().await?.query_opt(query, &[&chain_id_int, &token_id_int]).await?;
+        Ok(row.map(|row| RedemptionSample {
+            chain_id: row.get(0),
+            token_id: row.get(1),
+            content: row.get(2),
+            safeguard: row.get(3),
+        }))
+    }
+
+    /// The policy a redemption was made under, unless it was never indexed. Unlike
+    /// [`ClientDB::get_redemption`] this finds redemptions whose content is empty or purged.
+    pub async fn find_redemption_policy(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> eyre::Result<Option<String>> {
+        let token_id_int: i32 = token_id.parse()?;
+        let chain_id_int = chain_id as i64;
+        let query = if self.write_mode == WriteMode::Internal {
+            "SELECT safeguard FROM teleport.redemptions WHERE chain_id = $1 AND token_id = $2 LIMIT 1"
+        } else {
+            "SELECT \"safeguard\" FROM \"RedeemedIndex\" WHERE \"chainId\" = $1 AND \"tokenId\" = $2 LIMIT 1"
+        };
+        let row = self.client().await?.query_opt(query, &[&chain_id_int, &token_id_int]).await?;
+        Ok(row.map(|row| row.get(0)))
+    }
+
+    pub async fn archive_redemption(
+        &self,
+        chain_id: u64,
+        token_id: String,
+        revoked: bool,
+    ) -> eyre::Result<()> {
+        let token_id_int: i32 = token_id.parse()?;
+        let chain_id_int = chain_id as i64;
+        let client = self.client().await?;
+        if self.write_mode.writes_legacy() {
+            client
+                .execute(ARCHIVE_LEGACY_REDEMPTION, &[&chain_id_int, &token_id_int, &revoked])
+                .await?;
+        }
+        if self.write_mode.writes_internal() {
+            let result = client
+                .execute(ARCHIVE_INTERNAL_REDEMPTION, &[&chain_id_int, &token_id_int, &revoked])
+                .await;
+            self.check_internal_write("redemptions", result)?;
+        }
         Ok(())
     }
+
+    /// Returns how many redemptions had their content purged.
+    pub async fn purge_redemption_content(
+        &self,
+        chain_id: u64,
+        retention_days: i32,
+    ) -> eyre::Result<i64> {
+        let chain_id_int = chain_id as i64;
+        let row = self
+            .client()
+            .await?
+            .query_opt(CONTENT_PURGE, &[&chain_id_int, &retention_days])
+            .await?;
+        Ok(row.map_or(0, |row| row.get(0)))
+    }
+
+    /// `day` is a `YYYY-MM-DD` UTC date.
+    pub async fn roll_up_creator_stats(&self, day: String) -> eyre::Result<u64> {
+        Ok(self.client().await?.execute(CREATOR_DAILY_ROLLUP, &[&day]).await?)
+    }
+
+    pub async fn get_creator_daily_stats(
+        &self,
+        creator_user_id: String,
+        from: String,
+        to: String,
+    ) -> eyre::Result<Vec<CreatorDailyStats>> {
+        let rows = self
+            .client()
+            .await?
+            .query(
+                "SELECT day::text, mints, redemptions FROM teleport.creator_daily_stats
+                 WHERE creator_user_id = $1 AND day >= $2::text::date AND day <= $3::text::date
+                 ORDER BY day",
+                &[&creator_user_id, &from, &to],
+            )
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| CreatorDailyStats {
+                day: row.get(0),
+                mints: row.get(1),
+                redemptions: row.get(2),
+            })
+            .collect())
+    }
 }
@@ -0,0 +1,110 @@
+use std::{collections::BTreeMap, fmt::Debug, time::Duration};
+
+use super::client_db::ClientDB;
+use crate::metrics;
+
+const DEFAULT_VERIFY_INTERVAL_SECS: u64 = 300;
+
+/// Reads `DUAL_WRITE_VERIFY_INTERVAL_SECS`, how often the two schemas are compared.
+pub fn get_verify_interval() -> Duration {
+    let secs = std::env::var("DUAL_WRITE_VERIFY_INTERVAL_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(DEFAULT_VERIFY_INTERVAL_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Keys whose rows differ between the legacy and internal copies of a table.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Divergence<K> {
+    pub missing_internal: Vec<K>,
+    pub missing_legacy: Vec<K>,
+    pub mismatched: Vec<K>,
+}
+
+impl<K> Divergence<K> {
+    pub fn is_empty(&self) -> bool {
+        self.missing_internal.is_empty() &&
+            self.missing_legacy.is_empty() &&
+            self.mismatched.is_empty()
+    }
+}
+
+pub fn diff_rows<K: Ord + Clone, V: PartialEq>(
+    legacy: &BTreeMap<K, V>,
+    internal: &BTreeMap<K, V>,
+) -> Divergence<K> {
+    let mut divergence = Divergence {
+        missing_internal: Vec::new(),
+        missing_legacy: Vec::new(),
+        mismatched: Vec::new(),
+    };
+    for (key, row) in legacy {
+        match internal.get(key) {
+            None => divergence.missing_internal.push(key.clone()),
+            Some(internal_row) if internal_row != row => divergence.mismatched.push(key.clone()),
+            Some(_) => {}
+        }
+    }
+    divergence.missing_legacy =
+        internal.keys().filter(|key| !legacy.contains_key(key)).cloned().collect();
+    divergence
+}
+
+fn report<K: Debug>(table: &str, divergence: &Divergence<K>) {
+    for (kind, keys) in [
+        ("missing_internal", &divergence.missing_internal),
+        ("missing_legacy", &divergence.missing_legacy),
+        ("mismatched", &divergence.mismatched),
+    ] {
+        metrics::set_gauge(
+            "dual_write_divergence",
+            &[("table", table), ("kind", kind)],
+            keys.len() as i64,
+        );
+    }
+    if !divergence.is_empty() {
+        log::warn!("Dual write divergence in {}: {:?}", table, divergence);
+    }
+}
+
+async fn verify_once(client_db: &ClientDB) -> eyre::Result<()> {
+    let tokens =
+        diff_rows(&client_db.get_legacy_tokens().await?, &client_db.get_internal_tokens().await?);
+    report("tokens", &tokens);
+    let redemptions = diff_rows(
+        &client_db.get_legacy_redemptions().await?,
+        &client_db.get_internal_redemptions().await?,
+    );
+    report("redemptions", &redemptions);
+    Ok(())
+}
+
+/// Periodically compares the legacy tables with the internal schema while both are written, so
+/// the switch to `DB_WRITE_MODE=internal` can wait until they have agreed for a while.
+pub async fn run_dual_write_verifier(client_db: ClientDB, interval: Duration) {
+    loop {
+        if let Err(e) = verify_once(&client_db).await {
+            log::error!("Dual write verification failed: {:?}", e);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_rows_classifies_divergence() {
+        let legacy = BTreeMap::from([(1, "a"), (2, "b"), (3, "c")]);
+        let internal = BTreeMap::from([(2, "b"), (3, "x"), (4, "d")]);
+
+        let divergence = diff_rows(&legacy, &internal);
+        assert_eq!(
+            divergence,
+            Divergence { missing_internal: vec![1], missing_legacy: vec![4], mismatched: vec![3] }
+        );
+        assert!(diff_rows(&legacy, &legacy).is_empty());
+    }
+}
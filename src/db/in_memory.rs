@@ -1,31 +1,158 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Bound,
+};
 
 use serde::{Deserialize, Serialize};
 
-use super::{PendingNFT, Session, TeleportDB, User, NFT};
+use super::{
+    address_key, email_code_matches, layouts,
+    snapshot::{Image, SCHEMA_VERSION, UNVERSIONED},
+    token_id_order, unresolved_txs, AbuseReport, AccessListEntry, AdminAuditEntry, ApprovedContent,
+    BlockCursor, CollectionStats, ContentAnchor, ContentLicense, ContractStatus, CreatorSignals,
+    CreatorWebhook, EmailChallenge, FailedEvent, InboxMessage, LedgerEntry, LicenseRule,
+    MentionRule, MintConfirmation, MintPayment, ModerationRecord, ModerationTier, PendingApproval,
+    PendingBurn, PendingNFT, PolicySnapshot, RecoveryEmail, RedemptionLink, RedemptionRecord,
+    ReputationSnapshot, RoyaltyInfo, Session, TeleportDB, TokenMetadataRecord, TxRecord, TxStatus,
+    User, MAX_EMAIL_CHALLENGE_ATTEMPTS, MAX_TX_REPLACEMENTS, NFT,
+};
+
+/// Snapshots kept per creator; older ones are dropped.
+const MAX_REPUTATION_HISTORY: usize = 200;
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct InMemoryDB {
     pub x_id_to_address: BTreeMap<String, String>,
     pub users: BTreeMap<String, User>,
     pub pending_nfts: BTreeMap<String, PendingNFT>,
+    pub mint_confirmations: BTreeMap<String, MintConfirmation>,
+    pub pending_policy_snapshots: BTreeMap<String, PolicySnapshot>,
+    pub policy_snapshots: BTreeMap<(u64, String), PolicySnapshot>,
     pub nfts: BTreeMap<String, NFT>,
-    pub tweets: BTreeMap<String, String>,
+    pub tweets: BTreeMap<(u64, String), String>,
     pub sessions: BTreeMap<String, Session>,
+    pub moderation_records: BTreeMap<(u64, String), ModerationRecord>,
+    pub policy_precheck_thresholds: BTreeMap<String, f32>,
+    pub policy_mention_rules: BTreeMap<String, MentionRule>,
+    pub policy_license_rules: BTreeMap<String, LicenseRule>,
+    pub policy_moderation_tiers: BTreeMap<String, ModerationTier>,
+    pub redemption_licenses: BTreeMap<(u64, String), ContentLicense>,
+    pub approved_mentions: BTreeSet<(u64, String)>,
+    pub approved_quorum_disputes: BTreeSet<(u64, String)>,
+    pub last_processed_blocks: BTreeMap<u64, BlockCursor>,
+    /// Keyed by chain id, tx hash and log index.
+    pub processed_events: BTreeSet<(u64, String, u64)>,
+    pub failed_events: BTreeMap<String, FailedEvent>,
+    pub redemption_links: BTreeMap<String, RedemptionLink>,
+    pub recovery_emails: BTreeMap<String, RecoveryEmail>,
+    pub timezones: BTreeMap<String, String>,
+    pub creator_webhooks: BTreeMap<String, CreatorWebhook>,
+    pub email_challenges: BTreeMap<String, EmailChallenge>,
+    pub admin_audit_log: Vec<AdminAuditEntry>,
+    pub txs: BTreeMap<String, TxRecord>,
+    pub pending_approvals: BTreeMap<String, PendingApproval>,
+    pub abuse_reports: BTreeMap<(u64, String), Vec<AbuseReport>>,
+    pub creator_strikes: BTreeMap<String, u32>,
+    pub royalties: BTreeMap<(u64, String), RoyaltyInfo>,
+    pub burn_on_redeem: BTreeSet<String>,
+    /// Keyed by creator x_id, policy hash and content hash.
+    pub approved_contents: BTreeMap<(String, String, String), ApprovedContent>,
+    pub burns: BTreeMap<(u64, String), PendingBurn>,
+    pub anchors: BTreeMap<(u64, String), ContentAnchor>,
+    /// When each address was topped up in the last day, by chain.
+    pub top_ups: BTreeMap<(u64, String), Vec<i64>>,
+    pub creator_signals: BTreeMap<String, CreatorSignals>,
+    pub reputation_history: BTreeMap<String, Vec<ReputationSnapshot>>,
+    pub redeem_nonces: BTreeMap<String, u64>,
+    pub policy_hashes: BTreeMap<(u64, String), String>,
+    pub mint_txs: BTreeMap<(u64, String), String>,
+    pub ledger: BTreeMap<String, Vec<LedgerEntry>>,
+    pub mint_payments: BTreeMap<(u64, String), MintPayment>,
+    pub relay_cursors: BTreeMap<u64, u64>,
+    pub relayed: BTreeMap<String, String>,
+    pub access_list: BTreeMap<String, AccessListEntry>,
+    pub inbox: Vec<InboxMessage>,
+    pub contract_statuses: BTreeMap<(u64, String), ContractStatus>,
+    pub kill_switches: BTreeMap<String, bool>,
+    pub deposit_cursors: BTreeMap<u64, u64>,
+    pub token_metadata: BTreeMap<(u64, String), TokenMetadataRecord>,
+    /// Read models, kept in step with the fields they are derived from on every write and
+    /// rebuilt from them on load instead of being saved.
+    #[serde(skip)]
+    pub token_nfts: BTreeMap<(u64, String), String>,
+    /// [`super::address_key`] of each user address to the address the user is stored under.
+    #[serde(skip)]
+    pub address_index: BTreeMap<String, String>,
+    #[serde(skip)]
+    pub collection_stats: BTreeMap<u64, CollectionStats>,
 }
 
 impl InMemoryDB {
     pub fn new() -> Self {
         Self::default()
     }
-    pub fn deserialize(data: &[u8]) -> Self {
-        bincode::deserialize(data).expect("Failed to deserialize InMemoryUserDB")
+    /// Reads an image in the current layout.
+    pub fn deserialize(data: &[u8]) -> eyre::Result<Self> {
+        let mut db: Self = bincode::deserialize(data)?;
+        db.refresh_read_models();
+        Ok(db)
+    }
+
+    /// Reads a snapshot's image, migrating it from the layout of the schema version it was
+    /// written with.
+    pub fn load(image: &Image) -> eyre::Result<Self> {
+        let mut db: Self = match image.version {
+            SCHEMA_VERSION => return Self::deserialize(&image.bytes),
+            UNVERSIONED => bincode::deserialize::<layouts::V0>(&image.bytes)?.into(),
+            1 => layouts::V2::from(bincode::deserialize::<layouts::V1>(&image.bytes)?).into(),
+            2 => bincode::deserialize::<layouts::V2>(&image.bytes)?.into(),
+            version => eyre::bail!("No migration from schema version {}", version),
+        };
+        db.refresh_read_models();
+        Ok(db)
+    }
+
+    /// Recomputes the read models from the fields they are derived from.
+    fn refresh_read_models(&mut self) {
+        self.token_nfts = self
+            .nfts
+            .iter()
+            .map(|(nft_id, nft)| ((nft.chain_id, nft.token_id.clone()), nft_id.clone()))
+            .collect();
+        self.address_index =
+            self.users.keys().map(|address| (address_key(address), address.clone())).collect();
+        self.collection_stats.clear();
+        for nft in self.nfts.values() {
+            self.collection_stats.entry(nft.chain_id).or_default().minted += 1;
+        }
+        for pending_nft in self.pending_nfts.values() {
+            self.collection_stats.entry(pending_nft.chain_id).or_default().pending_mints += 1;
+        }
+        for ((chain_id, _), record) in &self.moderation_records {
+            self.collection_stats.entry(*chain_id).or_default().count_decision(record.safe, true);
+        }
+        for (chain_id, _) in self.tweets.keys() {
+            self.collection_stats.entry(*chain_id).or_default().tweets += 1;
+        }
+    }
+
+    fn stats(&mut self, chain_id: u64) -> &mut CollectionStats {
+        self.collection_stats.entry(chain_id).or_default()
+    }
+
+    fn insert_pending_nft(&mut self, tx_hash: String, pending_nft: PendingNFT) {
+        self.stats(pending_nft.chain_id).pending_mints += 1;
+        if let Some(replaced) = self.pending_nfts.insert(tx_hash, pending_nft) {
+            let stats = self.stats(replaced.chain_id);
+            stats.pending_mints = stats.pending_mints.saturating_sub(1);
+        }
     }
 }
 
 impl TeleportDB for InMemoryDB {
     fn add_user(&mut self, address: String, user: User) -> eyre::Result<()> {
         self.users.insert(address.clone(), user.clone());
+        self.address_index.insert(address_key(&address), address.clone());
         if let Some(x_id) = user.x_id {
             self.x_id_to_address.insert(x_id, address);
         }
@@ -33,8 +160,19 @@ impl TeleportDB for InMemoryDB {
         Ok(())
     }
 
+    fn count_users(&self) -> eyre::Result<usize> {
+        Ok(self.users.len())
+    }
+
     fn get_user_by_address(&self, address: String) -> eyre::Result<User> {
-        let user = self.users.get(&address).ok_or_else(|| eyre::eyre!("User not found"))?;
+        let user = self
+            .users
+            .get(&address)
+            .or_else(|| {
+                let stored = self.address_index.get(&address_key(&address))?;
+                self.users.get(stored)
+            })
+            .ok_or_else(|| eyre::eyre!("User not found"))?;
         Ok(user.clone())
     }
 
@@ -47,13 +185,58 @@ impl TeleportDB for InMemoryDB {
         Ok(user.clone())
     }
 
+    fn count_creator_nfts(&self, address: String) -> eyre::Result<usize> {
+        let minted = self.nfts.values().filter(|nft| nft.address == address).count();
+        let pending = self.pending_nfts.values().filter(|nft| nft.address == address).count();
+        Ok(minted + pending)
+    }
+
+    fn unlink_x_account(&mut self, address: String) -> eyre::Result<String> {
+        let user = self.users.get_mut(&address).ok_or_else(|| eyre::eyre!("User not found"))?;
+        let x_id = user.x_id.take().ok_or_else(|| eyre::eyre!("No X account linked"))?;
+        user.access_tokens = None;
+        user.unlinked_x_id = Some(x_id.clone());
+        self.x_id_to_address.remove(&x_id);
+        self.sessions.retain(|_, session| session.x_id != x_id);
+        self.email_challenges.remove(&x_id);
+        Ok(x_id)
+    }
+
+    fn migrate_x_account(&mut self, from: String, to: String) -> eyre::Result<()> {
+        if from == to {
+            return Ok(());
+        }
+        if let Some(timezone) = self.timezones.remove(&from) {
+            self.timezones.insert(to.clone(), timezone);
+        }
+        if let Some(recovery) = self.recovery_emails.remove(&from) {
+            self.recovery_emails.insert(to.clone(), recovery);
+        }
+        if let Some(webhook) = self.creator_webhooks.remove(&from) {
+            self.creator_webhooks.insert(to.clone(), webhook);
+        }
+        if self.burn_on_redeem.remove(&from) {
+            self.burn_on_redeem.insert(to);
+        }
+        Ok(())
+    }
+
     fn serialize(&self) -> eyre::Result<Vec<u8>> {
         let serialized = bincode::serialize(&self)?;
         Ok(serialized)
     }
 
+    fn export_snapshot(&self) -> eyre::Result<Vec<u8>> {
+        self.serialize()
+    }
+
+    fn import_snapshot(&mut self, snapshot: &Image) -> eyre::Result<()> {
+        *self = Self::load(snapshot)?;
+        Ok(())
+    }
+
     fn add_pending_nft(&mut self, tx_hash: String, pending_nft: PendingNFT) -> eyre::Result<()> {
-        self.pending_nfts.insert(tx_hash, pending_nft);
+        self.insert_pending_nft(tx_hash, pending_nft);
         Ok(())
     }
 
@@ -62,25 +245,142 @@ impl TeleportDB for InMemoryDB {
             .pending_nfts
             .remove(&tx_hash)
             .ok_or_else(|| eyre::eyre!("Pending NFT not found"))?;
-        let nft = NFT { address: pending_nft.address, token_id: token_id.clone() };
+        let stats = self.stats(pending_nft.chain_id);
+        stats.pending_mints = stats.pending_mints.saturating_sub(1);
+        self.mint_confirmations.remove(&tx_hash);
+        if let Some(snapshot) = self.pending_policy_snapshots.remove(&pending_nft.nft_id) {
+            self.policy_snapshots.insert((pending_nft.chain_id, token_id.clone()), snapshot);
+        }
+        let nft = NFT {
+            address: pending_nft.address,
+            token_id: token_id.clone(),
+            chain_id: pending_nft.chain_id,
+        };
         let nft_id_clone = pending_nft.nft_id.clone();
-        self.nfts.insert(pending_nft.nft_id, nft);
+        // Mints migrated from before policies were pinned have none to pin.
+        if !pending_nft.policy_hash.is_empty() {
+            self.policy_hashes
+                .insert((pending_nft.chain_id, token_id.clone()), pending_nft.policy_hash);
+        }
+        self.mint_txs.insert((pending_nft.chain_id, token_id.clone()), tx_hash);
+        self.token_nfts.insert((pending_nft.chain_id, token_id), pending_nft.nft_id.clone());
+        self.stats(pending_nft.chain_id).minted += 1;
+        if let Some(replaced) = self.nfts.insert(pending_nft.nft_id, nft) {
+            let stats = self.stats(replaced.chain_id);
+            stats.minted = stats.minted.saturating_sub(1);
+        }
 
         Ok(nft_id_clone)
     }
 
+    fn get_pending_nft(&self, tx_hash: String) -> eyre::Result<Option<PendingNFT>> {
+        Ok(self.pending_nfts.get(&tx_hash).cloned())
+    }
+
+    fn set_pending_policy_snapshot(
+        &mut self,
+        nft_id: String,
+        snapshot: PolicySnapshot,
+    ) -> eyre::Result<()> {
+        self.pending_policy_snapshots.insert(nft_id, snapshot);
+        Ok(())
+    }
+
+    fn get_policy_snapshot(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> eyre::Result<Option<PolicySnapshot>> {
+        Ok(self.policy_snapshots.get(&(chain_id, token_id)).cloned())
+    }
+
+    fn get_pending_nft_by_id(&self, nft_id: String) -> eyre::Result<Option<PendingNFT>> {
+        Ok(self.pending_nfts.values().find(|pending_nft| pending_nft.nft_id == nft_id).cloned())
+    }
+
+    fn set_mint_confirmation(
+        &mut self,
+        tx_hash: String,
+        confirmation: MintConfirmation,
+    ) -> eyre::Result<()> {
+        self.mint_confirmations.insert(tx_hash, confirmation);
+        Ok(())
+    }
+
+    fn get_mint_confirmations(
+        &self,
+        chain_id: u64,
+    ) -> eyre::Result<Vec<(String, MintConfirmation)>> {
+        Ok(self
+            .mint_confirmations
+            .iter()
+            .filter(|(_, confirmation)| confirmation.chain_id == chain_id)
+            .map(|(tx_hash, confirmation)| (tx_hash.clone(), confirmation.clone()))
+            .collect())
+    }
+
+    fn get_mint_confirmation_by_nft_id(
+        &self,
+        nft_id: String,
+    ) -> eyre::Result<Option<MintConfirmation>> {
+        Ok(self
+            .mint_confirmations
+            .values()
+            .find(|confirmation| confirmation.nft_id == nft_id)
+            .cloned())
+    }
+
+    fn remove_mint_confirmation(&mut self, tx_hash: String) -> eyre::Result<bool> {
+        Ok(self.mint_confirmations.remove(&tx_hash).is_some())
+    }
+
+    fn get_policy_hash(&self, chain_id: u64, token_id: String) -> eyre::Result<Option<String>> {
+        Ok(self.policy_hashes.get(&(chain_id, token_id)).cloned())
+    }
+
+    fn get_mint_tx(&self, chain_id: u64, token_id: String) -> eyre::Result<Option<String>> {
+        Ok(self.mint_txs.get(&(chain_id, token_id)).cloned())
+    }
+
+    fn alias_pending_nft(
+        &mut self,
+        tx_hash: String,
+        replacement_tx_hash: String,
+    ) -> eyre::Result<()> {
+        if let Some(pending_nft) = self.pending_nfts.get(&tx_hash).cloned() {
+            self.insert_pending_nft(replacement_tx_hash, pending_nft);
+        }
+        Ok(())
+    }
+
     fn get_nft(&self, nft_id: String) -> eyre::Result<NFT> {
         let nft = self.nfts.get(&nft_id).ok_or_else(|| eyre::eyre!("NFT not found"))?;
         Ok(nft.clone())
     }
 
-    fn add_tweet(&mut self, token_id: String, tweet_id: String) -> eyre::Result<()> {
-        self.tweets.insert(token_id, tweet_id);
+    fn get_nft_by_token_id(&self, chain_id: u64, token_id: String) -> eyre::Result<NFT> {
+        let nft_id = self.get_nft_id_by_token_id(chain_id, token_id)?;
+        self.get_nft(nft_id)
+    }
+
+    fn get_nft_id_by_token_id(&self, chain_id: u64, token_id: String) -> eyre::Result<String> {
+        let nft_id = self
+            .token_nfts
+            .get(&(chain_id, token_id))
+            .ok_or_else(|| eyre::eyre!("NFT not found for token id"))?;
+        Ok(nft_id.clone())
+    }
+
+    fn add_tweet(&mut self, chain_id: u64, token_id: String, tweet_id: String) -> eyre::Result<()> {
+        if self.tweets.insert((chain_id, token_id), tweet_id).is_none() {
+            self.stats(chain_id).tweets += 1;
+        }
         Ok(())
     }
 
-    fn get_tweet(&self, token_id: String) -> eyre::Result<String> {
-        let tweet_id = self.tweets.get(&token_id).ok_or_else(|| eyre::eyre!("Tweet not found"))?;
+    fn get_tweet(&self, chain_id: u64, token_id: String) -> eyre::Result<String> {
+        let tweet_id =
+            self.tweets.get(&(chain_id, token_id)).ok_or_else(|| eyre::eyre!("Tweet not found"))?;
         Ok(tweet_id.clone())
     }
 
@@ -95,11 +395,890 @@ impl TeleportDB for InMemoryDB {
             self.sessions.get(&session_id).ok_or_else(|| eyre::eyre!("Session not found"))?;
         Ok(x_id.clone())
     }
+
+    fn add_moderation_record(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        record: ModerationRecord,
+    ) -> eyre::Result<()> {
+        self.stats(chain_id).count_decision(record.safe, true);
+        if let Some(replaced) = self.moderation_records.insert((chain_id, token_id), record) {
+            self.stats(chain_id).count_decision(replaced.safe, false);
+        }
+        Ok(())
+    }
+
+    fn get_moderation_record(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> eyre::Result<ModerationRecord> {
+        let record = self
+            .moderation_records
+            .get(&(chain_id, token_id))
+            .ok_or_else(|| eyre::eyre!("Moderation record not found"))?;
+        Ok(record.clone())
+    }
+
+    fn set_policy_precheck_threshold(
+        &mut self,
+        policy: String,
+        threshold: f32,
+    ) -> eyre::Result<()> {
+        self.policy_precheck_thresholds.insert(policy, threshold);
+        Ok(())
+    }
+
+    fn get_policy_precheck_threshold(&self, policy: String) -> eyre::Result<f32> {
+        self.policy_precheck_thresholds
+            .get(&policy)
+            .copied()
+            .ok_or_else(|| eyre::eyre!("Precheck threshold not set for policy"))
+    }
+
+    fn set_policy_mention_rule(&mut self, policy: String, rule: MentionRule) -> eyre::Result<()> {
+        self.policy_mention_rules.insert(policy, rule);
+        Ok(())
+    }
+
+    fn get_policy_mention_rule(&self, policy: String) -> eyre::Result<Option<MentionRule>> {
+        Ok(self.policy_mention_rules.get(&policy).cloned())
+    }
+
+    fn approve_mentions(&mut self, chain_id: u64, token_id: String) -> eyre::Result<()> {
+        self.approved_mentions.insert((chain_id, token_id));
+        Ok(())
+    }
+
+    fn are_mentions_approved(&self, chain_id: u64, token_id: String) -> eyre::Result<bool> {
+        Ok(self.approved_mentions.contains(&(chain_id, token_id)))
+    }
+
+    fn approve_quorum_dispute(&mut self, chain_id: u64, token_id: String) -> eyre::Result<()> {
+        self.approved_quorum_disputes.insert((chain_id, token_id));
+        Ok(())
+    }
+
+    fn is_quorum_dispute_approved(&self, chain_id: u64, token_id: String) -> eyre::Result<bool> {
+        Ok(self.approved_quorum_disputes.contains(&(chain_id, token_id)))
+    }
+
+    fn set_policy_license_rule(&mut self, policy: String, rule: LicenseRule) -> eyre::Result<()> {
+        self.policy_license_rules.insert(policy, rule);
+        Ok(())
+    }
+
+    fn get_policy_license_rule(&self, policy: String) -> eyre::Result<Option<LicenseRule>> {
+        Ok(self.policy_license_rules.get(&policy).cloned())
+    }
+
+    fn set_policy_moderation_tier(
+        &mut self,
+        policy: String,
+        tier: ModerationTier,
+    ) -> eyre::Result<()> {
+        self.policy_moderation_tiers.insert(policy, tier);
+        Ok(())
+    }
+
+    fn get_policy_moderation_tier(&self, policy: String) -> eyre::Result<ModerationTier> {
+        Ok(self.policy_moderation_tiers.get(&policy).copied().unwrap_or_default())
+    }
+
+    fn set_redemption_license(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        license: ContentLicense,
+    ) -> eyre::Result<()> {
+        self.redemption_licenses.insert((chain_id, token_id), license);
+        Ok(())
+    }
+
+    fn get_redemption_license(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> eyre::Result<Option<ContentLicense>> {
+        Ok(self.redemption_licenses.get(&(chain_id, token_id)).cloned())
+    }
+
+    fn set_last_processed_block(
+        &mut self,
+        chain_id: u64,
+        block_number: u64,
+        log_index: u64,
+    ) -> eyre::Result<()> {
+        // Concurrent handlers may finish out of order; never move the cursor backwards.
+        let cursor = BlockCursor { block_number, log_index };
+        let last = self.last_processed_blocks.entry(chain_id).or_insert(cursor);
+        *last = cursor.max(*last);
+        Ok(())
+    }
+
+    fn get_last_processed_block(&self, chain_id: u64) -> eyre::Result<BlockCursor> {
+        self.last_processed_blocks
+            .get(&chain_id)
+            .copied()
+            .ok_or_else(|| eyre::eyre!("Last processed block not set for chain {}", chain_id))
+    }
+
+    fn mark_event_processed(
+        &mut self,
+        chain_id: u64,
+        tx_hash: String,
+        log_index: u64,
+    ) -> eyre::Result<bool> {
+        Ok(self.processed_events.insert((chain_id, tx_hash, log_index)))
+    }
+
+    fn is_event_processed(
+        &self,
+        chain_id: u64,
+        tx_hash: String,
+        log_index: u64,
+    ) -> eyre::Result<bool> {
+        Ok(self.processed_events.contains(&(chain_id, tx_hash, log_index)))
+    }
+
+    fn upsert_failed_event(&mut self, event_id: String, event: FailedEvent) -> eyre::Result<()> {
+        self.failed_events.insert(event_id, event);
+        Ok(())
+    }
+
+    fn get_due_failed_events(
+        &self,
+        chain_id: u64,
+        now: i64,
+    ) -> eyre::Result<Vec<(String, FailedEvent)>> {
+        Ok(self
+            .failed_events
+            .iter()
+            .filter(|(_, event)| {
+                event.chain_id == chain_id && event.next_attempt_at.is_some_and(|at| at <= now)
+            })
+            .map(|(event_id, event)| (event_id.clone(), event.clone()))
+            .collect())
+    }
+
+    fn remove_failed_event(&mut self, event_id: String) -> eyre::Result<()> {
+        self.failed_events.remove(&event_id);
+        Ok(())
+    }
+
+    fn add_redemption_link(&mut self, link_id: String, link: RedemptionLink) -> eyre::Result<()> {
+        self.redemption_links.insert(link_id, link);
+        Ok(())
+    }
+
+    fn get_redemption_link(&self, link_id: String) -> eyre::Result<RedemptionLink> {
+        let link = self
+            .redemption_links
+            .get(&link_id)
+            .ok_or_else(|| eyre::eyre!("Redemption link not found"))?;
+        Ok(link.clone())
+    }
+
+    fn count_open_redemption_links(&self, session_id: String, now: i64) -> eyre::Result<usize> {
+        Ok(self
+            .redemption_links
+            .values()
+            .filter(|link| link.session_id == session_id && !link.used && link.expires_at > now)
+            .count())
+    }
+
+    fn consume_redemption_link(
+        &mut self,
+        link_id: String,
+        now: i64,
+    ) -> eyre::Result<RedemptionLink> {
+        let link = self
+            .redemption_links
+            .get_mut(&link_id)
+            .ok_or_else(|| eyre::eyre!("Redemption link not found"))?;
+        if link.used {
+            eyre::bail!("Redemption link already used");
+        }
+        if link.expires_at <= now {
+            eyre::bail!("Redemption link expired");
+        }
+        link.used = true;
+        Ok(link.clone())
+    }
+
+    fn set_recovery_email(&mut self, x_id: String, recovery: RecoveryEmail) -> eyre::Result<()> {
+        self.recovery_emails.insert(x_id, recovery);
+        Ok(())
+    }
+
+    fn get_recovery_email(&self, x_id: String) -> eyre::Result<RecoveryEmail> {
+        let recovery =
+            self.recovery_emails.get(&x_id).ok_or_else(|| eyre::eyre!("Recovery email not set"))?;
+        Ok(recovery.clone())
+    }
+
+    fn set_timezone(&mut self, x_id: String, timezone: String) -> eyre::Result<()> {
+        self.timezones.insert(x_id, timezone);
+        Ok(())
+    }
+
+    fn get_timezone(&self, x_id: String) -> eyre::Result<String> {
+        let timezone = self.timezones.get(&x_id).ok_or_else(|| eyre::eyre!("Timezone not set"))?;
+        Ok(timezone.clone())
+    }
+
+    fn set_creator_webhook(&mut self, x_id: String, webhook: CreatorWebhook) -> eyre::Result<()> {
+        self.creator_webhooks.insert(x_id, webhook);
+        Ok(())
+    }
+
+    fn get_creator_webhook(&self, x_id: String) -> eyre::Result<Option<CreatorWebhook>> {
+        Ok(self.creator_webhooks.get(&x_id).cloned())
+    }
+
+    fn remove_creator_webhook(&mut self, x_id: String) -> eyre::Result<bool> {
+        Ok(self.creator_webhooks.remove(&x_id).is_some())
+    }
+
+    fn set_email_challenge(
+        &mut self,
+        x_id: String,
+        mut challenge: EmailChallenge,
+        now: i64,
+    ) -> eyre::Result<bool> {
+        if let Some(running) = self.email_challenges.get(&x_id).filter(|c| c.expires_at > now) {
+            if running.attempts >= MAX_EMAIL_CHALLENGE_ATTEMPTS {
+                return Ok(false);
+            }
+            challenge.attempts = running.attempts;
+        }
+        self.email_challenges.insert(x_id, challenge);
+        Ok(true)
+    }
+
+    fn verify_email_challenge(
+        &mut self,
+        x_id: String,
+        code: String,
+        now: i64,
+    ) -> eyre::Result<EmailChallenge> {
+        let challenge = self
+            .email_challenges
+            .get_mut(&x_id)
+            .ok_or_else(|| eyre::eyre!("Email challenge not found"))?;
+        if challenge.expires_at <= now {
+            self.email_challenges.remove(&x_id);
+            eyre::bail!("Email challenge expired");
+        }
+        if challenge.attempts >= MAX_EMAIL_CHALLENGE_ATTEMPTS {
+            eyre::bail!("Email challenge locked after too many wrong codes");
+        }
+        if !email_code_matches(&challenge.code, &code) {
+            challenge.attempts += 1;
+            eyre::bail!("Wrong email confirmation code");
+        }
+        let challenge = self.email_challenges.remove(&x_id).expect("challenge checked above");
+        Ok(challenge)
+    }
+
+    fn add_tx(&mut self, tx_hash: String, tx: TxRecord) -> eyre::Result<()> {
+        self.txs.insert(tx_hash, tx);
+        Ok(())
+    }
+
+    fn get_tx(&self, tx_hash: String) -> eyre::Result<TxRecord> {
+        let tx = self.txs.get(&tx_hash).ok_or_else(|| eyre::eyre!("Transaction not found"))?;
+        Ok(tx.clone())
+    }
+
+    fn get_pending_txs(&self, chain_id: u64) -> eyre::Result<Vec<String>> {
+        Ok(unresolved_txs(&self.txs, chain_id))
+    }
+
+    fn set_tx_mined(
+        &mut self,
+        tx_hash: String,
+        success: bool,
+        block_number: Option<u64>,
+        gas_used: u64,
+    ) -> eyre::Result<()> {
+        let tx = self.txs.get_mut(&tx_hash).ok_or_else(|| eyre::eyre!("Transaction not found"))?;
+        tx.status = if success { TxStatus::Confirmed } else { TxStatus::Failed };
+        tx.block_number = block_number;
+        tx.gas_used = Some(gas_used);
+        let mut replacement = tx.replaced_by.take();
+        for _ in 0..MAX_TX_REPLACEMENTS {
+            let Some(tx) = replacement.and_then(|hash| self.txs.get_mut(&hash)) else {
+                break;
+            };
+            tx.status = TxStatus::Replaced;
+            replacement = tx.replaced_by.replace(tx_hash.clone());
+        }
+        Ok(())
+    }
+
+    fn replace_tx(&mut self, tx_hash: String, replacement_tx_hash: String) -> eyre::Result<()> {
+        let Some(tx) = self.txs.get_mut(&tx_hash) else {
+            return Ok(());
+        };
+        tx.status = TxStatus::Replaced;
+        tx.replaced_by = Some(replacement_tx_hash.clone());
+        let replacement = TxRecord { status: TxStatus::Pending, replaced_by: None, ..tx.clone() };
+        self.txs.insert(replacement_tx_hash, replacement);
+        Ok(())
+    }
+
+    fn cancel_tx(
+        &mut self,
+        tx_hash: String,
+        cancel_tx_hash: String,
+        submitted_at: i64,
+    ) -> eyre::Result<()> {
+        let tx = self.txs.get_mut(&tx_hash).ok_or_else(|| eyre::eyre!("Transaction not found"))?;
+        tx.status = TxStatus::Cancelled;
+        tx.replaced_by = Some(cancel_tx_hash.clone());
+        let cancellation = TxRecord {
+            kind: "cancel".to_string(),
+            status: TxStatus::Pending,
+            submitted_at,
+            replaced_by: None,
+            ..tx.clone()
+        };
+        self.txs.insert(cancel_tx_hash, cancellation);
+        Ok(())
+    }
+
+    fn get_failed_event(&self, event_id: String) -> eyre::Result<FailedEvent> {
+        let event = self
+            .failed_events
+            .get(&event_id)
+            .ok_or_else(|| eyre::eyre!("Failed event not found"))?;
+        Ok(event.clone())
+    }
+
+    fn add_pending_approval(
+        &mut self,
+        approval_id: String,
+        approval: PendingApproval,
+    ) -> eyre::Result<()> {
+        self.pending_approvals.insert(approval_id, approval);
+        Ok(())
+    }
+
+    fn get_pending_approvals(&self) -> eyre::Result<Vec<(String, PendingApproval)>> {
+        Ok(self
+            .pending_approvals
+            .iter()
+            .map(|(id, approval)| (id.clone(), approval.clone()))
+            .collect())
+    }
+
+    fn remove_pending_approval(&mut self, approval_id: String) -> eyre::Result<PendingApproval> {
+        self.pending_approvals
+            .remove(&approval_id)
+            .ok_or_else(|| eyre::eyre!("Pending approval not found"))
+    }
+
+    fn prune_pending_approvals(&mut self, now: i64) -> eyre::Result<usize> {
+        let before = self.pending_approvals.len();
+        self.pending_approvals.retain(|_, approval| approval.expires_at > now);
+        Ok(before - self.pending_approvals.len())
+    }
+
+    fn add_admin_audit_entry(&mut self, entry: AdminAuditEntry) -> eyre::Result<()> {
+        self.admin_audit_log.push(entry);
+        Ok(())
+    }
+
+    fn get_admin_audit_log(&self, limit: usize) -> eyre::Result<Vec<AdminAuditEntry>> {
+        Ok(self.admin_audit_log.iter().rev().take(limit).cloned().collect())
+    }
+
+    fn add_abuse_report(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        report: AbuseReport,
+    ) -> eyre::Result<Option<usize>> {
+        let reports = self.abuse_reports.entry((chain_id, token_id)).or_default();
+        if reports.iter().any(|existing| existing.reporter == report.reporter) {
+            return Ok(None);
+        }
+        reports.push(report);
+        Ok(Some(reports.len()))
+    }
+
+    fn get_abuse_reports(&self) -> eyre::Result<Vec<((u64, String), Vec<AbuseReport>)>> {
+        Ok(self.abuse_reports.iter().map(|(key, reports)| (key.clone(), reports.clone())).collect())
+    }
+
+    fn add_creator_strike(&mut self, x_id: String) -> eyre::Result<u32> {
+        let strikes = self.creator_strikes.entry(x_id).or_default();
+        *strikes += 1;
+        Ok(*strikes)
+    }
+
+    fn get_creator_strikes(&self, x_id: String) -> eyre::Result<u32> {
+        Ok(self.creator_strikes.get(&x_id).copied().unwrap_or_default())
+    }
+
+    fn clear_creator_strikes(&mut self, x_id: String) -> eyre::Result<()> {
+        self.creator_strikes.remove(&x_id);
+        Ok(())
+    }
+
+    fn set_royalty_info(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        royalty: RoyaltyInfo,
+    ) -> eyre::Result<()> {
+        self.royalties.insert((chain_id, token_id), royalty);
+        Ok(())
+    }
+
+    fn get_royalty_info(&self, chain_id: u64, token_id: String) -> eyre::Result<RoyaltyInfo> {
+        self.royalties
+            .get(&(chain_id, token_id))
+            .cloned()
+            .ok_or_else(|| eyre::eyre!("Royalty not cached"))
+    }
+
+    fn set_dm_opt_out(&mut self, x_id: String, opt_out: bool) -> eyre::Result<()> {
+        let address = self
+            .x_id_to_address
+            .get(&x_id)
+            .ok_or_else(|| eyre::eyre!("User address not found for x_id"))?;
+        let user = self.users.get_mut(address).ok_or_else(|| eyre::eyre!("User not found"))?;
+        user.dm_opt_out = opt_out;
+        Ok(())
+    }
+
+    fn set_burn_on_redeem(&mut self, x_id: String, enabled: bool) -> eyre::Result<()> {
+        if enabled {
+            self.burn_on_redeem.insert(x_id);
+        } else {
+            self.burn_on_redeem.remove(&x_id);
+        }
+        Ok(())
+    }
+
+    fn get_burn_on_redeem(&self, x_id: String) -> eyre::Result<bool> {
+        Ok(self.burn_on_redeem.contains(&x_id))
+    }
+
+    fn approve_content(
+        &mut self,
+        x_id: String,
+        policy_hash: String,
+        content_hash: String,
+        approved: ApprovedContent,
+    ) -> eyre::Result<()> {
+        self.approved_contents.insert((x_id, policy_hash, content_hash), approved);
+        Ok(())
+    }
+
+    fn get_approved_content(
+        &self,
+        x_id: String,
+        policy_hash: String,
+        content_hash: String,
+    ) -> eyre::Result<Option<ApprovedContent>> {
+        Ok(self.approved_contents.get(&(x_id, policy_hash, content_hash)).cloned())
+    }
+
+    fn revoke_approved_content(
+        &mut self,
+        x_id: String,
+        policy_hash: String,
+        content_hash: String,
+    ) -> eyre::Result<bool> {
+        Ok(self.approved_contents.remove(&(x_id, policy_hash, content_hash)).is_some())
+    }
+
+    fn queue_burn(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        revocation: bool,
+    ) -> eyre::Result<()> {
+        let burn = self
+            .burns
+            .entry((chain_id, token_id))
+            .or_insert(PendingBurn { revocation: false, tx_hash: None });
+        burn.revocation |= revocation;
+        Ok(())
+    }
+
+    fn get_pending_burns(&self, chain_id: u64) -> eyre::Result<Vec<(String, PendingBurn)>> {
+        Ok(self
+            .burns
+            .iter()
+            .filter(|((burn_chain_id, _), _)| *burn_chain_id == chain_id)
+            .map(|((_, token_id), burn)| (token_id.clone(), burn.clone()))
+            .collect())
+    }
+
+    fn set_burn_tx(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        tx_hash: String,
+    ) -> eyre::Result<()> {
+        let burn = self
+            .burns
+            .get_mut(&(chain_id, token_id))
+            .ok_or_else(|| eyre::eyre!("Burn not queued"))?;
+        burn.tx_hash = Some(tx_hash);
+        Ok(())
+    }
+
+    fn get_burn(&self, chain_id: u64, token_id: String) -> eyre::Result<Option<PendingBurn>> {
+        Ok(self.burns.get(&(chain_id, token_id)).cloned())
+    }
+
+    fn remove_burn(&mut self, chain_id: u64, token_id: String) -> eyre::Result<()> {
+        self.burns.remove(&(chain_id, token_id));
+        Ok(())
+    }
+
+    fn queue_anchor(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        tweet_id: String,
+        content_hash: String,
+    ) -> eyre::Result<()> {
+        let anchor =
+            ContentAnchor { tweet_id, content_hash, tx_hash: None, root: None, proof: Vec::new() };
+        self.anchors.insert((chain_id, token_id), anchor);
+        Ok(())
+    }
+
+    fn get_pending_anchors(&self, chain_id: u64) -> eyre::Result<Vec<(String, ContentAnchor)>> {
+        Ok(self
+            .anchors
+            .iter()
+            .filter(|((anchor_chain_id, _), anchor)| {
+                *anchor_chain_id == chain_id && anchor.tx_hash.is_none()
+            })
+            .map(|((_, token_id), anchor)| (token_id.clone(), anchor.clone()))
+            .collect())
+    }
+
+    fn get_anchor(&self, chain_id: u64, token_id: String) -> eyre::Result<Option<ContentAnchor>> {
+        Ok(self.anchors.get(&(chain_id, token_id)).cloned())
+    }
+
+    fn set_anchor(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        anchor: ContentAnchor,
+    ) -> eyre::Result<()> {
+        self.anchors.insert((chain_id, token_id), anchor);
+        Ok(())
+    }
+
+    fn remove_anchor(&mut self, chain_id: u64, token_id: String) -> eyre::Result<()> {
+        self.anchors.remove(&(chain_id, token_id));
+        Ok(())
+    }
+
+    fn add_top_up(&mut self, chain_id: u64, address: String, at: i64) -> eyre::Result<()> {
+        let top_ups = self.top_ups.entry((chain_id, address)).or_default();
+        // Only the last day counts, so older top-ups are dropped.
+        top_ups.retain(|sent_at| at - sent_at < 24 * 60 * 60);
+        top_ups.push(at);
+        Ok(())
+    }
+
+    fn count_top_ups_since(
+        &self,
+        chain_id: u64,
+        address: String,
+        since: i64,
+    ) -> eyre::Result<usize> {
+        Ok(self
+            .top_ups
+            .get(&(chain_id, address))
+            .map_or(0, |top_ups| top_ups.iter().filter(|sent_at| **sent_at >= since).count()))
+    }
+
+    fn get_user_addresses(&self) -> eyre::Result<Vec<String>> {
+        Ok(self.users.keys().cloned().collect())
+    }
+
+    fn add_ledger_entry(&mut self, address: String, entry: LedgerEntry) -> eyre::Result<bool> {
+        let entries = self.ledger.entry(address).or_default();
+        let recorded = entries.iter().any(|recorded| {
+            recorded.chain_id == entry.chain_id &&
+                recorded.tx_hash == entry.tx_hash &&
+                recorded.log_index == entry.log_index
+        });
+        if !recorded {
+            entries.push(entry);
+        }
+        Ok(!recorded)
+    }
+
+    fn get_ledger(&self, address: String) -> eyre::Result<Vec<LedgerEntry>> {
+        Ok(self.ledger.get(&address).cloned().unwrap_or_default())
+    }
+
+    fn get_deposit_cursor(&self, chain_id: u64) -> eyre::Result<Option<u64>> {
+        Ok(self.deposit_cursors.get(&chain_id).copied())
+    }
+
+    fn set_deposit_cursor(&mut self, chain_id: u64, block_number: u64) -> eyre::Result<()> {
+        self.deposit_cursors.insert(chain_id, block_number);
+        Ok(())
+    }
+
+    fn add_mint_payment(&mut self, payment: MintPayment) -> eyre::Result<bool> {
+        let key = (payment.chain_id, payment.payment_tx.clone());
+        if self.mint_payments.contains_key(&key) {
+            return Ok(false);
+        }
+        self.mint_payments.insert(key, payment);
+        Ok(true)
+    }
+
+    fn set_payment_mint_txs(
+        &mut self,
+        chain_id: u64,
+        payment_tx: String,
+        mint_txs: Vec<String>,
+    ) -> eyre::Result<()> {
+        let payment = self
+            .mint_payments
+            .get_mut(&(chain_id, payment_tx))
+            .ok_or_else(|| eyre::eyre!("Mint payment not found"))?;
+        payment.mint_txs = mint_txs;
+        Ok(())
+    }
+
+    fn remove_mint_payment(&mut self, chain_id: u64, payment_tx: String) -> eyre::Result<()> {
+        self.mint_payments.remove(&(chain_id, payment_tx));
+        Ok(())
+    }
+
+    fn get_mint_payments(&self) -> eyre::Result<Vec<MintPayment>> {
+        let mut payments: Vec<MintPayment> = self.mint_payments.values().cloned().collect();
+        payments.sort_by_key(|payment| payment.paid_at);
+        Ok(payments)
+    }
+
+    fn get_relay_cursor(&self, chain_id: u64) -> eyre::Result<Option<u64>> {
+        Ok(self.relay_cursors.get(&chain_id).copied())
+    }
+
+    fn set_relay_cursor(&mut self, chain_id: u64, block_number: u64) -> eyre::Result<()> {
+        self.relay_cursors.insert(chain_id, block_number);
+        Ok(())
+    }
+
+    fn get_relayed(&self, log_id: String) -> eyre::Result<Option<String>> {
+        Ok(self.relayed.get(&log_id).cloned())
+    }
+
+    fn set_relayed(&mut self, log_id: String, tx_hash: String) -> eyre::Result<()> {
+        self.relayed.insert(log_id, tx_hash);
+        Ok(())
+    }
+
+    fn set_access_list_entry(&mut self, entry: AccessListEntry) -> eyre::Result<()> {
+        self.access_list.insert(entry.subject.clone(), entry);
+        Ok(())
+    }
+
+    fn remove_access_list_entry(&mut self, subject: String) -> eyre::Result<bool> {
+        Ok(self.access_list.remove(&subject).is_some())
+    }
+
+    fn get_access_list_entry(&self, subject: String) -> eyre::Result<Option<AccessListEntry>> {
+        Ok(self.access_list.get(&subject).cloned())
+    }
+
+    fn get_access_list(&self) -> eyre::Result<Vec<AccessListEntry>> {
+        Ok(self.access_list.values().cloned().collect())
+    }
+
+    fn add_inbox_message(&mut self, message: InboxMessage) -> eyre::Result<()> {
+        self.inbox.push(message);
+        Ok(())
+    }
+
+    fn get_kill_switches(&self) -> eyre::Result<BTreeMap<String, bool>> {
+        Ok(self.kill_switches.clone())
+    }
+
+    fn set_kill_switch(&mut self, side_effect: String, enabled: bool) -> eyre::Result<()> {
+        self.kill_switches.insert(side_effect, enabled);
+        Ok(())
+    }
+
+    fn get_contract_status(&self, chain_id: u64, contract: String) -> eyre::Result<ContractStatus> {
+        Ok(self.contract_statuses.get(&(chain_id, contract)).cloned().unwrap_or_default())
+    }
+
+    fn set_contract_status(
+        &mut self,
+        chain_id: u64,
+        contract: String,
+        status: ContractStatus,
+    ) -> eyre::Result<()> {
+        self.contract_statuses.insert((chain_id, contract), status);
+        Ok(())
+    }
+
+    fn get_inbox(&self, creator: String) -> eyre::Result<Vec<InboxMessage>> {
+        Ok(self.inbox.iter().rev().filter(|message| message.creator == creator).cloned().collect())
+    }
+
+    fn count_inbox_messages_since(&self, sender: String, since: i64) -> eyre::Result<usize> {
+        Ok(self
+            .inbox
+            .iter()
+            .filter(|message| message.sender == sender && message.sent_at >= since)
+            .count())
+    }
+
+    fn set_token_metadata(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        metadata: String,
+    ) -> eyre::Result<()> {
+        self.token_metadata
+            .insert((chain_id, token_id), TokenMetadataRecord { metadata, cid: None });
+        Ok(())
+    }
+
+    fn get_token_metadata(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> eyre::Result<Option<TokenMetadataRecord>> {
+        Ok(self.token_metadata.get(&(chain_id, token_id)).cloned())
+    }
+
+    fn get_unpinned_metadata(&self) -> eyre::Result<Vec<(u64, String, String)>> {
+        Ok(self
+            .token_metadata
+            .iter()
+            .filter(|(_, record)| record.cid.is_none())
+            .map(|((chain_id, token_id), record)| {
+                (*chain_id, token_id.clone(), record.metadata.clone())
+            })
+            .collect())
+    }
+
+    fn set_metadata_cid(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        cid: String,
+    ) -> eyre::Result<()> {
+        let record = self
+            .token_metadata
+            .get_mut(&(chain_id, token_id))
+            .ok_or_else(|| eyre::eyre!("Token metadata not found"))?;
+        record.cid = Some(cid);
+        Ok(())
+    }
+
+    fn get_creator_signals(&self, x_id: String) -> eyre::Result<CreatorSignals> {
+        Ok(self.creator_signals.get(&x_id).cloned().unwrap_or_default())
+    }
+
+    fn set_creator_signals(&mut self, x_id: String, signals: CreatorSignals) -> eyre::Result<()> {
+        self.creator_signals.insert(x_id, signals);
+        Ok(())
+    }
+
+    fn add_reputation_snapshot(
+        &mut self,
+        x_id: String,
+        snapshot: ReputationSnapshot,
+    ) -> eyre::Result<()> {
+        let history = self.reputation_history.entry(x_id).or_default();
+        history.push(snapshot);
+        if history.len() > MAX_REPUTATION_HISTORY {
+            history.remove(0);
+        }
+        Ok(())
+    }
+
+    fn get_reputation_history(&self, x_id: String) -> eyre::Result<Vec<ReputationSnapshot>> {
+        Ok(self.reputation_history.get(&x_id).cloned().unwrap_or_default())
+    }
+
+    fn get_redeem_nonce(&self, address: String) -> eyre::Result<u64> {
+        Ok(self.redeem_nonces.get(&address).copied().unwrap_or_default())
+    }
+
+    fn use_redeem_nonce(&mut self, address: String, nonce: u64) -> eyre::Result<()> {
+        let current = self.redeem_nonces.entry(address).or_default();
+        if *current != nonce {
+            eyre::bail!("Expected redeem nonce {}, got {}", current, nonce);
+        }
+        *current += 1;
+        Ok(())
+    }
+
+    fn get_collection_stats(&self, chain_id: u64) -> eyre::Result<CollectionStats> {
+        Ok(self.collection_stats.get(&chain_id).cloned().unwrap_or_default())
+    }
+
+    fn list_users(&self, after: Option<String>, limit: usize) -> eyre::Result<Vec<(String, User)>> {
+        Ok(self
+            .users
+            .range((after.map_or(Bound::Unbounded, Bound::Excluded), Bound::Unbounded))
+            .take(limit)
+            .map(|(address, user)| (address.clone(), user.clone()))
+            .collect())
+    }
+
+    fn list_nfts(&self, after: Option<String>, limit: usize) -> eyre::Result<Vec<(String, NFT)>> {
+        Ok(self
+            .nfts
+            .range((after.map_or(Bound::Unbounded, Bound::Excluded), Bound::Unbounded))
+            .take(limit)
+            .map(|(nft_id, nft)| (nft_id.clone(), nft.clone()))
+            .collect())
+    }
+
+    fn list_redemptions(
+        &self,
+        chain_id: u64,
+        after: Option<String>,
+        limit: usize,
+    ) -> eyre::Result<Vec<RedemptionRecord>> {
+        let after = after.unwrap_or_default();
+        let mut records: Vec<_> = self
+            .moderation_records
+            .range((chain_id, String::new())..(chain_id + 1, String::new()))
+            .filter(|((_, token_id), _)| token_id_order(token_id) > token_id_order(&after))
+            .collect();
+        records.sort_by(|((_, a), _), ((_, b), _)| token_id_order(a).cmp(&token_id_order(b)));
+        Ok(records
+            .into_iter()
+            .take(limit)
+            .map(|(key, record)| RedemptionRecord {
+                token_id: key.1.clone(),
+                prompt_version: record.prompt_version.clone(),
+                safe: record.safe,
+                tweet_id: self.tweets.get(key).cloned(),
+            })
+            .collect())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::db::AccessTokens;
+    use crate::db::{AccessTokens, EmailPurpose};
 
     use super::*;
 
@@ -112,6 +1291,9 @@ mod tests {
             x_id: None,
             access_tokens: Some(access_tokens.clone()),
             oauth_tokens: access_tokens.clone(),
+            ens_name: None,
+            dm_opt_out: false,
+            unlinked_x_id: None,
         };
         db.add_user("2".to_string(), user.clone()).expect("Failed to add user tokens");
         let user = db.get_user_by_address("2".to_string())?;
@@ -128,6 +1310,9 @@ mod tests {
             x_id: None,
             access_tokens: Some(access_tokens.clone()),
             oauth_tokens: access_tokens.clone(),
+            ens_name: None,
+            dm_opt_out: false,
+            unlinked_x_id: None,
         };
         db.add_user("2".to_string(), user.clone()).expect("Failed to add user tokens");
         user.x_id = Some("1".to_string());
@@ -136,4 +1321,329 @@ mod tests {
         assert_eq!(user, fetched_user);
         Ok(())
     }
+
+    #[test]
+    fn db_test_dm_opt_out() -> eyre::Result<()> {
+        let mut db = InMemoryDB::new();
+        let user = User { x_id: Some("1".to_string()), ..Default::default() };
+        db.add_user("2".to_string(), user)?;
+        assert!(!db.get_user_by_address("2".to_string())?.dm_opt_out);
+        db.set_dm_opt_out("1".to_string(), true)?;
+        assert!(db.get_user_by_address("2".to_string())?.dm_opt_out);
+        db.set_dm_opt_out("1".to_string(), false)?;
+        assert!(!db.get_user_by_address("2".to_string())?.dm_opt_out);
+        assert!(db.set_dm_opt_out("3".to_string(), true).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_user_by_address_ignores_case() -> eyre::Result<()> {
+        let mut db = InMemoryDB::new();
+        let user = User { x_id: Some("1".to_string()), ..Default::default() };
+        db.add_user("0xabcdef".to_string(), user.clone())?;
+        assert_eq!(db.get_user_by_address("0xABCdef".to_string())?, user);
+        assert_eq!(db.get_user_by_address("0xabcdef".to_string())?, user);
+        assert!(db.get_user_by_address("0xabcde".to_string()).is_err());
+        // The index is not saved, but rebuilt on load.
+        let db = InMemoryDB::deserialize(&db.serialize()?)?;
+        assert_eq!(db.get_user_by_address("0xABCdef".to_string())?, user);
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_user_by_parsed_address() -> eyre::Result<()> {
+        let mut db = InMemoryDB::new();
+        let user = User { x_id: Some("1".to_string()), ..Default::default() };
+        let address = alloy::primitives::Address::repeat_byte(0xab);
+        db.add_user(address.to_string().to_lowercase(), user.clone())?;
+        assert_eq!(db.get_user_by_address(address.to_string())?, user);
+        let unprefixed = address.to_string().trim_start_matches("0x").to_string();
+        assert_eq!(db.get_user_by_address(format!(" {unprefixed} "))?, user);
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_unlink_and_relink() -> eyre::Result<()> {
+        let mut db = InMemoryDB::new();
+        let user = User { x_id: Some("old".to_string()), ..Default::default() };
+        db.add_user("0x1".to_string(), user)?;
+        let session_id =
+            db.add_session(Session { x_id: "old".to_string(), address: "0x1".to_string() })?;
+        db.set_timezone("old".to_string(), "Europe/Berlin".to_string())?;
+
+        assert_eq!(db.unlink_x_account("0x1".to_string())?, "old");
+        assert!(db.get_user_by_x_id("old".to_string()).is_err());
+        assert!(db.get_session(session_id).is_err());
+        let user = db.get_user_by_address("0x1".to_string())?;
+        assert_eq!((user.x_id, user.unlinked_x_id), (None, Some("old".to_string())));
+
+        db.migrate_x_account("old".to_string(), "new".to_string())?;
+        assert_eq!(db.get_timezone("new".to_string())?, "Europe/Berlin");
+        assert!(db.get_timezone("old".to_string()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_read_models_survive_reload() -> eyre::Result<()> {
+        let mut db = InMemoryDB::new();
+        let pending_nft = PendingNFT {
+            address: "0x1".to_string(),
+            nft_id: "nft".to_string(),
+            chain_id: 1,
+            policy_hash: "0xpolicy".to_string(),
+        };
+        db.add_pending_nft("0xtx".to_string(), pending_nft.clone())?;
+        db.alias_pending_nft("0xtx".to_string(), "0xreplacement".to_string())?;
+        db.add_pending_nft("0xother".to_string(), PendingNFT { chain_id: 2, ..pending_nft })?;
+        db.promote_pending_nft("0xtx".to_string(), "7".to_string())?;
+        let record = |safe| ModerationRecord { prompt_version: "v1".to_string(), safe };
+        db.add_moderation_record(1, "7".to_string(), record(false))?;
+        db.add_moderation_record(1, "7".to_string(), record(true))?;
+        db.add_tweet(1, "7".to_string(), "100".to_string())?;
+        db.add_tweet(1, "7".to_string(), "101".to_string())?;
+
+        let expected = CollectionStats {
+            minted: 1,
+            pending_mints: 1,
+            redeemed: 1,
+            rejected_redemptions: 0,
+            tweets: 1,
+        };
+        assert_eq!(db.get_collection_stats(1)?, expected);
+        let db = InMemoryDB::deserialize(&db.serialize()?)?;
+        assert_eq!(db.get_collection_stats(1)?, expected);
+        assert_eq!(db.get_collection_stats(2)?.pending_mints, 1);
+        assert_eq!(db.get_nft_id_by_token_id(1, "7".to_string())?, "nft");
+        assert!(db.get_nft_by_token_id(2, "7".to_string()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_list_users_pages() -> eyre::Result<()> {
+        let mut db = InMemoryDB::new();
+        for address in ["0x3", "0x1", "0x2"] {
+            db.add_user(address.to_string(), User::default())?;
+        }
+        let addresses = |page: Vec<(String, User)>| -> Vec<String> {
+            page.into_iter().map(|(address, _)| address).collect()
+        };
+        assert_eq!(addresses(db.list_users(None, 2)?), vec!["0x1", "0x2"]);
+        assert_eq!(addresses(db.list_users(Some("0x2".to_string()), 2)?), vec!["0x3"]);
+        assert!(db.list_users(Some("0x3".to_string()), 2)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_last_processed_block() -> eyre::Result<()> {
+        let mut db = InMemoryDB::new();
+        assert!(db.get_last_processed_block(1).is_err());
+        db.set_last_processed_block(1, 10, 2)?;
+        db.set_last_processed_block(1, 10, 1)?;
+        db.set_last_processed_block(1, 9, 5)?;
+        db.set_last_processed_block(2, 3, 0)?;
+        let expected = BlockCursor { block_number: 10, log_index: 2 };
+        assert_eq!(db.get_last_processed_block(1)?, expected);
+        let db = InMemoryDB::deserialize(&db.serialize()?)?;
+        assert_eq!(db.get_last_processed_block(1)?, expected);
+        assert_eq!(db.get_last_processed_block(2)?, BlockCursor { block_number: 3, log_index: 0 });
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_processed_events() -> eyre::Result<()> {
+        let mut db = InMemoryDB::new();
+        assert!(db.mark_event_processed(1, "0xabc".to_string(), 1)?);
+        assert!(db.mark_event_processed(1, "0xabc".to_string(), 2)?);
+        assert!(!db.mark_event_processed(1, "0xabc".to_string(), 1)?);
+        // The same hash and index on another chain is a different log.
+        assert!(db.mark_event_processed(2, "0xabc".to_string(), 1)?);
+        assert!(db.is_event_processed(2, "0xabc".to_string(), 1)?);
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_failed_events() -> eyre::Result<()> {
+        let mut db = InMemoryDB::new();
+        let event = FailedEvent {
+            chain_id: 1,
+            log: "{}".to_string(),
+            attempts: 1,
+            next_attempt_at: Some(100),
+            last_error: "error".to_string(),
+        };
+        db.upsert_failed_event("a".to_string(), event.clone())?;
+        db.upsert_failed_event("b".to_string(), FailedEvent { next_attempt_at: None, ..event })?;
+        assert!(db.get_due_failed_events(1, 99)?.is_empty());
+        assert!(db.get_due_failed_events(2, 100)?.is_empty());
+        let due = db.get_due_failed_events(1, 100)?;
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0, "a");
+        db.remove_failed_event("a".to_string())?;
+        assert!(db.get_due_failed_events(1, 100)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_redemption_link_is_single_use() -> eyre::Result<()> {
+        let mut db = InMemoryDB::new();
+        let link = RedemptionLink {
+            nft_id: "nft".to_string(),
+            session_id: "session".to_string(),
+            expires_at: 100,
+            used: false,
+        };
+        db.add_redemption_link("a".to_string(), link.clone())?;
+        db.add_redemption_link("b".to_string(), link)?;
+        assert_eq!(db.count_open_redemption_links("session".to_string(), 50)?, 2);
+        assert!(db.consume_redemption_link("a".to_string(), 50)?.used);
+        assert!(db.consume_redemption_link("a".to_string(), 50).is_err());
+        assert!(db.consume_redemption_link("b".to_string(), 100).is_err());
+        assert_eq!(db.count_open_redemption_links("session".to_string(), 50)?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_list_redemptions_in_token_order() -> eyre::Result<()> {
+        let mut db = InMemoryDB::new();
+        let record = ModerationRecord { prompt_version: "v1".to_string(), safe: true };
+        for token_id in ["9", "10", "100", "2"] {
+            db.add_moderation_record(1, token_id.to_string(), record.clone())?;
+        }
+        db.add_moderation_record(2, "1".to_string(), record)?;
+        let token_ids = |page: Vec<RedemptionRecord>| -> Vec<String> {
+            page.into_iter().map(|redemption| redemption.token_id).collect()
+        };
+        assert_eq!(token_ids(db.list_redemptions(1, None, 3)?), ["2", "9", "10"]);
+        assert_eq!(token_ids(db.list_redemptions(1, Some("9".to_string()), 3)?), ["10", "100"]);
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_tx_tracking() -> eyre::Result<()> {
+        let mut db = InMemoryDB::new();
+        let tx = TxRecord {
+            chain_id: 1,
+            kind: "mint".to_string(),
+            status: TxStatus::Pending,
+            submitted_at: 0,
+            block_number: None,
+            gas_used: None,
+            replaced_by: None,
+            requested_by: None,
+        };
+        db.add_tx("0xa".to_string(), tx.clone())?;
+        db.replace_tx("0xa".to_string(), "0xb".to_string())?;
+        assert_eq!(db.get_tx("0xa".to_string())?.replaced_by.as_deref(), Some("0xb"));
+        assert_eq!(db.get_pending_txs(1)?, vec!["0xb".to_string()]);
+        db.set_tx_mined("0xb".to_string(), true, Some(10), 21000)?;
+        assert_eq!(db.get_tx("0xb".to_string())?.status, TxStatus::Confirmed);
+        assert!(db.get_pending_txs(1)?.is_empty());
+
+        // The original can still be mined after it was replaced.
+        db.add_tx("0xc".to_string(), tx)?;
+        db.replace_tx("0xc".to_string(), "0xd".to_string())?;
+        db.replace_tx("0xd".to_string(), "0xe".to_string())?;
+        assert_eq!(db.get_pending_txs(1)?, ["0xc", "0xd", "0xe"]);
+        db.set_tx_mined("0xc".to_string(), true, Some(11), 21000)?;
+        let original = db.get_tx("0xc".to_string())?;
+        assert_eq!((original.status, original.replaced_by), (TxStatus::Confirmed, None));
+        for replacement in ["0xd", "0xe"] {
+            let replacement = db.get_tx(replacement.to_string())?;
+            assert_eq!(replacement.status, TxStatus::Replaced);
+            assert_eq!(replacement.replaced_by.as_deref(), Some("0xc"));
+        }
+        assert!(db.get_pending_txs(1)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_email_challenge_attempts() -> eyre::Result<()> {
+        let mut db = InMemoryDB::new();
+        let challenge = EmailChallenge {
+            purpose: EmailPurpose::Verify,
+            address: "0x1".to_string(),
+            email: "a@b.c".to_string(),
+            code: "123456".to_string(),
+            expires_at: 100,
+            attempts: 0,
+        };
+        assert!(db.set_email_challenge("1".to_string(), challenge.clone(), 0)?);
+        assert!(db.verify_email_challenge("1".to_string(), "000000".to_string(), 50).is_err());
+        assert_eq!(
+            db.verify_email_challenge("1".to_string(), "123456".to_string(), 50)?.email,
+            "a@b.c"
+        );
+        assert!(db.verify_email_challenge("1".to_string(), "123456".to_string(), 50).is_err());
+
+        assert!(db.set_email_challenge("1".to_string(), challenge.clone(), 0)?);
+        for _ in 0..MAX_EMAIL_CHALLENGE_ATTEMPTS - 1 {
+            assert!(db.verify_email_challenge("1".to_string(), "000000".to_string(), 50).is_err());
+        }
+        // Sending another code keeps the wrong attempts.
+        assert!(db.set_email_challenge("1".to_string(), challenge.clone(), 50)?);
+        assert!(db.verify_email_challenge("1".to_string(), "000000".to_string(), 50).is_err());
+        assert!(db.verify_email_challenge("1".to_string(), "123456".to_string(), 50).is_err());
+        assert!(!db.set_email_challenge("1".to_string(), challenge.clone(), 50)?);
+        // Until the locked challenge expires.
+        let fresh = EmailChallenge { expires_at: 300, ..challenge };
+        assert!(db.set_email_challenge("1".to_string(), fresh, 100)?);
+        assert!(db.verify_email_challenge("1".to_string(), "123456".to_string(), 150).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_promotion_clears_mint_confirmation() -> eyre::Result<()> {
+        let mut db = InMemoryDB::new();
+        let pending_nft = PendingNFT {
+            address: "0x1".to_string(),
+            nft_id: "nft".to_string(),
+            chain_id: 1,
+            policy_hash: "0xpolicy".to_string(),
+        };
+        db.add_pending_nft("0xtx".to_string(), pending_nft)?;
+        let confirmation = MintConfirmation {
+            nft_id: "nft".to_string(),
+            chain_id: 1,
+            token_id: "7".to_string(),
+            confirmations: 2,
+        };
+        db.set_mint_confirmation("0xtx".to_string(), confirmation.clone())?;
+        assert_eq!(db.get_mint_confirmation_by_nft_id("nft".to_string())?, Some(confirmation));
+        assert_eq!(db.get_mint_confirmations(2)?.len(), 0);
+
+        assert_eq!(db.promote_pending_nft("0xtx".to_string(), "7".to_string())?, "nft");
+        assert!(db.get_mint_confirmations(1)?.is_empty());
+        assert!(db.get_pending_nft_by_id("nft".to_string())?.is_none());
+        assert!(!db.remove_mint_confirmation("0xtx".to_string())?);
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_policy_snapshot_follows_promotion() -> eyre::Result<()> {
+        let mut db = InMemoryDB::new();
+        let pending_nft = PendingNFT {
+            address: "0x1".to_string(),
+            nft_id: "nft".to_string(),
+            chain_id: 1,
+            policy_hash: "0xpolicy".to_string(),
+        };
+        db.add_pending_nft("0xtx".to_string(), pending_nft)?;
+        let snapshot = PolicySnapshot {
+            policy: "no spam".to_string(),
+            policy_hash: "0xpolicy".to_string(),
+            precheck_threshold: Some(0.3),
+            mention_rule: None,
+            prompt_version: "v1".to_string(),
+            pinned_at: 0,
+            license_rule: None,
+            moderation_tier: ModerationTier::Standard,
+        };
+        db.set_pending_policy_snapshot("nft".to_string(), snapshot.clone())?;
+        assert_eq!(db.get_policy_snapshot(1, "7".to_string())?, None);
+
+        db.promote_pending_nft("0xtx".to_string(), "7".to_string())?;
+        assert_eq!(db.get_policy_snapshot(1, "7".to_string())?, Some(snapshot));
+        assert!(db.pending_policy_snapshots.is_empty());
+        Ok(())
+    }
 }
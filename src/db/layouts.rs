@@ -0,0 +1,402 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Deserialize;
+
+use super::{
+    in_memory::InMemoryDB, AbuseReport, AccessListEntry, AccessTokens, AdminAuditEntry,
+    ApprovedContent, BlockCursor, ContentAnchor, ContentLicense, ContractStatus, CreatorSignals,
+    EmailChallenge, FailedEvent, InboxMessage, LedgerEntry, LicenseRule, MentionRule,
+    MintConfirmation, MintPayment, ModerationRecord, ModerationTier, PendingApproval, PendingBurn,
+    PendingNFT, PolicySnapshot, RecoveryEmail, RedemptionLink, ReputationSnapshot, RoyaltyInfo,
+    Session, TokenMetadataRecord, TxRecord, User, NFT,
+};
+use crate::actions::chain::DEFAULT_CHAIN_ID;
+
+/// [`InMemoryDB`] as it was saved before snapshots were framed and versioned, when Base was the
+/// only chain served and users, tokens and tweets carried less.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Default, serde::Serialize))]
+pub struct V0 {
+    x_id_to_address: BTreeMap<String, String>,
+    users: BTreeMap<String, V0User>,
+    pending_nfts: BTreeMap<String, V0PendingNFT>,
+    nfts: BTreeMap<String, V0NFT>,
+    /// Keyed by token id alone.
+    tweets: BTreeMap<String, String>,
+    sessions: BTreeMap<String, Session>,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct V0User {
+    x_id: Option<String>,
+    access_tokens: Option<AccessTokens>,
+    oauth_tokens: AccessTokens,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct V0NFT {
+    address: String,
+    token_id: String,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct V0PendingNFT {
+    address: String,
+    nft_id: String,
+}
+
+impl From<V0> for InMemoryDB {
+    fn from(v0: V0) -> Self {
+        let chain_id = DEFAULT_CHAIN_ID;
+        let users = v0.users.into_iter().map(|(address, user)| {
+            let user = User {
+                x_id: user.x_id,
+                access_tokens: user.access_tokens,
+                oauth_tokens: user.oauth_tokens,
+                ..Default::default()
+            };
+            (address, user)
+        });
+        // Minted before policies were pinned to tokens, so with no policy hash to check.
+        let pending_nfts = v0.pending_nfts.into_iter().map(|(tx_hash, pending_nft)| {
+            let pending_nft = PendingNFT {
+                address: pending_nft.address,
+                nft_id: pending_nft.nft_id,
+                chain_id,
+                policy_hash: String::new(),
+            };
+            (tx_hash, pending_nft)
+        });
+        let nfts = v0.nfts.into_iter().map(|(nft_id, nft)| {
+            (nft_id, NFT { address: nft.address, token_id: nft.token_id, chain_id })
+        });
+        let tweets =
+            v0.tweets.into_iter().map(|(token_id, tweet_id)| ((chain_id, token_id), tweet_id));
+        Self {
+            x_id_to_address: v0.x_id_to_address,
+            users: users.collect(),
+            pending_nfts: pending_nfts.collect(),
+            nfts: nfts.collect(),
+            tweets: tweets.collect(),
+            sessions: v0.sessions,
+            ..Default::default()
+        }
+    }
+}
+
+/// [`InMemoryDB`] as schema version 1 laid it out, before creator webhooks. bincode is not
+/// self-describing, so an older image can only be read with the fields it was written with, in
+/// their order, before being moved into the next layout.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Default, serde::Serialize))]
+pub struct V1 {
+    x_id_to_address: BTreeMap<String, String>,
+    users: BTreeMap<String, User>,
+    pending_nfts: BTreeMap<String, PendingNFT>,
+    mint_confirmations: BTreeMap<String, MintConfirmation>,
+    pending_policy_snapshots: BTreeMap<String, PolicySnapshot>,
+    policy_snapshots: BTreeMap<(u64, String), PolicySnapshot>,
+    nfts: BTreeMap<String, NFT>,
+    tweets: BTreeMap<(u64, String), String>,
+    sessions: BTreeMap<String, Session>,
+    moderation_records: BTreeMap<(u64, String), ModerationRecord>,
+    policy_precheck_thresholds: BTreeMap<String, f32>,
+    policy_mention_rules: BTreeMap<String, MentionRule>,
+    policy_license_rules: BTreeMap<String, LicenseRule>,
+    policy_moderation_tiers: BTreeMap<String, ModerationTier>,
+    redemption_licenses: BTreeMap<(u64, String), ContentLicense>,
+    approved_mentions: BTreeSet<(u64, String)>,
+    approved_quorum_disputes: BTreeSet<(u64, String)>,
+    last_processed_block: Option<BlockCursor>,
+    processed_events: BTreeSet<(String, u64)>,
+    failed_events: BTreeMap<String, FailedEvent>,
+    redemption_links: BTreeMap<String, RedemptionLink>,
+    recovery_emails: BTreeMap<String, RecoveryEmail>,
+    timezones: BTreeMap<String, String>,
+    email_challenges: BTreeMap<String, EmailChallenge>,
+    admin_audit_log: Vec<AdminAuditEntry>,
+    txs: BTreeMap<String, TxRecord>,
+    pending_approvals: BTreeMap<String, PendingApproval>,
+    abuse_reports: BTreeMap<(u64, String), Vec<AbuseReport>>,
+    creator_strikes: BTreeMap<String, u32>,
+    royalties: BTreeMap<(u64, String), RoyaltyInfo>,
+    burn_on_redeem: BTreeSet<String>,
+    approved_contents: BTreeMap<(String, String, String), ApprovedContent>,
+    burns: BTreeMap<(u64, String), PendingBurn>,
+    anchors: BTreeMap<(u64, String), ContentAnchor>,
+    top_ups: BTreeMap<(u64, String), Vec<i64>>,
+    creator_signals: BTreeMap<String, CreatorSignals>,
+    reputation_history: BTreeMap<String, Vec<ReputationSnapshot>>,
+    redeem_nonces: BTreeMap<String, u64>,
+    policy_hashes: BTreeMap<(u64, String), String>,
+    mint_txs: BTreeMap<(u64, String), String>,
+    ledger: BTreeMap<String, Vec<LedgerEntry>>,
+    mint_payments: BTreeMap<(u64, String), MintPayment>,
+    relay_cursors: BTreeMap<u64, u64>,
+    relayed: BTreeMap<String, String>,
+    access_list: BTreeMap<String, AccessListEntry>,
+    inbox: Vec<InboxMessage>,
+    contract_statuses: BTreeMap<(u64, String), ContractStatus>,
+    kill_switches: BTreeMap<String, bool>,
+    deposit_cursors: BTreeMap<u64, u64>,
+    token_metadata: BTreeMap<(u64, String), TokenMetadataRecord>,
+}
+
+impl From<V1> for V2 {
+    fn from(v1: V1) -> Self {
+        Self {
+            x_id_to_address: v1.x_id_to_address,
+            users: v1.users,
+            pending_nfts: v1.pending_nfts,
+            mint_confirmations: v1.mint_confirmations,
+            pending_policy_snapshots: v1.pending_policy_snapshots,
+            policy_snapshots: v1.policy_snapshots,
+            nfts: v1.nfts,
+            tweets: v1.tweets,
+            sessions: v1.sessions,
+            moderation_records: v1.moderation_records,
+            policy_precheck_thresholds: v1.policy_precheck_thresholds,
+            policy_mention_rules: v1.policy_mention_rules,
+            policy_license_rules: v1.policy_license_rules,
+            policy_moderation_tiers: v1.policy_moderation_tiers,
+            redemption_licenses: v1.redemption_licenses,
+            approved_mentions: v1.approved_mentions,
+            approved_quorum_disputes: v1.approved_quorum_disputes,
+            last_processed_block: v1.last_processed_block,
+            processed_events: v1.processed_events,
+            failed_events: v1.failed_events,
+            redemption_links: v1.redemption_links,
+            recovery_emails: v1.recovery_emails,
+            timezones: v1.timezones,
+            creator_webhooks: BTreeMap::new(),
+            email_challenges: v1.email_challenges,
+            admin_audit_log: v1.admin_audit_log,
+            txs: v1.txs,
+            pending_approvals: v1.pending_approvals,
+            abuse_reports: v1.abuse_reports,
+            creator_strikes: v1.creator_strikes,
+            royalties: v1.royalties,
+            burn_on_redeem: v1.burn_on_redeem,
+            approved_contents: v1.approved_contents,
+            burns: v1.burns,
+            anchors: v1.anchors,
+            top_ups: v1.top_ups,
+            creator_signals: v1.creator_signals,
+            reputation_history: v1.reputation_history,
+            redeem_nonces: v1.redeem_nonces,
+            policy_hashes: v1.policy_hashes,
+            mint_txs: v1.mint_txs,
+            ledger: v1.ledger,
+            mint_payments: v1.mint_payments,
+            relay_cursors: v1.relay_cursors,
+            relayed: v1.relayed,
+            access_list: v1.access_list,
+            inbox: v1.inbox,
+            contract_statuses: v1.contract_statuses,
+            kill_switches: v1.kill_switches,
+            deposit_cursors: v1.deposit_cursors,
+            token_metadata: v1.token_metadata,
+        }
+    }
+}
+
+/// [`InMemoryDB`] as schema version 2 laid it out, when a single block cursor and set of
+/// processed events were shared by every chain's indexer.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Default, serde::Serialize))]
+pub struct V2 {
+    x_id_to_address: BTreeMap<String, String>,
+    users: BTreeMap<String, User>,
+    pending_nfts: BTreeMap<String, PendingNFT>,
+    mint_confirmations: BTreeMap<String, MintConfirmation>,
+    pending_policy_snapshots: BTreeMap<String, PolicySnapshot>,
+    policy_snapshots: BTreeMap<(u64, String), PolicySnapshot>,
+    nfts: BTreeMap<String, NFT>,
+    tweets: BTreeMap<(u64, String), String>,
+    sessions: BTreeMap<String, Session>,
+    moderation_records: BTreeMap<(u64, String), ModerationRecord>,
+    policy_precheck_thresholds: BTreeMap<String, f32>,
+    policy_mention_rules: BTreeMap<String, MentionRule>,
+    policy_license_rules: BTreeMap<String, LicenseRule>,
+    policy_moderation_tiers: BTreeMap<String, ModerationTier>,
+    redemption_licenses: BTreeMap<(u64, String), ContentLicense>,
+    approved_mentions: BTreeSet<(u64, String)>,
+    approved_quorum_disputes: BTreeSet<(u64, String)>,
+    last_processed_block: Option<BlockCursor>,
+    processed_events: BTreeSet<(String, u64)>,
+    failed_events: BTreeMap<String, FailedEvent>,
+    redemption_links: BTreeMap<String, RedemptionLink>,
+    recovery_emails: BTreeMap<String, RecoveryEmail>,
+    timezones: BTreeMap<String, String>,
+    creator_webhooks: BTreeMap<String, CreatorWebhook>,
+    email_challenges: BTreeMap<String, EmailChallenge>,
+    admin_audit_log: Vec<AdminAuditEntry>,
+    txs: BTreeMap<String, TxRecord>,
+    pending_approvals: BTreeMap<String, PendingApproval>,
+    abuse_reports: BTreeMap<(u64, String), Vec<AbuseReport>>,
+    creator_strikes: BTreeMap<String, u32>,
+    royalties: BTreeMap<(u64, String), RoyaltyInfo>,
+    burn_on_redeem: BTreeSet<String>,
+    approved_contents: BTreeMap<(String, String, String), ApprovedContent>,
+    burns: BTreeMap<(u64, String), PendingBurn>,
+    anchors: BTreeMap<(u64, String), ContentAnchor>,
+    top_ups: BTreeMap<(u64, String), Vec<i64>>,
+    creator_signals: BTreeMap<String, CreatorSignals>,
+    reputation_history: BTreeMap<String, Vec<ReputationSnapshot>>,
+    redeem_nonces: BTreeMap<String, u64>,
+    policy_hashes: BTreeMap<(u64, String), String>,
+    mint_txs: BTreeMap<(u64, String), String>,
+    ledger: BTreeMap<String, Vec<LedgerEntry>>,
+    mint_payments: BTreeMap<(u64, String), MintPayment>,
+    relay_cursors: BTreeMap<u64, u64>,
+    relayed: BTreeMap<String, String>,
+    access_list: BTreeMap<String, AccessListEntry>,
+    inbox: Vec<InboxMessage>,
+    contract_statuses: BTreeMap<(u64, String), ContractStatus>,
+    kill_switches: BTreeMap<String, bool>,
+    deposit_cursors: BTreeMap<u64, u64>,
+    token_metadata: BTreeMap<(u64, String), TokenMetadataRecord>,
+}
+
+impl From<V2> for InMemoryDB {
+    fn from(v2: V2) -> Self {
+        // The shared cursor is kept for Base, the default chain. Other chains start again from
+        // their confirmed head, as on a first start.
+        let chain_id = DEFAULT_CHAIN_ID;
+        Self {
+            x_id_to_address: v2.x_id_to_address,
+            users: v2.users,
+            pending_nfts: v2.pending_nfts,
+            mint_confirmations: v2.mint_confirmations,
+            pending_policy_snapshots: v2.pending_policy_snapshots,
+            policy_snapshots: v2.policy_snapshots,
+            nfts: v2.nfts,
+            tweets: v2.tweets,
+            sessions: v2.sessions,
+            moderation_records: v2.moderation_records,
+            policy_precheck_thresholds: v2.policy_precheck_thresholds,
+            policy_mention_rules: v2.policy_mention_rules,
+            policy_license_rules: v2.policy_license_rules,
+            policy_moderation_tiers: v2.policy_moderation_tiers,
+            redemption_licenses: v2.redemption_licenses,
+            approved_mentions: v2.approved_mentions,
+            approved_quorum_disputes: v2.approved_quorum_disputes,
+            last_processed_blocks: v2
+                .last_processed_block
+                .map(|cursor| (chain_id, cursor))
+                .into_iter()
+                .collect(),
+            processed_events: v2
+                .processed_events
+                .into_iter()
+                .map(|(tx_hash, log_index)| (chain_id, tx_hash, log_index))
+                .collect(),
+            failed_events: v2.failed_events,
+            redemption_links: v2.redemption_links,
+            recovery_emails: v2.recovery_emails,
+            timezones: v2.timezones,
+            creator_webhooks: v2.creator_webhooks,
+            email_challenges: v2.email_challenges,
+            admin_audit_log: v2.admin_audit_log,
+            txs: v2.txs,
+            pending_approvals: v2.pending_approvals,
+            abuse_reports: v2.abuse_reports,
+            creator_strikes: v2.creator_strikes,
+            royalties: v2.royalties,
+            burn_on_redeem: v2.burn_on_redeem,
+            approved_contents: v2.approved_contents,
+            burns: v2.burns,
+            anchors: v2.anchors,
+            top_ups: v2.top_ups,
+            creator_signals: v2.creator_signals,
+            reputation_history: v2.reputation_history,
+            redeem_nonces: v2.redeem_nonces,
+            policy_hashes: v2.policy_hashes,
+            mint_txs: v2.mint_txs,
+            ledger: v2.ledger,
+            mint_payments: v2.mint_payments,
+            relay_cursors: v2.relay_cursors,
+            relayed: v2.relayed,
+            access_list: v2.access_list,
+            inbox: v2.inbox,
+            contract_statuses: v2.contract_statuses,
+            kill_switches: v2.kill_switches,
+            deposit_cursors: v2.deposit_cursors,
+            token_metadata: v2.token_metadata,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        snapshot::{Image, UNVERSIONED},
+        TeleportDB,
+    };
+
+    #[test]
+    fn v0_image_migrates() -> eyre::Result<()> {
+        let v0 = V0 {
+            users: BTreeMap::from([(
+                "0x1".to_string(),
+                V0User {
+                    x_id: Some("1".to_string()),
+                    access_tokens: None,
+                    oauth_tokens: AccessTokens::default(),
+                },
+            )]),
+            x_id_to_address: BTreeMap::from([("1".to_string(), "0x1".to_string())]),
+            nfts: BTreeMap::from([(
+                "nft".to_string(),
+                V0NFT { address: "0x1".to_string(), token_id: "7".to_string() },
+            )]),
+            tweets: BTreeMap::from([("7".to_string(), "100".to_string())]),
+            ..Default::default()
+        };
+        let image = Image { version: UNVERSIONED, bytes: bincode::serialize(&v0)? };
+        let db = InMemoryDB::load(&image)?;
+        assert_eq!(db.get_user_by_x_id("1".to_string())?.x_id, Some("1".to_string()));
+        let nft = db.get_nft_by_token_id(DEFAULT_CHAIN_ID, "7".to_string())?;
+        assert_eq!(nft.address, "0x1");
+        assert_eq!(db.get_tweet(DEFAULT_CHAIN_ID, "7".to_string())?, "100");
+        Ok(())
+    }
+
+    #[test]
+    fn v1_image_migrates() -> eyre::Result<()> {
+        let user = User { x_id: Some("1".to_string()), ..Default::default() };
+        let v1 = V1 {
+            users: BTreeMap::from([("0x1".to_string(), user.clone())]),
+            x_id_to_address: BTreeMap::from([("1".to_string(), "0x1".to_string())]),
+            kill_switches: BTreeMap::from([("tweets".to_string(), true)]),
+            ..Default::default()
+        };
+        let image = Image { version: 1, bytes: bincode::serialize(&v1)? };
+        let db = InMemoryDB::load(&image)?;
+        assert_eq!(db.get_user_by_x_id("1".to_string())?, user);
+        assert!(db.creator_webhooks.is_empty());
+        // Fields after the ones v2 added are read from where v1 wrote them.
+        assert_eq!(db.kill_switches.get("tweets"), Some(&true));
+        Ok(())
+    }
+
+    #[test]
+    fn v2_image_migrates() -> eyre::Result<()> {
+        let cursor = BlockCursor { block_number: 10, log_index: 2 };
+        let v2 = V2 {
+            last_processed_block: Some(cursor),
+            processed_events: BTreeSet::from([("0xabc".to_string(), 1)]),
+            ..Default::default()
+        };
+        let image = Image { version: 2, bytes: bincode::serialize(&v2)? };
+        let db = InMemoryDB::load(&image)?;
+        assert_eq!(db.get_last_processed_block(DEFAULT_CHAIN_ID)?, cursor);
+        assert!(db.is_event_processed(DEFAULT_CHAIN_ID, "0xabc".to_string(), 1)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    ops::{Deref, DerefMut},
+    panic::Location,
+    sync::{Arc, Mutex as StdMutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::metrics;
+
+const DEFAULT_SLOW_HOLD_MS: u64 = 1_000;
+const DEFAULT_SLOW_WAIT_MS: u64 = 1_000;
+const DEFAULT_STALE_LOCK_SECS: u64 = 30;
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(5);
+
+fn env_duration_ms(name: &str, default_ms: u64) -> Duration {
+    let ms = std::env::var(name).ok().and_then(|ms| ms.parse().ok()).unwrap_or(default_ms);
+    Duration::from_millis(ms)
+}
+
+/// Holds longer than this are logged, from `DB_LOCK_SLOW_HOLD_MS`. Read once, since every lock
+/// checks it.
+fn get_slow_hold() -> Duration {
+    static SLOW_HOLD: OnceLock<Duration> = OnceLock::new();
+    *SLOW_HOLD.get_or_init(|| env_duration_ms("DB_LOCK_SLOW_HOLD_MS", DEFAULT_SLOW_HOLD_MS))
+}
+
+/// Waits longer than this are logged, from `DB_LOCK_SLOW_WAIT_MS`.
+fn get_slow_wait() -> Duration {
+    static SLOW_WAIT: OnceLock<Duration> = OnceLock::new();
+    *SLOW_WAIT.get_or_init(|| env_duration_ms("DB_LOCK_SLOW_WAIT_MS", DEFAULT_SLOW_WAIT_MS))
+}
+
+/// A holder this old is reported as a likely deadlock, from `DB_LOCK_STALE_SECS`.
+fn get_stale_after() -> Duration {
+    let secs = std::env::var("DB_LOCK_STALE_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(DEFAULT_STALE_LOCK_SECS);
+    Duration::from_secs(secs)
+}
+
+fn site(caller: &Location<'_>) -> String {
+    format!("{}:{}", caller.file(), caller.line())
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct SiteStats {
+    pub acquisitions: u64,
+    pub total_wait_ms: u64,
+    pub max_wait_ms: u64,
+    pub total_hold_ms: u64,
+    pub max_hold_ms: u64,
+}
+
+#[derive(Default)]
+struct LockState {
+    holder: Option<(&'static Location<'static>, Instant)>,
+    next_waiter: u64,
+    waiters: BTreeMap<u64, (&'static Location<'static>, Instant)>,
+    sites: BTreeMap<String, SiteStats>,
+}
+
+/// A tokio mutex that records who holds it, who waits for it and for how long, per call site.
+pub struct TrackedMutex<T> {
+    inner: tokio::sync::Mutex<T>,
+    state: StdMutex<LockState>,
+}
+
+pub struct TrackedGuard<'a, T> {
+    guard: tokio::sync::MutexGuard<'a, T>,
+    mutex: &'a TrackedMutex<T>,
+    caller: &'static Location<'static>,
+    acquired_at: Instant,
+}
+
+/// Removes a waiter whose lock future was dropped before it got the lock.
+struct Waiting<'a, T> {
+    mutex: &'a TrackedMutex<T>,
+    id: u64,
+}
+
+impl<T> Drop for Waiting<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.state.lock().unwrap().waiters.remove(&self.id);
+    }
+}
+
+impl<T> TrackedMutex<T> {
+    pub fn new(value: T) -> Self {
+        Self { inner: tokio::sync::Mutex::new(value), state: Default::default() }
+    }
+
+    /// Locks like `tokio::sync::Mutex::lock`, attributing the wait and hold to the caller.
+    #[track_caller]
+    pub fn lock(&self) -> impl Future<Output = TrackedGuard<'_, T>> {
+        let caller = Location::caller();
+        async move {
+            let requested_at = Instant::now();
+            let waiting = {
+                let mut state = self.state.lock().unwrap();
+                let id = state.next_waiter;
+                state.next_waiter += 1;
+                state.waiters.insert(id, (caller, requested_at));
+                Waiting { mutex: self, id }
+            };
+            let guard = self.inner.lock().await;
+            drop(waiting);
+
+            let acquired_at = Instant::now();
+            let wait = acquired_at - requested_at;
+            if wait > get_slow_wait() {
+                log::warn!("Waited {:?} for the DB lock at {}", wait, site(caller));
+            }
+            let mut state = self.state.lock().unwrap();
+            state.holder = Some((caller, acquired_at));
+            let stats = state.sites.entry(site(caller)).or_default();
+            stats.acquisitions += 1;
+            stats.total_wait_ms += wait.as_millis() as u64;
+            stats.max_wait_ms = stats.max_wait_ms.max(wait.as_millis() as u64);
+            drop(state);
+            TrackedGuard { guard, mutex: self, caller, acquired_at }
+        }
+    }
+
+    /// The current holder and waiters, and per call site totals.
+    pub fn report(&self) -> LockReport {
+        let now = Instant::now();
+        let state = self.state.lock().unwrap();
+        LockReport {
+            holder: state.holder.map(|(caller, since)| LockUser {
+                site: site(caller),
+                for_ms: (now - since).as_millis() as u64,
+            }),
+            waiters: state
+                .waiters
+                .values()
+                .map(|(caller, since)| LockUser {
+                    site: site(caller),
+                    for_ms: (now - *since).as_millis() as u64,
+                })
+                .collect(),
+            sites: state.sites.clone(),
+        }
+    }
+}
+
+impl<T> Deref for TrackedGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for TrackedGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for TrackedGuard<'_, T> {
+    fn drop(&mut self) {
+        let hold = self.acquired_at.elapsed();
+        let site = site(self.caller);
+        if hold > get_slow_hold() {
+            log::warn!("Held the DB lock for {:?} at {}", hold, site);
+            metrics::increment("db_lock_slow_holds_total", &[("site", &site)]);
+        }
+        let mut state = self.mutex.state.lock().unwrap();
+        state.holder = None;
+        let stats = state.sites.entry(site).or_default();
+        stats.total_hold_ms += hold.as_millis() as u64;
+        stats.max_hold_ms = stats.max_hold_ms.max(hold.as_millis() as u64);
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LockUser {
+    pub site: String,
+    pub for_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LockReport {
+    pub holder: Option<LockUser>,
+    pub waiters: Vec<LockUser>,
+    pub sites: BTreeMap<String, SiteStats>,
+}
+
+/// Logs the holder of `mutex` whenever it has held the lock past `DB_LOCK_STALE_SECS`, since a
+/// lock held that long is most likely never released.
+pub async fn run_lock_watchdog<T>(mutex: Arc<TrackedMutex<T>>) {
+    let stale_after = get_stale_after();
+    loop {
+        tokio::time::sleep(WATCHDOG_INTERVAL).await;
+        let report = mutex.report();
+        let Some(holder) = report.holder else {
+            continue;
+        };
+        if Duration::from_millis(holder.for_ms) > stale_after {
+            log::error!(
+                "DB lock held for {}ms at {} with {} waiters, possible deadlock",
+                holder.for_ms,
+                holder.site,
+                report.waiters.len()
+            );
+            metrics::increment("db_lock_stale_total", &[("site", &holder.site)]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn report_attributes_the_holder_and_waiters() {
+        let mutex = Arc::new(TrackedMutex::new(0));
+        let guard = mutex.lock().await;
+        let waiter = tokio::spawn({
+            let mutex = mutex.clone();
+            async move { *mutex.lock().await += 1 }
+        });
+        tokio::task::yield_now().await;
+
+        let report = mutex.report();
+        assert!(report.holder.is_some_and(|holder| holder.site.starts_with(file!())));
+        assert_eq!(report.waiters.len(), 1);
+
+        drop(guard);
+        waiter.await.unwrap();
+        let report = mutex.report();
+        assert!(report.holder.is_none() && report.waiters.is_empty());
+        assert_eq!(report.sites.values().map(|stats| stats.acquisitions).sum::<u64>(), 2);
+    }
+}
@@ -0,0 +1,370 @@
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use serde::Serialize;
+
+#[cfg(feature = "postgres")]
+use super::client_db::ClientDB;
+use super::storage::Redemption;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatorDailyStats {
+    pub day: String,
+    pub mints: i64,
+    pub redemptions: i64,
+}
+
+/// Who the marketplace lists a token under.
+#[derive(Debug, Clone)]
+pub struct TokenOwner {
+    pub user_id: String,
+    pub twitter_user_name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct RedemptionSample {
+    pub chain_id: i64,
+    pub token_id: i32,
+    pub content: String,
+    pub safeguard: String,
+}
+
+/// The marketplace's token and redemption index, which the indexer keeps in step with the chain.
+/// Deployments that only post redemptions to X run without one.
+pub trait MarketplaceIndex: Send + Sync {
+    fn set_token_id(
+        &self,
+        chain_id: u64,
+        token_id: String,
+        nft_id: String,
+    ) -> BoxFuture<'_, eyre::Result<()>>;
+    fn update_token_owner(
+        &self,
+        chain_id: u64,
+        token_id: String,
+        owner: String,
+    ) -> BoxFuture<'_, eyre::Result<()>>;
+    fn delete_token(&self, chain_id: u64, token_id: String) -> BoxFuture<'_, eyre::Result<()>>;
+    /// Lists a redemption under the token's owner with the tweet that fulfilled it, counts it
+    /// against them and delists the token if the redemption says so, all or nothing.
+    fn add_redemption(
+        &self,
+        chain_id: u64,
+        redemption: Redemption,
+    ) -> BoxFuture<'_, eyre::Result<()>>;
+    fn set_redemption_tweet_id(
+        &self,
+        chain_id: u64,
+        token_id: String,
+        tweet_id: String,
+    ) -> BoxFuture<'_, eyre::Result<()>>;
+    /// Moves a burned token's redemption out of the listed redemptions into the archive.
+    fn archive_redemption(
+        &self,
+        chain_id: u64,
+        token_id: String,
+        revoked: bool,
+    ) -> BoxFuture<'_, eyre::Result<()>>;
+    /// Random sample of redemptions whose content has not been purged.
+    fn sample_redemptions(&self, limit: i64) -> BoxFuture<'_, eyre::Result<Vec<RedemptionSample>>>;
+    /// A redemption's content and policy, unless it was never indexed or has been purged.
+    fn get_redemption(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> BoxFuture<'_, eyre::Result<Option<RedemptionSample>>>;
+    /// The policy a redemption was made under, unless it was never indexed. Finds redemptions
+    /// whose content is empty or purged, so it tells whether one was recorded at all.
+    fn find_redemption_policy(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> BoxFuture<'_, eyre::Result<Option<String>>>;
+    /// Who a token is listed under, unless it was never indexed or has been delisted.
+    fn find_token_owner(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> BoxFuture<'_, eyre::Result<Option<TokenOwner>>>;
+    /// A creator's daily aggregates between two inclusive `YYYY-MM-DD` UTC days.
+    fn get_creator_daily_stats(
+        &self,
+        creator_user_id: String,
+        from: String,
+        to: String,
+    ) -> BoxFuture<'_, eyre::Result<Vec<CreatorDailyStats>>>;
+    /// Recomputes the daily aggregates of a `YYYY-MM-DD` UTC day, returning how many creators had
+    /// any.
+    fn roll_up_creator_stats(&self, day: String) -> BoxFuture<'_, eyre::Result<u64>>;
+}
+
+/// Stands in for the marketplace index when there is none, so every update is a no-op.
+pub struct NoMarketplaceIndex;
+
+impl MarketplaceIndex for NoMarketplaceIndex {
+    fn set_token_id(&self, _: u64, _: String, _: String) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn update_token_owner(&self, _: u64, _: String, _: String) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn delete_token(&self, _: u64, _: String) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn add_redemption(&self, _: u64, _: Redemption) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn set_redemption_tweet_id(
+        &self,
+        _: u64,
+        _: String,
+        _: String,
+    ) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn archive_redemption(&self, _: u64, _: String, _: bool) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn sample_redemptions(&self, _: i64) -> BoxFuture<'_, eyre::Result<Vec<RedemptionSample>>> {
+        Box::pin(async { Ok(vec![]) })
+    }
+
+    fn get_redemption(
+        &self,
+        _: u64,
+        _: String,
+    ) -> BoxFuture<'_, eyre::Result<Option<RedemptionSample>>> {
+        Box::pin(async { Ok(None) })
+    }
+
+    fn find_redemption_policy(
+        &self,
+        _: u64,
+        _: String,
+    ) -> BoxFuture<'_, eyre::Result<Option<String>>> {
+        Box::pin(async { Ok(None) })
+    }
+
+    fn find_token_owner(
+        &self,
+        _: u64,
+        _: String,
+    ) -> BoxFuture<'_, eyre::Result<Option<TokenOwner>>> {
+        Box::pin(async { Ok(None) })
+    }
+
+    fn get_creator_daily_stats(
+        &self,
+        _: String,
+        _: String,
+        _: String,
+    ) -> BoxFuture<'_, eyre::Result<Vec<CreatorDailyStats>>> {
+        Box::pin(async { Ok(vec![]) })
+    }
+
+    fn roll_up_creator_stats(&self, _: String) -> BoxFuture<'_, eyre::Result<u64>> {
+        Box::pin(async { Ok(0) })
+    }
+}
+
+/// The marketplace index as a read-only instance sees it. Reads go through, but updates are
+/// dropped: the full instance sharing the database makes them, with the tweet ids this one never
+/// has.
+pub struct ReadOnlyMarketplaceIndex(pub Arc<dyn MarketplaceIndex>);
+
+impl MarketplaceIndex for ReadOnlyMarketplaceIndex {
+    fn set_token_id(&self, _: u64, _: String, _: String) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn update_token_owner(&self, _: u64, _: String, _: String) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn delete_token(&self, _: u64, _: String) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn add_redemption(&self, _: u64, _: Redemption) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn set_redemption_tweet_id(
+        &self,
+        _: u64,
+        _: String,
+        _: String,
+    ) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn archive_redemption(&self, _: u64, _: String, _: bool) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn sample_redemptions(&self, limit: i64) -> BoxFuture<'_, eyre::Result<Vec<RedemptionSample>>> {
+        self.0.sample_redemptions(limit)
+    }
+
+    fn get_redemption(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> BoxFuture<'_, eyre::Result<Option<RedemptionSample>>> {
+        self.0.get_redemption(chain_id, token_id)
+    }
+
+    fn find_redemption_policy(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> BoxFuture<'_, eyre::Result<Option<String>>> {
+        self.0.find_redemption_policy(chain_id, token_id)
+    }
+
+    fn find_token_owner(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> BoxFuture<'_, eyre::Result<Option<TokenOwner>>> {
+        self.0.find_token_owner(chain_id, token_id)
+    }
+
+    fn get_creator_daily_stats(
+        &self,
+        creator_user_id: String,
+        from: String,
+        to: String,
+    ) -> BoxFuture<'_, eyre::Result<Vec<CreatorDailyStats>>> {
+        self.0.get_creator_daily_stats(creator_user_id, from, to)
+    }
+
+    fn roll_up_creator_stats(&self, _: String) -> BoxFuture<'_, eyre::Result<u64>> {
+        Box::pin(async { Ok(0) })
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl MarketplaceIndex for ClientDB {
+    fn set_token_id(
+        &self,
+        chain_id: u64,
+        token_id: String,
+        nft_id: String,
+    ) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(ClientDB::set_token_id(self, chain_id, token_id, nft_id))
+    }
+
+    fn update_token_owner(
+        &self,
+        chain_id: u64,
+        token_id: String,
+        owner: String,
+    ) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(ClientDB::update_token_owner(self, chain_id, token_id, owner))
+    }
+
+    fn delete_token(&self, chain_id: u64, token_id: String) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(ClientDB::delete_token(self, chain_id, token_id))
+    }
+
+    fn add_redemption(
+        &self,
+        chain_id: u64,
+        redemption: Redemption,
+    ) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(ClientDB::record_redemption(self, chain_id, redemption))
+    }
+
+    fn set_redemption_tweet_id(
+        &self,
+        chain_id: u64,
+        token_id: String,
+        tweet_id: String,
+    ) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(ClientDB::set_redemption_tweet_id(self, chain_id, token_id, tweet_id))
+    }
+
+    fn archive_redemption(
+        &self,
+        chain_id: u64,
+        token_id: String,
+        revoked: bool,
+    ) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(ClientDB::archive_redemption(self, chain_id, token_id, revoked))
+    }
+
+    fn sample_redemptions(&self, limit: i64) -> BoxFuture<'_, eyre::Result<Vec<RedemptionSample>>> {
+        Box::pin(ClientDB::sample_redemptions(self, limit))
+    }
+
+    fn get_redemption(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> BoxFuture<'_, eyre::Result<Option<RedemptionSample>>> {
+        Box::pin(ClientDB::get_redemption(self, chain_id, token_id))
+    }
+
+    fn find_redemption_policy(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> BoxFuture<'_, eyre::Result<Option<String>>> {
+        Box::pin(ClientDB::find_redemption_policy(self, chain_id, token_id))
+    }
+
+    fn find_token_owner(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> BoxFuture<'_, eyre::Result<Option<TokenOwner>>> {
+        Box::pin(ClientDB::find_token_owner(self, chain_id, token_id))
+    }
+
+    fn get_creator_daily_stats(
+        &self,
+        creator_user_id: String,
+        from: String,
+        to: String,
+    ) -> BoxFuture<'_, eyre::Result<Vec<CreatorDailyStats>>> {
+        Box::pin(ClientDB::get_creator_daily_stats(self, creator_user_id, from, to))
+    }
+
+    fn roll_up_creator_stats(&self, day: String) -> BoxFuture<'_, eyre::Result<u64>> {
+        Box::pin(ClientDB::roll_up_creator_stats(self, day))
+    }
+}
+
+/// `DATABASE_URL`, the legacy Postgres database, when this deployment has one.
+pub fn get_database_url() -> Option<String> {
+    std::env::var("DATABASE_URL").ok().filter(|url| !url.is_empty())
+}
+
+/// The Postgres index created at startup, or none when `DATABASE_URL` is unset.
+#[cfg(feature = "postgres")]
+pub fn marketplace_index(client_db: Option<ClientDB>) -> Arc<dyn MarketplaceIndex> {
+    match client_db {
+        Some(client_db) => Arc::new(client_db),
+        None => {
+            log::info!("DATABASE_URL is not set, running without the marketplace index");
+            Arc::new(NoMarketplaceIndex)
+        }
+    }
+}
+
+/// No index, since Postgres support is compiled out; a `DATABASE_URL` would be ignored.
+#[cfg(not(feature = "postgres"))]
+pub fn marketplace_index_from_env() -> eyre::Result<Arc<dyn MarketplaceIndex>> {
+    if get_database_url().is_some() {
+        eyre::bail!("DATABASE_URL is set but the postgres feature is disabled");
+    }
+    log::info!("DATABASE_URL is not set, running without the marketplace index");
+    Ok(Arc::new(NoMarketplaceIndex))
+}
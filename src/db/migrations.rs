@@ -0,0 +1,388 @@
+use super::client_db::ClientDB;
+
+/// Held for the length of a migration run, so instances starting together do not race.
+const MIGRATION_LOCK_ID: i64 = 0x7465_6c65_706f_7274;
+
+const VERSIONS_TABLE: &str = "
+    CREATE SCHEMA IF NOT EXISTS teleport;
+    CREATE TABLE IF NOT EXISTS teleport.schema_migrations (
+        version INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+    );
+";
+
+const APPLIED_MIGRATIONS: &str =
+    "SELECT version, name FROM teleport.schema_migrations ORDER BY version";
+
+/// One schema change of the marketplace index. `up` only uses `IF NOT EXISTS` forms, so
+/// databases that had it applied by hand adopt it without error.
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    up: &'static str,
+    /// `None` for migrations that cannot be undone without losing data the enclave does not own.
+    down: Option<&'static str>,
+}
+
+/// Every migration, oldest first. Never edit one that has shipped; add another instead.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "legacy_tables",
+        // The frontend owns these tables and their other columns; these are the ones the enclave
+        // reads and writes, so a fresh database, such as a devnet's, can be indexed at all.
+        up: "
+            CREATE TABLE IF NOT EXISTS \"User\" (
+                \"id\" TEXT PRIMARY KEY,
+                \"haveBeenRedeemed\" INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE IF NOT EXISTS \"NftIndex\" (
+                \"id\" TEXT PRIMARY KEY,
+                \"userId\" TEXT,
+                \"twitterUserName\" TEXT,
+                \"tokenId\" INTEGER,
+                \"chainId\" BIGINT
+            );
+            CREATE TABLE IF NOT EXISTS \"RedeemedIndex\" (
+                \"id\" TEXT PRIMARY KEY,
+                \"creatorUserId\" TEXT NOT NULL,
+                \"tokenId\" INTEGER NOT NULL,
+                \"tweetId\" TEXT,
+                \"twitterUserName\" TEXT NOT NULL,
+                \"safeguard\" TEXT NOT NULL,
+                \"content\" TEXT NOT NULL,
+                \"chainId\" BIGINT NOT NULL
+            );
+        ",
+        down: None,
+    },
+    Migration {
+        version: 2,
+        name: "internal_schema",
+        up: "
+            CREATE TABLE IF NOT EXISTS teleport.tokens (
+                chain_id BIGINT NOT NULL,
+                token_id INTEGER NOT NULL,
+                nft_id TEXT NOT NULL,
+                user_id TEXT,
+                twitter_user_name TEXT,
+                PRIMARY KEY (chain_id, token_id)
+            );
+            CREATE TABLE IF NOT EXISTS teleport.redemptions (
+                id TEXT PRIMARY KEY,
+                chain_id BIGINT NOT NULL,
+                token_id INTEGER NOT NULL,
+                creator_user_id TEXT NOT NULL,
+                twitter_user_name TEXT NOT NULL,
+                safeguard TEXT NOT NULL,
+                content TEXT NOT NULL,
+                tweet_id TEXT NOT NULL DEFAULT '',
+                redeemed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+            );
+            CREATE TABLE IF NOT EXISTS teleport.mints (
+                chain_id BIGINT NOT NULL,
+                token_id INTEGER NOT NULL,
+                creator_user_id TEXT NOT NULL,
+                minted_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+                PRIMARY KEY (chain_id, token_id)
+            );
+        ",
+        down: Some(
+            "
+            DROP TABLE teleport.mints;
+            DROP TABLE teleport.redemptions;
+            DROP TABLE teleport.tokens;
+        ",
+        ),
+    },
+    Migration {
+        version: 3,
+        name: "content_purges",
+        up: "
+            ALTER TABLE teleport.redemptions
+                ADD COLUMN IF NOT EXISTS content_hash TEXT,
+                ADD COLUMN IF NOT EXISTS content_purged_at TIMESTAMPTZ;
+            CREATE TABLE IF NOT EXISTS teleport.content_purges (
+                id BIGSERIAL PRIMARY KEY,
+                purged_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+                chain_id BIGINT NOT NULL,
+                retention_days INTEGER NOT NULL,
+                redemptions BIGINT NOT NULL
+            );
+        ",
+        down: Some(
+            "
+            DROP TABLE teleport.content_purges;
+            ALTER TABLE teleport.redemptions
+                DROP COLUMN content_hash,
+                DROP COLUMN content_purged_at;
+        ",
+        ),
+    },
+    Migration {
+        version: 4,
+        name: "creator_daily_stats",
+        up: "
+            CREATE TABLE IF NOT EXISTS teleport.creator_daily_stats (
+                day DATE NOT NULL,
+                creator_user_id TEXT NOT NULL,
+                mints BIGINT NOT NULL,
+                redemptions BIGINT NOT NULL,
+                PRIMARY KEY (day, creator_user_id)
+            );
+        ",
+        down: Some("DROP TABLE teleport.creator_daily_stats;"),
+    },
+    Migration {
+        version: 5,
+        name: "archived_redemptions",
+        up: "
+            CREATE TABLE IF NOT EXISTS teleport.archived_redemptions (
+                id TEXT PRIMARY KEY,
+                chain_id BIGINT NOT NULL,
+                token_id INTEGER NOT NULL,
+                creator_user_id TEXT NOT NULL,
+                twitter_user_name TEXT NOT NULL,
+                safeguard TEXT NOT NULL,
+                content TEXT NOT NULL,
+                tweet_id TEXT NOT NULL,
+                revoked BOOLEAN NOT NULL,
+                archived_at TIMESTAMPTZ NOT NULL DEFAULT now()
+            );
+        ",
+        down: Some("DROP TABLE teleport.archived_redemptions;"),
+    },
+    Migration {
+        version: 6,
+        name: "redemption_timestamps",
+        // Redemptions tables created before the daily rollups have no timestamp to roll up by.
+        up: "
+            ALTER TABLE teleport.redemptions
+                ADD COLUMN IF NOT EXISTS redeemed_at TIMESTAMPTZ NOT NULL DEFAULT now();
+        ",
+        // Version 2 creates the column on fresh databases, so rolling back leaves it in place.
+        down: Some(""),
+    },
+];
+
+pub fn latest_version() -> i32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// Checks the recorded migrations are a prefix of [`MIGRATIONS`], returning the current version.
+/// A version this build does not know means a newer build already migrated the database.
+fn current_version(applied: &[(i32, String)]) -> eyre::Result<i32> {
+    for (i, (version, name)) in applied.iter().enumerate() {
+        let migration = MIGRATIONS.get(i).ok_or_else(|| {
+            eyre::eyre!(
+                "Database is at schema version {}, newer than this build's {}; deploy the newer \
+                 build, or roll back with its `teleport migrate down {}`",
+                version,
+                latest_version(),
+                latest_version()
+            )
+        })?;
+        if migration.version != *version || migration.name != name.as_str() {
+            eyre::bail!(
+                "Recorded migration {} {} does not match this build's {} {}; the database was \
+                 migrated by a different branch",
+                version,
+                name,
+                migration.version,
+                migration.name
+            );
+        }
+    }
+    Ok(applied.last().map_or(0, |(version, _)| *version))
+}
+
+/// Fails unless a database at `current` is at exactly this build's schema, for instances that
+/// may not migrate it themselves.
+fn ensure_latest(current: i32) -> eyre::Result<()> {
+    if current < latest_version() {
+        eyre::bail!(
+            "Database is at schema version {}, this build needs {}; start the full instance or \
+             run `teleport migrate up` first",
+            current,
+            latest_version()
+        );
+    }
+    Ok(())
+}
+
+/// The migrations to apply, oldest first, to bring `current` up to `target`.
+fn to_apply(current: i32, target: i32) -> eyre::Result<Vec<&'static Migration>> {
+    if target > latest_version() {
+        eyre::bail!("No schema version {}, the latest is {}", target, latest_version());
+    }
+    Ok(MIGRATIONS.iter().filter(|m| m.version > current && m.version <= target).collect())
+}
+
+/// The migrations to undo, newest first, to bring `current` down to `target`.
+fn to_roll_back(current: i32, target: i32) -> eyre::Result<Vec<&'static Migration>> {
+    let migrations: Vec<_> =
+        MIGRATIONS.iter().rev().filter(|m| m.version > target && m.version <= current).collect();
+    if let Some(migration) = migrations.iter().find(|m| m.down.is_none()) {
+        eyre::bail!("Migration {} {} cannot be rolled back", migration.version, migration.name);
+    }
+    Ok(migrations)
+}
+
+enum Direction {
+    Up,
+    Down,
+}
+
+/// Migrates the schema to `target` in one transaction, so a failing migration leaves the
+/// database as it was. Returns the version it was at before.
+async fn run(client_db: &ClientDB, direction: Direction, target: i32) -> eyre::Result<i32> {
+    let mut client = client_db.client().await?;
+    let transaction = client.transaction().await?;
+    transaction.execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK_ID]).await?;
+    transaction.batch_execute(VERSIONS_TABLE).await?;
+    let applied: Vec<(i32, String)> = transaction
+        .query(APPLIED_MIGRATIONS, &[])
+        .await?
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect();
+    let current = current_version(&applied)?;
+    match direction {
+        Direction::Up => {
+            for migration in to_apply(current, target)? {
+                log::info!("Applying migration {} {}", migration.version, migration.name);
+                transaction.batch_execute(migration.up).await?;
+                transaction
+                    .execute(
+                        "INSERT INTO teleport.schema_migrations (version, name) VALUES ($1, $2)",
+                        &[&migration.version, &migration.name],
+                    )
+                    .await?;
+            }
+        }
+        Direction::Down => {
+            for migration in to_roll_back(current, target)? {
+                log::info!("Rolling back migration {} {}", migration.version, migration.name);
+                transaction.batch_execute(migration.down.unwrap_or_default()).await?;
+                transaction
+                    .execute(
+                        "DELETE FROM teleport.schema_migrations WHERE version = $1",
+                        &[&migration.version],
+                    )
+                    .await?;
+            }
+        }
+    }
+    transaction.commit().await?;
+    Ok(current)
+}
+
+/// Applies every migration this build has that the database does not, as at startup.
+pub async fn migrate(client_db: &ClientDB) -> eyre::Result<()> {
+    let target = latest_version();
+    let previous = run(client_db, Direction::Up, target).await?;
+    if previous != target {
+        log::info!("Migrated the marketplace index from schema version {} to {}", previous, target);
+    }
+    Ok(())
+}
+
+/// Checks the database is at this build's schema version without migrating it, for a read-only
+/// instance, which leaves that to the full one. Run at startup, so a mismatch stops the instance
+/// before its first query against a missing column.
+pub async fn check(client_db: &ClientDB) -> eyre::Result<()> {
+    let client = client_db.client().await?;
+    let recorded: bool = client
+        .query_one("SELECT to_regclass('teleport.schema_migrations') IS NOT NULL", &[])
+        .await?
+        .get(0);
+    let applied: Vec<(i32, String)> = if recorded {
+        client
+            .query(APPLIED_MIGRATIONS, &[])
+            .await?
+            .iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    ensure_latest(current_version(&applied)?)
+}
+
+/// `teleport migrate [up [<version>] | down <version> | status]`, for applying or undoing
+/// migrations by hand. `up` defaults to the latest version.
+pub async fn run_cli(client_db: &ClientDB, args: &[String]) -> eyre::Result<()> {
+    let version = |arg: Option<&String>| -> eyre::Result<Option<i32>> {
+        arg.map(|version| version.parse().map_err(|_| eyre::eyre!("Bad version {}", version)))
+            .transpose()
+    };
+    match args.first().map(String::as_str) {
+        None | Some("up") => {
+            let target = version(args.get(1))?.unwrap_or_else(latest_version);
+            let previous = run(client_db, Direction::Up, target).await?;
+            println!("Schema version {} -> {}", previous, target.max(previous));
+        }
+        Some("down") => {
+            let target =
+                version(args.get(1))?.ok_or_else(|| eyre::eyre!("down needs a version"))?;
+            let previous = run(client_db, Direction::Down, target).await?;
+            println!("Schema version {} -> {}", previous, target.min(previous));
+        }
+        Some("status") => {
+            let previous = run(client_db, Direction::Up, 0).await?;
+            println!("Schema version {}, latest {}", previous, latest_version());
+        }
+        Some(command) => eyre::bail!("Unknown migrate command {}", command),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrations_are_numbered_in_order() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, i as i32 + 1);
+        }
+    }
+
+    #[test]
+    fn plans_migrations_between_versions() -> eyre::Result<()> {
+        let versions = |migrations: Vec<&Migration>| -> Vec<i32> {
+            migrations.iter().map(|migration| migration.version).collect()
+        };
+        assert_eq!(versions(to_apply(0, latest_version())?), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(versions(to_apply(3, 4)?), [4]);
+        assert!(to_apply(5, 5)?.is_empty());
+        assert!(to_apply(0, latest_version() + 1).is_err());
+
+        assert_eq!(versions(to_roll_back(5, 2)?), [5, 4, 3]);
+        assert!(to_roll_back(2, 2)?.is_empty());
+        // The legacy tables are the frontend's to drop.
+        assert!(to_roll_back(5, 0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_migrations_this_build_does_not_know() {
+        let applied = |names: &[&str]| -> Vec<(i32, String)> {
+            names.iter().enumerate().map(|(i, name)| (i as i32 + 1, name.to_string())).collect()
+        };
+        assert_eq!(current_version(&[]).unwrap(), 0);
+        assert_eq!(current_version(&applied(&["legacy_tables", "internal_schema"])).unwrap(), 2);
+        assert!(current_version(&applied(&["legacy_tables", "renamed"])).is_err());
+        let mut newer: Vec<&str> = MIGRATIONS.iter().map(|migration| migration.name).collect();
+        newer.push("from_the_future");
+        assert!(current_version(&applied(&newer)).is_err());
+    }
+
+    #[test]
+    fn read_only_instances_need_the_latest_schema() {
+        assert!(ensure_latest(latest_version()).is_ok());
+        assert!(ensure_latest(latest_version() - 1).is_err());
+        assert!(ensure_latest(0).is_err());
+    }
+}
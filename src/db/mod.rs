@@ -1,10 +1,29 @@
+use std::collections::BTreeMap;
+
+use alloy::primitives::Address;
 use rusqlite_from_row::FromRow;
 use serde::{Deserialize, Serialize};
 
 use crate::twitter::auth::TwitterTokenPair;
+pub mod backup;
+#[cfg(feature = "postgres")]
 pub mod client_db;
+#[cfg(feature = "postgres")]
+pub mod dual_write;
 pub mod in_memory;
-// pub mod sqlite;
+mod layouts;
+pub mod lock;
+pub mod marketplace;
+#[cfg(feature = "postgres")]
+pub mod migrations;
+#[cfg(feature = "postgres")]
+pub mod retention;
+#[cfg(feature = "postgres")]
+pub mod rollup;
+pub mod sealed;
+pub mod snapshot;
+pub mod sqlite;
+pub mod storage;
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
 pub struct AccessTokens {
@@ -29,18 +48,41 @@ pub struct User {
     pub x_id: Option<String>,
     pub access_tokens: Option<AccessTokens>,
     pub oauth_tokens: AccessTokens,
+    /// The ENS name the user registered with, which resolved to their address.
+    pub ens_name: Option<String>,
+    /// Whether the user turned off DMs about tokens they receive.
+    #[serde(default)]
+    pub dm_opt_out: bool,
+    /// The X account the user last unlinked, whose preferences move to the next one they link.
+    #[serde(default)]
+    pub unlinked_x_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, FromRow, PartialEq, Eq)]
 pub struct NFT {
     pub address: String,
     pub token_id: String,
+    pub chain_id: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, FromRow, PartialEq, Eq)]
 pub struct PendingNFT {
     pub address: String,
     pub nft_id: String,
+    pub chain_id: u64,
+    /// Hash of the policy the token was minted with, checked against its redeem event.
+    pub policy_hash: String,
+}
+
+/// A mint whose `NewTokenData` the indexer has seen, waiting for its transaction to be buried
+/// under `MINT_CONFIRMATIONS` blocks before the pending NFT is promoted.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct MintConfirmation {
+    pub nft_id: String,
+    pub chain_id: u64,
+    pub token_id: String,
+    /// As of the promoter's last check; zero while the transaction is not in the chain.
+    pub confirmations: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, FromRow, PartialEq, Eq)]
@@ -49,18 +91,863 @@ pub struct Session {
     pub address: String,
 }
 
+/// The handles a policy's redemptions may @mention, each pinned to the account id it resolved to
+/// when the policy was minted with them.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct MentionRule {
+    /// Lowercased handle to X user id.
+    pub allowed: BTreeMap<String, String>,
+}
+
+/// What others may do with a redemption's posted copy.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentReuse {
+    AllRightsReserved,
+    /// CC BY 4.0.
+    Attribution,
+    /// CC BY-NC 4.0.
+    NonCommercial,
+    /// CC0 1.0.
+    PublicDomain,
+}
+
+/// The license a redemption declares for the copy it posts.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ContentLicense {
+    /// Who owns the posted copy, such as the brand that commissioned it.
+    pub owner: String,
+    pub reuse: ContentReuse,
+}
+
+/// The licenses a policy's redemptions may declare.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct LicenseRule {
+    /// Reuse terms allowed; empty allows any.
+    #[serde(default)]
+    pub allowed: Vec<ContentReuse>,
+    /// Whether redemptions must declare a license at all.
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// How many moderators must judge a policy's redemptions safe before they are posted.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationTier {
+    #[default]
+    Standard,
+    /// For high-value brand accounts: both moderators of `MODERATION_QUORUM` must agree, and
+    /// redemptions they disagree on are held for manual review.
+    HighAssurance,
+}
+
+/// A policy as it stood when a token was minted under it. Redemptions of the token are judged by
+/// the snapshot, so the creator changing the policy's settings later cannot change the rules its
+/// buyers paid for.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PolicySnapshot {
+    pub policy: String,
+    pub policy_hash: String,
+    /// The policy's own threshold; `None` leaves it to `EMBEDDING_PRECHECK_THRESHOLD`.
+    pub precheck_threshold: Option<f32>,
+    pub mention_rule: Option<MentionRule>,
+    pub prompt_version: String,
+    pub pinned_at: i64,
+    #[serde(default)]
+    pub license_rule: Option<LicenseRule>,
+    #[serde(default)]
+    pub moderation_tier: ModerationTier,
+}
+
+/// Content a creator pre-approved for redemptions under one of their policies, such as a fixed
+/// campaign tweet. It was moderated once when approved, so matching redemptions skip moderation.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ApprovedContent {
+    /// The prompt version that judged the content when it was approved.
+    pub prompt_version: String,
+    pub approved_at: i64,
+}
+
+/// Which moderation prompt version judged a redemption, keyed by chain and token id.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ModerationRecord {
+    pub prompt_version: String,
+    pub safe: bool,
+}
+
+/// A moderated redemption as the service database has it, without the marketplace's copy.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RedemptionRecord {
+    pub token_id: String,
+    pub prompt_version: String,
+    pub safe: bool,
+    /// Unless nothing was posted yet.
+    pub tweet_id: Option<String>,
+}
+
+/// Position of the last NFT contract log the indexer finished handling.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BlockCursor {
+    pub block_number: u64,
+    pub log_index: u64,
+}
+
+impl BlockCursor {
+    /// A cursor past every log in `block_number`.
+    pub fn end_of_block(block_number: u64) -> Self {
+        Self { block_number, log_index: u64::MAX }
+    }
+}
+
+/// A contract log whose handler failed, waiting in the retry queue.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct FailedEvent {
+    pub chain_id: u64,
+    /// The log, JSON encoded.
+    pub log: String,
+    pub attempts: u32,
+    /// Unix timestamp of the next retry, `None` once the event is dead-lettered.
+    pub next_attempt_at: Option<i64>,
+    pub last_error: String,
+}
+
+/// A single-use link a holder hands to someone else to fill in the content of a redemption.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RedemptionLink {
+    pub nft_id: String,
+    pub session_id: String,
+    pub expires_at: i64,
+    pub used: bool,
+}
+
+/// A verified email a user can recover their account with, keyed by x_id.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RecoveryEmail {
+    pub address: String,
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum EmailPurpose {
+    /// Confirms an email captured at onboarding.
+    Verify,
+    /// Re-binds `address` to a new session.
+    Recover,
+}
+
+/// An emailed confirmation code awaiting entry, keyed by x_id.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct EmailChallenge {
+    pub purpose: EmailPurpose,
+    pub address: String,
+    pub email: String,
+    pub code: String,
+    pub expires_at: i64,
+    pub attempts: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TxStatus {
+    Pending,
+    Confirmed,
+    /// Mined but reverted.
+    Failed,
+    /// Superseded by a fee-bumped transaction with the same nonce.
+    Replaced,
+    /// Superseded by a zero-value transfer with the same nonce, at its requester's request.
+    Cancelled,
+}
+
+/// Counts for one chain's collection, safe to show publicly.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct CollectionStats {
+    pub minted: u64,
+    pub pending_mints: u64,
+    pub redeemed: u64,
+    pub rejected_redemptions: u64,
+    pub tweets: u64,
+}
+
+impl CollectionStats {
+    /// Counts a redemption's moderation decision in, or back out once it is replaced.
+    fn count_decision(&mut self, safe: bool, counted: bool) {
+        let count = if safe { &mut self.redeemed } else { &mut self.rejected_redemptions };
+        *count = if counted { *count + 1 } else { count.saturating_sub(1) };
+    }
+}
+
+/// A transaction submitted by the minter wallet, keyed by hash.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TxRecord {
+    pub chain_id: u64,
+    /// `mint`, `redeem`, `burn` or `cancel`.
+    pub kind: String,
+    pub status: TxStatus,
+    pub submitted_at: i64,
+    pub block_number: Option<u64>,
+    pub gas_used: Option<u64>,
+    pub replaced_by: Option<String>,
+    /// The address the transaction was sent for, who may cancel it.
+    pub requested_by: Option<String>,
+}
+
+/// An admin action that only runs once a second admin approves it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AdminAction {
+    /// Puts a dead-lettered event back on the retry queue, which can force a tweet out.
+    ReplayDeadLetter { event_id: String },
+    /// Burns a redeemed token and deletes its tweet once the burn is mined.
+    RevokeRedemption { chain_id: u64, token_id: String },
+    /// Lets the approving admin download the whole database, user keys included.
+    ExportBackup,
+    /// Lets the approving admin restore the backup with this hex SHA-256 digest.
+    RestoreBackup { sha256: String },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct PendingApproval {
+    pub action: AdminAction,
+    pub proposed_by: String,
+    pub expires_at: i64,
+}
+
+/// A report that a published redemption tweet is abusive.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AbuseReport {
+    /// Hash of the reporter's IP address, so each client counts once per tweet.
+    pub reporter: String,
+    pub reason: String,
+    pub at: i64,
+}
+
+/// A burn of a redeemed token that the minter wallet owes, keyed by chain and token id.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct PendingBurn {
+    /// Set when admins revoked the redemption, so its tweet is deleted once the token is burned.
+    pub revocation: bool,
+    pub tx_hash: Option<String>,
+}
+
+/// A posted redemption owed to, or recorded in, the fulfillment registry, keyed by chain and
+/// token id.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ContentAnchor {
+    pub tweet_id: String,
+    /// keccak256 of the redeemed content.
+    pub content_hash: String,
+    /// Set once the anchor is sent, on its own or in a batch.
+    pub tx_hash: Option<String>,
+    /// For batched anchors, the merkle root recorded on-chain and the proof of this leaf under it.
+    #[serde(default)]
+    pub root: Option<String>,
+    #[serde(default)]
+    pub proof: Vec<String>,
+}
+
+/// Something a creator's tokens did that counts against their reputation.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReputationSignal {
+    Rejection,
+    Report,
+    /// Reports that re-moderation upheld.
+    Strike,
+    DeletedTweet,
+    /// An admin cleared the creator's record.
+    Released,
+}
+
+/// Running counts of a creator's reputation signals.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct CreatorSignals {
+    pub rejections: u32,
+    pub reports: u32,
+    pub strikes: u32,
+    pub deleted_tweets: u32,
+}
+
+/// A creator's score right after a signal changed it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ReputationSnapshot {
+    pub at: i64,
+    pub signal: ReputationSignal,
+    pub score: u32,
+}
+
+/// A token's EIP-2981 royalty as last read from the NFT contract.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RoyaltyInfo {
+    pub receiver: String,
+    pub basis_points: u64,
+    pub fetched_at: i64,
+}
+
+/// A token's generated metadata JSON, and its IPFS CID once pinned.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TokenMetadataRecord {
+    pub metadata: String,
+    pub cid: Option<String>,
+}
+
+/// Funds that arrived at a user's address, as seen by the deposit watcher.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct LedgerEntry {
+    pub chain_id: u64,
+    /// The token contract, or none for ETH.
+    pub asset: Option<String>,
+    pub from: String,
+    /// Wei or token base units. An ERC-721 transfer moves one token.
+    pub amount: String,
+    /// The token id of an ERC-721 transfer.
+    pub token_id: Option<String>,
+    pub tx_hash: String,
+    /// The log of a token transfer, or none for an ETH transfer.
+    pub log_index: Option<u64>,
+    pub block_number: u64,
+    pub recorded_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessList {
+    Allow,
+    Deny,
+}
+
+/// An address or x_id on the mint allowlist or denylist.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AccessListEntry {
+    /// A lowercased address or an x_id.
+    pub subject: String,
+    pub list: AccessList,
+    pub reason: String,
+    pub added_by: String,
+    pub added_at: i64,
+}
+
+/// An ERC-20 payment for mints, in the earnings ledger.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct MintPayment {
+    pub chain_id: u64,
+    pub payer: String,
+    /// The stablecoin paid in.
+    pub token: String,
+    /// Token base units received.
+    pub amount: String,
+    pub payment_tx: String,
+    /// The mints the payment paid for, empty while they are being sent.
+    pub mint_txs: Vec<String>,
+    pub paid_at: i64,
+}
+
+/// What the indexer has seen of an NFT contract's admin state, keyed by chain and contract.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ContractStatus {
+    pub paused: bool,
+    /// `None` until the owner is read or an ownership transfer is indexed.
+    pub owner: Option<String>,
+}
+
+/// A note a holder sent a token's creator, encrypted with the enclave's inbox key.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct InboxMessage {
+    pub id: String,
+    pub chain_id: u64,
+    pub token_id: String,
+    /// The creator's x_id.
+    pub creator: String,
+    /// The holder's lowercased address.
+    pub sender: String,
+    pub sealed: String,
+    pub sent_at: i64,
+}
+
+/// A URL a creator registered to receive their own tokens' activity, signed with `secret`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct CreatorWebhook {
+    pub url: String,
+    /// Hex HMAC-SHA256 key, shown to the creator once when they register.
+    pub secret: String,
+    pub created_at: i64,
+}
+
+/// One admin API action, attributed to the admin that performed it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AdminAuditEntry {
+    pub at: i64,
+    pub admin: String,
+    pub role: String,
+    pub action: String,
+    pub detail: String,
+}
+
+/// Which backend holds the service database, from `DB_BACKEND` (`memory` or `sqlite`). The
+/// in-memory one is loaded from `DB_PATH` at startup and written back on shutdown as a checksummed
+/// [`snapshot`]; the SQLite one keeps `DB_PATH` as its database file and writes every change to it
+/// as it happens, its journal guarding against torn writes instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DbBackend {
+    InMemory,
+    Sqlite,
+}
+
+impl DbBackend {
+    pub fn from_env() -> eyre::Result<Self> {
+        match std::env::var("DB_BACKEND").as_deref() {
+            Err(_) | Ok("memory") => Ok(Self::InMemory),
+            Ok("sqlite") => Ok(Self::Sqlite),
+            Ok(backend) => eyre::bail!("Unknown DB_BACKEND {}", backend),
+        }
+    }
+}
+
+/// How an address is keyed in the user address index. Addresses are stored as they were
+/// registered, which may be lowercase, while events carry them checksummed, so anything that
+/// parses as an [`Address`] is keyed by its parsed form.
+pub fn address_key(address: &str) -> String {
+    let address = address.trim();
+    match address.parse::<Address>() {
+        Ok(parsed) => parsed.to_string().to_lowercase(),
+        Err(_) => address.to_lowercase(),
+    }
+}
+
+/// Orders decimal token ids numerically, however long they are.
+fn token_id_order(token_id: &str) -> (usize, &str) {
+    (token_id.len(), token_id)
+}
+
+/// Longest chain of fee-bump replacements and cancellations followed from one hash.
+pub const MAX_TX_REPLACEMENTS: usize = 16;
+
+/// The latest transaction to have replaced `tx`, or `tx` itself.
+fn latest_replacement<'a>(
+    txs: &'a BTreeMap<String, TxRecord>,
+    mut tx: &'a TxRecord,
+) -> &'a TxRecord {
+    for _ in 0..MAX_TX_REPLACEMENTS {
+        match tx.replaced_by.as_ref().and_then(|tx_hash| txs.get(tx_hash)) {
+            Some(replacement) => tx = replacement,
+            None => break,
+        }
+    }
+    tx
+}
+
+/// Hashes of `chain_id`'s transactions whose latest replacement is still pending. They share a
+/// nonce with it, so any of them may be the one that is mined.
+fn unresolved_txs(txs: &BTreeMap<String, TxRecord>, chain_id: u64) -> Vec<String> {
+    txs.iter()
+        .filter(|(_, tx)| {
+            tx.chain_id == chain_id && latest_replacement(txs, tx).status == TxStatus::Pending
+        })
+        .map(|(tx_hash, _)| tx_hash.clone())
+        .collect()
+}
+
+/// Wrong codes an email challenge takes before it is locked until it expires.
+pub const MAX_EMAIL_CHALLENGE_ATTEMPTS: u32 = 5;
+
+/// Whether an email confirmation code matches, in time that does not depend on where it differs.
+pub fn email_code_matches(expected: &str, code: &str) -> bool {
+    expected.len() == code.len() && openssl::memcmp::eq(expected.as_bytes(), code.as_bytes())
+}
+
 pub trait TeleportDB: Send + Sync + 'static {
     // async fn init(&mut self) -> eyre::Result<()>;
     // async fn open_from_file(file_path: &str) -> eyre::Result<Self>;
     fn add_user(&mut self, address: String, user: User) -> eyre::Result<()>;
+    fn count_users(&self) -> eyre::Result<usize>;
+    /// The user registered at `address`, however its letters are cased.
     fn get_user_by_address(&self, address: String) -> eyre::Result<User>;
     fn get_user_by_x_id(&self, x_id: String) -> eyre::Result<User>;
+    /// Tokens minted, or being minted, by `address`, all of which name its current X account.
+    fn count_creator_nfts(&self, address: String) -> eyre::Result<usize>;
+    /// Detaches the X account linked to `address` and signs it out everywhere, returning the
+    /// account's x_id.
+    fn unlink_x_account(&mut self, address: String) -> eyre::Result<String>;
+    /// Moves a user's preferences from the X account they unlinked to the one they linked.
+    fn migrate_x_account(&mut self, from: String, to: String) -> eyre::Result<()>;
     fn add_pending_nft(&mut self, tx_hash: String, pending_nft: PendingNFT) -> eyre::Result<()>;
+    /// Also clears the mint's confirmation, if it was waiting on one.
     fn promote_pending_nft(&mut self, tx_hash: String, token_id: String) -> eyre::Result<String>;
+    fn get_pending_nft(&self, tx_hash: String) -> eyre::Result<Option<PendingNFT>>;
+    /// Pins the policy a pending NFT is being minted under; it moves to the token on promotion.
+    fn set_pending_policy_snapshot(
+        &mut self,
+        nft_id: String,
+        snapshot: PolicySnapshot,
+    ) -> eyre::Result<()>;
+    /// `None` for tokens minted before policies were pinned.
+    fn get_policy_snapshot(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> eyre::Result<Option<PolicySnapshot>>;
+    fn get_pending_nft_by_id(&self, nft_id: String) -> eyre::Result<Option<PendingNFT>>;
+    fn set_mint_confirmation(
+        &mut self,
+        tx_hash: String,
+        confirmation: MintConfirmation,
+    ) -> eyre::Result<()>;
+    /// Mints on a chain waiting for confirmations, by transaction hash.
+    fn get_mint_confirmations(
+        &self,
+        chain_id: u64,
+    ) -> eyre::Result<Vec<(String, MintConfirmation)>>;
+    fn get_mint_confirmation_by_nft_id(
+        &self,
+        nft_id: String,
+    ) -> eyre::Result<Option<MintConfirmation>>;
+    /// Whether the transaction had a mint waiting for confirmations.
+    fn remove_mint_confirmation(&mut self, tx_hash: String) -> eyre::Result<bool>;
+    /// Makes a pending NFT findable under a replacement transaction's hash as well, since either
+    /// transaction may be the one that gets mined.
+    fn alias_pending_nft(
+        &mut self,
+        tx_hash: String,
+        replacement_tx_hash: String,
+    ) -> eyre::Result<()>;
+    /// The hash of the policy a token was minted with. Tokens minted before policies were
+    /// recorded have none.
+    fn get_policy_hash(&self, chain_id: u64, token_id: String) -> eyre::Result<Option<String>>;
+    /// The hash of the transaction that minted a token, once the indexer has seen it.
+    fn get_mint_tx(&self, chain_id: u64, token_id: String) -> eyre::Result<Option<String>>;
     fn get_nft(&self, nft_id: String) -> eyre::Result<NFT>;
-    fn add_tweet(&mut self, token_id: String, tweet_id: String) -> eyre::Result<()>;
-    fn get_tweet(&self, token_id: String) -> eyre::Result<String>;
+    fn get_nft_by_token_id(&self, chain_id: u64, token_id: String) -> eyre::Result<NFT>;
+    /// The id the marketplace knows a promoted token's NFT by.
+    fn get_nft_id_by_token_id(&self, chain_id: u64, token_id: String) -> eyre::Result<String>;
+    fn add_tweet(&mut self, chain_id: u64, token_id: String, tweet_id: String) -> eyre::Result<()>;
+    fn get_tweet(&self, chain_id: u64, token_id: String) -> eyre::Result<String>;
     fn add_session(&mut self, session: Session) -> eyre::Result<String>;
     fn get_session(&self, session_id: String) -> eyre::Result<Session>;
+    fn add_moderation_record(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        record: ModerationRecord,
+    ) -> eyre::Result<()>;
+    fn get_moderation_record(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> eyre::Result<ModerationRecord>;
+    fn set_policy_precheck_threshold(&mut self, policy: String, threshold: f32)
+        -> eyre::Result<()>;
+    fn get_policy_precheck_threshold(&self, policy: String) -> eyre::Result<f32>;
+    fn set_policy_mention_rule(&mut self, policy: String, rule: MentionRule) -> eyre::Result<()>;
+    /// `None` for policies that do not restrict mentions.
+    fn get_policy_mention_rule(&self, policy: String) -> eyre::Result<Option<MentionRule>>;
+    fn set_policy_license_rule(&mut self, policy: String, rule: LicenseRule) -> eyre::Result<()>;
+    /// `None` for policies that accept any license, or none.
+    fn get_policy_license_rule(&self, policy: String) -> eyre::Result<Option<LicenseRule>>;
+    fn set_policy_moderation_tier(
+        &mut self,
+        policy: String,
+        tier: ModerationTier,
+    ) -> eyre::Result<()>;
+    /// `ModerationTier::Standard` for policies never given a tier.
+    fn get_policy_moderation_tier(&self, policy: String) -> eyre::Result<ModerationTier>;
+    fn set_redemption_license(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        license: ContentLicense,
+    ) -> eyre::Result<()>;
+    fn get_redemption_license(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> eyre::Result<Option<ContentLicense>>;
+    /// Lets a redemption held for its mentions through when it is replayed.
+    fn approve_mentions(&mut self, chain_id: u64, token_id: String) -> eyre::Result<()>;
+    fn are_mentions_approved(&self, chain_id: u64, token_id: String) -> eyre::Result<bool>;
+    /// Lets a redemption its moderation quorum split on through when it is replayed.
+    fn approve_quorum_dispute(&mut self, chain_id: u64, token_id: String) -> eyre::Result<()>;
+    fn is_quorum_dispute_approved(&self, chain_id: u64, token_id: String) -> eyre::Result<bool>;
+    fn set_last_processed_block(
+        &mut self,
+        chain_id: u64,
+        block_number: u64,
+        log_index: u64,
+    ) -> eyre::Result<()>;
+    fn get_last_processed_block(&self, chain_id: u64) -> eyre::Result<BlockCursor>;
+    /// Records a contract log as processed, returning false if it already was.
+    fn mark_event_processed(
+        &mut self,
+        chain_id: u64,
+        tx_hash: String,
+        log_index: u64,
+    ) -> eyre::Result<bool>;
+    fn is_event_processed(
+        &self,
+        chain_id: u64,
+        tx_hash: String,
+        log_index: u64,
+    ) -> eyre::Result<bool>;
+    fn upsert_failed_event(&mut self, event_id: String, event: FailedEvent) -> eyre::Result<()>;
+    fn get_due_failed_events(
+        &self,
+        chain_id: u64,
+        now: i64,
+    ) -> eyre::Result<Vec<(String, FailedEvent)>>;
+    fn remove_failed_event(&mut self, event_id: String) -> eyre::Result<()>;
+    fn add_redemption_link(&mut self, link_id: String, link: RedemptionLink) -> eyre::Result<()>;
+    fn get_redemption_link(&self, link_id: String) -> eyre::Result<RedemptionLink>;
+    /// Unused, unexpired links created by a session.
+    fn count_open_redemption_links(&self, session_id: String, now: i64) -> eyre::Result<usize>;
+    /// Marks a link used, failing if it already was or has expired.
+    fn consume_redemption_link(
+        &mut self,
+        link_id: String,
+        now: i64,
+    ) -> eyre::Result<RedemptionLink>;
+    fn set_recovery_email(&mut self, x_id: String, recovery: RecoveryEmail) -> eyre::Result<()>;
+    fn get_recovery_email(&self, x_id: String) -> eyre::Result<RecoveryEmail>;
+    /// The IANA timezone a user has set, such as `Europe/Berlin`.
+    fn set_timezone(&mut self, x_id: String, timezone: String) -> eyre::Result<()>;
+    fn get_timezone(&self, x_id: String) -> eyre::Result<String>;
+    /// Replaces any webhook the creator registered before.
+    fn set_creator_webhook(&mut self, x_id: String, webhook: CreatorWebhook) -> eyre::Result<()>;
+    fn get_creator_webhook(&self, x_id: String) -> eyre::Result<Option<CreatorWebhook>>;
+    /// Whether the creator had a webhook to remove.
+    fn remove_creator_webhook(&mut self, x_id: String) -> eyre::Result<bool>;
+    /// Replaces any outstanding challenge for `x_id`. The wrong attempts of one still running
+    /// carry over to the new code, so sending another does not reset them. Returns false, keeping
+    /// the old challenge, if it is locked.
+    fn set_email_challenge(
+        &mut self,
+        x_id: String,
+        challenge: EmailChallenge,
+        now: i64,
+    ) -> eyre::Result<bool>;
+    /// Consumes the challenge if `code` matches, counting failed attempts against it. One that
+    /// used up its attempts stays, locked, until it expires.
+    fn verify_email_challenge(
+        &mut self,
+        x_id: String,
+        code: String,
+        now: i64,
+    ) -> eyre::Result<EmailChallenge>;
+    fn add_tx(&mut self, tx_hash: String, tx: TxRecord) -> eyre::Result<()>;
+    fn get_tx(&self, tx_hash: String) -> eyre::Result<TxRecord>;
+    /// Hashes of the chain's transactions that may still be mined: pending ones, and those
+    /// replaced by one still pending.
+    fn get_pending_txs(&self, chain_id: u64) -> eyre::Result<Vec<String>>;
+    /// Records `tx_hash`'s receipt. Whatever replaced it lost the nonce to it, so is marked
+    /// replaced by it.
+    fn set_tx_mined(
+        &mut self,
+        tx_hash: String,
+        success: bool,
+        block_number: Option<u64>,
+        gas_used: u64,
+    ) -> eyre::Result<()>;
+    /// Marks `tx_hash` replaced and tracks `replacement_tx_hash` in its place.
+    fn replace_tx(&mut self, tx_hash: String, replacement_tx_hash: String) -> eyre::Result<()>;
+    /// Marks `tx_hash` cancelled and tracks `cancel_tx_hash` as the `cancel` that replaced it.
+    fn cancel_tx(
+        &mut self,
+        tx_hash: String,
+        cancel_tx_hash: String,
+        submitted_at: i64,
+    ) -> eyre::Result<()>;
+    fn get_failed_event(&self, event_id: String) -> eyre::Result<FailedEvent>;
+    fn add_pending_approval(
+        &mut self,
+        approval_id: String,
+        approval: PendingApproval,
+    ) -> eyre::Result<()>;
+    fn get_pending_approvals(&self) -> eyre::Result<Vec<(String, PendingApproval)>>;
+    fn remove_pending_approval(&mut self, approval_id: String) -> eyre::Result<PendingApproval>;
+    /// Drops approvals that expired before a second admin approved them, returning how many.
+    fn prune_pending_approvals(&mut self, now: i64) -> eyre::Result<usize>;
+    fn add_admin_audit_entry(&mut self, entry: AdminAuditEntry) -> eyre::Result<()>;
+    /// The `limit` most recent entries, newest first.
+    fn get_admin_audit_log(&self, limit: usize) -> eyre::Result<Vec<AdminAuditEntry>>;
+    fn get_collection_stats(&self, chain_id: u64) -> eyre::Result<CollectionStats>;
+    /// At most `limit` users, in address order, after the address `after`.
+    fn list_users(&self, after: Option<String>, limit: usize) -> eyre::Result<Vec<(String, User)>>;
+    /// At most `limit` minted NFTs, in id order, after the NFT id `after`.
+    fn list_nfts(&self, after: Option<String>, limit: usize) -> eyre::Result<Vec<(String, NFT)>>;
+    /// At most `limit` of a chain's moderated redemptions, in numeric token id order, after the
+    /// token id `after`.
+    fn list_redemptions(
+        &self,
+        chain_id: u64,
+        after: Option<String>,
+        limit: usize,
+    ) -> eyre::Result<Vec<RedemptionRecord>>;
+    /// Stores a report and returns how many reporters the tweet now has, or `None` if its
+    /// reporter already reported the tweet.
+    fn add_abuse_report(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        report: AbuseReport,
+    ) -> eyre::Result<Option<usize>>;
+    /// Every reported tweet with its reports.
+    fn get_abuse_reports(&self) -> eyre::Result<Vec<((u64, String), Vec<AbuseReport>)>>;
+    /// Counts a confirmed abusive tweet against a creator, returning their strikes so far.
+    fn add_creator_strike(&mut self, x_id: String) -> eyre::Result<u32>;
+    fn get_creator_strikes(&self, x_id: String) -> eyre::Result<u32>;
+    fn clear_creator_strikes(&mut self, x_id: String) -> eyre::Result<()>;
+    fn set_royalty_info(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        royalty: RoyaltyInfo,
+    ) -> eyre::Result<()>;
+    fn get_royalty_info(&self, chain_id: u64, token_id: String) -> eyre::Result<RoyaltyInfo>;
+    /// Turns DMs about received tokens off, or back on, for the user with `x_id`.
+    fn set_dm_opt_out(&mut self, x_id: String, opt_out: bool) -> eyre::Result<()>;
+    /// Whether a creator wants their tokens burned once redeemed.
+    fn set_burn_on_redeem(&mut self, x_id: String, enabled: bool) -> eyre::Result<()>;
+    fn get_burn_on_redeem(&self, x_id: String) -> eyre::Result<bool>;
+    fn approve_content(
+        &mut self,
+        x_id: String,
+        policy_hash: String,
+        content_hash: String,
+        approved: ApprovedContent,
+    ) -> eyre::Result<()>;
+    fn get_approved_content(
+        &self,
+        x_id: String,
+        policy_hash: String,
+        content_hash: String,
+    ) -> eyre::Result<Option<ApprovedContent>>;
+    /// Whether the content had been approved.
+    fn revoke_approved_content(
+        &mut self,
+        x_id: String,
+        policy_hash: String,
+        content_hash: String,
+    ) -> eyre::Result<bool>;
+    /// Queues a burn of a token, marking it a revocation if `revocation` is set. A token queued
+    /// twice is burned once, as a revocation if either was one.
+    fn queue_burn(&mut self, chain_id: u64, token_id: String, revocation: bool)
+        -> eyre::Result<()>;
+    fn get_pending_burns(&self, chain_id: u64) -> eyre::Result<Vec<(String, PendingBurn)>>;
+    fn set_burn_tx(&mut self, chain_id: u64, token_id: String, tx_hash: String)
+        -> eyre::Result<()>;
+    fn get_burn(&self, chain_id: u64, token_id: String) -> eyre::Result<Option<PendingBurn>>;
+    fn get_creator_signals(&self, x_id: String) -> eyre::Result<CreatorSignals>;
+    fn set_creator_signals(&mut self, x_id: String, signals: CreatorSignals) -> eyre::Result<()>;
+    fn add_reputation_snapshot(
+        &mut self,
+        x_id: String,
+        snapshot: ReputationSnapshot,
+    ) -> eyre::Result<()>;
+    /// A creator's snapshots, oldest first.
+    fn get_reputation_history(&self, x_id: String) -> eyre::Result<Vec<ReputationSnapshot>>;
+    /// The nonce a holder's next signed redeem request must carry.
+    fn get_redeem_nonce(&self, address: String) -> eyre::Result<u64>;
+    /// Consumes `nonce` if it is the holder's current one, so each signed request is used once.
+    fn use_redeem_nonce(&mut self, address: String, nonce: u64) -> eyre::Result<()>;
+    fn remove_burn(&mut self, chain_id: u64, token_id: String) -> eyre::Result<()>;
+    /// Queues a posted redemption to be anchored in the fulfillment registry.
+    fn queue_anchor(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        tweet_id: String,
+        content_hash: String,
+    ) -> eyre::Result<()>;
+    /// Anchors queued on `chain_id` that have not been sent yet.
+    fn get_pending_anchors(&self, chain_id: u64) -> eyre::Result<Vec<(String, ContentAnchor)>>;
+    fn get_anchor(&self, chain_id: u64, token_id: String) -> eyre::Result<Option<ContentAnchor>>;
+    fn set_anchor(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        anchor: ContentAnchor,
+    ) -> eyre::Result<()>;
+    fn remove_anchor(&mut self, chain_id: u64, token_id: String) -> eyre::Result<()>;
+    /// Records a gas top-up sent to `address` at `at`.
+    fn add_top_up(&mut self, chain_id: u64, address: String, at: i64) -> eyre::Result<()>;
+    /// How many top-ups `address` was sent since `since`.
+    fn count_top_ups_since(
+        &self,
+        chain_id: u64,
+        address: String,
+        since: i64,
+    ) -> eyre::Result<usize>;
+    /// Every registered user address.
+    fn get_user_addresses(&self) -> eyre::Result<Vec<String>>;
+    /// Records a deposit to `address`. Returns false if it was already recorded.
+    fn add_ledger_entry(&mut self, address: String, entry: LedgerEntry) -> eyre::Result<bool>;
+    /// Deposits to `address`, oldest first.
+    fn get_ledger(&self, address: String) -> eyre::Result<Vec<LedgerEntry>>;
+    /// The last block the deposit watcher has scanned on a chain.
+    fn get_deposit_cursor(&self, chain_id: u64) -> eyre::Result<Option<u64>>;
+    fn set_deposit_cursor(&mut self, chain_id: u64, block_number: u64) -> eyre::Result<()>;
+    /// Claims a payment for a mint job. False if the payment transaction already paid for one.
+    fn add_mint_payment(&mut self, payment: MintPayment) -> eyre::Result<bool>;
+    fn set_payment_mint_txs(
+        &mut self,
+        chain_id: u64,
+        payment_tx: String,
+        mint_txs: Vec<String>,
+    ) -> eyre::Result<()>;
+    /// Releases a payment whose mints could not be sent, so it can be used again.
+    fn remove_mint_payment(&mut self, chain_id: u64, payment_tx: String) -> eyre::Result<()>;
+    /// The earnings ledger, oldest payment first.
+    fn get_mint_payments(&self) -> eyre::Result<Vec<MintPayment>>;
+    /// The last source block the relayer has anchored the redemptions of on a chain.
+    fn get_relay_cursor(&self, chain_id: u64) -> eyre::Result<Option<u64>>;
+    fn set_relay_cursor(&mut self, chain_id: u64, block_number: u64) -> eyre::Result<()>;
+    /// The L1 transaction a relayed log was anchored in.
+    fn get_relayed(&self, log_id: String) -> eyre::Result<Option<String>>;
+    fn set_relayed(&mut self, log_id: String, tx_hash: String) -> eyre::Result<()>;
+    /// Lists a subject, replacing any entry it already has.
+    fn set_access_list_entry(&mut self, entry: AccessListEntry) -> eyre::Result<()>;
+    /// False if the subject was not listed.
+    fn remove_access_list_entry(&mut self, subject: String) -> eyre::Result<bool>;
+    fn get_access_list_entry(&self, subject: String) -> eyre::Result<Option<AccessListEntry>>;
+    fn get_access_list(&self) -> eyre::Result<Vec<AccessListEntry>>;
+    fn add_inbox_message(&mut self, message: InboxMessage) -> eyre::Result<()>;
+    /// Kill switches admins flipped, by side effect name; true when switched back on.
+    fn get_kill_switches(&self) -> eyre::Result<BTreeMap<String, bool>>;
+    fn set_kill_switch(&mut self, side_effect: String, enabled: bool) -> eyre::Result<()>;
+    /// The default status for a contract nothing has been recorded for.
+    fn get_contract_status(&self, chain_id: u64, contract: String) -> eyre::Result<ContractStatus>;
+    fn set_contract_status(
+        &mut self,
+        chain_id: u64,
+        contract: String,
+        status: ContractStatus,
+    ) -> eyre::Result<()>;
+    /// Messages sent to a creator's x_id, newest first.
+    fn get_inbox(&self, creator: String) -> eyre::Result<Vec<InboxMessage>>;
+    /// Messages an address has sent since a unix timestamp.
+    fn count_inbox_messages_since(&self, sender: String, since: i64) -> eyre::Result<usize>;
+    fn set_token_metadata(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        metadata: String,
+    ) -> eyre::Result<()>;
+    fn get_token_metadata(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> eyre::Result<Option<TokenMetadataRecord>>;
+    /// Chain, token id and metadata of every token whose metadata is not pinned yet.
+    fn get_unpinned_metadata(&self) -> eyre::Result<Vec<(u64, String, String)>>;
+    fn set_metadata_cid(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        cid: String,
+    ) -> eyre::Result<()>;
     fn serialize(&self) -> eyre::Result<Vec<u8>>;
+    /// Everything in the database, in a form only [`TeleportDB::import_snapshot`] of the same
+    /// backend reads back.
+    fn export_snapshot(&self) -> eyre::Result<Vec<u8>>;
+    /// Replaces everything in the database with an exported snapshot, all at once.
+    fn import_snapshot(&mut self, snapshot: &snapshot::Image) -> eyre::Result<()>;
 }
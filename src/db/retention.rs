@@ -0,0 +1,37 @@
+use tokio::time::{sleep, Duration};
+
+use super::client_db::ClientDB;
+use crate::{actions::chain::chain_var, metrics};
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Reads `CONTENT_RETENTION_DAYS`, overridable per chain with a `_<chain_id>` suffix. Content is
+/// kept indefinitely on chains without a retention period.
+pub fn get_retention_days(chain_id: u64) -> eyre::Result<Option<i32>> {
+    match chain_var("CONTENT_RETENTION_DAYS", chain_id) {
+        Ok(days) => Ok(Some(days.parse()?)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Hourly purges redeemed tweet content past each chain's retention period, keeping only the
+/// content hash and the rest of the redemption record.
+pub async fn run_content_purge(client_db: ClientDB, retention: Vec<(u64, i32)>) {
+    loop {
+        for &(chain_id, retention_days) in &retention {
+            match client_db.purge_redemption_content(chain_id, retention_days).await {
+                Ok(0) => {}
+                Ok(purged) => {
+                    log::info!("Purged content of {} redemptions on chain {}", purged, chain_id);
+                    metrics::increment_by(
+                        "redemption_content_purged_total",
+                        &[("chain_id", &chain_id.to_string())],
+                        purged as u64,
+                    );
+                }
+                Err(e) => log::error!("Failed to purge content on chain {}: {:?}", chain_id, e),
+            }
+        }
+        sleep(PURGE_INTERVAL).await;
+    }
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+use super::client_db::ClientDB;
+
+/// Days before yesterday that are rolled up again each night, to pick up late writes.
+const DEFAULT_LOOKBACK_DAYS: u64 = 2;
+
+fn get_lookback_days() -> u64 {
+    std::env::var("ROLLUP_LOOKBACK_DAYS")
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(DEFAULT_LOOKBACK_DAYS)
+}
+
+/// Yesterday and the `lookback_days` before it, oldest first.
+fn days_to_roll_up(today: NaiveDate, lookback_days: u64) -> Vec<NaiveDate> {
+    (1..=lookback_days + 1)
+        .rev()
+        .filter_map(|days| today.checked_sub_days(chrono::Days::new(days)))
+        .collect()
+}
+
+fn until_next_midnight(now: DateTime<Utc>) -> Duration {
+    let next_midnight =
+        (now.date_naive() + chrono::Days::new(1)).and_hms_opt(0, 0, 0).unwrap().and_utc();
+    (next_midnight - now).to_std().unwrap_or_default()
+}
+
+async fn roll_up(client_db: &ClientDB, lookback_days: u64) {
+    for day in days_to_roll_up(Utc::now().date_naive(), lookback_days) {
+        match client_db.roll_up_creator_stats(day.to_string()).await {
+            Ok(creators) => log::info!("Rolled up {} creators for {}", creators, day),
+            Err(e) => log::error!("Failed to roll up creator stats for {}: {:?}", day, e),
+        }
+    }
+}
+
+/// Rolls the raw mint and redemption tables up into `creator_daily_stats` once at startup and
+/// then just after each UTC midnight, so stats queries never scan the raw tables.
+pub async fn run_daily_rollup(client_db: ClientDB) {
+    let lookback_days = get_lookback_days();
+    loop {
+        roll_up(&client_db, lookback_days).await;
+        tokio::time::sleep(until_next_midnight(Utc::now())).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolls_up_completed_days() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let days: Vec<String> = days_to_roll_up(today, 2).iter().map(ToString::to_string).collect();
+        assert_eq!(days, vec!["2024-02-27", "2024-02-28", "2024-02-29"]);
+
+        let now = today.and_hms_opt(23, 30, 0).unwrap().and_utc();
+        assert_eq!(until_next_midnight(now), Duration::from_secs(30 * 60));
+    }
+}
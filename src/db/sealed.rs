@@ -0,0 +1,241 @@
+use std::path::Path;
+
+use alloy::hex;
+use eyre::OptionExt;
+use openssl::{
+    hash::MessageDigest,
+    pkcs5::pbkdf2_hmac,
+    rand::rand_bytes,
+    symm::{decrypt_aead, encrypt_aead, Cipher},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Gramine's pseudo-file for the SGX sealing key bound to this enclave's measurement, the same key
+/// the `/root/save/` mount is encrypted with.
+const SGX_SEALING_KEY_PATH: &str = "/dev/attestation/keys/_sgx_mrenclave";
+/// Marks a database file as encrypted, telling it apart from a plaintext file from before sealing.
+const SEALED_MAGIC: &[u8] = b"teleport-sealed-db-v1\n";
+const PBKDF2_ITERATIONS: usize = 600_000;
+pub(super) const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// How the database file is encrypted at rest, from `DB_SEALING` (`none`, `sgx` or `passphrase`).
+/// `sgx` seals its storage key to the enclave; `passphrase` is for development outside one and
+/// derives the sealing key from `DB_PASSPHRASE` instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DbSealing {
+    None,
+    Sgx,
+    Passphrase,
+}
+
+/// Whether `DB_SEALING_MIGRATE=1` allows the one-time switch to sealing: generating a storage key
+/// when `<db_path>.key` is missing, and loading a plaintext database file to seal on its next save.
+/// Without it either means the key or the file was replaced, and startup fails.
+fn get_sealing_migrate() -> bool {
+    std::env::var("DB_SEALING_MIGRATE").as_deref() == Ok("1")
+}
+
+impl DbSealing {
+    pub fn from_env() -> eyre::Result<Self> {
+        match std::env::var("DB_SEALING").as_deref() {
+            Err(_) | Ok("none") => Ok(Self::None),
+            Ok("sgx") => Ok(Self::Sgx),
+            Ok("passphrase") => Ok(Self::Passphrase),
+            Ok(sealing) => eyre::bail!("Unknown DB_SEALING {}", sealing),
+        }
+    }
+
+    /// The storage key for the database at `db_path`, unsealed from `<db_path>.key`, or generated
+    /// and sealed there when migrating. `None` when the file is not encrypted.
+    pub fn storage_key(&self, db_path: &str) -> eyre::Result<Option<StorageKey>> {
+        let sealing_key = match self {
+            Self::None => return Ok(None),
+            Self::Sgx => SealingKey::Sgx,
+            Self::Passphrase => {
+                if crate::secrets::is_prod() {
+                    eyre::bail!("Prod profile requires DB_SEALING=sgx");
+                }
+                let passphrase = std::env::var("DB_PASSPHRASE")
+                    .map_err(|_| eyre::eyre!("DB_PASSPHRASE not set"))?;
+                log::warn!("Sealing the database with a passphrase, not the enclave");
+                SealingKey::Passphrase(passphrase)
+            }
+        };
+        let migrate = get_sealing_migrate();
+        if migrate {
+            log::warn!("DB_SEALING_MIGRATE is set, unset it once the database has been sealed");
+        }
+        let key_path = format!("{}.key", db_path);
+        if Path::new(&key_path).exists() {
+            let sealed: SealedStorageKey = serde_json::from_slice(&std::fs::read(&key_path)?)?;
+            return sealed.unseal(&sealing_key, migrate).map(Some);
+        }
+        if !migrate {
+            eyre::bail!("{} is missing; set DB_SEALING_MIGRATE=1 to generate one", key_path);
+        }
+        let mut key = [0u8; 32];
+        rand_bytes(&mut key)?;
+        let storage_key = StorageKey { key, accept_plaintext: true };
+        let sealed = SealedStorageKey::seal(&storage_key, &sealing_key)?;
+        std::fs::write(&key_path, serde_json::to_vec(&sealed)?)?;
+        log::info!("Generated a storage key for the database, sealed to {}", key_path);
+        Ok(Some(storage_key))
+    }
+}
+
+/// What the storage key is wrapped with.
+enum SealingKey {
+    Sgx,
+    Passphrase(String),
+}
+
+impl SealingKey {
+    /// The AES-256 key wrapping the storage key. `salt` only salts a passphrase; the SGX key is
+    /// already unique to the enclave.
+    fn derive(&self, salt: &[u8]) -> eyre::Result<[u8; 32]> {
+        let mut key = [0u8; 32];
+        match self {
+            Self::Sgx => {
+                let sgx_key = std::fs::read(SGX_SEALING_KEY_PATH).map_err(|e| {
+                    eyre::eyre!("SGX sealing key unavailable outside an enclave: {:?}", e)
+                })?;
+                key = Sha256::new()
+                    .chain_update(b"teleport-db-seal")
+                    .chain_update(sgx_key)
+                    .finalize()
+                    .into();
+            }
+            Self::Passphrase(passphrase) => pbkdf2_hmac(
+                passphrase.as_bytes(),
+                salt,
+                PBKDF2_ITERATIONS,
+                MessageDigest::sha256(),
+                &mut key,
+            )?,
+        }
+        Ok(key)
+    }
+}
+
+/// The storage key as kept beside the database, hex encoded.
+#[derive(Serialize, Deserialize)]
+struct SealedStorageKey {
+    salt: String,
+    nonce: String,
+    /// The storage key encrypted under the sealing key, with its tag appended.
+    sealed: String,
+}
+
+impl SealedStorageKey {
+    fn seal(storage_key: &StorageKey, sealing_key: &SealingKey) -> eyre::Result<Self> {
+        let mut salt = [0u8; 16];
+        rand_bytes(&mut salt)?;
+        let (nonce, sealed) = encrypt(&sealing_key.derive(&salt)?, &storage_key.key)?;
+        Ok(Self { salt: hex::encode(salt), nonce: hex::encode(nonce), sealed: hex::encode(sealed) })
+    }
+
+    fn unseal(&self, sealing_key: &SealingKey, accept_plaintext: bool) -> eyre::Result<StorageKey> {
+        let key = sealing_key.derive(&hex::decode(&self.salt)?)?;
+        let plaintext = decrypt(&key, &hex::decode(&self.nonce)?, &hex::decode(&self.sealed)?)
+            .map_err(|_| {
+                eyre::eyre!("Failed to unseal the storage key: wrong enclave or passphrase")
+            })?;
+        let key = plaintext.try_into().map_err(|_| eyre::eyre!("Malformed storage key"))?;
+        Ok(StorageKey { key, accept_plaintext })
+    }
+}
+
+/// The AES-256-GCM key the database file is encrypted with. It never leaves the process
+/// unsealed.
+pub struct StorageKey {
+    key: [u8; 32],
+    /// Whether a plaintext database file may be loaded, set only while migrating to sealing.
+    accept_plaintext: bool,
+}
+
+impl StorageKey {
+    /// Encrypts a database image for writing to disk.
+    pub fn seal(&self, image: &[u8]) -> eyre::Result<Vec<u8>> {
+        let (nonce, sealed) = encrypt(&self.key, image)?;
+        Ok([SEALED_MAGIC, nonce.as_slice(), sealed.as_slice()].concat())
+    }
+
+    /// Decrypts a database file written by [`StorageKey::seal`], failing if it was tampered with.
+    /// A plaintext file from before sealing was enabled is only returned while migrating, and
+    /// sealed on its next save.
+    pub fn open(&self, file: &[u8]) -> eyre::Result<Vec<u8>> {
+        let Some(sealed) = file.strip_prefix(SEALED_MAGIC) else {
+            if !self.accept_plaintext {
+                eyre::bail!("Database file is not sealed; set DB_SEALING_MIGRATE=1 to seal it");
+            }
+            log::warn!("Database file is not sealed, it will be on the next save");
+            return Ok(file.to_vec());
+        };
+        if sealed.len() < NONCE_LEN {
+            eyre::bail!("Sealed database file too short");
+        }
+        let (nonce, sealed) = sealed.split_at(NONCE_LEN);
+        decrypt(&self.key, nonce, sealed)
+    }
+}
+
+/// Encrypts under a fresh nonce, returning it and `ciphertext||tag`.
+pub(super) fn encrypt(
+    key: &[u8; 32],
+    plaintext: &[u8],
+) -> eyre::Result<([u8; NONCE_LEN], Vec<u8>)> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand_bytes(&mut nonce)?;
+    let mut tag = [0u8; TAG_LEN];
+    let mut sealed =
+        encrypt_aead(Cipher::aes_256_gcm(), key, Some(&nonce), &[], plaintext, &mut tag)?;
+    sealed.extend_from_slice(&tag);
+    Ok((nonce, sealed))
+}
+
+pub(super) fn decrypt(key: &[u8; 32], nonce: &[u8], sealed: &[u8]) -> eyre::Result<Vec<u8>> {
+    let tag_start = sealed.len().checked_sub(TAG_LEN).ok_or_eyre("Sealed data too short")?;
+    let (ciphertext, tag) = sealed.split_at(tag_start);
+    Ok(decrypt_aead(Cipher::aes_256_gcm(), key, Some(nonce), &[], ciphertext, tag)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn database_image_round_trip() -> eyre::Result<()> {
+        let key = StorageKey { key: [7u8; 32], accept_plaintext: false };
+        let sealed = key.seal(b"users and their tokens")?;
+        assert!(!sealed.windows(5).any(|window| window == b"users"));
+        assert_eq!(key.open(&sealed)?, b"users and their tokens");
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(key.open(&tampered).is_err());
+        assert!(StorageKey { key: [8u8; 32], accept_plaintext: false }.open(&sealed).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn plaintext_image_loads_only_while_migrating() -> eyre::Result<()> {
+        let key = StorageKey { key: [7u8; 32], accept_plaintext: false };
+        assert!(key.open(b"plaintext image").is_err());
+        let migrating = StorageKey { key: [7u8; 32], accept_plaintext: true };
+        assert_eq!(migrating.open(b"plaintext image")?, b"plaintext image");
+        Ok(())
+    }
+
+    #[test]
+    fn storage_key_unseals_only_with_its_passphrase() -> eyre::Result<()> {
+        let storage_key = StorageKey { key: [7u8; 32], accept_plaintext: false };
+        let sealing_key = SealingKey::Passphrase("correct horse".to_string());
+        let sealed = SealedStorageKey::seal(&storage_key, &sealing_key)?;
+        assert_eq!(sealed.unseal(&sealing_key, false)?.key, storage_key.key);
+        let wrong_key = SealingKey::Passphrase("wrong horse".to_string());
+        assert!(sealed.unseal(&wrong_key, false).is_err());
+        Ok(())
+    }
+}
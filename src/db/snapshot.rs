@@ -0,0 +1,212 @@
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::metrics;
+
+/// Starts every snapshot file. Files without it are raw images from before snapshots were framed.
+const SNAPSHOT_MAGIC: &[u8; 8] = b"TELESNAP";
+/// Bumped whenever the in-memory database's fields change shape, since bincode is not
+/// self-describing: an image is read in the layout of the version it was written with and then
+/// migrated, so the layout being replaced goes into `super::layouts`.
+pub const SCHEMA_VERSION: u16 = 3;
+/// The version of images from before snapshots were framed, which were all written by one layout.
+pub const UNVERSIONED: u16 = 0;
+const HEADER_LEN: usize = SNAPSHOT_MAGIC.len() + 2 + 8 + 32;
+
+/// A snapshot that failed its checksum or was cut short, as a torn write leaves one.
+#[derive(Debug)]
+pub struct CorruptSnapshot(pub String);
+
+impl std::fmt::Display for CorruptSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Corrupt snapshot: {}", self.0)
+    }
+}
+
+impl std::error::Error for CorruptSnapshot {}
+
+/// A database image and the schema version it is laid out for.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Image {
+    pub version: u16,
+    pub bytes: Vec<u8>,
+}
+
+/// Frames a database image as `magic || schema version || length || sha256 || image`.
+pub fn encode(image: &[u8]) -> Vec<u8> {
+    let mut snapshot = Vec::with_capacity(HEADER_LEN + image.len());
+    snapshot.extend_from_slice(SNAPSHOT_MAGIC);
+    snapshot.extend_from_slice(&SCHEMA_VERSION.to_le_bytes());
+    snapshot.extend_from_slice(&(image.len() as u64).to_le_bytes());
+    snapshot.extend_from_slice(&Sha256::digest(image));
+    snapshot.extend_from_slice(image);
+    snapshot
+}
+
+/// The image framed in `snapshot`, checked against its length and checksum. Images of older
+/// schema versions are returned for the caller to migrate; only a newer one is refused.
+pub fn decode(snapshot: &[u8]) -> eyre::Result<Image> {
+    let Some(framed) = snapshot.strip_prefix(SNAPSHOT_MAGIC) else {
+        log::warn!("Snapshot has no header, loading it as an unversioned image");
+        return Ok(Image { version: UNVERSIONED, bytes: snapshot.to_vec() });
+    };
+    if snapshot.len() < HEADER_LEN {
+        return Err(CorruptSnapshot("header cut short".to_string()).into());
+    }
+    let (version, framed) = framed.split_at(2);
+    let version = u16::from_le_bytes(version.try_into()?);
+    if version > SCHEMA_VERSION {
+        eyre::bail!("Snapshot schema version {} is newer than {}", version, SCHEMA_VERSION);
+    }
+    let (len, framed) = framed.split_at(8);
+    let len = u64::from_le_bytes(len.try_into()?);
+    let (checksum, image) = framed.split_at(32);
+    if image.len() as u64 != len {
+        let reason = format!("{} of {} bytes", image.len(), len);
+        return Err(CorruptSnapshot(reason).into());
+    }
+    if Sha256::digest(image).as_slice() != checksum {
+        return Err(CorruptSnapshot("checksum mismatch".to_string()).into());
+    }
+    Ok(Image { version, bytes: image.to_vec() })
+}
+
+/// Where the snapshot before the latest is kept.
+fn previous_path(path: &Path) -> PathBuf {
+    let mut previous = path.as_os_str().to_owned();
+    previous.push(".prev");
+    previous.into()
+}
+
+/// Decodes a snapshot and runs `open` over its image, which decrypts it when the database file is
+/// sealed. An image `open` fails on is as unusable as a torn one, so it counts as corrupt.
+fn open_snapshot(
+    snapshot: &[u8],
+    open: &impl Fn(&[u8]) -> eyre::Result<Vec<u8>>,
+) -> eyre::Result<Image> {
+    let image = decode(snapshot)?;
+    let bytes = open(&image.bytes)
+        .map_err(|e| CorruptSnapshot(format!("image failed to open: {:#}", e)))?;
+    Ok(Image { version: image.version, bytes })
+}
+
+/// Writes `image` as the snapshot at `path`, keeping the one it replaces at `<path>.prev`. The
+/// new snapshot is synced under a temporary name first, so a host killed mid-write leaves both
+/// earlier snapshots intact.
+pub async fn write(
+    path: &Path,
+    image: &[u8],
+    open: impl Fn(&[u8]) -> eyre::Result<Vec<u8>>,
+) -> eyre::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let mut file = fs::File::create(&tmp).await?;
+    file.write_all(&encode(image)).await?;
+    file.sync_all().await?;
+    drop(file);
+    // A corrupt snapshot, which a fallback was just loaded from, must not replace the fallback.
+    match fs::read(path).await {
+        Ok(current) if open_snapshot(&current, &open).is_ok() => {
+            fs::rename(path, previous_path(path)).await?
+        }
+        Ok(_) => log::warn!("Not keeping corrupt snapshot {} as a fallback", path.display()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    fs::rename(&tmp, path).await?;
+    Ok(())
+}
+
+/// Reads the image of the snapshot at `path` and opens it with `open`, falling back to
+/// `<path>.prev` when it is corrupt, missing or fails to open. `None` when there is no snapshot at
+/// all.
+pub async fn read(
+    path: &Path,
+    open: impl Fn(&[u8]) -> eyre::Result<Vec<u8>>,
+) -> eyre::Result<Option<Image>> {
+    let latest = match fs::read(path).await {
+        Ok(snapshot) => open_snapshot(&snapshot, &open),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Err(CorruptSnapshot("missing".to_string()).into())
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let error = match latest {
+        Ok(image) => return Ok(Some(image)),
+        Err(e) if e.is::<CorruptSnapshot>() => e,
+        Err(e) => return Err(e),
+    };
+    let previous = previous_path(path);
+    if !fs::try_exists(&previous).await? {
+        // A first start has neither; a corrupt snapshot with nothing to fall back to is fatal.
+        return if fs::try_exists(path).await? { Err(error) } else { Ok(None) };
+    }
+    log::error!("{}: {}, falling back to {}", path.display(), error, previous.display());
+    metrics::increment("snapshot_fallbacks_total", &[]);
+    Ok(Some(open_snapshot(&fs::read(&previous).await?, &open)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_detects_corruption() -> eyre::Result<()> {
+        let snapshot = encode(b"image");
+        let image = |version| Image { version, bytes: b"image".to_vec() };
+        assert_eq!(decode(&snapshot)?, image(SCHEMA_VERSION));
+        // Images from before snapshots were framed still load.
+        assert_eq!(decode(b"image")?, image(UNVERSIONED));
+
+        assert!(decode(&snapshot[..snapshot.len() - 1]).unwrap_err().is::<CorruptSnapshot>());
+        assert!(decode(&snapshot[..HEADER_LEN - 1]).unwrap_err().is::<CorruptSnapshot>());
+        let mut flipped = snapshot.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        assert!(decode(&flipped).unwrap_err().is::<CorruptSnapshot>());
+
+        // Older images are left for the database to migrate, newer ones are refused.
+        let mut older = snapshot.clone();
+        older[SNAPSHOT_MAGIC.len()] -= 1;
+        assert_eq!(decode(&older)?, image(SCHEMA_VERSION - 1));
+        let mut newer = snapshot;
+        newer[SNAPSHOT_MAGIC.len()] += 1;
+        assert!(!decode(&newer).unwrap_err().is::<CorruptSnapshot>());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn read_falls_back_to_previous_snapshot() -> eyre::Result<()> {
+        let path = std::env::temp_dir().join(format!("teleport-{}.db", cuid::cuid2()));
+        let plain = |bytes: &[u8]| -> eyre::Result<Vec<u8>> { Ok(bytes.to_vec()) };
+        assert_eq!(read(&path, plain).await?, None);
+
+        write(&path, b"first", plain).await?;
+        write(&path, b"second", plain).await?;
+        let image = |bytes: &[u8]| Some(Image { version: SCHEMA_VERSION, bytes: bytes.to_vec() });
+        assert_eq!(read(&path, plain).await?, image(b"second"));
+
+        // A latest snapshot that checks out but cannot be decrypted.
+        let sealed_wrongly = |bytes: &[u8]| -> eyre::Result<Vec<u8>> {
+            match bytes {
+                b"second" => eyre::bail!("bad tag"),
+                _ => Ok(bytes.to_vec()),
+            }
+        };
+        assert_eq!(read(&path, sealed_wrongly).await?, image(b"first"));
+        // Nor is it kept as the fallback once the next snapshot is written.
+        write(&path, b"third", sealed_wrongly).await?;
+        assert_eq!(decode(&fs::read(previous_path(&path)).await?)?.bytes, b"first");
+
+        // A torn write of the latest snapshot.
+        let snapshot = fs::read(&path).await?;
+        fs::write(&path, &snapshot[..snapshot.len() - 3]).await?;
+        assert_eq!(read(&path, plain).await?, image(b"first"));
+
+        fs::remove_file(&path).await?;
+        assert_eq!(read(&path, plain).await?, image(b"first"));
+        fs::remove_file(previous_path(&path)).await?;
+        Ok(())
+    }
+}
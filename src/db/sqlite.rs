@@ -0,0 +1,1818 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use rusqlite::{params, Connection, DatabaseName, OptionalExtension};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{
+    address_key, email_code_matches, snapshot::Image, unresolved_txs, AbuseReport, AccessListEntry,
+    AdminAuditEntry, ApprovedContent, BlockCursor, CollectionStats, ContentAnchor, ContentLicense,
+    ContractStatus, CreatorSignals, CreatorWebhook, EmailChallenge, FailedEvent, InboxMessage,
+    LedgerEntry, LicenseRule, MentionRule, MintConfirmation, MintPayment, ModerationRecord,
+    ModerationTier, PendingApproval, PendingBurn, PendingNFT, PolicySnapshot, RecoveryEmail,
+    RedemptionLink, RedemptionRecord, ReputationSnapshot, RoyaltyInfo, Session, TeleportDB,
+    TokenMetadataRecord, TxRecord, TxStatus, User, MAX_EMAIL_CHALLENGE_ATTEMPTS,
+    MAX_TX_REPLACEMENTS, NFT,
+};
+use crate::actions::chain::DEFAULT_CHAIN_ID;
+
+/// Snapshots kept per creator; older ones are dropped.
+const MAX_REPUTATION_HISTORY: usize = 200;
+
+/// Every collection of the in-memory backend is a set of rows here, with the value JSON encoded.
+/// The default rollback journal is kept: WAL needs shared memory, which Gramine's encrypted
+/// mounts do not provide.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS entries (
+    collection TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (collection, key)
+) WITHOUT ROWID;
+";
+
+/// Separates the parts of a compound key. It sorts before any printable character, so keys sort
+/// like the tuples they encode.
+const KEY_SEPARATOR: char = '\u{1f}';
+
+/// A key as stored in the `key` column. Numbers are zero padded so rows come back in the order
+/// the in-memory backend's maps iterate in.
+trait EntryKey {
+    fn encode(&self) -> String;
+}
+
+impl EntryKey for str {
+    fn encode(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl EntryKey for String {
+    fn encode(&self) -> String {
+        self.clone()
+    }
+}
+
+impl EntryKey for u64 {
+    fn encode(&self) -> String {
+        format!("{:020}", self)
+    }
+}
+
+impl EntryKey for (u64, String) {
+    fn encode(&self) -> String {
+        format!("{}{}{}", self.0.encode(), KEY_SEPARATOR, self.1)
+    }
+}
+
+impl EntryKey for (String, u64) {
+    fn encode(&self) -> String {
+        format!("{}{}{}", self.0, KEY_SEPARATOR, self.1.encode())
+    }
+}
+
+impl EntryKey for (u64, String, u64) {
+    fn encode(&self) -> String {
+        format!("{}{sep}{}{sep}{}", self.0.encode(), self.1, self.2.encode(), sep = KEY_SEPARATOR)
+    }
+}
+
+impl EntryKey for (String, String, String) {
+    fn encode(&self) -> String {
+        format!("{}{sep}{}{sep}{}", self.0, self.1, self.2, sep = KEY_SEPARATOR)
+    }
+}
+
+/// The chain id and token id of a key encoded from `(u64, String)`.
+fn decode_token_key(key: &str) -> eyre::Result<(u64, String)> {
+    let (chain_id, token_id) =
+        key.split_once(KEY_SEPARATOR).ok_or_else(|| eyre::eyre!("Malformed key {}", key))?;
+    Ok((chain_id.parse()?, token_id.to_string()))
+}
+
+/// The `entries` table, read through a connection or a transaction.
+struct Entries<'a>(&'a Connection);
+
+impl Entries<'_> {
+    fn get<K: EntryKey + ?Sized, V: DeserializeOwned>(
+        &self,
+        collection: &str,
+        key: &K,
+    ) -> eyre::Result<Option<V>> {
+        let value: Option<String> = self
+            .0
+            .query_row(
+                "SELECT value FROM entries WHERE collection = ?1 AND key = ?2",
+                params![collection, key.encode()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(value.map(|value| serde_json::from_str(&value)).transpose()?)
+    }
+
+    fn contains<K: EntryKey + ?Sized>(&self, collection: &str, key: &K) -> eyre::Result<bool> {
+        Ok(self.get::<K, serde_json::Value>(collection, key)?.is_some())
+    }
+
+    fn put<K: EntryKey + ?Sized, V: Serialize>(
+        &self,
+        collection: &str,
+        key: &K,
+        value: &V,
+    ) -> eyre::Result<()> {
+        self.0.execute(
+            "INSERT OR REPLACE INTO entries (collection, key, value) VALUES (?1, ?2, ?3)",
+            params![collection, key.encode(), serde_json::to_string(value)?],
+        )?;
+        Ok(())
+    }
+
+    /// Returns false, leaving the row as it was, if the key is already present.
+    fn insert_new<K: EntryKey + ?Sized, V: Serialize>(
+        &self,
+        collection: &str,
+        key: &K,
+        value: &V,
+    ) -> eyre::Result<bool> {
+        let inserted = self.0.execute(
+            "INSERT OR IGNORE INTO entries (collection, key, value) VALUES (?1, ?2, ?3)",
+            params![collection, key.encode(), serde_json::to_string(value)?],
+        )?;
+        Ok(inserted == 1)
+    }
+
+    /// Whether the key was present.
+    fn remove<K: EntryKey + ?Sized>(&self, collection: &str, key: &K) -> eyre::Result<bool> {
+        let removed = self.0.execute(
+            "DELETE FROM entries WHERE collection = ?1 AND key = ?2",
+            params![collection, key.encode()],
+        )?;
+        Ok(removed == 1)
+    }
+
+    fn take<K: EntryKey + ?Sized, V: DeserializeOwned>(
+        &self,
+        collection: &str,
+        key: &K,
+    ) -> eyre::Result<Option<V>> {
+        let value = self.get(collection, key)?;
+        if value.is_some() {
+            self.remove(collection, key)?;
+        }
+        Ok(value)
+    }
+
+    /// Every row of a collection with its encoded key, in key order.
+    fn scan<V: DeserializeOwned>(&self, collection: &str) -> eyre::Result<Vec<(String, V)>> {
+        let mut statement = self
+            .0
+            .prepare_cached("SELECT key, value FROM entries WHERE collection = ?1 ORDER BY key")?;
+        let rows = statement.query_map(params![collection], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut entries = Vec::new();
+        for row in rows {
+            let (key, value) = row?;
+            entries.push((key, serde_json::from_str(&value)?));
+        }
+        Ok(entries)
+    }
+
+    /// At most `limit` rows of a collection, in key order, with keys after `after` and, if
+    /// given, before `before`.
+    fn scan_range<V: DeserializeOwned>(
+        &self,
+        collection: &str,
+        after: &str,
+        before: Option<&str>,
+        limit: usize,
+    ) -> eyre::Result<Vec<(String, V)>> {
+        let mut statement = self.0.prepare_cached(
+            "SELECT key, value FROM entries WHERE collection = ?1 AND key > ?2 \
+             AND (?3 IS NULL OR key < ?3) ORDER BY key LIMIT ?4",
+        )?;
+        let rows = statement
+            .query_map(params![collection, after, before, limit as i64], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?;
+        let mut entries = Vec::new();
+        for row in rows {
+            let (key, value) = row?;
+            entries.push((key, serde_json::from_str(&value)?));
+        }
+        Ok(entries)
+    }
+
+    /// At most `limit` entries keyed by `(chain_id, token_id)` on one chain, in numeric token id
+    /// order, after the token id `after`.
+    fn scan_tokens<V: DeserializeOwned>(
+        &self,
+        collection: &str,
+        chain_id: u64,
+        after: &str,
+        limit: usize,
+    ) -> eyre::Result<Vec<(String, V)>> {
+        // Every key of the chain shares its prefix, so ordering by length first orders the token
+        // ids numerically.
+        let mut statement = self.0.prepare_cached(
+            "SELECT key, value FROM entries WHERE collection = ?1 AND key > ?2 AND key < ?3 \
+             AND (length(key), key) > (length(?4), ?4) ORDER BY length(key), key LIMIT ?5",
+        )?;
+        let prefix = (chain_id, String::new()).encode();
+        // Every key of the chain sorts before the next chain's bare, zero padded id.
+        let before = (chain_id + 1).encode();
+        let after = (chain_id, after.to_string()).encode();
+        let rows = statement
+            .query_map(params![collection, prefix, before, after, limit as i64], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?;
+        let mut entries = Vec::new();
+        for row in rows {
+            let (key, value) = row?;
+            entries.push((key, serde_json::from_str(&value)?));
+        }
+        Ok(entries)
+    }
+
+    fn values<V: DeserializeOwned>(&self, collection: &str) -> eyre::Result<Vec<V>> {
+        Ok(self.scan(collection)?.into_iter().map(|(_, value)| value).collect())
+    }
+
+    fn count(&self, collection: &str) -> eyre::Result<u64> {
+        let count: u64 = self.0.query_row(
+            "SELECT COUNT(*) FROM entries WHERE collection = ?1",
+            params![collection],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    /// Adds a value after every other one in a collection that is only appended to.
+    fn append<V: Serialize>(&self, collection: &str, value: &V) -> eyre::Result<()> {
+        let position = self.count(collection)?;
+        self.put(collection, &position, value)
+    }
+}
+
+/// Moves the block cursor and processed events written before they were kept per chain to Base,
+/// the default chain, as the in-memory backend does when migrating an older image.
+fn key_indexer_rows_by_chain(entries: &Entries) -> eyre::Result<()> {
+    if let Some(cursor) = entries.take::<_, BlockCursor>("last_processed_block", "")? {
+        entries.put("last_processed_block", &DEFAULT_CHAIN_ID, &cursor)?;
+    }
+    for (key, _) in entries.scan::<serde_json::Value>("processed_events")? {
+        if let Some((tx_hash, log_index)) = key.split_once(KEY_SEPARATOR) {
+            if !log_index.contains(KEY_SEPARATOR) {
+                entries.remove("processed_events", key.as_str())?;
+                let key = (DEFAULT_CHAIN_ID, tx_hash.to_string(), log_index.parse::<u64>()?);
+                entries.put("processed_events", &key, &())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Recomputes the read models from the collections they are derived from, as the in-memory
+/// backend does on load.
+fn refresh_read_models(entries: &Entries) -> eyre::Result<()> {
+    entries.0.execute(
+        "DELETE FROM entries WHERE collection IN ('token_nfts', 'collection_stats')",
+        [],
+    )?;
+    let mut stats: BTreeMap<u64, CollectionStats> = BTreeMap::new();
+    for (nft_id, nft) in entries.scan::<NFT>("nfts")? {
+        stats.entry(nft.chain_id).or_default().minted += 1;
+        entries.put("token_nfts", &(nft.chain_id, nft.token_id), &nft_id)?;
+    }
+    for pending_nft in entries.values::<PendingNFT>("pending_nfts")? {
+        stats.entry(pending_nft.chain_id).or_default().pending_mints += 1;
+    }
+    for (key, record) in entries.scan::<ModerationRecord>("moderation_records")? {
+        stats.entry(decode_token_key(&key)?.0).or_default().count_decision(record.safe, true);
+    }
+    for (key, _) in entries.scan::<String>("tweets")? {
+        stats.entry(decode_token_key(&key)?.0).or_default().tweets += 1;
+    }
+    for (chain_id, stats) in &stats {
+        entries.put("collection_stats", chain_id, stats)?;
+    }
+    Ok(())
+}
+
+fn update_stats(
+    entries: &Entries,
+    chain_id: u64,
+    f: impl FnOnce(&mut CollectionStats),
+) -> eyre::Result<()> {
+    let mut stats = entries.get("collection_stats", &chain_id)?.unwrap_or_default();
+    f(&mut stats);
+    entries.put("collection_stats", &chain_id, &stats)
+}
+
+fn put_pending_nft(entries: &Entries, tx_hash: &str, pending_nft: &PendingNFT) -> eyre::Result<()> {
+    update_stats(entries, pending_nft.chain_id, |stats| stats.pending_mints += 1)?;
+    if let Some(replaced) = entries.get::<_, PendingNFT>("pending_nfts", tx_hash)? {
+        update_stats(entries, replaced.chain_id, |stats| {
+            stats.pending_mints = stats.pending_mints.saturating_sub(1)
+        })?;
+    }
+    entries.put("pending_nfts", tx_hash, pending_nft)
+}
+
+/// The service database in SQLite, for local development and small deployments. It behaves like
+/// [`super::in_memory::InMemoryDB`], but every change is written to the database file as it is
+/// made, so a crash loses nothing. Inside the enclave the file lives on the encrypted
+/// `/root/save/` mount.
+pub struct SqliteDB {
+    // A connection cannot be shared between threads; the service only ever reaches it through
+    // its `TrackedMutex`, so this lock is never contended.
+    conn: Mutex<Connection>,
+}
+
+impl SqliteDB {
+    /// Opens the database file at `path`, creating it if it does not exist.
+    pub fn open(path: &str) -> eyre::Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    /// A database that is never written to disk, unless serialized.
+    pub fn open_in_memory() -> eyre::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> eyre::Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        let mut db = Self { conn: Mutex::new(conn) };
+        db.write(|entries| {
+            // Indexes users registered before the address index existed.
+            for (address, _) in entries.scan::<serde_json::Value>("users")? {
+                entries.insert_new("address_index", &address_key(&address), &address)?;
+            }
+            key_indexer_rows_by_chain(entries)?;
+            refresh_read_models(entries)
+        })?;
+        Ok(db)
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&Entries) -> eyre::Result<T>) -> eyre::Result<T> {
+        let conn = self.conn.lock().map_err(|_| eyre::eyre!("SQLite connection poisoned"))?;
+        f(&Entries(&conn))
+    }
+
+    /// Runs `f` in a transaction, committed only if `f` succeeds.
+    fn write<T>(&mut self, f: impl FnOnce(&Entries) -> eyre::Result<T>) -> eyre::Result<T> {
+        let conn = self.conn.get_mut().map_err(|_| eyre::eyre!("SQLite connection poisoned"))?;
+        let tx = conn.transaction()?;
+        let result = f(&Entries(&tx))?;
+        tx.commit()?;
+        Ok(result)
+    }
+}
+
+impl TeleportDB for SqliteDB {
+    fn add_user(&mut self, address: String, user: User) -> eyre::Result<()> {
+        self.write(|entries| {
+            entries.put("users", &address, &user)?;
+            entries.put("address_index", &address_key(&address), &address)?;
+            if let Some(x_id) = &user.x_id {
+                entries.put("x_id_to_address", x_id, &address)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn count_users(&self) -> eyre::Result<usize> {
+        self.read(|entries| Ok(entries.count("users")? as usize))
+    }
+
+    fn get_user_by_address(&self, address: String) -> eyre::Result<User> {
+        self.read(|entries| {
+            if let Some(user) = entries.get("users", &address)? {
+                return Ok(user);
+            }
+            let stored: String = entries
+                .get("address_index", &address_key(&address))?
+                .ok_or_else(|| eyre::eyre!("User not found"))?;
+            entries.get("users", &stored)?.ok_or_else(|| eyre::eyre!("User not found"))
+        })
+    }
+
+    fn get_user_by_x_id(&self, x_id: String) -> eyre::Result<User> {
+        self.read(|entries| {
+            let address: String = entries
+                .get("x_id_to_address", &x_id)?
+                .ok_or_else(|| eyre::eyre!("User address not found for x_id"))?;
+            entries.get("users", &address)?.ok_or_else(|| eyre::eyre!("User not found"))
+        })
+    }
+
+    fn count_creator_nfts(&self, address: String) -> eyre::Result<usize> {
+        self.read(|entries| {
+            let nfts: Vec<NFT> = entries.values("nfts")?;
+            let pending_nfts: Vec<PendingNFT> = entries.values("pending_nfts")?;
+            let minted = nfts.iter().filter(|nft| nft.address == address).count();
+            let pending = pending_nfts.iter().filter(|nft| nft.address == address).count();
+            Ok(minted + pending)
+        })
+    }
+
+    fn unlink_x_account(&mut self, address: String) -> eyre::Result<String> {
+        self.write(|entries| {
+            let mut user: User =
+                entries.get("users", &address)?.ok_or_else(|| eyre::eyre!("User not found"))?;
+            let x_id = user.x_id.take().ok_or_else(|| eyre::eyre!("No X account linked"))?;
+            user.access_tokens = None;
+            user.unlinked_x_id = Some(x_id.clone());
+            entries.put("users", &address, &user)?;
+            entries.remove("x_id_to_address", &x_id)?;
+            for (session_id, session) in entries.scan::<Session>("sessions")? {
+                if session.x_id == x_id {
+                    entries.remove("sessions", &session_id)?;
+                }
+            }
+            entries.remove("email_challenges", &x_id)?;
+            Ok(x_id)
+        })
+    }
+
+    fn migrate_x_account(&mut self, from: String, to: String) -> eyre::Result<()> {
+        if from == to {
+            return Ok(());
+        }
+        self.write(|entries| {
+            if let Some(timezone) = entries.take::<_, String>("timezones", &from)? {
+                entries.put("timezones", &to, &timezone)?;
+            }
+            if let Some(recovery) = entries.take::<_, RecoveryEmail>("recovery_emails", &from)? {
+                entries.put("recovery_emails", &to, &recovery)?;
+            }
+            if let Some(webhook) = entries.take::<_, CreatorWebhook>("creator_webhooks", &from)? {
+                entries.put("creator_webhooks", &to, &webhook)?;
+            }
+            if entries.remove("burn_on_redeem", &from)? {
+                entries.put("burn_on_redeem", &to, &())?;
+            }
+            Ok(())
+        })
+    }
+
+    fn serialize(&self) -> eyre::Result<Vec<u8>> {
+        self.read(|entries| Ok(entries.0.serialize(DatabaseName::Main)?.to_vec()))
+    }
+
+    /// Every row of `entries`, bincode encoded, so a restore never has to write a plaintext
+    /// database file to open it.
+    fn export_snapshot(&self) -> eyre::Result<Vec<u8>> {
+        self.read(|entries| {
+            let mut statement = entries.0.prepare("SELECT collection, key, value FROM entries")?;
+            let rows = statement
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                .collect::<Result<Vec<(String, String, String)>, _>>()?;
+            Ok(bincode::serialize(&rows)?)
+        })
+    }
+
+    fn import_snapshot(&mut self, snapshot: &Image) -> eyre::Result<()> {
+        // Rows carry their values as JSON, so an export of any schema version reads the same.
+        let rows: Vec<(String, String, String)> = bincode::deserialize(&snapshot.bytes)?;
+        self.write(|entries| {
+            entries.0.execute("DELETE FROM entries", [])?;
+            for (collection, key, value) in &rows {
+                entries.0.execute(
+                    "INSERT INTO entries (collection, key, value) VALUES (?1, ?2, ?3)",
+                    params![collection, key, value],
+                )?;
+            }
+            key_indexer_rows_by_chain(entries)?;
+            refresh_read_models(entries)
+        })
+    }
+
+    fn add_pending_nft(&mut self, tx_hash: String, pending_nft: PendingNFT) -> eyre::Result<()> {
+        self.write(|entries| put_pending_nft(entries, &tx_hash, &pending_nft))
+    }
+
+    fn promote_pending_nft(&mut self, tx_hash: String, token_id: String) -> eyre::Result<String> {
+        self.write(|entries| {
+            let pending_nft: PendingNFT = entries
+                .take("pending_nfts", &tx_hash)?
+                .ok_or_else(|| eyre::eyre!("Pending NFT not found"))?;
+            update_stats(entries, pending_nft.chain_id, |stats| {
+                stats.pending_mints = stats.pending_mints.saturating_sub(1)
+            })?;
+            entries.remove("mint_confirmations", &tx_hash)?;
+            let key = (pending_nft.chain_id, token_id.clone());
+            if let Some(snapshot) = entries
+                .take::<_, PolicySnapshot>("pending_policy_snapshots", &pending_nft.nft_id)?
+            {
+                entries.put("policy_snapshots", &key, &snapshot)?;
+            }
+            let nft =
+                NFT { address: pending_nft.address, token_id, chain_id: pending_nft.chain_id };
+            entries.put("policy_hashes", &key, &pending_nft.policy_hash)?;
+            entries.put("mint_txs", &key, &tx_hash)?;
+            entries.put("token_nfts", &key, &pending_nft.nft_id)?;
+            update_stats(entries, pending_nft.chain_id, |stats| stats.minted += 1)?;
+            if let Some(replaced) = entries.get::<_, NFT>("nfts", &pending_nft.nft_id)? {
+                update_stats(entries, replaced.chain_id, |stats| {
+                    stats.minted = stats.minted.saturating_sub(1)
+                })?;
+            }
+            entries.put("nfts", &pending_nft.nft_id, &nft)?;
+            Ok(pending_nft.nft_id)
+        })
+    }
+
+    fn get_pending_nft(&self, tx_hash: String) -> eyre::Result<Option<PendingNFT>> {
+        self.read(|entries| entries.get("pending_nfts", &tx_hash))
+    }
+
+    fn set_pending_policy_snapshot(
+        &mut self,
+        nft_id: String,
+        snapshot: PolicySnapshot,
+    ) -> eyre::Result<()> {
+        self.write(|entries| entries.put("pending_policy_snapshots", &nft_id, &snapshot))
+    }
+
+    fn get_policy_snapshot(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> eyre::Result<Option<PolicySnapshot>> {
+        self.read(|entries| entries.get("policy_snapshots", &(chain_id, token_id)))
+    }
+
+    fn get_pending_nft_by_id(&self, nft_id: String) -> eyre::Result<Option<PendingNFT>> {
+        self.read(|entries| {
+            let pending_nfts: Vec<PendingNFT> = entries.values("pending_nfts")?;
+            Ok(pending_nfts.into_iter().find(|pending_nft| pending_nft.nft_id == nft_id))
+        })
+    }
+
+    fn set_mint_confirmation(
+        &mut self,
+        tx_hash: String,
+        confirmation: MintConfirmation,
+    ) -> eyre::Result<()> {
+        self.write(|entries| entries.put("mint_confirmations", &tx_hash, &confirmation))
+    }
+
+    fn get_mint_confirmations(
+        &self,
+        chain_id: u64,
+    ) -> eyre::Result<Vec<(String, MintConfirmation)>> {
+        self.read(|entries| {
+            let confirmations = entries.scan::<MintConfirmation>("mint_confirmations")?;
+            Ok(confirmations
+                .into_iter()
+                .filter(|(_, confirmation)| confirmation.chain_id == chain_id)
+                .collect())
+        })
+    }
+
+    fn get_mint_confirmation_by_nft_id(
+        &self,
+        nft_id: String,
+    ) -> eyre::Result<Option<MintConfirmation>> {
+        self.read(|entries| {
+            let confirmations: Vec<MintConfirmation> = entries.values("mint_confirmations")?;
+            Ok(confirmations.into_iter().find(|confirmation| confirmation.nft_id == nft_id))
+        })
+    }
+
+    fn remove_mint_confirmation(&mut self, tx_hash: String) -> eyre::Result<bool> {
+        self.write(|entries| entries.remove("mint_confirmations", &tx_hash))
+    }
+
+    fn get_policy_hash(&self, chain_id: u64, token_id: String) -> eyre::Result<Option<String>> {
+        self.read(|entries| entries.get("policy_hashes", &(chain_id, token_id)))
+    }
+
+    fn get_mint_tx(&self, chain_id: u64, token_id: String) -> eyre::Result<Option<String>> {
+        self.read(|entries| entries.get("mint_txs", &(chain_id, token_id)))
+    }
+
+    fn alias_pending_nft(
+        &mut self,
+        tx_hash: String,
+        replacement_tx_hash: String,
+    ) -> eyre::Result<()> {
+        self.write(|entries| {
+            if let Some(pending_nft) = entries.get::<_, PendingNFT>("pending_nfts", &tx_hash)? {
+                put_pending_nft(entries, &replacement_tx_hash, &pending_nft)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn get_nft(&self, nft_id: String) -> eyre::Result<NFT> {
+        self.read(|entries| {
+            entries.get("nfts", &nft_id)?.ok_or_else(|| eyre::eyre!("NFT not found"))
+        })
+    }
+
+    fn get_nft_by_token_id(&self, chain_id: u64, token_id: String) -> eyre::Result<NFT> {
+        let nft_id = self.get_nft_id_by_token_id(chain_id, token_id)?;
+        self.get_nft(nft_id)
+    }
+
+    fn get_nft_id_by_token_id(&self, chain_id: u64, token_id: String) -> eyre::Result<String> {
+        self.read(|entries| {
+            entries
+                .get("token_nfts", &(chain_id, token_id))?
+                .ok_or_else(|| eyre::eyre!("NFT not found for token id"))
+        })
+    }
+
+    fn add_tweet(&mut self, chain_id: u64, token_id: String, tweet_id: String) -> eyre::Result<()> {
+        self.write(|entries| {
+            let key = (chain_id, token_id);
+            if !entries.contains("tweets", &key)? {
+                update_stats(entries, chain_id, |stats| stats.tweets += 1)?;
+            }
+            entries.put("tweets", &key, &tweet_id)
+        })
+    }
+
+    fn get_tweet(&self, chain_id: u64, token_id: String) -> eyre::Result<String> {
+        self.read(|entries| {
+            entries
+                .get("tweets", &(chain_id, token_id))?
+                .ok_or_else(|| eyre::eyre!("Tweet not found"))
+        })
+    }
+
+    fn add_session(&mut self, session: Session) -> eyre::Result<String> {
+        let session_id: i128 = rand::random();
+        let session_id = session_id.to_string();
+        self.write(|entries| entries.put("sessions", &session_id, &session))?;
+        Ok(session_id)
+    }
+
+    fn get_session(&self, session_id: String) -> eyre::Result<Session> {
+        self.read(|entries| {
+            entries.get("sessions", &session_id)?.ok_or_else(|| eyre::eyre!("Session not found"))
+        })
+    }
+
+    fn add_moderation_record(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        record: ModerationRecord,
+    ) -> eyre::Result<()> {
+        self.write(|entries| {
+            let key = (chain_id, token_id);
+            let replaced: Option<ModerationRecord> = entries.get("moderation_records", &key)?;
+            update_stats(entries, chain_id, |stats| {
+                stats.count_decision(record.safe, true);
+                if let Some(replaced) = replaced {
+                    stats.count_decision(replaced.safe, false);
+                }
+            })?;
+            entries.put("moderation_records", &key, &record)
+        })
+    }
+
+    fn get_moderation_record(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> eyre::Result<ModerationRecord> {
+        self.read(|entries| {
+            entries
+                .get("moderation_records", &(chain_id, token_id))?
+                .ok_or_else(|| eyre::eyre!("Moderation record not found"))
+        })
+    }
+
+    fn set_policy_precheck_threshold(
+        &mut self,
+        policy: String,
+        threshold: f32,
+    ) -> eyre::Result<()> {
+        self.write(|entries| entries.put("policy_precheck_thresholds", &policy, &threshold))
+    }
+
+    fn get_policy_precheck_threshold(&self, policy: String) -> eyre::Result<f32> {
+        self.read(|entries| {
+            entries
+                .get("policy_precheck_thresholds", &policy)?
+                .ok_or_else(|| eyre::eyre!("Precheck threshold not set for policy"))
+        })
+    }
+
+    fn set_policy_mention_rule(&mut self, policy: String, rule: MentionRule) -> eyre::Result<()> {
+        self.write(|entries| entries.put("policy_mention_rules", &policy, &rule))
+    }
+
+    fn get_policy_mention_rule(&self, policy: String) -> eyre::Result<Option<MentionRule>> {
+        self.read(|entries| entries.get("policy_mention_rules", &policy))
+    }
+
+    fn approve_mentions(&mut self, chain_id: u64, token_id: String) -> eyre::Result<()> {
+        self.write(|entries| entries.put("approved_mentions", &(chain_id, token_id), &()))
+    }
+
+    fn are_mentions_approved(&self, chain_id: u64, token_id: String) -> eyre::Result<bool> {
+        self.read(|entries| entries.contains("approved_mentions", &(chain_id, token_id)))
+    }
+
+    fn approve_quorum_dispute(&mut self, chain_id: u64, token_id: String) -> eyre::Result<()> {
+        self.write(|entries| entries.put("approved_quorum_disputes", &(chain_id, token_id), &()))
+    }
+
+    fn is_quorum_dispute_approved(&self, chain_id: u64, token_id: String) -> eyre::Result<bool> {
+        self.read(|entries| entries.contains("approved_quorum_disputes", &(chain_id, token_id)))
+    }
+
+    fn set_policy_license_rule(&mut self, policy: String, rule: LicenseRule) -> eyre::Result<()> {
+        self.write(|entries| entries.put("policy_license_rules", &policy, &rule))
+    }
+
+    fn get_policy_license_rule(&self, policy: String) -> eyre::Result<Option<LicenseRule>> {
+        self.read(|entries| entries.get("policy_license_rules", &policy))
+    }
+
+    fn set_policy_moderation_tier(
+        &mut self,
+        policy: String,
+        tier: ModerationTier,
+    ) -> eyre::Result<()> {
+        self.write(|entries| entries.put("policy_moderation_tiers", &policy, &tier))
+    }
+
+    fn get_policy_moderation_tier(&self, policy: String) -> eyre::Result<ModerationTier> {
+        self.read(|entries| {
+            Ok(entries.get("policy_moderation_tiers", &policy)?.unwrap_or_default())
+        })
+    }
+
+    fn set_redemption_license(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        license: ContentLicense,
+    ) -> eyre::Result<()> {
+        self.write(|entries| entries.put("redemption_licenses", &(chain_id, token_id), &license))
+    }
+
+    fn get_redemption_license(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> eyre::Result<Option<ContentLicense>> {
+        self.read(|entries| entries.get("redemption_licenses", &(chain_id, token_id)))
+    }
+
+    fn set_last_processed_block(
+        &mut self,
+        chain_id: u64,
+        block_number: u64,
+        log_index: u64,
+    ) -> eyre::Result<()> {
+        // Concurrent handlers may finish out of order; never move the cursor backwards.
+        let cursor = BlockCursor { block_number, log_index };
+        self.write(|entries| {
+            let last: Option<BlockCursor> = entries.get("last_processed_block", &chain_id)?;
+            if last.map_or(true, |last| cursor > last) {
+                entries.put("last_processed_block", &chain_id, &cursor)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn get_last_processed_block(&self, chain_id: u64) -> eyre::Result<BlockCursor> {
+        self.read(|entries| {
+            entries
+                .get("last_processed_block", &chain_id)?
+                .ok_or_else(|| eyre::eyre!("Last processed block not set for chain {}", chain_id))
+        })
+    }
+
+    fn mark_event_processed(
+        &mut self,
+        chain_id: u64,
+        tx_hash: String,
+        log_index: u64,
+    ) -> eyre::Result<bool> {
+        self.write(|entries| {
+            entries.insert_new("processed_events", &(chain_id, tx_hash, log_index), &())
+        })
+    }
+
+    fn is_event_processed(
+        &self,
+        chain_id: u64,
+        tx_hash: String,
+        log_index: u64,
+    ) -> eyre::Result<bool> {
+        self.read(|entries| entries.contains("processed_events", &(chain_id, tx_hash, log_index)))
+    }
+
+    fn upsert_failed_event(&mut self, event_id: String, event: FailedEvent) -> eyre::Result<()> {
+        self.write(|entries| entries.put("failed_events", &event_id, &event))
+    }
+
+    fn get_due_failed_events(
+        &self,
+        chain_id: u64,
+        now: i64,
+    ) -> eyre::Result<Vec<(String, FailedEvent)>> {
+        self.read(|entries| {
+            let events = entries.scan::<FailedEvent>("failed_events")?;
+            Ok(events
+                .into_iter()
+                .filter(|(_, event)| {
+                    event.chain_id == chain_id && event.next_attempt_at.is_some_and(|at| at <= now)
+                })
+                .collect())
+        })
+    }
+
+    fn remove_failed_event(&mut self, event_id: String) -> eyre::Result<()> {
+        self.write(|entries| entries.remove("failed_events", &event_id).map(|_| ()))
+    }
+
+    fn add_redemption_link(&mut self, link_id: String, link: RedemptionLink) -> eyre::Result<()> {
+        self.write(|entries| entries.put("redemption_links", &link_id, &link))
+    }
+
+    fn get_redemption_link(&self, link_id: String) -> eyre::Result<RedemptionLink> {
+        self.read(|entries| {
+            entries
+                .get("redemption_links", &link_id)?
+                .ok_or_else(|| eyre::eyre!("Redemption link not found"))
+        })
+    }
+
+    fn count_open_redemption_links(&self, session_id: String, now: i64) -> eyre::Result<usize> {
+        self.read(|entries| {
+            let links: Vec<RedemptionLink> = entries.values("redemption_links")?;
+            Ok(links
+                .iter()
+                .filter(|link| link.session_id == session_id && !link.used && link.expires_at > now)
+                .count())
+        })
+    }
+
+    fn consume_redemption_link(
+        &mut self,
+        link_id: String,
+        now: i64,
+    ) -> eyre::Result<RedemptionLink> {
+        self.write(|entries| {
+            let mut link: RedemptionLink = entries
+                .get("redemption_links", &link_id)?
+                .ok_or_else(|| eyre::eyre!("Redemption link not found"))?;
+            if link.used {
+                eyre::bail!("Redemption link already used");
+            }
+            if link.expires_at <= now {
+                eyre::bail!("Redemption link expired");
+            }
+            link.used = true;
+            entries.put("redemption_links", &link_id, &link)?;
+            Ok(link)
+        })
+    }
+
+    fn set_recovery_email(&mut self, x_id: String, recovery: RecoveryEmail) -> eyre::Result<()> {
+        self.write(|entries| entries.put("recovery_emails", &x_id, &recovery))
+    }
+
+    fn get_recovery_email(&self, x_id: String) -> eyre::Result<RecoveryEmail> {
+        self.read(|entries| {
+            entries
+                .get("recovery_emails", &x_id)?
+                .ok_or_else(|| eyre::eyre!("Recovery email not set"))
+        })
+    }
+
+    fn set_timezone(&mut self, x_id: String, timezone: String) -> eyre::Result<()> {
+        self.write(|entries| entries.put("timezones", &x_id, &timezone))
+    }
+
+    fn get_timezone(&self, x_id: String) -> eyre::Result<String> {
+        self.read(|entries| {
+            entries.get("timezones", &x_id)?.ok_or_else(|| eyre::eyre!("Timezone not set"))
+        })
+    }
+
+    fn set_creator_webhook(&mut self, x_id: String, webhook: CreatorWebhook) -> eyre::Result<()> {
+        self.write(|entries| entries.put("creator_webhooks", &x_id, &webhook))
+    }
+
+    fn get_creator_webhook(&self, x_id: String) -> eyre::Result<Option<CreatorWebhook>> {
+        self.read(|entries| entries.get("creator_webhooks", &x_id))
+    }
+
+    fn remove_creator_webhook(&mut self, x_id: String) -> eyre::Result<bool> {
+        self.write(|entries| entries.remove("creator_webhooks", &x_id))
+    }
+
+    fn set_email_challenge(
+        &mut self,
+        x_id: String,
+        mut challenge: EmailChallenge,
+        now: i64,
+    ) -> eyre::Result<bool> {
+        self.write(|entries| {
+            let running = entries
+                .get::<_, EmailChallenge>("email_challenges", &x_id)?
+                .filter(|running| running.expires_at > now);
+            if let Some(running) = running {
+                if running.attempts >= MAX_EMAIL_CHALLENGE_ATTEMPTS {
+                    return Ok(false);
+                }
+                challenge.attempts = running.attempts;
+            }
+            entries.put("email_challenges", &x_id, &challenge)?;
+            Ok(true)
+        })
+    }
+
+    fn verify_email_challenge(
+        &mut self,
+        x_id: String,
+        code: String,
+        now: i64,
+    ) -> eyre::Result<EmailChallenge> {
+        // A wrong code still counts against the challenge, so the outcome is returned from the
+        // transaction rather than rolling it back.
+        self.write(|entries| {
+            let Some(mut challenge) =
+                entries.get::<_, EmailChallenge>("email_challenges", &x_id)?
+            else {
+                return Ok(Err(eyre::eyre!("Email challenge not found")));
+            };
+            if challenge.expires_at <= now {
+                entries.remove("email_challenges", &x_id)?;
+                return Ok(Err(eyre::eyre!("Email challenge expired")));
+            }
+            if challenge.attempts >= MAX_EMAIL_CHALLENGE_ATTEMPTS {
+                return Ok(Err(eyre::eyre!("Email challenge locked after too many wrong codes")));
+            }
+            if !email_code_matches(&challenge.code, &code) {
+                challenge.attempts += 1;
+                entries.put("email_challenges", &x_id, &challenge)?;
+                return Ok(Err(eyre::eyre!("Wrong email confirmation code")));
+            }
+            entries.remove("email_challenges", &x_id)?;
+            Ok(Ok(challenge))
+        })?
+    }
+
+    fn add_tx(&mut self, tx_hash: String, tx: TxRecord) -> eyre::Result<()> {
+        self.write(|entries| entries.put("txs", &tx_hash, &tx))
+    }
+
+    fn get_tx(&self, tx_hash: String) -> eyre::Result<TxRecord> {
+        self.read(|entries| {
+            entries.get("txs", &tx_hash)?.ok_or_else(|| eyre::eyre!("Transaction not found"))
+        })
+    }
+
+    fn get_pending_txs(&self, chain_id: u64) -> eyre::Result<Vec<String>> {
+        self.read(|entries| {
+            let txs = entries.scan::<TxRecord>("txs")?.into_iter().collect();
+            Ok(unresolved_txs(&txs, chain_id))
+        })
+    }
+
+    fn set_tx_mined(
+        &mut self,
+        tx_hash: String,
+        success: bool,
+        block_number: Option<u64>,
+        gas_used: u64,
+    ) -> eyre::Result<()> {
+        self.write(|entries| {
+            let mut tx: TxRecord = entries
+                .get("txs", &tx_hash)?
+                .ok_or_else(|| eyre::eyre!("Transaction not found"))?;
+            tx.status = if success { TxStatus::Confirmed } else { TxStatus::Failed };
+            tx.block_number = block_number;
+            tx.gas_used = Some(gas_used);
+            let mut replacement = tx.replaced_by.take();
+            entries.put("txs", &tx_hash, &tx)?;
+            for _ in 0..MAX_TX_REPLACEMENTS {
+                let Some(hash) = replacement else {
+                    break;
+                };
+                let Some(mut tx) = entries.get::<_, TxRecord>("txs", &hash)? else {
+                    break;
+                };
+                tx.status = TxStatus::Replaced;
+                replacement = tx.replaced_by.replace(tx_hash.clone());
+                entries.put("txs", &hash, &tx)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn replace_tx(&mut self, tx_hash: String, replacement_tx_hash: String) -> eyre::Result<()> {
+        self.write(|entries| {
+            let Some(mut tx) = entries.get::<_, TxRecord>("txs", &tx_hash)? else {
+                return Ok(());
+            };
+            tx.status = TxStatus::Replaced;
+            tx.replaced_by = Some(replacement_tx_hash.clone());
+            entries.put("txs", &tx_hash, &tx)?;
+            let replacement = TxRecord { status: TxStatus::Pending, replaced_by: None, ..tx };
+            entries.put("txs", &replacement_tx_hash, &replacement)
+        })
+    }
+
+    fn cancel_tx(
+        &mut self,
+        tx_hash: String,
+        cancel_tx_hash: String,
+        submitted_at: i64,
+    ) -> eyre::Result<()> {
+        self.write(|entries| {
+            let mut tx: TxRecord = entries
+                .get("txs", &tx_hash)?
+                .ok_or_else(|| eyre::eyre!("Transaction not found"))?;
+            tx.status = TxStatus::Cancelled;
+            tx.replaced_by = Some(cancel_tx_hash.clone());
+            entries.put("txs", &tx_hash, &tx)?;
+            let cancellation = TxRecord {
+                kind: "cancel".to_string(),
+                status: TxStatus::Pending,
+                submitted_at,
+                replaced_by: None,
+                ..tx
+            };
+            entries.put("txs", &cancel_tx_hash, &cancellation)
+        })
+    }
+
+    fn get_failed_event(&self, event_id: String) -> eyre::Result<FailedEvent> {
+        self.read(|entries| {
+            entries
+                .get("failed_events", &event_id)?
+                .ok_or_else(|| eyre::eyre!("Failed event not found"))
+        })
+    }
+
+    fn add_pending_approval(
+        &mut self,
+        approval_id: String,
+        approval: PendingApproval,
+    ) -> eyre::Result<()> {
+        self.write(|entries| entries.put("pending_approvals", &approval_id, &approval))
+    }
+
+    fn get_pending_approvals(&self) -> eyre::Result<Vec<(String, PendingApproval)>> {
+        self.read(|entries| entries.scan("pending_approvals"))
+    }
+
+    fn remove_pending_approval(&mut self, approval_id: String) -> eyre::Result<PendingApproval> {
+        self.write(|entries| {
+            entries
+                .take("pending_approvals", &approval_id)?
+                .ok_or_else(|| eyre::eyre!("Pending approval not found"))
+        })
+    }
+
+    fn prune_pending_approvals(&mut self, now: i64) -> eyre::Result<usize> {
+        self.write(|entries| {
+            let approvals = entries.scan::<PendingApproval>("pending_approvals")?;
+            let mut pruned = 0;
+            for (approval_id, approval) in approvals {
+                if approval.expires_at <= now {
+                    entries.remove("pending_approvals", &approval_id)?;
+                    pruned += 1;
+                }
+            }
+            Ok(pruned)
+        })
+    }
+
+    fn add_admin_audit_entry(&mut self, entry: AdminAuditEntry) -> eyre::Result<()> {
+        self.write(|entries| entries.append("admin_audit_log", &entry))
+    }
+
+    fn get_admin_audit_log(&self, limit: usize) -> eyre::Result<Vec<AdminAuditEntry>> {
+        self.read(|entries| {
+            let log: Vec<AdminAuditEntry> = entries.values("admin_audit_log")?;
+            Ok(log.into_iter().rev().take(limit).collect())
+        })
+    }
+
+    fn add_abuse_report(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        report: AbuseReport,
+    ) -> eyre::Result<Option<usize>> {
+        let key = (chain_id, token_id);
+        self.write(|entries| {
+            let mut reports: Vec<AbuseReport> =
+                entries.get("abuse_reports", &key)?.unwrap_or_default();
+            if reports.iter().any(|existing| existing.reporter == report.reporter) {
+                return Ok(None);
+            }
+            reports.push(report);
+            entries.put("abuse_reports", &key, &reports)?;
+            Ok(Some(reports.len()))
+        })
+    }
+
+    fn get_abuse_reports(&self) -> eyre::Result<Vec<((u64, String), Vec<AbuseReport>)>> {
+        self.read(|entries| {
+            entries
+                .scan("abuse_reports")?
+                .into_iter()
+                .map(|(key, reports)| Ok((decode_token_key(&key)?, reports)))
+                .collect()
+        })
+    }
+
+    fn add_creator_strike(&mut self, x_id: String) -> eyre::Result<u32> {
+        self.write(|entries| {
+            let strikes = entries.get::<_, u32>("creator_strikes", &x_id)?.unwrap_or_default() + 1;
+            entries.put("creator_strikes", &x_id, &strikes)?;
+            Ok(strikes)
+        })
+    }
+
+    fn get_creator_strikes(&self, x_id: String) -> eyre::Result<u32> {
+        self.read(|entries| Ok(entries.get("creator_strikes", &x_id)?.unwrap_or_default()))
+    }
+
+    fn clear_creator_strikes(&mut self, x_id: String) -> eyre::Result<()> {
+        self.write(|entries| entries.remove("creator_strikes", &x_id).map(|_| ()))
+    }
+
+    fn set_royalty_info(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        royalty: RoyaltyInfo,
+    ) -> eyre::Result<()> {
+        self.write(|entries| entries.put("royalties", &(chain_id, token_id), &royalty))
+    }
+
+    fn get_royalty_info(&self, chain_id: u64, token_id: String) -> eyre::Result<RoyaltyInfo> {
+        self.read(|entries| {
+            entries
+                .get("royalties", &(chain_id, token_id))?
+                .ok_or_else(|| eyre::eyre!("Royalty not cached"))
+        })
+    }
+
+    fn set_dm_opt_out(&mut self, x_id: String, opt_out: bool) -> eyre::Result<()> {
+        self.write(|entries| {
+            let address: String = entries
+                .get("x_id_to_address", &x_id)?
+                .ok_or_else(|| eyre::eyre!("User address not found for x_id"))?;
+            let mut user: User =
+                entries.get("users", &address)?.ok_or_else(|| eyre::eyre!("User not found"))?;
+            user.dm_opt_out = opt_out;
+            entries.put("users", &address, &user)
+        })
+    }
+
+    fn set_burn_on_redeem(&mut self, x_id: String, enabled: bool) -> eyre::Result<()> {
+        self.write(|entries| {
+            if enabled {
+                entries.put("burn_on_redeem", &x_id, &())
+            } else {
+                entries.remove("burn_on_redeem", &x_id).map(|_| ())
+            }
+        })
+    }
+
+    fn get_burn_on_redeem(&self, x_id: String) -> eyre::Result<bool> {
+        self.read(|entries| entries.contains("burn_on_redeem", &x_id))
+    }
+
+    fn approve_content(
+        &mut self,
+        x_id: String,
+        policy_hash: String,
+        content_hash: String,
+        approved: ApprovedContent,
+    ) -> eyre::Result<()> {
+        let key = (x_id, policy_hash, content_hash);
+        self.write(|entries| entries.put("approved_contents", &key, &approved))
+    }
+
+    fn get_approved_content(
+        &self,
+        x_id: String,
+        policy_hash: String,
+        content_hash: String,
+    ) -> eyre::Result<Option<ApprovedContent>> {
+        let key = (x_id, policy_hash, content_hash);
+        self.read(|entries| entries.get("approved_contents", &key))
+    }
+
+    fn revoke_approved_content(
+        &mut self,
+        x_id: String,
+        policy_hash: String,
+        content_hash: String,
+    ) -> eyre::Result<bool> {
+        let key = (x_id, policy_hash, content_hash);
+        self.write(|entries| entries.remove("approved_contents", &key))
+    }
+
+    fn queue_burn(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        revocation: bool,
+    ) -> eyre::Result<()> {
+        let key = (chain_id, token_id);
+        self.write(|entries| {
+            let mut burn = entries
+                .get("burns", &key)?
+                .unwrap_or(PendingBurn { revocation: false, tx_hash: None });
+            burn.revocation |= revocation;
+            entries.put("burns", &key, &burn)
+        })
+    }
+
+    fn get_pending_burns(&self, chain_id: u64) -> eyre::Result<Vec<(String, PendingBurn)>> {
+        self.read(|entries| {
+            let mut burns = Vec::new();
+            for (key, burn) in entries.scan("burns")? {
+                let (burn_chain_id, token_id) = decode_token_key(&key)?;
+                if burn_chain_id == chain_id {
+                    burns.push((token_id, burn));
+                }
+            }
+            Ok(burns)
+        })
+    }
+
+    fn set_burn_tx(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        tx_hash: String,
+    ) -> eyre::Result<()> {
+        let key = (chain_id, token_id);
+        self.write(|entries| {
+            let mut burn: PendingBurn =
+                entries.get("burns", &key)?.ok_or_else(|| eyre::eyre!("Burn not queued"))?;
+            burn.tx_hash = Some(tx_hash);
+            entries.put("burns", &key, &burn)
+        })
+    }
+
+    fn get_burn(&self, chain_id: u64, token_id: String) -> eyre::Result<Option<PendingBurn>> {
+        self.read(|entries| entries.get("burns", &(chain_id, token_id)))
+    }
+
+    fn remove_burn(&mut self, chain_id: u64, token_id: String) -> eyre::Result<()> {
+        self.write(|entries| entries.remove("burns", &(chain_id, token_id)).map(|_| ()))
+    }
+
+    fn queue_anchor(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        tweet_id: String,
+        content_hash: String,
+    ) -> eyre::Result<()> {
+        let anchor =
+            ContentAnchor { tweet_id, content_hash, tx_hash: None, root: None, proof: Vec::new() };
+        self.write(|entries| entries.put("anchors", &(chain_id, token_id), &anchor))
+    }
+
+    fn get_pending_anchors(&self, chain_id: u64) -> eyre::Result<Vec<(String, ContentAnchor)>> {
+        self.read(|entries| {
+            let mut anchors = Vec::new();
+            for (key, anchor) in entries.scan::<ContentAnchor>("anchors")? {
+                let (anchor_chain_id, token_id) = decode_token_key(&key)?;
+                if anchor_chain_id == chain_id && anchor.tx_hash.is_none() {
+                    anchors.push((token_id, anchor));
+                }
+            }
+            Ok(anchors)
+        })
+    }
+
+    fn get_anchor(&self, chain_id: u64, token_id: String) -> eyre::Result<Option<ContentAnchor>> {
+        self.read(|entries| entries.get("anchors", &(chain_id, token_id)))
+    }
+
+    fn set_anchor(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        anchor: ContentAnchor,
+    ) -> eyre::Result<()> {
+        self.write(|entries| entries.put("anchors", &(chain_id, token_id), &anchor))
+    }
+
+    fn remove_anchor(&mut self, chain_id: u64, token_id: String) -> eyre::Result<()> {
+        self.write(|entries| entries.remove("anchors", &(chain_id, token_id)).map(|_| ()))
+    }
+
+    fn add_top_up(&mut self, chain_id: u64, address: String, at: i64) -> eyre::Result<()> {
+        let key = (chain_id, address);
+        self.write(|entries| {
+            let mut top_ups: Vec<i64> = entries.get("top_ups", &key)?.unwrap_or_default();
+            // Only the last day counts, so older top-ups are dropped.
+            top_ups.retain(|sent_at| at - sent_at < 24 * 60 * 60);
+            top_ups.push(at);
+            entries.put("top_ups", &key, &top_ups)
+        })
+    }
+
+    fn count_top_ups_since(
+        &self,
+        chain_id: u64,
+        address: String,
+        since: i64,
+    ) -> eyre::Result<usize> {
+        self.read(|entries| {
+            let top_ups: Vec<i64> =
+                entries.get("top_ups", &(chain_id, address))?.unwrap_or_default();
+            Ok(top_ups.iter().filter(|sent_at| **sent_at >= since).count())
+        })
+    }
+
+    fn get_user_addresses(&self) -> eyre::Result<Vec<String>> {
+        self.read(|entries| {
+            Ok(entries.scan::<User>("users")?.into_iter().map(|(address, _)| address).collect())
+        })
+    }
+
+    fn add_ledger_entry(&mut self, address: String, entry: LedgerEntry) -> eyre::Result<bool> {
+        self.write(|entries| {
+            let mut ledger: Vec<LedgerEntry> = entries.get("ledger", &address)?.unwrap_or_default();
+            let recorded = ledger.iter().any(|recorded| {
+                recorded.chain_id == entry.chain_id &&
+                    recorded.tx_hash == entry.tx_hash &&
+                    recorded.log_index == entry.log_index
+            });
+            if !recorded {
+                ledger.push(entry);
+                entries.put("ledger", &address, &ledger)?;
+            }
+            Ok(!recorded)
+        })
+    }
+
+    fn get_ledger(&self, address: String) -> eyre::Result<Vec<LedgerEntry>> {
+        self.read(|entries| Ok(entries.get("ledger", &address)?.unwrap_or_default()))
+    }
+
+    fn get_deposit_cursor(&self, chain_id: u64) -> eyre::Result<Option<u64>> {
+        self.read(|entries| entries.get("deposit_cursors", &chain_id))
+    }
+
+    fn set_deposit_cursor(&mut self, chain_id: u64, block_number: u64) -> eyre::Result<()> {
+        self.write(|entries| entries.put("deposit_cursors", &chain_id, &block_number))
+    }
+
+    fn add_mint_payment(&mut self, payment: MintPayment) -> eyre::Result<bool> {
+        let key = (payment.chain_id, payment.payment_tx.clone());
+        self.write(|entries| entries.insert_new("mint_payments", &key, &payment))
+    }
+
+    fn set_payment_mint_txs(
+        &mut self,
+        chain_id: u64,
+        payment_tx: String,
+        mint_txs: Vec<String>,
+    ) -> eyre::Result<()> {
+        let key = (chain_id, payment_tx);
+        self.write(|entries| {
+            let mut payment: MintPayment = entries
+                .get("mint_payments", &key)?
+                .ok_or_else(|| eyre::eyre!("Mint payment not found"))?;
+            payment.mint_txs = mint_txs;
+            entries.put("mint_payments", &key, &payment)
+        })
+    }
+
+    fn remove_mint_payment(&mut self, chain_id: u64, payment_tx: String) -> eyre::Result<()> {
+        self.write(|entries| entries.remove("mint_payments", &(chain_id, payment_tx)).map(|_| ()))
+    }
+
+    fn get_mint_payments(&self) -> eyre::Result<Vec<MintPayment>> {
+        let mut payments: Vec<MintPayment> =
+            self.read(|entries| entries.values("mint_payments"))?;
+        payments.sort_by_key(|payment| payment.paid_at);
+        Ok(payments)
+    }
+
+    fn get_relay_cursor(&self, chain_id: u64) -> eyre::Result<Option<u64>> {
+        self.read(|entries| entries.get("relay_cursors", &chain_id))
+    }
+
+    fn set_relay_cursor(&mut self, chain_id: u64, block_number: u64) -> eyre::Result<()> {
+        self.write(|entries| entries.put("relay_cursors", &chain_id, &block_number))
+    }
+
+    fn get_relayed(&self, log_id: String) -> eyre::Result<Option<String>> {
+        self.read(|entries| entries.get("relayed", &log_id))
+    }
+
+    fn set_relayed(&mut self, log_id: String, tx_hash: String) -> eyre::Result<()> {
+        self.write(|entries| entries.put("relayed", &log_id, &tx_hash))
+    }
+
+    fn set_access_list_entry(&mut self, entry: AccessListEntry) -> eyre::Result<()> {
+        self.write(|entries| entries.put("access_list", &entry.subject, &entry))
+    }
+
+    fn remove_access_list_entry(&mut self, subject: String) -> eyre::Result<bool> {
+        self.write(|entries| entries.remove("access_list", &subject))
+    }
+
+    fn get_access_list_entry(&self, subject: String) -> eyre::Result<Option<AccessListEntry>> {
+        self.read(|entries| entries.get("access_list", &subject))
+    }
+
+    fn get_access_list(&self) -> eyre::Result<Vec<AccessListEntry>> {
+        self.read(|entries| entries.values("access_list"))
+    }
+
+    fn add_inbox_message(&mut self, message: InboxMessage) -> eyre::Result<()> {
+        self.write(|entries| entries.append("inbox", &message))
+    }
+
+    fn get_kill_switches(&self) -> eyre::Result<BTreeMap<String, bool>> {
+        self.read(|entries| Ok(entries.scan("kill_switches")?.into_iter().collect()))
+    }
+
+    fn set_kill_switch(&mut self, side_effect: String, enabled: bool) -> eyre::Result<()> {
+        self.write(|entries| entries.put("kill_switches", &side_effect, &enabled))
+    }
+
+    fn get_contract_status(&self, chain_id: u64, contract: String) -> eyre::Result<ContractStatus> {
+        self.read(|entries| {
+            Ok(entries.get("contract_statuses", &(chain_id, contract))?.unwrap_or_default())
+        })
+    }
+
+    fn set_contract_status(
+        &mut self,
+        chain_id: u64,
+        contract: String,
+        status: ContractStatus,
+    ) -> eyre::Result<()> {
+        self.write(|entries| entries.put("contract_statuses", &(chain_id, contract), &status))
+    }
+
+    fn get_inbox(&self, creator: String) -> eyre::Result<Vec<InboxMessage>> {
+        self.read(|entries| {
+            let inbox: Vec<InboxMessage> = entries.values("inbox")?;
+            Ok(inbox.into_iter().rev().filter(|message| message.creator == creator).collect())
+        })
+    }
+
+    fn count_inbox_messages_since(&self, sender: String, since: i64) -> eyre::Result<usize> {
+        self.read(|entries| {
+            let inbox: Vec<InboxMessage> = entries.values("inbox")?;
+            Ok(inbox
+                .iter()
+                .filter(|message| message.sender == sender && message.sent_at >= since)
+                .count())
+        })
+    }
+
+    fn set_token_metadata(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        metadata: String,
+    ) -> eyre::Result<()> {
+        let record = TokenMetadataRecord { metadata, cid: None };
+        self.write(|entries| entries.put("token_metadata", &(chain_id, token_id), &record))
+    }
+
+    fn get_token_metadata(
+        &self,
+        chain_id: u64,
+        token_id: String,
+    ) -> eyre::Result<Option<TokenMetadataRecord>> {
+        self.read(|entries| entries.get("token_metadata", &(chain_id, token_id)))
+    }
+
+    fn get_unpinned_metadata(&self) -> eyre::Result<Vec<(u64, String, String)>> {
+        self.read(|entries| {
+            let mut unpinned = Vec::new();
+            for (key, record) in entries.scan::<TokenMetadataRecord>("token_metadata")? {
+                if record.cid.is_none() {
+                    let (chain_id, token_id) = decode_token_key(&key)?;
+                    unpinned.push((chain_id, token_id, record.metadata));
+                }
+            }
+            Ok(unpinned)
+        })
+    }
+
+    fn set_metadata_cid(
+        &mut self,
+        chain_id: u64,
+        token_id: String,
+        cid: String,
+    ) -> eyre::Result<()> {
+        let key = (chain_id, token_id);
+        self.write(|entries| {
+            let mut record: TokenMetadataRecord = entries
+                .get("token_metadata", &key)?
+                .ok_or_else(|| eyre::eyre!("Token metadata not found"))?;
+            record.cid = Some(cid);
+            entries.put("token_metadata", &key, &record)
+        })
+    }
+
+    fn get_creator_signals(&self, x_id: String) -> eyre::Result<CreatorSignals> {
+        self.read(|entries| Ok(entries.get("creator_signals", &x_id)?.unwrap_or_default()))
+    }
+
+    fn set_creator_signals(&mut self, x_id: String, signals: CreatorSignals) -> eyre::Result<()> {
+        self.write(|entries| entries.put("creator_signals", &x_id, &signals))
+    }
+
+    fn add_reputation_snapshot(
+        &mut self,
+        x_id: String,
+        snapshot: ReputationSnapshot,
+    ) -> eyre::Result<()> {
+        self.write(|entries| {
+            let mut history: Vec<ReputationSnapshot> =
+                entries.get("reputation_history", &x_id)?.unwrap_or_default();
+            history.push(snapshot);
+            if history.len() > MAX_REPUTATION_HISTORY {
+                history.remove(0);
+            }
+            entries.put("reputation_history", &x_id, &history)
+        })
+    }
+
+    fn get_reputation_history(&self, x_id: String) -> eyre::Result<Vec<ReputationSnapshot>> {
+        self.read(|entries| Ok(entries.get("reputation_history", &x_id)?.unwrap_or_default()))
+    }
+
+    fn get_redeem_nonce(&self, address: String) -> eyre::Result<u64> {
+        self.read(|entries| Ok(entries.get("redeem_nonces", &address)?.unwrap_or_default()))
+    }
+
+    fn use_redeem_nonce(&mut self, address: String, nonce: u64) -> eyre::Result<()> {
+        self.write(|entries| {
+            let current: u64 = entries.get("redeem_nonces", &address)?.unwrap_or_default();
+            if current != nonce {
+                eyre::bail!("Expected redeem nonce {}, got {}", current, nonce);
+            }
+            entries.put("redeem_nonces", &address, &(current + 1))
+        })
+    }
+
+    fn get_collection_stats(&self, chain_id: u64) -> eyre::Result<CollectionStats> {
+        self.read(|entries| Ok(entries.get("collection_stats", &chain_id)?.unwrap_or_default()))
+    }
+
+    fn list_users(&self, after: Option<String>, limit: usize) -> eyre::Result<Vec<(String, User)>> {
+        self.read(|entries| {
+            entries.scan_range("users", after.as_deref().unwrap_or(""), None, limit)
+        })
+    }
+
+    fn list_nfts(&self, after: Option<String>, limit: usize) -> eyre::Result<Vec<(String, NFT)>> {
+        self.read(|entries| entries.scan_range("nfts", after.as_deref().unwrap_or(""), None, limit))
+    }
+
+    fn list_redemptions(
+        &self,
+        chain_id: u64,
+        after: Option<String>,
+        limit: usize,
+    ) -> eyre::Result<Vec<RedemptionRecord>> {
+        self.read(|entries| {
+            let after = after.unwrap_or_default();
+            let mut redemptions = Vec::new();
+            for (key, record) in entries.scan_tokens::<ModerationRecord>(
+                "moderation_records",
+                chain_id,
+                &after,
+                limit,
+            )? {
+                let key = decode_token_key(&key)?;
+                redemptions.push(RedemptionRecord {
+                    tweet_id: entries.get("tweets", &key)?,
+                    token_id: key.1,
+                    prompt_version: record.prompt_version,
+                    safe: record.safe,
+                });
+            }
+            Ok(redemptions)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{snapshot::SCHEMA_VERSION, AdminAction, EmailPurpose};
+
+    use super::*;
+
+    #[test]
+    fn db_test_persists_to_file() -> eyre::Result<()> {
+        let path = std::env::temp_dir().join(format!("teleport-{}.sqlite", cuid::cuid2()));
+        let path = path.to_str().unwrap();
+        {
+            let mut db = SqliteDB::open(path)?;
+            let user = User { x_id: Some("1".to_string()), ..Default::default() };
+            db.add_user("0x1".to_string(), user)?;
+            db.set_last_processed_block(1, 10, 2)?;
+        }
+        let db = SqliteDB::open(path)?;
+        assert_eq!(db.get_user_by_x_id("1".to_string())?.x_id.as_deref(), Some("1"));
+        let cursor = db.get_last_processed_block(1)?;
+        assert_eq!(cursor, BlockCursor { block_number: 10, log_index: 2 });
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_serialized_image_opens_as_a_file() -> eyre::Result<()> {
+        let mut db = SqliteDB::open_in_memory()?;
+        db.set_timezone("1".to_string(), "Europe/Berlin".to_string())?;
+        let path = std::env::temp_dir().join(format!("teleport-{}.sqlite", cuid::cuid2()));
+        std::fs::write(&path, db.serialize()?)?;
+        let db = SqliteDB::open(path.to_str().unwrap())?;
+        assert_eq!(db.get_timezone("1".to_string())?, "Europe/Berlin");
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_snapshot_replaces_everything() -> eyre::Result<()> {
+        let mut db = SqliteDB::open_in_memory()?;
+        db.set_timezone("1".to_string(), "Europe/Berlin".to_string())?;
+        let snapshot = Image { version: SCHEMA_VERSION, bytes: db.export_snapshot()? };
+        let mut restored = SqliteDB::open_in_memory()?;
+        restored.set_timezone("2".to_string(), "Asia/Tokyo".to_string())?;
+        restored.import_snapshot(&snapshot)?;
+        assert_eq!(restored.get_timezone("1".to_string())?, "Europe/Berlin");
+        assert!(restored.get_timezone("2".to_string()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_chainless_indexer_rows_move_to_base() -> eyre::Result<()> {
+        let mut db = SqliteDB::open_in_memory()?;
+        db.write(|entries| {
+            let cursor = BlockCursor { block_number: 10, log_index: 2 };
+            entries.put("last_processed_block", "", &cursor)?;
+            entries.put("processed_events", &("0xabc".to_string(), 1_u64), &())
+        })?;
+        let snapshot = Image { version: SCHEMA_VERSION, bytes: db.export_snapshot()? };
+        db.import_snapshot(&snapshot)?;
+        let cursor = db.get_last_processed_block(DEFAULT_CHAIN_ID)?;
+        assert_eq!(cursor, BlockCursor { block_number: 10, log_index: 2 });
+        assert!(db.is_event_processed(DEFAULT_CHAIN_ID, "0xabc".to_string(), 1)?);
+        assert!(!db.mark_event_processed(DEFAULT_CHAIN_ID, "0xabc".to_string(), 1)?);
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_user_by_address_ignores_case() -> eyre::Result<()> {
+        let mut db = SqliteDB::open_in_memory()?;
+        let user = User { x_id: Some("1".to_string()), ..Default::default() };
+        db.add_user("0xabcdef".to_string(), user.clone())?;
+        assert_eq!(db.get_user_by_address("0xABCdef".to_string())?, user);
+        assert!(db.get_user_by_address("0xabcde".to_string()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_failed_write_rolls_back() -> eyre::Result<()> {
+        let mut db = SqliteDB::open_in_memory()?;
+        let user = User { x_id: Some("old".to_string()), ..Default::default() };
+        db.add_user("0x1".to_string(), user)?;
+        db.unlink_x_account("0x1".to_string())?;
+        assert!(db.unlink_x_account("0x1".to_string()).is_err());
+        let user = db.get_user_by_address("0x1".to_string())?;
+        assert_eq!((user.x_id, user.unlinked_x_id), (None, Some("old".to_string())));
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_prune_pending_approvals() -> eyre::Result<()> {
+        let mut db = SqliteDB::open_in_memory()?;
+        for (approval_id, expires_at) in [("a", 100), ("b", 200)] {
+            let approval = PendingApproval {
+                action: AdminAction::ReplayDeadLetter { event_id: "0x1:0".to_string() },
+                proposed_by: "0x1".to_string(),
+                expires_at,
+            };
+            db.add_pending_approval(approval_id.to_string(), approval)?;
+        }
+        assert_eq!(db.prune_pending_approvals(100)?, 1);
+        let remaining: Vec<String> =
+            db.get_pending_approvals()?.into_iter().map(|(approval_id, _)| approval_id).collect();
+        assert_eq!(remaining, ["b"]);
+        assert_eq!(db.prune_pending_approvals(100)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_email_challenge_attempts() -> eyre::Result<()> {
+        let mut db = SqliteDB::open_in_memory()?;
+        let challenge = EmailChallenge {
+            purpose: EmailPurpose::Verify,
+            address: "0x1".to_string(),
+            email: "a@b.c".to_string(),
+            code: "123456".to_string(),
+            expires_at: 100,
+            attempts: 0,
+        };
+        assert!(db.set_email_challenge("1".to_string(), challenge.clone(), 0)?);
+        for _ in 0..MAX_EMAIL_CHALLENGE_ATTEMPTS {
+            assert!(db.verify_email_challenge("1".to_string(), "000000".to_string(), 50).is_err());
+        }
+        assert!(db.verify_email_challenge("1".to_string(), "123456".to_string(), 50).is_err());
+        assert!(!db.set_email_challenge("1".to_string(), challenge, 50)?);
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_chain_scans_follow_key_order() -> eyre::Result<()> {
+        let mut db = SqliteDB::open_in_memory()?;
+        for (chain_id, token_id) in [(10, "2"), (9, "1"), (10, "10")] {
+            db.queue_burn(chain_id, token_id.to_string(), false)?;
+        }
+        let burns: Vec<String> =
+            db.get_pending_burns(10)?.into_iter().map(|(token_id, _)| token_id).collect();
+        assert_eq!(burns, vec!["10".to_string(), "2".to_string()]);
+        assert_eq!(db.get_pending_burns(9)?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_promotion_moves_pending_state() -> eyre::Result<()> {
+        let mut db = SqliteDB::open_in_memory()?;
+        let pending_nft = PendingNFT {
+            address: "0x1".to_string(),
+            nft_id: "nft".to_string(),
+            chain_id: 1,
+            policy_hash: "0xpolicy".to_string(),
+        };
+        db.add_pending_nft("0xtx".to_string(), pending_nft)?;
+        assert_eq!(db.get_collection_stats(1)?.pending_mints, 1);
+
+        assert_eq!(db.promote_pending_nft("0xtx".to_string(), "7".to_string())?, "nft");
+        assert_eq!(db.get_nft_by_token_id(1, "7".to_string())?.address, "0x1");
+        assert_eq!(db.get_policy_hash(1, "7".to_string())?.as_deref(), Some("0xpolicy"));
+        assert_eq!(db.get_mint_tx(1, "7".to_string())?.as_deref(), Some("0xtx"));
+        let stats = db.get_collection_stats(1)?;
+        assert_eq!((stats.minted, stats.pending_mints), (1, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_list_redemptions_stays_on_chain() -> eyre::Result<()> {
+        let mut db = SqliteDB::open_in_memory()?;
+        let record = |safe| ModerationRecord { prompt_version: "v1".to_string(), safe };
+        db.add_moderation_record(1, "1".to_string(), record(true))?;
+        db.add_moderation_record(1, "2".to_string(), record(false))?;
+        db.add_moderation_record(1, "10".to_string(), record(true))?;
+        db.add_moderation_record(2, "0".to_string(), record(true))?;
+        db.add_tweet(1, "1".to_string(), "100".to_string())?;
+
+        let page = db.list_redemptions(1, None, 2)?;
+        let token_ids: Vec<&str> = page.iter().map(|r| r.token_id.as_str()).collect();
+        assert_eq!(token_ids, vec!["1", "2"]);
+        assert_eq!(page[0].tweet_id.as_deref(), Some("100"));
+        assert!(!page[1].safe);
+        let page = db.list_redemptions(1, Some("2".to_string()), 2)?;
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].token_id, "10");
+        assert_eq!(db.list_redemptions(2, None, 10)?.len(), 1);
+        assert!(db.list_redemptions(3, None, 10)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn db_test_read_models_follow_writes() -> eyre::Result<()> {
+        let path = std::env::temp_dir().join(format!("teleport-{}.sqlite", cuid::cuid2()));
+        let path = path.to_str().unwrap();
+        {
+            let mut db = SqliteDB::open(path)?;
+            let record = |safe| ModerationRecord { prompt_version: "v1".to_string(), safe };
+            db.add_moderation_record(1, "7".to_string(), record(false))?;
+            db.add_moderation_record(1, "7".to_string(), record(true))?;
+            db.add_tweet(1, "7".to_string(), "100".to_string())?;
+            db.add_tweet(1, "7".to_string(), "101".to_string())?;
+            db.add_moderation_record(2, "7".to_string(), record(false))?;
+        }
+        let db = SqliteDB::open(path)?;
+        let stats = db.get_collection_stats(1)?;
+        assert_eq!((stats.redeemed, stats.rejected_redemptions, stats.tweets), (1, 0, 1));
+        assert_eq!(db.get_collection_stats(2)?.rejected_redemptions, 1);
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}
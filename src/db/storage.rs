@@ -0,0 +1,300 @@
+use std::sync::Arc;
+
+use alloy::primitives::Address;
+use futures::future::BoxFuture;
+
+use super::{lock::TrackedMutex, marketplace::MarketplaceIndex, TeleportDB};
+
+/// A redemption that went through moderation, to be recorded everywhere it is tracked.
+#[derive(Debug, Clone)]
+pub struct Redemption {
+    pub token_id: String,
+    /// The tweet that fulfilled it, posted now or by an earlier attempt, unless nothing was
+    /// posted (read-only, or no linked user).
+    pub tweet_id: Option<String>,
+    /// The posted text, as the marketplace lists it.
+    pub text: String,
+    pub policy: String,
+    /// Content hash to anchor on the fulfillment registry, when the chain anchors tweets and the
+    /// tweet was just posted.
+    pub anchor: Option<String>,
+    /// Whether the token leaves the marketplace listing, as redeemed ERC-721 tokens do.
+    pub delist: bool,
+}
+
+/// The state event handlers change, as whole operations. Each touches the service database and
+/// the marketplace index as needed, so handlers neither know which store holds what nor keep the
+/// two in step themselves. The service database is written first: its records are what make a
+/// retried operation skip work already done, such as a posted tweet.
+pub trait Storage: Send + Sync {
+    /// Turns the pending NFT minted by `tx_hash` into `token_id`, returning the NFT's id.
+    fn record_mint(
+        &self,
+        chain_id: u64,
+        tx_hash: String,
+        token_id: String,
+    ) -> BoxFuture<'_, eyre::Result<String>>;
+    /// Lists the token under its new holder, or delists it when sent to the zero address.
+    fn transfer_token(
+        &self,
+        chain_id: u64,
+        token_id: String,
+        to: Address,
+    ) -> BoxFuture<'_, eyre::Result<()>>;
+    /// Records a redemption once; recording one the marketplace already lists does nothing.
+    fn record_redemption(
+        &self,
+        chain_id: u64,
+        redemption: Redemption,
+    ) -> BoxFuture<'_, eyre::Result<()>>;
+    /// Moves a burned token's redemption out of the listed ones.
+    fn archive_redemption(
+        &self,
+        chain_id: u64,
+        token_id: String,
+        revoked: bool,
+    ) -> BoxFuture<'_, eyre::Result<()>>;
+}
+
+/// [`Storage`] over a [`TeleportDB`] and the marketplace index.
+pub struct Stores<A: TeleportDB> {
+    db: Arc<TrackedMutex<A>>,
+    marketplace: Arc<dyn MarketplaceIndex>,
+}
+
+impl<A: TeleportDB> Stores<A> {
+    pub fn new(db: Arc<TrackedMutex<A>>, marketplace: Arc<dyn MarketplaceIndex>) -> Self {
+        Self { db, marketplace }
+    }
+}
+
+impl<A: TeleportDB> Clone for Stores<A> {
+    fn clone(&self) -> Self {
+        Self { db: self.db.clone(), marketplace: self.marketplace.clone() }
+    }
+}
+
+impl<A: TeleportDB> Storage for Stores<A> {
+    fn record_mint(
+        &self,
+        chain_id: u64,
+        tx_hash: String,
+        token_id: String,
+    ) -> BoxFuture<'_, eyre::Result<String>> {
+        Box::pin(async move {
+            let nft_id = self.db.lock().await.promote_pending_nft(tx_hash, token_id.clone())?;
+            self.marketplace.set_token_id(chain_id, token_id, nft_id.clone()).await?;
+            Ok(nft_id)
+        })
+    }
+
+    fn transfer_token(
+        &self,
+        chain_id: u64,
+        token_id: String,
+        to: Address,
+    ) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(async move {
+            if to == Address::ZERO {
+                self.marketplace.delete_token(chain_id, token_id).await
+            } else {
+                self.marketplace.update_token_owner(chain_id, token_id, to.to_string()).await
+            }
+        })
+    }
+
+    fn record_redemption(
+        &self,
+        chain_id: u64,
+        redemption: Redemption,
+    ) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(async move {
+            // A retry of a redemption an earlier attempt already recorded only has the steps
+            // after it left, and recording it again would list and count it twice.
+            let recorded = self
+                .marketplace
+                .find_redemption_policy(chain_id, redemption.token_id.clone())
+                .await?
+                .is_some();
+            if recorded {
+                log::info!("NFT {}'s redemption was already recorded", redemption.token_id);
+                return Ok(());
+            }
+            if let Some(tweet_id) = &redemption.tweet_id {
+                let mut db = self.db.lock().await;
+                db.add_tweet(chain_id, redemption.token_id.clone(), tweet_id.clone())?;
+                if let Some(content_hash) = &redemption.anchor {
+                    db.queue_anchor(
+                        chain_id,
+                        redemption.token_id.clone(),
+                        tweet_id.clone(),
+                        content_hash.clone(),
+                    )?;
+                }
+            }
+            self.marketplace.add_redemption(chain_id, redemption).await
+        })
+    }
+
+    fn archive_redemption(
+        &self,
+        chain_id: u64,
+        token_id: String,
+        revoked: bool,
+    ) -> BoxFuture<'_, eyre::Result<()>> {
+        self.marketplace.archive_redemption(chain_id, token_id, revoked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::db::{
+        in_memory::InMemoryDB,
+        marketplace::{CreatorDailyStats, NoMarketplaceIndex, RedemptionSample, TokenOwner},
+    };
+
+    /// Lists every redemption it is given, including repeats.
+    #[derive(Default)]
+    struct ListingIndex {
+        redemptions: Mutex<Vec<(u64, Redemption)>>,
+    }
+
+    impl MarketplaceIndex for ListingIndex {
+        fn set_token_id(&self, _: u64, _: String, _: String) -> BoxFuture<'_, eyre::Result<()>> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn update_token_owner(
+            &self,
+            _: u64,
+            _: String,
+            _: String,
+        ) -> BoxFuture<'_, eyre::Result<()>> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn delete_token(&self, _: u64, _: String) -> BoxFuture<'_, eyre::Result<()>> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn add_redemption(
+            &self,
+            chain_id: u64,
+            redemption: Redemption,
+        ) -> BoxFuture<'_, eyre::Result<()>> {
+            self.redemptions.lock().unwrap().push((chain_id, redemption));
+            Box::pin(async { Ok(()) })
+        }
+
+        fn set_redemption_tweet_id(
+            &self,
+            _: u64,
+            _: String,
+            _: String,
+        ) -> BoxFuture<'_, eyre::Result<()>> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn archive_redemption(
+            &self,
+            _: u64,
+            _: String,
+            _: bool,
+        ) -> BoxFuture<'_, eyre::Result<()>> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn sample_redemptions(&self, _: i64) -> BoxFuture<'_, eyre::Result<Vec<RedemptionSample>>> {
+            Box::pin(async { Ok(vec![]) })
+        }
+
+        fn get_redemption(
+            &self,
+            _: u64,
+            _: String,
+        ) -> BoxFuture<'_, eyre::Result<Option<RedemptionSample>>> {
+            Box::pin(async { Ok(None) })
+        }
+
+        fn find_redemption_policy(
+            &self,
+            chain_id: u64,
+            token_id: String,
+        ) -> BoxFuture<'_, eyre::Result<Option<String>>> {
+            let policy = self
+                .redemptions
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|(id, redemption)| *id == chain_id && redemption.token_id == token_id)
+                .map(|(_, redemption)| redemption.policy.clone());
+            Box::pin(async move { Ok(policy) })
+        }
+
+        fn find_token_owner(
+            &self,
+            _: u64,
+            _: String,
+        ) -> BoxFuture<'_, eyre::Result<Option<TokenOwner>>> {
+            Box::pin(async { Ok(None) })
+        }
+
+        fn get_creator_daily_stats(
+            &self,
+            _: String,
+            _: String,
+            _: String,
+        ) -> BoxFuture<'_, eyre::Result<Vec<CreatorDailyStats>>> {
+            Box::pin(async { Ok(vec![]) })
+        }
+
+        fn roll_up_creator_stats(&self, _: String) -> BoxFuture<'_, eyre::Result<u64>> {
+            Box::pin(async { Ok(0) })
+        }
+    }
+
+    #[tokio::test]
+    async fn records_the_fulfilling_tweet_and_its_anchor() {
+        let db = Arc::new(TrackedMutex::new(InMemoryDB::new()));
+        let storage = Stores::new(db.clone(), Arc::new(NoMarketplaceIndex));
+        let redemption = Redemption {
+            token_id: "7".to_string(),
+            tweet_id: Some("1800".to_string()),
+            text: "gm".to_string(),
+            policy: "be nice".to_string(),
+            anchor: Some("0xabc".to_string()),
+            delist: true,
+        };
+        storage.record_redemption(1, redemption).await.unwrap();
+
+        let db = db.lock().await;
+        assert_eq!(db.get_tweet(1, "7".to_string()).unwrap(), "1800");
+        let anchor = db.get_anchor(1, "7".to_string()).unwrap().unwrap();
+        assert_eq!(anchor.content_hash, "0xabc");
+    }
+
+    #[tokio::test]
+    async fn retried_redemption_is_recorded_once() {
+        let db = Arc::new(TrackedMutex::new(InMemoryDB::new()));
+        let index = Arc::new(ListingIndex::default());
+        let storage = Stores::new(db.clone(), index.clone());
+        // Empty text still counts as recorded.
+        let redemption = Redemption {
+            token_id: "7".to_string(),
+            tweet_id: Some("1800".to_string()),
+            text: String::new(),
+            policy: "be nice".to_string(),
+            anchor: None,
+            delist: true,
+        };
+        storage.record_redemption(1, redemption.clone()).await.unwrap();
+        // A step after recording failed, so the event is handled again.
+        storage.record_redemption(1, redemption).await.unwrap();
+
+        assert_eq!(index.redemptions.lock().unwrap().len(), 1);
+        assert_eq!(db.lock().await.get_tweet(1, "7".to_string()).unwrap(), "1800");
+    }
+}
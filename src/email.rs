@@ -0,0 +1,46 @@
+use serde::Serialize;
+
+use crate::secrets::get_secret;
+
+#[derive(Debug, Serialize)]
+struct Email<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    text: &'a str,
+}
+
+/// Sends transactional email through an HTTP email API (`EMAIL_API_URL`, bearer `EMAIL_API_KEY`).
+#[derive(Debug, Clone, Default)]
+pub struct Mailer {
+    api_url: Option<String>,
+    api_key: Option<String>,
+    from: String,
+}
+
+impl Mailer {
+    pub fn from_env() -> Self {
+        Self {
+            api_url: std::env::var("EMAIL_API_URL").ok(),
+            api_key: get_secret("EMAIL_API_KEY").ok(),
+            from: std::env::var("EMAIL_FROM")
+                .unwrap_or_else(|_| "noreply@teleport.best".to_string()),
+        }
+    }
+
+    /// Fails when no email API is configured; codes are never written to the host's logs instead.
+    pub async fn send(&self, to: &str, subject: &str, text: &str) -> eyre::Result<()> {
+        let api_url = self.api_url.as_ref().ok_or_else(|| eyre::eyre!("EMAIL_API_URL not set"))?;
+        let mut request = reqwest::Client::new().post(api_url).json(&Email {
+            from: &self.from,
+            to,
+            subject,
+            text,
+        });
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
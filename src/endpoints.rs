@@ -1,30 +1,73 @@
 use alloy::{
-    primitives::Address,
+    hex,
+    hex::ToHexExt,
+    primitives::{utils::format_ether, Address, Bytes, Signature, TxHash, B256, U256},
     signers::{k256::ecdsa::SigningKey, local::LocalSigner},
 };
 use http::HeaderMap;
-use std::{str::FromStr, sync::Arc};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    str::FromStr,
+    sync::{Arc, Mutex, OnceLock},
+    time::Instant,
+};
+
+use chrono::NaiveDate;
 
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     http::StatusCode,
-    response::{IntoResponse, Redirect},
+    response::{IntoResponse, Redirect, Response},
     Json,
 };
-use rustls::ClientConfig;
 use serde::{Deserialize, Serialize};
-use tokio::sync::Mutex;
-use tokio_postgres_rustls::MakeRustlsConnect;
 
 use crate::{
+    access_list::MintDenied,
     actions::{
-        nft::{mint_nft, redeem_nft},
-        wallet::WalletProvider,
+        chain::ChainClient,
+        ens::{resolve_address, UnresolvedName},
+        forwarder::{prepare_redeem, redeemed_token, relay_redeem, ForwardDomain, ForwardRequest},
+        gas::GasEstimate,
+        nft::{
+            batch_mint_nft, content_hash, is_nft_holder, mint_nft, policy_hash, redeem_nft,
+            Reverted, TweetContent,
+        },
+        pause::{check_contract, ContractUnavailable},
+        payments::{verify_payment, PaymentRejected},
+        promotion::get_mint_confirmations,
+        smart_account::{
+            prepare_redeem as prepare_user_op, redeemed_token as user_op_redeemed_token,
+            send_user_operation, UserOperation,
+        },
+        top_up::top_up,
+        tx_monitor::get_fee_bump_percent,
+        wallet::NotInFlight,
     },
-    db::{in_memory::InMemoryDB, PendingNFT, Session, TeleportDB},
+    admin::Role,
+    context::{MintCtx, ReadChain, ReadCtx, RedeemCtx},
+    db::{
+        lock::TrackedMutex,
+        marketplace::{CreatorDailyStats, MarketplaceIndex},
+        ApprovedContent, ContentAnchor, EmailChallenge, EmailPurpose, LicenseRule, MentionRule,
+        MintPayment, ModerationTier, PendingNFT, PolicySnapshot, RecoveryEmail, RedemptionLink,
+        Session, TeleportDB, TxRecord, TxStatus, User, MAX_TX_REPLACEMENTS, NFT,
+    },
+    email::Mailer,
+    error_codes::{ApiError, ErrorCode},
+    events,
+    kill_switch::{self, SideEffect, SideEffectDisabled},
+    license, mentions, metrics,
+    mode::ServiceMode,
     oai,
-    templates::{HtmlTemplate, PolicyTemplate},
-    twitter::{builder::TwitterBuilder, get_callback_url},
+    public_api::RateLimiter,
+    quorum::Quorum,
+    redeem_auth::{CancelRequest, RedeemDomain, RedeemRequest},
+    reputation,
+    sgx_attest::EnclaveMeasurement,
+    templates::{HtmlTemplate, PolicyTemplate, RedemptionLinkTemplate},
+    timezone::parse_timezone,
+    twitter::{builder::TwitterBuilder, error::TwitterError, get_callback_url},
 };
 
 use alloy::signers::Signer;
@@ -39,6 +82,7 @@ fn default_str() -> String {
 
 #[derive(Deserialize)]
 pub struct NewUserQuery {
+    /// An address or ENS name.
     address: String,
     #[serde(default = "default_str")]
     frontend_nonce: String,
@@ -54,14 +98,50 @@ pub struct CallbackQuery {
 
 #[derive(Deserialize)]
 pub struct MintQuery {
+    /// An address or ENS name.
     address: String,
     policy: String,
     nft_id: String,
+    precheck_threshold: Option<f32>,
+    /// Handles redemptions under the policy may @mention; any others are held or rejected.
+    allowed_mentions: Option<Vec<String>>,
+    /// The content licenses redemptions under the policy may declare, and whether they must.
+    license_rule: Option<LicenseRule>,
+    /// `high_assurance` has every redemption under the policy checked by two moderators.
+    moderation_tier: Option<ModerationTier>,
+    chain_id: Option<u64>,
+    /// The ERC-20 transfer paying the mint fee, on chains that charge one.
+    payment_tx: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct BatchMintRecipient {
+    /// An address or ENS name.
+    address: String,
+    nft_id: String,
+}
+
+/// `address` is the creator whose token is minted to each recipient.
+#[derive(Deserialize)]
+pub struct MintBatchQuery {
+    address: String,
+    policy: String,
+    recipients: Vec<BatchMintRecipient>,
+    chain_id: Option<u64>,
+    /// The ERC-20 transfer paying the mint fee for every recipient, on chains that charge one.
+    payment_tx: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BatchMintResult {
+    hash: Option<String>,
+    error: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct TweetIdQuery {
     token_id: String,
+    chain_id: Option<u64>,
 }
 
 #[derive(Serialize)]
@@ -69,6 +149,14 @@ pub struct TweetIdResponse {
     tweet_id: String,
 }
 
+/// Dates are inclusive `YYYY-MM-DD` UTC days; the range defaults to the last 30 days.
+#[derive(Deserialize)]
+pub struct CreatorStatsQuery {
+    user_id: String,
+    from: Option<String>,
+    to: Option<String>,
+}
+
 #[derive(Serialize)]
 pub struct AttestationResponse {
     cert: String,
@@ -78,6 +166,24 @@ pub struct AttestationResponse {
 pub struct RedeemQuery {
     nft_id: String,
     content: String,
+    nonce: u64,
+    deadline: u64,
+    /// The holder's EIP-712 signature of the `RedeemRequest`.
+    signature: String,
+}
+
+#[derive(Deserialize)]
+pub struct RedeemAuthorizationQuery {
+    nft_id: String,
+    /// The address holding the token now, which signs the request.
+    holder: Address,
+}
+
+#[derive(Serialize)]
+pub struct RedeemAuthorization {
+    domain: RedeemDomain,
+    token_id: String,
+    nonce: u64,
 }
 
 #[derive(Serialize)]
@@ -85,6 +191,141 @@ pub struct TxHashResponse {
     pub hash: String,
 }
 
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EstimateAction {
+    Mint,
+    Redeem,
+}
+
+#[derive(Deserialize)]
+pub struct EstimateQuery {
+    action: EstimateAction,
+    chain_id: Option<u64>,
+    /// For mints, the registered address or ENS name the token would go to.
+    address: Option<String>,
+    policy: Option<String>,
+    /// For redemptions, the token, the tweet it would post and the address holding the token now.
+    nft_id: Option<String>,
+    content: Option<String>,
+    holder: Option<Address>,
+}
+
+/// Amounts are decimal strings, in wei unless suffixed `_eth`.
+#[derive(Serialize)]
+pub struct EstimateResponse {
+    chain_id: u64,
+    gas: String,
+    fee_per_gas: String,
+    max_fee_per_gas: String,
+    cost_wei: String,
+    cost_eth: String,
+    max_cost_wei: String,
+    max_cost_eth: String,
+}
+
+impl EstimateResponse {
+    fn new(chain_id: u64, estimate: GasEstimate) -> Self {
+        Self {
+            chain_id,
+            gas: estimate.gas.to_string(),
+            fee_per_gas: estimate.fee_per_gas.to_string(),
+            max_fee_per_gas: estimate.max_fee_per_gas.to_string(),
+            cost_wei: estimate.cost().to_string(),
+            cost_eth: format_ether(estimate.cost()),
+            max_cost_wei: estimate.max_cost().to_string(),
+            max_cost_eth: format_ether(estimate.max_cost()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TxStatusQuery {
+    hash: String,
+}
+
+#[derive(Deserialize)]
+pub struct NftStatusQuery {
+    nft_id: String,
+}
+
+/// Where a mint is between the request and the token: its transaction not yet seen by the
+/// indexer, seen but not yet `MINT_CONFIRMATIONS` deep, or promoted to a token.
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum NftStatus {
+    Pending,
+    Confirming { token_id: String, confirmations: u64, required_confirmations: u64 },
+    Minted { token_id: String },
+}
+
+#[derive(Serialize)]
+pub struct NftStatusResponse {
+    nft_id: String,
+    chain_id: u64,
+    #[serde(flatten)]
+    status: NftStatus,
+}
+
+#[derive(Deserialize)]
+pub struct CancelTxQuery {
+    signature: String,
+}
+
+#[derive(Serialize)]
+pub struct TxStatusResponse {
+    /// The hash the status belongs to, which differs from the queried one after a replacement.
+    hash: String,
+    #[serde(flatten)]
+    tx: TxRecord,
+}
+
+/// Rejection for endpoints that submit transactions, so a simulated revert reaches the caller
+/// with its reason instead of a bare status.
+pub struct TxError(ApiError);
+
+impl From<StatusCode> for TxError {
+    fn from(status: StatusCode) -> Self {
+        Self(status.into())
+    }
+}
+
+impl From<ApiError> for TxError {
+    fn from(error: ApiError) -> Self {
+        Self(error)
+    }
+}
+
+impl IntoResponse for TxError {
+    fn into_response(self) -> Response {
+        self.0.into_response()
+    }
+}
+
+impl TxError {
+    fn from_send(action: &str, e: eyre::Report) -> Self {
+        if e.is::<MintDenied>() {
+            log::warn!("Refused to {}: {}", action, e);
+            return Self(ErrorCode::MintDenied.into());
+        }
+        if let Some(unavailable) = e.downcast_ref::<ContractUnavailable>() {
+            return Self(ApiError::with_message(ErrorCode::ContractPaused, unavailable.to_string()));
+        }
+        if let Some(disabled) = e.downcast_ref::<SideEffectDisabled>() {
+            return Self(ApiError::with_message(ErrorCode::FeatureDisabled, disabled.to_string()));
+        }
+        match e.downcast_ref::<Reverted>() {
+            Some(Reverted(reason)) => {
+                Self(ApiError::with_message(ErrorCode::TransactionReverted, reason.clone()))
+            }
+            None => {
+                log::error!("Failed to {}: {:?}", action, e);
+                StatusCode::INTERNAL_SERVER_ERROR.into()
+            }
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct CheckRedeemQuery {
     pub content: String,
@@ -94,16 +335,108 @@ pub struct CheckRedeemQuery {
 #[derive(Serialize)]
 pub struct CheckRedeemResponse {
     pub safe: bool,
+    /// Parts of the content the X API tier cannot post, which are left out of the tweet.
+    pub unsupported: Vec<&'static str>,
+    /// Why the content's license would be rejected under the policy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license_violation: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RedemptionLinkQuery {
+    nft_id: String,
+    ttl_secs: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct RedemptionLinkResponse {
+    url: String,
+    expires_at: i64,
+}
+
+#[derive(Deserialize)]
+pub struct RedemptionLinkFormQuery {
+    token: String,
+}
+
+#[derive(Deserialize)]
+pub struct RedeemWithLinkQuery {
+    token: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+pub struct BurnOnRedeemQuery {
+    enabled: bool,
 }
 
+#[derive(Deserialize)]
+pub struct DmNotificationsQuery {
+    enabled: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ApproveContentQuery {
+    policy: String,
+    content: String,
+}
+
+#[derive(Serialize)]
+pub struct ApproveContentResponse {
+    content_hash: String,
+}
+
+#[derive(Deserialize)]
+pub struct RevokeContentQuery {
+    policy: String,
+    content_hash: String,
+}
+
+#[derive(Deserialize)]
+pub struct TimezoneQuery {
+    timezone: String,
+}
+
+#[derive(Deserialize)]
+pub struct EmailQuery {
+    email: String,
+}
+
+#[derive(Deserialize)]
+pub struct EmailCodeQuery {
+    code: String,
+}
+
+#[derive(Serialize)]
+pub struct RecoveredAccountResponse {
+    address: String,
+}
+
+/// Everything the service holds, for handlers that need most of it. Handlers that need less take
+/// one of the narrower contexts in [`crate::context`] instead, built from this by `FromRef`.
 #[derive(Clone)]
 pub struct SharedState<A: TeleportDB> {
-    pub db: Arc<Mutex<A>>,
-    pub provider: WalletProvider,
+    pub db: Arc<TrackedMutex<A>>,
+    pub chains: BTreeMap<u64, ChainClient>,
+    pub default_chain_id: u64,
     pub signer: LocalSigner<SigningKey>,
     pub app_url: String,
     pub tee_url: String,
     pub twitter_builder: TwitterBuilder,
+    pub mailer: Mailer,
+    pub measurement: Option<EnclaveMeasurement>,
+    pub admins: BTreeMap<Address, Role>,
+    pub marketplace: Arc<dyn MarketplaceIndex>,
+    pub mode: ServiceMode,
+    /// From [`max_batch_mint`].
+    pub max_batch_mint: usize,
+}
+
+impl<A: TeleportDB> SharedState<A> {
+    /// The requested chain, or the default chain when the request doesn't name one.
+    pub fn chain(&self, chain_id: Option<u64>) -> Option<&ChainClient> {
+        self.chains.get(&chain_id.unwrap_or(self.default_chain_id))
+    }
 }
 
 pub async fn cookietest<A: TeleportDB>(
@@ -114,11 +447,28 @@ pub async fn cookietest<A: TeleportDB>(
     (jar.add(Cookie::new(SESSION_ID_COOKIE_NAME, "cookieasdf")), Redirect::temporary("localhost"))
 }
 
+/// Reads an address a request names directly or by ENS name, and the name if it was one.
+/// Addresses are passed through as given, since users are stored under them.
+pub(crate) async fn resolve_request_address(
+    chains: &BTreeMap<u64, ChainClient>,
+    address_or_name: &str,
+) -> Result<(String, Option<String>), StatusCode> {
+    match resolve_address(chains, address_or_name).await {
+        Ok((_, None)) => Ok((address_or_name.to_string(), None)),
+        Ok((address, name)) => Ok((address.to_string(), name)),
+        Err(e) if e.downcast_ref::<UnresolvedName>().is_some() => Err(StatusCode::BAD_REQUEST),
+        Err(e) => {
+            log::error!("Failed to resolve {}: {:?}", address_or_name, e);
+            Err(StatusCode::BAD_GATEWAY)
+        }
+    }
+}
+
 pub async fn register_or_login<A: TeleportDB>(
     State(shared_state): State<SharedState<A>>,
     Query(query): Query<NewUserQuery>,
-) -> Redirect {
-    let address = query.address;
+) -> Result<Redirect, StatusCode> {
+    let (address, ens_name) = resolve_request_address(&shared_state.chains, &query.address).await?;
     let frontend_nonce = query.frontend_nonce;
 
     let callback_url =
@@ -133,12 +483,21 @@ pub async fn register_or_login<A: TeleportDB>(
     let mut db = shared_state.db.lock().await;
     let mut existing_user = db.get_user_by_address(address.clone()).ok().unwrap_or_default();
     existing_user.oauth_tokens = oauth_tokens.clone().into();
+    if ens_name.is_some() {
+        existing_user.ens_name = ens_name;
+    }
     db.add_user(address.clone(), existing_user).expect("Failed to add oauth tokens to database");
 
     let url =
         format!("https://api.twitter.com/oauth/authenticate?oauth_token={}", oauth_tokens.token);
 
-    Redirect::temporary(&url)
+    Ok(Redirect::temporary(&url))
+}
+
+#[derive(Serialize)]
+struct CallbackFailure {
+    success: bool,
+    code: ErrorCode,
 }
 
 pub async fn callback<A: TeleportDB>(
@@ -170,10 +529,37 @@ pub async fn callback<A: TeleportDB>(
     let twitter_client = shared_state.twitter_builder.with_auth(token_pair);
     let x_info = twitter_client.get_user_info().await.expect("Failed to get user info");
 
+    // Relinking after an unlink: the new account takes over the old one's preferences, unless
+    // another address already has it.
+    if oauth_user.x_id.is_none() {
+        if let Some(previous_x_id) = oauth_user.unlinked_x_id.take() {
+            if db.get_user_by_x_id(x_info.id.clone()).is_ok() {
+                let failure = CallbackFailure { success: false, code: ErrorCode::XAccountInUse };
+                let params = serde_urlencoded::to_string(&failure)
+                    .expect("Failed to encode callback failure as query params");
+                let url = format!("{}/create?{}", shared_state.app_url, params);
+                return (jar, Redirect::temporary(&url));
+            }
+            db.migrate_x_account(previous_x_id, x_info.id.clone())
+                .expect("Failed to migrate account preferences");
+        }
+    }
+
     let session_id = db
         .add_session(Session { x_id: x_info.id.clone(), address: address.clone() })
         .expect("Failed to add session to database");
 
+    // Topped up in the background so a slow transfer never holds up the redirect.
+    let chain = shared_state.chain(None).cloned().filter(|_| shared_state.mode.has_wallet());
+    if let (Some(chain), Ok(recipient)) = (chain, Address::from_str(&address)) {
+        let db = shared_state.db.clone();
+        tokio::spawn(async move {
+            if let Err(e) = top_up(&db, &chain, recipient).await {
+                log::error!("Failed to top up {}: {:?}", recipient, e);
+            }
+        });
+    }
+
     if oauth_user.x_id.is_none() {
         oauth_user.x_id = Some(x_info.id.clone());
         oauth_user.access_tokens = Some(access_tokens);
@@ -199,12 +585,36 @@ pub async fn callback<A: TeleportDB>(
     )
 }
 
-pub async fn mint(
-    jar: CookieJar,
-    headers: HeaderMap,
-    State(shared_state): State<SharedState<InMemoryDB>>,
-    Json(query): Json<MintQuery>,
-) -> Result<Json<TxHashResponse>, StatusCode> {
+/// Starts tracking a submitted transaction for `/tx_status`, and for `requested_by` to cancel.
+async fn track_tx<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain_id: u64,
+    tx_hash: &str,
+    kind: &str,
+    requested_by: String,
+) {
+    let tx = TxRecord {
+        chain_id,
+        kind: kind.to_string(),
+        status: TxStatus::Pending,
+        submitted_at: chrono::Utc::now().timestamp(),
+        block_number: None,
+        gas_used: None,
+        replaced_by: None,
+        requested_by: Some(requested_by),
+    };
+    if let Err(e) = db.lock().await.add_tx(tx_hash.to_string(), tx) {
+        log::error!("Failed to track transaction {}: {:?}", tx_hash, e);
+    }
+}
+
+/// Mints must come from the approval page and a session belonging to the minting user.
+async fn authorize_mint<A: TeleportDB>(
+    shared_state: &MintCtx<A>,
+    jar: &CookieJar,
+    headers: &HeaderMap,
+    address: &str,
+) -> Result<User, StatusCode> {
     if let Some(referer) = headers.get("Referer") {
         let referer = referer.to_str().unwrap_or("");
         if !referer.starts_with(&format!("https://{}/approve", shared_state.tee_url)) {
@@ -214,33 +624,156 @@ pub async fn mint(
         return Err(StatusCode::FORBIDDEN);
     }
     let db = shared_state.db.lock().await;
-    let user =
-        db.get_user_by_address(query.address.clone()).expect("Failed to get user by address");
+    let user = db.get_user_by_address(address.to_string()).expect("Failed to get user by address");
 
     if let Some(session_id) = jar.get(SESSION_ID_COOKIE_NAME) {
         let session_id = session_id.value();
         let session = db.get_session(session_id.to_string()).expect("Failed to getsession");
-        if session.x_id != user.x_id.clone().unwrap() {
+        if user.x_id.as_deref() != Some(session.x_id.as_str()) {
             return Err(StatusCode::UNAUTHORIZED);
         }
     } else {
         return Err(StatusCode::UNAUTHORIZED);
     }
-    drop(db);
+    Ok(user)
+}
 
-    let tx_hash = mint_nft(
-        shared_state.provider,
+/// Verifies and claims the ERC-20 payment for `count` mints when `chain` charges a mint fee, so
+/// one payment cannot pay for two mint jobs. Returns the claimed payment transaction.
+async fn claim_mint_payment<A: TeleportDB>(
+    shared_state: &MintCtx<A>,
+    chain: &ChainClient,
+    payer: &str,
+    payment_tx: Option<&str>,
+    count: u64,
+) -> Result<Option<String>, StatusCode> {
+    let Some(fees) = &chain.config.mint_fee else {
+        return Ok(None);
+    };
+    let payment_tx = payment_tx.ok_or(StatusCode::PAYMENT_REQUIRED)?;
+    let payment_tx = B256::from_str(payment_tx).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let payer_address = Address::from_str(payer).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let payment = match verify_payment(chain, fees, payment_tx, payer_address, count).await {
+        Ok(payment) => payment,
+        Err(e) if e.downcast_ref::<PaymentRejected>().is_some() => {
+            log::warn!("Mint payment {} from {}: {}", payment_tx, payer, e);
+            return Err(StatusCode::PAYMENT_REQUIRED);
+        }
+        Err(e) => {
+            log::error!("Failed to verify mint payment {}: {:?}", payment_tx, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let payment_tx = payment_tx.encode_hex_with_prefix();
+    let claimed = shared_state
+        .db
+        .lock()
+        .await
+        .add_mint_payment(MintPayment {
+            chain_id: chain.config.chain_id,
+            payer: payer.to_string(),
+            token: payment.token.to_string(),
+            amount: payment.amount.to_string(),
+            payment_tx: payment_tx.clone(),
+            mint_txs: Vec::new(),
+            paid_at: chrono::Utc::now().timestamp(),
+        })
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !claimed {
+        return Err(StatusCode::CONFLICT);
+    }
+    Ok(Some(payment_tx))
+}
+
+/// Ties a claimed payment to the mints it paid for, or releases it when none were sent.
+async fn settle_mint_payment<A: TeleportDB>(
+    shared_state: &MintCtx<A>,
+    chain_id: u64,
+    payment_tx: Option<String>,
+    mint_txs: Vec<String>,
+) {
+    let Some(payment_tx) = payment_tx else {
+        return;
+    };
+    let mut db = shared_state.db.lock().await;
+    let settled = if mint_txs.is_empty() {
+        db.remove_mint_payment(chain_id, payment_tx.clone())
+    } else {
+        metrics::increment("mint_payments_total", &[("chain_id", &chain_id.to_string())]);
+        db.set_payment_mint_txs(chain_id, payment_tx.clone(), mint_txs)
+    };
+    if let Err(e) = settled {
+        log::error!("Failed to settle mint payment {}: {:?}", payment_tx, e);
+    }
+}
+
+pub async fn mint<A: TeleportDB>(
+    jar: CookieJar,
+    headers: HeaderMap,
+    State(shared_state): State<MintCtx<A>>,
+    Json(mut query): Json<MintQuery>,
+) -> Result<Json<TxHashResponse>, TxError> {
+    query.address = resolve_request_address(&shared_state.chains, &query.address).await?.0;
+    let user = authorize_mint(&shared_state, &jar, &headers, &query.address).await?;
+
+    let chain = shared_state.chain(query.chain_id).ok_or(StatusCode::BAD_REQUEST)?;
+    check_contract(&shared_state.db, chain, Some(shared_state.minter))
+        .await
+        .map_err(|e| TxError::from_send("mint NFT", e))?;
+    if query.moderation_tier == Some(ModerationTier::HighAssurance) {
+        if let Err(e) = Quorum::from_env() {
+            log::warn!("High-assurance mint rejected: {:?}", e);
+            return Err(StatusCode::BAD_REQUEST.into());
+        }
+    }
+    let mention_rule = match &query.allowed_mentions {
+        Some(handles) => Some(pin_mentions(&shared_state, &user, handles).await?),
+        None => None,
+    };
+    let payment_tx =
+        claim_mint_payment(&shared_state, chain, &query.address, query.payment_tx.as_deref(), 1)
+            .await?;
+    let minted = mint_nft(
+        chain,
+        &shared_state.db,
         Address::from_str(&query.address).expect("Failed to parse user address"),
         user.x_id.expect("User x_id not set"),
-        query.policy,
+        query.policy.clone(),
     )
-    .await
-    .expect("Failed to mint NFT");
+    .await;
+    let mint_txs = minted.iter().cloned().collect();
+    settle_mint_payment(&shared_state, chain.config.chain_id, payment_tx, mint_txs).await;
+    let tx_hash = minted.map_err(|e| TxError::from_send("mint NFT", e))?;
+    track_tx(&shared_state.db, chain.config.chain_id, &tx_hash, "mint", query.address.clone())
+        .await;
 
     let mut db = shared_state.db.lock().await;
+    if let Some(threshold) = query.precheck_threshold {
+        db.set_policy_precheck_threshold(query.policy.clone(), threshold)
+            .expect("Failed to set policy precheck threshold");
+    }
+    if let Some(rule) = mention_rule {
+        db.set_policy_mention_rule(query.policy.clone(), rule)
+            .expect("Failed to set policy mention rule");
+    }
+    if let Some(rule) = query.license_rule.take() {
+        db.set_policy_license_rule(query.policy.clone(), rule)
+            .expect("Failed to set policy license rule");
+    }
+    if let Some(tier) = query.moderation_tier {
+        db.set_policy_moderation_tier(query.policy.clone(), tier)
+            .expect("Failed to set policy moderation tier");
+    }
+    let snapshot = policy_snapshot(&*db, &query.policy).expect("Failed to snapshot policy");
+    db.set_pending_policy_snapshot(query.nft_id.clone(), snapshot).expect("Failed to pin policy");
     db.add_pending_nft(
         tx_hash.clone(),
-        PendingNFT { address: query.address, nft_id: query.nft_id.clone() },
+        PendingNFT {
+            address: query.address,
+            nft_id: query.nft_id.clone(),
+            chain_id: chain.config.chain_id,
+            policy_hash: policy_hash(&query.policy),
+        },
     )
     .expect("Failed to add pending NFT");
     drop(db);
@@ -248,58 +781,1041 @@ pub async fn mint(
     Ok(Json(TxHashResponse { hash: tx_hash }))
 }
 
+/// The policy's settings as they stand now, for pinning to a token minted under it.
+fn policy_snapshot<A: TeleportDB>(db: &A, policy: &str) -> eyre::Result<PolicySnapshot> {
+    Ok(PolicySnapshot {
+        policy: policy.to_string(),
+        policy_hash: policy_hash(policy),
+        precheck_threshold: db.get_policy_precheck_threshold(policy.to_string()).ok(),
+        mention_rule: db.get_policy_mention_rule(policy.to_string())?,
+        prompt_version: oai::pin_prompt_version(),
+        pinned_at: chrono::Utc::now().timestamp(),
+        license_rule: db.get_policy_license_rule(policy.to_string())?,
+        moderation_tier: db.get_policy_moderation_tier(policy.to_string())?,
+    })
+}
+
+/// Resolves the handles a policy allows to the accounts that own them now, so a handle that
+/// later changes hands no longer counts as allowed. Handles that do not resolve are a 400.
+async fn pin_mentions<A: TeleportDB>(
+    shared_state: &MintCtx<A>,
+    user: &User,
+    handles: &[String],
+) -> Result<MentionRule, ApiError> {
+    let access_tokens = user.access_tokens.clone().ok_or(StatusCode::UNAUTHORIZED)?;
+    let client = shared_state.twitter_builder.with_auth(access_tokens.into());
+    let allowed = mentions::resolve_handles(&client, handles).await.map_err(|e| {
+        log::error!("Failed to resolve allowed mentions: {:?}", e);
+        twitter_error(&e)
+    })?;
+    let all_resolved = handles
+        .iter()
+        .all(|handle| allowed.contains_key(&handle.trim_start_matches('@').to_lowercase()));
+    if !all_resolved {
+        return Err(StatusCode::BAD_REQUEST.into());
+    }
+    Ok(MentionRule { allowed })
+}
+
+/// The error for a failed X API call, telling rate limiting apart from other failures.
+fn twitter_error(e: &eyre::Report) -> ApiError {
+    let status = e
+        .downcast_ref::<reqwest::Error>()
+        .and_then(|e| e.status())
+        .map(|status| status.as_u16())
+        .or_else(|| e.downcast_ref::<TwitterError>().map(|e| e.status));
+    match status {
+        Some(status) if status == StatusCode::TOO_MANY_REQUESTS.as_u16() => {
+            ErrorCode::TwitterRateLimited.into()
+        }
+        _ => ErrorCode::UpstreamFailed.into(),
+    }
+}
+
+const DEFAULT_MAX_BATCH_MINT: usize = 50;
+
+/// Largest `/mint_batch` request accepted, from `MAX_BATCH_MINT`.
+pub(crate) fn max_batch_mint() -> eyre::Result<usize> {
+    match std::env::var("MAX_BATCH_MINT") {
+        Ok(max) => match max.parse() {
+            Ok(max) => Ok(max),
+            Err(_) => eyre::bail!("Invalid MAX_BATCH_MINT {}", max),
+        },
+        Err(_) => Ok(DEFAULT_MAX_BATCH_MINT),
+    }
+}
+
+/// Mints the creator's token to several fans at once. One hash (or error) is returned per
+/// recipient, in request order.
+pub async fn mint_batch<A: TeleportDB>(
+    jar: CookieJar,
+    headers: HeaderMap,
+    State(shared_state): State<MintCtx<A>>,
+    Json(mut query): Json<MintBatchQuery>,
+) -> Result<Json<Vec<BatchMintResult>>, TxError> {
+    if query.recipients.is_empty() || query.recipients.len() > shared_state.max_batch_mint {
+        return Err(StatusCode::BAD_REQUEST.into());
+    }
+    query.address = resolve_request_address(&shared_state.chains, &query.address).await?.0;
+    for recipient in &mut query.recipients {
+        recipient.address =
+            resolve_request_address(&shared_state.chains, &recipient.address).await?.0;
+    }
+    let user = authorize_mint(&shared_state, &jar, &headers, &query.address).await?;
+    let x_id = user.x_id.expect("User x_id not set");
+    let standing = reputation::standing(&*shared_state.db.lock().await, x_id.clone())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if query.recipients.len() > standing.batch_mint_cap(shared_state.max_batch_mint) {
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+    let recipients = query
+        .recipients
+        .iter()
+        .map(|recipient| Address::from_str(&recipient.address))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let chain = shared_state.chain(query.chain_id).ok_or(StatusCode::BAD_REQUEST)?;
+    check_contract(&shared_state.db, chain, Some(shared_state.minter))
+        .await
+        .map_err(|e| TxError::from_send("batch mint NFTs", e))?;
+    let count = recipients.len() as u64;
+    let payment_tx = claim_mint_payment(
+        &shared_state,
+        chain,
+        &query.address,
+        query.payment_tx.as_deref(),
+        count,
+    )
+    .await?;
+    let minted =
+        batch_mint_nft(chain, &shared_state.db, recipients, x_id, query.policy.clone()).await;
+    let mint_txs = match &minted {
+        Ok(results) => results.iter().filter_map(|result| result.as_ref().ok().cloned()).collect(),
+        Err(_) => Vec::new(),
+    };
+    // Recipients whose mint failed stay paid for; support refunds them from the ledger.
+    settle_mint_payment(&shared_state, chain.config.chain_id, payment_tx, mint_txs).await;
+    let results = minted.map_err(|e| TxError::from_send("batch mint NFTs", e))?;
+
+    let mut response = Vec::with_capacity(results.len());
+    for (recipient, result) in query.recipients.into_iter().zip(results) {
+        match result {
+            Ok(tx_hash) => {
+                let requested_by = query.address.clone();
+                track_tx(&shared_state.db, chain.config.chain_id, &tx_hash, "mint", requested_by)
+                    .await;
+                let mut db = shared_state.db.lock().await;
+                let snapshot =
+                    policy_snapshot(&*db, &query.policy).expect("Failed to snapshot policy");
+                db.set_pending_policy_snapshot(recipient.nft_id.clone(), snapshot)
+                    .expect("Failed to pin policy");
+                db.add_pending_nft(
+                    tx_hash.clone(),
+                    PendingNFT {
+                        address: query.address.clone(),
+                        nft_id: recipient.nft_id,
+                        chain_id: chain.config.chain_id,
+                        policy_hash: policy_hash(&query.policy),
+                    },
+                )
+                .expect("Failed to add pending NFT");
+                response.push(BatchMintResult { hash: Some(tx_hash), error: None });
+            }
+            Err(e) => {
+                log::error!("Failed to batch mint to {}: {:?}", recipient.address, e);
+                response.push(BatchMintResult { hash: None, error: Some(e.to_string()) });
+            }
+        }
+    }
+    Ok(Json(response))
+}
+
+/// The domain and nonce the holder of an NFT signs a `RedeemRequest` for `/redeem` with.
+pub async fn get_redeem_authorization<A: TeleportDB>(
+    State(ctx): State<ReadCtx<A>>,
+    Query(query): Query<RedeemAuthorizationQuery>,
+) -> Result<Json<RedeemAuthorization>, StatusCode> {
+    let db = ctx.db.lock().await;
+    let nft = db.get_nft(query.nft_id).map_err(|_| StatusCode::NOT_FOUND)?;
+    let nonce = db
+        .get_redeem_nonce(query.holder.to_string())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    drop(db);
+    let chain = ctx.chain(Some(nft.chain_id)).ok_or(StatusCode::NOT_FOUND)?;
+    ensure_holder(chain, &nft, query.holder).await?;
+    let domain = RedeemDomain::new(nft.chain_id, chain.config().nft_address);
+    Ok(Json(RedeemAuthorization { domain, token_id: nft.token_id, nonce }))
+}
+
+/// What a mint or redemption would cost the minter wallet at current fees, so the frontend can
+/// show it up front. Mints need `address` and `policy`; redemptions need `nft_id`, `content` and
+/// `holder`. One that would revert is a 422 with the reason.
+pub async fn get_estimate<A: TeleportDB>(
+    State(ctx): State<ReadCtx<A>>,
+    Query(query): Query<EstimateQuery>,
+) -> Result<Json<EstimateResponse>, TxError> {
+    let (chain, estimate) = match query.action {
+        EstimateAction::Mint => {
+            let (Some(address), Some(policy)) = (query.address, query.policy) else {
+                return Err(StatusCode::BAD_REQUEST.into());
+            };
+            let address = ctx.resolve_address(&address).await?.0;
+            let user = ctx
+                .db
+                .lock()
+                .await
+                .get_user_by_address(address.clone())
+                .map_err(|_| StatusCode::NOT_FOUND)?;
+            let x_id = user.x_id.ok_or(StatusCode::NOT_FOUND)?;
+            let recipient = Address::from_str(&address).map_err(|_| StatusCode::BAD_REQUEST)?;
+            let chain = ctx.chain(query.chain_id).ok_or(StatusCode::BAD_REQUEST)?;
+            (chain, chain.estimate_mint(recipient, x_id, policy).await)
+        }
+        EstimateAction::Redeem => {
+            let (Some(nft_id), Some(content), Some(holder)) =
+                (query.nft_id, query.content, query.holder)
+            else {
+                return Err(StatusCode::BAD_REQUEST.into());
+            };
+            let nft = ctx.db.lock().await.get_nft(nft_id).map_err(|_| StatusCode::NOT_FOUND)?;
+            let chain = ctx.chain(Some(nft.chain_id)).ok_or(StatusCode::NOT_FOUND)?;
+            ensure_holder(chain, &nft, holder).await?;
+            (chain, chain.estimate_redeem(nft.token_id, holder, content).await)
+        }
+    };
+    let estimate = estimate.map_err(|e| TxError::from_send("estimate gas", e))?;
+    Ok(Json(EstimateResponse::new(chain.config().chain_id, estimate)))
+}
+
+/// Refuses an address that does not hold the NFT's token now, whoever it was minted to.
+async fn ensure_holder(chain: ReadChain<'_>, nft: &NFT, holder: Address) -> Result<(), StatusCode> {
+    let is_holder = chain.is_holder(nft.token_id.clone(), holder).await.map_err(|e| {
+        log::error!("Failed to look up holders of NFT {}: {:?}", nft.token_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if !is_holder {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(())
+}
+
+/// Refuses a token that was already redeemed, before any gas is spent redeeming it again.
+async fn ensure_not_redeemed<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    chain_id: u64,
+    token_id: &str,
+) -> Result<(), ApiError> {
+    if db.lock().await.get_tweet(chain_id, token_id.to_string()).is_ok() {
+        return Err(ErrorCode::TokenAlreadyRedeemed.into());
+    }
+    Ok(())
+}
+
+/// Holders whose signed redeem request is being sent. A nonce is only consumed once its redeem
+/// went out, so this keeps two requests from using it at once in the meantime.
+fn redeems_in_flight() -> &'static Mutex<BTreeSet<String>> {
+    static REDEEMS_IN_FLIGHT: OnceLock<Mutex<BTreeSet<String>>> = OnceLock::new();
+    REDEEMS_IN_FLIGHT.get_or_init(Default::default)
+}
+
+/// Holds a holder in [`redeems_in_flight`] until dropped.
+struct RedeemInFlight(String);
+
+impl RedeemInFlight {
+    fn claim(holder: &str) -> Option<Self> {
+        redeems_in_flight()
+            .lock()
+            .unwrap()
+            .insert(holder.to_string())
+            .then(|| Self(holder.to_string()))
+    }
+}
+
+impl Drop for RedeemInFlight {
+    fn drop(&mut self) {
+        redeems_in_flight().lock().unwrap().remove(&self.0);
+    }
+}
+
+/// Redeems an NFT with a `RedeemRequest` its current holder signed, which is only accepted once
+/// and before its deadline.
 pub async fn redeem<A: TeleportDB>(
-    State(shared_state): State<SharedState<A>>,
+    State(ctx): State<RedeemCtx<A>>,
     Json(query): Json<RedeemQuery>,
-) -> Json<TxHashResponse> {
-    let db = shared_state.db.lock().await;
-    let nft = db
-        .get_nft(query.nft_id.clone())
-        .unwrap_or_else(|_| panic!("Failed to get NFT by id {}", query.nft_id));
+) -> Result<Json<TxHashResponse>, TxError> {
+    let db = ctx.db.lock().await;
+    let nft = db.get_nft(query.nft_id.clone()).map_err(|_| StatusCode::NOT_FOUND)?;
     drop(db);
 
-    let tx_hash = redeem_nft(shared_state.provider, nft.token_id.clone(), query.content)
+    let chain = ctx.chain(Some(nft.chain_id)).ok_or(StatusCode::NOT_FOUND)?;
+    check_contract(&ctx.db, chain, None)
+        .await
+        .map_err(|e| TxError::from_send(&format!("redeem NFT with id {}", nft.token_id), e))?;
+    ensure_not_redeemed(&ctx.db, nft.chain_id, &nft.token_id).await?;
+    if query.deadline < chrono::Utc::now().timestamp() as u64 {
+        return Err(StatusCode::GONE.into());
+    }
+    let token_id = U256::from_str(&nft.token_id).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let signature = Signature::from_str(&query.signature).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let request = RedeemRequest::new(token_id, &query.content, query.nonce, query.deadline);
+    let domain = RedeemDomain::new(nft.chain_id, chain.config.nft_address);
+    let holder = request.signer(&domain, &signature).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let is_holder = is_nft_holder(chain, nft.token_id.clone(), holder).await.map_err(|e| {
+        log::error!("Failed to look up holders of NFT {}: {:?}", nft.token_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if !is_holder {
+        return Err(StatusCode::UNAUTHORIZED.into());
+    }
+
+    let holder_key = holder.to_string();
+    let _in_flight = RedeemInFlight::claim(&holder_key).ok_or(StatusCode::CONFLICT)?;
+    let nonce = ctx
+        .db
+        .lock()
+        .await
+        .get_redeem_nonce(holder_key.clone())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if nonce != query.nonce {
+        return Err(StatusCode::CONFLICT.into());
+    }
+
+    let tx_hash = redeem_nft(chain, nft.token_id.clone(), holder, query.content)
         .await
-        .unwrap_or_else(|_| panic!("Failed to redeem NFT with id {}", nft.token_id));
-    Json(TxHashResponse { hash: tx_hash })
+        .map_err(|e| TxError::from_send(&format!("redeem NFT with id {}", nft.token_id), e))?;
+    if let Err(e) = ctx.db.lock().await.use_redeem_nonce(holder_key.clone(), query.nonce) {
+        log::error!("Failed to consume redeem nonce {} of {}: {:?}", query.nonce, holder_key, e);
+    }
+    track_tx(&ctx.db, nft.chain_id, &tx_hash, "redeem", holder_key).await;
+    Ok(Json(TxHashResponse { hash: tx_hash }))
 }
 
-pub async fn check_redeem<A: TeleportDB>(
-    State(_): State<SharedState<A>>,
-    Json(query): Json<CheckRedeemQuery>,
-) -> Json<CheckRedeemResponse> {
-    let safe = oai::is_tweet_safe(&query.content, &query.policy).await;
-    Json(CheckRedeemResponse { safe })
+#[derive(Deserialize)]
+pub struct ForwardedRedeemQuery {
+    chain_id: Option<u64>,
+    request: ForwardRequest,
+    signature: Bytes,
 }
 
-pub async fn get_tweet_id<A: TeleportDB>(
+#[derive(Deserialize)]
+pub struct PrepareForwardedRedeemQuery {
+    nft_id: String,
+    content: String,
+    /// The address holding the token now, which signs the forward request.
+    holder: Address,
+}
+
+#[derive(Serialize)]
+pub struct PrepareForwardedRedeemResponse {
+    domain: ForwardDomain,
+    request: ForwardRequest,
+}
+
+/// The ERC-2771 forward request the current holder of an NFT signs to redeem it without holding
+/// ETH.
+pub async fn prepare_forwarded_redeem<A: TeleportDB>(
+    State(ctx): State<RedeemCtx<A>>,
+    Json(query): Json<PrepareForwardedRedeemQuery>,
+) -> Result<Json<PrepareForwardedRedeemResponse>, ApiError> {
+    let db = ctx.db.lock().await;
+    let nft = db.get_nft(query.nft_id.clone()).map_err(|_| StatusCode::NOT_FOUND)?;
+    drop(db);
+
+    let chain = ctx.chain(Some(nft.chain_id)).ok_or(StatusCode::NOT_FOUND)?;
+    if chain.config.trusted_forwarder.is_none() {
+        return Err(StatusCode::NOT_IMPLEMENTED.into());
+    }
+    ensure_not_redeemed(&ctx.db, nft.chain_id, &nft.token_id).await?;
+    ensure_holder(ReadChain::new(chain), &nft, query.holder).await?;
+    let token_id = U256::from_str(&nft.token_id).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let (domain, request) =
+        prepare_redeem(chain, token_id, query.holder, query.content).await.map_err(|e| {
+            log::error!("Failed to prepare forwarded redeem of {}: {:?}", nft.token_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok(Json(PrepareForwardedRedeemResponse { domain, request }))
+}
+
+/// Relays a redeem the holder signed as an ERC-2771 forward request, paying its gas from the
+/// minter wallet.
+pub async fn forwarded_redeem<A: TeleportDB>(
+    State(ctx): State<RedeemCtx<A>>,
+    Json(query): Json<ForwardedRedeemQuery>,
+) -> Result<Json<TxHashResponse>, TxError> {
+    let chain = ctx.chain(query.chain_id).ok_or(StatusCode::NOT_FOUND)?;
+    if chain.config.trusted_forwarder.is_none() {
+        return Err(StatusCode::NOT_IMPLEMENTED.into());
+    }
+    let token_id =
+        redeemed_token(chain.config.token_standard, chain.config.nft_address, &query.request)
+            .ok_or(StatusCode::BAD_REQUEST)?;
+    check_contract(&ctx.db, chain, None)
+        .await
+        .map_err(|e| TxError::from_send(&format!("relay redeem of {}", token_id), e))?;
+    let chain_id = chain.config.chain_id;
+    ensure_not_redeemed(&ctx.db, chain_id, &token_id.to_string()).await?;
+    let holder = query.request.from.to_string();
+    let tx_hash = relay_redeem(chain, query.request, query.signature)
+        .await
+        .map_err(|e| TxError::from_send(&format!("relay redeem of {}", token_id), e))?;
+    track_tx(&ctx.db, chain_id, &tx_hash, "redeem", holder).await;
+    Ok(Json(TxHashResponse { hash: tx_hash }))
+}
+
+#[derive(Deserialize)]
+pub struct SmartAccountQuery {
+    owner: Address,
+    chain_id: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct SmartAccountResponse {
+    address: Address,
+    deployed: bool,
+}
+
+/// The ERC-4337 smart account an owner key gets, to register in place of the key's own address.
+pub async fn get_smart_account<A: TeleportDB>(
+    State(ctx): State<ReadCtx<A>>,
+    Query(query): Query<SmartAccountQuery>,
+) -> Result<Json<SmartAccountResponse>, StatusCode> {
+    let chain = ctx.chain(query.chain_id).ok_or(StatusCode::NOT_FOUND)?;
+    if chain.config().smart_accounts.is_none() {
+        return Err(StatusCode::NOT_IMPLEMENTED);
+    }
+    let (address, deployed) = chain.smart_account(query.owner).await.map_err(|e| {
+        log::error!("Failed to get smart account of {}: {:?}", query.owner, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(SmartAccountResponse { address, deployed }))
+}
+
+#[derive(Deserialize)]
+pub struct PrepareUserOpRedeemQuery {
+    nft_id: String,
+    content: String,
+    /// The smart account holding the token now.
+    account: Address,
+    /// Owner key of `account`, needed until the account is deployed.
+    owner: Option<Address>,
+}
+
+#[derive(Serialize)]
+pub struct PrepareUserOpRedeemResponse {
+    user_operation: UserOperation,
+    user_op_hash: B256,
+}
+
+/// A sponsored user operation redeeming an NFT from the smart account holding it, for the
+/// account's owner key to sign the hash of.
+pub async fn prepare_user_op_redeem<A: TeleportDB>(
+    State(ctx): State<RedeemCtx<A>>,
+    Json(query): Json<PrepareUserOpRedeemQuery>,
+) -> Result<Json<PrepareUserOpRedeemResponse>, StatusCode> {
+    let db = ctx.db.lock().await;
+    let nft = db.get_nft(query.nft_id.clone()).map_err(|_| StatusCode::NOT_FOUND)?;
+    drop(db);
+
+    let chain = ctx.chain(Some(nft.chain_id)).ok_or(StatusCode::NOT_FOUND)?;
+    if chain.config.smart_accounts.is_none() {
+        return Err(StatusCode::NOT_IMPLEMENTED);
+    }
+    ensure_holder(ReadChain::new(chain), &nft, query.account).await?;
+    let token_id = U256::from_str(&nft.token_id).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let (user_operation, user_op_hash) =
+        prepare_user_op(chain, query.account, query.owner, token_id, query.content).await.map_err(
+            |e| {
+                log::error!("Failed to prepare user operation redeem of {}: {:?}", nft.token_id, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            },
+        )?;
+    Ok(Json(PrepareUserOpRedeemResponse { user_operation, user_op_hash }))
+}
+
+#[derive(Deserialize)]
+pub struct UserOpRedeemQuery {
+    chain_id: Option<u64>,
+    user_operation: UserOperation,
+}
+
+#[derive(Serialize)]
+pub struct UserOpHashResponse {
+    user_op_hash: B256,
+}
+
+/// Sends a signed redeem user operation to the bundler, with its gas sponsored by the paymaster.
+pub async fn user_op_redeem<A: TeleportDB>(
+    State(ctx): State<RedeemCtx<A>>,
+    Json(query): Json<UserOpRedeemQuery>,
+) -> Result<Json<UserOpHashResponse>, StatusCode> {
+    let chain = ctx.chain(query.chain_id).ok_or(StatusCode::NOT_FOUND)?;
+    if chain.config.smart_accounts.is_none() {
+        return Err(StatusCode::NOT_IMPLEMENTED);
+    }
+    let token_id =
+        user_op_redeemed_token(chain, &query.user_operation).ok_or(StatusCode::BAD_REQUEST)?;
+    check_contract(&ctx.db, chain, None).await.map_err(|e| {
+        log::warn!("Refused user operation redeem of {}: {}", token_id, e);
+        StatusCode::SERVICE_UNAVAILABLE
+    })?;
+    let user_op_hash = send_user_operation(chain, query.user_operation).await.map_err(|e| {
+        log::error!("Failed to send user operation redeem of {}: {:?}", token_id, e);
+        StatusCode::BAD_GATEWAY
+    })?;
+    Ok(Json(UserOpHashResponse { user_op_hash }))
+}
+
+const DEFAULT_REDEMPTION_LINK_TTL_SECS: i64 = 24 * 60 * 60;
+const MAX_REDEMPTION_LINK_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+const DEFAULT_MAX_OPEN_REDEMPTION_LINKS: usize = 3;
+
+/// Open links a single session may hold at once, from `MAX_REDEMPTION_LINKS_PER_SESSION`.
+pub(crate) fn max_open_redemption_links() -> usize {
+    std::env::var("MAX_REDEMPTION_LINKS_PER_SESSION")
+        .ok()
+        .and_then(|max| max.parse().ok())
+        .unwrap_or(DEFAULT_MAX_OPEN_REDEMPTION_LINKS)
+}
+
+fn redemption_link_message(link_id: &str, nft: &NFT, expires_at: i64) -> String {
+    format!("teleport-redemption-link:{}:{}:{}:{}", link_id, nft.chain_id, nft.token_id, expires_at)
+}
+
+/// Checks a `<link_id>.<signature>` token against the enclave signer and the stored link,
+/// returning the link id and the NFT it redeems.
+async fn open_redemption_link<A: TeleportDB>(
+    shared_state: &SharedState<A>,
+    token: &str,
+) -> Result<(String, RedemptionLink, NFT), StatusCode> {
+    let (link_id, signature) = token.split_once('.').ok_or(StatusCode::BAD_REQUEST)?;
+    let signature = Signature::from_str(signature).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let db = shared_state.db.lock().await;
+    let link = db.get_redemption_link(link_id.to_string()).map_err(|_| StatusCode::NOT_FOUND)?;
+    let nft = db.get_nft(link.nft_id.clone()).map_err(|_| StatusCode::NOT_FOUND)?;
+    drop(db);
+
+    let message = redemption_link_message(link_id, &nft, link.expires_at);
+    let signer =
+        signature.recover_address_from_msg(message).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    if signer != shared_state.signer.address() {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    if link.used || link.expires_at <= chrono::Utc::now().timestamp() {
+        return Err(StatusCode::GONE);
+    }
+    Ok((link_id.to_string(), link, nft))
+}
+
+/// Links whose redemption is being sent. A link is only marked used once its redemption went
+/// out, so this keeps two requests from redeeming it at once in the meantime.
+fn links_in_use() -> &'static Mutex<BTreeSet<String>> {
+    static LINKS_IN_USE: OnceLock<Mutex<BTreeSet<String>>> = OnceLock::new();
+    LINKS_IN_USE.get_or_init(Default::default)
+}
+
+/// Holds a link in [`links_in_use`] until dropped.
+struct LinkInUse(String);
+
+impl LinkInUse {
+    fn claim(link_id: &str) -> Option<Self> {
+        links_in_use()
+            .lock()
+            .unwrap()
+            .insert(link_id.to_string())
+            .then(|| Self(link_id.to_string()))
+    }
+}
+
+impl Drop for LinkInUse {
+    fn drop(&mut self) {
+        links_in_use().lock().unwrap().remove(&self.0);
+    }
+}
+
+/// Lets the current holder of an NFT hand a single-use, expiring redemption link to someone
+/// without a wallet. Each open link counts against the holder's session.
+pub async fn create_redemption_link<A: TeleportDB>(
+    jar: CookieJar,
     State(shared_state): State<SharedState<A>>,
-    Query(query): Query<TweetIdQuery>,
-) -> Json<TweetIdResponse> {
+    Json(query): Json<RedemptionLinkQuery>,
+) -> Result<Json<RedemptionLinkResponse>, StatusCode> {
+    let session_id =
+        jar.get(SESSION_ID_COOKIE_NAME).ok_or(StatusCode::UNAUTHORIZED)?.value().to_string();
     let db = shared_state.db.lock().await;
-    let tweet_id = db.get_tweet(query.token_id.clone()).expect("Failed to get tweet id");
+    let session = db.get_session(session_id.clone()).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let nft = db.get_nft(query.nft_id.clone()).map_err(|_| StatusCode::NOT_FOUND)?;
     drop(db);
 
-    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let mut config = ClientConfig::new();
-    config.root_store.add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
-    let tls = MakeRustlsConnect::new(config);
-    let (client, connection) = tokio_postgres::connect(&database_url, tls).await.unwrap();
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            log::error!("connection error: {}", e);
-        }
-    });
-    let token_id_int: i32 = query.token_id.parse().unwrap();
-    client
-        .execute(
-            "UPDATE \"RedeemedIndex\" SET \"tweetId\" = $1 WHERE \"tokenId\" = $2",
-            &[&tweet_id, &token_id_int],
+    let chain = shared_state.chain(Some(nft.chain_id)).ok_or(StatusCode::BAD_REQUEST)?;
+    let address = Address::from_str(&session.address).map_err(|_| StatusCode::FORBIDDEN)?;
+    let is_holder = is_nft_holder(chain, nft.token_id.clone(), address).await.map_err(|e| {
+        log::error!("Failed to look up holders of NFT {}: {:?}", nft.token_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if !is_holder {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let ttl_secs = query
+        .ttl_secs
+        .unwrap_or(DEFAULT_REDEMPTION_LINK_TTL_SECS)
+        .clamp(1, MAX_REDEMPTION_LINK_TTL_SECS);
+    let expires_at = now + ttl_secs;
+    let link_id = cuid::cuid2();
+    let signature = shared_state
+        .signer
+        .sign_message(redemption_link_message(&link_id, &nft, expires_at).as_bytes())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut db = shared_state.db.lock().await;
+    let open_links = db
+        .count_open_redemption_links(session_id.clone(), now)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if open_links >= max_open_redemption_links() {
+        return Err(StatusCode::TOO_MANY_REQUESTS);
+    }
+    db.add_redemption_link(
+        link_id.clone(),
+        RedemptionLink { nft_id: query.nft_id, session_id, expires_at, used: false },
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    drop(db);
+
+    let url = format!(
+        "https://{}/redemptionLink?token={}.{}",
+        shared_state.tee_url,
+        link_id,
+        hex::encode(signature.as_bytes())
+    );
+    Ok(Json(RedemptionLinkResponse { url, expires_at }))
+}
+
+pub async fn redemption_link_form<A: TeleportDB>(
+    State(shared_state): State<SharedState<A>>,
+    Query(query): Query<RedemptionLinkFormQuery>,
+) -> Result<HtmlTemplate<RedemptionLinkTemplate>, StatusCode> {
+    open_redemption_link(&shared_state, &query.token).await?;
+    Ok(HtmlTemplate(RedemptionLinkTemplate { token: query.token }))
+}
+
+pub async fn redeem_with_link<A: TeleportDB>(
+    State(shared_state): State<SharedState<A>>,
+    Json(query): Json<RedeemWithLinkQuery>,
+) -> Result<Json<TxHashResponse>, TxError> {
+    let (link_id, link, nft) = open_redemption_link(&shared_state, &query.token).await?;
+    let _in_use = LinkInUse::claim(&link_id).ok_or(StatusCode::CONFLICT)?;
+    let chain = shared_state.chain(Some(nft.chain_id)).ok_or(StatusCode::BAD_REQUEST)?;
+    check_contract(&shared_state.db, chain, None)
+        .await
+        .map_err(|e| TxError::from_send(&format!("redeem NFT {} via link", nft.token_id), e))?;
+    ensure_not_redeemed(&shared_state.db, nft.chain_id, &nft.token_id).await?;
+
+    // A link redeems for the holder who issued it, and only while they still hold the token.
+    let issuer =
+        shared_state.db.lock().await.get_session(link.session_id).map_err(|_| StatusCode::GONE)?;
+    let holder = Address::from_str(&issuer.address).map_err(|_| StatusCode::GONE)?;
+    let is_holder = is_nft_holder(chain, nft.token_id.clone(), holder).await.map_err(|e| {
+        log::error!("Failed to look up holders of NFT {}: {:?}", nft.token_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if !is_holder {
+        return Err(StatusCode::GONE.into());
+    }
+
+    let tx_hash = redeem_nft(chain, nft.token_id.clone(), holder, query.content)
+        .await
+        .map_err(|e| TxError::from_send(&format!("redeem NFT {} via link", nft.token_id), e))?;
+    // Used up only now, so a redemption that could not be sent leaves the link working.
+    let consumed = shared_state
+        .db
+        .lock()
+        .await
+        .consume_redemption_link(link_id.clone(), chrono::Utc::now().timestamp());
+    if let Err(e) = consumed {
+        log::error!("Failed to mark redemption link {} used: {:?}", link_id, e);
+    }
+    track_tx(&shared_state.db, nft.chain_id, &tx_hash, "redeem", issuer.address).await;
+    Ok(Json(TxHashResponse { hash: tx_hash }))
+}
+
+const EMAIL_CHALLENGE_TTL_SECS: i64 = 15 * 60;
+const DEFAULT_EMAIL_CHALLENGES_PER_MINUTE: u32 = 2;
+
+/// Confirmation emails per account per minute, from `EMAIL_CHALLENGES_PER_MINUTE`.
+fn get_email_challenge_limit() -> u32 {
+    std::env::var("EMAIL_CHALLENGES_PER_MINUTE")
+        .ok()
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(DEFAULT_EMAIL_CHALLENGES_PER_MINUTE)
+}
+
+fn email_challenge_limiter() -> &'static Mutex<RateLimiter<String>> {
+    static EMAIL_CHALLENGE_LIMITER: OnceLock<Mutex<RateLimiter<String>>> = OnceLock::new();
+    EMAIL_CHALLENGE_LIMITER.get_or_init(Default::default)
+}
+
+pub fn session_cookie(jar: &CookieJar) -> Result<String, StatusCode> {
+    Ok(jar.get(SESSION_ID_COOKIE_NAME).ok_or(StatusCode::UNAUTHORIZED)?.value().to_string())
+}
+
+/// Stores a fresh six digit code for `x_id` and emails it. Accounts sending too many, or whose
+/// last code took too many wrong guesses, get 429.
+async fn send_email_challenge<A: TeleportDB>(
+    shared_state: &SharedState<A>,
+    x_id: String,
+    purpose: EmailPurpose,
+    address: String,
+    email: String,
+) -> Result<(), StatusCode> {
+    let allowed = email_challenge_limiter().lock().unwrap().allow(
+        x_id.clone(),
+        Instant::now(),
+        get_email_challenge_limit(),
+    );
+    if !allowed {
+        return Err(StatusCode::TOO_MANY_REQUESTS);
+    }
+    let code = format!("{:06}", rand::random::<u32>() % 1_000_000);
+    let now = chrono::Utc::now().timestamp();
+    let challenge = EmailChallenge {
+        purpose,
+        address,
+        email: email.clone(),
+        code: code.clone(),
+        expires_at: now + EMAIL_CHALLENGE_TTL_SECS,
+        attempts: 0,
+    };
+    let replaced = shared_state
+        .db
+        .lock()
+        .await
+        .set_email_challenge(x_id, challenge, now)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !replaced {
+        return Err(StatusCode::TOO_MANY_REQUESTS);
+    }
+    let text = format!("Your Teleport confirmation code is {}. It expires in 15 minutes.", code);
+    shared_state.mailer.send(&email, "Your Teleport confirmation code", &text).await.map_err(|e| {
+        log::error!("Failed to send confirmation email: {:?}", e);
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+/// Sets the IANA timezone, such as `Europe/Berlin`, that the session's account is notified in.
+pub async fn set_timezone<A: TeleportDB>(
+    jar: CookieJar,
+    State(shared_state): State<SharedState<A>>,
+    Json(query): Json<TimezoneQuery>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = session_cookie(&jar)?;
+    let mut db = shared_state.db.lock().await;
+    let session = db.get_session(session_id).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let timezone = parse_timezone(&query.timezone).map_err(|_| StatusCode::BAD_REQUEST)?;
+    db.set_timezone(session.x_id, timezone.name().to_string())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Sets whether the session's account wants its tokens burned once redeemed. Only applies on
+/// chains whose contract lets the minter wallet burn; editions already burn the redeemed copy.
+pub async fn set_burn_on_redeem<A: TeleportDB>(
+    jar: CookieJar,
+    State(shared_state): State<SharedState<A>>,
+    Json(query): Json<BurnOnRedeemQuery>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = session_cookie(&jar)?;
+    let mut db = shared_state.db.lock().await;
+    let session = db.get_session(session_id).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    db.set_burn_on_redeem(session.x_id, query.enabled)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Unlinks the session's X account from its address and signs it out, so a different account can
+/// be linked by signing in through `/new` again; the new account takes over the preferences.
+/// Refused while any token the address minted, or is minting, names the account: the contract
+/// cannot rebind a token's x_id, and its redemptions post from the account it names.
+pub async fn unlink_account<A: TeleportDB>(
+    jar: CookieJar,
+    State(shared_state): State<SharedState<A>>,
+) -> Result<StatusCode, ApiError> {
+    let session_id = session_cookie(&jar)?;
+    let mut db = shared_state.db.lock().await;
+    let session = db.get_session(session_id).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let user =
+        db.get_user_by_address(session.address.clone()).map_err(|_| StatusCode::NOT_FOUND)?;
+    if user.x_id.as_deref() != Some(session.x_id.as_str()) {
+        return Err(StatusCode::UNAUTHORIZED.into());
+    }
+    let tokens = db
+        .count_creator_nfts(session.address.clone())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if tokens > 0 {
+        return Err(ErrorCode::AccountHasTokens.into());
+    }
+    db.unlink_x_account(session.address.clone()).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    log::info!("Unlinked X account {} from {}", session.x_id, session.address);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Sets whether the session's account is DMed when a token reaches one of its addresses.
+pub async fn set_dm_notifications<A: TeleportDB>(
+    jar: CookieJar,
+    State(shared_state): State<SharedState<A>>,
+    Json(query): Json<DmNotificationsQuery>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = session_cookie(&jar)?;
+    let mut db = shared_state.db.lock().await;
+    let session = db.get_session(session_id).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    db.set_dm_opt_out(session.x_id, !query.enabled)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Pre-approves exact content for redemptions of the session's tokens minted under `policy`,
+/// such as a fixed campaign tweet. The content is moderated once now; redemptions that match it
+/// byte for byte then post without being moderated again. Rejected content is a 422.
+pub async fn approve_content<A: TeleportDB>(
+    jar: CookieJar,
+    State(shared_state): State<SharedState<A>>,
+    Json(query): Json<ApproveContentQuery>,
+) -> Result<Json<ApproveContentResponse>, ApiError> {
+    let session_id = session_cookie(&jar)?;
+    let session = shared_state
+        .db
+        .lock()
+        .await
+        .get_session(session_id)
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+    if oai::calls_openai() && !kill_switch::is_enabled(SideEffect::OpenAi) {
+        return Err(ErrorCode::FeatureDisabled.into());
+    }
+    let moderation = oai::moderate_tweet(&query.content, &query.policy, None).await;
+    if !moderation.safe {
+        return Err(ErrorCode::ModerationRejected.into());
+    }
+    let content_hash = content_hash(&query.content);
+    let approved = ApprovedContent {
+        prompt_version: moderation.prompt_version,
+        approved_at: chrono::Utc::now().timestamp(),
+    };
+    shared_state
+        .db
+        .lock()
+        .await
+        .approve_content(session.x_id, policy_hash(&query.policy), content_hash.clone(), approved)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(ApproveContentResponse { content_hash }))
+}
+
+/// Withdraws a pre-approval, so matching redemptions are moderated as usual again.
+pub async fn revoke_approved_content<A: TeleportDB>(
+    jar: CookieJar,
+    State(shared_state): State<SharedState<A>>,
+    Json(query): Json<RevokeContentQuery>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = session_cookie(&jar)?;
+    let mut db = shared_state.db.lock().await;
+    let session = db.get_session(session_id).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let revoked = db
+        .revoke_approved_content(
+            session.x_id,
+            policy_hash(&query.policy),
+            query.content_hash.to_lowercase(),
         )
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(if revoked { StatusCode::NO_CONTENT } else { StatusCode::NOT_FOUND })
+}
+
+/// Captures an optional recovery email for the session's account and sends a confirmation code.
+pub async fn add_email<A: TeleportDB>(
+    jar: CookieJar,
+    State(shared_state): State<SharedState<A>>,
+    Json(query): Json<EmailQuery>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = session_cookie(&jar)?;
+    let session = shared_state
+        .db
+        .lock()
+        .await
+        .get_session(session_id)
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+    if !query.email.contains('@') {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    send_email_challenge(
+        &shared_state,
+        session.x_id,
+        EmailPurpose::Verify,
+        session.address,
+        query.email,
+    )
+    .await?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+pub async fn verify_email<A: TeleportDB>(
+    jar: CookieJar,
+    State(shared_state): State<SharedState<A>>,
+    Json(query): Json<EmailCodeQuery>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = session_cookie(&jar)?;
+    let mut db = shared_state.db.lock().await;
+    let session = db.get_session(session_id).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let challenge = db
+        .verify_email_challenge(session.x_id.clone(), query.code, chrono::Utc::now().timestamp())
+        .map_err(|_| StatusCode::FORBIDDEN)?;
+    if challenge.purpose != EmailPurpose::Verify || challenge.address != session.address {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    db.set_recovery_email(
+        session.x_id,
+        RecoveryEmail { address: challenge.address, email: challenge.email },
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(StatusCode::OK)
+}
+
+/// Starts recovering the account previously bound to the session's x_id. The session must come
+/// from a fresh Twitter login; the code goes to the email verified for the original account.
+pub async fn start_recovery<A: TeleportDB>(
+    jar: CookieJar,
+    State(shared_state): State<SharedState<A>>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = session_cookie(&jar)?;
+    let db = shared_state.db.lock().await;
+    let session = db.get_session(session_id).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let recovery =
+        db.get_recovery_email(session.x_id.clone()).map_err(|_| StatusCode::NOT_FOUND)?;
+    drop(db);
+    if recovery.address == session.address {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    send_email_challenge(
+        &shared_state,
+        session.x_id,
+        EmailPurpose::Recover,
+        recovery.address,
+        recovery.email,
+    )
+    .await?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Confirms a recovery code and re-binds the original account to a new session, carrying over
+/// the access tokens from the fresh Twitter login.
+pub async fn confirm_recovery<A: TeleportDB>(
+    jar: CookieJar,
+    State(shared_state): State<SharedState<A>>,
+    Json(query): Json<EmailCodeQuery>,
+) -> Result<(CookieJar, Json<RecoveredAccountResponse>), StatusCode> {
+    let session_id = session_cookie(&jar)?;
+    let mut db = shared_state.db.lock().await;
+    let session = db.get_session(session_id).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let challenge = db
+        .verify_email_challenge(session.x_id.clone(), query.code, chrono::Utc::now().timestamp())
+        .map_err(|_| StatusCode::FORBIDDEN)?;
+    let recovery =
+        db.get_recovery_email(session.x_id.clone()).map_err(|_| StatusCode::NOT_FOUND)?;
+    if challenge.purpose != EmailPurpose::Recover || challenge.address != recovery.address {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let fresh_user =
+        db.get_user_by_address(session.address.clone()).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let mut original_user =
+        db.get_user_by_address(recovery.address.clone()).map_err(|_| StatusCode::NOT_FOUND)?;
+    if original_user.x_id.as_deref() != Some(session.x_id.as_str()) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if fresh_user.access_tokens.is_some() {
+        original_user.access_tokens = fresh_user.access_tokens;
+    }
+    db.add_user(recovery.address.clone(), original_user)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let new_session_id = db
+        .add_session(Session { x_id: session.x_id, address: recovery.address.clone() })
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    drop(db);
+
+    log::info!("Recovered account {}", recovery.address);
+    Ok((
+        jar.add(
+            Cookie::build((SESSION_ID_COOKIE_NAME, new_session_id))
+                .secure(true)
+                .http_only(false)
+                .same_site(SameSite::None),
+        ),
+        Json(RecoveredAccountResponse { address: recovery.address }),
+    ))
+}
+
+pub async fn check_redeem<A: TeleportDB>(
+    State(shared_state): State<SharedState<A>>,
+    Json(query): Json<CheckRedeemQuery>,
+) -> Result<Json<CheckRedeemResponse>, ApiError> {
+    let db = shared_state.db.lock().await;
+    let threshold = db
+        .get_policy_precheck_threshold(query.policy.clone())
+        .ok()
+        .or_else(oai::get_default_precheck_threshold);
+    let license_rule = db
+        .get_policy_license_rule(query.policy.clone())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    drop(db);
+    if oai::calls_openai() && !kill_switch::is_enabled(SideEffect::OpenAi) {
+        return Err(ErrorCode::FeatureDisabled.into());
+    }
+    let safe = oai::moderate_tweet_with_precheck(&query.content, &query.policy, threshold, None)
         .await
-        .expect("Failed to update tweetId in RedeemedIndex");
+        .safe;
+    let content = TweetContent::parse(&query.content);
+    let unsupported = shared_state
+        .twitter_builder
+        .capabilities
+        .unsupported(content.media_url.is_some(), content.poll.is_some());
+    let license_violation = license::check(license_rule.as_ref(), content.license.as_ref())
+        .err()
+        .map(|violation| violation.to_string());
+    Ok(Json(CheckRedeemResponse {
+        safe: safe && license_violation.is_none(),
+        unsupported,
+        license_violation,
+    }))
+}
 
-    Json(TweetIdResponse { tweet_id })
+/// The tweet a redeemed token produced. The marketplace index gets it with the redemption itself.
+pub async fn get_tweet_id<A: TeleportDB>(
+    State(ctx): State<ReadCtx<A>>,
+    Query(query): Query<TweetIdQuery>,
+) -> Result<Json<TweetIdResponse>, StatusCode> {
+    let chain_id = query.chain_id.unwrap_or(ctx.default_chain_id);
+    let tweet_id = ctx
+        .db
+        .lock()
+        .await
+        .get_tweet(chain_id, query.token_id)
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok(Json(TweetIdResponse { tweet_id }))
+}
+
+/// The fulfillment registry anchor for a redeemed token: the transaction that recorded it and,
+/// for batched anchors, the merkle root and the proof a contract needs to check it against.
+pub async fn get_anchor<A: TeleportDB>(
+    State(ctx): State<ReadCtx<A>>,
+    Query(query): Query<TweetIdQuery>,
+) -> Result<Json<ContentAnchor>, StatusCode> {
+    let chain_id = query.chain_id.unwrap_or(ctx.default_chain_id);
+    let anchor = ctx
+        .db
+        .lock()
+        .await
+        .get_anchor(chain_id, query.token_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    anchor.map(Json).ok_or(StatusCode::NOT_FOUND)
 }
 
 pub async fn approve_mint<A: TeleportDB>(
@@ -327,6 +1843,201 @@ pub async fn approve_mint<A: TeleportDB>(
     Ok(HtmlTemplate(template))
 }
 
+#[derive(Serialize)]
+pub struct VersionResponse {
+    version: &'static str,
+    git_commit: &'static str,
+    build_timestamp: &'static str,
+    mr_enclave: Option<String>,
+    mr_signer: Option<String>,
+    features: Vec<&'static str>,
+    default_chain_id: u64,
+    chain_ids: Vec<u64>,
+}
+
+fn enabled_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "https") {
+        features.push("https");
+    }
+    if cfg!(feature = "local-moderation") {
+        features.push("local-moderation");
+    }
+    if cfg!(feature = "event-bus") {
+        features.push("event-bus");
+    }
+    features
+}
+
+/// JSON schemas of the event payloads delivered to webhooks.
+pub async fn get_event_schemas() -> Json<serde_json::Value> {
+    Json(events::schemas())
+}
+
+pub async fn get_version<A: TeleportDB>(State(ctx): State<ReadCtx<A>>) -> Json<VersionResponse> {
+    Json(VersionResponse {
+        version: env!("CARGO_PKG_VERSION"),
+        git_commit: env!("TELEPORT_GIT_COMMIT"),
+        build_timestamp: env!("TELEPORT_BUILD_TIMESTAMP"),
+        mr_enclave: ctx.measurement.map(|m| alloy::hex::encode(m.mr_enclave)),
+        mr_signer: ctx.measurement.map(|m| alloy::hex::encode(m.mr_signer)),
+        features: enabled_features(),
+        default_chain_id: ctx.default_chain_id,
+        chain_ids: ctx.chain_ids,
+    })
+}
+
+/// Status of a mint or redeem transaction, following fee-bump replacements to the latest one.
+pub async fn get_tx_status<A: TeleportDB>(
+    State(ctx): State<ReadCtx<A>>,
+    Query(query): Query<TxStatusQuery>,
+) -> Result<Json<TxStatusResponse>, StatusCode> {
+    let db = ctx.db.lock().await;
+    let mut hash = query.hash.to_lowercase();
+    let mut tx = db.get_tx(hash.clone()).map_err(|_| StatusCode::NOT_FOUND)?;
+    for _ in 0..MAX_TX_REPLACEMENTS {
+        let Some(replacement) = tx.replaced_by.clone() else {
+            break;
+        };
+        tx = db.get_tx(replacement.clone()).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        hash = replacement;
+    }
+    Ok(Json(TxStatusResponse { hash, tx }))
+}
+
+pub async fn get_nft_status<A: TeleportDB>(
+    State(ctx): State<ReadCtx<A>>,
+    Query(query): Query<NftStatusQuery>,
+) -> Result<Json<NftStatusResponse>, StatusCode> {
+    let db = ctx.db.lock().await;
+    let nft_id = query.nft_id;
+    let (chain_id, status) = if let Ok(nft) = db.get_nft(nft_id.clone()) {
+        (nft.chain_id, NftStatus::Minted { token_id: nft.token_id })
+    } else if let Some(confirmation) = db
+        .get_mint_confirmation_by_nft_id(nft_id.clone())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        let status = NftStatus::Confirming {
+            token_id: confirmation.token_id,
+            confirmations: confirmation.confirmations,
+            required_confirmations: get_mint_confirmations(),
+        };
+        (confirmation.chain_id, status)
+    } else {
+        let pending_nft = db
+            .get_pending_nft_by_id(nft_id.clone())
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::NOT_FOUND)?;
+        (pending_nft.chain_id, NftStatus::Pending)
+    };
+    Ok(Json(NftStatusResponse { nft_id, chain_id, status }))
+}
+
+const DEFAULT_TX_CANCEL_MIN_AGE_SECS: i64 = 120;
+
+/// How long a transaction must have been pending before it can be cancelled, from
+/// `TX_CANCEL_MIN_AGE_SECS`. The stuck transaction monitor gets the first chance to bump it.
+fn get_tx_cancel_min_age() -> i64 {
+    std::env::var("TX_CANCEL_MIN_AGE_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(DEFAULT_TX_CANCEL_MIN_AGE_SECS)
+}
+
+/// Cancels a stuck transaction the minter wallet sent for the caller, by replacing it with a
+/// zero-value transfer under the same nonce with higher fees. The address it was sent for must
+/// sign a `CancelRequest` for its hash, and it must have been pending a while. Mined, replaced
+/// and already cancelled transactions conflict.
+pub async fn cancel_tx<A: TeleportDB>(
+    State(shared_state): State<SharedState<A>>,
+    Path(hash): Path<String>,
+    Json(query): Json<CancelTxQuery>,
+) -> Result<Json<TxHashResponse>, TxError> {
+    let hash = hash.to_lowercase();
+    let tx =
+        shared_state.db.lock().await.get_tx(hash.clone()).map_err(|_| StatusCode::NOT_FOUND)?;
+    if tx.status != TxStatus::Pending {
+        return Err(StatusCode::CONFLICT.into());
+    }
+    if chrono::Utc::now().timestamp() - tx.submitted_at < get_tx_cancel_min_age() {
+        return Err(StatusCode::TOO_EARLY.into());
+    }
+    let requested_by = tx.requested_by.ok_or(StatusCode::FORBIDDEN)?;
+    let requested_by =
+        Address::from_str(&requested_by).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let chain = shared_state.chain(Some(tx.chain_id)).ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let tx_hash = TxHash::from_str(&hash).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let signature = Signature::from_str(&query.signature).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let domain = RedeemDomain::new(tx.chain_id, chain.config.nft_address);
+    let signer = CancelRequest::new(tx_hash)
+        .signer(&domain, &signature)
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    if signer != requested_by {
+        return Err(StatusCode::UNAUTHORIZED.into());
+    }
+
+    let replacement = chain
+        .nonces
+        .cancel(chain.submit_provider(), &chain.config.gas, tx_hash, get_fee_bump_percent())
+        .await
+        .map_err(|e| match e.downcast_ref::<NotInFlight>() {
+            Some(_) => StatusCode::CONFLICT.into(),
+            None => TxError::from_send(&format!("cancel transaction {}", hash), e),
+        })?;
+    let cancel_hash = replacement.new_tx_hash.encode_hex_with_prefix();
+    let now = chrono::Utc::now().timestamp();
+    if let Err(e) = shared_state.db.lock().await.cancel_tx(hash.clone(), cancel_hash.clone(), now) {
+        log::error!("Failed to record cancellation of {}: {:?}", hash, e);
+    }
+    log::warn!(
+        "Cancelled {} transaction {} (nonce {}) for {} with {}",
+        tx.kind,
+        hash,
+        replacement.nonce,
+        requested_by,
+        cancel_hash
+    );
+    metrics::increment("transactions_cancelled_total", &[("kind", &tx.kind)]);
+    Ok(Json(TxHashResponse { hash: cancel_hash }))
+}
+
+const DEFAULT_STATS_RANGE_DAYS: u64 = 30;
+
+fn parse_stats_day(day: Option<String>, default: NaiveDate) -> Result<NaiveDate, StatusCode> {
+    match day {
+        Some(day) => NaiveDate::from_str(&day).map_err(|_| StatusCode::BAD_REQUEST),
+        None => Ok(default),
+    }
+}
+
+/// Serves a creator's daily aggregates from the rollup table rather than the raw event tables.
+pub async fn get_creator_stats<A: TeleportDB>(
+    State(ctx): State<ReadCtx<A>>,
+    Query(query): Query<CreatorStatsQuery>,
+) -> Result<Json<Vec<CreatorDailyStats>>, StatusCode> {
+    let today = chrono::Utc::now().date_naive();
+    let to = parse_stats_day(query.to, today)?;
+    let default_from = to - chrono::Days::new(DEFAULT_STATS_RANGE_DAYS - 1);
+    let from = parse_stats_day(query.from, default_from)?;
+    if from > to {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let stats = ctx
+        .marketplace
+        .get_creator_daily_stats(query.user_id, from.to_string(), to.to_string())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to read creator stats: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok(Json(stats))
+}
+
+pub async fn get_metrics() -> String {
+    metrics::render()
+}
+
 pub async fn hello_world() -> &'static str {
     log::info!("Hello, World!");
     "Hello, World!"
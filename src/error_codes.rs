@@ -0,0 +1,239 @@
+use axum::{
+    extract::Request,
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+/// Longest plain-text error body kept as the message when it is given a code.
+const MAX_ERROR_TEXT: usize = 4096;
+
+/// The stable, machine-readable reason returned as `code` in every error body. Frontends branch on
+/// and translate from the code; the English `error` next to it is for people reading logs and
+/// may change. Codes are never renamed or reused once shipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    InvalidRequest,
+    Unauthenticated,
+    Forbidden,
+    NotFound,
+    Conflict,
+    Expired,
+    PaymentRequired,
+    PayloadTooLarge,
+    Unprocessable,
+    TooEarly,
+    RateLimited,
+    NotImplemented,
+    UpstreamFailed,
+    Unavailable,
+    Internal,
+    /// Moderation found the content breaks its policy.
+    ModerationRejected,
+    /// The token has already been redeemed, so it cannot be again.
+    TokenAlreadyRedeemed,
+    /// X is rate limiting the account the request needed it to act for.
+    TwitterRateLimited,
+    /// Simulating the transaction reverted; `error` carries the decoded reason.
+    TransactionReverted,
+    /// The NFT contract is paused or otherwise cannot take transactions right now.
+    ContractPaused,
+    /// An operator has switched off the side effect the request needs.
+    FeatureDisabled,
+    /// The creator is not allowed to mint.
+    MintDenied,
+    /// The X account cannot be unlinked while tokens name it.
+    AccountHasTokens,
+    /// The X account is already linked to another address.
+    XAccountInUse,
+    /// This instance runs without signing keys, so it cannot sign or send transactions.
+    WalletUnavailable,
+}
+
+impl ErrorCode {
+    /// The generic code for a status, for errors nothing more specific is known about.
+    pub fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::BAD_REQUEST => Self::InvalidRequest,
+            StatusCode::UNAUTHORIZED => Self::Unauthenticated,
+            StatusCode::FORBIDDEN => Self::Forbidden,
+            StatusCode::NOT_FOUND => Self::NotFound,
+            StatusCode::CONFLICT => Self::Conflict,
+            StatusCode::GONE => Self::Expired,
+            StatusCode::PAYMENT_REQUIRED => Self::PaymentRequired,
+            StatusCode::PAYLOAD_TOO_LARGE => Self::PayloadTooLarge,
+            StatusCode::UNPROCESSABLE_ENTITY => Self::Unprocessable,
+            StatusCode::TOO_EARLY => Self::TooEarly,
+            StatusCode::TOO_MANY_REQUESTS => Self::RateLimited,
+            StatusCode::NOT_IMPLEMENTED => Self::NotImplemented,
+            StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT => Self::UpstreamFailed,
+            StatusCode::SERVICE_UNAVAILABLE => Self::Unavailable,
+            status if status.is_client_error() => Self::InvalidRequest,
+            _ => Self::Internal,
+        }
+    }
+
+    pub fn status(self) -> StatusCode {
+        match self {
+            Self::InvalidRequest => StatusCode::BAD_REQUEST,
+            Self::Unauthenticated => StatusCode::UNAUTHORIZED,
+            Self::Forbidden | Self::MintDenied => StatusCode::FORBIDDEN,
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::Conflict |
+            Self::TokenAlreadyRedeemed |
+            Self::AccountHasTokens |
+            Self::XAccountInUse => StatusCode::CONFLICT,
+            Self::Expired => StatusCode::GONE,
+            Self::PaymentRequired => StatusCode::PAYMENT_REQUIRED,
+            Self::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::Unprocessable | Self::ModerationRejected | Self::TransactionReverted => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            Self::TooEarly => StatusCode::TOO_EARLY,
+            Self::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            Self::NotImplemented | Self::WalletUnavailable => StatusCode::NOT_IMPLEMENTED,
+            Self::UpstreamFailed => StatusCode::BAD_GATEWAY,
+            Self::Unavailable |
+            Self::TwitterRateLimited |
+            Self::ContractPaused |
+            Self::FeatureDisabled => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn message(self) -> &'static str {
+        match self {
+            Self::InvalidRequest => "The request is malformed",
+            Self::Unauthenticated => "Sign in to do this",
+            Self::Forbidden => "Not allowed",
+            Self::NotFound => "Not found",
+            Self::Conflict => "Conflicts with the current state",
+            Self::Expired => "No longer available",
+            Self::PaymentRequired => "Payment required",
+            Self::PayloadTooLarge => "The request is too large",
+            Self::Unprocessable => "The request cannot be processed",
+            Self::TooEarly => "Too early, try again later",
+            Self::RateLimited => "Too many requests",
+            Self::NotImplemented => "Not supported",
+            Self::UpstreamFailed => "An upstream service failed",
+            Self::Unavailable => "Temporarily unavailable",
+            Self::Internal => "Internal error",
+            Self::ModerationRejected => "The content breaks its policy",
+            Self::TokenAlreadyRedeemed => "The token has already been redeemed",
+            Self::TwitterRateLimited => "X is rate limiting this account, try again later",
+            Self::TransactionReverted => "The transaction would revert",
+            Self::ContractPaused => "The contract is not taking transactions",
+            Self::FeatureDisabled => "This is switched off for now",
+            Self::MintDenied => "Not allowed to mint",
+            Self::AccountHasTokens => {
+                "Tokens were minted with this X account, so it cannot be unlinked"
+            }
+            Self::XAccountInUse => "This X account is linked to another address",
+            Self::WalletUnavailable => "This instance holds no signing keys",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    pub error: String,
+}
+
+/// An error response with a specific code. Handlers that only know a status can keep returning a
+/// bare `StatusCode`; [`describe_errors`] gives it a body.
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub body: ErrorBody,
+}
+
+impl ApiError {
+    pub fn new(code: ErrorCode) -> Self {
+        Self::with_message(code, code.message())
+    }
+
+    pub fn with_message(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self { status: code.status(), body: ErrorBody { code, error: message.into() } }
+    }
+}
+
+impl From<ErrorCode> for ApiError {
+    fn from(code: ErrorCode) -> Self {
+        Self::new(code)
+    }
+}
+
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        let code = ErrorCode::from_status(status);
+        Self { status, body: ErrorBody { code, error: code.message().to_string() } }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self.body)).into_response()
+    }
+}
+
+/// Gives error responses without a JSON body one with the generic code for their status, so
+/// every error carries a code. Plain-text bodies, such as extractor rejections, become the
+/// message; headers such as `Retry-After` are kept.
+pub async fn describe_errors(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    let status = response.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return response;
+    }
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|content_type| content_type.as_bytes().starts_with(b"application/json"));
+    if is_json {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let mut error = ApiError::from(status);
+    match axum::body::to_bytes(body, MAX_ERROR_TEXT).await {
+        Ok(text) if !text.is_empty() => error.body.error = String::from_utf8_lossy(&text).into(),
+        _ => {}
+    }
+    parts.headers.remove(header::CONTENT_TYPE);
+    parts.headers.remove(header::CONTENT_LENGTH);
+    let mut described = error.into_response();
+    described.headers_mut().extend(parts.headers);
+    described
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_serialize_as_stable_strings() {
+        let body = ErrorBody {
+            code: ErrorCode::TokenAlreadyRedeemed,
+            error: "The token has already been redeemed".to_string(),
+        };
+        let json = serde_json::to_value(&body).unwrap();
+        assert_eq!(json["code"], "TOKEN_ALREADY_REDEEMED");
+        assert_eq!(
+            serde_json::to_value(ErrorCode::TwitterRateLimited).unwrap(),
+            "TWITTER_RATE_LIMITED"
+        );
+    }
+
+    #[test]
+    fn statuses_map_to_generic_codes() {
+        assert_eq!(ErrorCode::from_status(StatusCode::GONE), ErrorCode::Expired);
+        assert_eq!(ErrorCode::from_status(StatusCode::IM_A_TEAPOT), ErrorCode::InvalidRequest);
+        assert_eq!(ErrorCode::from_status(StatusCode::BAD_GATEWAY), ErrorCode::UpstreamFailed);
+        for code in [ErrorCode::NotFound, ErrorCode::Conflict, ErrorCode::RateLimited] {
+            assert_eq!(ErrorCode::from_status(code.status()), code);
+        }
+    }
+}
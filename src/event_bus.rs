@@ -0,0 +1,137 @@
+#[cfg(feature = "event-bus")]
+use tokio::time::{sleep, Duration};
+
+use crate::events::ContractLog;
+#[cfg(feature = "event-bus")]
+use crate::{
+    events::{Envelope, Event},
+    metrics,
+};
+
+#[cfg(feature = "event-bus")]
+const DEFAULT_SUBJECT_PREFIX: &str = "teleport.events";
+#[cfg(feature = "event-bus")]
+const PUBLISH_ATTEMPTS: u32 = 5;
+#[cfg(feature = "event-bus")]
+const PUBLISH_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Subject a contract log is published on. Logs for the same token share a subject, so a
+/// JetStream subject mapping can partition the stream by token id while keeping each token's
+/// events in order.
+#[cfg_attr(not(feature = "event-bus"), allow(dead_code))]
+pub fn subject(prefix: &str, contract_log: &ContractLog) -> String {
+    let key = contract_log.token_id.as_deref().unwrap_or("none");
+    format!("{}.{}.{}", prefix, contract_log.chain_id, key)
+}
+
+/// Deduplication id of a contract log, so JetStream drops the copies that at-least-once
+/// delivery republishes after a restart.
+#[cfg_attr(not(feature = "event-bus"), allow(dead_code))]
+pub fn message_id(contract_log: &ContractLog) -> String {
+    format!("{}:{}:{}", contract_log.chain_id, contract_log.tx_hash, contract_log.log_index)
+}
+
+/// Mirrors indexed contract logs to a NATS JetStream stream at `EVENT_BUS_URL`, under
+/// `EVENT_BUS_SUBJECT_PREFIX`. Disabled when no URL is set or without the `event-bus` feature.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    #[cfg(feature = "event-bus")]
+    publisher: Option<Publisher>,
+}
+
+impl EventBus {
+    pub async fn from_env() -> eyre::Result<Self> {
+        let Ok(url) = std::env::var("EVENT_BUS_URL") else {
+            return Ok(Self::default());
+        };
+        #[cfg(feature = "event-bus")]
+        {
+            let subject_prefix = std::env::var("EVENT_BUS_SUBJECT_PREFIX")
+                .unwrap_or_else(|_| DEFAULT_SUBJECT_PREFIX.to_string());
+            let client = async_nats::connect(&url).await?;
+            log::info!("Publishing contract events to {} under {}", url, subject_prefix);
+            let jetstream = async_nats::jetstream::new(client);
+            Ok(Self { publisher: Some(Publisher { jetstream, subject_prefix }) })
+        }
+        #[cfg(not(feature = "event-bus"))]
+        eyre::bail!("EVENT_BUS_URL is set to {} but the event-bus feature is disabled", url)
+    }
+
+    /// Publishes `contract_log`, retrying with backoff until the stream acknowledges it. Fails
+    /// once every attempt has.
+    pub async fn publish(&self, contract_log: ContractLog) -> eyre::Result<()> {
+        #[cfg(feature = "event-bus")]
+        if let Some(publisher) = &self.publisher {
+            return publisher.publish(contract_log).await;
+        }
+        #[cfg(not(feature = "event-bus"))]
+        drop(contract_log);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "event-bus")]
+#[derive(Clone)]
+struct Publisher {
+    jetstream: async_nats::jetstream::Context,
+    subject_prefix: String,
+}
+
+#[cfg(feature = "event-bus")]
+impl Publisher {
+    async fn publish_once(
+        &self,
+        subject: String,
+        message_id: &str,
+        payload: Vec<u8>,
+    ) -> eyre::Result<()> {
+        let mut headers = async_nats::HeaderMap::new();
+        headers.insert("Nats-Msg-Id", message_id);
+        // The second await waits for the stream's ack.
+        self.jetstream.publish_with_headers(subject, headers, payload.into()).await?.await?;
+        Ok(())
+    }
+
+    async fn publish(&self, contract_log: ContractLog) -> eyre::Result<()> {
+        let subject = subject(&self.subject_prefix, &contract_log);
+        let message_id = message_id(&contract_log);
+        let payload = serde_json::to_vec(&Envelope::from(Event::ContractLog(contract_log)))?;
+        let mut backoff = PUBLISH_INITIAL_BACKOFF;
+        for attempt in 1..=PUBLISH_ATTEMPTS {
+            match self.publish_once(subject.clone(), &message_id, payload.clone()).await {
+                Ok(()) => {
+                    metrics::increment("event_bus_published_total", &[]);
+                    return Ok(());
+                }
+                Err(e) => log::warn!("Publish {} of {} failed: {:?}", attempt, message_id, e),
+            }
+            sleep(backoff).await;
+            backoff *= 2;
+        }
+        metrics::increment("event_bus_publish_failures_total", &[]);
+        eyre::bail!("Gave up publishing {} to the event bus", message_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logs_are_keyed_by_token() {
+        let contract_log = ContractLog {
+            chain_id: 8453,
+            contract: "0x01".to_string(),
+            name: "Transfer".to_string(),
+            token_id: Some("7".to_string()),
+            tx_hash: "0x02".to_string(),
+            block_number: 100,
+            log_index: 3,
+            log: serde_json::Value::Null,
+        };
+        assert_eq!(subject("teleport.events", &contract_log), "teleport.events.8453.7");
+        assert_eq!(message_id(&contract_log), "8453:0x02:3");
+        let approval = ContractLog { token_id: None, ..contract_log };
+        assert_eq!(subject("teleport.events", &approval), "teleport.events.8453.none");
+    }
+}
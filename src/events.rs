@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Version of the event payloads below. Adding an optional field keeps the version; removing,
+/// renaming or retyping a field, or making one required, bumps it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A message for a user, such as a creator's redemption NFT changing hands.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notification {
+    /// X user id of the recipient.
+    pub x_id: String,
+    pub message: String,
+    /// Unix time before which the message should not be delivered, when it was raised during
+    /// the recipient's quiet hours.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deliver_after: Option<i64>,
+}
+
+/// A log emitted by an indexed NFT contract, mirrored to the event bus.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractLog {
+    pub chain_id: u64,
+    pub contract: String,
+    /// Contract event name, such as `RedeemTweet` or `Transfer`.
+    pub name: String,
+    pub token_id: Option<String>,
+    pub tx_hash: String,
+    pub block_number: u64,
+    pub log_index: u64,
+    /// The raw log, as returned by `eth_getLogs`.
+    pub log: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Activity {
+    Minted,
+    Redeemed,
+    Transferred,
+    TweetPosted,
+}
+
+/// Something that happened to one of a creator's tokens, delivered to that creator's own webhook.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenActivity {
+    /// X user id of the creator.
+    pub x_id: String,
+    pub chain_id: u64,
+    pub token_id: String,
+    pub activity: Activity,
+    /// The new holder, for transfers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    /// The redemption's tweet, once posted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tweet_id: Option<String>,
+}
+
+/// Every event pushed to other services, tagged by its `event` name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Notification(Notification),
+    ContractLog(ContractLog),
+    TokenActivity(TokenActivity),
+}
+
+/// The body of every delivered event: its schema version next to the tagged payload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Envelope {
+    pub version: u32,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+impl From<Event> for Envelope {
+    fn from(event: Event) -> Self {
+        Self { version: SCHEMA_VERSION, event }
+    }
+}
+
+fn event_schema(event: &str, description: &str, properties: Value, required: &[&str]) -> Value {
+    let mut properties = properties;
+    properties["version"] = json!({ "type": "integer", "const": SCHEMA_VERSION });
+    properties["event"] = json!({ "type": "string", "const": event });
+    let mut required: Vec<&str> = required.to_vec();
+    required.extend(["version", "event"]);
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": event,
+        "description": description,
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+/// JSON schemas of every event payload, keyed by event name.
+pub fn schemas() -> Value {
+    json!({
+        "version": SCHEMA_VERSION,
+        "events": {
+            "notification": event_schema(
+                "notification",
+                "A message for a user, such as a creator's redemption NFT changing hands.",
+                json!({
+                    "x_id": { "type": "string", "description": "X user id of the recipient." },
+                    "message": { "type": "string" },
+                    "deliver_after": {
+                        "type": "integer",
+                        "description": "Unix time to hold the message until.",
+                    },
+                }),
+                &["x_id", "message"],
+            ),
+            "contract_log": event_schema(
+                "contract_log",
+                "A log emitted by an indexed NFT contract, mirrored to the event bus.",
+                json!({
+                    "chain_id": { "type": "integer" },
+                    "contract": { "type": "string" },
+                    "name": {
+                        "type": "string",
+                        "description": "Contract event name, such as RedeemTweet or Transfer.",
+                    },
+                    "token_id": { "type": ["string", "null"] },
+                    "tx_hash": { "type": "string" },
+                    "block_number": { "type": "integer" },
+                    "log_index": { "type": "integer" },
+                    "log": { "type": "object", "description": "The raw log." },
+                }),
+                &[
+                    "chain_id",
+                    "contract",
+                    "name",
+                    "token_id",
+                    "tx_hash",
+                    "block_number",
+                    "log_index",
+                    "log",
+                ],
+            ),
+            "token_activity": event_schema(
+                "token_activity",
+                "Something that happened to one of a creator's tokens, sent to their webhook.",
+                json!({
+                    "x_id": { "type": "string", "description": "X user id of the creator." },
+                    "chain_id": { "type": "integer" },
+                    "token_id": { "type": "string" },
+                    "activity": {
+                        "type": "string",
+                        "enum": ["minted", "redeemed", "transferred", "tweet_posted"],
+                    },
+                    "to": { "type": "string", "description": "The new holder, for transfers." },
+                    "tweet_id": {
+                        "type": "string",
+                        "description": "The redemption's tweet, once posted.",
+                    },
+                }),
+                &["x_id", "chain_id", "token_id", "activity"],
+            ),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification() -> Envelope {
+        Event::Notification(Notification {
+            x_id: "1234".to_string(),
+            message: "your redemption NFT #1 changed hands".to_string(),
+            deliver_after: None,
+        })
+        .into()
+    }
+
+    /// Consumers parse this exact shape; changing it needs a new schema version.
+    #[test]
+    fn notification_payload_is_stable() -> eyre::Result<()> {
+        let payload = serde_json::to_value(notification())?;
+        assert_eq!(
+            payload,
+            json!({
+                "version": 1,
+                "event": "notification",
+                "x_id": "1234",
+                "message": "your redemption NFT #1 changed hands",
+            })
+        );
+        assert_eq!(serde_json::from_value::<Envelope>(payload)?, notification());
+        Ok(())
+    }
+
+    #[test]
+    fn payloads_match_their_schemas() -> eyre::Result<()> {
+        let schemas = schemas();
+        let contract_log = Envelope::from(Event::ContractLog(ContractLog {
+            chain_id: 8453,
+            contract: "0xe1c4c77c45081dab2eba1d8af9eb468ea6c5cdd8".to_string(),
+            name: "Transfer".to_string(),
+            token_id: Some("1".to_string()),
+            tx_hash: "0x01".to_string(),
+            block_number: 100,
+            log_index: 2,
+            log: json!({}),
+        }));
+        let token_activity = Envelope::from(Event::TokenActivity(TokenActivity {
+            x_id: "1234".to_string(),
+            chain_id: 8453,
+            token_id: "1".to_string(),
+            activity: Activity::TweetPosted,
+            to: None,
+            tweet_id: Some("1800000000000000000".to_string()),
+        }));
+        for envelope in [notification(), contract_log, token_activity] {
+            let payload = serde_json::to_value(envelope)?;
+            let schema = &schemas["events"][payload["event"].as_str().unwrap()];
+            let properties = schema["properties"].as_object().unwrap();
+            for (field, _) in payload.as_object().unwrap() {
+                assert!(properties.contains_key(field), "{} is not in the schema", field);
+            }
+            for field in schema["required"].as_array().unwrap() {
+                assert!(payload.get(field.as_str().unwrap()).is_some(), "{} is missing", field);
+            }
+        }
+        Ok(())
+    }
+}
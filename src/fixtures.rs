@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use alloy::{
+    hex::ToHexExt,
+    network::EthereumWallet,
+    primitives::U256,
+    providers::{Provider, ProviderBuilder},
+    rpc::types::TransactionReceipt,
+    signers::local::{coins_bip39::English, MnemonicBuilder, PrivateKeySigner},
+    sol_types::SolEventInterface,
+};
+use eyre::OptionExt;
+
+use crate::{
+    actions::nft::{
+        content_hash, get_nft_address, policy_hash,
+        NFT::{self, NFTEvents},
+    },
+    db::{
+        lock::TrackedMutex, AccessTokens, ApprovedContent, PendingNFT, Session, TeleportDB, User,
+    },
+};
+
+/// The mnemonic anvil funds its accounts from.
+const ANVIL_MNEMONIC: &str = "test test test test test test test test test test test junk";
+const DEFAULT_FIXTURE_USERS: u32 = 5;
+const DEFAULT_FIXTURE_CHAIN_ID: u64 = 31337;
+const DEFAULT_FIXTURE_RPC_URL: &str = "http://127.0.0.1:8545";
+/// Mock X ids count up from here, far from real ones.
+const FIXTURE_X_ID_BASE: u64 = 9_000_000_000;
+const FIXTURE_POLICY: &str = "Only wholesome posts about the devnet.";
+
+/// What `teleport fixtures` seeds, from `FIXTURE_USERS` (default 5), `FIXTURE_CHAIN_ID` (default
+/// anvil's 31337), `FIXTURE_RPC_URL` and `FIXTURE_MNEMONIC` (default anvil's). Account 0 of the
+/// mnemonic is the minter and must own the chain's `NFT_ADDRESS`; accounts 1 to N are the users,
+/// so they need gas for their transfers.
+pub struct FixtureConfig {
+    pub users: u32,
+    pub chain_id: u64,
+    pub rpc_url: String,
+    pub mnemonic: String,
+}
+
+impl FixtureConfig {
+    pub fn from_env() -> eyre::Result<Self> {
+        let users = match std::env::var("FIXTURE_USERS") {
+            Ok(users) => users.parse()?,
+            Err(_) => DEFAULT_FIXTURE_USERS,
+        };
+        if users < 2 {
+            eyre::bail!("FIXTURE_USERS must be at least 2 for tokens to change hands");
+        }
+        let chain_id = match std::env::var("FIXTURE_CHAIN_ID") {
+            Ok(chain_id) => chain_id.parse()?,
+            Err(_) => DEFAULT_FIXTURE_CHAIN_ID,
+        };
+        Ok(Self {
+            users,
+            chain_id,
+            rpc_url: std::env::var("FIXTURE_RPC_URL")
+                .unwrap_or_else(|_| DEFAULT_FIXTURE_RPC_URL.to_string()),
+            mnemonic: std::env::var("FIXTURE_MNEMONIC")
+                .unwrap_or_else(|_| ANVIL_MNEMONIC.to_string()),
+        })
+    }
+
+    fn signer(&self, index: u32) -> eyre::Result<PrivateKeySigner> {
+        Ok(MnemonicBuilder::<English>::default().phrase(&self.mnemonic).index(index)?.build()?)
+    }
+}
+
+/// A fixture user: a funded devnet account linked to a mock X identity.
+struct FixtureUser {
+    signer: PrivateKeySigner,
+    x_id: String,
+}
+
+/// Seeds a devnet for frontend work: users with mock X identities and sessions, a token minted
+/// by each to the next, some of them transferred on and some redeemed. Only the chain and the
+/// service database are written; the service started on the seeded database indexes the
+/// fixture's blocks like any others, filling the marketplace index through the real handlers.
+/// Redemptions come pre-approved and pre-tweeted under mock tweet ids, so indexing them calls
+/// neither OpenAI nor X. `db` is the empty database of the configured backend, written to
+/// `db_path` once seeded.
+pub async fn seed<A: TeleportDB>(config: &FixtureConfig, db: A, db_path: &str) -> eyre::Result<()> {
+    if std::path::Path::new(db_path).exists() {
+        eyre::bail!("{} already exists, refusing to seed over it", db_path);
+    }
+    let nft_address = get_nft_address(config.chain_id)?;
+    let minter = config.signer(0)?;
+    let provider = ProviderBuilder::new()
+        .with_recommended_fillers()
+        .wallet(EthereumWallet::from(minter))
+        .on_http(config.rpc_url.parse()?);
+    let chain_id = provider.get_chain_id().await?;
+    if chain_id != config.chain_id {
+        eyre::bail!("{} serves chain {}, not {}", config.rpc_url, chain_id, config.chain_id);
+    }
+    let start_block = provider.get_block_number().await?;
+    let nft = NFT::new(nft_address, provider.clone());
+
+    let db = Arc::new(TrackedMutex::new(db));
+    let mut users = Vec::new();
+    for index in 1..=config.users {
+        let signer = config.signer(index)?;
+        let x_id = (FIXTURE_X_ID_BASE + u64::from(index)).to_string();
+        let address = signer.address().to_string();
+        let user = User {
+            x_id: Some(x_id.clone()),
+            access_tokens: Some(AccessTokens {
+                token: format!("{}-fixture", x_id),
+                secret: "fixture".to_string(),
+            }),
+            ..Default::default()
+        };
+        let mut db = db.lock().await;
+        db.add_user(address.clone(), user)?;
+        let session_id =
+            db.add_session(Session { x_id: x_id.clone(), address: address.clone() })?;
+        log::info!("Fixture user {} ({}) has session {}", x_id, address, session_id);
+        users.push(FixtureUser { signer, x_id });
+    }
+
+    // Each user mints a token to the next one.
+    let mut tokens = Vec::new();
+    for (index, creator) in users.iter().enumerate() {
+        let holder = &users[(index + 1) % users.len()];
+        let x_id = U256::from_str_radix(&creator.x_id, 10)?;
+        let receipt = nft
+            .mintTo(holder.signer.address(), x_id, FIXTURE_POLICY.to_string())
+            .send()
+            .await?
+            .get_receipt()
+            .await?;
+        let tx_hash = receipt.transaction_hash.encode_hex_with_prefix();
+        db.lock().await.add_pending_nft(
+            tx_hash,
+            PendingNFT {
+                address: creator.signer.address().to_string(),
+                nft_id: cuid::cuid2(),
+                chain_id,
+                policy_hash: policy_hash(FIXTURE_POLICY),
+            },
+        )?;
+        let token_id = minted_token_id(&receipt)?;
+        log::info!("Minted fixture NFT {} of {} to {}", token_id, creator.x_id, holder.x_id);
+        tokens.push((index, (index + 1) % users.len(), token_id));
+    }
+
+    // Every other token moves on to the user after its holder.
+    for (_, holder, token_id) in tokens.iter_mut().step_by(2) {
+        let from = &users[*holder];
+        let to = (*holder + 1) % users.len();
+        let provider = ProviderBuilder::new()
+            .with_recommended_fillers()
+            .wallet(EthereumWallet::from(from.signer.clone()))
+            .on_http(config.rpc_url.parse()?);
+        NFT::new(nft_address, provider)
+            .transferFrom(from.signer.address(), users[to].signer.address(), *token_id)
+            .send()
+            .await?
+            .get_receipt()
+            .await?;
+        log::info!("Transferred fixture NFT {} to {}", token_id, users[to].x_id);
+        *holder = to;
+    }
+
+    // Every third token is redeemed.
+    for (redemption, (creator, holder, token_id)) in tokens.iter().step_by(3).enumerate() {
+        let creator = &users[*creator];
+        let content = format!("gm from the devnet, fixture redemption #{}", redemption + 1);
+        let mut db_lock = db.lock().await;
+        db_lock.approve_content(
+            creator.x_id.clone(),
+            policy_hash(FIXTURE_POLICY),
+            content_hash(&content),
+            ApprovedContent {
+                prompt_version: "fixture".to_string(),
+                approved_at: chrono::Utc::now().timestamp(),
+            },
+        )?;
+        let tweet_id = format!("{}{}", FIXTURE_X_ID_BASE, token_id);
+        db_lock.add_tweet(chain_id, token_id.to_string(), tweet_id)?;
+        drop(db_lock);
+        nft.redeem(*token_id, content, 0u8).send().await?.get_receipt().await?;
+        log::info!("Redeemed fixture NFT {} held by {}", token_id, users[*holder].x_id);
+    }
+
+    // The service picks up right after the block the fixture started from.
+    let mut db = db.lock().await;
+    db.set_last_processed_block(chain_id, start_block, u64::MAX)?;
+    tokio::fs::write(db_path, db.serialize()?).await?;
+    log::info!(
+        "Seeded {} with {} users and {} tokens on chain {}",
+        db_path,
+        users.len(),
+        tokens.len(),
+        chain_id
+    );
+    Ok(())
+}
+
+fn minted_token_id(receipt: &TransactionReceipt) -> eyre::Result<U256> {
+    receipt
+        .inner
+        .logs()
+        .iter()
+        .find_map(|log| match NFTEvents::decode_raw_log(log.topics(), &log.data().data, true) {
+            Ok(NFTEvents::NewTokenData(new_token_data)) => Some(new_token_data.tokenId),
+            _ => None,
+        })
+        .ok_or_eyre("Mint emitted no NewTokenData")
+}
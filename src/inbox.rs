@@ -0,0 +1,226 @@
+use std::str::FromStr;
+
+use alloy::{hex, primitives::Address};
+use axum::{extract::State, http::StatusCode, Json};
+use axum_extra::extract::cookie::CookieJar;
+use eyre::OptionExt;
+use openssl::{
+    rand::rand_bytes,
+    symm::{decrypt_aead, encrypt_aead, Cipher},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    actions::nft::is_nft_holder,
+    db::{InboxMessage, TeleportDB},
+    endpoints::{session_cookie, SharedState},
+    metrics,
+};
+
+const DEFAULT_MAX_MESSAGE_CHARS: usize = 500;
+const DEFAULT_MESSAGES_PER_DAY: usize = 5;
+pub(crate) const DAY_SECS: i64 = 24 * 60 * 60;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// Longest note a holder may send, from `INBOX_MAX_MESSAGE_CHARS`.
+fn get_max_message_chars() -> usize {
+    std::env::var("INBOX_MAX_MESSAGE_CHARS")
+        .ok()
+        .and_then(|max| max.parse().ok())
+        .unwrap_or(DEFAULT_MAX_MESSAGE_CHARS)
+}
+
+/// Notes each holder address may send per day, from `INBOX_MESSAGES_PER_DAY`.
+pub(crate) fn get_messages_per_day() -> usize {
+    std::env::var("INBOX_MESSAGES_PER_DAY")
+        .ok()
+        .and_then(|max| max.parse().ok())
+        .unwrap_or(DEFAULT_MESSAGES_PER_DAY)
+}
+
+/// The AES-256-GCM key inbox messages are sealed with. It is derived from the enclave's signing
+/// key, so neither the host nor the database ever sees plaintext.
+pub struct InboxKey([u8; 32]);
+
+impl InboxKey {
+    pub fn derive(signing_key: &[u8]) -> Self {
+        Self(
+            Sha256::new()
+                .chain_update(b"teleport-inbox")
+                .chain_update(signing_key)
+                .finalize()
+                .into(),
+        )
+    }
+
+    /// Encrypts `plaintext` bound to the message id, as hex `nonce:ciphertext||tag`.
+    pub fn seal(&self, id: &str, plaintext: &str) -> eyre::Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand_bytes(&mut nonce)?;
+        let mut tag = [0u8; TAG_LEN];
+        let ciphertext = encrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.0,
+            Some(&nonce),
+            id.as_bytes(),
+            plaintext.as_bytes(),
+            &mut tag,
+        )?;
+        Ok(format!("{}:{}{}", hex::encode(nonce), hex::encode(ciphertext), hex::encode(tag)))
+    }
+
+    /// Decrypts a message sealed under `id`, failing if either was tampered with.
+    pub fn open(&self, id: &str, sealed: &str) -> eyre::Result<String> {
+        let (nonce, sealed) = sealed.split_once(':').ok_or_eyre("Malformed inbox message")?;
+        let nonce = hex::decode(nonce)?;
+        let sealed = hex::decode(sealed)?;
+        if sealed.len() < TAG_LEN {
+            eyre::bail!("Inbox message too short");
+        }
+        let (ciphertext, tag) = sealed.split_at(sealed.len() - TAG_LEN);
+        let plaintext = decrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.0,
+            Some(&nonce),
+            id.as_bytes(),
+            ciphertext,
+            tag,
+        )?;
+        Ok(String::from_utf8(plaintext)?)
+    }
+}
+
+fn inbox_key<A: TeleportDB>(shared_state: &SharedState<A>) -> InboxKey {
+    InboxKey::derive(shared_state.signer.to_bytes().as_slice())
+}
+
+#[derive(Deserialize)]
+pub struct SendMessageQuery {
+    nft_id: String,
+    message: String,
+}
+
+/// Sends the creator of an NFT a short note from its current holder, such as what they would
+/// like the redemption to say. Notes are encrypted at rest and rate limited per holder address.
+pub async fn send_message<A: TeleportDB>(
+    jar: CookieJar,
+    State(shared_state): State<SharedState<A>>,
+    Json(query): Json<SendMessageQuery>,
+) -> Result<StatusCode, StatusCode> {
+    let message = query.message.trim();
+    if message.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if message.chars().count() > get_max_message_chars() {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+    let session_id = session_cookie(&jar)?;
+    let db = shared_state.db.lock().await;
+    let session = db.get_session(session_id).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let nft = db.get_nft(query.nft_id).map_err(|_| StatusCode::NOT_FOUND)?;
+    let creator = db
+        .get_user_by_address(nft.address.clone())
+        .ok()
+        .and_then(|user| user.x_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    drop(db);
+
+    let chain = shared_state.chain(Some(nft.chain_id)).ok_or(StatusCode::BAD_REQUEST)?;
+    let address = Address::from_str(&session.address).map_err(|_| StatusCode::FORBIDDEN)?;
+    let is_holder = is_nft_holder(chain, nft.token_id.clone(), address).await.map_err(|e| {
+        log::error!("Failed to look up holders of NFT {}: {:?}", nft.token_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if !is_holder {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let id = cuid::cuid2();
+    let sealed = inbox_key(&shared_state).seal(&id, message).map_err(|e| {
+        log::error!("Failed to encrypt inbox message: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let now = chrono::Utc::now().timestamp();
+    let sender = session.address.to_lowercase();
+    let mut db = shared_state.db.lock().await;
+    let sent_today = db
+        .count_inbox_messages_since(sender.clone(), now - DAY_SECS)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if sent_today >= get_messages_per_day() {
+        return Err(StatusCode::TOO_MANY_REQUESTS);
+    }
+    db.add_inbox_message(InboxMessage {
+        id,
+        chain_id: nft.chain_id,
+        token_id: nft.token_id,
+        creator,
+        sender,
+        sealed,
+        sent_at: now,
+    })
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    drop(db);
+
+    metrics::increment("inbox_messages_total", &[("chain_id", &nft.chain_id.to_string())]);
+    Ok(StatusCode::CREATED)
+}
+
+#[derive(Serialize)]
+pub struct InboxEntry {
+    id: String,
+    chain_id: u64,
+    token_id: String,
+    sender: String,
+    message: String,
+    sent_at: i64,
+}
+
+/// The notes holders sent the session's account about its tokens, newest first.
+pub async fn get_messages<A: TeleportDB>(
+    jar: CookieJar,
+    State(shared_state): State<SharedState<A>>,
+) -> Result<Json<Vec<InboxEntry>>, StatusCode> {
+    let session_id = session_cookie(&jar)?;
+    let db = shared_state.db.lock().await;
+    let session = db.get_session(session_id).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let messages = db.get_inbox(session.x_id).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    drop(db);
+
+    let key = inbox_key(&shared_state);
+    let entries = messages
+        .into_iter()
+        .map(|message| {
+            Ok(InboxEntry {
+                message: key.open(&message.id, &message.sealed)?,
+                id: message.id,
+                chain_id: message.chain_id,
+                token_id: message.token_id,
+                sender: message.sender,
+                sent_at: message.sent_at,
+            })
+        })
+        .collect::<eyre::Result<_>>()
+        .map_err(|e| {
+            log::error!("Failed to decrypt inbox: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok(Json(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_messages_are_bound_to_their_id() -> eyre::Result<()> {
+        let key = InboxKey::derive(&[7u8; 32]);
+        let sealed = key.seal("a", "please mention the launch")?;
+        assert!(!sealed.contains("launch"));
+        assert_eq!(key.open("a", &sealed)?, "please mention the launch");
+        assert!(key.open("b", &sealed).is_err());
+        assert!(InboxKey::derive(&[8u8; 32]).open("a", &sealed).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    collections::BTreeSet,
+    str::FromStr,
+    sync::{OnceLock, RwLock},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::TeleportDB;
+
+/// A side effect operators can switch off on its own during an incident. Work that needs a
+/// switched off side effect waits in its queue (the event retry queue, queued burns, the relay
+/// cursor) instead of running, and requests that would need it right away get a 503.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum SideEffect {
+    #[serde(rename = "tweets")]
+    Tweets,
+    #[serde(rename = "transactions")]
+    Transactions,
+    #[serde(rename = "top_ups")]
+    TopUps,
+    #[serde(rename = "openai")]
+    OpenAi,
+}
+
+impl SideEffect {
+    pub const ALL: [Self; 4] = [Self::Tweets, Self::Transactions, Self::TopUps, Self::OpenAi];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Tweets => "tweets",
+            Self::Transactions => "transactions",
+            Self::TopUps => "top_ups",
+            Self::OpenAi => "openai",
+        }
+    }
+}
+
+impl FromStr for SideEffect {
+    type Err = eyre::Report;
+
+    fn from_str(name: &str) -> eyre::Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|effect| effect.name() == name)
+            .ok_or_else(|| eyre::eyre!("Unknown side effect {}", name))
+    }
+}
+
+/// Work that was not done because its side effect is switched off. Queued work that fails with
+/// it is retried without counting an attempt.
+#[derive(Debug)]
+pub struct SideEffectDisabled(pub SideEffect);
+
+impl std::fmt::Display for SideEffectDisabled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The {} kill switch is on; try again once it is released", self.0.name())
+    }
+}
+
+impl std::error::Error for SideEffectDisabled {}
+
+fn disabled() -> &'static RwLock<BTreeSet<SideEffect>> {
+    static DISABLED: OnceLock<RwLock<BTreeSet<SideEffect>>> = OnceLock::new();
+    DISABLED.get_or_init(Default::default)
+}
+
+/// Switches off the side effects in `KILL_SWITCHES` (comma separated), then applies the switches
+/// admins have flipped since, which outlive restarts.
+pub fn init<A: TeleportDB>(db: &A) -> eyre::Result<()> {
+    let mut switched_off = BTreeSet::new();
+    if let Ok(names) = std::env::var("KILL_SWITCHES") {
+        for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            switched_off.insert(SideEffect::from_str(name)?);
+        }
+    }
+    for (name, enabled) in db.get_kill_switches()? {
+        let effect = SideEffect::from_str(&name)?;
+        if enabled {
+            switched_off.remove(&effect);
+        } else {
+            switched_off.insert(effect);
+        }
+    }
+    for effect in &switched_off {
+        log::warn!("Kill switch on: {}", effect.name());
+    }
+    *disabled().write().unwrap() = switched_off;
+    Ok(())
+}
+
+pub fn is_enabled(effect: SideEffect) -> bool {
+    !disabled().read().unwrap().contains(&effect)
+}
+
+pub fn set_enabled(effect: SideEffect, enabled: bool) {
+    let mut disabled = disabled().write().unwrap();
+    if enabled {
+        disabled.remove(&effect);
+    } else {
+        disabled.insert(effect);
+    }
+}
+
+/// Fails with `SideEffectDisabled` while `effect` is switched off.
+pub fn ensure_enabled(effect: SideEffect) -> Result<(), SideEffectDisabled> {
+    if is_enabled(effect) {
+        Ok(())
+    } else {
+        Err(SideEffectDisabled(effect))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn side_effect_names_round_trip() -> eyre::Result<()> {
+        for effect in SideEffect::ALL {
+            assert_eq!(SideEffect::from_str(effect.name())?, effect);
+            assert_eq!(serde_json::to_string(&effect)?, format!("\"{}\"", effect.name()));
+        }
+        assert!(SideEffect::from_str("emails").is_err());
+        Ok(())
+    }
+}
@@ -1 +1,5 @@
+pub mod error_codes;
+pub mod log_sink;
+pub mod metrics;
+pub mod middleware;
 pub mod twitter;
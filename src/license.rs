@@ -0,0 +1,82 @@
+use crate::db::{ContentLicense, ContentReuse, LicenseRule};
+
+/// Recorded as the prompt version of redemptions rejected for their license.
+pub const LICENSE_CHECK_VERSION: &str = "license";
+const MAX_OWNER_LEN: usize = 100;
+
+/// Why a redemption's license does not satisfy its policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LicenseViolation {
+    Missing,
+    /// The owner is blank or too long to show.
+    InvalidOwner,
+    ReuseNotAllowed(ContentReuse),
+}
+
+impl std::fmt::Display for LicenseViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing => write!(f, "The policy requires a content license"),
+            Self::InvalidOwner => write!(f, "The content license must name its owner"),
+            Self::ReuseNotAllowed(reuse) => {
+                write!(f, "The policy does not allow {} content", label(*reuse))
+            }
+        }
+    }
+}
+
+impl std::error::Error for LicenseViolation {}
+
+/// Checks the license a redemption declares against its policy's rule, if the policy has one.
+/// Declared licenses are checked for an owner either way.
+pub fn check(
+    rule: Option<&LicenseRule>,
+    license: Option<&ContentLicense>,
+) -> Result<(), LicenseViolation> {
+    let Some(license) = license else {
+        return match rule {
+            Some(rule) if rule.required => Err(LicenseViolation::Missing),
+            _ => Ok(()),
+        };
+    };
+    let owner = license.owner.trim();
+    if owner.is_empty() || owner.chars().count() > MAX_OWNER_LEN {
+        return Err(LicenseViolation::InvalidOwner);
+    }
+    match rule {
+        Some(rule) if !rule.allowed.is_empty() && !rule.allowed.contains(&license.reuse) => {
+            Err(LicenseViolation::ReuseNotAllowed(license.reuse))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// How the reuse terms read in token metadata.
+pub fn label(reuse: ContentReuse) -> &'static str {
+    match reuse {
+        ContentReuse::AllRightsReserved => "All rights reserved",
+        ContentReuse::Attribution => "CC BY 4.0",
+        ContentReuse::NonCommercial => "CC BY-NC 4.0",
+        ContentReuse::PublicDomain => "CC0 1.0",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enforces_the_policy_rule() {
+        let license = |reuse| ContentLicense { owner: "Acme".to_string(), reuse };
+        let rule = LicenseRule { allowed: vec![ContentReuse::AllRightsReserved], required: true };
+        assert_eq!(check(Some(&rule), None), Err(LicenseViolation::Missing));
+        assert_eq!(check(Some(&rule), Some(&license(ContentReuse::AllRightsReserved))), Ok(()));
+        assert_eq!(
+            check(Some(&rule), Some(&license(ContentReuse::PublicDomain))),
+            Err(LicenseViolation::ReuseNotAllowed(ContentReuse::PublicDomain))
+        );
+        assert_eq!(check(None, Some(&license(ContentReuse::PublicDomain))), Ok(()));
+        let unowned = ContentLicense { owner: " ".to_string(), reuse: ContentReuse::Attribution };
+        assert_eq!(check(None, Some(&unowned)), Err(LicenseViolation::InvalidOwner));
+    }
+}
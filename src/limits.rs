@@ -0,0 +1,149 @@
+use std::{net::SocketAddr, time::Instant};
+
+use axum::{
+    extract::{ConnectInfo, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    access_list,
+    context::ReadCtx,
+    db::TeleportDB,
+    endpoints::max_open_redemption_links,
+    inbox::{self, DAY_SECS},
+    public_api::{self, RATE_LIMIT_WINDOW},
+    reports,
+    reputation::{self, Standing},
+};
+
+#[derive(Deserialize)]
+pub struct LimitsQuery {
+    /// The user's registered address or ENS name.
+    teleport_id: String,
+    chain_id: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct MintLimits {
+    /// Whether the access lists let the user mint their token to themselves.
+    allowed: bool,
+    standing: Standing,
+    /// Most recipients one `/mint_batch` request may have.
+    batch_cap: usize,
+    /// Whether each mint has to be paid for up front.
+    fee_required: bool,
+}
+
+#[derive(Serialize)]
+pub struct RedemptionLimits {
+    /// Whether redemptions of the user's tokens are held for review.
+    held: bool,
+    /// Open redemption links a session may hold at once.
+    max_open_links: usize,
+}
+
+/// A fixed window limit. Public API and report limits are per client IP, so theirs are the
+/// caller's; the inbox limit is the user's.
+#[derive(Serialize)]
+pub struct RateLimitWindow {
+    name: &'static str,
+    limit: u64,
+    remaining: u64,
+    window_secs: u64,
+}
+
+/// Which ways of redeeming without holding ETH the chain offers. Sponsorship has no budget of
+/// its own here; it lasts as long as the minter wallet or the paymaster can pay.
+#[derive(Serialize)]
+pub struct GasSponsorship {
+    /// Redeems relayed as ERC-2771 forward requests, paid for by the minter wallet.
+    relayed: bool,
+    /// Redeems from ERC-4337 smart accounts, paid for by the paymaster.
+    paymaster: bool,
+}
+
+#[derive(Serialize)]
+pub struct LimitsResponse {
+    chain_id: u64,
+    mint: MintLimits,
+    redemption: RedemptionLimits,
+    rate_limits: Vec<RateLimitWindow>,
+    gas_sponsorship: GasSponsorship,
+}
+
+/// The limits a user is under right now, so the frontend can show them before they are hit.
+pub async fn get_limits<A: TeleportDB>(
+    State(ctx): State<ReadCtx<A>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Query(query): Query<LimitsQuery>,
+) -> Result<Json<LimitsResponse>, StatusCode> {
+    let address = ctx.resolve_address(&query.teleport_id).await?.0;
+    let config = ctx.chain(query.chain_id).ok_or(StatusCode::BAD_REQUEST)?.config();
+
+    let db = ctx.db.lock().await;
+    let user = db.get_user_by_address(address.clone()).map_err(|_| StatusCode::NOT_FOUND)?;
+    let x_id = user.x_id.ok_or(StatusCode::NOT_FOUND)?;
+    let standing =
+        reputation::standing(&*db, x_id.clone()).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let allowed = access_list::check_mint(&*db, &address, &x_id).is_ok();
+    let strikes = db.get_creator_strikes(x_id).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let now = chrono::Utc::now().timestamp();
+    let messages_sent = db
+        .count_inbox_messages_since(address.to_lowercase(), now - DAY_SECS)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    drop(db);
+
+    let now = Instant::now();
+    let public_api_limit = public_api::get_rate_limit();
+    let report_limit = reports::get_report_rate_limit();
+    let messages_per_day = inbox::get_messages_per_day();
+    let rate_limits = vec![
+        RateLimitWindow {
+            name: "public_api",
+            limit: public_api_limit.into(),
+            remaining: public_api::rate_limiter()
+                .lock()
+                .unwrap()
+                .remaining(client.ip(), now, public_api_limit)
+                .into(),
+            window_secs: RATE_LIMIT_WINDOW.as_secs(),
+        },
+        RateLimitWindow {
+            name: "reports",
+            limit: report_limit.into(),
+            remaining: reports::report_limiter()
+                .lock()
+                .unwrap()
+                .remaining(client.ip(), now, report_limit)
+                .into(),
+            window_secs: RATE_LIMIT_WINDOW.as_secs(),
+        },
+        RateLimitWindow {
+            name: "inbox_messages",
+            limit: messages_per_day as u64,
+            remaining: messages_per_day.saturating_sub(messages_sent) as u64,
+            window_secs: DAY_SECS as u64,
+        },
+    ];
+
+    Ok(Json(LimitsResponse {
+        chain_id: config.chain_id,
+        mint: MintLimits {
+            allowed,
+            standing,
+            batch_cap: standing.batch_mint_cap(ctx.max_batch_mint),
+            fee_required: config.mint_fee.is_some(),
+        },
+        redemption: RedemptionLimits {
+            held: reports::is_held(strikes) || standing == Standing::Restricted,
+            max_open_links: max_open_redemption_links(),
+        },
+        rate_limits,
+        gas_sponsorship: GasSponsorship {
+            relayed: config.trusted_forwarder.is_some(),
+            paymaster: config.smart_accounts.is_some(),
+        },
+    }))
+}
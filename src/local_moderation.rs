@@ -0,0 +1,133 @@
+use std::sync::OnceLock;
+
+use eyre::OptionExt;
+use ndarray::{Array2, Axis, Ix3};
+use ort::{GraphOptimizationLevel, Session};
+use tokenizers::{Encoding, Tokenizer};
+
+/// Recorded as the prompt version for redemptions judged by the local classifier.
+pub const LOCAL_PROMPT_VERSION: &str = "local-classifier";
+
+/// A small (policy, tweet) pair classifier run with onnxruntime inside the enclave, so tweet
+/// content never has to leave it for moderation.
+struct LocalModerator {
+    session: Session,
+    tokenizer: Tokenizer,
+    unsafe_label: usize,
+}
+
+impl LocalModerator {
+    fn from_env() -> eyre::Result<Self> {
+        let model_path = std::env::var("LOCAL_MODERATION_MODEL_PATH")?;
+        let tokenizer_path = std::env::var("LOCAL_MODERATION_TOKENIZER_PATH")?;
+        let unsafe_label = std::env::var("LOCAL_MODERATION_UNSAFE_LABEL")
+            .ok()
+            .and_then(|label| label.parse().ok())
+            .unwrap_or(1);
+
+        let session = Session::builder()?
+            .with_optimization_level(GraphOptimizationLevel::Level3)?
+            .commit_from_file(model_path)?;
+        let tokenizer = Tokenizer::from_file(tokenizer_path).map_err(|e| eyre::eyre!(e))?;
+        log::info!("Loaded local moderation model");
+        Ok(Self { session, tokenizer, unsafe_label })
+    }
+
+    fn is_unsafe(&self, tweet: &str, policy: &str) -> eyre::Result<bool> {
+        let encoding = self.tokenizer.encode((policy, tweet), true).map_err(|e| eyre::eyre!(e))?;
+        let (input_ids, attention_mask) = model_inputs(&encoding)?;
+
+        let outputs = self.session.run(ort::inputs![
+            "input_ids" => input_ids,
+            "attention_mask" => attention_mask,
+        ]?)?;
+        let logits = outputs["logits"].try_extract_tensor::<f32>()?;
+        let (label, _) = logits
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .ok_or_eyre("Local moderation model returned no logits")?;
+        Ok(label == self.unsafe_label)
+    }
+}
+
+/// A sentence embedding model for the similarity precheck, so that with the local backend the
+/// precheck keeps tweet content inside the enclave too.
+struct LocalEmbedder {
+    session: Session,
+    tokenizer: Tokenizer,
+}
+
+impl LocalEmbedder {
+    fn from_env() -> eyre::Result<Self> {
+        let model_path = std::env::var("LOCAL_EMBEDDING_MODEL_PATH")?;
+        let tokenizer_path = std::env::var("LOCAL_EMBEDDING_TOKENIZER_PATH")?;
+
+        let session = Session::builder()?
+            .with_optimization_level(GraphOptimizationLevel::Level3)?
+            .commit_from_file(model_path)?;
+        let tokenizer = Tokenizer::from_file(tokenizer_path).map_err(|e| eyre::eyre!(e))?;
+        log::info!("Loaded local embedding model");
+        Ok(Self { session, tokenizer })
+    }
+
+    /// The mean of the model's token embeddings for `text`.
+    fn embed(&self, text: &str) -> eyre::Result<Vec<f32>> {
+        let encoding = self.tokenizer.encode(text, true).map_err(|e| eyre::eyre!(e))?;
+        let (input_ids, attention_mask) = model_inputs(&encoding)?;
+
+        let outputs = self.session.run(ort::inputs![
+            "input_ids" => input_ids,
+            "attention_mask" => attention_mask,
+        ]?)?;
+        let hidden = outputs["last_hidden_state"].try_extract_tensor::<f32>()?;
+        let hidden = hidden.into_dimensionality::<Ix3>()?;
+        let mean = hidden
+            .index_axis(Axis(0), 0)
+            .mean_axis(Axis(0))
+            .ok_or_eyre("Local embedding model returned no tokens")?;
+        Ok(mean.to_vec())
+    }
+}
+
+/// One unpadded sequence's ids and attention mask, shaped as a batch of one.
+fn model_inputs(encoding: &Encoding) -> eyre::Result<(Array2<i64>, Array2<i64>)> {
+    let input_ids: Vec<i64> = encoding.get_ids().iter().map(|&id| id as i64).collect();
+    let attention_mask: Vec<i64> =
+        encoding.get_attention_mask().iter().map(|&mask| mask as i64).collect();
+    let len = input_ids.len();
+    Ok((
+        Array2::from_shape_vec((1, len), input_ids)?,
+        Array2::from_shape_vec((1, len), attention_mask)?,
+    ))
+}
+
+fn moderator() -> eyre::Result<&'static LocalModerator> {
+    static MODERATOR: OnceLock<LocalModerator> = OnceLock::new();
+    if let Some(moderator) = MODERATOR.get() {
+        return Ok(moderator);
+    }
+    let moderator = LocalModerator::from_env()?;
+    Ok(MODERATOR.get_or_init(|| moderator))
+}
+
+pub async fn is_tweet_unsafe(tweet: String, policy: String) -> eyre::Result<bool> {
+    tokio::task::spawn_blocking(move || moderator()?.is_unsafe(&tweet, &policy)).await?
+}
+
+fn embedder() -> eyre::Result<&'static LocalEmbedder> {
+    static EMBEDDER: OnceLock<LocalEmbedder> = OnceLock::new();
+    if let Some(embedder) = EMBEDDER.get() {
+        return Ok(embedder);
+    }
+    let embedder = LocalEmbedder::from_env()?;
+    Ok(EMBEDDER.get_or_init(|| embedder))
+}
+
+pub async fn embed(inputs: Vec<String>) -> eyre::Result<Vec<Vec<f32>>> {
+    tokio::task::spawn_blocking(move || {
+        let embedder = embedder()?;
+        inputs.iter().map(|input| embedder.embed(input)).collect()
+    })
+    .await?
+}
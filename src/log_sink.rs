@@ -0,0 +1,164 @@
+use std::{
+    fs::OpenOptions,
+    io::{self, BufRead, Write},
+};
+
+use alloy::hex;
+use eyre::OptionExt;
+use openssl::{
+    encrypt::{Decrypter, Encrypter},
+    pkey::{PKey, Private, Public},
+    rand::rand_bytes,
+    rsa::Padding,
+    symm::{decrypt_aead, encrypt_aead, Cipher},
+};
+
+/// Header line carrying the per-process AES key, wrapped to the operator's RSA key.
+const KEY_PREFIX: &str = "key:";
+const TAG_LEN: usize = 16;
+
+/// Encrypts every log line with AES-256-GCM under a fresh key that only the operator's private
+/// key can unwrap, so the untrusted host only ever sees ciphertext.
+pub struct EncryptedWriter<W: Write> {
+    inner: W,
+    key: [u8; 32],
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> EncryptedWriter<W> {
+    pub fn new(mut inner: W, public_key: &PKey<Public>) -> eyre::Result<Self> {
+        let mut key = [0u8; 32];
+        rand_bytes(&mut key)?;
+
+        let mut encrypter = Encrypter::new(public_key)?;
+        encrypter.set_rsa_padding(Padding::PKCS1_OAEP)?;
+        let mut wrapped_key = vec![0u8; encrypter.encrypt_len(&key)?];
+        let wrapped_len = encrypter.encrypt(&key, &mut wrapped_key)?;
+        wrapped_key.truncate(wrapped_len);
+        writeln!(inner, "{}{}", KEY_PREFIX, hex::encode(wrapped_key))?;
+
+        Ok(Self { inner, key, buffer: Vec::new() })
+    }
+
+    fn write_record(&mut self, record: &[u8]) -> io::Result<()> {
+        let mut nonce = [0u8; 12];
+        rand_bytes(&mut nonce).map_err(io::Error::other)?;
+        let mut tag = [0u8; TAG_LEN];
+        let ciphertext =
+            encrypt_aead(Cipher::aes_256_gcm(), &self.key, Some(&nonce), &[], record, &mut tag)
+                .map_err(io::Error::other)?;
+        writeln!(
+            self.inner,
+            "{}:{}{}",
+            hex::encode(nonce),
+            hex::encode(ciphertext),
+            hex::encode(tag)
+        )
+    }
+}
+
+impl<W: Write> Write for EncryptedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        while let Some(newline) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let record: Vec<u8> = self.buffer.drain(..=newline).collect();
+            self.write_record(&record[..newline])?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decrypts a log written by [`EncryptedWriter`], one plaintext line per record.
+pub fn decrypt_log<R: BufRead, W: Write>(
+    input: R,
+    output: &mut W,
+    private_key: &PKey<Private>,
+) -> eyre::Result<()> {
+    let mut key: Option<Vec<u8>> = None;
+    for line in input.lines() {
+        let line = line?;
+        if let Some(wrapped_key) = line.strip_prefix(KEY_PREFIX) {
+            let wrapped_key = hex::decode(wrapped_key)?;
+            let mut decrypter = Decrypter::new(private_key)?;
+            decrypter.set_rsa_padding(Padding::PKCS1_OAEP)?;
+            let mut unwrapped = vec![0u8; decrypter.decrypt_len(&wrapped_key)?];
+            let unwrapped_len = decrypter.decrypt(&wrapped_key, &mut unwrapped)?;
+            unwrapped.truncate(unwrapped_len);
+            key = Some(unwrapped);
+            continue;
+        }
+
+        let key = key.as_ref().ok_or_eyre("Log record found before a key header")?;
+        let (nonce, sealed) = line.split_once(':').ok_or_eyre("Malformed log record")?;
+        let nonce = hex::decode(nonce)?;
+        let sealed = hex::decode(sealed)?;
+        if sealed.len() < TAG_LEN {
+            eyre::bail!("Log record too short");
+        }
+        let (ciphertext, tag) = sealed.split_at(sealed.len() - TAG_LEN);
+        let plaintext =
+            decrypt_aead(Cipher::aes_256_gcm(), key, Some(&nonce), &[], ciphertext, tag)?;
+        output.write_all(&plaintext)?;
+        output.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Initializes `env_logger`, encrypting its output when `LOG_ENCRYPTION_PUBLIC_KEY_PATH` is set.
+///
+/// The key file must be a Gramine trusted file, otherwise the host could swap in its own key.
+/// Encrypted records go to `ENCRYPTED_LOG_FILE` if set, stdout otherwise.
+pub fn init_logging() {
+    let Ok(public_key_path) = std::env::var("LOG_ENCRYPTION_PUBLIC_KEY_PATH") else {
+        env_logger::init();
+        return;
+    };
+    let public_key_pem =
+        std::fs::read(public_key_path).expect("Failed to read log encryption public key");
+    let public_key = PKey::public_key_from_pem(&public_key_pem)
+        .expect("Failed to parse log encryption public key");
+    let sink: Box<dyn Write + Send> = match std::env::var("ENCRYPTED_LOG_FILE") {
+        Ok(path) => Box::new(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .expect("Failed to open encrypted log file"),
+        ),
+        Err(_) => Box::new(io::stdout()),
+    };
+    let writer =
+        EncryptedWriter::new(sink, &public_key).expect("Failed to initialize encrypted log sink");
+    env_logger::Builder::from_default_env()
+        .write_style(env_logger::WriteStyle::Never)
+        .target(env_logger::Target::Pipe(Box::new(writer)))
+        .init();
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::rsa::Rsa;
+
+    use super::*;
+
+    #[test]
+    fn encrypted_log_round_trip() -> eyre::Result<()> {
+        let private_key = PKey::from_rsa(Rsa::generate(2048)?)?;
+        let public_key = PKey::public_key_from_pem(&private_key.public_key_to_pem()?)?;
+
+        let mut writer = EncryptedWriter::new(Vec::new(), &public_key)?;
+        writer.write_all(b"first record\nsecond ")?;
+        writer.write_all(b"record\n")?;
+        let encrypted = writer.inner;
+        assert!(!String::from_utf8_lossy(&encrypted).contains("record"));
+
+        let mut decrypted = Vec::new();
+        decrypt_log(encrypted.as_slice(), &mut decrypted, &private_key)?;
+        assert_eq!(decrypted, b"first record\nsecond record\n");
+        Ok(())
+    }
+}
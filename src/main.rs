@@ -1,34 +1,113 @@
-use std::{net::SocketAddr, path::Path, sync::Arc};
+use std::{collections::BTreeMap, net::SocketAddr, path::Path, sync::Arc};
 
 use acme_lib::create_rsa_key;
 use alloy::{
     providers::ProviderBuilder,
-    signers::local::{coins_bip39::English, MnemonicBuilder},
+    signers::local::{coins_bip39::English, LocalSigner, MnemonicBuilder, PrivateKeySigner},
 };
 use tokio::time::Duration;
 
 use axum_server::tls_rustls::RustlsConfig;
 use endpoints::{
-    approve_mint, callback, cookietest, get_tweet_id, hello_world, mint, redeem, register_or_login,
-    SharedState,
+    add_email, approve_content, approve_mint, callback, cancel_tx, confirm_recovery, cookietest,
+    create_redemption_link, forwarded_redeem, get_anchor, get_creator_stats, get_estimate,
+    get_event_schemas, get_metrics, get_nft_status, get_redeem_authorization, get_smart_account,
+    get_tweet_id, get_tx_status, get_version, hello_world, max_batch_mint, mint, mint_batch,
+    prepare_forwarded_redeem, prepare_user_op_redeem, redeem, redeem_with_link,
+    redemption_link_form, register_or_login, revoke_approved_content, set_burn_on_redeem,
+    set_dm_notifications, set_timezone, start_recovery, unlink_account, user_op_redeem,
+    verify_email, SharedState,
 };
-use openssl::pkey::PKey;
-use tokio::{fs, sync::Mutex, time::sleep};
-use tower_http::cors::CorsLayer;
+use openssl::pkey::{PKey, Private};
+use teleport::{error_codes, metrics, middleware};
+use tokio::{fs, time::sleep};
 
+#[cfg(not(feature = "postgres"))]
+use crate::db::marketplace::marketplace_index_from_env;
+#[cfg(feature = "postgres")]
+use crate::db::{
+    client_db::{ClientDB, WriteMode},
+    dual_write::{get_verify_interval, run_dual_write_verifier},
+    marketplace::marketplace_index,
+    migrations,
+    retention::{get_retention_days, run_content_purge},
+    rollup::run_daily_rollup,
+};
 use crate::{
-    actions::nft::subscribe_to_nft_events, cert::create_csr, db::TeleportDB,
-    endpoints::check_redeem, twitter::builder::TwitterBuilder,
+    actions::{
+        anchor::run_anchorer,
+        balance::{run_balance_monitor, BalanceMonitor},
+        burn::run_burner,
+        chain::{chain_var, load_chains, ChainClient, ChainConfig, TokenStandard},
+        deposits::run_deposit_watcher,
+        nft::{
+            get_confirmation_depth, get_indexer_concurrency, run_nft_indexer, IndexerConfig,
+            IndexerMode,
+        },
+        pause::sync_contract_status,
+        preflight::check_chain,
+        promotion::{get_mint_confirmations, run_mint_promoter},
+        relay::{run_relayer, RelayConfig, RelayTarget},
+        rpc::{run_rpc_health_checks, RpcPool},
+        tx_monitor::run_tx_monitor,
+        wallet::NonceManager,
+    },
+    cert::create_csr,
+    db::{
+        in_memory::InMemoryDB,
+        lock::{run_lock_watchdog, TrackedMutex},
+        marketplace::{MarketplaceIndex, ReadOnlyMarketplaceIndex},
+        sealed::{DbSealing, StorageKey},
+        snapshot,
+        sqlite::SqliteDB,
+        DbBackend, TeleportDB,
+    },
+    endpoints::check_redeem,
+    event_bus::EventBus,
+    metadata::{run_metadata_pinner, Pinner},
+    metadata_refresh::MetadataRefreshers,
+    mode::{self, ServiceMode},
+    notify::Notifier,
+    twitter::{
+        builder::TwitterBuilder,
+        tier::{check_credentials, detect_tier, ApiTier},
+    },
 };
 
+mod access_list;
 mod actions;
+mod admin;
 mod cert;
+mod context;
 mod db;
+mod email;
 mod endpoints;
+mod event_bus;
+mod events;
+mod fixtures;
+mod inbox;
+mod kill_switch;
+mod license;
+mod limits;
+#[cfg(feature = "local-moderation")]
+mod local_moderation;
+mod mentions;
+mod metadata;
+mod metadata_refresh;
+mod mode;
+mod notify;
 mod oai;
+mod public_api;
+mod quorum;
+mod redeem_auth;
+mod reports;
+mod reputation;
+mod secrets;
 mod sgx_attest;
 mod templates;
+mod timezone;
 pub mod twitter;
+mod webhooks;
 
 const PRIVATE_KEY_PATH: &str = "/root/save/private_key.pem";
 const CERTIFICATE_PATH: &str = "untrustedhost/certificate.pem";
@@ -37,30 +116,87 @@ const QUOTE_PATH: &str = "untrustedhost/quote.dat";
 
 #[tokio::main]
 async fn main() {
-    env_logger::init();
     dotenv::dotenv().ok();
     dotenv::from_filename("/teleport.env").ok();
+    teleport::log_sink::init_logging();
+    log::info!(
+        "Starting teleport {} ({}, built {})",
+        env!("CARGO_PKG_VERSION"),
+        env!("TELEPORT_GIT_COMMIT"),
+        env!("TELEPORT_BUILD_TIMESTAMP")
+    );
+
+    // `teleport fixtures` seeds a devnet first, then serves it as usual.
+    if std::env::args().nth(1).as_deref() == Some("fixtures") {
+        let db_path = std::env::var("DB_PATH").expect("DB_PATH not set");
+        let config = fixtures::FixtureConfig::from_env().expect("Failed to parse fixture config");
+        let seeded = match DbBackend::from_env().expect("Failed to parse DB_BACKEND") {
+            DbBackend::InMemory => fixtures::seed(&config, InMemoryDB::new(), &db_path).await,
+            DbBackend::Sqlite => {
+                let db = SqliteDB::open_in_memory().expect("Failed to open SQLite db");
+                fixtures::seed(&config, db, &db_path).await
+            }
+        };
+        seeded.expect("Failed to seed fixtures");
+    }
+
+    // `teleport migrate ...` applies or rolls back marketplace index migrations, then exits.
+    #[cfg(feature = "postgres")]
+    if std::env::args().nth(1).as_deref() == Some("migrate") {
+        let client_db = ClientDB::from_env()
+            .expect("Failed to set up the Postgres pool")
+            .expect("DATABASE_URL not set");
+        let args: Vec<String> = std::env::args().skip(2).collect();
+        migrations::run_cli(&client_db, &args).await.expect("Failed to migrate");
+        return;
+    }
 
     // Published values
-    let ws_rpc_url = std::env::var("WS_RPC_URL").expect("WS_RPC_URL not set");
-    let rpc_url = std::env::var("RPC_URL").expect("RPC_URL not set");
     let tee_url = std::env::var("TEE_URL").expect("TEE_URL not set");
 
+    let service_mode = ServiceMode::from_env().expect("Failed to parse SERVICE_MODE");
+    let read_only = service_mode.is_read_only();
+    let has_wallet = service_mode.has_wallet();
+    if read_only {
+        log::info!("Read-only mode: indexing without the minter wallet or Twitter posting");
+    } else if !has_wallet {
+        log::info!("Watch-only mode: indexing and tweeting without the minter wallet");
+    }
+
     // Private API values
+    secrets::validate_measurement_policy().expect("Secrets measurement policy not satisfied");
     let rpc_key = std::env::var("RPC_KEY").expect("RPC_KEY not set");
-    let mnemonic = std::env::var("NFT_MINTER_MNEMONIC").expect("NFT_MINTER_MNEMONIC not set");
     let db_path = std::env::var("DB_PATH").expect("DB_PATH not set");
+    let db_backend = DbBackend::from_env().expect("Failed to parse DB_BACKEND");
+    let db_sealing = DbSealing::from_env().expect("Failed to parse DB_SEALING");
+    // SQLite writes its own file as it goes, so only the in-memory image can be sealed.
+    if db_backend == DbBackend::Sqlite && db_sealing != DbSealing::None {
+        panic!("DB_SEALING requires DB_BACKEND=memory");
+    }
+    let storage_key = db_sealing.storage_key(&db_path).expect("Failed to unseal the storage key");
     let app_url = std::env::var("APP_URL").expect("APP_URL not set");
-    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL not set");
 
     let app_key = std::env::var("TWITTER_CONSUMER_KEY").expect("TWITTER_CONSUMER_KEY not set");
-    let app_secret =
-        std::env::var("TWITTER_CONSUMER_SECRET").expect("TWITTER_CONSUMER_SECRET not set");
+    // Without the app secret no request to X can be signed.
+    let app_secret = if read_only {
+        String::new()
+    } else {
+        secrets::get_secret("TWITTER_CONSUMER_SECRET").expect("TWITTER_CONSUMER_SECRET not set")
+    };
 
-    let twitter_builder = TwitterBuilder::new(app_key, app_secret);
+    let twitter_tier = if read_only {
+        ApiTier::Free
+    } else {
+        check_credentials(&app_key, &app_secret).await.expect("X credentials check failed");
+        detect_tier(&app_key, &app_secret).await.unwrap_or_else(|e| {
+            log::warn!("Failed to detect X API tier, assuming free: {:?}", e);
+            ApiTier::Free
+        })
+    };
+    log::info!("X API tier: {:?}", twitter_tier);
+    let twitter_builder = TwitterBuilder::new(app_key, app_secret).with_tier(twitter_tier);
 
-    let ws_rpc_url = ws_rpc_url + &rpc_key;
-    let rpc_url = rpc_url + &rpc_key;
+    let chain_configs = load_chains(&rpc_key).expect("Failed to load chain configuration");
 
     let pkey = if std::path::Path::new(PRIVATE_KEY_PATH).exists() {
         let pk_bytes = fs::read(PRIVATE_KEY_PATH).await.expect("Failed to read pk file");
@@ -85,44 +221,339 @@ async fn main() {
         fs::write(QUOTE_PATH, quote).await.expect("Failed to write quote to file");
     }
 
-    let signer =
-        MnemonicBuilder::<English>::default().phrase(mnemonic).index(0).unwrap().build().unwrap();
+    // Read-only and watch-only instances never see the minter mnemonic; their throwaway key holds
+    // no funds and owns no contract.
+    let signer = if !has_wallet {
+        LocalSigner::random()
+    } else {
+        let mnemonic =
+            secrets::get_secret("NFT_MINTER_MNEMONIC").expect("NFT_MINTER_MNEMONIC not set");
+        MnemonicBuilder::<English>::default().phrase(mnemonic).index(0).unwrap().build().unwrap()
+    };
 
-    let provider = ProviderBuilder::new()
-        .with_recommended_fillers()
-        .wallet(signer.clone().into())
-        .on_http(rpc_url.parse().unwrap());
+    let chains: BTreeMap<u64, ChainClient> = chain_configs
+        .iter()
+        .map(|config| {
+            let wallet_provider = |url: &str| {
+                ProviderBuilder::new()
+                    .with_recommended_fillers()
+                    .wallet(signer.clone().into())
+                    .on_http(url.parse().unwrap())
+            };
+            let rpc =
+                RpcPool::new(config.rpc_urls.iter().map(|url| wallet_provider(url)).collect());
+            let private_provider = config.private_tx_rpc_url.as_ref().map(|url| {
+                log::info!("Submitting transactions on chain {} privately", config.chain_id);
+                wallet_provider(url)
+            });
+            let nonces = NonceManager::new(signer.address());
+            let client = ChainClient { config: config.clone(), rpc, private_provider, nonces };
+            (config.chain_id, client)
+        })
+        .collect();
+    for chain in chains.values() {
+        let minter = Some(signer.address()).filter(|_| has_wallet);
+        check_chain(chain, minter).await.expect("Chain configuration check failed");
+    }
+    let relay_target = RelayConfig::from_env()
+        .expect("Failed to parse relay config")
+        .filter(|_| has_wallet)
+        .map(|config| {
+            // A relay chain that is also served shares its client, so nonces are handed out once.
+            let (provider, nonces) = match chains.get(&config.chain_id) {
+                Some(chain) => (chain.submit_provider().clone(), chain.nonces.clone()),
+                None => (
+                    ProviderBuilder::new()
+                        .with_recommended_fillers()
+                        .wallet(signer.clone().into())
+                        .on_http(
+                            config
+                                .rpc_url
+                                .as_ref()
+                                .expect("RELAY_RPC_URL not set")
+                                .parse()
+                                .unwrap(),
+                        ),
+                    NonceManager::new(signer.address()),
+                ),
+            };
+            RelayTarget { config, provider, nonces, signer: signer.clone() }
+        });
 
-    let db = if std::path::Path::new(&db_path).exists() {
-        let serialized_bytes = fs::read(&db_path).await.expect("Failed to read db file");
-        let db = db::in_memory::InMemoryDB::deserialize(&serialized_bytes);
-        log::info!("Loaded db from file: {}", db_path);
-        db
-    } else {
-        db::in_memory::InMemoryDB::new()
+    let service = Service {
+        db_backend,
+        db_path,
+        storage_key,
+        chain_configs,
+        chains,
+        relay_target,
+        signer,
+        app_url,
+        tee_url,
+        twitter_builder,
+        service_mode,
+        pkey,
     };
-    let db = Arc::new(Mutex::new(db));
+    match db_backend {
+        DbBackend::InMemory => {
+            let open = |bytes: &[u8]| open_db_file(service.storage_key.as_ref(), bytes);
+            let snapshot = snapshot::read(Path::new(&service.db_path), open)
+                .await
+                .expect("Failed to read db snapshot");
+            let db = match snapshot {
+                Some(image) => {
+                    // Starting empty instead would overwrite the file on shutdown.
+                    let db = InMemoryDB::load(&image).unwrap_or_else(|e| {
+                        log::error!("Failed to load db file {}: {:?}", service.db_path, e);
+                        std::process::exit(1)
+                    });
+                    log::info!("Loaded db from file: {}", service.db_path);
+                    db
+                }
+                None => InMemoryDB::new(),
+            };
+            serve(db, service).await;
+        }
+        DbBackend::Sqlite => {
+            let db = SqliteDB::open(&service.db_path).expect("Failed to open SQLite db");
+            log::info!("Opened SQLite db: {}", service.db_path);
+            serve(db, service).await;
+        }
+    }
+}
+
+/// Decrypts a database file with `storage_key`, or passes it through when files are not sealed.
+fn open_db_file(storage_key: Option<&StorageKey>, file: &[u8]) -> eyre::Result<Vec<u8>> {
+    match storage_key {
+        Some(storage_key) => storage_key.open(file),
+        None => Ok(file.to_vec()),
+    }
+}
+
+/// What `main` sets up before the service database is opened.
+struct Service {
+    db_backend: DbBackend,
+    db_path: String,
+    /// Encrypts the in-memory database's file, when `DB_SEALING` is set.
+    storage_key: Option<StorageKey>,
+    chain_configs: Vec<ChainConfig>,
+    chains: BTreeMap<u64, ChainClient>,
+    relay_target: Option<RelayTarget>,
+    signer: PrivateKeySigner,
+    app_url: String,
+    tee_url: String,
+    twitter_builder: TwitterBuilder,
+    service_mode: ServiceMode,
+    pkey: PKey<Private>,
+}
+
+/// Serves the API and runs the background tasks over the service database until ctrl-c.
+async fn serve<A: TeleportDB>(db: A, service: Service) {
+    let Service {
+        db_backend,
+        db_path,
+        storage_key,
+        chain_configs,
+        chains,
+        relay_target,
+        signer,
+        app_url,
+        tee_url,
+        twitter_builder,
+        service_mode,
+        pkey,
+    } = service;
+    let read_only = service_mode.is_read_only();
+    let has_wallet = service_mode.has_wallet();
+    kill_switch::init(&db).expect("Failed to parse KILL_SWITCHES");
+    let db = Arc::new(TrackedMutex::new(db));
+    for chain in chains.values() {
+        if let Err(e) = sync_contract_status(&db, chain).await {
+            log::warn!(
+                "Failed to read NFT contract status on chain {}: {:?}",
+                chain.config.chain_id,
+                e
+            );
+        }
+    }
+    tokio::spawn(run_lock_watchdog(db.clone()));
+    // One pool for the marketplace index, shared by the endpoints, the indexer and schema upkeep.
+    #[cfg(feature = "postgres")]
+    let client_db = ClientDB::from_env().expect("Failed to set up the Postgres pool");
+    // A read-only instance leaves the schema to the full one it shadows, but must not run
+    // against another version of it.
+    #[cfg(feature = "postgres")]
+    if let Some(client_db) = client_db.as_ref() {
+        if read_only {
+            migrations::check(client_db).await.expect("Marketplace index schema check failed");
+        } else {
+            migrations::migrate(client_db).await.expect("Failed to migrate the marketplace index");
+        }
+    }
+    #[cfg(feature = "postgres")]
+    let marketplace = marketplace_index(client_db.clone());
+    #[cfg(not(feature = "postgres"))]
+    let marketplace = marketplace_index_from_env().expect("Failed to set up the marketplace index");
+    // A read-only instance shares the full one's index, which it must only read.
+    let marketplace: Arc<dyn MarketplaceIndex> =
+        if read_only { Arc::new(ReadOnlyMarketplaceIndex(marketplace)) } else { marketplace };
+    // The internal schema, which the rollups and purges read, is only written outside legacy mode.
+    #[cfg(feature = "postgres")]
+    let has_index = client_db.as_ref().is_some_and(|db| db.write_mode() != WriteMode::Legacy);
+    #[cfg(not(feature = "postgres"))]
+    let has_index = false;
+    // Content is only purged from the internal schema, so a retention period would never apply.
+    if !has_index &&
+        chain_configs
+            .iter()
+            .any(|chain| chain_var("CONTENT_RETENTION_DAYS", chain.chain_id).is_ok())
+    {
+        panic!(
+            "CONTENT_RETENTION_DAYS needs the marketplace index in DB_WRITE_MODE dual or internal"
+        );
+    }
     let shared_state = SharedState {
         db: db.clone(),
-        provider,
+        chains: chains.clone(),
+        default_chain_id: chain_configs[0].chain_id,
         app_url,
         tee_url,
         signer,
         twitter_builder: twitter_builder.clone(),
+        mailer: email::Mailer::from_env(),
+        measurement: sgx_attest::read_measurement()
+            .map_err(|e| log::warn!("Admin API disabled, no enclave measurement: {:?}", e))
+            .ok(),
+        admins: admin::admin_roles().expect("Failed to parse ADMIN_ADDRESSES or ADMIN_ROLES"),
+        marketplace: marketplace.clone(),
+        mode: service_mode,
+        max_batch_mint: max_batch_mint().expect("Failed to parse MAX_BATCH_MINT"),
     };
 
-    let app = axum::Router::new()
+    let write_routes = axum::Router::new()
         .route("/new", axum::routing::get(register_or_login))
         .route("/approve", axum::routing::get(approve_mint))
-        .route("/callback", axum::routing::get(callback))
         .route("/cookietest", axum::routing::get(cookietest))
+        .route("/redeem/authorization", axum::routing::get(get_redeem_authorization))
+        .route("/redeem/forward/prepare", axum::routing::post(prepare_forwarded_redeem))
+        .route("/redeem/userop/prepare", axum::routing::post(prepare_user_op_redeem))
+        .route("/checkRedeem", axum::routing::post(check_redeem))
+        .route("/email", axum::routing::post(add_email))
+        .route("/email/verify", axum::routing::post(verify_email))
+        .route("/timezone", axum::routing::post(set_timezone))
+        .route("/burnOnRedeem", axum::routing::post(set_burn_on_redeem))
+        .route("/dmNotifications", axum::routing::post(set_dm_notifications))
+        .route(
+            "/webhook",
+            axum::routing::get(webhooks::get_webhook)
+                .post(webhooks::set_webhook)
+                .delete(webhooks::delete_webhook),
+        )
+        .route("/account/unlink", axum::routing::post(unlink_account))
+        .route(
+            "/policy/approvedContent",
+            axum::routing::post(approve_content).delete(revoke_approved_content),
+        )
+        .route("/recover", axum::routing::post(start_recovery))
+        .route("/recover/verify", axum::routing::post(confirm_recovery))
+        .route("/report", axum::routing::post(reports::report))
+        .route(
+            "/admin/approvals",
+            axum::routing::get(admin::pending_approvals).post(admin::propose_action),
+        )
+        .route("/admin/approvals/approve", axum::routing::post(admin::approve_action))
+        .route("/admin/moderation/preview", axum::routing::post(admin::preview_moderation))
+        .route("/admin/reports/release", axum::routing::post(admin::release_creator))
+        .route("/admin/mentions/approve", axum::routing::post(admin::approve_mentions))
+        .route(
+            "/admin/moderation/quorum/approve",
+            axum::routing::post(admin::approve_quorum_dispute),
+        )
+        .route(
+            "/admin/access_list",
+            axum::routing::get(admin::access_list)
+                .post(admin::set_access_list_entry)
+                .delete(admin::remove_access_list_entry),
+        )
+        .route("/admin/kill_switches/set", axum::routing::post(admin::set_kill_switch))
+        .route(
+            "/admin/backup/restore",
+            axum::routing::post(admin::restore_backup)
+                .layer(axum::extract::DefaultBodyLimit::max(admin::MAX_BACKUP_BYTES)),
+        )
+        .route("/admin/rebuild/start", axum::routing::post(admin::start_rebuild));
+    // Everything the minter wallet signs or sends for, or derives a key from.
+    let signing_routes = axum::Router::new()
+        .route("/callback", axum::routing::get(callback))
         .route("/mint", axum::routing::post(mint))
+        .route("/mint_batch", axum::routing::post(mint_batch))
         .route("/redeem", axum::routing::post(redeem))
-        .route("/checkRedeem", axum::routing::post(check_redeem))
+        .route("/redeem/forward", axum::routing::post(forwarded_redeem))
+        .route("/redeem/userop", axum::routing::post(user_op_redeem))
+        .route(
+            "/redemptionLink",
+            axum::routing::get(redemption_link_form).post(create_redemption_link),
+        )
+        .route("/redeemWithLink", axum::routing::post(redeem_with_link))
+        .route("/inbox", axum::routing::get(inbox::get_messages).post(inbox::send_message))
+        .route("/tx/:hash/cancel", axum::routing::post(cancel_tx));
+    let signing_routes = if has_wallet {
+        signing_routes
+    } else {
+        signing_routes.route_layer(axum::middleware::from_fn(mode::reject_without_wallet))
+    };
+    let index_routes =
+        axum::Router::new().route("/stats/creator", axum::routing::get(get_creator_stats));
+    let index_routes = if has_index {
+        index_routes
+    } else {
+        index_routes.route_layer(axum::middleware::from_fn(mode::reject_without_index))
+    };
+    let mut app = axum::Router::new()
+        .route("/account", axum::routing::get(get_smart_account))
+        .route("/user_by_address", axum::routing::get(admin::user_by_address))
         .route("/tweetId", axum::routing::get(get_tweet_id))
+        .route("/anchor", axum::routing::get(get_anchor))
+        .route("/tx_status", axum::routing::get(get_tx_status))
+        .route("/nft_status", axum::routing::get(get_nft_status))
+        .route("/estimate", axum::routing::get(get_estimate))
+        .route("/limits", axum::routing::get(limits::get_limits))
+        .route("/metrics", axum::routing::get(get_metrics))
+        .route("/version", axum::routing::get(get_version))
+        .route("/events/schema", axum::routing::get(get_event_schemas))
+        .route("/admin/handshake", axum::routing::get(admin::handshake))
+        .route("/admin/status", axum::routing::get(admin::status))
+        .route("/admin/kill_switches", axum::routing::get(admin::kill_switches))
+        .route("/admin/audit", axum::routing::get(admin::audit_log))
+        .route("/admin/reports", axum::routing::get(admin::abuse_reports))
+        .route("/admin/reputation", axum::routing::get(admin::creator_reputation))
+        .route("/admin/ledger", axum::routing::get(admin::deposit_ledger))
+        .route("/admin/earnings", axum::routing::get(admin::mint_earnings))
+        .route("/admin/users", axum::routing::get(admin::list_users))
+        .route("/admin/nfts", axum::routing::get(admin::list_nfts))
+        .route("/admin/redemptions", axum::routing::get(admin::list_redemptions))
+        .route("/admin/backup", axum::routing::get(admin::export_backup))
+        .route("/admin/rebuild", axum::routing::get(admin::rebuild_progress))
+        .route("/debug/locks", axum::routing::get(admin::lock_report))
         .route("/", axum::routing::get(hello_world))
-        .layer(CorsLayer::permissive())
-        .with_state(shared_state);
+        .merge(index_routes)
+        .merge(
+            axum::Router::new()
+                .route("/public/token", axum::routing::get(public_api::get_token))
+                .route("/public/redemption", axum::routing::get(public_api::get_redemption))
+                .route("/public/stats", axum::routing::get(public_api::get_collection_stats))
+                .route("/nft/:token_id", axum::routing::get(public_api::get_nft_metadata))
+                .route("/nft/:token_id/royalty", axum::routing::get(public_api::get_royalty))
+                .route(
+                    "/metadata/:chain_id/:token_id",
+                    axum::routing::get(public_api::get_token_uri),
+                )
+                .route_layer(axum::middleware::from_fn(public_api::rate_limit)),
+        );
+    if !read_only {
+        app = app.merge(write_routes).merge(signing_routes);
+    }
+    let app = middleware::stack().apply(app).with_state(shared_state);
 
     #[cfg(feature = "https")]
     {
@@ -136,7 +567,10 @@ async fn main() {
             RustlsConfig::from_pem(cert, pkey.private_key_to_pem_pkcs8().unwrap()).await.unwrap();
         let addr = SocketAddr::from(([0, 0, 0, 0], 8001));
         tokio::spawn(async move {
-            axum_server::bind_rustls(addr, config).serve(app.into_make_service()).await.unwrap();
+            axum_server::bind_rustls(addr, config)
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                .await
+                .unwrap();
         });
     }
 
@@ -144,19 +578,119 @@ async fn main() {
     {
         let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
         tokio::spawn(async move {
-            axum::serve(listener, app).await.unwrap();
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+                .await
+                .unwrap();
         });
     }
 
-    let db_clone = db.clone();
-    tokio::spawn(async move {
-        subscribe_to_nft_events(db_clone, twitter_builder, ws_rpc_url, database_url).await.unwrap();
-    });
+    // Schema upkeep for the marketplace index, when there is one this instance writes.
+    #[cfg(feature = "postgres")]
+    {
+        let client_db = client_db.filter(|db| !read_only && db.write_mode() != WriteMode::Legacy);
+        if let Some(client_db) = client_db {
+            let write_mode = client_db.write_mode();
+            if write_mode == WriteMode::Dual {
+                client_db
+                    .backfill_internal_schema()
+                    .await
+                    .expect("Failed to backfill internal schema");
+                tokio::spawn(run_dual_write_verifier(client_db.clone(), get_verify_interval()));
+            }
+            let retention: Vec<(u64, i32)> = chain_configs
+                .iter()
+                .filter_map(|chain| {
+                    let days = get_retention_days(chain.chain_id)
+                        .expect("Failed to parse CONTENT_RETENTION_DAYS")?;
+                    Some((chain.chain_id, days))
+                })
+                .collect();
+            if !retention.is_empty() {
+                tokio::spawn(run_content_purge(client_db.clone(), retention));
+            }
+            tokio::spawn(run_daily_rollup(client_db));
+        }
+    }
+
+    // Users are notified by the full instance; a read-only one would only repeat it.
+    let notifier = if read_only {
+        Notifier::new(None, None, None)
+    } else {
+        Notifier::from_env().expect("Failed to parse NOTIFICATION_QUIET_HOURS")
+    };
+    webhooks::init(!read_only);
+    let pinner = Pinner::from_env();
+    if pinner.is_configured() {
+        tokio::spawn(run_metadata_pinner(db.clone(), pinner));
+    } else {
+        log::info!("IPFS_PINNING_URL not set; token metadata is served unpinned");
+    }
+    for chain in chains.into_values() {
+        if chain.config.rpc_urls.len() > 1 {
+            tokio::spawn(run_rpc_health_checks(chain.config.chain_id, chain.rpc.clone()));
+        }
+        if chain.config.token_standard == TokenStandard::Erc721 && has_wallet {
+            tokio::spawn(run_burner(db.clone(), chain.clone()));
+        }
+        if let Some(anchor) = chain.config.anchor.clone().filter(|_| has_wallet) {
+            tokio::spawn(run_anchorer(db.clone(), chain.clone(), anchor));
+        }
+        if let Some(target) =
+            relay_target.as_ref().filter(|t| t.config.relays(chain.config.chain_id))
+        {
+            tokio::spawn(run_relayer(db.clone(), chain.clone(), target.clone()));
+        }
+        if chain.config.watch_deposits {
+            tokio::spawn(run_deposit_watcher(db.clone(), chain.clone(), notifier.clone()));
+        }
+        if get_mint_confirmations() > 0 && !read_only {
+            tokio::spawn(run_mint_promoter(db.clone(), chain.clone(), marketplace.clone()));
+        }
+        if has_wallet {
+            let monitor = BalanceMonitor::from_env(chain.config.chain_id, chain.nonces.address())
+                .expect("Failed to parse funder balance settings");
+            tokio::spawn(run_balance_monitor(chain.clone(), monitor));
+            tokio::spawn(run_tx_monitor(db.clone(), chain));
+        }
+    }
+
+    let event_bus = EventBus::from_env().await.expect("Failed to connect to EVENT_BUS_URL");
+    let refreshers = MetadataRefreshers::from_env();
+    let indexer_mode = IndexerMode::from_env().expect("Failed to parse INDEXER_MODE");
+    let confirmation_depth = get_confirmation_depth().expect("Failed to parse CONFIRMATION_DEPTH");
+    let concurrency = get_indexer_concurrency().expect("Failed to parse INDEXER_CONCURRENCY");
+    for chain in chain_configs {
+        let db = db.clone();
+        let twitter_builder = twitter_builder.clone();
+        let notifier = notifier.clone();
+        let config = IndexerConfig {
+            mode: indexer_mode,
+            confirmation_depth,
+            concurrency,
+            chain,
+            marketplace: marketplace.clone(),
+            event_bus: event_bus.clone(),
+            refreshers: refreshers.clone(),
+            read_only,
+        };
+        tokio::spawn(async move {
+            run_nft_indexer(db, twitter_builder, notifier, config).await;
+        });
+    }
     tokio::signal::ctrl_c().await.expect("failed to listen for event");
-    let db = db.lock().await;
-    let serialized = db.serialize().unwrap();
-    let serialized_bytes = serialized.to_vec();
-    fs::write(&db_path, serialized_bytes).await.expect("Failed to save serialized data to file");
-    log::info!("Saved db to file: {}", db_path);
+    // SQLite has written every change to its file already.
+    if db_backend == DbBackend::InMemory {
+        let db = db.lock().await;
+        let serialized = db.serialize().unwrap();
+        let mut serialized_bytes = serialized.to_vec();
+        if let Some(storage_key) = &storage_key {
+            serialized_bytes = storage_key.seal(&serialized_bytes).expect("Failed to encrypt db");
+        }
+        let open = |bytes: &[u8]| open_db_file(storage_key.as_ref(), bytes);
+        snapshot::write(Path::new(&db_path), &serialized_bytes, open)
+            .await
+            .expect("Failed to save serialized data to file");
+        log::info!("Saved db to file: {}", db_path);
+    }
     log::info!("Shutting down gracefully");
 }
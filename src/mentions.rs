@@ -0,0 +1,200 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{db::MentionRule, twitter::builder::TwitterClient};
+
+const MAX_HANDLE_LEN: usize = 15;
+/// Recorded as the prompt version of redemptions rejected for their mentions.
+pub const MENTION_CHECK_VERSION: &str = "mentions";
+
+/// What happens to a redemption that mentions a handle its policy does not allow, from
+/// `MENTION_VIOLATION_ACTION` (`review` or `reject`). Lookalikes of allowed handles are always
+/// rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViolationAction {
+    Review,
+    Reject,
+}
+
+pub fn get_violation_action() -> ViolationAction {
+    match std::env::var("MENTION_VIOLATION_ACTION").as_deref() {
+        Ok("reject") => ViolationAction::Reject,
+        _ => ViolationAction::Review,
+    }
+}
+
+/// The verdict on a redemption's @mentions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MentionCheck {
+    Allowed,
+    /// Mentions a handle the policy does not allow.
+    Unlisted(String),
+    /// Mentions a handle posing as an allowed one: a lookalike, or an allowed handle that now
+    /// belongs to a different account.
+    Impersonation(String),
+}
+
+/// A redemption held because it mentions handles its policy does not allow. The event is
+/// dead-lettered, so an admin can approve its mentions and replay it.
+#[derive(Debug)]
+pub struct MentionsNeedReview(pub String);
+
+impl std::fmt::Display for MentionsNeedReview {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Redemption of NFT {} mentions handles its policy does not allow", self.0)
+    }
+}
+
+impl std::error::Error for MentionsNeedReview {}
+
+/// The handles @mentioned in `content`, lowercased. An `@` inside a word, as in an email
+/// address, is not a mention.
+pub fn extract_mentions(content: &str) -> BTreeSet<String> {
+    let mut mentions = BTreeSet::new();
+    let mut previous = None;
+    for (i, c) in content.char_indices() {
+        let in_word = previous.is_some_and(|p: char| p.is_alphanumeric() || p == '_');
+        previous = Some(c);
+        if c != '@' || in_word {
+            continue;
+        }
+        let handle: String = content[i + 1..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect();
+        if !handle.is_empty() && handle.len() <= MAX_HANDLE_LEN {
+            mentions.insert(handle.to_lowercase());
+        }
+    }
+    mentions
+}
+
+/// Folds the characters impersonators swap in for each other, so `paypa1` and `paypal` match.
+fn skeleton(handle: &str) -> String {
+    handle
+        .to_lowercase()
+        .replace("rn", "m")
+        .replace("vv", "w")
+        .chars()
+        .filter(|c| *c != '_')
+        .map(|c| match c {
+            '0' => 'o',
+            '1' | 'i' => 'l',
+            '3' => 'e',
+            '5' => 's',
+            c => c,
+        })
+        .collect()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let substituted = diagonal + usize::from(a != *b);
+            diagonal = row[j + 1];
+            row[j + 1] = substituted.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Whether `handle` looks like `allowed` without being it.
+fn is_lookalike(handle: &str, allowed: &str) -> bool {
+    if handle == allowed {
+        return false;
+    }
+    let (handle, allowed) = (skeleton(handle), skeleton(allowed));
+    handle == allowed || (allowed.len() >= 4 && edit_distance(&handle, &allowed) <= 1)
+}
+
+/// Checks the mentions in `content` against what `rule` allows, without calling the X API.
+/// Allowed handles still need [`verify_accounts`].
+pub fn check_handles(content: &str, rule: &MentionRule) -> MentionCheck {
+    let mut unlisted = None;
+    for mention in extract_mentions(content) {
+        if rule.allowed.contains_key(&mention) {
+            continue;
+        }
+        if rule.allowed.keys().any(|allowed| is_lookalike(&mention, allowed)) {
+            return MentionCheck::Impersonation(mention);
+        }
+        unlisted.get_or_insert(mention);
+    }
+    unlisted.map_or(MentionCheck::Allowed, MentionCheck::Unlisted)
+}
+
+/// Resolves handles to the accounts that own them now, keyed by lowercased handle. Handles that
+/// do not resolve are left out.
+pub async fn resolve_handles(
+    client: &TwitterClient<'_>,
+    handles: &[String],
+) -> eyre::Result<BTreeMap<String, String>> {
+    let handles: Vec<String> =
+        handles.iter().map(|h| h.trim_start_matches('@').to_string()).collect();
+    let mut resolved = BTreeMap::new();
+    for chunk in handles.chunks(100) {
+        for user in client.get_users_by_usernames(chunk).await? {
+            resolved.insert(user.username.to_lowercase(), user.id);
+        }
+    }
+    Ok(resolved)
+}
+
+/// Checks that the allowed handles `content` mentions still belong to the accounts they were
+/// pinned to, which catches a brand's old handle re-registered by someone else.
+pub async fn verify_accounts(
+    client: &TwitterClient<'_>,
+    content: &str,
+    rule: &MentionRule,
+) -> eyre::Result<MentionCheck> {
+    let mentioned: Vec<String> = extract_mentions(content)
+        .into_iter()
+        .filter(|mention| rule.allowed.contains_key(mention))
+        .collect();
+    let resolved = resolve_handles(client, &mentioned).await?;
+    for mention in mentioned {
+        if resolved.get(&mention) != rule.allowed.get(&mention) {
+            return Ok(MentionCheck::Impersonation(mention));
+        }
+    }
+    Ok(MentionCheck::Allowed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(handles: &[&str]) -> MentionRule {
+        MentionRule {
+            allowed: handles.iter().map(|h| (h.to_string(), format!("id-{}", h))).collect(),
+        }
+    }
+
+    #[test]
+    fn extracts_mentions_but_not_emails() {
+        let mentions = extract_mentions("Thanks @Acme and @acme_support! mail hi@acme.com @");
+        assert_eq!(mentions, BTreeSet::from(["acme".to_string(), "acme_support".to_string()]));
+    }
+
+    #[test]
+    fn flags_lookalikes_and_unlisted_handles() {
+        let rule = rule(&["paypal", "acme"]);
+        assert_eq!(check_handles("Loving @PayPal today", &rule), MentionCheck::Allowed);
+        assert_eq!(
+            check_handles("Claim at @paypa1", &rule),
+            MentionCheck::Impersonation("paypa1".to_string())
+        );
+        assert_eq!(
+            check_handles("Claim at @pay_pal", &rule),
+            MentionCheck::Impersonation("pay_pal".to_string())
+        );
+        assert_eq!(
+            check_handles("Shoutout @someone", &rule),
+            MentionCheck::Unlisted("someone".to_string())
+        );
+        assert_eq!(check_handles("No mentions here", &rule), MentionCheck::Allowed);
+    }
+}
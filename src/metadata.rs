@@ -0,0 +1,171 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::time::{sleep, Duration};
+
+use crate::{
+    db::{lock::TrackedMutex, ContentLicense, TeleportDB},
+    license,
+    secrets::get_secret,
+};
+
+const DEFAULT_GATEWAY_URL: &str = "https://ipfs.io/ipfs/";
+const PIN_INTERVAL: Duration = Duration::from_secs(30);
+const LICENSE_TRAITS: [&str; 2] = ["content_owner", "content_license"];
+/// Characters of a policy kept in a token's description.
+const POLICY_SUMMARY_CHARS: usize = 200;
+
+/// `IPFS_GATEWAY_URL`, the gateway pinned metadata is served from, ending in `/ipfs/`.
+pub fn get_gateway_url() -> String {
+    std::env::var("IPFS_GATEWAY_URL").unwrap_or_else(|_| DEFAULT_GATEWAY_URL.to_string())
+}
+
+/// The artwork of `token_id`, from `METADATA_IMAGE_URL` with `{token_id}` substituted.
+fn get_image_url(token_id: &str) -> Option<String> {
+    let template = std::env::var("METADATA_IMAGE_URL").ok()?;
+    Some(template.replace("{token_id}", token_id))
+}
+
+fn summarize_policy(policy: &str) -> String {
+    match policy.char_indices().nth(POLICY_SUMMARY_CHARS) {
+        Some((end, _)) => format!("{}…", policy[..end].trim_end()),
+        None => policy.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attribute {
+    pub trait_type: String,
+    pub value: String,
+}
+
+/// ERC-721 metadata JSON, as wallets and marketplaces read it from `tokenURI`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    pub name: String,
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    pub attributes: Vec<Attribute>,
+}
+
+impl TokenMetadata {
+    /// Metadata for a newly minted token, naming its creator by X handle when it is known.
+    pub fn new(token_id: &str, x_id: &str, handle: Option<&str>, policy: &str) -> Self {
+        let creator =
+            handle.map_or_else(|| format!("X user {}", x_id), |handle| format!("@{}", handle));
+        let attribute = |trait_type: &str, value: &str| Attribute {
+            trait_type: trait_type.to_string(),
+            value: value.to_string(),
+        };
+        let mut attributes = vec![attribute("x_id", x_id)];
+        if let Some(handle) = handle {
+            attributes.push(attribute("creator", handle));
+        }
+        Self {
+            name: format!("Teleport #{}", token_id),
+            description: format!(
+                "Redeemable for one tweet from {}, within this policy: {}",
+                creator,
+                summarize_policy(policy)
+            ),
+            image: get_image_url(token_id),
+            attributes,
+        }
+    }
+
+    /// Records the license the token's redemption declared, replacing any recorded before.
+    pub fn set_license(&mut self, license: &ContentLicense) {
+        self.attributes
+            .retain(|attribute| !LICENSE_TRAITS.contains(&attribute.trait_type.as_str()));
+        self.attributes.push(Attribute {
+            trait_type: "content_owner".to_string(),
+            value: license.owner.clone(),
+        });
+        self.attributes.push(Attribute {
+            trait_type: "content_license".to_string(),
+            value: license::label(license.reuse).to_string(),
+        });
+    }
+}
+
+/// Pins JSON to IPFS through a pinning service with a Pinata-style `pinJSONToIPFS` API
+/// (`IPFS_PINNING_URL`, bearer `IPFS_PINNING_JWT`).
+#[derive(Debug, Clone, Default)]
+pub struct Pinner {
+    api_url: Option<String>,
+    api_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PinResponse {
+    ipfs_hash: String,
+}
+
+impl Pinner {
+    pub fn from_env() -> Self {
+        Self {
+            api_url: std::env::var("IPFS_PINNING_URL").ok(),
+            api_key: get_secret("IPFS_PINNING_JWT").ok(),
+        }
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.api_url.is_some()
+    }
+
+    /// Pins `content` under `name` and returns its CID.
+    pub async fn pin_json(&self, name: &str, content: Value) -> eyre::Result<String> {
+        let api_url =
+            self.api_url.as_ref().ok_or_else(|| eyre::eyre!("IPFS_PINNING_URL not set"))?;
+        let body = json!({ "pinataContent": content, "pinataMetadata": { "name": name } });
+        let mut request = reqwest::Client::new().post(api_url).json(&body);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response: PinResponse = request.send().await?.error_for_status()?.json().await?;
+        Ok(response.ipfs_hash)
+    }
+}
+
+async fn pin_pending<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    pinner: &Pinner,
+) -> eyre::Result<()> {
+    let unpinned = db.lock().await.get_unpinned_metadata()?;
+    for (chain_id, token_id, metadata) in unpinned {
+        let name = format!("teleport-{}-{}.json", chain_id, token_id);
+        let cid = pinner.pin_json(&name, serde_json::from_str(&metadata)?).await?;
+        log::info!("Pinned metadata of NFT {} on chain {} as {}", token_id, chain_id, cid);
+        db.lock().await.set_metadata_cid(chain_id, token_id, cid)?;
+    }
+    Ok(())
+}
+
+/// Pins the metadata of newly minted tokens. Until a token's metadata is pinned, `/metadata`
+/// serves it directly, so its `tokenURI` resolves either way.
+pub async fn run_metadata_pinner<A: TeleportDB>(db: Arc<TrackedMutex<A>>, pinner: Pinner) {
+    loop {
+        sleep(PIN_INTERVAL).await;
+        if let Err(e) = pin_pending(&db, &pinner).await {
+            log::error!("Failed to pin token metadata: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_policies_are_summarized() {
+        let policy = "no spam ".repeat(50);
+        let metadata = TokenMetadata::new("7", "1234", Some("teleport"), &policy);
+        assert_eq!(metadata.name, "Teleport #7");
+        assert!(metadata.description.starts_with("Redeemable for one tweet from @teleport"));
+        assert!(metadata.description.ends_with("spam…"));
+        assert_eq!(summarize_policy("be kind"), "be kind");
+    }
+}
@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use alloy::primitives::{Address, U256};
+use futures::future::BoxFuture;
+use serde_json::json;
+
+use crate::{actions::chain::chain_var, metrics, secrets::get_secret};
+
+const OPENSEA_API_URL: &str = "https://api.opensea.io/api/v2";
+
+/// A marketplace that caches token metadata and can be asked to read it again, so its listings
+/// show whether a token has been redeemed.
+pub trait MetadataRefresher: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn refresh(
+        &self,
+        chain_id: u64,
+        contract: Address,
+        token_id: U256,
+    ) -> BoxFuture<'_, eyre::Result<()>>;
+}
+
+/// The chain names OpenSea uses in its API paths.
+fn opensea_chain(chain_id: u64) -> Option<&'static str> {
+    match chain_id {
+        1 => Some("ethereum"),
+        10 => Some("optimism"),
+        137 => Some("matic"),
+        8453 => Some("base"),
+        42161 => Some("arbitrum"),
+        84532 => Some("base_sepolia"),
+        11155111 => Some("sepolia"),
+        _ => None,
+    }
+}
+
+/// OpenSea's refresh endpoint, with the `OPENSEA_API_KEY` secret.
+pub struct OpenSea {
+    api_key: String,
+}
+
+impl MetadataRefresher for OpenSea {
+    fn name(&self) -> &'static str {
+        "opensea"
+    }
+
+    fn refresh(
+        &self,
+        chain_id: u64,
+        contract: Address,
+        token_id: U256,
+    ) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(async move {
+            let chain = opensea_chain(chain_id)
+                .ok_or_else(|| eyre::eyre!("OpenSea does not list chain {}", chain_id))?;
+            let url = format!(
+                "{}/chain/{}/contract/{}/nfts/{}/refresh",
+                OPENSEA_API_URL, chain, contract, token_id
+            );
+            reqwest::Client::new()
+                .post(url)
+                .header("x-api-key", &self.api_key)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        })
+    }
+}
+
+/// Reservoir's token refresh endpoint, with the `RESERVOIR_API_KEY` secret. Reservoir serves each
+/// chain from its own host, `RESERVOIR_API_URL` (e.g. `https://api-base.reservoir.tools`).
+pub struct Reservoir {
+    api_key: String,
+}
+
+impl MetadataRefresher for Reservoir {
+    fn name(&self) -> &'static str {
+        "reservoir"
+    }
+
+    fn refresh(
+        &self,
+        chain_id: u64,
+        contract: Address,
+        token_id: U256,
+    ) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(async move {
+            let api_url = chain_var("RESERVOIR_API_URL", chain_id)?;
+            reqwest::Client::new()
+                .post(format!("{}/tokens/refresh/v2", api_url))
+                .header("x-api-key", &self.api_key)
+                .json(&json!({ "token": format!("{}:{}", contract, token_id) }))
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        })
+    }
+}
+
+/// Every marketplace asked to refresh a token after it is minted or redeemed. Marketplaces are
+/// enabled by their API key; with none, refreshing does nothing.
+#[derive(Clone, Default)]
+pub struct MetadataRefreshers(Vec<Arc<dyn MetadataRefresher>>);
+
+impl MetadataRefreshers {
+    pub fn from_env() -> Self {
+        let mut refreshers: Vec<Arc<dyn MetadataRefresher>> = Vec::new();
+        if let Ok(api_key) = get_secret("OPENSEA_API_KEY") {
+            refreshers.push(Arc::new(OpenSea { api_key }));
+        }
+        if let Ok(api_key) = get_secret("RESERVOIR_API_KEY") {
+            refreshers.push(Arc::new(Reservoir { api_key }));
+        }
+        Self(refreshers)
+    }
+
+    /// Asks each marketplace to refresh `token_id`. A marketplace that fails is logged and
+    /// skipped; its listing catches up on its own schedule.
+    pub async fn refresh(&self, chain_id: u64, contract: Address, token_id: U256) {
+        for refresher in &self.0 {
+            let result = refresher.refresh(chain_id, contract, token_id).await;
+            if let Err(e) = &result {
+                log::warn!("Failed to refresh NFT {} on {}: {:?}", token_id, refresher.name(), e);
+            }
+            metrics::increment(
+                "metadata_refreshes_total",
+                &[
+                    ("marketplace", refresher.name()),
+                    ("result", if result.is_ok() { "ok" } else { "error" }),
+                ],
+            );
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, OnceLock},
+};
+
+/// Process-wide counters, rendered in the Prometheus text format by the `/metrics` endpoint.
+fn counters() -> &'static Mutex<BTreeMap<String, u64>> {
+    static COUNTERS: OnceLock<Mutex<BTreeMap<String, u64>>> = OnceLock::new();
+    COUNTERS.get_or_init(Default::default)
+}
+
+fn gauges() -> &'static Mutex<BTreeMap<String, i64>> {
+    static GAUGES: OnceLock<Mutex<BTreeMap<String, i64>>> = OnceLock::new();
+    GAUGES.get_or_init(Default::default)
+}
+
+/// Upper bounds, in seconds, of the latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 10] = [0.005, 0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Default)]
+struct Histogram {
+    /// Cumulative, like the rendered `_bucket` series.
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+/// Keyed by name and rendered labels, since the `le` label is added per bucket.
+fn histograms() -> &'static Mutex<BTreeMap<(String, Vec<String>), Histogram>> {
+    static HISTOGRAMS: OnceLock<Mutex<BTreeMap<(String, Vec<String>), Histogram>>> =
+        OnceLock::new();
+    HISTOGRAMS.get_or_init(Default::default)
+}
+
+fn render_labels(labels: &[(&str, &str)]) -> Vec<String> {
+    labels.iter().map(|(key, value)| format!("{}=\"{}\"", key, value)).collect()
+}
+
+fn series(name: &str, labels: &[String]) -> String {
+    if labels.is_empty() {
+        return name.to_string();
+    }
+    format!("{}{{{}}}", name, labels.join(","))
+}
+
+fn metric_key(name: &str, labels: &[(&str, &str)]) -> String {
+    series(name, &render_labels(labels))
+}
+
+pub fn increment(name: &str, labels: &[(&str, &str)]) {
+    increment_by(name, labels, 1);
+}
+
+pub fn increment_by(name: &str, labels: &[(&str, &str)], value: u64) {
+    let mut counters = counters().lock().unwrap();
+    *counters.entry(metric_key(name, labels)).or_default() += value;
+}
+
+pub fn set_gauge(name: &str, labels: &[(&str, &str)], value: i64) {
+    gauges().lock().unwrap().insert(metric_key(name, labels), value);
+}
+
+/// Records a duration in the latency histogram `name`.
+pub fn observe(name: &str, labels: &[(&str, &str)], seconds: f64) {
+    let mut histograms = histograms().lock().unwrap();
+    let histogram = histograms.entry((name.to_string(), render_labels(labels))).or_default();
+    for (bound, bucket) in LATENCY_BUCKETS.iter().zip(histogram.buckets.iter_mut()) {
+        if seconds <= *bound {
+            *bucket += 1;
+        }
+    }
+    histogram.sum += seconds;
+    histogram.count += 1;
+}
+
+fn render_histogram(name: &str, labels: &[String], histogram: &Histogram) -> String {
+    let bucket = |le: String, count: u64| {
+        let mut labels = labels.to_vec();
+        labels.push(format!("le=\"{}\"", le));
+        format!("{} {}\n", series(&format!("{}_bucket", name), &labels), count)
+    };
+    let mut rendered: String = LATENCY_BUCKETS
+        .iter()
+        .zip(histogram.buckets)
+        .map(|(bound, count)| bucket(bound.to_string(), count))
+        .collect();
+    rendered.push_str(&bucket("+Inf".to_string(), histogram.count));
+    rendered.push_str(&format!("{} {}\n", series(&format!("{}_sum", name), labels), histogram.sum));
+    rendered.push_str(&format!(
+        "{} {}\n",
+        series(&format!("{}_count", name), labels),
+        histogram.count
+    ));
+    rendered
+}
+
+pub fn render() -> String {
+    let counters = counters().lock().unwrap();
+    let gauges = gauges().lock().unwrap();
+    let histograms = histograms().lock().unwrap();
+    let counters = counters.iter().map(|(key, value)| format!("{} {}\n", key, value));
+    let gauges = gauges.iter().map(|(key, value)| format!("{} {}\n", key, value));
+    let histograms = histograms
+        .iter()
+        .map(|((name, labels), histogram)| render_histogram(name, labels, histogram));
+    counters.chain(gauges).chain(histograms).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_cumulative_histogram_buckets() {
+        observe("test_duration_seconds", &[("event", "Transfer")], 0.2);
+        observe("test_duration_seconds", &[("event", "Transfer")], 20.0);
+        let rendered = render();
+        assert!(
+            rendered.contains("test_duration_seconds_bucket{event=\"Transfer\",le=\"0.1\"} 0\n")
+        );
+        assert!(
+            rendered.contains("test_duration_seconds_bucket{event=\"Transfer\",le=\"0.25\"} 1\n")
+        );
+        assert!(
+            rendered.contains("test_duration_seconds_bucket{event=\"Transfer\",le=\"+Inf\"} 2\n")
+        );
+        assert!(rendered.contains("test_duration_seconds_count{event=\"Transfer\"} 2\n"));
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::{HeaderName, HeaderValue, StatusCode},
+    middleware::{from_fn, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+use tower_http::cors::CorsLayer;
+
+use crate::{error_codes, metrics};
+
+pub const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+/// Clients tracked before windows that have ended are dropped.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Fixed one minute windows of requests per client, by IP unless keyed otherwise.
+pub struct RateLimiter<K = IpAddr> {
+    windows: HashMap<K, (Instant, u32)>,
+}
+
+impl<K> Default for RateLimiter<K> {
+    fn default() -> Self {
+        Self { windows: HashMap::new() }
+    }
+}
+
+impl<K: Hash + Eq> RateLimiter<K> {
+    pub fn allow(&mut self, client: K, now: Instant, limit: u32) -> bool {
+        if self.windows.len() >= MAX_TRACKED_CLIENTS {
+            self.windows.retain(|_, (start, _)| now.duration_since(*start) < RATE_LIMIT_WINDOW);
+        }
+        let (start, count) = self.windows.entry(client).or_insert((now, 0));
+        if now.duration_since(*start) >= RATE_LIMIT_WINDOW {
+            (*start, *count) = (now, 0);
+        }
+        *count += 1;
+        *count <= limit
+    }
+
+    /// Requests `client` has left in its current window, without counting one.
+    pub fn remaining(&self, client: K, now: Instant, limit: u32) -> u32 {
+        match self.windows.get(&client) {
+            Some((start, count)) if now.duration_since(*start) < RATE_LIMIT_WINDOW => {
+                limit.saturating_sub(*count)
+            }
+            _ => limit,
+        }
+    }
+}
+
+/// The id of the request being handled, from the caller's `x-request-id` or freshly assigned.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// Gives every request an id, kept from the caller's `x-request-id` when it sent one, and echoes
+/// it on the response. Handlers read it as the [`RequestId`] extension.
+pub async fn request_id(mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .filter(|id| !id.is_empty())
+        .map(String::from)
+        .unwrap_or_else(cuid::cuid2);
+    request.extensions_mut().insert(RequestId(id.clone()));
+    let mut response = next.run(request).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Counts requests by method and status as `http_requests_total`, and their latency as
+/// `http_request_duration_seconds`.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let status = response.status().as_u16().to_string();
+    metrics::increment("http_requests_total", &[("method", &method), ("status", &status)]);
+    metrics::observe(
+        "http_request_duration_seconds",
+        &[("method", &method)],
+        started.elapsed().as_secs_f64(),
+    );
+    response
+}
+
+/// Starts the [`Stack`] with its defaults: permissive CORS and no rate limit.
+pub fn stack() -> Stack {
+    Stack { cors: CorsLayer::permissive(), rate_limit: None }
+}
+
+/// The layers every router serving this API gets, so routers mounted next to the built-in ones
+/// behave the same: request ids, request metrics, CORS, coded error bodies and, when set, a
+/// per-client rate limit. Authentication stays with the handlers, through the session and admin
+/// extractors, since what it checks differs per route.
+pub struct Stack {
+    cors: CorsLayer,
+    /// Requests per client IP per minute. Needs the router to be served with `ConnectInfo`.
+    rate_limit: Option<u32>,
+}
+
+impl Stack {
+    pub fn cors(mut self, cors: CorsLayer) -> Self {
+        self.cors = cors;
+        self
+    }
+
+    pub fn rate_limit(mut self, per_minute: u32) -> Self {
+        self.rate_limit = Some(per_minute);
+        self
+    }
+
+    /// Wraps `router` in the stack. Each call has its own rate limit windows.
+    pub fn apply<S>(self, router: Router<S>) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let router = match self.rate_limit {
+            Some(limit) => {
+                let limiter = Arc::new(Mutex::new(RateLimiter::default()));
+                router.layer(from_fn(
+                    move |ConnectInfo(client): ConnectInfo<SocketAddr>,
+                          request: Request,
+                          next: Next| {
+                        let allowed =
+                            limiter.lock().unwrap().allow(client.ip(), Instant::now(), limit);
+                        async move {
+                            if !allowed {
+                                return StatusCode::TOO_MANY_REQUESTS.into_response();
+                            }
+                            next.run(request).await
+                        }
+                    },
+                ))
+            }
+            None => router,
+        };
+        router
+            .layer(from_fn(error_codes::describe_errors))
+            .layer(self.cors)
+            .layer(from_fn(track_requests))
+            .layer(from_fn(request_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limit_resets_each_window() {
+        let mut limiter = RateLimiter::default();
+        let client = IpAddr::from([127, 0, 0, 1]);
+        let other = IpAddr::from([127, 0, 0, 2]);
+        let start = Instant::now();
+        assert!(limiter.allow(client, start, 2));
+        assert!(limiter.allow(client, start, 2));
+        assert!(!limiter.allow(client, start, 2));
+        assert_eq!(limiter.remaining(client, start, 2), 0);
+        assert!(limiter.allow(other, start, 2));
+        assert_eq!(limiter.remaining(other, start, 2), 1);
+        assert_eq!(limiter.remaining(client, start + RATE_LIMIT_WINDOW, 2), 2);
+        assert!(limiter.allow(client, start + RATE_LIMIT_WINDOW, 2));
+    }
+}
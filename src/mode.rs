@@ -0,0 +1,52 @@
+use axum::{
+    extract::Request,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use crate::error_codes::{ApiError, ErrorCode};
+
+/// Whether this instance acts on what it indexes, from `SERVICE_MODE` (`full`, `watch_only` or
+/// `read_only`). A read-only instance indexes events and serves the read endpoints for analytics,
+/// but never loads the minter wallet or the Twitter app secret, so it cannot send transactions or
+/// tweet. A watch-only instance has no wallet either, but still posts tweets and serves users;
+/// only the endpoints that need a signature or a transaction are refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceMode {
+    Full,
+    WatchOnly,
+    ReadOnly,
+}
+
+impl ServiceMode {
+    pub fn from_env() -> eyre::Result<Self> {
+        match std::env::var("SERVICE_MODE").as_deref() {
+            Err(_) | Ok("full") => Ok(Self::Full),
+            Ok("watch_only") => Ok(Self::WatchOnly),
+            Ok("read_only") => Ok(Self::ReadOnly),
+            Ok(mode) => eyre::bail!("Unknown SERVICE_MODE {}", mode),
+        }
+    }
+
+    pub fn is_read_only(self) -> bool {
+        self == Self::ReadOnly
+    }
+
+    /// Whether the minter wallet is loaded, so the instance can sign and send transactions.
+    pub fn has_wallet(self) -> bool {
+        self == Self::Full
+    }
+}
+
+/// Refuses the endpoints that sign or send transactions on an instance without a wallet.
+pub async fn reject_without_wallet(_request: Request, _next: Next) -> Response {
+    ApiError::new(ErrorCode::WalletUnavailable).into_response()
+}
+
+/// Refuses the endpoints served from the marketplace index's internal schema on an instance
+/// that has none, or only writes the legacy tables.
+pub async fn reject_without_index(_request: Request, _next: Next) -> Response {
+    ApiError::new(ErrorCode::NotImplemented).into_response()
+}
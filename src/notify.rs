@@ -0,0 +1,141 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+
+use crate::{
+    events::{Envelope, Event, Notification},
+    timezone::QuietHours,
+    twitter::auth::TwitterTokenPair,
+};
+
+const DEFAULT_TRANSFER_TEMPLATE: &str = "your redemption NFT #{token_id} changed hands";
+const DEFAULT_HOLDER_DM_TEMPLATE: &str = "You now hold @{creator}'s Teleport NFT #{token_id}. \
+     Redeem it at {app_url} to post a tweet from their account, within their policy.";
+
+/// The X account new holders are DMed from when a token reaches them, and what they are sent.
+#[derive(Debug, Clone)]
+pub struct HolderDms {
+    sender: TwitterTokenPair,
+    template: String,
+    app_url: String,
+}
+
+impl HolderDms {
+    pub fn new(sender: TwitterTokenPair, template: Option<String>, app_url: String) -> Self {
+        Self {
+            sender,
+            template: template.unwrap_or_else(|| DEFAULT_HOLDER_DM_TEMPLATE.to_string()),
+            app_url,
+        }
+    }
+
+    pub fn sender(&self) -> TwitterTokenPair {
+        self.sender.clone()
+    }
+
+    /// Renders the DM template, substituting `{creator}` (the creator's handle), `{token_id}`
+    /// and `{app_url}`.
+    pub fn message(&self, creator: &str, token_id: &str) -> String {
+        self.template
+            .replace("{creator}", creator)
+            .replace("{token_id}", token_id)
+            .replace("{app_url}", &self.app_url)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    webhook_url: Option<String>,
+    transfer_template: String,
+    quiet_hours: Option<QuietHours>,
+    holder_dms: Option<HolderDms>,
+}
+
+impl Notifier {
+    pub fn new(
+        webhook_url: Option<String>,
+        transfer_template: Option<String>,
+        quiet_hours: Option<QuietHours>,
+    ) -> Self {
+        Self {
+            webhook_url,
+            transfer_template: transfer_template
+                .unwrap_or_else(|| DEFAULT_TRANSFER_TEMPLATE.to_string()),
+            quiet_hours,
+            holder_dms: None,
+        }
+    }
+
+    pub fn with_holder_dms(self, holder_dms: HolderDms) -> Self {
+        Self { holder_dms: Some(holder_dms), ..self }
+    }
+
+    /// Reads `NOTIFICATION_QUIET_HOURS` such as `22-8`, kept in each recipient's own timezone.
+    /// New holders are DMed from the account `HOLDER_DM_ACCESS_TOKEN` and
+    /// `HOLDER_DM_ACCESS_SECRET` authorize, with `HOLDER_DM_TEMPLATE`, when both are set.
+    pub fn from_env() -> eyre::Result<Self> {
+        let quiet_hours = match std::env::var("NOTIFICATION_QUIET_HOURS") {
+            Ok(quiet_hours) => Some(quiet_hours.parse()?),
+            Err(_) => None,
+        };
+        let notifier = Self::new(
+            std::env::var("NOTIFICATION_WEBHOOK_URL").ok(),
+            std::env::var("TRANSFER_NOTIFICATION_TEMPLATE").ok(),
+            quiet_hours,
+        );
+        let sender =
+            (std::env::var("HOLDER_DM_ACCESS_TOKEN"), std::env::var("HOLDER_DM_ACCESS_SECRET"));
+        let (Ok(token), Ok(secret)) = sender else {
+            return Ok(notifier);
+        };
+        let holder_dms = HolderDms::new(
+            TwitterTokenPair { token, secret },
+            std::env::var("HOLDER_DM_TEMPLATE").ok(),
+            std::env::var("APP_URL").unwrap_or_default(),
+        );
+        Ok(notifier.with_holder_dms(holder_dms))
+    }
+
+    pub fn holder_dms(&self) -> Option<&HolderDms> {
+        self.holder_dms.as_ref()
+    }
+
+    /// Renders the transfer template, substituting `{token_id}`, `{to}`, `{fan}` and `{time}`,
+    /// the recipient's local time.
+    pub fn transfer_message(&self, token_id: &str, to: &str, fan: &str, time: &str) -> String {
+        self.transfer_template
+            .replace("{token_id}", token_id)
+            .replace("{to}", to)
+            .replace("{fan}", fan)
+            .replace("{time}", time)
+    }
+
+    /// Unix time a notification raised at `now` should wait for, when it falls in the quiet
+    /// hours of a recipient in `timezone`.
+    pub fn deliver_after(&self, timezone: Tz, now: DateTime<Utc>) -> Option<i64> {
+        let quiet_hours = self.quiet_hours?;
+        Some(quiet_hours.end_after(timezone, now)?.timestamp())
+    }
+
+    pub async fn notify(&self, notification: Notification) -> eyre::Result<()> {
+        match &self.webhook_url {
+            Some(webhook_url) => {
+                reqwest::Client::new()
+                    .post(webhook_url)
+                    .json(&Envelope::from(Event::Notification(notification)))
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+            None => {
+                log::info!("Notification for {}: {}", notification.x_id, notification.message);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        Self::new(None, None, None)
+    }
+}
@@ -1,32 +1,378 @@
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{metrics, secrets::get_secret};
+
 const TASK: &str = "Task: Check if the tweet adheres to the provided policy.";
 const OUTPUT: &str = "Provide your safety assessment for the above tweet as one word: either \"safe\" or \"unsafe\".";
 
-pub async fn is_tweet_safe(tweet: &String, policy: &String) -> bool {
-    let client =
-        openai_rust::Client::new(&std::env::var("OPENAI_API_KEY").expect("OPENAI_API_KEY not set"));
+const DEFAULT_PROMPT_VERSION: &str = "v1";
+
+/// A moderation prompt template. Versions are append-only: once a version id has judged live
+/// redemptions its wording must not change, add a new version instead.
+pub struct PromptVersion {
+    pub id: &'static str,
+    task: &'static str,
+    output: &'static str,
+}
+
+const PROMPT_VERSIONS: &[PromptVersion] = &[PromptVersion { id: "v1", task: TASK, output: OUTPUT }];
+
+impl PromptVersion {
+    fn render(&self, tweet: &str, policy: &str) -> String {
+        format!(
+            "{}\n<BEGIN POLICY>\n{}\n<END POLICY>\n<BEGIN TWEET>\n{}\n<END TWEET>\n{}\n",
+            self.task, policy, tweet, self.output
+        )
+    }
+}
+
+pub fn get_prompt_version(id: &str) -> eyre::Result<&'static PromptVersion> {
+    PROMPT_VERSIONS
+        .iter()
+        .find(|version| version.id == id)
+        .ok_or_else(|| eyre::eyre!("Unknown prompt version {}", id))
+}
 
-    let inputs = format!(
-        "{}\n<BEGIN POLICY>\n{}\n<END POLICY>\n<BEGIN TWEET>\n{}\n<END TWEET>\n{}\n",
-        TASK, policy, tweet, OUTPUT
+/// Picks the canary version when `roll` (0..100) falls under the canary percentage.
+fn choose_prompt_version<'a>(stable: &'a str, canary: Option<(&'a str, u8)>, roll: u8) -> &'a str {
+    match canary {
+        Some((canary, percent)) if roll < percent => canary,
+        _ => stable,
+    }
+}
+
+/// The prompt version outside any canary, from `MODERATION_PROMPT_VERSION`.
+pub fn stable_prompt_version() -> String {
+    std::env::var("MODERATION_PROMPT_VERSION")
+        .unwrap_or_else(|_| DEFAULT_PROMPT_VERSION.to_string())
+}
+
+/// Resolves the prompt version for one moderation call from `MODERATION_PROMPT_VERSION`,
+/// `MODERATION_CANARY_VERSION` and `MODERATION_CANARY_PERCENT`.
+fn select_prompt_version() -> &'static PromptVersion {
+    let stable = stable_prompt_version();
+    let canary = std::env::var("MODERATION_CANARY_VERSION").ok();
+    let canary_percent = std::env::var("MODERATION_CANARY_PERCENT")
+        .ok()
+        .and_then(|percent| percent.parse::<u8>().ok())
+        .unwrap_or(0);
+    let roll = rand::thread_rng().gen_range(0..100);
+    let id = choose_prompt_version(
+        &stable,
+        canary.as_deref().map(|canary| (canary, canary_percent)),
+        roll,
     );
+    get_prompt_version(id).unwrap_or_else(|e| {
+        log::error!("{:?}, falling back to {}", e, DEFAULT_PROMPT_VERSION);
+        &PROMPT_VERSIONS[0]
+    })
+}
+
+/// The prompt version a token minted now is pinned to. Rolling the canary here rather than per
+/// moderation keeps its share of tokens at `MODERATION_CANARY_PERCENT`.
+pub fn pin_prompt_version() -> String {
+    select_prompt_version().id.to_string()
+}
+
+/// A token's pinned prompt version, or one selected for this call when it has none.
+fn resolve_prompt_version(pinned: Option<&str>) -> &'static PromptVersion {
+    match pinned.map(get_prompt_version) {
+        Some(Ok(prompt_version)) => prompt_version,
+        Some(Err(e)) => {
+            log::error!("{:?}, selecting a prompt version instead", e);
+            select_prompt_version()
+        }
+        None => select_prompt_version(),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Moderation {
+    pub safe: bool,
+    pub prompt_version: String,
+}
+
+/// Returns whether gpt-4o judges `tweet` unsafe under `policy` with the given prompt.
+async fn classify_with_llm(
+    prompt_version: &PromptVersion,
+    tweet: &str,
+    policy: &str,
+) -> eyre::Result<bool> {
+    let client = openai_rust::Client::new(&get_secret("OPENAI_API_KEY")?);
+    let inputs = prompt_version.render(tweet, policy);
     let mut args = openai_rust::chat::ChatArguments::new(
         "gpt-4o",
         vec![openai_rust::chat::Message { role: "user".to_owned(), content: inputs }],
     );
     args.temperature = Some(0.0);
-    let res = client.create_chat(args).await.expect("Failed to create chat");
-    let is_unsafe = res.choices[0].message.content.contains("unsafe");
-    log::info!("gpt-4o response: {:?}", res.choices[0].message.content);
-    !is_unsafe
+    let res = client.create_chat(args).await.map_err(|e| eyre::eyre!("{:?}", e))?;
+    let content =
+        &res.choices.first().ok_or_else(|| eyre::eyre!("Empty chat response"))?.message.content;
+    log::info!("gpt-4o response ({}): {:?}", prompt_version.id, content);
+    Ok(content.contains("unsafe"))
+}
+
+/// Moderates `tweet` with the `pinned` prompt version, if any. The local model has its own.
+pub async fn moderate_tweet(tweet: &String, policy: &String, pinned: Option<&str>) -> Moderation {
+    #[cfg(feature = "local-moderation")]
+    if std::env::var("MODERATION_BACKEND").as_deref() == Ok("local") {
+        return moderate_tweet_locally(tweet, policy).await;
+    }
+
+    let prompt_version = resolve_prompt_version(pinned);
+    let is_unsafe =
+        classify_with_llm(prompt_version, tweet, policy).await.expect("Failed to create chat");
+    metrics::increment(
+        "moderation_decisions_total",
+        &[("prompt_version", prompt_version.id), ("verdict", verdict(!is_unsafe))],
+    );
+    Moderation { safe: !is_unsafe, prompt_version: prompt_version.id.to_string() }
+}
+
+/// Never falls back to OpenAI: a failing local model rejects the tweet rather than sending its
+/// content out of the enclave.
+#[cfg(feature = "local-moderation")]
+async fn moderate_tweet_locally(tweet: &str, policy: &str) -> Moderation {
+    use crate::local_moderation::{is_tweet_unsafe, LOCAL_PROMPT_VERSION};
+
+    let safe = match is_tweet_unsafe(tweet.to_string(), policy.to_string()).await {
+        Ok(is_unsafe) => !is_unsafe,
+        Err(e) => {
+            log::error!("Local moderation failed: {:?}", e);
+            false
+        }
+    };
+    metrics::increment(
+        "moderation_decisions_total",
+        &[("prompt_version", LOCAL_PROMPT_VERSION), ("verdict", verdict(safe))],
+    );
+    Moderation { safe, prompt_version: LOCAL_PROMPT_VERSION.to_string() }
+}
+
+/// A moderator that can be asked on its own, as each member of a quorum is: an LLM prompt
+/// version, or `local` for the local model.
+#[derive(Clone, Copy)]
+pub enum Moderator {
+    Llm(&'static PromptVersion),
+    #[cfg(feature = "local-moderation")]
+    Local,
+}
+
+impl Moderator {
+    pub fn parse(id: &str) -> eyre::Result<Self> {
+        #[cfg(feature = "local-moderation")]
+        if id == "local" {
+            return Ok(Self::Local);
+        }
+        Ok(Self::Llm(get_prompt_version(id)?))
+    }
+
+    /// The prompt version its verdicts are recorded under.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Llm(prompt_version) => prompt_version.id,
+            #[cfg(feature = "local-moderation")]
+            Self::Local => crate::local_moderation::LOCAL_PROMPT_VERSION,
+        }
+    }
+
+    pub fn calls_openai(&self) -> bool {
+        matches!(self, Self::Llm(_))
+    }
+
+    /// Whether it judges `tweet` safe under `policy`. Unlike [`moderate_tweet`], a failing
+    /// moderator returns its error rather than a verdict.
+    pub async fn judge(&self, tweet: &str, policy: &str) -> eyre::Result<bool> {
+        let is_unsafe = match self {
+            Self::Llm(prompt_version) => classify_with_llm(prompt_version, tweet, policy).await?,
+            #[cfg(feature = "local-moderation")]
+            Self::Local => {
+                crate::local_moderation::is_tweet_unsafe(tweet.to_string(), policy.to_string())
+                    .await?
+            }
+        };
+        metrics::increment(
+            "moderation_decisions_total",
+            &[("prompt_version", self.id()), ("verdict", verdict(!is_unsafe))],
+        );
+        Ok(!is_unsafe)
+    }
+}
+
+const EMBEDDING_MODEL: &str = "text-embedding-3-small";
+
+/// Recorded as the prompt version when the embedding precheck rejects a tweet on its own.
+pub const PRECHECK_PROMPT_VERSION: &str = "embedding-precheck";
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingData>,
+}
+
+async fn embed(inputs: Vec<String>) -> eyre::Result<Vec<Vec<f32>>> {
+    let api_key = get_secret("OPENAI_API_KEY")?;
+    let response: EmbeddingsResponse = reqwest::Client::new()
+        .post("https://api.openai.com/v1/embeddings")
+        .bearer_auth(api_key)
+        .json(&serde_json::json!({ "model": EMBEDDING_MODEL, "input": inputs }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(response.data.into_iter().map(|data| data.embedding).collect())
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+/// With the local backend the precheck embeds with the local model too, and fails rather than
+/// sending the tweet to OpenAI when that model is not configured.
+pub async fn policy_similarity(tweet: &str, policy: &str) -> eyre::Result<f32> {
+    let inputs = vec![tweet.to_string(), policy.to_string()];
+    #[cfg(feature = "local-moderation")]
+    let embeddings = if uses_local_model() {
+        crate::local_moderation::embed(inputs).await?
+    } else {
+        embed(inputs).await?
+    };
+    #[cfg(not(feature = "local-moderation"))]
+    let embeddings = embed(inputs).await?;
+    let [tweet_embedding, policy_embedding] = embeddings.as_slice() else {
+        eyre::bail!("Expected two embeddings, got {}", embeddings.len());
+    };
+    Ok(cosine_similarity(tweet_embedding, policy_embedding))
+}
+
+/// Similarity threshold applied to policies without their own, from
+/// `EMBEDDING_PRECHECK_THRESHOLD`. The precheck is disabled when neither is set.
+pub fn get_default_precheck_threshold() -> Option<f32> {
+    std::env::var("EMBEDDING_PRECHECK_THRESHOLD").ok().and_then(|threshold| threshold.parse().ok())
+}
+
+fn record_precheck(precheck: &str, llm: &str) {
+    metrics::increment("moderation_precheck_total", &[("precheck", precheck), ("llm", llm)]);
+}
+
+fn verdict(safe: bool) -> &'static str {
+    if safe {
+        "safe"
+    } else {
+        "unsafe"
+    }
+}
+
+/// Whether `MODERATION_BACKEND` selects the local model, which only builds with the
+/// `local-moderation` feature have.
+fn uses_local_model() -> bool {
+    cfg!(feature = "local-moderation") &&
+        std::env::var("MODERATION_BACKEND").as_deref() == Ok("local")
+}
+
+/// Whether moderating calls OpenAI, for the embedding precheck or the LLM: neither does when the
+/// local model is configured.
+pub fn calls_openai() -> bool {
+    !uses_local_model()
+}
+
+/// Rejects tweets whose embedding is too far from the policy before paying for the LLM call.
+///
+/// A sample of rejections (`EMBEDDING_PRECHECK_SHADOW_PERCENT`) is still sent to the LLM so the
+/// `moderation_precheck_total` counters give the precheck's precision and recall against it.
+pub async fn moderate_tweet_with_precheck(
+    tweet: &String,
+    policy: &String,
+    threshold: Option<f32>,
+    pinned: Option<&str>,
+) -> Moderation {
+    let Some(threshold) = threshold else {
+        return moderate_tweet(tweet, policy, pinned).await;
+    };
+    let similarity = match policy_similarity(tweet, policy).await {
+        Ok(similarity) => similarity,
+        Err(e) => {
+            log::error!("Embedding precheck failed, falling back to LLM only: {:?}", e);
+            return moderate_tweet(tweet, policy, pinned).await;
+        }
+    };
+    if similarity >= threshold {
+        let moderation = moderate_tweet(tweet, policy, pinned).await;
+        record_precheck("pass", verdict(moderation.safe));
+        return moderation;
+    }
+
+    log::info!("Embedding precheck rejected tweet: similarity {} < {}", similarity, threshold);
+    let shadow_percent = std::env::var("EMBEDDING_PRECHECK_SHADOW_PERCENT")
+        .ok()
+        .and_then(|percent| percent.parse::<u8>().ok())
+        .unwrap_or(0);
+    if rand::thread_rng().gen_range(0..100) < shadow_percent {
+        let moderation = moderate_tweet(tweet, policy, pinned).await;
+        record_precheck("reject", verdict(moderation.safe));
+    } else {
+        record_precheck("reject", "skipped");
+    }
+    Moderation { safe: false, prompt_version: PRECHECK_PROMPT_VERSION.to_string() }
+}
+
+/// Moderates `tweet` under explicit settings without recording metrics, for previewing a settings
+/// change against past redemptions. Returns whether the tweet would be judged safe.
+pub async fn dry_run_moderation(
+    tweet: &str,
+    policy: &str,
+    prompt_version: &str,
+    precheck_threshold: Option<f32>,
+) -> eyre::Result<bool> {
+    if let Some(threshold) = precheck_threshold {
+        if policy_similarity(tweet, policy).await? < threshold {
+            return Ok(false);
+        }
+    }
+    #[cfg(feature = "local-moderation")]
+    if std::env::var("MODERATION_BACKEND").as_deref() == Ok("local") {
+        let is_unsafe =
+            crate::local_moderation::is_tweet_unsafe(tweet.to_string(), policy.to_string()).await?;
+        return Ok(!is_unsafe);
+    }
+    let is_unsafe = classify_with_llm(get_prompt_version(prompt_version)?, tweet, policy).await?;
+    Ok(!is_unsafe)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::oai::is_tweet_safe;
+    use crate::oai::{choose_prompt_version, cosine_similarity, moderate_tweet};
+
+    #[test]
+    fn cosine_similarity_bounds() {
+        assert!((cosine_similarity(&[1.0, 2.0], &[2.0, 4.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+    }
+
+    #[test]
+    fn prompt_version_canary_split() {
+        assert_eq!(choose_prompt_version("v1", None, 0), "v1");
+        assert_eq!(choose_prompt_version("v1", Some(("v2", 10)), 9), "v2");
+        assert_eq!(choose_prompt_version("v1", Some(("v2", 10)), 10), "v1");
+        assert_eq!(choose_prompt_version("v1", Some(("v2", 0)), 0), "v1");
+    }
 
     async fn test_is_tweet_safe(tweet: &str, policy: &str, expected: bool) {
         dotenv::dotenv().ok();
-        let is_safe = is_tweet_safe(&tweet.to_string(), &policy.to_string()).await;
+        let is_safe = moderate_tweet(&tweet.to_string(), &policy.to_string(), None).await.safe;
         assert_eq!(is_safe, expected);
     }
 
@@ -0,0 +1,391 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Path, Query, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+pub(crate) use teleport::middleware::{RateLimiter, RATE_LIMIT_WINDOW};
+
+use crate::{
+    actions::chain::TokenStandard,
+    context::ReadCtx,
+    db::{CollectionStats, ContentLicense, RoyaltyInfo, TeleportDB},
+    metadata,
+};
+
+const DEFAULT_RATE_LIMIT_PER_MINUTE: u32 = 60;
+
+const TOKEN_MAX_AGE_SECS: u64 = 300;
+/// A redemption receipt never changes once the tweet is out.
+const RECEIPT_MAX_AGE_SECS: u64 = 24 * 60 * 60;
+const STATS_MAX_AGE: Duration = Duration::from_secs(60);
+const DEFAULT_ROYALTY_CACHE_SECS: u64 = 60 * 60;
+/// Metadata changes on transfers and redemptions, which the frontend polls for.
+const METADATA_MAX_AGE_SECS: u64 = 30;
+/// Token URI metadata only changes when it is pinned.
+const TOKEN_URI_MAX_AGE_SECS: u64 = 5 * 60;
+
+/// Requests per client IP per minute on the public API, from `PUBLIC_API_RATE_LIMIT_PER_MINUTE`.
+pub(crate) fn get_rate_limit() -> u32 {
+    std::env::var("PUBLIC_API_RATE_LIMIT_PER_MINUTE")
+        .ok()
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(DEFAULT_RATE_LIMIT_PER_MINUTE)
+}
+
+pub(crate) fn rate_limiter() -> &'static Mutex<RateLimiter> {
+    static RATE_LIMITER: OnceLock<Mutex<RateLimiter>> = OnceLock::new();
+    RATE_LIMITER.get_or_init(Default::default)
+}
+
+/// Rejects clients that exceed the public API's rate limit with 429.
+pub async fn rate_limit(
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let allowed =
+        rate_limiter().lock().unwrap().allow(client.ip(), Instant::now(), get_rate_limit());
+    if !allowed {
+        return StatusCode::TOO_MANY_REQUESTS.into_response();
+    }
+    next.run(request).await
+}
+
+fn cached<T: Serialize>(body: T, max_age_secs: u64) -> Response {
+    let mut response = Json(body).into_response();
+    let cache_control = format!("public, max-age={}", max_age_secs);
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_str(&cache_control).unwrap());
+    response
+}
+
+#[derive(Deserialize)]
+pub struct PublicTokenQuery {
+    chain_id: Option<u64>,
+    token_id: String,
+}
+
+#[derive(Serialize)]
+pub struct PublicToken {
+    chain_id: u64,
+    token_id: String,
+    contract: String,
+    standard: &'static str,
+    redeemed: bool,
+    /// The content license its redemption declared.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license: Option<ContentLicense>,
+}
+
+/// What a token is and whether it has been redeemed, without anything about its holder.
+pub async fn get_token<A: TeleportDB>(
+    State(ctx): State<ReadCtx<A>>,
+    Query(query): Query<PublicTokenQuery>,
+) -> Result<Response, StatusCode> {
+    let chain = ctx.chain(query.chain_id).ok_or(StatusCode::NOT_FOUND)?;
+    let chain_id = chain.config().chain_id;
+    let db = ctx.db.lock().await;
+    db.get_nft_by_token_id(chain_id, query.token_id.clone()).map_err(|_| StatusCode::NOT_FOUND)?;
+    let redeemed =
+        db.get_moderation_record(chain_id, query.token_id.clone()).is_ok_and(|record| record.safe);
+    let license = db
+        .get_redemption_license(chain_id, query.token_id.clone())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    drop(db);
+    let standard = match chain.config().token_standard {
+        TokenStandard::Erc721 => "erc721",
+        TokenStandard::Erc1155 => "erc1155",
+    };
+    Ok(cached(
+        PublicToken {
+            chain_id,
+            token_id: query.token_id,
+            contract: chain.config().nft_address.to_string(),
+            standard,
+            redeemed,
+            license,
+        },
+        TOKEN_MAX_AGE_SECS,
+    ))
+}
+
+#[derive(Serialize)]
+pub struct RedemptionReceipt {
+    chain_id: u64,
+    token_id: String,
+    safe: bool,
+    prompt_version: String,
+    tweet_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license: Option<ContentLicense>,
+}
+
+/// The moderation verdict on a redeemed token and the tweet it produced.
+pub async fn get_redemption<A: TeleportDB>(
+    State(ctx): State<ReadCtx<A>>,
+    Query(query): Query<PublicTokenQuery>,
+) -> Result<Response, StatusCode> {
+    let chain_id = query.chain_id.unwrap_or(ctx.default_chain_id);
+    let db = ctx.db.lock().await;
+    let record = db
+        .get_moderation_record(chain_id, query.token_id.clone())
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let tweet_id = db.get_tweet(chain_id, query.token_id.clone()).ok();
+    let license = db
+        .get_redemption_license(chain_id, query.token_id.clone())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    drop(db);
+    // A safe redemption gets its tweet id shortly after the verdict.
+    let max_age_secs = if record.safe && tweet_id.is_none() { 0 } else { RECEIPT_MAX_AGE_SECS };
+    Ok(cached(
+        RedemptionReceipt {
+            chain_id,
+            token_id: query.token_id,
+            safe: record.safe,
+            prompt_version: record.prompt_version,
+            tweet_id,
+            license,
+        },
+        max_age_secs,
+    ))
+}
+
+/// How long a royalty read from the NFT contract is served from the DB, from
+/// `ROYALTY_CACHE_SECS`. Contracts can change royalties, so they are read again after this.
+fn get_royalty_cache_secs() -> u64 {
+    std::env::var("ROYALTY_CACHE_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(DEFAULT_ROYALTY_CACHE_SECS)
+}
+
+#[derive(Deserialize)]
+pub struct ChainQuery {
+    chain_id: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct Royalty {
+    chain_id: u64,
+    token_id: String,
+    receiver: String,
+    basis_points: u64,
+}
+
+/// A token's EIP-2981 royalty, for marketplaces. A contract without ERC-2981 pays no royalty.
+pub async fn get_royalty<A: TeleportDB>(
+    State(ctx): State<ReadCtx<A>>,
+    Path(token_id): Path<String>,
+    Query(query): Query<ChainQuery>,
+) -> Result<Response, StatusCode> {
+    let chain = ctx.chain(query.chain_id).ok_or(StatusCode::NOT_FOUND)?;
+    let chain_id = chain.config().chain_id;
+    let token = token_id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let db = ctx.db.lock().await;
+    db.get_nft_by_token_id(chain_id, token_id.clone()).map_err(|_| StatusCode::NOT_FOUND)?;
+    let now = chrono::Utc::now().timestamp();
+    let max_age_secs = get_royalty_cache_secs();
+    let cached_royalty = db
+        .get_royalty_info(chain_id, token_id.clone())
+        .ok()
+        .filter(|royalty| now - royalty.fetched_at < max_age_secs as i64);
+    drop(db);
+
+    let royalty = match cached_royalty {
+        Some(royalty) => royalty,
+        None => {
+            let (receiver, basis_points) = chain.royalty(token).await.map_err(|e| {
+                log::error!("Failed to read royalty of NFT {}: {:?}", token_id, e);
+                StatusCode::BAD_GATEWAY
+            })?;
+            let royalty =
+                RoyaltyInfo { receiver: receiver.to_string(), basis_points, fetched_at: now };
+            ctx.db
+                .cache_royalty(chain_id, token_id.clone(), royalty.clone())
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            royalty
+        }
+    };
+    let max_age_secs = max_age_secs.saturating_sub((now - royalty.fetched_at) as u64);
+    Ok(cached(
+        Royalty {
+            chain_id,
+            token_id,
+            receiver: royalty.receiver,
+            basis_points: royalty.basis_points,
+        },
+        max_age_secs,
+    ))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedemptionStatus {
+    Unredeemed,
+    /// Redeemed, but moderation kept the tweet from being posted.
+    Rejected,
+    Redeemed,
+}
+
+#[derive(Serialize)]
+pub struct NftMetadata {
+    chain_id: u64,
+    token_id: String,
+    contract: String,
+    /// The current holder read from the contract. Edition tokens have none.
+    owner: Option<String>,
+    /// The creator's X account.
+    x_id: Option<String>,
+    twitter_user_name: Option<String>,
+    /// The policy the token was redeemed under, while the marketplace index keeps it.
+    policy: Option<String>,
+    policy_hash: Option<String>,
+    mint_tx: Option<String>,
+    redemption: RedemptionStatus,
+    tweet_id: Option<String>,
+    tweet_url: Option<String>,
+}
+
+/// Everything the frontend shows about a token, from the DB, the marketplace index and the
+/// contract. The index and contract reads are best effort; what they fail to return is left out.
+pub async fn get_nft_metadata<A: TeleportDB>(
+    State(ctx): State<ReadCtx<A>>,
+    Path(token_id): Path<String>,
+    Query(query): Query<ChainQuery>,
+) -> Result<Response, StatusCode> {
+    let chain = ctx.chain(query.chain_id).ok_or(StatusCode::NOT_FOUND)?;
+    let chain_id = chain.config().chain_id;
+    let token = token_id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let db = ctx.db.lock().await;
+    let nft =
+        db.get_nft_by_token_id(chain_id, token_id.clone()).map_err(|_| StatusCode::NOT_FOUND)?;
+    let x_id = db.get_user_by_address(nft.address).ok().and_then(|user| user.x_id);
+    let policy_hash = db.get_policy_hash(chain_id, token_id.clone()).ok().flatten();
+    let mint_tx = db.get_mint_tx(chain_id, token_id.clone()).ok().flatten();
+    let redemption = match db.get_moderation_record(chain_id, token_id.clone()) {
+        Ok(record) if record.safe => RedemptionStatus::Redeemed,
+        Ok(_) => RedemptionStatus::Rejected,
+        Err(_) => RedemptionStatus::Unredeemed,
+    };
+    let tweet_id = db.get_tweet(chain_id, token_id.clone()).ok();
+    drop(db);
+
+    let (owner, listing, redeemed) = futures::join!(
+        chain.owner(token),
+        ctx.marketplace.find_token_owner(chain_id, token_id.clone()),
+        ctx.marketplace.find_redemption_policy(chain_id, token_id.clone()),
+    );
+    // Burned tokens have no owner, so a failed read is not worth failing the request over.
+    let owner = owner
+        .map_err(|e| log::warn!("Failed to read owner of NFT {}: {:?}", token_id, e))
+        .ok()
+        .flatten();
+    let twitter_user_name = listing
+        .map_err(|e| log::warn!("Failed to read listing of NFT {}: {:?}", token_id, e))
+        .ok()
+        .flatten()
+        .map(|listing| listing.twitter_user_name);
+    let policy = redeemed
+        .map_err(|e| log::warn!("Failed to read redemption of NFT {}: {:?}", token_id, e))
+        .ok()
+        .flatten();
+    let tweet_url = tweet_id.as_ref().map(|tweet_id| {
+        let user_name = twitter_user_name.as_deref().unwrap_or("i");
+        format!("https://x.com/{}/status/{}", user_name, tweet_id)
+    });
+    Ok(cached(
+        NftMetadata {
+            chain_id,
+            token_id,
+            contract: chain.config().nft_address.to_string(),
+            owner: owner.map(|owner| owner.to_string()),
+            x_id,
+            twitter_user_name,
+            policy,
+            policy_hash,
+            mint_tx,
+            redemption,
+            tweet_id,
+            tweet_url,
+        },
+        METADATA_MAX_AGE_SECS,
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct PublicStatsQuery {
+    chain_id: Option<u64>,
+}
+
+fn stats_cache() -> &'static Mutex<HashMap<u64, (Instant, CollectionStats)>> {
+    static STATS_CACHE: OnceLock<Mutex<HashMap<u64, (Instant, CollectionStats)>>> = OnceLock::new();
+    STATS_CACHE.get_or_init(Default::default)
+}
+
+/// Collection-wide counts, recomputed at most once a minute per chain.
+pub async fn get_collection_stats<A: TeleportDB>(
+    State(ctx): State<ReadCtx<A>>,
+    Query(query): Query<PublicStatsQuery>,
+) -> Result<Response, StatusCode> {
+    let chain_id = query.chain_id.unwrap_or(ctx.default_chain_id);
+    if !ctx.chain_ids.contains(&chain_id) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let now = Instant::now();
+    let cached_stats = stats_cache()
+        .lock()
+        .unwrap()
+        .get(&chain_id)
+        .filter(|(computed_at, _)| now.duration_since(*computed_at) < STATS_MAX_AGE)
+        .map(|(_, stats)| stats.clone());
+    let stats = match cached_stats {
+        Some(stats) => stats,
+        None => {
+            let stats = ctx
+                .db
+                .lock()
+                .await
+                .get_collection_stats(chain_id)
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            stats_cache().lock().unwrap().insert(chain_id, (now, stats.clone()));
+            stats
+        }
+    };
+    Ok(cached(stats, STATS_MAX_AGE.as_secs()))
+}
+
+/// The ERC-721 metadata a token's `tokenURI` resolves to, with the contract's `baseURI` set to
+/// `{TEE_URL}/metadata/{chain_id}/`. Pinned metadata redirects to its IPFS gateway URL; metadata
+/// not pinned yet is served directly.
+pub async fn get_token_uri<A: TeleportDB>(
+    State(ctx): State<ReadCtx<A>>,
+    Path((chain_id, token_id)): Path<(u64, String)>,
+) -> Result<Response, StatusCode> {
+    let record = ctx
+        .db
+        .lock()
+        .await
+        .get_token_metadata(chain_id, token_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if let Some(cid) = record.cid {
+        return Ok(
+            Redirect::temporary(&format!("{}{}", metadata::get_gateway_url(), cid)).into_response()
+        );
+    }
+    let metadata: serde_json::Value =
+        serde_json::from_str(&record.metadata).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(cached(metadata, TOKEN_URI_MAX_AGE_SECS))
+}
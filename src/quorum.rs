@@ -0,0 +1,101 @@
+use crate::{
+    metrics,
+    oai::{Moderation, Moderator},
+};
+
+/// Recorded as the prompt version of redemptions an admin let through after the quorum split.
+pub const QUORUM_REVIEW_VERSION: &str = "quorum-review";
+
+/// The two moderators that must both judge a high-assurance redemption safe, from
+/// `MODERATION_QUORUM`: two comma-separated moderator ids, each a prompt version or `local` for
+/// the local model, as in `v1,local`.
+pub struct Quorum {
+    moderators: [Moderator; 2],
+}
+
+impl Quorum {
+    pub fn from_env() -> eyre::Result<Self> {
+        let ids = std::env::var("MODERATION_QUORUM")
+            .map_err(|_| eyre::eyre!("MODERATION_QUORUM not set"))?;
+        Self::parse(&ids)
+    }
+
+    fn parse(ids: &str) -> eyre::Result<Self> {
+        let ids: Vec<&str> = ids.split(',').map(str::trim).collect();
+        let [first, second] = ids.as_slice() else {
+            eyre::bail!("MODERATION_QUORUM must name two moderators, got {}", ids.len());
+        };
+        if first == second {
+            eyre::bail!("MODERATION_QUORUM must name two different moderators");
+        }
+        Ok(Self { moderators: [Moderator::parse(first)?, Moderator::parse(second)?] })
+    }
+
+    pub fn calls_openai(&self) -> bool {
+        self.moderators.iter().any(Moderator::calls_openai)
+    }
+
+    /// Asks both moderators at once. Their verdict when they agree, recorded under both their
+    /// ids, or `None` when they do not. A moderator that fails fails the whole quorum, so the
+    /// redemption is retried instead of judged by one.
+    pub async fn moderate(&self, tweet: &str, policy: &str) -> eyre::Result<Option<Moderation>> {
+        let [first, second] = &self.moderators;
+        let (first_safe, second_safe) =
+            futures::try_join!(first.judge(tweet, policy), second.judge(tweet, policy))?;
+        let outcome = match (first_safe, second_safe) {
+            (true, true) => "safe",
+            (false, false) => "unsafe",
+            _ => "split",
+        };
+        metrics::increment("moderation_quorum_total", &[("outcome", outcome)]);
+        if first_safe != second_safe {
+            log::warn!(
+                "Moderation quorum split: {} judged {}, {} judged {}",
+                first.id(),
+                if first_safe { "safe" } else { "unsafe" },
+                second.id(),
+                if second_safe { "safe" } else { "unsafe" },
+            );
+            return Ok(None);
+        }
+        let prompt_version = format!("{}+{}", first.id(), second.id());
+        Ok(Some(Moderation { safe: first_safe, prompt_version }))
+    }
+}
+
+/// A high-assurance redemption its quorum split on. The event is dead-lettered, so an admin can
+/// approve the redemption and replay it.
+#[derive(Debug)]
+pub struct QuorumNeedsReview(pub String);
+
+impl std::fmt::Display for QuorumNeedsReview {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Moderators disagree on the redemption of NFT {}", self.0)
+    }
+}
+
+impl std::error::Error for QuorumNeedsReview {}
+
+#[cfg(test)]
+mod tests {
+    use super::Quorum;
+
+    #[test]
+    fn parse_needs_two_different_moderators() {
+        assert!(Quorum::parse("v1").is_err());
+        assert!(Quorum::parse("v1,v1").is_err());
+        assert!(Quorum::parse("v1,unknown").is_err());
+    }
+
+    #[cfg(feature = "local-moderation")]
+    #[test]
+    fn parse_pairs_llm_with_local_model() {
+        let quorum = Quorum::parse("v1, local").unwrap();
+        assert_eq!(
+            quorum.moderators.map(|moderator| moderator.id()),
+            ["v1", crate::local_moderation::LOCAL_PROMPT_VERSION]
+        );
+        assert!(quorum.calls_openai());
+        assert!(Quorum::parse("v1,local,v1").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use alloy::{
+    primitives::{keccak256, Address, Signature, TxHash, U256},
+    sol,
+    sol_types::{eip712_domain, Eip712Domain, SolStruct},
+};
+use serde::Serialize;
+
+const DOMAIN_NAME: &str = "Teleport";
+const DOMAIN_VERSION: &str = "1";
+
+sol! {
+    /// What a holder signs to let `/redeem` redeem their token with `content`.
+    struct RedeemRequest {
+        uint256 tokenId;
+        bytes32 contentHash;
+        uint256 nonce;
+        uint256 deadline;
+    }
+
+    /// What the address a transaction was sent for signs to have `/tx/:hash/cancel` cancel it.
+    struct CancelRequest {
+        bytes32 txHash;
+    }
+}
+
+/// The EIP-712 domain redeem requests are signed under, bound to the NFT contract so a
+/// signature for one chain or collection cannot be replayed on another.
+#[derive(Serialize)]
+pub struct RedeemDomain {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub chain_id: u64,
+    pub verifying_contract: Address,
+}
+
+impl RedeemDomain {
+    pub fn new(chain_id: u64, nft_address: Address) -> Self {
+        Self {
+            name: DOMAIN_NAME,
+            version: DOMAIN_VERSION,
+            chain_id,
+            verifying_contract: nft_address,
+        }
+    }
+
+    fn eip712(&self) -> Eip712Domain {
+        eip712_domain! {
+            name: self.name,
+            version: self.version,
+            chain_id: self.chain_id,
+            verifying_contract: self.verifying_contract,
+        }
+    }
+}
+
+impl RedeemRequest {
+    pub fn new(token_id: U256, content: &str, nonce: u64, deadline: u64) -> Self {
+        Self {
+            tokenId: token_id,
+            contentHash: keccak256(content),
+            nonce: U256::from(nonce),
+            deadline: U256::from(deadline),
+        }
+    }
+
+    /// The address that signed this request under `domain`.
+    pub fn signer(&self, domain: &RedeemDomain, signature: &Signature) -> eyre::Result<Address> {
+        let hash = self.eip712_signing_hash(&domain.eip712());
+        Ok(signature.recover_address_from_prehash(&hash)?)
+    }
+}
+
+impl CancelRequest {
+    pub fn new(tx_hash: TxHash) -> Self {
+        Self { txHash: tx_hash }
+    }
+
+    /// The address that signed this request under `domain`.
+    pub fn signer(&self, domain: &RedeemDomain, signature: &Signature) -> eyre::Result<Address> {
+        let hash = self.eip712_signing_hash(&domain.eip712());
+        Ok(signature.recover_address_from_prehash(&hash)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::signers::{local::PrivateKeySigner, SignerSync};
+
+    use super::*;
+
+    #[test]
+    fn only_the_signed_request_recovers_the_holder() -> eyre::Result<()> {
+        let holder = PrivateKeySigner::random();
+        let domain = RedeemDomain::new(1, Address::with_last_byte(1));
+        let request = RedeemRequest::new(U256::from(7), "gm", 0, 100);
+        let signature = holder.sign_hash_sync(&request.eip712_signing_hash(&domain.eip712()))?;
+        assert_eq!(request.signer(&domain, &signature)?, holder.address());
+
+        let other_content = RedeemRequest::new(U256::from(7), "gn", 0, 100);
+        assert_ne!(other_content.signer(&domain, &signature)?, holder.address());
+        let other_chain = RedeemDomain::new(2, Address::with_last_byte(1));
+        assert_ne!(request.signer(&other_chain, &signature)?, holder.address());
+        Ok(())
+    }
+}
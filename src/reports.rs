@@ -0,0 +1,200 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::{Mutex, OnceLock},
+    time::Instant,
+};
+
+use alloy::hex;
+use axum::{
+    extract::{ConnectInfo, State},
+    http::StatusCode,
+    Json,
+};
+use eyre::OptionExt;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    db::{AbuseReport, ModerationRecord, ReputationSignal, TeleportDB},
+    endpoints::SharedState,
+    kill_switch::{ensure_enabled, SideEffect},
+    metrics, oai,
+    public_api::RateLimiter,
+    reputation,
+};
+
+const DEFAULT_REPORT_RATE_LIMIT_PER_MINUTE: u32 = 5;
+const DEFAULT_REMODERATION_THRESHOLD: usize = 3;
+const DEFAULT_HOLD_AFTER_STRIKES: u32 = 2;
+const MAX_REASON_CHARS: usize = 500;
+
+/// Reports per client IP per minute, from `REPORT_RATE_LIMIT_PER_MINUTE`.
+pub(crate) fn get_report_rate_limit() -> u32 {
+    std::env::var("REPORT_RATE_LIMIT_PER_MINUTE")
+        .ok()
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(DEFAULT_REPORT_RATE_LIMIT_PER_MINUTE)
+}
+
+/// Reporters a tweet needs before it is re-moderated, from `REPORT_REMODERATION_THRESHOLD`.
+fn get_remoderation_threshold() -> usize {
+    std::env::var("REPORT_REMODERATION_THRESHOLD")
+        .ok()
+        .and_then(|threshold| threshold.parse().ok())
+        .unwrap_or(DEFAULT_REMODERATION_THRESHOLD)
+}
+
+/// Strikes after which a creator's redemptions are held, from `REPORT_HOLD_AFTER_STRIKES`.
+fn get_hold_after_strikes() -> u32 {
+    std::env::var("REPORT_HOLD_AFTER_STRIKES")
+        .ok()
+        .and_then(|strikes| strikes.parse().ok())
+        .unwrap_or(DEFAULT_HOLD_AFTER_STRIKES)
+}
+
+/// Whether a creator with `strikes` has their redemptions held for review.
+pub fn is_held(strikes: u32) -> bool {
+    strikes >= get_hold_after_strikes()
+}
+
+/// A redemption that was not posted because its creator is held for review. The event is
+/// dead-lettered, so an admin can replay it after releasing the creator.
+#[derive(Debug)]
+pub struct HeldForReview(pub String);
+
+impl std::fmt::Display for HeldForReview {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Redemptions of creator {} are held for review", self.0)
+    }
+}
+
+impl std::error::Error for HeldForReview {}
+
+pub(crate) fn report_limiter() -> &'static Mutex<RateLimiter> {
+    static REPORT_LIMITER: OnceLock<Mutex<RateLimiter>> = OnceLock::new();
+    REPORT_LIMITER.get_or_init(Default::default)
+}
+
+/// Reporters are only kept as a hash of their IP address.
+fn reporter_id(client: IpAddr) -> String {
+    hex::encode(Sha256::digest(client.to_string()))
+}
+
+/// The x_id of the creator of a token.
+fn get_creator<A: TeleportDB>(db: &A, chain_id: u64, token_id: String) -> eyre::Result<String> {
+    let nft = db.get_nft_by_token_id(chain_id, token_id)?;
+    db.get_user_by_address(nft.address)?.x_id.ok_or_eyre("Creator has no x_id")
+}
+
+#[derive(Deserialize)]
+pub struct ReportQuery {
+    chain_id: Option<u64>,
+    token_id: String,
+    reason: String,
+}
+
+/// Flags a published redemption tweet as abusive. Once enough clients report the same tweet it
+/// is moderated again.
+pub async fn report<A: TeleportDB>(
+    State(shared_state): State<SharedState<A>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Json(query): Json<ReportQuery>,
+) -> Result<StatusCode, StatusCode> {
+    let allowed = report_limiter().lock().unwrap().allow(
+        client.ip(),
+        Instant::now(),
+        get_report_rate_limit(),
+    );
+    if !allowed {
+        return Err(StatusCode::TOO_MANY_REQUESTS);
+    }
+    let reason: String = query.reason.trim().chars().take(MAX_REASON_CHARS).collect();
+    if reason.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let chain_id = query.chain_id.unwrap_or(shared_state.default_chain_id);
+
+    let mut db = shared_state.db.lock().await;
+    db.get_tweet(chain_id, query.token_id.clone()).map_err(|_| StatusCode::NOT_FOUND)?;
+    let report = AbuseReport {
+        reporter: reporter_id(client.ip()),
+        reason,
+        at: chrono::Utc::now().timestamp(),
+    };
+    let reporters = db
+        .add_abuse_report(chain_id, query.token_id.clone(), report)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let creator = get_creator(&*db, chain_id, query.token_id.clone()).ok();
+    drop(db);
+
+    metrics::increment("abuse_reports_total", &[("chain_id", &chain_id.to_string())]);
+    if let Some(creator) = creator.filter(|_| reporters.is_some()) {
+        reputation::record(&shared_state.db, creator, ReputationSignal::Report)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+    if reporters == Some(get_remoderation_threshold()) {
+        tokio::spawn(async move {
+            if let Err(e) = escalate(&shared_state, chain_id, query.token_id.clone()).await {
+                log::error!("Failed to escalate reports of NFT {}: {:?}", query.token_id, e);
+            }
+        });
+    }
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Moderates a much reported tweet again, and counts a strike against its creator if it is now
+/// rejected. A tweet whose content is no longer available is judged on its reports alone.
+async fn escalate<A: TeleportDB>(
+    shared_state: &SharedState<A>,
+    chain_id: u64,
+    token_id: String,
+) -> eyre::Result<()> {
+    let rejected = match shared_state.marketplace.get_redemption(chain_id, token_id.clone()).await?
+    {
+        Some(redemption) => {
+            let db = shared_state.db.lock().await;
+            let snapshot = db.get_policy_snapshot(chain_id, token_id.clone())?;
+            let threshold = match &snapshot {
+                Some(snapshot) => snapshot.precheck_threshold,
+                None => db.get_policy_precheck_threshold(redemption.safeguard.clone()).ok(),
+            }
+            .or_else(oai::get_default_precheck_threshold);
+            drop(db);
+            if oai::calls_openai() {
+                ensure_enabled(SideEffect::OpenAi)?;
+            }
+            let moderation = oai::moderate_tweet_with_precheck(
+                &redemption.content,
+                &redemption.safeguard,
+                threshold,
+                snapshot.as_ref().map(|snapshot| snapshot.prompt_version.as_str()),
+            )
+            .await;
+            if !moderation.safe {
+                shared_state.db.lock().await.add_moderation_record(
+                    chain_id,
+                    token_id.clone(),
+                    ModerationRecord { prompt_version: moderation.prompt_version, safe: false },
+                )?;
+            }
+            !moderation.safe
+        }
+        None => true,
+    };
+    if !rejected {
+        log::info!("Reported NFT {} passed moderation again", token_id);
+        return Ok(());
+    }
+
+    let mut db = shared_state.db.lock().await;
+    let creator = get_creator(&*db, chain_id, token_id.clone())?;
+    let strikes = db.add_creator_strike(creator.clone())?;
+    drop(db);
+    log::warn!("Reported NFT {} rejected, creator {} has {} strikes", token_id, creator, strikes);
+    reputation::record(&shared_state.db, creator, ReputationSignal::Strike).await?;
+    if is_held(strikes) {
+        metrics::increment("creators_held_total", &[]);
+    }
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::{
+    db::{lock::TrackedMutex, CreatorSignals, ReputationSignal, ReputationSnapshot, TeleportDB},
+    metrics,
+};
+
+const MAX_SCORE: u32 = 100;
+const DEFAULT_WATCH_BELOW: u32 = 70;
+const DEFAULT_RESTRICT_BELOW: u32 = 40;
+
+/// Points each signal takes off a creator's score.
+const REJECTION_PENALTY: u32 = 5;
+const REPORT_PENALTY: u32 = 1;
+const STRIKE_PENALTY: u32 = 15;
+const DELETED_TWEET_PENALTY: u32 = 20;
+
+/// Scores below this put a creator under watch, from `REPUTATION_WATCH_BELOW`.
+fn get_watch_below() -> u32 {
+    std::env::var("REPUTATION_WATCH_BELOW")
+        .ok()
+        .and_then(|score| score.parse().ok())
+        .unwrap_or(DEFAULT_WATCH_BELOW)
+}
+
+/// Scores below this restrict a creator, from `REPUTATION_RESTRICT_BELOW`.
+fn get_restrict_below() -> u32 {
+    std::env::var("REPUTATION_RESTRICT_BELOW")
+        .ok()
+        .and_then(|score| score.parse().ok())
+        .unwrap_or(DEFAULT_RESTRICT_BELOW)
+}
+
+/// A creator's score out of 100. Every creator starts at 100.
+pub fn score(signals: &CreatorSignals) -> u32 {
+    [
+        (signals.rejections, REJECTION_PENALTY),
+        (signals.reports, REPORT_PENALTY),
+        (signals.strikes, STRIKE_PENALTY),
+        (signals.deleted_tweets, DELETED_TWEET_PENALTY),
+    ]
+    .into_iter()
+    .fold(MAX_SCORE, |score, (count, penalty)| score.saturating_sub(count.saturating_mul(penalty)))
+}
+
+/// How strictly a creator's tokens are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Standing {
+    Good,
+    /// Redemptions skip the embedding precheck and always get a full moderation, and batch mints
+    /// are capped at half the usual size.
+    Watched,
+    /// Redemptions are held for an admin to review and batch mints are limited to one recipient.
+    Restricted,
+}
+
+impl Standing {
+    pub fn of(score: u32) -> Self {
+        if score < get_restrict_below() {
+            Self::Restricted
+        } else if score < get_watch_below() {
+            Self::Watched
+        } else {
+            Self::Good
+        }
+    }
+
+    pub fn batch_mint_cap(self, cap: usize) -> usize {
+        match self {
+            Self::Good => cap,
+            Self::Watched => (cap / 2).max(1),
+            Self::Restricted => 1,
+        }
+    }
+}
+
+/// The standing of a creator, from their signals so far.
+pub fn standing<A: TeleportDB>(db: &A, x_id: String) -> eyre::Result<Standing> {
+    Ok(Standing::of(score(&db.get_creator_signals(x_id)?)))
+}
+
+/// Counts `signal` against a creator and records their new score. A release clears the count.
+pub async fn record<A: TeleportDB>(
+    db: &Arc<TrackedMutex<A>>,
+    x_id: String,
+    signal: ReputationSignal,
+) -> eyre::Result<u32> {
+    let mut db = db.lock().await;
+    let mut signals = db.get_creator_signals(x_id.clone())?;
+    let before = Standing::of(score(&signals));
+    match signal {
+        ReputationSignal::Rejection => signals.rejections += 1,
+        ReputationSignal::Report => signals.reports += 1,
+        ReputationSignal::Strike => signals.strikes += 1,
+        ReputationSignal::DeletedTweet => signals.deleted_tweets += 1,
+        ReputationSignal::Released => signals = CreatorSignals::default(),
+    }
+    let score = score(&signals);
+    db.set_creator_signals(x_id.clone(), signals)?;
+    let at = chrono::Utc::now().timestamp();
+    db.add_reputation_snapshot(x_id.clone(), ReputationSnapshot { at, signal, score })?;
+    drop(db);
+
+    let after = Standing::of(score);
+    if after != before {
+        log::warn!("Creator {} is now {:?} with score {}", x_id, after, score);
+        let standing = format!("{:?}", after).to_lowercase();
+        metrics::increment("creator_standing_changes_total", &[("standing", &standing)]);
+    }
+    Ok(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signals_lower_the_score_and_tighten_standing() {
+        let clean = CreatorSignals::default();
+        assert_eq!(score(&clean), 100);
+        assert_eq!(Standing::of(score(&clean)), Standing::Good);
+
+        let rejected = CreatorSignals { rejections: 4, reports: 12, ..Default::default() };
+        assert_eq!(score(&rejected), 68);
+        assert_eq!(Standing::of(score(&rejected)), Standing::Watched);
+        assert_eq!(Standing::Watched.batch_mint_cap(50), 25);
+
+        let revoked = CreatorSignals { strikes: 2, deleted_tweets: 2, ..rejected };
+        assert_eq!(score(&revoked), 0);
+        assert_eq!(Standing::of(score(&revoked)), Standing::Restricted);
+        assert_eq!(Standing::Restricted.batch_mint_cap(50), 1);
+    }
+}
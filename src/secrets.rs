@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+use alloy::hex;
+
+use crate::sgx_attest::{read_measurement, EnclaveMeasurement};
+
+/// Gramine encrypted mount holding the secret config, one file per secret.
+const DEFAULT_SECRETS_DIR: &str = "/secrets";
+
+pub(crate) fn is_prod() -> bool {
+    std::env::var("TELEPORT_PROFILE").as_deref() == Ok("prod")
+}
+
+fn secrets_dir() -> PathBuf {
+    std::env::var("SECRETS_DIR").unwrap_or_else(|_| DEFAULT_SECRETS_DIR.to_string()).into()
+}
+
+fn check_measurement(
+    measurement: &EnclaveMeasurement,
+    expected_mr_enclave: Option<&str>,
+    expected_mr_signer: Option<&str>,
+) -> eyre::Result<()> {
+    if let Some(expected) = expected_mr_enclave {
+        let actual = hex::encode(measurement.mr_enclave);
+        if !actual.eq_ignore_ascii_case(expected.trim_start_matches("0x")) {
+            eyre::bail!("MRENCLAVE {} does not match the secrets policy", actual);
+        }
+    }
+    if let Some(expected) = expected_mr_signer {
+        let actual = hex::encode(measurement.mr_signer);
+        if !actual.eq_ignore_ascii_case(expected.trim_start_matches("0x")) {
+            eyre::bail!("MRSIGNER {} does not match the secrets policy", actual);
+        }
+    }
+    Ok(())
+}
+
+/// Checks the running enclave against `SECRETS_EXPECTED_MRENCLAVE`/`SECRETS_EXPECTED_MRSIGNER`
+/// before any secret is read. Prod profiles must pin at least one of them.
+pub fn validate_measurement_policy() -> eyre::Result<()> {
+    let expected_mr_enclave = std::env::var("SECRETS_EXPECTED_MRENCLAVE").ok();
+    let expected_mr_signer = std::env::var("SECRETS_EXPECTED_MRSIGNER").ok();
+    if expected_mr_enclave.is_none() && expected_mr_signer.is_none() {
+        if is_prod() {
+            eyre::bail!(
+                "Prod profile requires SECRETS_EXPECTED_MRENCLAVE or SECRETS_EXPECTED_MRSIGNER"
+            );
+        }
+        log::warn!("No secrets measurement policy configured");
+        return Ok(());
+    }
+    let measurement = read_measurement()?;
+    check_measurement(&measurement, expected_mr_enclave.as_deref(), expected_mr_signer.as_deref())
+}
+
+/// Reads a secret from the protected files mount. Outside of a prod profile a missing file falls
+/// back to the plaintext environment variable of the same name.
+pub fn get_secret(name: &str) -> eyre::Result<String> {
+    let path = secrets_dir().join(name);
+    match std::fs::read_to_string(&path) {
+        Ok(secret) => Ok(secret.trim().to_string()),
+        Err(e) if is_prod() => {
+            eyre::bail!("{} not readable from {:?} and env fallback is disabled: {}", name, path, e)
+        }
+        Err(_) => std::env::var(name).map_err(|_| eyre::eyre!("{} not set", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measurement_policy() {
+        let measurement = EnclaveMeasurement { mr_enclave: [0xab; 32], mr_signer: [0x01; 32] };
+        let mr_enclave = hex::encode([0xab; 32]);
+        let mr_signer = format!("0x{}", hex::encode([0x01; 32]).to_uppercase());
+
+        assert!(check_measurement(&measurement, None, None).is_ok());
+        assert!(check_measurement(&measurement, Some(&mr_enclave), Some(&mr_signer)).is_ok());
+        assert!(check_measurement(&measurement, Some(&mr_signer), None).is_err());
+        assert!(check_measurement(&measurement, None, Some(&mr_enclave)).is_err());
+    }
+}
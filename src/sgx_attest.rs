@@ -36,3 +36,29 @@ pub fn sgx_attest(input: Vec<u8>) -> eyre::Result<Vec<u8>> {
 
     Ok(quote)
 }
+
+/// Measurements of the running enclave, taken from its own SGX report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnclaveMeasurement {
+    pub mr_enclave: [u8; 32],
+    pub mr_signer: [u8; 32],
+}
+
+pub fn read_measurement() -> eyre::Result<EnclaveMeasurement> {
+    let report = match fs::read("/dev/attestation/report") {
+        Ok(report) => report,
+        Err(error) => {
+            eyre::bail!("sgx report read failed {:?}", error);
+        }
+    };
+    if report.len() < 160 {
+        eyre::bail!("sgx report too short: {} bytes", report.len());
+    }
+
+    // Offsets into sgx_report_body_t.
+    let mut mr_enclave = [0u8; 32];
+    mr_enclave.copy_from_slice(&report[64..96]);
+    let mut mr_signer = [0u8; 32];
+    mr_signer.copy_from_slice(&report[128..160]);
+    Ok(EnclaveMeasurement { mr_enclave, mr_signer })
+}
@@ -10,6 +10,12 @@ pub struct PolicyTemplate {
     pub nft_id: String,
 }
 
+#[derive(Template)]
+#[template(path = "redemption_link.html")]
+pub struct RedemptionLinkTemplate {
+    pub token: String,
+}
+
 pub struct HtmlTemplate<T>(pub T);
 
 impl<T> IntoResponse for HtmlTemplate<T>
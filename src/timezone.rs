@@ -0,0 +1,101 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+
+/// Parses an IANA timezone name such as `Europe/Berlin`.
+pub fn parse_timezone(name: &str) -> eyre::Result<Tz> {
+    Tz::from_str(name).map_err(|e| eyre::eyre!("Unknown timezone {}: {}", name, e))
+}
+
+/// `at` as wall-clock time in `timezone`, for messages shown to a creator.
+pub fn format_local(timezone: Tz, at: DateTime<Utc>) -> String {
+    at.with_timezone(&timezone).format("%Y-%m-%d %H:%M %Z").to_string()
+}
+
+/// The instant a local wall-clock time happens in `timezone`. A time skipped by a DST jump is
+/// moved past the jump, and a repeated one resolves to its first occurrence.
+fn resolve_local(timezone: Tz, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+    timezone
+        .from_local_datetime(&local)
+        .earliest()
+        .or_else(|| timezone.from_local_datetime(&(local + Duration::hours(1))).earliest())
+        .map(|at| at.with_timezone(&Utc))
+}
+
+/// Daily local hours during which creators are not notified, written as `<start>-<end>` in
+/// 24-hour clock hours, such as `22-8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    start: u32,
+    end: u32,
+}
+
+impl FromStr for QuietHours {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> eyre::Result<Self> {
+        let (start, end) =
+            s.split_once('-').ok_or_else(|| eyre::eyre!("Expected <start>-<end>, got {}", s))?;
+        let (start, end) = (start.trim().parse()?, end.trim().parse()?);
+        if start > 23 || end > 23 {
+            eyre::bail!("Quiet hours must be between 0 and 23, got {}", s);
+        }
+        Ok(Self { start, end })
+    }
+}
+
+impl QuietHours {
+    fn contains(&self, hour: u32) -> bool {
+        if self.start <= self.end {
+            self.start <= hour && hour < self.end
+        } else {
+            hour >= self.start || hour < self.end
+        }
+    }
+
+    /// When the quiet hours `now` falls in end for someone in `timezone`, or `None` if `now` is
+    /// outside them.
+    pub fn end_after(&self, timezone: Tz, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let local = now.with_timezone(&timezone);
+        if !self.contains(local.hour()) {
+            return None;
+        }
+        let mut date = local.date_naive();
+        if local.hour() >= self.end {
+            date = date.succ_opt()?;
+        }
+        resolve_local(timezone, date.and_hms_opt(self.end, 0, 0)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn quiet_hours_follow_the_creators_clock_across_dst() -> eyre::Result<()> {
+        let quiet: QuietHours = "22-8".parse()?;
+        let berlin = parse_timezone("Europe/Berlin")?;
+        // 23:00 in Berlin on the night clocks go forward, so 08:00 is only eight hours later.
+        assert_eq!(
+            quiet.end_after(berlin, utc("2024-03-30T22:00:00Z")),
+            Some(utc("2024-03-31T06:00:00Z"))
+        );
+        assert_eq!(quiet.end_after(berlin, utc("2024-03-31T12:00:00Z")), None);
+        // New York skips 02:00 that night, so quiet hours ending then end at 03:00 instead.
+        let new_york = parse_timezone("America/New_York")?;
+        let skipped: QuietHours = "1-2".parse()?;
+        assert_eq!(
+            skipped.end_after(new_york, utc("2024-03-10T06:30:00Z")),
+            Some(utc("2024-03-10T07:00:00Z"))
+        );
+        assert_eq!(format_local(berlin, utc("2024-03-31T06:00:00Z")), "2024-03-31 08:00 CEST");
+        assert!("22-24".parse::<QuietHours>().is_err());
+        Ok(())
+    }
+}
@@ -1,21 +1,33 @@
 use oauth1_request::signature_method::hmac_sha1::HmacSha1;
 use reqwest_oauth1::{Client, OAuthClientProvider, Secrets, Signer};
 
-use super::auth::{self, TwitterTokenPair};
+use super::{
+    auth::{self, TwitterTokenPair},
+    tier::{ApiTier, Capabilities},
+};
 
 #[derive(Debug, Clone)]
 pub struct TwitterBuilder {
     pub consumer_key: String,
     pub consumer_secret: String,
+    pub tier: ApiTier,
+    pub capabilities: Capabilities,
 }
 
 pub struct TwitterClient<'a> {
     pub client: Client<Signer<'a, Secrets<'a>, HmacSha1>>,
+    pub capabilities: Capabilities,
 }
 
 impl TwitterBuilder {
+    /// A builder for an app on the free tier, until [`Self::with_tier`] says otherwise.
     pub fn new(consumer_key: String, consumer_secret: String) -> Self {
-        Self { consumer_key, consumer_secret }
+        let tier = ApiTier::Free;
+        Self { consumer_key, consumer_secret, tier, capabilities: tier.capabilities() }
+    }
+
+    pub fn with_tier(self, tier: ApiTier) -> Self {
+        Self { tier, capabilities: tier.capabilities(), ..self }
     }
 
     pub async fn request_oauth_token(
@@ -56,6 +68,6 @@ impl TwitterBuilder {
 
         let client = reqwest::Client::new();
         // client.oauth1(secrets)
-        TwitterClient { client: client.oauth1(secrets) }
+        TwitterClient { client: client.oauth1(secrets), capabilities: self.capabilities }
     }
 }
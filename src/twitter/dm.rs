@@ -0,0 +1,27 @@
+use serde::Serialize;
+
+use super::builder::TwitterClient;
+
+#[derive(Debug, Serialize)]
+struct DirectMessage<'a> {
+    text: &'a str,
+}
+
+impl TwitterClient<'_> {
+    /// Sends a direct message from the authorized user to `participant_id`, which fails if they
+    /// do not accept DMs from the sender.
+    pub async fn send_dm(&self, participant_id: &str, text: &str) -> eyre::Result<()> {
+        let body = serde_json::to_string(&DirectMessage { text })?;
+        self.client
+            .post(format!(
+                "https://api.twitter.com/2/dm_conversations/with/{}/messages",
+                participant_id
+            ))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use serde::Deserialize;
+
+/// What X said was wrong, from the problem details it returns with a failed request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TwitterErrorKind {
+    /// The account already posted a tweet with the same content.
+    DuplicateContent,
+    RateLimited,
+    Other,
+}
+
+/// A request X refused, with the reason it gave.
+#[derive(Debug, Clone)]
+pub struct TwitterError {
+    pub status: u16,
+    pub kind: TwitterErrorKind,
+    pub detail: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Problem {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    detail: String,
+}
+
+impl TwitterError {
+    /// Reads the problem details X sent with a failed request. Bodies it cannot parse are kept
+    /// as the detail.
+    pub fn from_response(status: u16, body: &str) -> Self {
+        let detail = match serde_json::from_str::<Problem>(body) {
+            Ok(problem) if !problem.detail.is_empty() => problem.detail,
+            Ok(problem) if !problem.title.is_empty() => problem.title,
+            _ => body.to_string(),
+        };
+        let kind = if status == 429 {
+            TwitterErrorKind::RateLimited
+        } else if status == 403 && detail.to_lowercase().contains("duplicate content") {
+            TwitterErrorKind::DuplicateContent
+        } else {
+            TwitterErrorKind::Other
+        };
+        Self { status, kind, detail }
+    }
+
+    pub fn is_duplicate(&self) -> bool {
+        self.kind == TwitterErrorKind::DuplicateContent
+    }
+}
+
+impl std::fmt::Display for TwitterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "X refused the request ({}): {}", self.status, self.detail)
+    }
+}
+
+impl std::error::Error for TwitterError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_duplicate_content() {
+        let body = r#"{"detail":"You are not allowed to create a Tweet with duplicate content.","type":"about:blank","title":"Forbidden","status":403}"#;
+        assert!(TwitterError::from_response(403, body).is_duplicate());
+        let error = TwitterError::from_response(403, r#"{"title":"Forbidden"}"#);
+        assert_eq!(error.kind, TwitterErrorKind::Other);
+        assert_eq!(error.detail, "Forbidden");
+        assert_eq!(TwitterError::from_response(429, "").kind, TwitterErrorKind::RateLimited);
+    }
+}
@@ -31,3 +31,46 @@ impl TwitterClient<'_> {
         Ok(user_info)
     }
 }
+
+#[derive(Debug, Deserialize)]
+struct UserByIdResponse {
+    data: UserIdentity,
+}
+
+#[derive(Debug, Deserialize)]
+struct UsersByUsernameResponse {
+    #[serde(default)]
+    data: Vec<UserIdentity>,
+}
+
+/// The account a handle belongs to right now.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserIdentity {
+    pub id: String,
+    pub username: String,
+}
+
+impl TwitterClient<'_> {
+    /// Resolves up to 100 handles at once. Handles that do not exist, or belong to suspended
+    /// accounts, are left out.
+    pub async fn get_users_by_usernames(
+        &self,
+        usernames: &[String],
+    ) -> eyre::Result<Vec<UserIdentity>> {
+        if usernames.is_empty() {
+            return Ok(Vec::new());
+        }
+        let url = format!("https://api.twitter.com/2/users/by?usernames={}", usernames.join(","));
+        let resp = self.client.get(url).send().await?.error_for_status()?;
+        let users: UsersByUsernameResponse = resp.json().await?;
+        Ok(users.data)
+    }
+
+    /// The account with X user id `id`, for its current handle.
+    pub async fn get_user_by_id(&self, id: &str) -> eyre::Result<UserIdentity> {
+        let url = format!("https://api.twitter.com/2/users/{}", id);
+        let resp = self.client.get(url).send().await?.error_for_status()?;
+        let user: UserByIdResponse = resp.json().await?;
+        Ok(user.data)
+    }
+}
@@ -1,8 +1,12 @@
 pub mod auth;
 pub mod builder;
+pub mod dm;
+pub mod error;
 pub mod info;
 pub mod post;
 pub mod react;
+pub mod tier;
+pub mod timeline;
 pub mod tweet;
 
 pub fn get_callback_url(
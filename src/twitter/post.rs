@@ -1,6 +1,6 @@
 use serde::Deserialize;
 
-use super::{builder::TwitterClient, tweet::Tweet};
+use super::{builder::TwitterClient, error::TwitterError, tweet::Tweet};
 
 #[derive(Debug, Deserialize)]
 struct SendTweetData {
@@ -14,6 +14,16 @@ struct SendTweetResponse {
     data: SendTweetData,
 }
 
+#[derive(Debug, Deserialize)]
+struct DeleteTweetData {
+    deleted: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeleteTweetResponse {
+    data: DeleteTweetData,
+}
+
 #[derive(Deserialize, Debug)]
 struct MediaUploadResponse {
     // media_data: String,
@@ -32,7 +42,11 @@ impl TwitterClient<'_> {
             .send()
             .await?;
 
+        let status = resp.status();
         let body = resp.text().await?;
+        if !status.is_success() {
+            return Err(TwitterError::from_response(status.as_u16(), &body).into());
+        }
 
         let tweet_response: Result<SendTweetResponse, _> = serde_json::from_str(&body);
         match tweet_response {
@@ -47,6 +61,25 @@ impl TwitterClient<'_> {
         }
     }
 
+    /// Deletes one of the authorized user's tweets.
+    pub async fn delete_tweet(&self, tweet_id: String) -> eyre::Result<()> {
+        let resp = self
+            .client
+            .delete(format!("https://api.twitter.com/2/tweets/{}", tweet_id))
+            .send()
+            .await?;
+
+        let body = resp.text().await?;
+        match serde_json::from_str::<DeleteTweetResponse>(&body) {
+            Ok(response) if response.data.deleted => Ok(()),
+            Ok(_) => Err(eyre::eyre!("Tweet {} was not deleted", tweet_id)),
+            Err(e) => {
+                log::error!("Failed to decode delete tweet response: {:?}, body: {}", e, body);
+                Err(eyre::eyre!("Failed to decode delete tweet response"))
+            }
+        }
+    }
+
     pub async fn upload_media(
         &self,
         media_bytes: Vec<u8>,
@@ -8,7 +8,12 @@ struct LikeTweet {
 }
 
 impl TwitterClient<'_> {
+    /// Likes `tweet_id` as `x_id`. Skipped on tiers that cannot post likes.
     pub async fn like(&self, x_id: String, tweet_id: String) -> eyre::Result<()> {
+        if !self.capabilities.reactions {
+            log::warn!("X API tier cannot like tweets, not liking {}", tweet_id);
+            return Ok(());
+        }
         let _ = self
             .client
             .post(format!("https://api.twitter.com/2/users/{}/likes", x_id))
@@ -19,7 +24,12 @@ impl TwitterClient<'_> {
         Ok(())
     }
 
+    /// Retweets `tweet_id` as `x_id`. Skipped on tiers that cannot post retweets.
     pub async fn retweet(&self, x_id: String, tweet_id: String) -> eyre::Result<()> {
+        if !self.capabilities.reactions {
+            log::warn!("X API tier cannot retweet, not retweeting {}", tweet_id);
+            return Ok(());
+        }
         let _ = self
             .client
             .post(format!("https://api.twitter.com/2/users/{}/retweets", x_id))
@@ -0,0 +1,149 @@
+use std::str::FromStr;
+
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+const BEARER_TOKEN_URL: &str = "https://api.twitter.com/oauth2/token";
+const RECENT_SEARCH_URL: &str =
+    "https://api.twitter.com/2/tweets/search/recent?query=from:X&max_results=10";
+const FULL_ARCHIVE_SEARCH_URL: &str =
+    "https://api.twitter.com/2/tweets/search/all?query=from:X&max_results=10";
+
+/// The X API access tier the app's developer account is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiTier {
+    Free,
+    Basic,
+    Pro,
+}
+
+impl FromStr for ApiTier {
+    type Err = eyre::Report;
+
+    fn from_str(tier: &str) -> eyre::Result<Self> {
+        match tier {
+            "free" => Ok(Self::Free),
+            "basic" => Ok(Self::Basic),
+            "pro" => Ok(Self::Pro),
+            _ => eyre::bail!("Unknown X API tier {}", tier),
+        }
+    }
+}
+
+/// What publishing may rely on at an API tier. Features a tier lacks are left out of tweets
+/// rather than failing the redemption.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Capabilities {
+    pub media_upload: bool,
+    pub polls: bool,
+    /// Likes and retweets, which the free tier cannot post.
+    pub reactions: bool,
+    /// Tweets each user may post per day through the app.
+    pub posts_per_day: u32,
+}
+
+impl ApiTier {
+    pub fn capabilities(self) -> Capabilities {
+        match self {
+            Self::Free => Capabilities {
+                media_upload: true,
+                polls: true,
+                reactions: false,
+                posts_per_day: 17,
+            },
+            Self::Basic => Capabilities {
+                media_upload: true,
+                polls: true,
+                reactions: true,
+                posts_per_day: 100,
+            },
+            // 100 per 15 minutes.
+            Self::Pro => Capabilities {
+                media_upload: true,
+                polls: true,
+                reactions: true,
+                posts_per_day: 9_600,
+            },
+        }
+    }
+}
+
+impl Capabilities {
+    /// The features of a tweet with media and/or a poll that would be left out when published.
+    pub fn unsupported(&self, has_media: bool, has_poll: bool) -> Vec<&'static str> {
+        let mut unsupported = Vec::new();
+        if has_media && !self.media_upload {
+            unsupported.push("media");
+        }
+        if has_poll && !self.polls {
+            unsupported.push("poll");
+        }
+        unsupported
+    }
+}
+
+#[derive(Deserialize)]
+struct BearerTokenResponse {
+    access_token: String,
+}
+
+async fn app_bearer_token(consumer_key: &str, consumer_secret: &str) -> eyre::Result<String> {
+    let response: BearerTokenResponse = reqwest::Client::new()
+        .post(BEARER_TOKEN_URL)
+        .basic_auth(consumer_key, Some(consumer_secret))
+        .form(&[("grant_type", "client_credentials")])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(response.access_token)
+}
+
+/// Checks X accepts the app's consumer key and secret, so bad credentials stop startup instead
+/// of failing every login and tweet.
+pub async fn check_credentials(consumer_key: &str, consumer_secret: &str) -> eyre::Result<()> {
+    let status = reqwest::Client::new()
+        .post(BEARER_TOKEN_URL)
+        .basic_auth(consumer_key, Some(consumer_secret))
+        .form(&[("grant_type", "client_credentials")])
+        .send()
+        .await?
+        .status();
+    match status {
+        status if status.is_success() => Ok(()),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => eyre::bail!(
+            "X rejected the app's credentials ({}); check TWITTER_CONSUMER_KEY and \
+             TWITTER_CONSUMER_SECRET belong to the same app and were not regenerated",
+            status
+        ),
+        status => eyre::bail!("Unexpected status {} checking the app's X credentials", status),
+    }
+}
+
+/// Whether the app's tier includes `url`. A rate limited request still proves access.
+async fn has_access(bearer_token: &str, url: &str) -> eyre::Result<bool> {
+    let status = reqwest::Client::new().get(url).bearer_auth(bearer_token).send().await?.status();
+    match status {
+        StatusCode::FORBIDDEN => Ok(false),
+        status if status.is_success() || status == StatusCode::TOO_MANY_REQUESTS => Ok(true),
+        status => eyre::bail!("Unexpected status {} probing {}", status, url),
+    }
+}
+
+/// The app's tier from `TWITTER_API_TIER`, or else probed from the search endpoints only paid
+/// tiers can read: recent search from basic up, full-archive search from pro up.
+pub async fn detect_tier(consumer_key: &str, consumer_secret: &str) -> eyre::Result<ApiTier> {
+    if let Ok(tier) = std::env::var("TWITTER_API_TIER") {
+        return tier.parse();
+    }
+    let bearer_token = app_bearer_token(consumer_key, consumer_secret).await?;
+    if has_access(&bearer_token, FULL_ARCHIVE_SEARCH_URL).await? {
+        Ok(ApiTier::Pro)
+    } else if has_access(&bearer_token, RECENT_SEARCH_URL).await? {
+        Ok(ApiTier::Basic)
+    } else {
+        Ok(ApiTier::Free)
+    }
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Deserialize;
+
+use super::builder::TwitterClient;
+
+/// Most tweets one timeline request returns.
+const MAX_TIMELINE_RESULTS: u32 = 100;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TimelineTweet {
+    pub id: String,
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TimelineResponse {
+    #[serde(default)]
+    data: Vec<TimelineTweet>,
+}
+
+/// Tweet text as it compares across posting and reading back: X rewrites links to t.co and may
+/// trim whitespace, so links are dropped and whitespace runs collapsed.
+fn normalize(text: &str) -> Vec<&str> {
+    let is_link = |word: &&str| word.starts_with("http://") || word.starts_with("https://");
+    text.split_whitespace().filter(|word| !is_link(word)).collect()
+}
+
+impl TwitterClient<'_> {
+    /// The account's own tweets and replies since `since`, newest first, up to 100.
+    pub async fn recent_tweets(
+        &self,
+        x_id: &str,
+        since: DateTime<Utc>,
+    ) -> eyre::Result<Vec<TimelineTweet>> {
+        let url = format!(
+            "https://api.twitter.com/2/users/{}/tweets?max_results={}&exclude=retweets&start_time={}",
+            x_id,
+            MAX_TIMELINE_RESULTS,
+            since.to_rfc3339_opts(SecondsFormat::Secs, true)
+        );
+        let resp = self.client.get(url).send().await?.error_for_status()?;
+        let timeline: TimelineResponse = resp.json().await?;
+        Ok(timeline.data)
+    }
+
+    /// The newest tweet the account posted since `since` with the same text as `text`.
+    pub async fn find_recent_tweet(
+        &self,
+        x_id: &str,
+        text: &str,
+        since: DateTime<Utc>,
+    ) -> eyre::Result<Option<String>> {
+        let wanted = normalize(text);
+        let tweets = self.recent_tweets(x_id, since).await?;
+        Ok(tweets.into_iter().find(|tweet| normalize(&tweet.text) == wanted).map(|tweet| tweet.id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_and_spacing_do_not_matter() {
+        assert_eq!(
+            normalize("gm  https://example.com/cat.png"),
+            normalize("gm https://t.co/abc123")
+        );
+        assert_ne!(normalize("gm"), normalize("gn"));
+    }
+}
@@ -10,6 +10,16 @@ struct Media {
     media_ids: Vec<String>,
 }
 
+#[derive(Debug, Serialize)]
+struct Poll {
+    options: Vec<String>,
+    duration_minutes: u32,
+}
+
+const MAX_POLL_OPTION_CHARS: usize = 25;
+const MIN_POLL_MINUTES: u32 = 5;
+const MAX_POLL_MINUTES: u32 = 7 * 24 * 60;
+
 #[serde_with::skip_serializing_none]
 #[derive(Debug, Serialize, Default)]
 pub struct Tweet {
@@ -17,11 +27,16 @@ pub struct Tweet {
     quote_tweet_id: Option<String>,
     reply: Option<Reply>,
     media: Option<Media>,
+    poll: Option<Poll>,
 }
 
 impl Tweet {
     pub fn new(text: String) -> Self {
-        Self { text, quote_tweet_id: None, reply: None, media: None }
+        Self { text, quote_tweet_id: None, reply: None, media: None, poll: None }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
     }
 
     pub fn validate(&self) -> eyre::Result<()> {
@@ -36,6 +51,24 @@ impl Tweet {
                 eyre::bail!("Media IDs cannot be empty");
             }
         }
+        if let Some(poll) = &self.poll {
+            if self.media.is_some() || self.quote_tweet_id.is_some() {
+                eyre::bail!("Poll cannot be combined with media or a quote");
+            }
+            if !(2..=4).contains(&poll.options.len()) {
+                eyre::bail!("Poll must have 2 to 4 options");
+            }
+            if poll
+                .options
+                .iter()
+                .any(|option| option.is_empty() || option.chars().count() > MAX_POLL_OPTION_CHARS)
+            {
+                eyre::bail!("Poll options must be 1 to {} characters", MAX_POLL_OPTION_CHARS);
+            }
+            if !(MIN_POLL_MINUTES..=MAX_POLL_MINUTES).contains(&poll.duration_minutes) {
+                eyre::bail!("Poll must last {} to {} minutes", MIN_POLL_MINUTES, MAX_POLL_MINUTES);
+            }
+        }
         Ok(())
     }
 
@@ -50,4 +83,8 @@ impl Tweet {
     pub fn set_media_ids(&mut self, media_ids: Vec<String>) {
         self.media = Some(Media { media_ids });
     }
+
+    pub fn set_poll(&mut self, options: Vec<String>, duration_minutes: u32) {
+        self.poll = Some(Poll { options, duration_minutes });
+    }
 }
@@ -0,0 +1,210 @@
+use std::sync::{Arc, OnceLock};
+
+use alloy::hex;
+use axum::{extract::State, http::StatusCode, Json};
+use axum_extra::extract::cookie::CookieJar;
+use openssl::{hash::MessageDigest, pkey::PKey, rand::rand_bytes, sign::Signer};
+use serde::{Deserialize, Serialize};
+use tokio::time::Duration;
+use url::{Host, Url};
+
+use crate::{
+    db::{lock::TrackedMutex, CreatorWebhook, TeleportDB},
+    endpoints::{session_cookie, SharedState},
+    events::{Envelope, Event, TokenActivity},
+    metrics,
+};
+
+pub const SIGNATURE_HEADER: &str = "X-Teleport-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Teleport-Timestamp";
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+static ENABLED: OnceLock<bool> = OnceLock::new();
+
+/// Switches deliveries on. Creators are notified by the full instance; a read-only one would only
+/// repeat it.
+pub fn init(enabled: bool) {
+    ENABLED.get_or_init(|| enabled);
+}
+
+fn is_enabled() -> bool {
+    ENABLED.get().copied().unwrap_or(false)
+}
+
+/// Hex HMAC-SHA256 of `{timestamp}.{body}` under the creator's secret, sent as
+/// `X-Teleport-Signature: sha256=<signature>`. Binding the timestamp lets receivers reject
+/// replayed deliveries.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> eyre::Result<String> {
+    let key = PKey::hmac(secret.as_bytes())?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+    signer.update(format!("{}.", timestamp).as_bytes())?;
+    signer.update(body)?;
+    Ok(hex::encode(signer.sign_to_vec()?))
+}
+
+/// Posts `activity` to its creator's webhook in the background, if they registered one. Only the
+/// creator the activity belongs to is looked up, so no creator sees another's tokens. Delivery is
+/// best effort: failures are logged and counted, and never fail the event.
+pub async fn deliver<A: TeleportDB>(db: &Arc<TrackedMutex<A>>, activity: TokenActivity) {
+    if !is_enabled() {
+        return;
+    }
+    let webhook = match db.lock().await.get_creator_webhook(activity.x_id.clone()) {
+        Ok(Some(webhook)) => webhook,
+        Ok(None) => return,
+        Err(e) => {
+            log::error!("Failed to look up the webhook of creator {}: {:?}", activity.x_id, e);
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        let outcome = match post(&webhook, &activity).await {
+            Ok(()) => "delivered",
+            Err(e) => {
+                log::warn!(
+                    "Failed to deliver {:?} of NFT {} to the webhook of creator {}: {:?}",
+                    activity.activity,
+                    activity.token_id,
+                    activity.x_id,
+                    e
+                );
+                "failed"
+            }
+        };
+        metrics::increment("creator_webhook_deliveries_total", &[("outcome", outcome)]);
+    });
+}
+
+async fn post(webhook: &CreatorWebhook, activity: &TokenActivity) -> eyre::Result<()> {
+    let body = serde_json::to_vec(&Envelope::from(Event::TokenActivity(activity.clone())))?;
+    let timestamp = chrono::Utc::now().timestamp();
+    let signature = sign(&webhook.secret, timestamp, &body)?;
+    // Redirects are not followed, so a registered URL cannot bounce deliveries elsewhere.
+    reqwest::Client::builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?
+        .post(&webhook.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(TIMESTAMP_HEADER, timestamp.to_string())
+        .header(SIGNATURE_HEADER, format!("sha256={}", signature))
+        .body(body)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// The webhook URL a creator asked for, if the enclave may post to it: https, and named by a
+/// public hostname instead of an IP address or localhost, which could reach the host's own
+/// services.
+fn check_url(url: &str) -> Option<Url> {
+    let url = Url::parse(url).ok()?;
+    let public = match url.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_lowercase();
+            domain != "localhost" && !domain.ends_with(".localhost") && domain.contains('.')
+        }
+        Some(Host::Ipv4(_) | Host::Ipv6(_)) | None => false,
+    };
+    (url.scheme() == "https" && public).then_some(url)
+}
+
+#[derive(Deserialize)]
+pub struct SetWebhookQuery {
+    url: String,
+}
+
+#[derive(Serialize)]
+pub struct RegisteredWebhook {
+    url: String,
+    /// Only ever returned here; registering again rotates it.
+    secret: String,
+}
+
+/// Registers the URL the session's account receives its own tokens' activity at: mints,
+/// redemptions, transfers and the tweets posted for them. Replaces any earlier webhook, and
+/// its secret.
+pub async fn set_webhook<A: TeleportDB>(
+    jar: CookieJar,
+    State(shared_state): State<SharedState<A>>,
+    Json(query): Json<SetWebhookQuery>,
+) -> Result<Json<RegisteredWebhook>, StatusCode> {
+    let url = check_url(query.url.trim()).ok_or(StatusCode::BAD_REQUEST)?;
+    let session_id = session_cookie(&jar)?;
+    let mut secret = [0u8; 32];
+    rand_bytes(&mut secret).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let webhook = CreatorWebhook {
+        url: url.to_string(),
+        secret: hex::encode(secret),
+        created_at: chrono::Utc::now().timestamp(),
+    };
+    let mut db = shared_state.db.lock().await;
+    let session = db.get_session(session_id).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    db.set_creator_webhook(session.x_id, webhook.clone())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    drop(db);
+
+    Ok(Json(RegisteredWebhook { url: webhook.url, secret: webhook.secret }))
+}
+
+#[derive(Serialize)]
+pub struct WebhookInfo {
+    url: String,
+    created_at: i64,
+}
+
+/// The webhook the session's account registered, without its secret.
+pub async fn get_webhook<A: TeleportDB>(
+    jar: CookieJar,
+    State(shared_state): State<SharedState<A>>,
+) -> Result<Json<WebhookInfo>, StatusCode> {
+    let session_id = session_cookie(&jar)?;
+    let db = shared_state.db.lock().await;
+    let session = db.get_session(session_id).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let webhook = db
+        .get_creator_webhook(session.x_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(WebhookInfo { url: webhook.url, created_at: webhook.created_at }))
+}
+
+pub async fn delete_webhook<A: TeleportDB>(
+    jar: CookieJar,
+    State(shared_state): State<SharedState<A>>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = session_cookie(&jar)?;
+    let mut db = shared_state.db.lock().await;
+    let session = db.get_session(session_id).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let removed =
+        db.remove_creator_webhook(session.x_id).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(if removed { StatusCode::NO_CONTENT } else { StatusCode::NOT_FOUND })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_covers_secret_timestamp_and_body() -> eyre::Result<()> {
+        let signature = sign("secret", 1_700_000_000, b"{}")?;
+        assert_eq!(signature.len(), 64);
+        assert_eq!(sign("secret", 1_700_000_000, b"{}")?, signature);
+        assert_ne!(sign("other", 1_700_000_000, b"{}")?, signature);
+        assert_ne!(sign("secret", 1_700_000_001, b"{}")?, signature);
+        assert_ne!(sign("secret", 1_700_000_000, b"[]")?, signature);
+        Ok(())
+    }
+
+    #[test]
+    fn check_url_only_allows_public_https_hosts() {
+        assert!(check_url("https://hooks.example.com/teleport").is_some());
+        assert!(check_url("http://hooks.example.com/teleport").is_none());
+        assert!(check_url("https://localhost/teleport").is_none());
+        assert!(check_url("https://api.localhost/teleport").is_none());
+        assert!(check_url("https://127.0.0.1/teleport").is_none());
+        assert!(check_url("https://[::1]/teleport").is_none());
+        assert!(check_url("https://metadata/teleport").is_none());
+        assert!(check_url("not a url").is_none());
+    }
+}